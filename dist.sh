@@ -3,12 +3,18 @@ set -e
 
 RELEASE=target/release
 CROSS_RELEASE=xtarget/xtensa-esp8266-none-elf/release-embedded
+ARM_RELEASE=target/thumbv7em-none-eabi/release-embedded
+RISCV_RELEASE=target/riscv32imc-unknown-none-elf/release-embedded
+WEB_RELEASE=target/wasm32-unknown-unknown/release
 DIST="$RELEASE"/dist
 LIB_NATIVE="$DIST"/lib/native
 LIB_ESP8266="$DIST"/lib/esp8266
+LIB_STM32="$DIST"/lib/stm32
+LIB_ESP32C3="$DIST"/lib/esp32c3
+LIB_WEB="$DIST"/lib/web
 
 mkdir "$DIST"
-mkdir -p "$LIB_NATIVE" "$LIB_ESP8266"
+mkdir -p "$LIB_NATIVE" "$LIB_ESP8266" "$LIB_STM32" "$LIB_ESP32C3" "$LIB_WEB"
 
 cp "$RELEASE"/{compiler,editor} "$DIST"
 cp $RELEASE/libruntime.a "$LIB_NATIVE"
@@ -16,4 +22,8 @@ cp $RELEASE/libruntime.a "$LIB_NATIVE"
 cp -r editor/resources "$DIST/"
 
 cp "$CROSS_RELEASE"/libruntime.a "$LIB_ESP8266"
-find "$CROSS_RELEASE" -name '*.x' -exec cp {} $DIST/lib/esp8266 \;
+cp "$ARM_RELEASE"/libruntime.a "$LIB_STM32"
+cp "$RISCV_RELEASE"/libruntime.a "$LIB_ESP32C3"
+
+# El simulador web se sirve junto al runtime, ver runtime/web/
+cp "$WEB_RELEASE"/runtime.wasm runtime/web/* "$LIB_WEB"
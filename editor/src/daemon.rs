@@ -0,0 +1,166 @@
+//! Cliente del servidor de compilación
+//!
+//! En vez de iniciar un proceso del compilador por
+//! cada compilación, el editor se comunica con una
+//! instancia persistente (`compiler --daemon`), la
+//! cual se inicia la primera vez que se necesita y
+//! se detiene al cerrar el editor.
+//!
+//! El servidor no acepta opciones arbitrarias de la
+//! CLI, por lo que si se configuran opciones adicionales
+//! se ejecuta el compilador directamente.
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use crate::settings::Settings;
+
+/// Ruta del socket, relativa al directorio del compilador
+const SOCKET: &str = "compiler.sock";
+
+/// Intentos de conexión luego de iniciar el servidor
+const RETRIES: u32 = 40;
+
+/// Compila un archivo y retorna el texto a desplegar en la terminal
+pub fn compile(settings: &Settings, filename: &str, strip: bool) -> String {
+    if !settings.extra_flags().is_empty() {
+        return compile_directly(settings, filename, strip);
+    }
+
+    let request = json!({
+        "command": "compile",
+        "path": filename,
+        "output": settings.output,
+        "target": settings.target,
+        "strip": strip,
+    });
+
+    let response = match connect(&settings.compiler).and_then(|stream| send(stream, &request)) {
+        Ok(response) => response,
+        Err(error) => return format!("Compiler daemon is unavailable: {}", error),
+    };
+
+    if let Some(diagnostics) = response["diagnostics"].as_str() {
+        diagnostics.to_string()
+    } else if let Some(error) = response["error"].as_str() {
+        format!("Error: {}", error)
+    } else {
+        let elapsed = response["elapsed_ms"].as_f64().unwrap_or(0.0);
+        format!("Finished successful build in {:.0}ms", elapsed)
+    }
+}
+
+/// Error señalado por el compilador, con líneas y columnas desde 0
+pub struct Squiggle {
+    pub start: (i32, i32),
+    pub end: (i32, i32),
+    pub message: String,
+}
+
+/// Compila un archivo con una instancia nueva del compilador
+fn compile_directly(settings: &Settings, filename: &str, strip: bool) -> String {
+    let mut command = Command::new(&settings.compiler);
+    command
+        .args(&["--color", "never"])
+        .args(&["-t", &settings.target, "-o", &settings.output])
+        .args(settings.extra_flags());
+
+    if strip {
+        command.arg("-s");
+    }
+
+    let result = command.arg(filename).stdin(Stdio::null()).output();
+    let output = match result {
+        Ok(output) => output,
+        Err(error) => return format!("Failed to run {}: {}", settings.compiler, error),
+    };
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.trim().is_empty() && output.status.success() {
+        String::from("Finished successful build")
+    } else {
+        stderr.trim_end().to_string()
+    }
+}
+
+/// Verifica un archivo y retorna los errores a subrayar en el buffer
+pub fn check(compiler: &str, filename: &str) -> Vec<Squiggle> {
+    let request = json!({
+        "command": "check",
+        "path": filename,
+    });
+
+    let response = match connect(compiler).and_then(|stream| send(stream, &request)) {
+        Ok(response) => response,
+        Err(_) => return Vec::new(),
+    };
+
+    let position = |value: &Value| {
+        let line = value["line"].as_i64().unwrap_or(1) as i32;
+        let column = value["column"].as_i64().unwrap_or(1) as i32;
+        (line - 1, column - 1)
+    };
+
+    let diagnostics = match response["structured"].as_array() {
+        Some(diagnostics) => diagnostics,
+        None => return Vec::new(),
+    };
+
+    diagnostics
+        .iter()
+        .map(|diagnostic| Squiggle {
+            start: position(&diagnostic["start"]),
+            end: position(&diagnostic["end"]),
+            message: format!(
+                "{}: {}",
+                diagnostic["kind"].as_str().unwrap_or("Error"),
+                diagnostic["message"].as_str().unwrap_or_default()
+            ),
+        })
+        .collect()
+}
+
+/// Detiene el servidor, si es que está en ejecución
+pub fn shutdown() {
+    if let Ok(stream) = UnixStream::connect(SOCKET) {
+        let _ = send(stream, &json!({ "command": "shutdown" }));
+    }
+}
+
+/// Conecta con el servidor, iniciándolo si no existe
+fn connect(compiler: &str) -> std::io::Result<UnixStream> {
+    if let Ok(stream) = UnixStream::connect(SOCKET) {
+        return Ok(stream);
+    }
+
+    Command::new(compiler)
+        .args(&["--daemon", SOCKET])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    let mut last_error = None;
+    for _ in 0..RETRIES {
+        thread::sleep(Duration::from_millis(25));
+        match UnixStream::connect(SOCKET) {
+            Ok(stream) => return Ok(stream),
+            Err(error) => last_error = Some(error),
+        }
+    }
+
+    Err(last_error.unwrap())
+}
+
+/// Envía una solicitud y espera su respuesta
+fn send(mut stream: UnixStream, request: &Value) -> std::io::Result<Value> {
+    writeln!(stream, "{}", request)?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+
+    Ok(serde_json::from_str(&line)?)
+}
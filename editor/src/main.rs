@@ -13,6 +13,16 @@
 //! creado , compilación y ejecución automáticas
 //! y una terminal para desplegar errores de
 //! compilación y otra información revelante.
+//! Cada vez que se guarda, el archivo se verifica
+//! en segundo plano y los errores se subrayan en
+//! el buffer, con el mensaje como tooltip.
+//! Un monitor serial muestra la salida de la
+//! tarjeta luego de flashearla. El compilador,
+//! la plataforma, el puerto y demás se ajustan
+//! en el diálogo de preferencias. La vista
+//! previa ejecuta el programa en la computadora
+//! y dibuja la matriz de 8x8 sin necesidad de
+//! la tarjeta.
 //!
 //! Además cuenta con diferentes estilos para
 //! la intefaz. Y un botón de about que muestra
@@ -22,16 +32,24 @@ extern crate gio;
 extern crate glib;
 extern crate gtk;
 
+mod daemon;
+mod preview;
+mod serial;
+mod settings;
+
 use gio::prelude::*;
 use glib::clone;
 use gtk::prelude::*;
 use sourceview::*;
+use std::cell::RefCell;
 use std::env::args;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::path::Path;
 use std::process::Command;
+use std::rc::Rc;
+use std::thread;
 
 /// Función main
 /// Incia la aplicación de GTK
@@ -45,6 +63,10 @@ fn main() {
         build_ui(app);
     });
 
+    application.connect_shutdown(|_| {
+        daemon::shutdown();
+    });
+
     //Start main loop
     application.run(&args().collect::<Vec<_>>());
 }
@@ -84,6 +106,7 @@ fn build_ui(application: &gtk::Application) {
     //Buttons
     let compile_run: gtk::Button = builder.get_object("comp_and_run").unwrap();
     let compile: gtk::Button = builder.get_object("comp").unwrap();
+    let preview_button: gtk::ToggleButton = builder.get_object("preview").unwrap();
 
     //Menu Items
     //File
@@ -91,6 +114,7 @@ fn build_ui(application: &gtk::Application) {
     let open: gtk::MenuItem = builder.get_object("open").unwrap();
     let save: gtk::MenuItem = builder.get_object("save").unwrap();
     let save_as: gtk::MenuItem = builder.get_object("save_as").unwrap();
+    let preferences: gtk::MenuItem = builder.get_object("preferences").unwrap();
     let quit: gtk::MenuItem = builder.get_object("quit").unwrap();
     //Help
     let about: gtk::MenuItem = builder.get_object("about").unwrap();
@@ -141,30 +165,106 @@ fn build_ui(application: &gtk::Application) {
     let terminal: gtk::TextView = builder.get_object("terminal").unwrap();
     terminal.set_widget_name("terminal");
 
+    //Diagnostics
+    let error_tag = gtk::TextTag::new(Some("error"));
+    error_tag.set_property_underline(pango::Underline::Error);
+    buffer.get_tag_table().unwrap().add(&error_tag);
+
+    let squiggles: Rc<RefCell<Vec<daemon::Squiggle>>> = Default::default();
+    let (check_sender, check_receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+    sourceview.set_has_tooltip(true);
+
+    //Serial monitor
+    let serial_connect: gtk::ToggleButton = builder.get_object("serial_connect").unwrap();
+    let serial_pause: gtk::ToggleButton = builder.get_object("serial_pause").unwrap();
+    let serial_clear: gtk::Button = builder.get_object("serial_clear").unwrap();
+    let serial_view: gtk::TextView = builder.get_object("serial_view").unwrap();
+
+    let monitor: Rc<RefCell<Option<serial::Monitor>>> = Default::default();
+    let paused_output: Rc<RefCell<String>> = Default::default();
+    let (serial_sender, serial_receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+    //Preview
+    let preview_area: gtk::DrawingArea = builder.get_object("preview_area").unwrap();
+
+    let running_preview: Rc<RefCell<Option<preview::Preview>>> = Default::default();
+    let preview_rows: Rc<RefCell<[u8; 8]>> = Default::default();
+    let (preview_sender, preview_receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
     //Notebook
     let doc_name: gtk::Label = builder.get_object("doc_name").unwrap();
 
     //File
     let current_file = gtk::Label::new(Some("tmp.led")); //Ruta de guardado para archivos unnamed
 
+    //Settings
+    let settings = Rc::new(RefCell::new(settings::Settings::load()));
+
     //               ___________________
     //______________/  Add funtionality
 
+    // Show diagnostics from background checks
+    //
+    // Quitar subrayados anteriores
+    // Subrayar el rango de cada error
+    check_receiver.attach(
+        None,
+        clone!(@weak buffer, @strong squiggles => @default-return glib::Continue(false), move |found: Vec<daemon::Squiggle>| {
+
+            let bounds = buffer.get_bounds();
+            buffer.remove_tag_by_name("error", &bounds.0, &bounds.1);
+
+            for squiggle in &found {
+                let start = iter_at(&buffer, squiggle.start);
+                let end = iter_at(&buffer, squiggle.end);
+                buffer.apply_tag_by_name("error", &start, &end);
+            }
+
+            *squiggles.borrow_mut() = found;
+            glib::Continue(true)
+
+        }),
+    );
+
+    // Add diagnostic tooltips
+    //
+    // Mostrar el mensaje del error bajo el puntero
+    sourceview.connect_query_tooltip(clone!(@strong squiggles => move |view, x, y, _, tooltip| {
+
+        let (x, y) = view.window_to_buffer_coords(gtk::TextWindowType::Widget, x, y);
+        let iter = match view.get_iter_at_location(x, y) {
+            Some(iter) => iter,
+            None => return false,
+        };
+
+        let position = (iter.get_line(), iter.get_line_offset());
+        let squiggles = squiggles.borrow();
+
+        match squiggles.iter().find(|squiggle| squiggle.start <= position && position < squiggle.end) {
+            Some(squiggle) => {
+                tooltip.set_text(Some(&squiggle.message));
+                true
+            }
+
+            None => false,
+        }
+
+    }));
+
     // Add "compile" button functionality
     //
     // Guardado automático
     // Envio de archivo al compilador
     // Despliegue de mensajes del compilador en la terminal
     compile.connect_clicked(
-        clone!(@weak save, @weak current_file, @weak terminal=> move |_| {
+        clone!(@weak save, @weak current_file, @weak terminal, @strong settings => move |_| {
 
             save.activate();
 
             let filename: &str = &current_file.get_text();
 
-            let cmd = Command::new("./compiler").args(&[filename, "-svo","exe","--target","esp8266"]).output().unwrap();
-
-            let answer = std::str::from_utf8(&cmd.stderr).unwrap();
+            let answer = daemon::compile(&settings.borrow(), filename, true);
 
             let term_buffer = terminal.get_buffer().unwrap();
             let mut bounds = term_buffer.get_bounds();
@@ -177,28 +277,222 @@ fn build_ui(application: &gtk::Application) {
     // Add "compile and run" button functionality
     //
     // Ejecutar compile primero
+    // Liberar el puerto serial
     // Flasheo del código compilado
     // Despliegue de mensajes en la terminal
-    compile_run.connect_clicked(clone!(@weak save, @weak current_file, @weak terminal => move |_| {
+    // Reabrir el monitor serial
+    compile_run.connect_clicked(clone!(@weak save, @weak current_file, @weak terminal, @weak serial_connect, @strong settings => move |_| {
 
         save.activate();
 
         let filename: &str = &current_file.get_text();
+        let settings = settings.borrow();
 
-        let cmd = Command::new("./compiler").args(&[filename,"-o","exe","--target","esp8266"]).output().unwrap();
-        let answer = std::str::from_utf8(&cmd.stderr).unwrap();
+        let answer = daemon::compile(&settings, filename, false);
 
         let term_buffer = terminal.get_buffer().unwrap();
         let mut bounds = term_buffer.get_bounds();
         term_buffer.insert(&mut bounds.1,&answer);
+        term_buffer.insert(&mut bounds.1,"\n");
 
-       let cmd = Command::new("espflash").args(&["/dev/ttyUSB0","exe"]).output().unwrap();
+       serial_connect.set_active(false);
+
+       let cmd = Command::new(&settings.compiler).args(&["flash", "--port", &settings.port, &settings.output]).output().unwrap();
        let answer = std::str::from_utf8(&cmd.stderr).unwrap();
 
        let mut bounds = term_buffer.get_bounds();
        term_buffer.insert(&mut bounds.1,&answer);
        term_buffer.insert(&mut bounds.1,"\n");
 
+       if cmd.status.success() {
+           serial_connect.set_active(true);
+       }
+
+    }));
+
+    // Add "preview" button functionality
+    //
+    // Guardado automático
+    // Compilar para la plataforma nativa
+    // Ejecutar el programa y recibir el estado de la matriz
+    // Detener el programa al desactivar el botón
+    preview_button.connect_toggled(clone!(@weak save, @weak current_file, @weak terminal, @weak preview_area, @strong running_preview, @strong preview_rows, @strong settings => move |preview_button| {
+
+        if !preview_button.get_active() {
+            running_preview.borrow_mut().take();
+            return;
+        }
+
+        if running_preview.borrow().is_some() {
+            return;
+        }
+
+        save.activate();
+
+        let filename: &str = &current_file.get_text();
+        let settings = settings::Settings {
+            target: String::from("native"),
+            output: String::from(preview::EXECUTABLE),
+            ..settings.borrow().clone()
+        };
+
+        let answer = daemon::compile(&settings, filename, true);
+
+        let term_buffer = terminal.get_buffer().unwrap();
+        let mut bounds = term_buffer.get_bounds();
+        term_buffer.insert(&mut bounds.1,&answer);
+        term_buffer.insert(&mut bounds.1,"\n");
+
+        *preview_rows.borrow_mut() = [0; 8];
+        preview_area.queue_draw();
+
+        match preview::Preview::start(preview::EXECUTABLE, preview_sender.clone()) {
+            Ok(started) => *running_preview.borrow_mut() = Some(started),
+            Err(error) => {
+                let mut bounds = term_buffer.get_bounds();
+                let message = format!("Couldn't start preview: {}\n", error);
+                term_buffer.insert(&mut bounds.1, &message);
+
+                preview_button.set_active(false);
+            }
+        }
+
+    }));
+
+    // Add preview frames
+    //
+    // Guardar el último cuadro y redibujar la matriz
+    // Desactivar el botón si el programa terminó
+    preview_receiver.attach(
+        None,
+        clone!(@weak preview_area, @weak preview_button, @strong preview_rows => @default-return glib::Continue(false), move |event: preview::Event| {
+
+            match event {
+                preview::Event::Frame(rows) => {
+                    *preview_rows.borrow_mut() = rows;
+                    preview_area.queue_draw();
+                }
+
+                preview::Event::Finished => preview_button.set_active(false),
+            }
+
+            glib::Continue(true)
+
+        }),
+    );
+
+    // Draw the LED matrix
+    //
+    // Un círculo por luz, rojo si está encendida
+    preview_area.connect_draw(clone!(@strong preview_rows => move |area, cr| {
+
+        let width = area.get_allocated_width() as f64;
+        let height = area.get_allocated_height() as f64;
+        let cell = width.min(height) / 8.0;
+
+        cr.set_source_rgb(0.11, 0.11, 0.12);
+        cr.paint();
+
+        for (row, bits) in preview_rows.borrow().iter().enumerate() {
+            for column in 0..8 {
+                if bits & (0x80 >> column) != 0 {
+                    cr.set_source_rgb(0.95, 0.15, 0.1);
+                } else {
+                    cr.set_source_rgb(0.3, 0.05, 0.05);
+                }
+
+                let x = (column as f64 + 0.5) * cell;
+                let y = (row as f64 + 0.5) * cell;
+                cr.arc(x, y, cell * 0.4, 0.0, 2.0 * std::f64::consts::PI);
+                cr.fill();
+            }
+        }
+
+        Inhibit(false)
+
+    }));
+
+    // Add serial monitor output
+    //
+    // Agregar lo recibido al final de la vista
+    // Retener lo recibido mientras se esté en pausa
+    // Reportar en la terminal si el puerto se cierra
+    serial_receiver.attach(
+        None,
+        clone!(@weak serial_view, @weak serial_pause, @weak serial_connect, @weak terminal, @strong paused_output => @default-return glib::Continue(false), move |event: serial::Event| {
+
+            match event {
+                serial::Event::Data(text) => {
+                    if serial_pause.get_active() {
+                        paused_output.borrow_mut().push_str(&text);
+                    } else {
+                        append_output(&serial_view, &text);
+                    }
+                }
+
+                serial::Event::Closed(error) => {
+                    let term_buffer = terminal.get_buffer().unwrap();
+                    let mut bounds = term_buffer.get_bounds();
+                    term_buffer.insert(&mut bounds.1, &format!("Serial port closed: {}\n", error));
+
+                    serial_connect.set_active(false);
+                }
+            }
+
+            glib::Continue(true)
+
+        }),
+    );
+
+    // Add "connect" button functionality
+    //
+    // Abrir o cerrar el puerto serial
+    serial_connect.connect_toggled(clone!(@weak terminal, @strong monitor, @strong settings => move |serial_connect| {
+
+        if !serial_connect.get_active() {
+            monitor.borrow_mut().take();
+            return;
+        }
+
+        if monitor.borrow().is_some() {
+            return;
+        }
+
+        let port = settings.borrow().port.clone();
+        match serial::Monitor::open(&port, serial_sender.clone()) {
+            Ok(opened) => *monitor.borrow_mut() = Some(opened),
+            Err(error) => {
+                let term_buffer = terminal.get_buffer().unwrap();
+                let mut bounds = term_buffer.get_bounds();
+                let message = format!("Couldn't open {}: {}\n", port, error);
+                term_buffer.insert(&mut bounds.1, &message);
+
+                serial_connect.set_active(false);
+            }
+        }
+
+    }));
+
+    // Add "pause" button functionality
+    //
+    // Al reanudar, desplegar lo recibido durante la pausa
+    serial_pause.connect_toggled(clone!(@weak serial_view, @strong paused_output => move |serial_pause| {
+
+        if !serial_pause.get_active() {
+            let pending = std::mem::take(&mut *paused_output.borrow_mut());
+            append_output(&serial_view, &pending);
+        }
+
+    }));
+
+    // Add "clear" button functionality
+    //
+    // Limpia la salida del monitor
+    serial_clear.connect_clicked(clone!(@weak serial_view, @strong paused_output => move |_| {
+
+        serial_view.get_buffer().unwrap().set_text("");
+        paused_output.borrow_mut().clear();
+
     }));
 
     // Add "new" button functionality
@@ -286,8 +580,9 @@ fn build_ui(application: &gtk::Application) {
     // Tomar ruta de guardado actual
     // Tomar texto actual del buffer
     // Escribir bytes a la ruta especificada
+    // Verificar el archivo en segundo plano
     save.connect_activate(
-        clone!(@weak current_file_save,@weak src_view_save,@weak terminal => move |_| {
+        clone!(@weak current_file_save,@weak src_view_save,@weak terminal, @strong check_sender, @strong settings => move |_| {
 
             let filename = current_file_save.get_text();
 
@@ -317,6 +612,13 @@ fn build_ui(application: &gtk::Application) {
                                 }
                 };
 
+            let check_sender = check_sender.clone();
+            let compiler = settings.borrow().compiler.clone();
+            let filename = filename.to_string();
+            thread::spawn(move || {
+                let _ = check_sender.send(daemon::check(&compiler, &filename));
+            });
+
         }),
     );
 
@@ -392,6 +694,30 @@ fn build_ui(application: &gtk::Application) {
         file_chooser.show_all();
     }));
 
+    // Add "preferences" button functionality
+    //
+    // Abrir el diálogo de preferencias
+    // Reportar en la terminal si se guardaron
+    // Reiniciar el servidor, por si cambió el compilador
+    preferences.connect_activate(clone!(@weak window, @weak terminal, @strong settings => move |_| {
+
+        settings::edit(&window, &settings, clone!(@weak terminal => move |result| {
+
+            let message = match result {
+                Ok(()) => String::from("Preferences saved\n\n"),
+                Err(error) => format!("Couldn't save preferences: {}\n\n", error),
+            };
+
+            let term_buffer = terminal.get_buffer().unwrap();
+            let mut bounds = term_buffer.get_bounds();
+            term_buffer.insert(&mut bounds.1, &message);
+
+            daemon::shutdown();
+
+        }));
+
+    }));
+
     // Add themes button functionality
     //
     // Cambia el esquema de colores del buffer segun lo seleccionado
@@ -423,10 +749,33 @@ fn build_ui(application: &gtk::Application) {
     // When window destroyed
     //
     // Guarda el archivo
+    // Detiene la vista previa
     // Detiene el ciclo principal de GTK
     window.connect_delete_event(move |_, _| {
         save.activate();
+        running_preview.borrow_mut().take();
         gtk::main_quit();
         Inhibit(false)
     });
 }
+
+/// Función append_output
+/// Agrega texto al final de una vista y se desplaza
+/// hasta este
+fn append_output(view: &gtk::TextView, text: &str) {
+    let buffer = view.get_buffer().unwrap();
+    let mut end = buffer.get_end_iter();
+    buffer.insert(&mut end, text);
+
+    let mut end = buffer.get_end_iter();
+    view.scroll_to_iter(&mut end, 0.0, false, 0.0, 1.0);
+}
+
+/// Función iter_at
+/// Obtiene la posición del buffer que corresponde a una
+/// línea y columna, ajustándola si el buffer cambió
+fn iter_at(buffer: &sourceview::Buffer, (line, column): (i32, i32)) -> gtk::TextIter {
+    let mut iter = buffer.get_iter_at_line(line);
+    iter.set_line_offset(column.min(iter.get_chars_in_line()));
+    iter
+}
@@ -0,0 +1,145 @@
+//! Vista previa de la matriz
+//!
+//! El buffer se compila para la plataforma nativa y el
+//! ejecutable resultante se inicia con la variable
+//! `ANIMLED_DISPLAY_SOCKET`, de modo que el runtime hospedado
+//! transmita por TCP una línea JSON por cada cambio de la
+//! matriz. Un hilo aparte lee estas líneas y envía cada
+//! cuadro al ciclo principal de GTK para dibujarse.
+
+use serde_json::Value;
+use std::io::{self, BufRead, BufReader};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Ejecutable nativo que se genera para la vista previa
+pub const EXECUTABLE: &str = "./preview";
+
+/// Intentos de conexión luego de iniciar el programa
+const RETRIES: u32 = 40;
+
+/// Mensajes del hilo lector hacia la interfaz
+pub enum Event {
+    /// Filas de la matriz, el bit más significativo es la columna 0
+    Frame([u8; 8]),
+
+    /// El programa terminó por sí solo
+    Finished,
+}
+
+/// Programa en ejecución para la vista previa
+pub struct Preview {
+    child: Child,
+    stopped: Arc<AtomicBool>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl Preview {
+    /// Inicia un ejecutable nativo y se conecta a su matriz
+    pub fn start(executable: &str, sender: glib::Sender<Event>) -> io::Result<Preview> {
+        let address = free_address()?;
+        let mut child = Command::new(executable)
+            .env("ANIMLED_DISPLAY_SOCKET", address.to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stream = match connect(&address, &mut child) {
+            Ok(stream) => stream,
+            Err(error) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(error);
+            }
+        };
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let stopped = Arc::clone(&stopped);
+            thread::spawn(move || read_loop(stream, &stopped, &sender))
+        };
+
+        Ok(Preview {
+            child,
+            stopped,
+            reader: Some(reader),
+        })
+    }
+}
+
+impl Drop for Preview {
+    /// Detiene el programa y el hilo lector
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+/// Obtiene un puerto local que no esté en uso
+fn free_address() -> io::Result<SocketAddr> {
+    TcpListener::bind("127.0.0.1:0")?.local_addr()
+}
+
+/// Espera a que el programa acepte conexiones
+fn connect(address: &SocketAddr, child: &mut Child) -> io::Result<TcpStream> {
+    let mut last_error = None;
+    for _ in 0..RETRIES {
+        thread::sleep(Duration::from_millis(25));
+
+        if let Some(status) = child.try_wait()? {
+            let message = format!("program exited before starting: {}", status);
+            return Err(io::Error::new(io::ErrorKind::Other, message));
+        }
+
+        match TcpStream::connect(address) {
+            Ok(stream) => return Ok(stream),
+            Err(error) => last_error = Some(error),
+        }
+    }
+
+    Err(last_error.unwrap())
+}
+
+/// Lee cuadros hasta que el programa termine
+fn read_loop(stream: TcpStream, stopped: &AtomicBool, sender: &glib::Sender<Event>) {
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+
+        if let Some(rows) = parse_frame(&line) {
+            if sender.send(Event::Frame(rows)).is_err() {
+                return;
+            }
+        }
+    }
+
+    // Si la vista previa se detuvo desde la interfaz no hay nada que reportar
+    if !stopped.load(Ordering::Relaxed) {
+        let _ = sender.send(Event::Finished);
+    }
+}
+
+/// Extrae las filas de un mensaje del runtime
+fn parse_frame(line: &str) -> Option<[u8; 8]> {
+    let message: Value = serde_json::from_str(line).ok()?;
+    let mut rows = [0; 8];
+
+    for (row, value) in rows.iter_mut().zip(message["rows"].as_array()?) {
+        *row = value.as_u64()? as u8;
+    }
+
+    Some(rows)
+}
@@ -0,0 +1,118 @@
+//! Monitor serial
+//!
+//! Luego de flashear el programa, la tarjeta reporta la
+//! salida de `sys_debug!` por UART0. Este módulo abre el
+//! puerto serial en modo crudo y lee desde un hilo aparte,
+//! enviando lo recibido al ciclo principal de GTK.
+//!
+//! El puerto debe cerrarse antes de flashear, ya que
+//! `compiler flash` necesita acceso exclusivo a este.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::mem::MaybeUninit;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Puerto en el que se conecta la tarjeta
+pub const PORT: &str = "/dev/ttyUSB0";
+
+/// Velocidad a la que transmite UART0 en el runtime
+const BAUD_RATE: libc::speed_t = libc::B115200;
+
+/// Mensajes del hilo lector hacia la interfaz
+pub enum Event {
+    Data(String),
+    Closed(String),
+}
+
+/// Lectura en curso de un puerto serial
+pub struct Monitor {
+    stop: Arc<AtomicBool>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl Monitor {
+    /// Abre un puerto y comienza a leer de este
+    pub fn open(port: &str, sender: glib::Sender<Event>) -> io::Result<Monitor> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(port)?;
+
+        configure(&file)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || read_loop(file, &stop, &sender))
+        };
+
+        Ok(Monitor {
+            stop,
+            reader: Some(reader),
+        })
+    }
+}
+
+impl Drop for Monitor {
+    /// Detiene el hilo lector y libera el puerto
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+/// Configura el puerto en modo crudo a la velocidad del runtime
+///
+/// Cada lectura espera a lo sumo una décima de segundo, para
+/// que el hilo lector note a tiempo que debe detenerse.
+fn configure(file: &File) -> io::Result<()> {
+    let fd = file.as_raw_fd();
+    let mut termios = MaybeUninit::<libc::termios>::uninit();
+
+    unsafe {
+        if libc::tcgetattr(fd, termios.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut termios = termios.assume_init();
+        libc::cfmakeraw(&mut termios);
+        libc::cfsetspeed(&mut termios, BAUD_RATE);
+
+        termios.c_cflag |= libc::CLOCAL | libc::CREAD;
+        termios.c_cc[libc::VMIN] = 0;
+        termios.c_cc[libc::VTIME] = 1;
+
+        if libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+/// Lee del puerto hasta que se solicite detenerse o ocurra un error
+fn read_loop(mut file: File, stop: &AtomicBool, sender: &glib::Sender<Event>) {
+    let mut buffer = [0; 256];
+
+    while !stop.load(Ordering::Relaxed) {
+        let event = match file.read(&mut buffer) {
+            Ok(0) => continue,
+            Ok(count) => Event::Data(String::from_utf8_lossy(&buffer[..count]).replace('\r', "")),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => Event::Closed(error.to_string()),
+        };
+
+        let closed = matches!(event, Event::Closed(_));
+        if sender.send(event).is_err() || closed {
+            break;
+        }
+    }
+}
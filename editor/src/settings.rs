@@ -0,0 +1,195 @@
+//! Preferencias del editor
+//!
+//! La ruta del compilador, la plataforma objetivo, el nombre
+//! del ejecutable, el puerto serial y opciones adicionales para
+//! el compilador se guardan como JSON en
+//! `$XDG_CONFIG_HOME/animationled/editor.json` (por defecto en
+//! `~/.config`). Valores ausentes o inválidos toman su valor
+//! por defecto.
+
+use gtk::prelude::*;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::serial;
+
+/// Plataformas que acepta `compiler --target`
+const TARGETS: &[&str] = &["native", "esp8266", "stm32", "esp32c3", "web"];
+
+/// Valores configurables
+#[derive(Clone)]
+pub struct Settings {
+    pub compiler: String,
+    pub target: String,
+    pub output: String,
+    pub port: String,
+    pub flags: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            compiler: String::from("./compiler"),
+            target: String::from("esp8266"),
+            output: String::from("exe"),
+            port: String::from(serial::PORT),
+            flags: String::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Carga las preferencias guardadas, o las de por defecto
+    pub fn load() -> Settings {
+        let mut settings = Settings::default();
+
+        let saved = path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str::<Value>(&text).ok());
+
+        if let Some(saved) = saved {
+            let mut fields = [
+                ("compiler", &mut settings.compiler),
+                ("target", &mut settings.target),
+                ("output", &mut settings.output),
+                ("port", &mut settings.port),
+                ("flags", &mut settings.flags),
+            ];
+
+            for (key, field) in fields.iter_mut() {
+                if let Some(value) = saved[*key].as_str() {
+                    **field = value.to_string();
+                }
+            }
+        }
+
+        if !TARGETS.contains(&settings.target.as_str()) {
+            settings.target = Settings::default().target;
+        }
+
+        settings
+    }
+
+    /// Guarda las preferencias en el archivo de configuración
+    pub fn save(&self) -> io::Result<()> {
+        let path = path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no configuration directory"))?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let saved = json!({
+            "compiler": self.compiler,
+            "target": self.target,
+            "output": self.output,
+            "port": self.port,
+            "flags": self.flags,
+        });
+
+        fs::write(path, format!("{:#}\n", saved))
+    }
+
+    /// Opciones adicionales, separadas por espacios
+    pub fn extra_flags(&self) -> Vec<&str> {
+        self.flags.split_whitespace().collect()
+    }
+}
+
+/// Ruta del archivo de configuración
+fn path() -> Option<PathBuf> {
+    let config = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(config) if !config.is_empty() => PathBuf::from(config),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(config.join("animationled").join("editor.json"))
+}
+
+/// Función edit
+/// Muestra el diálogo de preferencias. Al aceptar, se
+/// actualizan y guardan los valores y se invoca on_saved
+/// con el resultado del guardado
+pub fn edit<W, F>(parent: &W, settings: &Rc<RefCell<Settings>>, on_saved: F)
+where
+    W: IsA<gtk::Window>,
+    F: Fn(io::Result<()>) + 'static,
+{
+    let dialog = gtk::Dialog::with_buttons(
+        Some("Preferences"),
+        Some(parent),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[
+            ("Cancel", gtk::ResponseType::Cancel),
+            ("Save", gtk::ResponseType::Ok),
+        ],
+    );
+
+    let grid = gtk::Grid::new();
+    grid.set_row_spacing(6);
+    grid.set_column_spacing(12);
+    grid.set_border_width(12);
+
+    let current = settings.borrow().clone();
+
+    let entry = |row: i32, label: &str, value: &str| {
+        let entry = gtk::Entry::new();
+        entry.set_text(value);
+        entry.set_hexpand(true);
+
+        attach(&grid, row, label, &entry);
+        entry
+    };
+
+    let compiler = entry(0, "Compiler", &current.compiler);
+
+    let target = gtk::ComboBoxText::new();
+    for name in TARGETS {
+        target.append(Some(*name), name);
+    }
+
+    target.set_active_id(Some(current.target.as_str()));
+    attach(&grid, 1, "Target platform", &target);
+
+    let output = entry(2, "Output name", &current.output);
+    let port = entry(3, "Serial port", &current.port);
+    let flags = entry(4, "Extra flags", &current.flags);
+
+    dialog.get_content_area().add(&grid);
+
+    let settings = Rc::clone(settings);
+    dialog.connect_response(move |dialog, response| {
+        if response == gtk::ResponseType::Ok {
+            let updated = Settings {
+                compiler: compiler.get_text().to_string(),
+                target: target
+                    .get_active_id()
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|| current.target.clone()),
+                output: output.get_text().to_string(),
+                port: port.get_text().to_string(),
+                flags: flags.get_text().to_string(),
+            };
+
+            on_saved(updated.save());
+            *settings.borrow_mut() = updated;
+        }
+
+        dialog.close();
+    });
+
+    dialog.show_all();
+}
+
+/// Agrega una fila con etiqueta al diálogo
+fn attach<W: IsA<gtk::Widget>>(grid: &gtk::Grid, row: i32, label: &str, widget: &W) {
+    let label = gtk::Label::new(Some(label));
+    label.set_halign(gtk::Align::End);
+
+    grid.attach(&label, 0, row, 1, 1);
+    grid.attach(widget, 1, row, 1, 1);
+}
@@ -0,0 +1,3 @@
+INCLUDE "memory.x"
+INCLUDE "bl-riscv-link.x"
+INCLUDE "hal-defaults.x"
@@ -0,0 +1,4 @@
+REGION_ALIAS("ROTEXT", irom_seg);
+REGION_ALIAS("RWTEXT", iram_seg);
+REGION_ALIAS("RODATA", dram_seg);
+REGION_ALIAS("RWDATA", dram_seg);
@@ -0,0 +1,83 @@
+/* This memory map assumes the flash cache is on; 
+   the blocks used are excluded from the various memory ranges 
+   
+   see: https://github.com/espressif/esp-idf/blob/master/components/soc/src/esp32/soc_memory_layout.c
+   for details
+   */
+
+/* override entry point */
+ENTRY(ESP8266Reset)
+
+/* define stack size for both cores */
+STACK_SIZE = 8k;
+
+/* MEMORY se genera a partir de las regiones en src/memory.rs */
+
+/* map generic regions to output sections */
+INCLUDE "alias.x"
+
+/* esp8266 specific regions */
+SECTIONS {
+  .rtc.text : {
+   . = ALIGN(4);
+    *(.rtc.literal .rtc.text .rtc.literal.* .rtc.text.*)
+  } > rtc_seg AT > RODATA
+
+  .rtc.data :
+  {
+    _rtc_data_start = ABSOLUTE(.);
+    . = ALIGN(4);
+    *(.rtc.data .rtc.data.*)
+    _rtc_data_end = ABSOLUTE(.);
+  } > rtc_seg AT > RODATA
+
+ .rtc.bss (NOLOAD) :
+  {
+    _rtc_bss_start = ABSOLUTE(.);
+    . = ALIGN(4);
+    *(.rtc.bss .rtc.bss.*)
+    _rtc_bss_end = ABSOLUTE(.);
+  } > rtc_seg
+
+ .rtc.noinit (NOLOAD) :
+  {
+    . = ALIGN(4);
+    *(.rtc.noinit .rtc.noinit.*)
+  } > rtc_seg
+}
+
+_heap_end = ABSOLUTE(ORIGIN(dram_seg))+LENGTH(dram_seg)+LENGTH(reserved_for_boot_seg) - 2*STACK_SIZE;
+_text_heap_end = ABSOLUTE(ORIGIN(iram_seg)+LENGTH(iram_seg));
+
+_stack_start_cpu1 = _heap_end;
+_stack_end_cpu1 = _stack_start_cpu1 + STACK_SIZE;
+_stack_start_cpu0 = _stack_end_cpu1;
+_stack_end_cpu0 = _stack_start_cpu0 + STACK_SIZE;
+
+EXTERN(DefaultHandler);
+
+/* interrupt handlers */
+PROVIDE(__slc_interrupt = __default_interrupt);
+PROVIDE(__spi_interrupt = __default_interrupt);
+PROVIDE(__gpio_interrupt = __default_interrupt);
+PROVIDE(__uart_interrupt = __default_interrupt);
+PROVIDE(__ccompare_interrupt = __default_interrupt);
+PROVIDE(__soft_interrupt = __default_interrupt);
+PROVIDE(__wdt_interrupt = __default_interrupt);
+PROVIDE(__timer1_interrupt = __default_interrupt);
+
+PROVIDE(__slc_hal_interrupt = __default_interrupt);
+PROVIDE(__spi_hal_interrupt = __default_interrupt);
+PROVIDE(__gpio_hal_interrupt = __default_interrupt);
+PROVIDE(__uart_hal_interrupt = __default_interrupt);
+PROVIDE(__ccompare_hal_interrupt = __default_interrupt);
+PROVIDE(__soft_hal_interrupt = __default_interrupt);
+PROVIDE(__wdt_hal_interrupt = __default_interrupt);
+PROVIDE(__timer1_hal_interrupt = __default_interrupt);
+
+/* ROM routines for persistent storage, see runtime/src/esp8266/mod.rs */
+PROVIDE(SPIRead = 0x40004b1c);
+PROVIDE(SPIWrite = 0x40004a4c);
+PROVIDE(SPIEraseSector = 0x40004a00);
+PROVIDE(Cache_Read_Disable = 0x400047f0);
+PROVIDE(Cache_Read_Enable = 0x40004678);
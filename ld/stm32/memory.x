@@ -0,0 +1,7 @@
+/* STM32F411CE */
+/* MEMORY se genera a partir de las regiones en src/memory.rs */
+
+/* This is where the call stack will be allocated. */
+/* The stack is of the full descending type. */
+/* NOTE Do NOT modify `_stack_start` unless you know what you are doing */
+_stack_start = ORIGIN(RAM) + LENGTH(RAM);
@@ -3,17 +3,22 @@
 use nix::libc;
 use std::{
     env,
-    fs::File,
+    fs::{self, File},
+    ops::RangeInclusive,
     os::unix::{io::AsRawFd, process::CommandExt},
     path::PathBuf,
     process::{Command, ExitCode},
 };
 
 fn main() -> ExitCode {
-    if std::env::var("CARGO_CFG_TARGET_ARCH").unwrap() == "xtensa" {
-        xbuild_main()
-    } else {
-        hosted_main()
+    match std::env::var("CARGO_CFG_TARGET_ARCH").unwrap().as_str() {
+        "xtensa" => xbuild_main(),
+
+        // cortex-m-rt y riscv-rt proveen lo necesario, no hay nada que hacer;
+        // en WebAssembly el entorno JavaScript invoca a user_main()
+        "arm" | "riscv32" | "wasm32" => ExitCode::SUCCESS,
+
+        _ => hosted_main(),
     }
 }
 
@@ -26,9 +31,61 @@ fn xbuild_main() -> ExitCode {
         .file(PATH)
         .compile("atomic_shim");*/
 
+    display_config()
+}
+
+/// Genera los parámetros de multiplexado de la matriz para ESP8266.
+///
+/// `ANIMLED_SCAN_RATE` es la frecuencia de refresco de la matriz
+/// completa en Hz. `ANIMLED_BRIGHTNESS` es el porcentaje del tiempo
+/// asignado a cada fila durante el cual esta permanece encendida.
+/// `ANIMLED_PANIC_RESET` es la cantidad de segundos tras un panic antes
+/// de reiniciar la tarjeta, `0` para nunca. Ver `src/esp8266/mod.rs`.
+fn display_config() -> ExitCode {
+    // El límite superior corresponde a una fila por tick de 100us
+    let scan_rate = env_param("ANIMLED_SCAN_RATE", 125, 1..=1250);
+    let brightness = env_param("ANIMLED_BRIGHTNESS", 100, 1..=100);
+    let panic_reset = env_param("ANIMLED_PANIC_RESET", 0, 0..=3600);
+
+    let (scan_rate, brightness, panic_reset) = match (scan_rate, brightness, panic_reset) {
+        (Some(scan_rate), Some(brightness), Some(panic_reset)) => {
+            (scan_rate, brightness, panic_reset)
+        }
+
+        _ => return ExitCode::FAILURE,
+    };
+
+    let config = format!(
+        "pub const SCAN_RATE: u32 = {};\npub const BRIGHTNESS: u32 = {};\npub const PANIC_RESET: u32 = {};\n",
+        scan_rate, brightness, panic_reset
+    );
+
+    let path = PathBuf::from(env::var("OUT_DIR").unwrap()).join("display.rs");
+    fs::write(path, config).expect("Failed to write display configuration");
+
     ExitCode::SUCCESS
 }
 
+fn env_param(name: &str, default: u32, range: RangeInclusive<u32>) -> Option<u32> {
+    println!("cargo:rerun-if-env-changed={}", name);
+
+    let value = match env::var(name) {
+        Ok(value) => value.parse().ok().filter(|value| range.contains(value)),
+        Err(_) => Some(default),
+    };
+
+    if value.is_none() {
+        eprintln!(
+            "{} must be an integer from {} to {}",
+            name,
+            range.start(),
+            range.end()
+        );
+    }
+
+    value
+}
+
 fn hosted_main() -> ExitCode {
     let xtensa_root: PathBuf = if let Ok(xtensa_root) = env::var("RUST_XTENSA") {
         xtensa_root.into()
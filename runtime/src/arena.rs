@@ -0,0 +1,97 @@
+//! Arena de temporales por cuadro.
+//!
+//! Las animaciones suelen crear y destruir listas en cada iteración de
+//! un ciclo, lo cual fragmenta un heap tan pequeño como el de ESP8266.
+//! Con `--arena`, el compilador emite `builtin_frame_end()` cada vez
+//! que un ciclo regresa a su inicio. A partir de la primera de estas
+//! llamadas, los objetos que crean `builtin_new_list()`,
+//! `builtin_new_ilist()` y `builtin_new_mat()` se toman de una región
+//! fija con solo incrementar un desplazamiento.
+//!
+//! La región no libera objetos individuales, sino que cuenta cuántos
+//! siguen vivos, y al final de cada cuadro se reinicia por completo si
+//! no queda ninguno. Un objeto que sobrevive al cuadro, por ejemplo al
+//! asignarse a una variable externa al ciclo, impide reiniciarla hasta
+//! que se libere. Mientras tanto, al igual que cuando la región se
+//! agota, los objetos se toman del heap como de costumbre.
+//!
+//! Solo el objeto en sí reside en la región, no así el contenido de la
+//! lista o matriz. La región no cuenta para `HeapUsed()`.
+
+use core::{alloc::Layout, ptr};
+
+/// Tamaño de la región en bytes.
+pub const ARENA_SIZE: usize = 2048;
+
+#[repr(align(8))]
+struct Region([u8; ARENA_SIZE]);
+
+pub struct Arena {
+    region: Region,
+    top: usize,
+    live: usize,
+    enabled: bool,
+    requested: bool,
+}
+
+impl Arena {
+    pub const fn new() -> Self {
+        Arena {
+            region: Region([0; ARENA_SIZE]),
+            top: 0,
+            live: 0,
+            enabled: false,
+            requested: false,
+        }
+    }
+
+    /// Indica si las asignaciones siguientes corresponden a objetos
+    /// que pueden residir en la región.
+    pub fn request(&mut self, requested: bool) {
+        self.requested = requested;
+    }
+
+    /// Toma un bloque de la región, o retorna un puntero nulo si la
+    /// asignación no corresponde a la región o no cabe en esta.
+    pub fn alloc(&mut self, layout: Layout) -> *mut u8 {
+        if !self.enabled || !self.requested {
+            return ptr::null_mut();
+        }
+
+        let base = self.region.0.as_mut_ptr();
+        let align = layout.align();
+        let start = (base as usize + self.top + align - 1) & !(align - 1);
+        let start = start - base as usize;
+
+        match start.checked_add(layout.size()) {
+            Some(end) if end <= ARENA_SIZE => {
+                self.top = end;
+                self.live += 1;
+
+                unsafe { base.add(start) }
+            }
+
+            _ => ptr::null_mut(),
+        }
+    }
+
+    /// Determina si un bloque proviene de la región.
+    pub fn owns(&self, block: *mut u8) -> bool {
+        let base = self.region.0.as_ptr() as usize;
+        (base..base + ARENA_SIZE).contains(&(block as usize))
+    }
+
+    /// Libera un bloque de la región. El espacio no se recupera sino
+    /// hasta el siguiente reinicio.
+    pub fn dealloc(&mut self) {
+        self.live -= 1;
+    }
+
+    /// Finaliza un cuadro, reiniciando la región si está libre.
+    pub fn frame_end(&mut self) {
+        self.enabled = true;
+        if self.live == 0 {
+            self.top = 0;
+        }
+    }
+}
@@ -11,22 +11,74 @@
 //! vez de eso, este módulo debe realizar a llamadas a
 //! `sys::*` cuando se necesita una operación
 //! que depende de la plataforma.
+//!
+//! # Filas compartidas
+//! Las filas de una matriz son `Rc<List>`, por lo cual varias matrices
+//! (o una matriz y una lista) pueden compartir una misma fila, por
+//! ejemplo tras un slice. Una matriz nunca modifica una fila compartida
+//! en su lugar, sino que la copia antes de escribir mediante
+//! `Rc::make_mut()`.
+
+use alloc::{borrow::Cow, rc::Rc, string::String, vec::Vec};
+use core::{
+    convert::TryInto,
+    iter,
+    mem::ManuallyDrop,
+    ops::{Deref, Range},
+};
 
-use alloc::{rc::Rc, vec::Vec};
-use core::{convert::TryInto, iter, ops::Deref};
-
-#[cfg(target_arch = "xtensa")]
+#[cfg(any(target_arch = "xtensa", target_arch = "arm", target_arch = "riscv32"))]
 use micromath::F32Ext;
 use paste::paste;
 
 use crate::{
     chrono::{Duration, Ticks},
-    matrix::State,
-    sys,
+    error::{self, RuntimeError},
+    global::Global,
+    heap,
+    matrix::{self, State},
+    random::Xorshift,
+    schedule, storage, sys, uart,
 };
 
+#[cfg(feature = "debug-rc")]
+use crate::refcount;
+
 type List = Vec<bool>;
+type IntList = Vec<isize>;
 type Mat = Vec<Rc<List>>;
+type Str = String;
+
+/// Generador de `builtin_random()`.
+static RANDOM: Global<Xorshift> = Global::new(Xorshift::new(0));
+
+/// Un objeto con conteo de referencias que el código emitido manipula
+/// por medio de punteros obtenidos con `Rc::into_raw()`.
+trait Object: Sized {
+    /// Sufijo de los builtins del tipo, ver `refcount`.
+    #[cfg(feature = "debug-rc")]
+    const KIND: &'static str;
+}
+
+impl Object for List {
+    #[cfg(feature = "debug-rc")]
+    const KIND: &'static str = "list";
+}
+
+impl Object for IntList {
+    #[cfg(feature = "debug-rc")]
+    const KIND: &'static str = "ilist";
+}
+
+impl Object for Mat {
+    #[cfg(feature = "debug-rc")]
+    const KIND: &'static str = "mat";
+}
+
+impl Object for Str {
+    #[cfg(feature = "debug-rc")]
+    const KIND: &'static str = "str";
+}
 
 trait Tensor {
     fn mutate_entries<F>(&mut self, mutator: F)
@@ -58,12 +110,13 @@ impl Tensor for [Rc<List>] {
         F: Fn(&mut bool),
     {
         self.iter_mut().for_each(|row| {
-            let row = unsafe { Rc::get_mut_unchecked(row) };
+            let row = Rc::make_mut(row);
             row.mutate_entries(&mutator);
         });
     }
 }
 
+#[derive(Copy, Clone)]
 enum Orientation {
     Rows,
     Columns,
@@ -95,137 +148,227 @@ pub extern "C" fn builtin_debug_float(line: isize, hint: isize) {
 
 #[no_mangle]
 pub extern "C" fn builtin_debug_list(line: isize, list: *mut List) {
-    let list = unsafe { &*list };
+    let list = deref(list);
     sys_debug!("[line {}] builtin_debug_list({:?})", line, list);
 }
 
+#[no_mangle]
+pub extern "C" fn builtin_debug_ilist(line: isize, list: *mut IntList) {
+    let list = deref(list);
+    sys_debug!("[line {}] builtin_debug_ilist({:?})", line, list);
+}
+
 #[no_mangle]
 pub extern "C" fn builtin_debug_mat(line: isize, mat: *mut Mat) {
-    let mat = unsafe { &*mat };
+    let mat = deref(mat);
     sys_debug!("[line {}] builtin_debug_mat({:?})", line, mat);
 }
 
+#[no_mangle]
+pub extern "C" fn builtin_debug_str(line: isize, string: *mut Str) {
+    let string = deref(string);
+    sys_debug!("[line {}] {}", line, string);
+}
+
 #[no_mangle]
 pub extern "C" fn builtin_new_list() -> *mut List {
-    Rc::into_raw(Rc::<List>::default()) as *mut _
+    give(sys::heap().in_arena(Rc::<List>::default))
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_new_ilist() -> *mut IntList {
+    give(sys::heap().in_arena(Rc::<IntList>::default))
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_new_mat() -> *mut Mat {
-    Rc::into_raw(Rc::<Mat>::default()) as *mut _
+    give(sys::heap().in_arena(Rc::<Mat>::default))
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_new_str() -> *mut Str {
+    give(Rc::<Str>::default())
 }
 
 #[no_mangle]
-pub extern "C" fn builtin_ref_list(list: *mut List) {
-    let list = unsafe { Rc::from_raw(list) };
-    let clone = Rc::clone(&list);
+pub extern "C" fn builtin_ref_list(list: *mut List) -> *mut List {
+    give(Rc::clone(&*shared(list)))
+}
 
-    Rc::into_raw(list);
-    Rc::into_raw(clone);
+#[no_mangle]
+pub extern "C" fn builtin_ref_ilist(list: *mut IntList) -> *mut IntList {
+    give(Rc::clone(&*shared(list)))
 }
 
 #[no_mangle]
-pub extern "C" fn builtin_ref_mat(mat: *mut Mat) {
-    let mat = unsafe { Rc::from_raw(mat) };
-    let clone = Rc::clone(&mat);
+pub extern "C" fn builtin_ref_mat(mat: *mut Mat) -> *mut Mat {
+    give(Rc::clone(&*shared(mat)))
+}
 
-    Rc::into_raw(mat);
-    Rc::into_raw(clone);
+#[no_mangle]
+pub extern "C" fn builtin_ref_str(string: *mut Str) -> *mut Str {
+    give(Rc::clone(&*shared(string)))
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_drop_list(list: *mut List) {
-    //dropea valor al tomar ownership
-    unsafe {
-        Rc::from_raw(list);
-    }
+    release(list);
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_drop_ilist(list: *mut IntList) {
+    release(list);
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_drop_mat(mat: *mut Mat) {
-    unsafe {
-        Rc::from_raw(mat);
-    }
+    release(mat);
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_drop_str(string: *mut Str) {
+    release(string);
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_eq_list(first: *mut List, second: *mut List) -> isize {
-    let (first, second) = unsafe { (&*first, &*second) };
+    let (first, second) = (deref(first), deref(second));
+    bool_to_ffi(first == second)
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_eq_ilist(first: *mut IntList, second: *mut IntList) -> isize {
+    let (first, second) = (deref(first), deref(second));
     bool_to_ffi(first == second)
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_eq_mat(first: *mut Mat, second: *mut Mat) -> isize {
-    let (first, second) = unsafe { (&*first, &*second) };
+    let (first, second) = (deref(first), deref(second));
+    bool_to_ffi(first == second)
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_eq_str(first: *mut Str, second: *mut Str) -> isize {
+    let (first, second) = (deref(first), deref(second));
     bool_to_ffi(first == second)
 }
 
+#[no_mangle]
+pub extern "C" fn builtin_and_mat(first: *mut Mat, second: *mut Mat) -> *mut Mat {
+    zip_mat(first, second, |a, b| a & b)
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_or_mat(first: *mut Mat, second: *mut Mat) -> *mut Mat {
+    zip_mat(first, second, |a, b| a | b)
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_xor_mat(first: *mut Mat, second: *mut Mat) -> *mut Mat {
+    zip_mat(first, second, |a, b| a ^ b)
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_push_str(string: *mut Str, c: isize) {
+    let string = deref_mut(string);
+    let c = core::char::from_u32(c as u32).unwrap_or(core::char::REPLACEMENT_CHARACTER);
+    string.push(c);
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_concat_str(first: *mut Str, second: *mut Str) -> *mut Str {
+    let (first, second) = (deref(first), deref(second));
+
+    let mut concat = Str::with_capacity(first.len() + second.len());
+    concat.push_str(first);
+    concat.push_str(second);
+
+    give(Rc::new(concat))
+}
+
 #[no_mangle]
 pub extern "C" fn builtin_index_list(list: *mut List, index: isize) -> isize {
-    let list = unsafe { &*list };
-    bool_to_ffi(list[try_usize(index)])
+    let list = deref(list);
+    bool_to_ffi(list[error::index(index, list.len())])
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_index_ilist(list: *mut IntList, index: isize) -> isize {
+    let list = deref(list);
+    list[error::index(index, list.len())]
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_index_entry_mat(mat: *mut Mat, row: isize, column: isize) -> isize {
-    let mat = unsafe { &*mat };
-    bool_to_ffi(mat[try_usize(row)][try_usize(column)])
+    let mat = deref(mat);
+    let row = &mat[error::index(row, shapef(mat))];
+    bool_to_ffi(row[error::index(column, row.len())])
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_index_row_mat(mat: *mut Mat, row: isize) -> *mut List {
-    let mat = unsafe { &*mat };
-    let row_list = Rc::clone(&mat[try_usize(row)]);
-    Rc::into_raw(row_list) as *mut _
+    let mat = deref(mat);
+    let row_list = Rc::clone(&mat[error::index(row, shapef(mat))]);
+    give(row_list)
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_index_column_mat(mat: *mut Mat, column: isize) -> *mut List {
-    let mat = unsafe { &*mat };
-    let column = try_usize(column);
+    let mat = deref(mat);
+    let column = error::index(column, shapec(mat));
 
     let column_list = mat.iter().map(|row| row[column]).collect::<List>();
-    Rc::into_raw(Rc::new(column_list)) as *mut _
+    give(Rc::new(column_list))
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_insert_list(list: *mut List, index: isize, item: bool) {
-    let list = unsafe { &mut *list };
-    list.insert(try_usize(index), item);
+    let list = deref_mut(list);
+    list.insert(error::position(index, list.len()), item);
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_insert_ilist(list: *mut IntList, index: isize, item: isize) {
+    let list = deref_mut(list);
+    list.insert(error::position(index, list.len()), item);
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_insert_mat(mat: *mut Mat, vectors: *mut Mat, mode: isize, index: isize) {
-    let (mat, vectors) = unsafe { (&mut *mat, &*vectors) };
-    insert_in_mat(mat, vectors, mode, try_usize(index));
+    let length = length_along(deref(mat), try_orientation(mode));
+    insert_in_mat(mat, vectors, mode, error::position(index, length));
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_insert_end_mat(mat: *mut Mat, vectors: *mut Mat, mode: isize) {
-    let (mat, vectors) = unsafe { (&mut *mat, &*vectors) };
-    let length = match try_orientation(mode) {
-        Orientation::Rows => shapef(mat),
-        Orientation::Columns => shapec(mat),
-    };
-
+    let length = length_along(deref(mat), try_orientation(mode));
     insert_in_mat(mat, vectors, mode, length);
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_delete_list(list: *mut List, index: isize) {
-    let list = unsafe { &mut *list };
-    list.remove(try_usize(index));
+    let list = deref_mut(list);
+    list.remove(error::index(index, list.len()));
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_delete_ilist(list: *mut IntList, index: isize) {
+    let list = deref_mut(list);
+    list.remove(error::index(index, list.len()));
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_delete_mat(mat: *mut Mat, index: isize, mode: isize) {
-    let mat = unsafe { &mut *mat };
+    let mat = deref_mut(mat);
+
+    let orientation = try_orientation(mode);
+    let index = error::index(index, length_along(mat, orientation));
 
-    let index = try_usize(index);
-    match try_orientation(mode) {
+    match orientation {
         Orientation::Rows => drop(mat.remove(index)),
         Orientation::Columns => mat.iter_mut().for_each(|row| {
-            let row = unsafe { Rc::get_mut_unchecked(row) };
+            let row = Rc::make_mut(row);
             row.remove(index);
         }),
     }
@@ -233,15 +376,100 @@ pub extern "C" fn builtin_delete_mat(mat: *mut Mat, index: isize, mode: isize) {
 
 #[no_mangle]
 pub extern "C" fn builtin_push_mat(mat: *mut Mat, item: *mut List) {
-    let (mat, item) = unsafe { (&mut *mat, Rc::from_raw(item)) };
+    let (mat, item) = (deref_mut(mat), shared(item));
+
+    insert_vectors(mat, core::slice::from_ref(&*item), 0, mat.len());
+}
+
+/// Desplaza las entradas `n` posiciones hacia índices mayores, o hacia
+/// índices menores si `n` es negativo. Las entradas que salen por un
+/// extremo entran por el otro si `wrap` es verdadero, de lo contrario
+/// se descartan y las posiciones liberadas quedan apagadas.
+#[no_mangle]
+pub extern "C" fn builtin_shift_list(list: *mut List, n: isize, wrap: bool) {
+    let list = deref_mut(list);
+
+    let vacated = shift(list, n, wrap);
+    list[vacated].iter_mut().for_each(|entry| *entry = false);
+}
+
+/// Desplaza columnas `dx` posiciones y filas `dy` posiciones, de la
+/// misma forma que `builtin_shift_list()`. Un valor positivo mueve hacia
+/// la derecha o hacia abajo, respectivamente.
+#[no_mangle]
+pub extern "C" fn builtin_shift_mat(mat: *mut Mat, dx: isize, dy: isize, wrap: bool) {
+    let mat = deref_mut(mat);
+    let width = shapec(mat);
+
+    if dy != 0 {
+        let vacated = shift(mat, dy, wrap);
+        if !vacated.is_empty() {
+            let blank = Rc::new(iter::repeat(false).take(width).collect::<List>());
+            mat[vacated]
+                .iter_mut()
+                .for_each(|row| *row = Rc::clone(&blank));
+        }
+    }
+
+    if dx != 0 {
+        for row in mat.iter_mut() {
+            let row = Rc::make_mut(row);
+
+            let vacated = shift(row, dx, wrap);
+            row[vacated].iter_mut().for_each(|entry| *entry = false);
+        }
+    }
+}
+
+/// Intercambia filas por columnas.
+#[no_mangle]
+pub extern "C" fn builtin_transpose_mat(mat: *mut Mat) {
+    transpose(deref_mut(mat));
+}
+
+/// Rota en cuartos de vuelta, en sentido horario si `quarter_turns`
+/// es positivo y antihorario si es negativo.
+#[no_mangle]
+pub extern "C" fn builtin_rotate_mat(mat: *mut Mat, quarter_turns: isize) {
+    let mat = deref_mut(mat);
+
+    match quarter_turns.rem_euclid(4) {
+        1 => {
+            transpose(mat);
+            mirror(mat, Orientation::Columns);
+        }
+
+        2 => {
+            mirror(mat, Orientation::Rows);
+            mirror(mat, Orientation::Columns);
+        }
+
+        3 => {
+            transpose(mat);
+            mirror(mat, Orientation::Rows);
+        }
+
+        _ => (),
+    }
+}
 
-    insert_in_mat(mat, core::slice::from_ref(&item), 0, mat.len());
-    Rc::into_raw(item);
+/// Invierte el orden de las filas (`axis = 0`) o de las columnas
+/// (`axis = 1`).
+#[no_mangle]
+pub extern "C" fn builtin_mirror_mat(mat: *mut Mat, axis: isize) {
+    let mat = deref_mut(mat);
+    mirror(mat, try_orientation(axis));
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_len_list(list: *mut List) -> isize {
-    let list = unsafe { &*list };
+    let list = deref(list);
+    list.len() as isize
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_len_ilist(list: *mut IntList) -> isize {
+    let list = deref(list);
     list.len() as isize
 }
 
@@ -249,62 +477,68 @@ pub extern "C" fn builtin_len_list(list: *mut List) -> isize {
 
 #[no_mangle]
 pub extern "C" fn builtin_slice_list(list: *mut List, from: isize, to: isize) -> *mut List {
-    let list = unsafe { &*list };
-    let slice = (&list[try_usize(from)..try_usize(to)]).to_vec();
-    Rc::into_raw(Rc::new(slice)) as *mut _
+    let list = deref(list);
+    let slice = (&list[error::range(from, to, list.len())]).to_vec();
+    give(Rc::new(slice))
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_slice_ilist(list: *mut IntList, from: isize, to: isize) -> *mut IntList {
+    let list = deref(list);
+    let slice = (&list[error::range(from, to, list.len())]).to_vec();
+    give(Rc::new(slice))
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_slice_mat(mat: *mut Mat, from: isize, to: isize) -> *mut Mat {
-    let mat = unsafe { &*mat };
-    let slice = (&mat[try_usize(from)..try_usize(to)]).to_vec();
-    Rc::into_raw(Rc::new(slice)) as *mut _
+    let mat = deref(mat);
+    let slice = (&mat[error::range(from, to, shapef(mat))]).to_vec();
+    give(Rc::new(slice))
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_set_entry_list(list: *mut List, index: isize, entry: bool) {
-    let list = unsafe { &mut *list };
-    list[try_usize(index)] = entry;
+    let list = deref_mut(list);
+    let index = error::index(index, list.len());
+    list[index] = entry;
+}
+
+#[no_mangle]
+pub extern "C" fn builtin_set_entry_ilist(list: *mut IntList, index: isize, entry: isize) {
+    let list = deref_mut(list);
+    let index = error::index(index, list.len());
+    list[index] = entry;
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_set_entry_mat(mat: *mut Mat, row: isize, col: isize, entry: bool) {
-    let mat = unsafe { &mut *mat };
-    let row = unsafe { Rc::get_mut_unchecked(&mut mat[try_usize(row)]) };
-    row[try_usize(col)] = entry;
+    let mat = deref_mut(mat);
+    let row = error::index(row, shapef(mat));
+    let row = Rc::make_mut(&mut mat[row]);
+
+    let col = error::index(col, row.len());
+    row[col] = entry;
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_set_row_mat(mat: *mut Mat, row: isize, entry: *mut List) {
-    let (mat, entry) = unsafe { (&mut *mat, Rc::from_raw(entry)) };
-
-    let shapec = shapec(mat);
-    assert!(
-        shapec == entry.len(),
-        "attempted to replace row of length {} with list of length {}",
-        shapec,
-        entry.len()
-    );
+    let (mat, entry) = (deref_mut(mat), shared(entry));
 
-    mat[try_usize(row)] = Rc::clone(&entry);
-    Rc::into_raw(entry);
+    error::shape(shapec(mat), entry.len());
+
+    let row = error::index(row, shapef(mat));
+    mat[row] = Rc::clone(&*entry);
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_set_column_mat(mat: *mut Mat, column: isize, entry: *mut List) {
-    let (mat, entry) = unsafe { (&mut *mat, &*entry) };
-
-    let shapef = shapef(mat);
-    assert!(
-        shapef == entry.len(),
-        "attempted to replace column of length {} with list of length {}",
-        shapef,
-        entry.len()
-    );
+    let (mat, entry) = (deref_mut(mat), deref(entry));
+
+    error::shape(shapef(mat), entry.len());
+    let column = error::index(column, shapec(mat));
 
-    let column = try_usize(column);
     for (row, value) in mat.iter_mut().zip(entry.iter().cloned()) {
-        let row = unsafe { Rc::get_mut_unchecked(row) };
+        let row = Rc::make_mut(row);
         row[column] = value;
     }
 }
@@ -316,10 +550,12 @@ pub extern "C" fn builtin_set_slice_list(
     to: isize,
     values: *mut List,
 ) {
-    let (list, values) = unsafe { (&mut *list, &*values) };
+    let (list, values) = deref_aliased(list, values);
+
+    let range = error::range(from, to, list.len());
+    error::shape(range.len(), values.len());
 
-    let target = &mut list[try_usize(from)..try_usize(to)];
-    assert!(target.len() == values.len());
+    let target = &mut list[range];
 
     target
         .iter_mut()
@@ -327,20 +563,31 @@ pub extern "C" fn builtin_set_slice_list(
         .for_each(|(entry, value)| *entry = value);
 }
 
+#[no_mangle]
+pub extern "C" fn builtin_set_slice_ilist(
+    list: *mut IntList,
+    from: isize,
+    to: isize,
+    values: *mut IntList,
+) {
+    let (list, values) = deref_aliased(list, values);
+
+    let range = error::range(from, to, list.len());
+    error::shape(range.len(), values.len());
+
+    list[range].copy_from_slice(&values);
+}
+
 #[no_mangle]
 pub extern "C" fn builtin_set_slice_mat(mat: *mut Mat, from: isize, to: isize, rows: *mut Mat) {
-    let (mat, rows) = unsafe { (&mut *mat, &*rows) };
-
-    let (target_shapec, source_shapec) = (shapec(mat), shapec(rows));
-    assert!(
-        source_shapec == target_shapec,
-        "attempted to replace matrix slice of {} columns with matrix of {} columns",
-        target_shapec,
-        source_shapec
-    );
+    let (mat, rows) = deref_aliased(mat, rows);
 
-    let target = &mut mat[try_usize(from)..try_usize(to)];
-    assert!(target.len() == rows.len());
+    error::shape(shapec(mat), shapec(&rows));
+
+    let range = error::range(from, to, shapef(mat));
+    error::shape(range.len(), shapef(&rows));
+
+    let target = &mut mat[range];
 
     for (target_row, source_row) in target.iter_mut().zip(rows.iter()) {
         *target_row = source_row.clone();
@@ -349,20 +596,20 @@ pub extern "C" fn builtin_set_slice_mat(mat: *mut Mat, from: isize, to: isize, r
 
 #[no_mangle]
 pub extern "C" fn builtin_shapef(mat: *mut Mat) -> isize {
-    let mat = unsafe { &*mat };
+    let mat = deref(mat);
     shapef(mat) as isize
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_shapec(mat: *mut Mat) -> isize {
-    let mat = unsafe { &*mat };
+    let mat = deref(mat);
     shapec(mat) as isize
 }
 
 #[no_mangle]
 pub extern "C" fn builtin_range(length: isize, value: bool) -> *mut List {
     let list = (0..length).map(|_| value).collect::<List>();
-    Rc::into_raw(Rc::new(list)) as *mut _
+    give(Rc::new(list))
 }
 
 #[no_mangle]
@@ -380,6 +627,42 @@ pub extern "C" fn builtin_div_int(a: isize, b: isize) -> isize {
     f32_to_ffi((a as f32) / (b as f32))
 }
 
+/// Multiplicación para código compilado con `--no-hw-mul`.
+#[no_mangle]
+pub extern "C" fn builtin_mul_int(a: isize, b: isize) -> isize {
+    (a as i32).wrapping_mul(b as i32) as isize
+}
+
+/// División entera para código compilado con `--no-hw-mul`. Trunca
+/// hacia cero, al igual que la instrucción de cada arquitectura.
+#[no_mangle]
+pub extern "C" fn builtin_intdiv_int(a: isize, b: isize) -> isize {
+    if b == 0 {
+        error::fail(RuntimeError::DivisionByZero);
+    }
+
+    (a as i32).wrapping_div(b as i32) as isize
+}
+
+/// Módulo entero para código compilado con `--no-hw-mul`, con el signo
+/// del dividendo.
+#[no_mangle]
+pub extern "C" fn builtin_mod_int(a: isize, b: isize) -> isize {
+    if b == 0 {
+        error::fail(RuntimeError::DivisionByZero);
+    }
+
+    (a as i32).wrapping_rem(b as i32) as isize
+}
+
+/// Invocado por código compilado con `--checked-arithmetic` antes de
+/// dividir entre cero.
+#[no_mangle]
+pub extern "C" fn builtin_trap_div_zero(line: isize) -> ! {
+    error::set_line(line);
+    error::fail(RuntimeError::DivisionByZero)
+}
+
 #[no_mangle]
 pub extern "C" fn builtin_pow_int(a: isize, b: isize) -> isize {
     f32_to_ffi((a as f32).powf(b as f32))
@@ -424,60 +707,126 @@ pub extern "C" fn builtin_cmp_float(a: isize, b: isize) -> isize {
     }
 }
 
+/// Reinicia la secuencia de `builtin_random()`. Una misma semilla
+/// produce siempre la misma secuencia.
+#[no_mangle]
+pub extern "C" fn builtin_seed(seed: isize) {
+    RANDOM.with(|random| *random = Xorshift::new(seed as u32));
+}
+
+/// Un entero pseudoaleatorio en `[0, max)`.
+#[no_mangle]
+pub extern "C" fn builtin_random(max: isize) -> isize {
+    let max = error::random_bound(max);
+    RANDOM.with(|random| random.below(max) as isize)
+}
+
+/// Persiste un entero en un slot, ver `storage`.
+#[no_mangle]
+pub extern "C" fn builtin_store(slot: isize, value: isize) {
+    storage::store(error::slot(slot), value as i32);
+}
+
+/// Lee un entero persistido con `builtin_store()`.
+#[no_mangle]
+pub extern "C" fn builtin_load(slot: isize) -> isize {
+    storage::load(error::slot(slot)) as isize
+}
+
+/// Envía un entero por el puerto serial, ver `uart`.
+#[no_mangle]
+pub extern "C" fn builtin_uart_write_int(value: isize) {
+    uart::write_int(value);
+}
+
+/// Siguiente entero recibido por el puerto serial, o `-1` si aún no
+/// se ha recibido ninguno. Nunca bloquea.
+#[no_mangle]
+pub extern "C" fn builtin_uart_read_int() -> isize {
+    uart::read_int().unwrap_or(-1)
+}
+
+/// Bytes del heap en uso, ver `heap`.
+#[no_mangle]
+pub extern "C" fn builtin_heap_used() -> isize {
+    heap::used().min(isize::MAX as usize) as isize
+}
+
+/// Bytes libres del heap, o el mayor entero si la capacidad es
+/// ilimitada.
+#[no_mangle]
+pub extern "C" fn builtin_heap_free() -> isize {
+    heap::free().min(isize::MAX as usize) as isize
+}
+
+/// Finaliza un cuadro de animación, ver `arena`. Solo se emite con
+/// `--arena`.
+#[no_mangle]
+pub extern "C" fn builtin_frame_end() {
+    sys::heap().frame_end();
+}
+
 macro_rules! mutator {
     ($op:literal, $mutator:expr) => {
         paste! {
             #[no_mangle]
-            pub fn [<builtin_ $op _list>](list: *mut List) {
-                unsafe { &mut *list }.mutate_entries($mutator);
+            pub extern "C" fn [<builtin_ $op _list>](list: *mut List) {
+                deref_mut(list).mutate_entries($mutator);
             }
 
             #[no_mangle]
-            pub fn [<builtin_ $op _mat>](mat: *mut Mat) {
-                unsafe { &mut *mat }.mutate_entries($mutator);
+            pub extern "C" fn [<builtin_ $op _mat>](mat: *mut Mat) {
+                deref_mut(mat).mutate_entries($mutator);
             }
 
             #[no_mangle]
-            pub fn [<builtin_ $op _entry_list>](list: *mut List, index: isize) {
-                let entry = &mut unsafe { &mut *list }[try_usize(index)];
-                entry.mutate_entries($mutator);
+            pub extern "C" fn [<builtin_ $op _entry_list>](list: *mut List, index: isize) {
+                let list = deref_mut(list);
+                let index = error::index(index, list.len());
+                (&mut list[index]).mutate_entries($mutator);
             }
 
             #[no_mangle]
-            pub fn [<builtin_ $op _entry_mat>](mat: *mut Mat, row: isize, column: isize) {
-                let row = &mut unsafe { &mut *mat }[try_usize(row)];
-                let row = unsafe { Rc::get_mut_unchecked(row) };
-                (&mut row[try_usize(column)]).mutate_entries($mutator);
+            pub extern "C" fn [<builtin_ $op _entry_mat>](mat: *mut Mat, row: isize, column: isize) {
+                let mat = deref_mut(mat);
+                let row = error::index(row, shapef(mat));
+                let row = Rc::make_mut(&mut mat[row]);
+
+                let column = error::index(column, row.len());
+                (&mut row[column]).mutate_entries($mutator);
             }
 
             #[no_mangle]
-            pub fn [<builtin_ $op _row_mat>](mat: *mut Mat, row: isize) {
-                let row = &mut unsafe { &mut *mat }[try_usize(row)];
-                let row = unsafe { Rc::get_mut_unchecked(row) };
+            pub extern "C" fn [<builtin_ $op _row_mat>](mat: *mut Mat, row: isize) {
+                let mat = deref_mut(mat);
+                let row = error::index(row, shapef(mat));
+                let row = Rc::make_mut(&mut mat[row]);
                 row.mutate_entries($mutator);
             }
 
             #[no_mangle]
-            pub fn [<builtin_ $op _column_mat>](mat: *mut Mat, column: isize) {
-                let mat = unsafe { &mut *mat };
-                let column = try_usize(column);
+            pub extern "C" fn [<builtin_ $op _column_mat>](mat: *mut Mat, column: isize) {
+                let mat = deref_mut(mat);
+                let column = error::index(column, shapec(mat));
 
                 for row in mat.iter_mut() {
-                    let row = unsafe { Rc::get_mut_unchecked(row) };
+                    let row = Rc::make_mut(row);
                     (&mut row[column]).mutate_entries($mutator);
                 }
             }
 
             #[no_mangle]
-            pub fn [<builtin_ $op _slice_list>](list: *mut List, from: isize, to: isize) {
-                let range = &mut unsafe { &mut *list }[try_usize(from)..try_usize(to)];
-                range.mutate_entries($mutator);
+            pub extern "C" fn [<builtin_ $op _slice_list>](list: *mut List, from: isize, to: isize) {
+                let list = deref_mut(list);
+                let range = error::range(from, to, list.len());
+                (&mut list[range]).mutate_entries($mutator);
             }
 
             #[no_mangle]
-            pub fn [<builtin_ $op _slice_mat>](mat: *mut Mat, from: isize, to: isize) {
-                let range = &mut unsafe { &mut *mat }[try_usize(from)..try_usize(to)];
-                range.mutate_entries($mutator);
+            pub extern "C" fn [<builtin_ $op _slice_mat>](mat: *mut Mat, from: isize, to: isize) {
+                let mat = deref_mut(mat);
+                let range = error::range(from, to, shapef(mat));
+                (&mut mat[range]).mutate_entries($mutator);
             }
         }
     };
@@ -487,22 +836,51 @@ mutator!("neg", |entry| *entry = !*entry);
 mutator!("f", |entry| *entry = false);
 mutator!("t", |entry| *entry = true);
 
+/// Ejecuta un procedimiento una vez, tras una cantidad de milisegundos.
+/// El programa continúa mientras tanto, ver `schedule`.
+#[no_mangle]
+pub extern "C" fn builtin_after(millis: isize, procedure: schedule::Procedure) {
+    schedule::schedule(procedure, milliseconds(millis), None);
+}
+
+/// Ejecuta un procedimiento cada cierta cantidad de milisegundos,
+/// iniciando tras el primer periodo.
+#[no_mangle]
+pub extern "C" fn builtin_every(millis: isize, procedure: schedule::Procedure) {
+    let period = milliseconds(error::period(millis));
+    schedule::schedule(procedure, period, Some(period));
+}
+
 /// Detiene el programa por una cantidad de milisegundos.
 #[no_mangle]
 pub extern "C" fn builtin_delay_mil(millis: isize) {
-    sys::delay(milliseconds(millis));
+    schedule::delay(milliseconds(millis));
+}
+
+/// Completa una iteración de `loop at`, esperando lo que reste de un
+/// cuadro de `millis` milisegundos tras descontar lo que tomó el cuerpo.
+#[no_mangle]
+pub extern "C" fn builtin_frame_sync(millis: isize) {
+    schedule::frame_sync(milliseconds(millis));
+}
+
+/// Milisegundos transcurridos desde el arranque del programa, según el
+/// contador de ticks del temporizador o el reloj del sistema anfitrión.
+#[no_mangle]
+pub extern "C" fn builtin_millis() -> isize {
+    sys::uptime().as_millis().min(isize::MAX as u128) as isize
 }
 
 /// Detiene el programa por una cantidad de segundos.
 #[no_mangle]
 pub extern "C" fn builtin_delay_seg(secs: isize) {
-    sys::delay(seconds(secs));
+    schedule::delay(seconds(secs));
 }
 
 /// Detiene el programa por una cantidad de minutos.
 #[no_mangle]
 pub extern "C" fn builtin_delay_min(mins: isize) {
-    sys::delay(minutes(mins));
+    schedule::delay(minutes(mins));
 }
 
 #[no_mangle]
@@ -522,17 +900,29 @@ pub extern "C" fn builtin_blink_min(col: isize, row: isize, mins: isize, cond: b
 
 #[no_mangle]
 pub extern "C" fn builtin_printled(col: isize, row: isize, value: bool) {
+    error::display(row, col);
     sys::with_display(|display| {
         display[(row, col)].set(State::from_bool(value));
     });
 }
 
+#[no_mangle]
+pub extern "C" fn builtin_printled_intensity(col: isize, row: isize, level: isize) {
+    error::display(row, col);
+    let level = error::intensity(level);
+
+    sys::with_display(|display| {
+        display[(row, col)].set_level(level);
+    });
+}
+
 #[no_mangle]
 pub extern "C" fn builtin_printledx_f(row: isize, list: *mut List) {
-    let list = unsafe { &*list };
+    let list = deref(list);
+    error::display(row, 0);
 
     sys::with_display(|display| {
-        for (col, value) in list_bits(&list) {
+        for (col, value) in list_bits(&list, display.width()) {
             display[(row, col)].set(State::from_bool(value));
         }
     });
@@ -540,10 +930,11 @@ pub extern "C" fn builtin_printledx_f(row: isize, list: *mut List) {
 
 #[no_mangle]
 pub extern "C" fn builtin_printledx_c(col: isize, list: *mut List) {
-    let list = unsafe { &*list };
+    let list = deref(list);
+    error::display(0, col);
 
     sys::with_display(|display| {
-        for (row, value) in list_bits(&list) {
+        for (row, value) in list_bits(&list, display.height()) {
             display[(row, col)].set(State::from_bool(value));
         }
     });
@@ -551,35 +942,148 @@ pub extern "C" fn builtin_printledx_c(col: isize, list: *mut List) {
 
 #[no_mangle]
 pub extern "C" fn builtin_printledx_m(index: isize, mat: *mut Mat) {
-    assert!(
-        index == 0,
-        "PrintLedX(\"M\", index, ...) requires index 0, found {}",
-        index
-    );
+    if index != 0 {
+        error::fail(RuntimeError::BadDisplayIndex(index));
+    }
 
-    let mat = unsafe { &*mat };
+    let mat = deref(mat);
     sys::with_display(|display| {
-        for (row, col, value) in mat_bits(&mat) {
+        let (width, height) = (display.width(), display.height());
+        for (row, col, value) in mat_bits(&mat, width, height) {
             display[(row, col)].set(State::from_bool(value));
         }
     });
 }
 
-fn blink(col: isize, row: isize, duration: Duration, cond: bool) {
-    let allowed = 0..8;
-    if allowed.contains(&col) && allowed.contains(&row) {
-        let ticks = if cond {
-            Ticks::from_duration(duration)
-        } else {
-            Ticks::default()
-        };
-
-        sys::with_display(|display| {
-            display[(row, col)].blink(ticks);
-        });
+/// Presenta el búfer trasero de la matriz, ver `Display::present()`.
+#[no_mangle]
+pub extern "C" fn builtin_present() {
+    sys::with_display(|display| display.present());
+}
+
+/// Cambia las dimensiones de la pantalla cuando esta se compone de
+/// varias matrices encadenadas, ver `Display::resize()`.
+#[no_mangle]
+pub extern "C" fn builtin_set_display_size(width: isize, height: isize) {
+    let (width, height) = error::display_size(width, height);
+    sys::with_display(|display| display.resize(width, height));
+}
+
+/// Desplaza un texto por la matriz de derecha a izquierda, avanzando
+/// una columna cada `speed_ms` milisegundos. Retorna una vez que el
+/// texto sale por completo de la matriz, la cual queda apagada.
+#[no_mangle]
+pub extern "C" fn builtin_scroll_text(text: *mut Str, speed_ms: isize) {
+    let text = deref(text);
+    let step = milliseconds(speed_ms);
+
+    // La primera ventana está vacía
+    let width = sys::with_display(|display| display.width());
+    for frame in matrix::scroll_columns(text, width).windows(width).skip(1) {
+        sys::with_display(|display| display.draw_columns(frame));
+        schedule::delay(step);
+    }
+}
+
+/// Reproduce una secuencia de `len` cuadros generada por `Play`.
+///
+/// Cada cuadro ocupa 10 bytes: ocho filas de bits, donde el bit más
+/// significativo es la columna 0, seguidas de la duración del cuadro
+/// en milisegundos como entero de 16 bits little-endian. Con `looping`,
+/// la secuencia se repite indefinidamente y esta función nunca retorna.
+#[no_mangle]
+pub extern "C" fn builtin_play_frames(frames: *const u8, len: isize, looping: bool) {
+    const FRAME_SIZE: usize = 10;
+
+    // Repetir una secuencia vacía nunca cedería el control
+    let len: usize = len.try_into().unwrap_or_default();
+    if len == 0 {
+        return;
+    }
+
+    let frames = unsafe { core::slice::from_raw_parts(frames, len * FRAME_SIZE) };
+
+    loop {
+        for frame in frames.chunks_exact(FRAME_SIZE) {
+            let (rows, duration) = frame.split_at(8);
+            let duration = u16::from_le_bytes([duration[0], duration[1]]);
+
+            sys::with_display(|display| display.draw_rows(rows));
+            schedule::delay(Duration::from_millis(duration.into()));
+        }
+
+        if !looping {
+            break;
+        }
     }
 }
 
+/// Entrega una nueva referencia al código emitido.
+fn give<T: Object>(object: Rc<T>) -> *mut T {
+    let ptr = Rc::into_raw(object) as *mut T;
+
+    #[cfg(feature = "debug-rc")]
+    refcount::acquire(ptr as usize, T::KIND);
+
+    ptr
+}
+
+/// Recupera una referencia que el código emitido retiene.
+fn shared<T: Object>(ptr: *mut T) -> ManuallyDrop<Rc<T>> {
+    #[cfg(feature = "debug-rc")]
+    refcount::check(ptr as usize, T::KIND);
+
+    ManuallyDrop::new(unsafe { Rc::from_raw(ptr) })
+}
+
+/// Libera una referencia del código emitido.
+fn release<T: Object>(ptr: *mut T) {
+    #[cfg(feature = "debug-rc")]
+    refcount::release(ptr as usize, T::KIND);
+
+    drop(unsafe { Rc::from_raw(ptr) });
+}
+
+fn deref<'a, T: Object>(ptr: *mut T) -> &'a T {
+    #[cfg(feature = "debug-rc")]
+    refcount::check(ptr as usize, T::KIND);
+
+    unsafe { &*ptr }
+}
+
+fn deref_mut<'a, T: Object>(ptr: *mut T) -> &'a mut T {
+    #[cfg(feature = "debug-rc")]
+    refcount::check(ptr as usize, T::KIND);
+
+    unsafe { &mut *ptr }
+}
+
+/// Obtiene un objeto a modificar junto con otro del cual se lee. Si
+/// ambos son el mismo, se lee de una copia.
+fn deref_aliased<'a, T: Object + Clone>(target: *mut T, source: *mut T) -> (&'a mut T, Cow<'a, T>) {
+    let source = if target == source {
+        Cow::Owned(deref(source).clone())
+    } else {
+        Cow::Borrowed(deref(source))
+    };
+
+    (deref_mut(target), source)
+}
+
+fn blink(col: isize, row: isize, duration: Duration, cond: bool) {
+    error::display(row, col);
+
+    let ticks = if cond {
+        Ticks::from_duration(duration)
+    } else {
+        Ticks::default()
+    };
+
+    sys::with_display(|display| {
+        display[(row, col)].blink(ticks);
+    });
+}
+
 fn milliseconds(millis: isize) -> Duration {
     Duration::from_millis(millis.try_into().unwrap_or_default())
 }
@@ -593,39 +1097,41 @@ fn minutes(mins: isize) -> Duration {
     Duration::from_secs(mins * 60)
 }
 
-fn list_bits(list: &[bool]) -> impl '_ + Iterator<Item = (isize, bool)> {
+fn list_bits(list: &[bool], length: usize) -> impl '_ + Iterator<Item = (isize, bool)> {
     list.iter()
         .copied()
         .chain(iter::repeat(false))
         .enumerate()
         .map(|(i, value)| (i as isize, value))
-        .take(8)
+        .take(length)
 }
 
-fn mat_bits(mat: &[Rc<List>]) -> impl '_ + Iterator<Item = (isize, isize, bool)> {
-    const EMPTY_ROW: &'static [bool] = &[false; 8];
+fn mat_bits(
+    mat: &[Rc<List>],
+    width: usize,
+    height: usize,
+) -> impl '_ + Iterator<Item = (isize, isize, bool)> {
+    const EMPTY_ROW: &'static [bool] = &[];
     mat.iter()
         .map(Rc::deref)
         .map(Vec::as_slice)
         .chain(iter::repeat(EMPTY_ROW))
         .enumerate()
-        .map(|(row, row_bits)| list_bits(row_bits).map(move |(col, bit)| (row as isize, col, bit)))
+        .map(move |(row, row_bits)| {
+            list_bits(row_bits, width).map(move |(col, bit)| (row as isize, col, bit))
+        })
         .flatten()
-        .take(8 * 8)
-}
-
-fn try_usize(as_isize: isize) -> usize {
-    as_isize
-        .try_into()
-        .expect("attempted to use negative integer as index")
+        .take(width * height)
 }
 
 fn f32_from_ffi(arg: isize) -> f32 {
     f32::from_bits(arg as u32)
 }
 
+/// Los bits se extienden en signo al igual que cualquier otro valor de
+/// 32 bits en plataformas de 64 bits.
 fn f32_to_ffi(float: f32) -> isize {
-    float.to_bits() as isize
+    float.to_bits() as i32 as isize
 }
 
 fn bool_to_ffi(boolean: bool) -> isize {
@@ -640,17 +1146,89 @@ fn shapec(mat: &Mat) -> usize {
     mat.first().map(|row| row.len()).unwrap_or(0)
 }
 
-fn insert_in_mat(mat: &mut Mat, vectors: &[Rc<List>], mode: isize, index: usize) {
-    // Formalmente, para este punto ya ocurrió UB si la condición de
-    // aserción es falsa, ya que significa una violación de las reglas
-    // de ownership y borrowing. Es posible que esto sea un probleam
-    // cuando rustc utilice el atributo noalias. La solución correcta
-    // es realizar esta verificación antes de dereferenciar ambos.
-    assert!(
-        mat.as_slice() as *const _ != vectors as *const _,
-        "attempted to insert matrix into itself"
-    );
+/// Rota `items` según `builtin_shift_list()` y retorna el rango de
+/// posiciones liberadas, el cual es vacío si `wrap` es verdadero.
+fn shift<T>(items: &mut [T], n: isize, wrap: bool) -> Range<usize> {
+    let length = items.len();
+    if length == 0 {
+        return 0..0;
+    }
+
+    let amount = if wrap {
+        n.unsigned_abs() % length
+    } else {
+        n.unsigned_abs().min(length)
+    };
+
+    let vacated = if n >= 0 {
+        items.rotate_right(amount);
+        0..amount
+    } else {
+        items.rotate_left(amount);
+        length - amount..length
+    };
+
+    if wrap {
+        0..0
+    } else {
+        vacated
+    }
+}
+
+fn transpose(mat: &mut Mat) {
+    let transposed = (0..shapec(mat))
+        .map(|column| Rc::new(mat.iter().map(|row| row[column]).collect::<List>()))
+        .collect();
 
+    *mat = transposed;
+}
+
+/// Invertir el orden de las filas conserva las filas compartidas.
+fn mirror(mat: &mut Mat, axis: Orientation) {
+    match axis {
+        Orientation::Rows => mat.reverse(),
+        Orientation::Columns => mat.iter_mut().for_each(|row| Rc::make_mut(row).reverse()),
+    }
+}
+
+/// Combina entrada por entrada dos matrices de igual forma.
+fn zip_mat(first: *mut Mat, second: *mut Mat, op: fn(bool, bool) -> bool) -> *mut Mat {
+    let (first, second) = (deref(first), deref(second));
+
+    error::shape(shapef(first), shapef(second));
+    error::shape(shapec(first), shapec(second));
+
+    let mat = first
+        .iter()
+        .zip(second.iter())
+        .map(|(first, second)| {
+            let row = first.iter().zip(second.iter());
+            Rc::new(row.map(|(&a, &b)| op(a, b)).collect::<List>())
+        })
+        .collect::<Mat>();
+
+    give(Rc::new(mat))
+}
+
+fn length_along(mat: &Mat, orientation: Orientation) -> usize {
+    match orientation {
+        Orientation::Rows => shapef(mat),
+        Orientation::Columns => shapec(mat),
+    }
+}
+
+/// Inserta las filas de `vectors` en `mat`. Ambos punteros se comparan
+/// antes de dereferenciarse, ya que un `&mut` y un `&` al mismo objeto
+/// violarían las reglas de borrowing.
+fn insert_in_mat(mat: *mut Mat, vectors: *mut Mat, mode: isize, index: usize) {
+    if mat == vectors {
+        error::fail(RuntimeError::SelfInsertion);
+    }
+
+    insert_vectors(deref_mut(mat), deref(vectors).as_slice(), mode, index);
+}
+
+fn insert_vectors(mat: &mut Mat, vectors: &[Rc<List>], mode: isize, index: usize) {
     let row_count = shapef(mat);
     let column_count = shapec(mat);
     let mut corrected_rows = false;
@@ -658,25 +1236,17 @@ fn insert_in_mat(mat: &mut Mat, vectors: &[Rc<List>], mode: isize, index: usize)
     for item in vectors.iter().rev() {
         match try_orientation(mode) {
             Orientation::Rows => {
-                assert!(
-                    row_count == 0 || column_count == item.len(),
-                    "attempted to insert row of length {} in {}x{} matrix",
-                    item.len(),
-                    row_count,
-                    column_count
-                );
+                if row_count > 0 {
+                    error::shape(column_count, item.len());
+                }
 
                 mat.insert(index, Rc::new(List::clone(item)));
             }
 
             Orientation::Columns => {
-                assert!(
-                    row_count == 0 || row_count == item.len(),
-                    "attempted to insert column of length {} in {}x{} matrix",
-                    item.len(),
-                    row_count,
-                    column_count
-                );
+                if row_count > 0 {
+                    error::shape(row_count, item.len());
+                }
 
                 if row_count == 0 && !corrected_rows {
                     (0..item.len()).for_each(|_| mat.push(Rc::new(List::new())));
@@ -684,7 +1254,7 @@ fn insert_in_mat(mat: &mut Mat, vectors: &[Rc<List>], mode: isize, index: usize)
                 }
 
                 for (row_list, entry) in mat.iter_mut().zip(item.iter().copied()) {
-                    let row_list = unsafe { Rc::get_mut_unchecked(row_list) };
+                    let row_list = Rc::make_mut(row_list);
                     row_list.insert(index, entry);
                 }
             }
@@ -696,6 +1266,6 @@ fn try_orientation(mode: isize) -> Orientation {
     match mode {
         0 => Orientation::Rows,
         1 => Orientation::Columns,
-        _ => panic!("bad matrix insertion mode: {}", mode),
+        _ => error::fail(RuntimeError::BadMatMode(mode)),
     }
 }
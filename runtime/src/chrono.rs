@@ -3,6 +3,14 @@ pub use core::time::Duration;
 #[derive(Copy, Clone, Default)]
 pub struct Ticks(usize);
 
+/// Tiempo que abarcan `ticks` ticks del reloj de la plataforma. Solo
+/// aplica a plataformas con al menos un tick por milisegundo.
+#[allow(dead_code)]
+pub fn elapsed(ticks: u64) -> Duration {
+    let per_millisecond = crate::sys::tick_count_for(Duration::from_millis(1)) as u64;
+    Duration::from_micros(ticks * 1000 / per_millisecond)
+}
+
 impl Ticks {
     pub const fn from_duration(duration: Duration) -> Self {
         Ticks(crate::sys::tick_count_for(duration))
@@ -0,0 +1,317 @@
+//! Errores en tiempo de ejecución.
+//!
+//! Los builtins validan sus argumentos en vez de confiar en los
+//! panics de `core`. Cuando una validación falla, el error se
+//! reporta junto a la línea de código fuente que lo originó y el
+//! programa se detiene de la forma que corresponda a la plataforma.
+//!
+//! # Línea actual
+//! El compilador escribe el número de línea del enunciado en ejecución
+//! en [`builtin_line`] antes de cada llamada a un builtin que puede
+//! fallar, salvo que ya lo contenga. Los builtins que nunca fallan no
+//! deben consultarla. Esta es la única variable global que el
+//! compilador accede en `libruntime`.
+
+use core::{
+    fmt::{self, Display},
+    ops::Range,
+    sync::atomic::{AtomicIsize, Ordering},
+};
+
+use crate::{
+    matrix::{MAX_LEVEL, MAX_MATRICES},
+    storage::SLOTS,
+    sys,
+};
+
+/// Línea de código fuente en ejecución, `0` si se desconoce.
+///
+/// Es escrita directamente por código emitido y no por Rust, por lo
+/// cual debe tener el tamaño de un registro de propósito general.
+/// `AtomicIsize` tiene la misma representación que `isize`.
+#[no_mangle]
+#[allow(non_upper_case_globals)]
+pub static builtin_line: AtomicIsize = AtomicIsize::new(0);
+
+/// Línea de código fuente en ejecución, ver [`builtin_line`].
+pub fn line() -> isize {
+    builtin_line.load(Ordering::Relaxed)
+}
+
+/// Reemplaza la línea en ejecución.
+pub fn set_line(line: isize) {
+    builtin_line.store(line, Ordering::Relaxed);
+}
+
+/// Un error en tiempo de ejecución.
+pub enum RuntimeError {
+    /// Se utilizó un entero negativo como índice.
+    NegativeIndex(isize),
+
+    /// Un índice excede la longitud de una lista o dimensión.
+    OutOfBounds { index: usize, length: usize },
+
+    /// Un rango `[from:to]` no está contenido en `[0, length]`.
+    BadRange {
+        from: usize,
+        to: usize,
+        length: usize,
+    },
+
+    /// Las dimensiones de dos operandos no concuerdan.
+    ShapeMismatch { expected: usize, found: usize },
+
+    /// El modo de inserción o eliminación de matrices no es `0` o `1`.
+    BadMatMode(isize),
+
+    /// Se intentó insertar una matriz dentro de sí misma.
+    SelfInsertion,
+
+    /// Coordenadas fuera de la matriz de LEDs.
+    DisplayBounds { row: isize, column: isize },
+
+    /// Dimensiones de pantalla que no corresponden a matrices completas
+    /// o que exceden `MAX_MATRICES`.
+    BadDisplaySize { width: isize, height: isize },
+
+    /// `PrintLedX("M", ...)` solo acepta el índice `0`.
+    BadDisplayIndex(isize),
+
+    /// La intensidad de `PrintLedB` está fuera de `[0, MAX_LEVEL]`.
+    BadIntensity(isize),
+
+    /// División o módulo entero entre cero, ver `--checked-arithmetic`
+    /// y `--no-hw-mul`.
+    DivisionByZero,
+
+    /// El periodo de `Every` no es positivo.
+    BadPeriod(isize),
+
+    /// La cota de `Random` no es positiva.
+    BadRandomBound(isize),
+
+    /// Un slot de `Store` o `Load` no existe.
+    BadSlot(isize),
+
+    /// La plataforma no logró persistir un valor de `Store`.
+    StorageFailure,
+
+    /// Un puntero recibido del código emitido no corresponde a una
+    /// referencia viva del tipo esperado, ver `crate::refcount`.
+    #[cfg(feature = "debug-rc")]
+    BadReference {
+        expected: &'static str,
+        found: Option<&'static str>,
+        address: usize,
+    },
+
+    /// El heap no pudo satisfacer una asignación de `size` bytes.
+    /// Solo ocurre en plataformas embebidas, ver `crate::heap`.
+    #[cfg(any(target_arch = "xtensa", target_arch = "arm", target_arch = "riscv32"))]
+    OutOfMemory {
+        size: usize,
+        used: usize,
+        peak: usize,
+        capacity: usize,
+    },
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use RuntimeError::*;
+
+        match self {
+            NegativeIndex(index) => write!(fmt, "negative index {}", index),
+
+            OutOfBounds { index, length } => write!(
+                fmt,
+                "index {} is out of bounds for length {}",
+                index, length
+            ),
+
+            BadRange { from, to, length } => write!(
+                fmt,
+                "range [{}:{}] is invalid for length {}",
+                from, to, length
+            ),
+
+            ShapeMismatch { expected, found } => {
+                write!(fmt, "expected length {}, found {}", expected, found)
+            }
+
+            BadMatMode(mode) => write!(fmt, "matrix mode must be 0 or 1, found {}", mode),
+            SelfInsertion => fmt.write_str("attempted to insert a matrix into itself"),
+
+            DisplayBounds { row, column } => write!(
+                fmt,
+                "display position [{}, {}] is out of bounds",
+                row, column
+            ),
+
+            BadDisplaySize { width, height } => write!(
+                fmt,
+                "display size {}x{} is not a grid of up to {} 8x8 matrices",
+                width, height, MAX_MATRICES
+            ),
+
+            BadDisplayIndex(index) => write!(
+                fmt,
+                "PrintLedX(\"M\", index, ...) requires index 0, found {}",
+                index
+            ),
+
+            BadIntensity(level) => write!(
+                fmt,
+                "intensity must be from 0 to {}, found {}",
+                MAX_LEVEL, level
+            ),
+
+            DivisionByZero => fmt.write_str("division by zero"),
+            BadPeriod(millis) => write!(fmt, "period must be positive, found {}ms", millis),
+            BadRandomBound(max) => write!(fmt, "random bound must be positive, found {}", max),
+
+            BadSlot(slot) => write!(
+                fmt,
+                "storage slot must be from 0 to {}, found {}",
+                SLOTS - 1,
+                slot
+            ),
+
+            StorageFailure => fmt.write_str("failed to write persistent storage"),
+
+            #[cfg(feature = "debug-rc")]
+            BadReference {
+                expected,
+                found: None,
+                address,
+            } => write!(fmt, "invalid {} reference {:#x}", expected, address),
+
+            #[cfg(feature = "debug-rc")]
+            BadReference {
+                expected,
+                found: Some(found),
+                address,
+            } => write!(
+                fmt,
+                "expected {} reference, {:#x} is a {}",
+                expected, address, found
+            ),
+
+            #[cfg(any(target_arch = "xtensa", target_arch = "arm", target_arch = "riscv32"))]
+            OutOfMemory {
+                size,
+                used,
+                peak,
+                capacity,
+            } => write!(
+                fmt,
+                "out of memory allocating {} bytes ({} of {} bytes in use, peak {})",
+                size, used, capacity, peak
+            ),
+        }
+    }
+}
+
+/// Reporta un error y detiene el programa.
+pub fn fail(error: RuntimeError) -> ! {
+    sys::fail(line(), error)
+}
+
+/// Valida un índice de acceso a un elemento existente.
+pub fn index(index: isize, length: usize) -> usize {
+    match position(index, length) {
+        index if index < length => index,
+        index => fail(RuntimeError::OutOfBounds { index, length }),
+    }
+}
+
+/// Valida una posición de inserción, la cual puede ser `length`.
+pub fn position(index: isize, length: usize) -> usize {
+    if index < 0 {
+        fail(RuntimeError::NegativeIndex(index));
+    }
+
+    let index = index as usize;
+    if index > length {
+        fail(RuntimeError::OutOfBounds { index, length });
+    }
+
+    index
+}
+
+/// Valida un rango `[from:to]`.
+pub fn range(from: isize, to: isize, length: usize) -> Range<usize> {
+    let (from, to) = (position(from, length), position(to, length));
+    if from > to {
+        fail(RuntimeError::BadRange { from, to, length });
+    }
+
+    from..to
+}
+
+/// Valida que una longitud sea la esperada.
+pub fn shape(expected: usize, found: usize) {
+    if expected != found {
+        fail(RuntimeError::ShapeMismatch { expected, found });
+    }
+}
+
+/// Valida el periodo de una tarea repetitiva, ver `crate::schedule`.
+pub fn period(millis: isize) -> isize {
+    if millis <= 0 {
+        fail(RuntimeError::BadPeriod(millis));
+    }
+
+    millis
+}
+
+/// Valida la cota superior, exclusiva, de un número aleatorio.
+pub fn random_bound(max: isize) -> u32 {
+    if max <= 0 {
+        fail(RuntimeError::BadRandomBound(max));
+    }
+
+    max as u32
+}
+
+/// Valida un slot de almacenamiento persistente.
+pub fn slot(slot: isize) -> usize {
+    if !(0..SLOTS as isize).contains(&slot) {
+        fail(RuntimeError::BadSlot(slot));
+    }
+
+    slot as usize
+}
+
+/// Valida una intensidad de la matriz de LEDs.
+pub fn intensity(level: isize) -> u8 {
+    if !(0..=MAX_LEVEL as isize).contains(&level) {
+        fail(RuntimeError::BadIntensity(level));
+    }
+
+    level as u8
+}
+
+/// Valida coordenadas de la matriz de LEDs.
+pub fn display(row: isize, column: isize) {
+    let (width, height) = sys::with_display(|display| (display.width(), display.height()));
+    if !(0..height as isize).contains(&row) || !(0..width as isize).contains(&column) {
+        fail(RuntimeError::DisplayBounds { row, column });
+    }
+}
+
+/// Valida dimensiones de pantalla, ver `Display::resize()`.
+pub fn display_size(width: isize, height: isize) -> (usize, usize) {
+    let matrices = |length: isize| match length {
+        length if length > 0 && length % 8 == 0 => Some(length as usize / 8),
+        _ => None,
+    };
+
+    match (matrices(width), matrices(height)) {
+        (Some(columns), Some(rows)) if columns * rows <= MAX_MATRICES => {
+            (width as usize, height as usize)
+        }
+
+        _ => fail(RuntimeError::BadDisplaySize { width, height }),
+    }
+}
@@ -0,0 +1,392 @@
+//! Implementación de `runtime::sys` para ESP32-C3 (RISC-V).
+//!
+//! La matriz de LEDs se conecta de la misma forma que en ESP8266,
+//! mediante dos registros de desplazamiento para filas y columnas.
+//! Al igual que allá, esta es una plataforma `#![no_std]`, por lo
+//! cual este módulo implementa punto de entrada, panic handler y
+//! heap. El núcleo carece de la extensión "A" (atómicos), por lo
+//! cual toda sincronización se realiza mediante secciones críticas.
+
+use buddy_system_allocator::Heap;
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::RefCell,
+    convert::Infallible,
+    fmt::Write,
+    ptr::{self, NonNull},
+};
+
+use critical_section::Mutex;
+
+use esp32c3_hal::{
+    clock::ClockControl,
+    ehal::digital::v2::OutputPin,
+    gpio::{Gpio4, Gpio5, Gpio6, Gpio7, IO},
+    gpio_types::{Output, PushPull},
+    interrupt::{self, Priority, TrapFrame},
+    pac::{self, Peripherals, UART0},
+    prelude::*,
+    systimer::{Alarm, Periodic, SystemTimer},
+    timer::TimerGroup,
+    Rtc, Serial,
+};
+
+use riscv_rt::entry;
+
+use crate::{
+    chrono::{self, Duration, Ticks},
+    error::RuntimeError,
+    heap::{Trackable, TrackingHeap},
+    matrix::{Display, State},
+    storage::RECORD_WORDS,
+};
+
+#[global_allocator]
+static HEAP_ALLOCATOR: TrackingHeap<CriticalHeap> =
+    TrackingHeap::new(CriticalHeap(Mutex::new(RefCell::new(Heap::empty()))));
+
+/// Tamaño del heap. El resto de la RAM queda disponible para stack.
+const HEAP_SIZE: usize = 128 * 1024;
+
+/// Frecuencia de interrupciones de SYSTIMER, en Hz (pasos de 1ms).
+///
+/// `esp32c3-hal` configura periodos en milisegundos enteros, por lo
+/// cual esta es la frecuencia máxima.
+const TICK_RATE: u32 = 1_000;
+
+extern "C" {
+    // Provisto por el linker script de riscv-rt
+    static _sheap: u8;
+}
+
+pub static SERIAL: Mutex<RefCell<Option<Serial<UART0>>>> = Mutex::new(RefCell::new(None));
+
+static HW: Mutex<RefCell<Option<Hw>>> = Mutex::new(RefCell::new(None));
+
+macro_rules! sys_debug {
+    ($($b:tt)*) => {
+        {
+            use core::fmt::Write;
+
+            critical_section::with(|cs| {
+                let mut serial = crate::esp32c3::SERIAL.borrow(cs).borrow_mut();
+                writeln!(serial.as_mut().unwrap(), $($b)*).unwrap();
+            });
+        }
+    }
+}
+
+//==================================================================================//
+//================================🄱🅄🄸🄻🅃🄸🄽🅂==================================//
+//==================================================================================//
+/// Detienen el programa por una cantidad de milisegundos.
+pub fn delay(duration: Duration) {
+    hw(|hw| hw.start_delay(Ticks::from_duration(duration)));
+
+    while !hw(Hw::delay_finished) {
+        continue;
+    }
+}
+
+pub const fn tick_count_for(duration: Duration) -> usize {
+    duration.as_millis() as usize
+}
+
+/// Tiempo transcurrido desde el arranque.
+pub fn uptime() -> Duration {
+    chrono::elapsed(hw(|hw| hw.uptime))
+}
+
+pub fn with_display<F, R>(callback: F) -> R
+where
+    F: FnOnce(&mut Display) -> R,
+{
+    hw(|hw| callback(&mut hw.states))
+}
+
+/// Reporta un error en tiempo de ejecución y detiene el programa.
+///
+/// Al igual que en ESP8266, el error se imprime una única vez por el
+/// puerto serial y luego la matriz muestra una "X" parpadeante.
+pub fn fail(line: isize, error: RuntimeError) -> ! {
+    const BLINK_TICKS: Ticks = Ticks::from_duration(Duration::from_millis(250));
+
+    sys_debug!("\r\nRuntime error at line {}: {}\r", line, error);
+
+    with_display(|display| {
+        for row in 0..8 {
+            for col in 0..8 {
+                let light = &mut display[(row, col)];
+                let on_cross = row == col || row + col == 7;
+                let interval = if on_cross {
+                    BLINK_TICKS
+                } else {
+                    Ticks::default()
+                };
+
+                light.set(State::from_bool(on_cross));
+                light.blink(interval);
+            }
+        }
+
+        // Si el programa utiliza doble búfer, la "X" quedó en el trasero
+        display.present();
+    });
+
+    loop {
+        continue;
+    }
+}
+
+/// Envía una línea por el puerto serial.
+pub fn uart_write(line: &str) {
+    sys_debug!("{}\r", line);
+}
+
+/// La recepción no está implementada en esta plataforma.
+pub fn uart_read() -> Option<u8> {
+    None
+}
+
+/// Allocator global, ver `crate::heap`.
+pub fn heap() -> &'static TrackingHeap<CriticalHeap> {
+    &HEAP_ALLOCATOR
+}
+
+/// Esta plataforma carece de almacenamiento persistente, por lo cual
+/// los slots de `crate::storage` viven únicamente en RAM.
+pub const STORAGE_SECTORS: usize = 0;
+
+pub fn read_storage(_sector: usize, _record: &mut [u32; RECORD_WORDS]) -> bool {
+    false
+}
+
+pub fn write_storage(_sector: usize, _record: &[u32; RECORD_WORDS]) -> bool {
+    false
+}
+
+//==================================================================================//
+//===========================🅂🄸🅂🅃🄴🄼🄰 🄴🄼🄿🄾🅃🅁🄰🄳🄾======================//
+//==================================================================================//
+//Descripción de sistema MCU + Periféricos
+struct Hw {
+    col_datapin: Gpio4<Output<PushPull>>,
+    col_clockpin: Gpio5<Output<PushPull>>,
+    row_datapin: Gpio6<Output<PushPull>>,
+    row_clockpin: Gpio7<Output<PushPull>>,
+    alarm: Alarm<Periodic, 0>,
+    states: Display,
+    current_state: usize,
+    timeout: Ticks,
+
+    /// Ticks transcurridos desde el arranque.
+    uptime: u64,
+    draw_clock: Ticks,
+}
+
+impl Hw {
+    const DRAW_TICKS: Ticks = Ticks::from_duration(Duration::from_millis(3));
+
+    fn tick(&mut self) {
+        self.alarm.clear_interrupt();
+        self.states.tick();
+        self.timeout.countdown();
+        self.uptime += 1;
+
+        if self.draw_clock.cycle_each(Self::DRAW_TICKS) {
+            self.draw();
+        }
+    }
+
+    fn draw(&mut self) {
+        let row_data = !(0b10000000 >> self.current_state);
+        let col_data = self.states.row_bits(self.current_state) as usize;
+
+        shift(row_data, &mut self.row_clockpin, &mut self.row_datapin);
+        shift(col_data, &mut self.col_clockpin, &mut self.col_datapin);
+
+        self.current_state += 1;
+        if self.current_state == 8 {
+            self.current_state = 0;
+        }
+    }
+
+    //======================timer functions====================
+    fn start_delay(&mut self, timeout: Ticks) {
+        self.timeout = timeout;
+    }
+
+    fn delay_finished(&mut self) -> bool {
+        self.timeout.done()
+    }
+}
+
+/// Punto de entrada para ESP32-C3.
+#[entry]
+fn main() -> ! {
+    let peripherals = Peripherals::take().unwrap();
+    let system = peripherals.SYSTEM.split();
+    let clocks = ClockControl::boot_defaults(system.clock_control).freeze();
+
+    // Los watchdogs quedan activos desde el bootloader
+    let mut rtc = Rtc::new(peripherals.RTC_CNTL);
+    let mut wdt0 = TimerGroup::new(peripherals.TIMG0, &clocks).wdt;
+    let mut wdt1 = TimerGroup::new(peripherals.TIMG1, &clocks).wdt;
+
+    rtc.swd.disable();
+    rtc.rwdt.disable();
+    wdt0.disable();
+    wdt1.disable();
+
+    // UART0 ya se encuentra configurado a 115200 baudios por el bootloader
+    let mut serial = Serial::new(peripherals.UART0);
+    writeln!(serial).unwrap();
+    critical_section::with(|cs| SERIAL.borrow(cs).replace(Some(serial)));
+
+    let io = IO::new(peripherals.GPIO, peripherals.IO_MUX);
+    let alarm = SystemTimer::new(peripherals.SYSTIMER)
+        .alarm0
+        .into_periodic();
+
+    alarm.set_period(TICK_RATE.Hz());
+    alarm.clear_interrupt();
+    alarm.enable_interrupt();
+
+    {
+        let hw = Hw {
+            col_datapin: io.pins.gpio4.into_push_pull_output(),
+            col_clockpin: io.pins.gpio5.into_push_pull_output(),
+            row_datapin: io.pins.gpio6.into_push_pull_output(),
+            row_clockpin: io.pins.gpio7.into_push_pull_output(),
+            alarm,
+            states: Default::default(),
+            current_state: 0,
+            timeout: Default::default(),
+            uptime: 0,
+            draw_clock: Default::default(),
+        };
+
+        // Esto no puede escribirse con hw() debido al unwrap
+        critical_section::with(|cs| HW.borrow(cs).replace(Some(hw)));
+    }
+
+    // HEAP allocation
+    critical_section::with(|cs| unsafe {
+        let start = &_sheap as *const u8;
+        HEAP_ALLOCATOR
+            .inner()
+            .0
+            .borrow(cs)
+            .borrow_mut()
+            .init(start as usize, HEAP_SIZE);
+    });
+
+    interrupt::enable(pac::Interrupt::SYSTIMER_TARGET0, Priority::Priority1).unwrap();
+
+    crate::handover();
+
+    // Aquí no hay un sistema operativo que se encargue de hacer algo
+    // cuando un progrma finaliza, por lo cual eso no puede pasar
+    panic!("user_main() returned")
+}
+
+fn shift<Clock, Data>(data: usize, clock_pin: &mut Clock, data_pin: &mut Data)
+where
+    Data: OutputPin<Error = Infallible>,
+    Clock: OutputPin<Error = Infallible>,
+{
+    digital_write(clock_pin, 0);
+
+    for i in 0..9 {
+        //escribe un bit adicional para limpiar
+        digital_write(clock_pin, 1);
+        digital_write(data_pin, (data >> i) & 1);
+        digital_write(clock_pin, 0);
+    }
+}
+
+fn digital_write<Pin>(pin: &mut Pin, value: usize)
+where
+    Pin: OutputPin<Error = Infallible>,
+{
+    if value != 0 {
+        pin.set_high().unwrap();
+    } else {
+        pin.set_low().unwrap();
+    }
+}
+
+// No se utiliza #[interrupt] ya que este espera encontrar el módulo
+// `interrupt` en la raíz del crate, lo cual solo ocurre en binarios
+#[export_name = "SYSTIMER_TARGET0"]
+extern "C" fn systimer_target0(_frame: &mut TrapFrame) {
+    maybe_hw(Hw::tick);
+}
+
+/// Algo salió mal.
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    let mut x = 0;
+    loop {
+        x += 1;
+        if x > 100_000_000 {
+            x = 0;
+            sys_debug!(
+                "\r\n-----------Panic cause---------- \n{}\r\n-----This message repeats-----\n",
+                info
+            );
+        }
+    }
+}
+
+/// Heap protegido por secciones críticas.
+///
+/// `LockedHeap` depende de `spin`, que requiere operaciones atómicas
+/// de las que este núcleo carece.
+struct CriticalHeap(Mutex<RefCell<Heap<32>>>);
+
+unsafe impl GlobalAlloc for CriticalHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        critical_section::with(|cs| {
+            self.0
+                .borrow(cs)
+                .borrow_mut()
+                .alloc(layout)
+                .map_or(ptr::null_mut(), NonNull::as_ptr)
+        })
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        critical_section::with(|cs| {
+            let ptr = NonNull::new_unchecked(ptr);
+            self.0.borrow(cs).borrow_mut().dealloc(ptr, layout)
+        })
+    }
+}
+
+unsafe impl Trackable for CriticalHeap {
+    fn exclusive<F, R>(&self, callback: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        critical_section::with(|_| callback())
+    }
+
+    fn capacity(&self) -> usize {
+        critical_section::with(|cs| self.0.borrow(cs).borrow().stats_total_bytes())
+    }
+}
+
+fn hw<F, R>(callback: F) -> R
+where
+    F: FnOnce(&mut Hw) -> R,
+{
+    critical_section::with(|cs| callback(HW.borrow(cs).borrow_mut().as_mut().unwrap()))
+}
+
+fn maybe_hw<F, R>(callback: F) -> Option<R>
+where
+    F: FnOnce(&mut Hw) -> R,
+{
+    critical_section::with(|cs| HW.borrow(cs).borrow_mut().as_mut().map(callback))
+}
@@ -1,5 +1,9 @@
-// THIS FILE IS GENERATED; PLEASE DO NOT CHANGE!
-#![allow(non_snake_case)]
+//! Operaciones atómicas para lx106.
+//!
+//! El núcleo carece de instrucciones atómicas, por lo cual `core`
+//! emite llamadas a estos símbolos. Cada operación se realiza con
+//! interrupciones suspendidas, lo cual basta en un núcleo único. Todas
+//! siguen la convención de llamada de C, ya que así las invoca LLVM.
 
 use xtensa_lx::interrupt::free;
 
@@ -16,10 +20,10 @@ unsafe extern "C" fn __sync_val_compare_and_swap_1(ptr: *mut i8, old: i8, new: i
 }
 
 #[no_mangle]
-unsafe fn __sync_fetch_and_add_4(ptr: *mut i32, arg: i32) -> i32 {
+unsafe extern "C" fn __sync_fetch_and_add_4(ptr: *mut i32, arg: i32) -> i32 {
     free(|_| {
         let last = *ptr;
-        *ptr += arg;
+        *ptr = last.wrapping_add(arg);
         last
     })
 }
@@ -1,9 +1,55 @@
 //! Implementación de `runtime::sys` para Espressif ESP8266.
 //!
-//! De momento, la ausencia de atómicos funcionales en `rust-xtensa`
-//! obliga a utilizar más `unsafe` de lo ideal. Como esta es una
-//! plataforma `#![no_std]`, este módulo debe implementar un punto
-//! de entrada específico a la plataforma y un panic handler.
+//! Como esta es una plataforma `#![no_std]`, este módulo debe
+//! implementar un punto de entrada específico a la plataforma y un
+//! panic handler.
+//!
+//! # Concurrencia
+//! El lx106 carece de instrucciones atómicas, ver [`atomic`]. Todo
+//! estado compartido con interrupciones vive en un `CriticalSectionMutex`,
+//! al igual que el estado global de los builtins (ver `crate::global`),
+//! por lo que no existe `static mut` alguno en el runtime. El acceso al
+//! hardware se realiza únicamente por medio de [`with_hw()`] y
+//! [`try_with_hw()`], los cuales suspenden interrupciones mientras se
+//! ejecuta el callback. Por esto los callbacks deben ser breves, y no
+//! pueden volver a solicitar el mismo recurso.
+//!
+//! # Matriz
+//! La matriz se multiplexa por filas desde la interrupción del
+//! temporizador. La frecuencia de refresco y el brillo se definen
+//! al compilar mediante `ANIMLED_SCAN_RATE` (Hz, 125 por defecto) y
+//! `ANIMLED_BRIGHTNESS` (porcentaje, 100 por defecto). Distinguir
+//! todas las intensidades de `PrintLedB` requiere al menos `MAX_LEVEL`
+//! ticks encendidos por fila, por ejemplo con `ANIMLED_SCAN_RATE=78`.
+//!
+//! Con varias matrices encadenadas (ver `Display::resize()`), los
+//! registros de filas y de columnas forman cada uno una cadena: cada
+//! fila de matrices comparte un byte de la cadena de filas y cada
+//! columna de matrices uno de la cadena de columnas. El barrido
+//! recorre todas las filas de la pantalla, por lo cual el tiempo
+//! asignado a cada una disminuye con la cantidad de filas de matrices.
+//!
+//! # Almacenamiento persistente
+//! Los sectores de `crate::storage` son sectores de la flash a partir
+//! de `STORAGE_BASE`, lejos de la imagen del programa. Se accede a ellos
+//! mediante las rutinas de la ROM, durante lo cual la caché de la flash
+//! permanece deshabilitada. Por esto las interrupciones se suspenden y
+//! la matriz se congela por unos milisegundos en cada escritura.
+//!
+//! # Puerto serial
+//! UART0 se comparte entre `sys_debug!` y `crate::uart`. Cada byte
+//! recibido dispara una interrupción que lo encola en `RX`, de donde
+//! `uart_read()` lo extrae sin bloquear.
+//!
+//! # Fallas
+//! Tanto un error en tiempo de ejecución como un panic se reportan una
+//! única vez por el puerto serial y luego se muestran en la matriz: una
+//! "X" parpadeante para el primero y un "!" parpadeante para el segundo.
+//! El watchdog de hardware se alimenta durante los delays y mientras se
+//! muestra la falla, por lo cual solo reinicia la tarjeta si el programa
+//! queda atascado. Tras un panic, la tarjeta se reinicia por software
+//! luego de `ANIMLED_PANIC_RESET` segundos, o nunca si este es `0` (el
+//! valor por defecto).
 
 use buddy_system_allocator::LockedHeap;
 use core::convert::Infallible;
@@ -14,29 +60,100 @@ use esp8266_hal::{
     gpio::{self, Output, PushPull},
     interrupt::*,
     prelude::*,
-    target::{Peripherals, DPORT, TIMER},
+    target::{Peripherals, DPORT, TIMER, UART0},
     uart::{UART0Ext, UART0Serial},
 };
 
 use crate::{
-    chrono::{Duration, Ticks},
-    matrix::Display,
+    chrono::{self, Duration, Ticks},
+    error::RuntimeError,
+    heap::{Trackable, TrackingHeap},
+    matrix::{Display, State, MAX_LEVEL, MAX_MATRICES},
+    storage::RECORD_WORDS,
+    uart::RingBuffer,
 };
 
 mod atomic;
 
+/// Parámetros de la matriz y de fallas definidos al compilar, ver `build.rs`.
+mod config {
+    include!(concat!(env!("OUT_DIR"), "/display.rs"));
+}
+
 #[global_allocator]
-static HEAP_ALLOCATOR: LockedHeap<32> = LockedHeap::empty();
+static HEAP_ALLOCATOR: TrackingHeap<LockedHeap<32>> = TrackingHeap::new(LockedHeap::empty());
+
+/// Frecuencia del temporizador FRC1, igual a la del CPU.
+const CPU_FREQ: u32 = 80_000_000;
+
+/// Frecuencia de interrupciones del temporizador, en Hz (pasos de 100us).
+const TICK_RATE: u32 = 10_000;
+
+/// Ticks asignados a cada fila durante el barrido de una pantalla de
+/// `rows` filas, al menos uno.
+const fn row_ticks(rows: usize) -> u32 {
+    match TICK_RATE / (rows as u32 * config::SCAN_RATE) {
+        0 => 1,
+        ticks => ticks,
+    }
+}
+
+/// Ticks durante los cuales cada fila permanece encendida, al menos uno.
+const fn on_ticks(row_ticks: u32) -> u32 {
+    match row_ticks * config::BRIGHTNESS / 100 {
+        0 => 1,
+        ticks => ticks,
+    }
+}
+
+/// Desplazamiento en flash del primer sector de almacenamiento, tras
+/// el espacio que `memory.x` reserva para el programa.
+const STORAGE_BASE: u32 = 0x20_0000;
+
+/// Interrupción de UART0 por FIFO de recepción con datos (`UART_RXFIFO_FULL_INT`).
+const UART_RXFIFO_FULL: u32 = 1 << 0;
+
+/// Registro de alimentación del watchdog de hardware (`WDT_RST`).
+const WDT_FEED: *mut u32 = 0x6000_0914 as *mut u32;
+
+/// Valor que alimenta al watchdog al escribirse en `WDT_FEED`.
+const WDT_FEED_MAGIC: u32 = 0x73;
+
+/// Registro de control del RTC, cuyo bit `RTC_SW_RESET` reinicia el chip.
+const RTC_CTRL: *mut u32 = 0x6000_0700 as *mut u32;
+
+const RTC_SW_RESET: u32 = 1 << 20;
+
+/// "X" que indica un error en tiempo de ejecución, una fila por byte.
+const ERROR_GLYPH: [u8; 8] = [0x81, 0x42, 0x24, 0x18, 0x18, 0x24, 0x42, 0x81];
+
+/// "!" que indica un panic.
+const PANIC_GLYPH: [u8; 8] = [0x18, 0x18, 0x18, 0x18, 0x18, 0x00, 0x18, 0x00];
+
+/// Tamaño de un sector borrable de la flash.
+const FLASH_SECTOR_SIZE: u32 = 0x1000;
 
 extern "C" {
     static _heap_start: u8;
     static _heap_end: u8;
+
+    // Rutinas de la ROM, ver memory.x
+    fn SPIRead(address: u32, buffer: *mut u32, length: u32) -> i32;
+    fn SPIWrite(address: u32, data: *const u32, length: u32) -> i32;
+    fn SPIEraseSector(sector: u32) -> i32;
+    fn Cache_Read_Disable();
+    fn Cache_Read_Enable(odd_even: u32, mb_count: u32, unknown: u32);
 }
 
 pub static SERIAL: CriticalSectionMutex<Option<UART0Serial>> = CriticalSectionMutex::new(None);
 
 static HW: CriticalSectionMutex<Option<Hw>> = CriticalSectionMutex::new(None);
 
+static RX: CriticalSectionMutex<RingBuffer> = CriticalSectionMutex::new(RingBuffer::new());
+
+/// Evita reportar más de una vez un panic, incluso uno recursivo.
+static PANICKED: CriticalSectionMutex<bool> = CriticalSectionMutex::new(false);
+
 macro_rules! sys_debug {
     ($($b:tt)*) => {
         {
@@ -55,10 +172,10 @@ macro_rules! sys_debug {
 //==================================================================================//
 /// Detienen el programa por una cantidad de milisegundos.
 pub fn delay(duration: Duration) {
-    hw(|hw| hw.start_delay(Ticks::from_duration(duration)));
+    with_hw(|hw| hw.start_delay(Ticks::from_duration(duration)));
 
-    while !hw(Hw::delay_finished) {
-        continue;
+    while !with_hw(Hw::delay_finished) {
+        feed_watchdog();
     }
 }
 
@@ -66,11 +183,149 @@ pub const fn tick_count_for(duration: Duration) -> usize {
     duration.as_millis() as usize * 10
 }
 
+/// Tiempo transcurrido desde el arranque.
+pub fn uptime() -> Duration {
+    chrono::elapsed(with_hw(|hw| hw.uptime))
+}
+
 pub fn with_display<F, R>(callback: F) -> R
 where
     F: FnOnce(&mut Display) -> R,
 {
-    hw(|hw| callback(&mut hw.states))
+    with_hw(|hw| callback(&mut hw.states))
+}
+
+/// Reporta un error en tiempo de ejecución y detiene el programa.
+///
+/// El error se imprime una única vez por el puerto serial. Después de
+/// ello la matriz muestra una "X" parpadeante de forma indefinida, de
+/// manera que el error sea visible aun sin un monitor serial.
+pub fn fail(line: isize, error: RuntimeError) -> ! {
+    sys_debug!("\r\nRuntime error at line {}: {}\r", line, error);
+    show_glyph(&ERROR_GLYPH);
+
+    loop {
+        feed_watchdog();
+    }
+}
+
+/// Muestra un glifo parpadeante en la esquina superior izquierda y
+/// apaga el resto de la pantalla. No hace nada si el hardware aún no
+/// ha sido inicializado.
+fn show_glyph(glyph: &[u8; 8]) {
+    const BLINK_TICKS: Ticks = Ticks::from_duration(Duration::from_millis(250));
+
+    try_with_hw(|hw| {
+        let display = &mut hw.states;
+
+        for row in 0..8 {
+            for col in 0..8 {
+                let light = &mut display[(row, col)];
+                let on_glyph = glyph[row] & (0x80 >> col) != 0;
+                let interval = if on_glyph {
+                    BLINK_TICKS
+                } else {
+                    Ticks::default()
+                };
+
+                light.set(State::from_bool(on_glyph));
+                light.blink(interval);
+            }
+        }
+
+        // Si el programa utiliza doble búfer, el glifo quedó en el trasero
+        display.present();
+    });
+}
+
+/// Reinicia el contador del watchdog de hardware.
+fn feed_watchdog() {
+    unsafe { WDT_FEED.write_volatile(WDT_FEED_MAGIC) };
+}
+
+/// Espera una cantidad de ciclos del CPU alimentando al watchdog. No
+/// depende de interrupciones, las cuales pueden estar suspendidas.
+fn spin(cycles: u32) {
+    let start = xtensa_lx::timer::get_cycle_count();
+    while xtensa_lx::timer::get_cycle_count().wrapping_sub(start) < cycles {
+        feed_watchdog();
+    }
+}
+
+/// Reinicia el chip como si se tratara de un reset externo.
+fn soft_reset() -> ! {
+    unsafe { RTC_CTRL.write_volatile(RTC_CTRL.read_volatile() | RTC_SW_RESET) };
+
+    loop {
+        continue;
+    }
+}
+
+/// Envía una línea por el puerto serial.
+pub fn uart_write(line: &str) {
+    use core::fmt::Write;
+
+    (&SERIAL).lock(|serial| {
+        let _ = write!(serial.as_mut().unwrap(), "{}\r\n", line);
+    });
+}
+
+/// Extrae el siguiente byte recibido, si lo hay.
+pub fn uart_read() -> Option<u8> {
+    (&RX).lock(RingBuffer::pop)
+}
+
+/// Allocator global, ver `crate::heap`.
+pub fn heap() -> &'static TrackingHeap<LockedHeap<32>> {
+    &HEAP_ALLOCATOR
+}
+
+/// Sectores de almacenamiento persistente, ver `crate::storage`.
+pub const STORAGE_SECTORS: usize = 4;
+
+/// Lee un sector, retornando `false` si no fue posible.
+pub fn read_storage(sector: usize, record: &mut [u32; RECORD_WORDS]) -> bool {
+    let address = STORAGE_BASE + sector as u32 * FLASH_SECTOR_SIZE;
+    let length = (RECORD_WORDS * 4) as u32;
+
+    xtensa_lx::interrupt::free(|_| unsafe { flash_read(address, record.as_mut_ptr(), length) })
+}
+
+/// Borra y sobreescribe un sector, retornando `false` si no fue posible.
+pub fn write_storage(sector: usize, record: &[u32; RECORD_WORDS]) -> bool {
+    let address = STORAGE_BASE + sector as u32 * FLASH_SECTOR_SIZE;
+    let length = (RECORD_WORDS * 4) as u32;
+
+    xtensa_lx::interrupt::free(|_| unsafe { flash_write(address, record.as_ptr(), length) })
+}
+
+/// Se ubica en IRAM, ya que la flash no es legible mientras la ROM
+/// opera sobre esta. Asume interrupciones suspendidas.
+#[link_section = ".rwtext"]
+#[inline(never)]
+unsafe fn flash_read(address: u32, buffer: *mut u32, length: u32) -> bool {
+    Cache_Read_Disable();
+    let result = SPIRead(address, buffer, length);
+    Cache_Read_Enable(0, 0, 1);
+
+    result == 0
+}
+
+/// Igual que `flash_read()`, borrando el sector antes de escribir.
+#[link_section = ".rwtext"]
+#[inline(never)]
+unsafe fn flash_write(address: u32, data: *const u32, length: u32) -> bool {
+    Cache_Read_Disable();
+
+    let mut result = SPIEraseSector(address / FLASH_SECTOR_SIZE);
+    if result == 0 {
+        result = SPIWrite(address, data, length);
+    }
+
+    // Se restaura el mapeo del primer MiB, el mismo del bootloader
+    Cache_Read_Enable(0, 0, 1);
+
+    result == 0
 }
 
 //==================================================================================//
@@ -89,34 +344,85 @@ struct Hw {
     states: Display,
     current_state: usize,
     timeout: Ticks,
-    draw_clock: Ticks,
+
+    /// Ticks transcurridos desde el arranque.
+    uptime: u64,
+    scan_tick: u32,
+    col_data: [u8; MAX_MATRICES],
 }
 
 impl Hw {
-    const DRAW_TICKS: Ticks = Ticks::from_duration(Duration::from_millis(3));
-
     fn tick(&mut self) {
         self.states.tick();
         self.timeout.countdown();
+        self.uptime += 1;
+        self.scan();
+    }
 
-        if self.draw_clock.cycle_each(Self::DRAW_TICKS) {
-            self.draw();
+    /// Multiplexa la matriz, una fila a la vez.
+    ///
+    /// Cada fila ocupa `ROW_TICKS` ticks, de los cuales permanece
+    /// encendida durante `ON_TICKS`. Durante el resto se apagan las
+    /// columnas, lo cual reduce el brillo sin alterar la frecuencia
+    /// de refresco. Dentro del tiempo encendido, cada luz se apaga
+    /// antes según su intensidad (PWM por software), por lo cual la
+    /// intensidad se distingue en a lo sumo `ON_TICKS` pasos.
+    fn scan(&mut self) {
+        let rows = self.states.height();
+        let matrix_columns = self.states.width() / 8;
+
+        let row_ticks = row_ticks(rows);
+        let on_ticks = on_ticks(row_ticks);
+
+        // Un cambio de dimensiones puede dejar la fila actual fuera
+        if self.current_state >= rows {
+            self.current_state = 0;
+            self.scan_tick = 0;
         }
-    }
 
-    fn draw(&mut self) {
-        let row_data = !(0b10000000 >> self.current_state);
-        let col_data = self.states.row_bits(self.current_state) as usize;
+        if self.scan_tick == 0 {
+            let mut row_data = [0xff; MAX_MATRICES];
+            row_data[self.current_state / 8] = !(0b10000000 >> self.current_state % 8);
 
-        shift(row_data, &mut self.row_clockpin, &mut self.row_datapin);
-        shift(col_data, &mut self.col_clockpin, &mut self.col_datapin);
+            let row_data = &row_data[..rows / 8];
+            shift(row_data, &mut self.row_clockpin, &mut self.row_datapin);
+        }
 
-        self.current_state += 1;
-        if self.current_state == 8 {
-            self.current_state = 0;
+        let mut col_data = [0; MAX_MATRICES];
+        if self.scan_tick < on_ticks {
+            for (matrix, bits) in col_data.iter_mut().take(matrix_columns).enumerate() {
+                *bits = self.pwm_columns(matrix, on_ticks);
+            }
+        }
+
+        if self.scan_tick == 0 || col_data != self.col_data {
+            let data = &col_data[..matrix_columns];
+            shift(data, &mut self.col_clockpin, &mut self.col_datapin);
+            self.col_data = col_data;
+        }
+
+        self.scan_tick += 1;
+        if self.scan_tick >= row_ticks {
+            self.scan_tick = 0;
+
+            self.current_state += 1;
+            if self.current_state == rows {
+                self.current_state = 0;
+            }
         }
     }
 
+    /// Columnas de una matriz en la fila actual que permanecen
+    /// encendidas en este tick.
+    fn pwm_columns(&self, matrix: usize, on_ticks: u32) -> u8 {
+        (0..8).fold(0, |acc, col| {
+            let level = self.states.level(self.current_state, matrix * 8 + col) as u32;
+            let lit = self.scan_tick * (MAX_LEVEL as u32) < level * on_ticks;
+
+            acc << 1 | lit as u8
+        })
+    }
+
     //======================timer functions====================
     fn start_delay(&mut self, timeout: Ticks) {
         self.timeout = timeout;
@@ -139,6 +445,15 @@ fn main() -> ! {
 
     (&SERIAL).lock(|x| *x = Some(serial));
 
+    // Umbral de recepción (bits 0-6 de UART_CONF1) de un byte, ver uart()
+    let uart = unsafe { &*UART0::ptr() };
+    uart.uart_conf1
+        .modify(|r, w| unsafe { w.bits((r.bits() & !0x7f) | 1) });
+    uart.uart_int_clr.write(|w| unsafe { w.bits(!0) });
+    uart.uart_int_ena
+        .write(|w| unsafe { w.bits(UART_RXFIFO_FULL) });
+    enable_interrupt(InterruptType::UART);
+
     {
         let hw = Hw {
             //d1: gpio.gpio5.into_push_pull_output(),
@@ -152,10 +467,12 @@ fn main() -> ! {
             states: Default::default(),
             current_state: 0,
             timeout: Default::default(),
-            draw_clock: Default::default(),
+            uptime: 0,
+            scan_tick: 0,
+            col_data: [0; MAX_MATRICES],
         };
 
-        // Esto no puede escribirse con hw() debido al unwrap
+        // Esto no puede escribirse con with_hw() debido al unwrap
         (&HW).lock(|hardware| *hardware = Some(hw));
     }
 
@@ -165,6 +482,7 @@ fn main() -> ! {
         let end = &_heap_end as *const u8;
 
         HEAP_ALLOCATOR
+            .inner()
             .lock()
             .init(start as usize, end.offset_from(start) as usize);
     }
@@ -187,10 +505,12 @@ fn main() -> ! {
         .edge_int_enable
         .modify(|_, w| w.timer1_edge_int_enable().set_bit());
 
-    timer.frc1_load.write(|w| unsafe { w.bits(8000) }); //pasos de 100us
+    timer
+        .frc1_load
+        .write(|w| unsafe { w.bits(CPU_FREQ / TICK_RATE) });
     enable_interrupt(InterruptType::TIMER1);
 
-    hw(|hw| {
+    with_hw(|hw| {
         hw.d4.set_high().unwrap();
         hw.d7.set_low().unwrap();
     });
@@ -202,17 +522,24 @@ fn main() -> ! {
     panic!("user_main() returned")
 }
 
-fn shift<Clock, Data>(data: usize, clock_pin: &mut Clock, data_pin: &mut Data)
+/// Desplaza un byte por cada registro de una cadena. El primer byte
+/// corresponde al registro más cercano, por lo cual se envía de último.
+fn shift<Clock, Data>(data: &[u8], clock_pin: &mut Clock, data_pin: &mut Data)
 where
     Data: OutputPin<Error = Infallible>,
     Clock: OutputPin<Error = Infallible>,
 {
     digital_write(clock_pin, 0);
 
-    for i in 0..9 {
-        //escribe un bit adicional para limpiar
+    let bits = data
+        .iter()
+        .rev()
+        .flat_map(|&byte| (0..8).map(move |i| (byte as usize >> i) & 1));
+
+    //escribe un bit adicional para limpiar
+    for bit in bits.chain(core::iter::once(0)) {
         digital_write(clock_pin, 1);
-        digital_write(data_pin, (data >> i) & 1);
+        digital_write(data_pin, bit);
         digital_write(clock_pin, 0);
     }
 }
@@ -230,33 +557,84 @@ where
 
 #[interrupt]
 fn timer1() {
-    maybe_hw(Hw::tick);
+    try_with_hw(Hw::tick);
+}
+
+/// Vacía la FIFO de recepción de UART0 hacia `RX`.
+#[interrupt]
+fn uart() {
+    let uart = unsafe { &*UART0::ptr() };
+
+    (&RX).lock(|rx| {
+        // Los bits 0-7 de UART_STATUS indican la cantidad de bytes en la FIFO
+        while uart.uart_status.read().bits() & 0xff != 0 {
+            rx.push(uart.uart_fifo.read().bits() as u8);
+        }
+    });
+
+    uart.uart_int_clr
+        .write(|w| unsafe { w.bits(UART_RXFIFO_FULL) });
 }
 
 /// Algo salió mal.
+///
+/// Ver la sección de fallas en la documentación de este módulo.
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
-    let mut x = 0;
+    let first = (&PANICKED).lock(|panicked| !core::mem::replace(panicked, true));
+
+    if first {
+        use core::fmt::Write;
+
+        // A diferencia de sys_debug!, esto no falla antes de que main()
+        // inicialice el puerto serial
+        (&SERIAL).lock(|serial| {
+            if let Some(serial) = serial.as_mut() {
+                let _ = write!(serial, "\r\nPanic: {}\r\n", info);
+            }
+        });
+
+        show_glyph(&PANIC_GLYPH);
+    }
+
+    let mut seconds = 0;
     loop {
-        x += 1;
-        if x > 100_000_000 {
-            x = 0;
-            sys_debug!(
-                "\r\n-----------Panic cause---------- \n{}\r\n-----This message repeats-----\n",
-                info
-            );
+        spin(CPU_FREQ);
+
+        seconds += 1;
+        if config::PANIC_RESET > 0 && seconds >= config::PANIC_RESET {
+            soft_reset();
         }
     }
 }
 
-fn hw<F, R>(callback: F) -> R
+// Suspender interrupciones basta para la exclusión, pues hay un único núcleo
+unsafe impl Trackable for LockedHeap<32> {
+    fn exclusive<F, R>(&self, callback: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        xtensa_lx::interrupt::free(|_| callback())
+    }
+
+    fn capacity(&self) -> usize {
+        self.lock().stats_total_bytes()
+    }
+}
+
+/// Accede al hardware desde el flujo principal del programa.
+///
+/// Falla si se invoca antes de que `main()` inicialice el hardware.
+fn with_hw<F, R>(callback: F) -> R
 where
     F: FnOnce(&mut Hw) -> R,
 {
     (&HW).lock(|hw| callback(hw.as_mut().unwrap()))
 }
 
-fn maybe_hw<F, R>(callback: F) -> Option<R>
+/// Igual que [`with_hw()`], pero no falla si el hardware aún no ha
+/// sido inicializado, lo cual es posible desde una interrupción.
+fn try_with_hw<F, R>(callback: F) -> Option<R>
 where
     F: FnOnce(&mut Hw) -> R,
 {
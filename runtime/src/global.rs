@@ -0,0 +1,72 @@
+//! Estado global del runtime.
+//!
+//! Los builtins que recuerdan algo entre llamadas (la semilla de
+//! `builtin_random()`, los slots persistentes, el planificador, etc.)
+//! lo guardan en un [`Global`]. Cada plataforma lo protege con el
+//! mismo mecanismo que ya utiliza para su propio estado: un `Mutex`
+//! de `std` en plataformas hosted y WebAssembly, y una sección crítica
+//! en microcontroladores, donde el estado podría compartirse con
+//! interrupciones.
+//!
+//! Los callbacks deben ser breves, ya que en microcontroladores se
+//! ejecutan con interrupciones suspendidas, y no pueden volver a
+//! solicitar el mismo [`Global`].
+
+#[cfg(any(target_arch = "arm", target_arch = "riscv32"))]
+use core::cell::RefCell;
+
+/// Un valor global protegido contra accesos concurrentes.
+pub struct Global<T>(Inner<T>);
+
+#[cfg(any(target_family = "unix", target_arch = "wasm32"))]
+type Inner<T> = std::sync::Mutex<T>;
+
+#[cfg(target_arch = "xtensa")]
+type Inner<T> = xtensa_lx::mutex::CriticalSectionMutex<T>;
+
+#[cfg(target_arch = "arm")]
+type Inner<T> = cortex_m::interrupt::Mutex<RefCell<T>>;
+
+#[cfg(target_arch = "riscv32")]
+type Inner<T> = critical_section::Mutex<RefCell<T>>;
+
+impl<T> Global<T> {
+    #[cfg(any(target_family = "unix", target_arch = "wasm32", target_arch = "xtensa"))]
+    pub const fn new(value: T) -> Self {
+        Global(Inner::new(value))
+    }
+
+    #[cfg(any(target_arch = "arm", target_arch = "riscv32"))]
+    pub const fn new(value: T) -> Self {
+        Global(Inner::new(RefCell::new(value)))
+    }
+
+    /// Presta el valor de forma exclusiva durante `callback`.
+    pub fn with<F, R>(&self, callback: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        #[cfg(any(target_family = "unix", target_arch = "wasm32"))]
+        {
+            // Un panic en un test previo no invalida el estado
+            let mut guard = self.0.lock().unwrap_or_else(|error| error.into_inner());
+            callback(&mut guard)
+        }
+
+        #[cfg(target_arch = "xtensa")]
+        {
+            use xtensa_lx::mutex::Mutex;
+            (&self.0).lock(callback)
+        }
+
+        #[cfg(target_arch = "arm")]
+        {
+            cortex_m::interrupt::free(|cs| callback(&mut self.0.borrow(cs).borrow_mut()))
+        }
+
+        #[cfg(target_arch = "riscv32")]
+        {
+            critical_section::with(|cs| callback(&mut self.0.borrow(cs).borrow_mut()))
+        }
+    }
+}
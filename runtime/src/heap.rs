@@ -0,0 +1,188 @@
+//! Contabilidad del heap.
+//!
+//! Cada plataforma instala como allocator global un [`TrackingHeap`],
+//! el cual lleva cuenta de los bytes en uso y del máximo alcanzado.
+//! `HeapUsed()` y `HeapFree()` exponen estos valores a los programas.
+//!
+//! En plataformas embebidas el heap tiene una capacidad fija. Una
+//! asignación que no cabe detiene el programa con un error que incluye
+//! el tamaño solicitado y las estadísticas del heap, en vez del aborto
+//! sin información que `alloc` realiza por defecto. En las demás
+//! plataformas la capacidad se considera ilimitada.
+//!
+//! Antes de recurrir al allocator de la plataforma, algunos objetos se
+//! toman de una [`Arena`], ver `crate::arena`.
+
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::UnsafeCell,
+};
+
+use crate::{arena::Arena, sys};
+
+#[cfg(any(target_arch = "xtensa", target_arch = "arm", target_arch = "riscv32"))]
+use crate::error::{self, RuntimeError};
+
+/// Estado del heap en un instante dado.
+#[derive(Copy, Clone)]
+pub struct Usage {
+    /// Bytes entregados y aún no liberados.
+    pub used: usize,
+
+    /// Máximo histórico de `used`, el cual solo se reporta al agotar
+    /// un heap de capacidad fija.
+    #[cfg(any(target_arch = "xtensa", target_arch = "arm", target_arch = "riscv32"))]
+    pub peak: usize,
+
+    /// Bytes totales del heap.
+    pub capacity: usize,
+}
+
+impl Usage {
+    /// Bytes disponibles. Por fragmentación, no se garantiza que una
+    /// asignación de este tamaño sea exitosa.
+    pub fn free(&self) -> usize {
+        self.capacity.saturating_sub(self.used)
+    }
+}
+
+/// Un allocator cuyo uso puede contabilizarse.
+///
+/// # Safety
+/// Mientras se ejecuta el callback de [`Trackable::exclusive()`] no
+/// puede ocurrir ninguna otra asignación o liberación, ya sea desde
+/// otro hilo o desde una interrupción.
+pub unsafe trait Trackable: GlobalAlloc {
+    /// Ejecuta `callback` con acceso exclusivo a los contadores y a la arena.
+    fn exclusive<F, R>(&self, callback: F) -> R
+    where
+        F: FnOnce() -> R;
+
+    /// Bytes totales, `usize::MAX` si la capacidad es ilimitada.
+    fn capacity(&self) -> usize;
+}
+
+/// Envuelve a un allocator para llevar cuenta de su uso.
+pub struct TrackingHeap<A> {
+    inner: A,
+    counters: UnsafeCell<Counters>,
+    arena: UnsafeCell<Arena>,
+}
+
+#[derive(Copy, Clone)]
+struct Counters {
+    used: usize,
+    peak: usize,
+}
+
+// Los contadores y la arena solo se acceden dentro de `Trackable::exclusive()`
+unsafe impl<A: Sync> Sync for TrackingHeap<A> {}
+
+impl<A: Trackable> TrackingHeap<A> {
+    pub const fn new(inner: A) -> Self {
+        TrackingHeap {
+            inner,
+            counters: UnsafeCell::new(Counters { used: 0, peak: 0 }),
+            arena: UnsafeCell::new(Arena::new()),
+        }
+    }
+
+    /// Allocator envuelto, por ejemplo para inicializar un heap embebido.
+    #[cfg(any(target_arch = "xtensa", target_arch = "arm", target_arch = "riscv32"))]
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    pub fn usage(&self) -> Usage {
+        let counters = self.inner.exclusive(|| unsafe { *self.counters.get() });
+
+        Usage {
+            used: counters.used,
+            #[cfg(any(target_arch = "xtensa", target_arch = "arm", target_arch = "riscv32"))]
+            peak: counters.peak,
+            capacity: self.inner.capacity(),
+        }
+    }
+
+    /// Ejecuta `callback` de forma que los objetos que este cree puedan
+    /// residir en la arena.
+    pub fn in_arena<F, R>(&self, callback: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let request = |requested| {
+            self.inner
+                .exclusive(|| unsafe { (*self.arena.get()).request(requested) })
+        };
+
+        request(true);
+        let result = callback();
+        request(false);
+
+        result
+    }
+
+    /// Finaliza un cuadro, ver `Arena::frame_end()`.
+    pub fn frame_end(&self) {
+        self.inner
+            .exclusive(|| unsafe { (*self.arena.get()).frame_end() });
+    }
+}
+
+unsafe impl<A: Trackable> GlobalAlloc for TrackingHeap<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.inner.exclusive(|| {
+            let ptr = (*self.arena.get()).alloc(layout);
+            if !ptr.is_null() {
+                return ptr;
+            }
+
+            let ptr = self.inner.alloc(layout);
+            if !ptr.is_null() {
+                let counters = &mut *self.counters.get();
+                counters.used += layout.size();
+                counters.peak = counters.peak.max(counters.used);
+            }
+
+            ptr
+        })
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.exclusive(|| {
+            let arena = &mut *self.arena.get();
+            if arena.owns(ptr) {
+                arena.dealloc();
+                return;
+            }
+
+            self.inner.dealloc(ptr, layout);
+            (*self.counters.get()).used -= layout.size();
+        })
+    }
+}
+
+/// Bytes en uso.
+pub fn used() -> usize {
+    sys::heap().usage().used
+}
+
+/// Bytes disponibles, ver [`Usage::free()`].
+pub fn free() -> usize {
+    sys::heap().usage().free()
+}
+
+/// Reporta una asignación fallida. Para este punto la asignación ya
+/// concluyó, por lo cual es posible consultar el estado del heap.
+#[cfg(any(target_arch = "xtensa", target_arch = "arm", target_arch = "riscv32"))]
+#[alloc_error_handler]
+fn out_of_memory(layout: Layout) -> ! {
+    let usage = sys::heap().usage();
+
+    error::fail(RuntimeError::OutOfMemory {
+        size: layout.size(),
+        used: usage.used,
+        peak: usage.peak,
+        capacity: usage.capacity,
+    })
+}
@@ -0,0 +1,390 @@
+//! Implementación de `runtime::sys` cuando se dispone de
+//! un sistema operativo huésped.
+//!
+//! Naturalmente, esto es mucho más sencillo y trivial que
+//! implementar las mismas operaciones para plataformas
+//! embebidas y `#![no_std]`.
+//!
+//! En tiempo real, la matriz se muestra en la terminal, ver [`terminal`].
+//! Su estado también puede transmitirse a otros procesos, ver [`socket`].
+//!
+//! # Reloj virtual
+//! Si la variable de entorno `ANIMATIONLED_TRACE` indica una ruta,
+//! el programa no se ejecuta en tiempo real. En su lugar, cada
+//! `delay()` avanza un reloj virtual y actualiza la matriz de forma
+//! síncrona, sin dormir ni dibujar en la terminal. Cada vez que el
+//! contenido de la matriz cambia se agrega una línea a esa ruta con
+//! el tiempo virtual en milisegundos y las ocho filas en hexadecimal,
+//! donde el bit más significativo es la columna 0:
+//!
+//! ```text
+//! 1500 8142241818244281
+//! ```
+//!
+//! Si además se define `ANIMATIONLED_STOP_AT`, el programa termina
+//! exitosamente una vez que el reloj virtual alcanza esa cantidad
+//! de milisegundos. Esto permite evaluar animaciones que no terminan.
+//!
+//! Con `ANIMATIONLED_TRACE_LEVELS` también se registran intensidades.
+//! Cada línea incluye entonces un tercer campo con 64 dígitos
+//! hexadecimales, uno por LED fila por fila, y los cambios de
+//! intensidad producen nuevas líneas aunque las filas no cambien:
+//!
+//! ```text
+//! 1500 8000000000000000 f000000000000000000000000000000000000000000000000000000000000000
+//! ```
+//!
+//! # Almacenamiento persistente
+//! Los sectores de `crate::storage` se guardan uno tras otro en el
+//! archivo `.animationled-storage` del directorio actual, o bien en la
+//! ruta que indique `ANIMATIONLED_STORAGE`. Un archivo ausente o corto
+//! equivale a sectores nunca escritos.
+//!
+//! # Puerto serial
+//! Lo que `crate::uart` envía se imprime como cualquier otra línea,
+//! mientras que lo recibido proviene de la entrada estándar. Esta se
+//! lee desde un hilo aparte a partir de la primera lectura.
+
+use lazy_static::lazy_static;
+
+use std::{
+    alloc::System,
+    env,
+    fmt::Write as _,
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write as _},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::Instant,
+};
+
+use crate::{
+    chrono::Duration,
+    error::RuntimeError,
+    heap::{Trackable, TrackingHeap},
+    matrix::Display,
+    storage::RECORD_WORDS,
+    uart::RingBuffer,
+};
+
+mod socket;
+mod terminal;
+
+/// Imprime un mensaje de depuración.
+macro_rules! sys_debug {
+    ($($b:tt)*) => {
+        $crate::sys::debug(&format!($($b)*))
+    }
+}
+
+#[global_allocator]
+static HEAP_ALLOCATOR: TrackingHeap<System> = TrackingHeap::new(System);
+
+/// Excluye asignaciones concurrentes entre hilos, ver `Trackable`.
+///
+/// No puede ser un `Mutex`, ya que este podría asignar memoria.
+static ALLOCATING: AtomicBool = AtomicBool::new(false);
+
+/// Prepara el entorno antes de iniciar el programa.
+pub fn start() {
+    lazy_static::initialize(&START);
+    socket::initialize();
+}
+
+/// Detiene el programa durante una cantidad de tiempo.
+pub fn delay(duration: Duration) {
+    match &*TRACE {
+        Some(trace) => trace.lock().unwrap().advance(duration),
+        None => std::thread::sleep(duration),
+    }
+}
+
+pub const fn tick_count_for(duration: Duration) -> usize {
+    duration.as_millis() as usize / 10
+}
+
+/// Tiempo transcurrido desde el arranque, o bien el reloj virtual.
+pub fn uptime() -> Duration {
+    match &*TRACE {
+        Some(trace) => trace.lock().unwrap().now,
+        None => START.elapsed(),
+    }
+}
+
+pub fn with_display<F, R>(callback: F) -> R
+where
+    F: FnOnce(&mut Display) -> R,
+{
+    // TRACE se adquiere antes que DISPLAY, al igual que en Trace::advance()
+    let trace = TRACE.as_ref().map(|trace| trace.lock().unwrap());
+
+    let mut display = lock();
+    let result = callback(&mut display);
+
+    match trace {
+        Some(mut trace) => {
+            trace.record(&display);
+            socket::publish(&display, Some(trace.now));
+        }
+
+        None => {
+            terminal::lock().update(&display);
+            socket::publish(&display, None);
+        }
+    }
+
+    result
+}
+
+/// Reporta un error en tiempo de ejecución y termina el proceso.
+pub fn fail(line: isize, error: RuntimeError) -> ! {
+    // Se retiene la terminal para que la matriz no se dibuje encima
+    let _terminal = terminal::lock();
+
+    eprintln!("Runtime error at line {}: {}", line, error);
+    std::process::exit(1)
+}
+
+pub fn debug(message: &str) {
+    terminal::lock().print(message);
+}
+
+/// Envía una línea por el puerto serial.
+pub fn uart_write(line: &str) {
+    terminal::lock().print(line);
+}
+
+/// Extrae el siguiente byte recibido, si lo hay.
+pub fn uart_read() -> Option<u8> {
+    lazy_static! {
+        static ref STDIN_THREAD: () = {
+            std::thread::spawn(stdin_main);
+        };
+    }
+
+    lazy_static::initialize(&STDIN_THREAD);
+    RX.lock().unwrap().pop()
+}
+
+/// Allocator global, ver `crate::heap`.
+pub fn heap() -> &'static TrackingHeap<System> {
+    &HEAP_ALLOCATOR
+}
+
+/// Sectores de almacenamiento persistente, ver `crate::storage`.
+pub const STORAGE_SECTORS: usize = 4;
+
+/// Lee un sector, retornando `false` si no fue posible.
+pub fn read_storage(sector: usize, record: &mut [u32; RECORD_WORDS]) -> bool {
+    let mut bytes = [0; RECORD_WORDS * 4];
+    let read = File::open(storage_path()).and_then(|mut file| {
+        file.seek(SeekFrom::Start(storage_offset(sector)))?;
+        file.read_exact(&mut bytes)
+    });
+
+    for (word, bytes) in record.iter_mut().zip(bytes.chunks_exact(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+
+    read.is_ok()
+}
+
+/// Sobreescribe un sector, retornando `false` si no fue posible.
+pub fn write_storage(sector: usize, record: &[u32; RECORD_WORDS]) -> bool {
+    let mut bytes = Vec::with_capacity(RECORD_WORDS * 4);
+    for word in record.iter() {
+        bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .open(storage_path())
+        .and_then(|mut file| {
+            file.seek(SeekFrom::Start(storage_offset(sector)))?;
+            file.write_all(&bytes)?;
+            file.sync_data()
+        })
+        .is_ok()
+}
+
+fn storage_path() -> PathBuf {
+    env::var_os("ANIMATIONLED_STORAGE")
+        .unwrap_or_else(|| ".animationled-storage".into())
+        .into()
+}
+
+fn storage_offset(sector: usize) -> u64 {
+    (sector * RECORD_WORDS * 4) as u64
+}
+
+lazy_static! {
+    static ref DISPLAY: Mutex<Display> = Mutex::new(Display::default());
+    static ref TRACE: Option<Mutex<Trace>> = Trace::from_env().map(Mutex::new);
+    static ref RX: Mutex<RingBuffer> = Mutex::new(RingBuffer::new());
+    static ref START: Instant = Instant::now();
+}
+
+/// Estado del reloj virtual.
+struct Trace {
+    output: File,
+    now: Duration,
+    ticks: u32,
+    stop_at: Option<Duration>,
+    levels: bool,
+    last_frame: Option<([u8; 8], [u8; 64])>,
+}
+
+impl Trace {
+    /// Cada tick del reloj equivale a 10ms, ver `tick_count_for()`.
+    const TICK: Duration = Duration::from_millis(10);
+
+    fn from_env() -> Option<Self> {
+        let path = env::var_os("ANIMATIONLED_TRACE")?;
+        let output = File::create(&path).expect("Failed to create trace file");
+
+        let stop_at = env::var("ANIMATIONLED_STOP_AT").ok().map(|millis| {
+            let millis = millis.parse().expect("Bad value for ANIMATIONLED_STOP_AT");
+            Duration::from_millis(millis)
+        });
+
+        let mut trace = Trace {
+            output,
+            now: Duration::default(),
+            ticks: 0,
+            stop_at,
+            levels: env::var_os("ANIMATIONLED_TRACE_LEVELS").is_some(),
+            last_frame: None,
+        };
+
+        // La matriz inicia apagada
+        trace.record(&Display::default());
+        Some(trace)
+    }
+
+    /// Avanza el reloj virtual, tick por tick.
+    ///
+    /// Los ticks ocurren en múltiplos exactos de `TICK` sin importar
+    /// la duración de cada `delay()`, al igual que con el hilo de reloj.
+    fn advance(&mut self, duration: Duration) {
+        let target = self.now + duration;
+
+        while Self::TICK * (self.ticks + 1) <= target {
+            self.ticks += 1;
+            self.now = Self::TICK * self.ticks;
+            self.check_stop();
+
+            let mut display = lock();
+            display.tick();
+            self.record(&display);
+            socket::publish(&display, Some(self.now));
+        }
+
+        self.now = target;
+        self.check_stop();
+    }
+
+    /// Agrega el contenido actual de la matriz a la traza si cambió.
+    fn record(&mut self, display: &Display) {
+        let mut frame = [0; 8];
+        for (row, bits) in frame.iter_mut().enumerate() {
+            *bits = display.row_bits(row);
+        }
+
+        // Sin intensidades en la traza, sus cambios no se consideran
+        let mut levels = [0; 64];
+        if self.levels {
+            for (index, level) in levels.iter_mut().enumerate() {
+                *level = display.level(index / 8, index % 8);
+            }
+        }
+
+        if self.last_frame != Some((frame, levels)) {
+            let mut line = format!("{} ", self.now.as_millis());
+
+            for bits in frame.iter() {
+                write!(&mut line, "{:02x}", bits).unwrap();
+            }
+
+            if self.levels {
+                line.push(' ');
+                for level in levels.iter() {
+                    write!(&mut line, "{:x}", level).unwrap();
+                }
+            }
+
+            writeln!(self.output, "{}", line).expect("Failed to write trace");
+            self.last_frame = Some((frame, levels));
+        }
+    }
+
+    fn check_stop(&self) {
+        if matches!(self.stop_at, Some(stop_at) if self.now >= stop_at) {
+            std::process::exit(0);
+        }
+    }
+}
+
+fn lock() -> MutexGuard<'static, Display> {
+    lazy_static! {
+        static ref CLOCK_THREAD: () = {
+            std::thread::spawn(clock_main);
+        };
+    }
+
+    lazy_static::initialize(&DISPLAY);
+
+    // Con reloj virtual la matriz se actualiza en delay()
+    if TRACE.is_none() {
+        lazy_static::initialize(&CLOCK_THREAD);
+    }
+
+    DISPLAY.lock().unwrap()
+}
+
+fn clock_main() {
+    loop {
+        delay(Duration::from_millis(10));
+
+        let mut display = DISPLAY.lock().unwrap();
+        display.tick();
+
+        terminal::lock().update(&display);
+        socket::publish(&display, None);
+    }
+}
+
+fn stdin_main() {
+    let mut stdin = io::stdin();
+    let mut byte = [0];
+
+    while let Ok(1) = stdin.read(&mut byte) {
+        RX.lock().unwrap().push(byte[0]);
+    }
+}
+
+unsafe impl Trackable for System {
+    fn exclusive<F, R>(&self, callback: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        while ALLOCATING
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            std::hint::spin_loop();
+        }
+
+        let result = callback();
+        ALLOCATING.store(false, Ordering::Release);
+
+        result
+    }
+
+    fn capacity(&self) -> usize {
+        usize::MAX
+    }
+}
@@ -0,0 +1,138 @@
+//! Transmisión del estado de la matriz por TCP.
+//!
+//! Si la variable de entorno `ANIMLED_DISPLAY_SOCKET` indica una
+//! dirección, por ejemplo `127.0.0.1:7070`, el programa escucha
+//! conexiones en ella. Cada cliente recibe una línea JSON cada vez que
+//! cambia el contenido de la matriz o el conjunto de luces que
+//! parpadean, comenzando por el estado actual al conectarse:
+//!
+//! ```text
+//! {"millis":1500,"rows":[129, 66, 36, 24, 24, 36, 66, 129],"blink":[0, 0, 0, 0, 0, 0, 0, 0]}
+//! ```
+//!
+//! Cada fila es una máscara de bits donde el bit más significativo es
+//! la columna 0. `blink` tiene la misma forma e indica cuáles luces
+//! parpadean; cada alternancia se reporta como un cambio de `rows`.
+//! `millis` es el tiempo desde el inicio del programa, o el tiempo
+//! virtual si `ANIMATIONLED_TRACE` está presente.
+//!
+//! Cada cliente se atiende desde su propio hilo, por lo cual un cliente
+//! lento no detiene al programa. Los cuadros pendientes se pierden
+//! cuando el programa termina.
+
+use lazy_static::lazy_static;
+
+use std::{
+    env,
+    io::Write as _,
+    net::{TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use crate::{chrono::Duration, matrix::Display};
+
+lazy_static! {
+    static ref SOCKET: Option<Mutex<Socket>> = Socket::from_env().map(Mutex::new);
+}
+
+/// Inicia el servidor, si así se solicitó.
+pub fn initialize() {
+    lazy_static::initialize(&SOCKET);
+}
+
+/// Envía el estado de la matriz a los clientes si cambió.
+///
+/// Sin reloj virtual, `now` se omite y se utiliza el tiempo real.
+pub fn publish(display: &Display, now: Option<Duration>) {
+    if let Some(socket) = &*SOCKET {
+        let mut socket = socket.lock().unwrap();
+        let now = now.unwrap_or_else(|| socket.start.elapsed());
+
+        socket.update(display, now);
+    }
+}
+
+/// Estado del servidor.
+struct Socket {
+    start: Instant,
+    clients: Vec<Sender<String>>,
+
+    /// Filas y parpadeos del último mensaje.
+    frame: Option<([u8; 8], [u8; 8])>,
+    message: String,
+}
+
+impl Socket {
+    fn from_env() -> Option<Self> {
+        let address = env::var("ANIMLED_DISPLAY_SOCKET").ok()?;
+        let listener = TcpListener::bind(&address).expect("Failed to bind display socket");
+
+        thread::spawn(move || accept_main(listener));
+
+        let mut socket = Socket {
+            start: Instant::now(),
+            clients: Vec::new(),
+            frame: None,
+            message: String::new(),
+        };
+
+        // La matriz inicia apagada
+        socket.update(&Display::default(), Duration::default());
+        Some(socket)
+    }
+
+    fn update(&mut self, display: &Display, now: Duration) {
+        let mut rows = [0; 8];
+        let mut blink = [0; 8];
+
+        for (row, (bits, blinking)) in rows.iter_mut().zip(blink.iter_mut()).enumerate() {
+            *bits = display.row_bits(row);
+            *blinking = display.blink_bits(row);
+        }
+
+        if self.frame != Some((rows, blink)) {
+            self.message = format!(
+                r#"{{"millis":{},"rows":{:?},"blink":{:?}}}"#,
+                now.as_millis(),
+                rows,
+                blink
+            );
+
+            self.frame = Some((rows, blink));
+
+            let message = &self.message;
+            self.clients
+                .retain(|client| client.send(message.clone()).is_ok());
+        }
+    }
+}
+
+fn accept_main(listener: TcpListener) {
+    for stream in listener.incoming().flatten() {
+        let (sender, receiver) = mpsc::channel();
+
+        // Este hilo se crea al inicializar SOCKET, por lo cual no es None
+        let mut socket = SOCKET.as_ref().unwrap().lock().unwrap();
+        if sender.send(socket.message.clone()).is_ok() {
+            socket.clients.push(sender);
+        }
+
+        drop(socket);
+
+        thread::spawn(move || client_main(stream, receiver));
+    }
+}
+
+/// Escribe mensajes a un cliente hasta que este se desconecte.
+fn client_main(mut stream: TcpStream, messages: Receiver<String>) {
+    for message in messages {
+        if writeln!(stream, "{}", message).is_err() {
+            break;
+        }
+    }
+}
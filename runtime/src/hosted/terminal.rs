@@ -0,0 +1,131 @@
+//! Simulador de la matriz en terminal.
+//!
+//! Si la salida estándar es una terminal, la matriz se dibuja con
+//! secuencias de escape ANSI al final de todo lo que el programa ha
+//! impreso. Cada mensaje de depuración borra la matriz, se imprime y
+//! vuelve a dibujarla, por lo cual la matriz permanece siempre debajo
+//! de la salida del programa. Si la salida estándar se redirige, por
+//! ejemplo a un archivo, no se dibuja nada.
+//!
+//! La matriz se redibuja solamente cuando su contenido cambia. Esto se
+//! revisa tras cada tick del reloj, de forma que los parpadeos se
+//! observan con la misma resolución que `blink()`. Las intensidades
+//! de `PrintLedB` se aproximan con tonos de rojo de la paleta de 256
+//! colores.
+
+use ansi_escapes::{CursorUp, EraseDown, EraseEndLine};
+use lazy_static::lazy_static;
+
+use std::{
+    fmt::Write as _,
+    io::{self, Write as _},
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::matrix::{Display, MAX_LEVEL};
+
+/// Intensidad de cada luz, ver `Display::level()`.
+type Frame = [[u8; 8]; 8];
+
+/// Líneas que ocupa la matriz dibujada, incluyendo el borde.
+const HEIGHT: u16 = 10;
+
+/// Tonos de rojo en orden creciente de intensidad.
+const SHADES: [u8; 5] = [52, 88, 124, 160, 196];
+
+const LIGHT_OFF: &str = "○";
+
+lazy_static! {
+    static ref TERMINAL: Mutex<Terminal> = Mutex::new(Terminal {
+        enabled: atty::is(atty::Stream::Stdout),
+        frame: None,
+    });
+}
+
+/// Estado de la salida estándar.
+pub struct Terminal {
+    enabled: bool,
+
+    /// Último cuadro dibujado, ausente si la matriz aún no está en pantalla.
+    frame: Option<Frame>,
+}
+
+/// Obtiene acceso exclusivo a la terminal.
+///
+/// Si también se requiere la matriz, esta debe bloquearse primero.
+pub fn lock() -> MutexGuard<'static, Terminal> {
+    TERMINAL.lock().unwrap()
+}
+
+impl Terminal {
+    /// Dibuja la matriz si su contenido cambió.
+    pub fn update(&mut self, display: &Display) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut frame = [[0; 8]; 8];
+        for (row, levels) in frame.iter_mut().enumerate() {
+            for (col, level) in levels.iter_mut().enumerate() {
+                *level = display.level(row, col);
+            }
+        }
+
+        if self.frame != Some(frame) {
+            let mut output = String::new();
+            if self.frame.is_some() {
+                write!(&mut output, "{}", CursorUp(HEIGHT)).unwrap();
+            }
+
+            render(&mut output, &frame);
+            self.frame = Some(frame);
+
+            flush(&output);
+        }
+    }
+
+    /// Imprime una línea por encima de la matriz.
+    pub fn print(&mut self, message: &str) {
+        match self.frame {
+            Some(frame) if self.enabled => {
+                let mut output = format!("{}{}", CursorUp(HEIGHT), EraseDown);
+                writeln!(&mut output, "{}", message).unwrap();
+                render(&mut output, &frame);
+
+                flush(&output);
+            }
+
+            _ => println!("{}", message),
+        }
+    }
+}
+
+/// Dibuja un cuadro. El cursor queda al inicio de la línea siguiente.
+fn render(output: &mut String, frame: &Frame) {
+    writeln!(output, "┌─────────────────┐{}", EraseEndLine).unwrap();
+
+    for levels in frame.iter() {
+        output.push_str("│ ");
+
+        for &level in levels.iter() {
+            if level == 0 {
+                output.push_str(LIGHT_OFF);
+            } else {
+                let shade = (level as usize * SHADES.len() - 1) / MAX_LEVEL as usize;
+                write!(output, "\x1b[38;5;{}m●\x1b[0m", SHADES[shade]).unwrap();
+            }
+
+            output.push(' ');
+        }
+
+        writeln!(output, "│{}", EraseEndLine).unwrap();
+    }
+
+    writeln!(output, "└─────────────────┘{}", EraseEndLine).unwrap();
+}
+
+fn flush(output: &str) {
+    let mut stdout = io::stdout();
+    stdout.write_all(output.as_bytes()).unwrap();
+    stdout.flush().unwrap();
+}
@@ -35,8 +35,11 @@
 //! prueba. Ello requiere definir `#[no_mangle] extern "C" fn user_main() {}`
 //! e invocar a [`handover()`].
 
-#![feature(get_mut_unchecked)]
-#![cfg_attr(target_arch = "xtensa", no_std, feature(default_alloc_error_handler))]
+#![cfg_attr(
+    any(target_arch = "xtensa", target_arch = "arm", target_arch = "riscv32"),
+    no_std,
+    feature(alloc_error_handler)
+)]
 
 extern crate alloc;
 
@@ -51,28 +54,72 @@ mod hosted;
 #[macro_use]
 mod esp8266;
 
+#[cfg(target_arch = "arm")]
+#[macro_use]
+mod stm32;
+
+#[cfg(target_arch = "riscv32")]
+#[macro_use]
+mod esp32c3;
+
+#[cfg(target_arch = "wasm32")]
+#[macro_use]
+mod web;
+
 #[cfg(target_family = "unix")]
 use crate::hosted as sys;
 
 #[cfg(target_arch = "xtensa")]
 use crate::esp8266 as sys;
 
+#[cfg(target_arch = "arm")]
+use crate::stm32 as sys;
+
+#[cfg(target_arch = "riscv32")]
+use crate::esp32c3 as sys;
+
+#[cfg(target_arch = "wasm32")]
+use crate::web as sys;
+
 pub mod builtin;
 
+mod arena;
 mod chrono;
+mod error;
+mod font;
+mod global;
+mod heap;
 mod matrix;
+mod random;
+
+#[cfg(feature = "debug-rc")]
+mod refcount;
+
+mod schedule;
+mod storage;
+mod uart;
 
 /// Transfiere control al programa.
 ///
 /// Esta función es el mecanismo seguro para iniciar el programa que enlazó
-/// contra `libruntime`.
+/// contra `libruntime`. En WebAssembly el entorno JavaScript invoca
+/// directamente a `user_main()`, por lo cual esta función no existe.
+#[cfg(not(target_arch = "wasm32"))]
 #[no_mangle]
 pub fn handover() {
     extern "C" {
         fn user_main();
     }
 
+    #[cfg(target_family = "unix")]
+    sys::start();
+
     unsafe {
         user_main();
     }
+
+    schedule::run();
+
+    #[cfg(all(feature = "debug-rc", target_family = "unix"))]
+    refcount::report_leaks();
 }
@@ -1,54 +1,225 @@
-use crate::chrono::Ticks;
-use core::ops::{Index, IndexMut, Not};
+use crate::{chrono::Ticks, font};
+use alloc::vec::Vec;
+use core::{
+    iter,
+    ops::{Index, IndexMut, Not},
+};
 
-#[derive(Default)]
-pub struct Display([[Light; 8]; 8]);
+/// Cantidad máxima de matrices de 8x8 encadenadas, ver `Display::resize()`.
+pub const MAX_MATRICES: usize = 4;
+
+/// Luces de todas las matrices, fila por fila.
+type Frame = [Light; 64 * MAX_MATRICES];
+
+/// Intensidad máxima de una luz, ver `Light::set_level()`.
+pub const MAX_LEVEL: u8 = 15;
+
+/// Estado de la matriz.
+///
+/// Inicialmente toda escritura es visible de inmediato. A partir del
+/// primer `present()` la matriz utiliza doble búfer: las escrituras
+/// ocurren sobre un búfer trasero y cada `present()` lo copia al búfer
+/// frontal, que es el que se muestra. Esto evita que se observen cuadros
+/// a medio dibujar. Ambos búferes avanzan con cada tick, por lo cual los
+/// parpadeos conservan su fase al presentar.
+///
+/// # Matrices encadenadas
+/// La pantalla puede estar compuesta por varias matrices de 8x8
+/// dispuestas en una cuadrícula, ver `resize()`. Las coordenadas son
+/// globales a toda la pantalla. Las plataformas que solo manejan una
+/// matriz muestran la de la esquina superior izquierda.
+pub struct Display {
+    front: Frame,
+    back: Option<Frame>,
+    width: usize,
+    height: usize,
+}
 
 impl Display {
+    /// Ancho de la pantalla en luces.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Alto de la pantalla en luces.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Cambia las dimensiones de la pantalla, en luces, y la apaga por
+    /// completo. Ambas dimensiones deben ser múltiplos de 8 y en total
+    /// no pueden haber más de `MAX_MATRICES` matrices, ver
+    /// `error::display_size()`.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+
+        self.front = [Light::default(); 64 * MAX_MATRICES];
+        if let Some(back) = &mut self.back {
+            *back = self.front;
+        }
+    }
+
+    /// Obtiene los bits de una fila de la matriz de 8x8 en la columna de
+    /// matrices `matrix`, donde el más significativo es su columna 0.
     #[allow(dead_code)]
-    pub fn rows(&self) -> &[[Light; 8]; 8] {
-        &self.0
+    pub fn matrix_row_bits(&self, matrix: usize, row: usize) -> u8 {
+        (0..8).fold(0, |acc, col| {
+            let light = &self.front[self.offset(row, matrix * 8 + col)];
+            acc << 1 | (light.state == State::On) as u8
+        })
     }
 
+    /// Como `matrix_row_bits()`, para la primera matriz.
     #[allow(dead_code)]
     pub fn row_bits(&self, row: usize) -> u8 {
-        self.0[row]
-            .iter()
-            .fold(0, |acc, light| acc << 1 | (light.state == State::On) as u8)
+        self.matrix_row_bits(0, row)
+    }
+
+    /// Intensidad visible de una luz, `0` si está apagada.
+    #[allow(dead_code)]
+    pub fn level(&self, row: usize, col: usize) -> u8 {
+        let light = &self.front[self.offset(row, col)];
+        match light.state {
+            State::On => light.level,
+            State::Off => 0,
+        }
+    }
+
+    /// Como `row_bits()`, pero indica cuáles luces parpadean.
+    #[allow(dead_code)]
+    pub fn blink_bits(&self, row: usize) -> u8 {
+        (0..8).fold(0, |acc, col| {
+            let light = &self.front[self.offset(row, col)];
+            acc << 1 | !light.interval.done() as u8
+        })
+    }
+
+    /// Muestra el búfer trasero, o bien activa el doble búfer.
+    pub fn present(&mut self) {
+        match &self.back {
+            Some(back) => self.front = *back,
+            None => self.back = Some(self.front),
+        }
+    }
+
+    /// Reemplaza el contenido de la pantalla por columnas de bits, una
+    /// por cada columna de la pantalla, donde el bit menos significativo
+    /// es la fila 0. Las filas a partir de la octava se apagan. Las luces
+    /// afectadas dejan de parpadear. Con doble búfer, el cuadro se
+    /// presenta de inmediato.
+    pub fn draw_columns(&mut self, columns: &[u8]) {
+        let (width, height) = (self.width, self.height);
+        for (col, bits) in columns.iter().take(width).enumerate() {
+            for row in 0..height {
+                let mut light = Light::default();
+                light.set(State::from_bool(row < 8 && bits & 1 << row != 0));
+
+                let offset = self.offset(row, col);
+                self.drawing_mut()[offset] = light;
+            }
+        }
+
+        if self.back.is_some() {
+            self.present();
+        }
+    }
+
+    /// Reemplaza el contenido de la primera matriz por ocho filas de bits,
+    /// donde el bit más significativo es la columna 0, como en
+    /// [`row_bits()`]. El resto de la pantalla se apaga. Por lo demás,
+    /// se comporta igual que [`draw_columns()`].
+    ///
+    /// [`row_bits()`]: Self::row_bits
+    /// [`draw_columns()`]: Self::draw_columns
+    pub fn draw_rows(&mut self, rows: &[u8]) {
+        let (width, height) = (self.width, self.height);
+        for row in 0..height {
+            let bits = rows.get(row).copied().filter(|_| row < 8).unwrap_or(0);
+
+            for col in 0..width {
+                let mut light = Light::default();
+                light.set(State::from_bool(col < 8 && bits & 0x80 >> col != 0));
+
+                let offset = self.offset(row, col);
+                self.drawing_mut()[offset] = light;
+            }
+        }
+
+        if self.back.is_some() {
+            self.present();
+        }
     }
 
     pub fn tick(&mut self) {
-        for row in self.0.iter_mut() {
-            for light in row.iter_mut() {
-                if light.clock.cycle_each(light.interval) {
-                    light.state = !light.state;
-                }
+        let frames = iter::once(&mut self.front).chain(self.back.as_mut());
+
+        for light in frames.flatten() {
+            if light.clock.cycle_each(light.interval) {
+                light.state = !light.state;
             }
         }
     }
+
+    fn drawing(&self) -> &Frame {
+        self.back.as_ref().unwrap_or(&self.front)
+    }
+
+    fn drawing_mut(&mut self) -> &mut Frame {
+        self.back.as_mut().unwrap_or(&mut self.front)
+    }
+
+    fn offset(&self, row: usize, col: usize) -> usize {
+        row * self.width + col
+    }
+
+    fn check_indices(&self, row: isize, col: isize) {
+        assert!(
+            (0..self.height as isize).contains(&row) && (0..self.width as isize).contains(&col),
+            "Display matrix index [{}, {}] is out of bounds",
+            row,
+            col
+        );
+    }
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Display {
+            front: [Light::default(); 64 * MAX_MATRICES],
+            back: None,
+            width: 8,
+            height: 8,
+        }
+    }
 }
 
 impl Index<(isize, isize)> for Display {
     type Output = Light;
 
     fn index(&self, (row, col): (isize, isize)) -> &Self::Output {
-        check_indices(row, col);
-        &self.0[row as usize][col as usize]
+        self.check_indices(row, col);
+        &self.drawing()[self.offset(row as usize, col as usize)]
     }
 }
 
 impl IndexMut<(isize, isize)> for Display {
     fn index_mut(&mut self, (row, col): (isize, isize)) -> &mut Self::Output {
-        check_indices(row, col);
-        &mut self.0[row as usize][col as usize]
+        self.check_indices(row, col);
+
+        let offset = self.offset(row as usize, col as usize);
+        &mut self.drawing_mut()[offset]
     }
 }
 
-#[derive(Default)]
+#[derive(Copy, Clone)]
 pub struct Light {
     state: State,
     clock: Ticks,
     interval: Ticks,
+
+    /// Intensidad mientras la luz está encendida, incluso al parpadear.
+    level: u8,
 }
 
 impl Light {
@@ -57,8 +228,22 @@ impl Light {
         self.state
     }
 
+    /// Enciende o apaga la luz. Al encenderse lo hace a intensidad máxima.
     pub fn set(&mut self, state: State) {
         self.state = state;
+        self.level = MAX_LEVEL;
+    }
+
+    /// Enciende la luz con una intensidad de `1` a `MAX_LEVEL`, o la
+    /// apaga si la intensidad es `0`.
+    pub fn set_level(&mut self, level: u8) {
+        match level {
+            0 => self.set(State::Off),
+            level => {
+                self.state = State::On;
+                self.level = level;
+            }
+        }
     }
 
     pub fn blink(&mut self, interval: Ticks) {
@@ -67,6 +252,17 @@ impl Light {
     }
 }
 
+impl Default for Light {
+    fn default() -> Self {
+        Light {
+            state: State::Off,
+            clock: Ticks::default(),
+            interval: Ticks::default(),
+            level: MAX_LEVEL,
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum State {
     On,
@@ -100,12 +296,12 @@ impl Not for State {
     }
 }
 
-fn check_indices(row: isize, col: isize) {
-    let valid = 0..8;
-    assert!(
-        valid.contains(&row) && valid.contains(&col),
-        "Display matrix index [{}, {}] is out of bounds",
-        row,
-        col
-    );
+/// Columnas que recorre un texto desplazable con `draw_columns()`.
+///
+/// Cada ventana de `width` columnas es un cuadro. El texto entra por
+/// la derecha y el último cuadro, ya sin texto, deja la matriz apagada.
+pub fn scroll_columns(text: &str, width: usize) -> Vec<u8> {
+    let blank = || iter::repeat(0).take(width);
+
+    blank().chain(font::columns(text)).chain(blank()).collect()
 }
@@ -0,0 +1,48 @@
+//! Números pseudoaleatorios.
+//!
+//! Se utiliza xorshift de 32 bits, el cual no requiere más que unas
+//! cuantas operaciones sobre enteros y cuyo estado cabe en un registro.
+//! Su calidad es más que suficiente para efectos visuales, pero no para
+//! criptografía.
+//!
+//! Este módulo no depende del resto del crate, ya que el intérprete del
+//! compilador también lo incluye.
+
+/// Semilla inicial, la cual además reemplaza a una semilla nula.
+const DEFAULT_SEED: u32 = 0x2545_f491;
+
+/// Estado del generador, nunca nulo.
+pub struct Xorshift(u32);
+
+impl Xorshift {
+    /// Un generador a partir de una semilla arbitraria.
+    pub const fn new(seed: u32) -> Self {
+        // Con estado nulo, xorshift produce ceros indefinidamente
+        Xorshift(if seed == 0 { DEFAULT_SEED } else { seed })
+    }
+
+    /// Siguiente valor de la secuencia.
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+
+        self.0 = x;
+        x
+    }
+
+    /// Un valor en `[0, max)`. `max` no puede ser cero.
+    ///
+    /// Se escala en vez de tomar el residuo, lo cual evita favorecer
+    /// a los valores bajos cuando `max` no es una potencia de dos.
+    pub fn below(&mut self, max: u32) -> u32 {
+        ((u64::from(self.next_u32()) * u64::from(max)) >> 32) as u32
+    }
+}
+
+impl Default for Xorshift {
+    fn default() -> Self {
+        Xorshift::new(DEFAULT_SEED)
+    }
+}
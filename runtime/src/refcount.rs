@@ -0,0 +1,99 @@
+//! Depuración de conteo de referencias.
+//!
+//! Con la feature `debug-rc`, el runtime registra cada referencia a una
+//! lista, matriz o string que posee el código emitido, junto con la
+//! línea en que se creó el objeto. Un `builtin_ref_*` agrega una
+//! referencia y un `builtin_drop_*` la elimina. Las referencias que
+//! mantiene el propio runtime, como las filas dentro de una matriz, no
+//! se registran.
+//!
+//! Todo puntero que el código emitido entrega a un builtin debe
+//! corresponder a una referencia registrada del tipo esperado. De lo
+//! contrario, el programa se detiene con [`RuntimeError::BadReference`]
+//! en vez de corromper el heap. Esto detecta tanto liberaciones dobles
+//! como usos tras liberar.
+//!
+//! En plataformas hosted, las referencias que sobreviven al final del
+//! programa se reportan como fugas, ver [`report_leaks()`].
+
+use alloc::collections::BTreeMap;
+
+use crate::{
+    error::{self, RuntimeError},
+    global::Global,
+};
+
+struct Entry {
+    kind: &'static str,
+    refs: usize,
+    line: isize,
+}
+
+/// Se indexa por dirección del objeto.
+static LIVE: Global<BTreeMap<usize, Entry>> = Global::new(BTreeMap::new());
+
+/// Registra una referencia recién entregada al código emitido.
+pub fn acquire(address: usize, kind: &'static str) {
+    let line = error::line();
+
+    with_live(|live| {
+        live.entry(address)
+            .or_insert(Entry {
+                kind,
+                refs: 0,
+                line,
+            })
+            .refs += 1;
+    });
+}
+
+/// Valida un puntero recibido del código emitido.
+pub fn check(address: usize, kind: &'static str) {
+    let found = with_live(|live| live.get(&address).map(|entry| entry.kind));
+
+    if found != Some(kind) {
+        error::fail(RuntimeError::BadReference {
+            expected: kind,
+            found,
+            address,
+        });
+    }
+}
+
+/// Elimina una referencia, la cual debe existir.
+pub fn release(address: usize, kind: &'static str) {
+    check(address, kind);
+
+    with_live(|live| {
+        let entry = live.get_mut(&address).unwrap();
+        entry.refs -= 1;
+
+        if entry.refs == 0 {
+            live.remove(&address);
+        }
+    });
+}
+
+/// Reporta las referencias que el programa no liberó.
+#[cfg(target_family = "unix")]
+pub fn report_leaks() {
+    with_live(|live| {
+        for (address, entry) in live.iter() {
+            eprintln!(
+                "Leaked {} at {:#x} created at line {} ({} reference{})",
+                entry.kind,
+                address,
+                entry.line,
+                entry.refs,
+                if entry.refs == 1 { "" } else { "s" }
+            );
+        }
+    });
+}
+
+fn with_live<F, R>(callback: F) -> R
+where
+    F: FnOnce(&mut BTreeMap<usize, Entry>) -> R,
+{
+    LIVE.with(callback)
+}
@@ -0,0 +1,159 @@
+//! Planificación de procedimientos.
+//!
+//! `After` y `Every` encolan procedimientos del usuario para ejecutarse
+//! más adelante sin detener el programa. No hay hilos ni interrupciones
+//! de por medio: las tareas vencidas se ejecutan mientras el programa
+//! espera en un delay (incluyendo los de `PrintText` y `Play`), así como
+//! tras finalizar `user_main()` mientras existan tareas pendientes.
+//!
+//! El reloj del planificador avanza únicamente con estas esperas, por lo
+//! cual el tiempo de cómputo entre ellas no se contabiliza. Una tarea no
+//! interrumpe a otra: los delays dentro de una tarea no ejecutan tareas.
+//!
+//! Los cuadros de `loop at` son la excepción: [`frame_sync()`] mide con
+//! `sys::uptime()` cuánto tomó el cuadro y solo espera lo que reste.
+
+use alloc::vec::Vec;
+
+use crate::{chrono::Duration, global::Global, sys};
+
+/// Un procedimiento sin parámetros emitido por el compilador.
+pub type Procedure = extern "C" fn();
+
+struct Task {
+    procedure: Procedure,
+    due: Duration,
+    period: Option<Duration>,
+}
+
+struct Scheduler {
+    now: Duration,
+    tasks: Vec<Task>,
+    running: bool,
+
+    /// Inicio del cuadro en curso de `loop at`, según `sys::uptime()`.
+    frame: Option<Duration>,
+}
+
+/// Ningún préstamo sobrevive a la ejecución de una tarea.
+static SCHEDULER: Global<Scheduler> = Global::new(Scheduler {
+    now: Duration::from_secs(0),
+    tasks: Vec::new(),
+    running: false,
+    frame: None,
+});
+
+/// Encola un procedimiento para ejecutarse tras `delay`, y luego cada
+/// `period` si corresponde.
+pub fn schedule(procedure: Procedure, delay: Duration, period: Option<Duration>) {
+    with_scheduler(|scheduler| {
+        let due = scheduler.now + delay;
+        scheduler.tasks.push(Task {
+            procedure,
+            due,
+            period,
+        });
+    });
+}
+
+/// Detiene el programa, ejecutando las tareas que venzan mientras tanto.
+pub fn delay(duration: Duration) {
+    let target = with_scheduler(|scheduler| scheduler.now + duration);
+
+    loop {
+        run_due();
+
+        let (now, next) = with_scheduler(|scheduler| {
+            let next = if scheduler.running {
+                None
+            } else {
+                scheduler.tasks.iter().map(|task| task.due).min()
+            };
+
+            (scheduler.now, next.map_or(target, |next| next.min(target)))
+        });
+
+        if now >= target {
+            break;
+        }
+
+        sys::delay(next - now);
+        with_scheduler(|scheduler| scheduler.now = next);
+    }
+}
+
+/// Espera a que termine el cuadro en curso de `loop at`, el cual dura
+/// `period` desde que terminó el anterior.
+///
+/// El primer cuadro se considera iniciado en la primera llamada. Un
+/// cuadro que excede `period` no se compensa acortando los siguientes.
+pub fn frame_sync(period: Duration) {
+    let now = sys::uptime();
+    let deadline = with_scheduler(|scheduler| scheduler.frame).unwrap_or(now) + period;
+
+    if now < deadline {
+        delay(deadline - now);
+    }
+
+    with_scheduler(|scheduler| scheduler.frame = Some(deadline.max(now)));
+}
+
+/// Ejecuta tareas hasta que no quede ninguna pendiente.
+pub fn run() {
+    while let Some(next) = with_scheduler(|scheduler| {
+        let now = scheduler.now;
+        scheduler
+            .tasks
+            .iter()
+            .map(|task| task.due.saturating_sub(now))
+            .min()
+    }) {
+        delay(next);
+    }
+}
+
+/// Ejecuta, en orden de vencimiento, cada tarea vencida.
+fn run_due() {
+    loop {
+        let procedure = with_scheduler(|scheduler| {
+            if scheduler.running {
+                return None;
+            }
+
+            let now = scheduler.now;
+            let (index, _) = scheduler
+                .tasks
+                .iter()
+                .enumerate()
+                .filter(|(_, task)| task.due <= now)
+                .min_by_key(|(_, task)| task.due)?;
+
+            let mut task = scheduler.tasks.swap_remove(index);
+            let procedure = task.procedure;
+
+            if let Some(period) = task.period {
+                task.due += period;
+                scheduler.tasks.push(task);
+            }
+
+            scheduler.running = true;
+            Some(procedure)
+        });
+
+        match procedure {
+            Some(procedure) => {
+                procedure();
+                with_scheduler(|scheduler| scheduler.running = false);
+            }
+
+            None => break,
+        }
+    }
+}
+
+fn with_scheduler<F, R>(callback: F) -> R
+where
+    F: FnOnce(&mut Scheduler) -> R,
+{
+    SCHEDULER.with(callback)
+}
@@ -0,0 +1,378 @@
+//! Implementación de `runtime::sys` para STM32 (Cortex-M4).
+//!
+//! La tarjeta de referencia es una "Black Pill" con STM32F411CE.
+//! La matriz de LEDs se conecta de la misma forma que en ESP8266,
+//! mediante dos registros de desplazamiento para filas y columnas.
+//! Al igual que allá, esta es una plataforma `#![no_std]`, por lo
+//! cual este módulo implementa punto de entrada, panic handler y
+//! heap. A diferencia de Xtensa, Cortex-M dispone de atómicos
+//! nativos, por lo cual no se requieren shims.
+
+use buddy_system_allocator::LockedHeap;
+use core::{cell::RefCell, convert::Infallible};
+
+use cortex_m::{
+    interrupt::{self, Mutex},
+    peripheral::syst::SystClkSource,
+};
+
+use cortex_m_rt::{entry, exception, ExceptionFrame};
+
+use stm32f4xx_hal::{
+    gpio::{gpiob, gpioc, Output, PushPull},
+    hal::digital::v2::OutputPin,
+    pac::{self, USART1},
+    prelude::*,
+    serial::{config::Config, Serial, Tx},
+};
+
+use crate::{
+    chrono::{self, Duration, Ticks},
+    error::RuntimeError,
+    heap::{Trackable, TrackingHeap},
+    matrix::{Display, State},
+    storage::RECORD_WORDS,
+};
+
+#[global_allocator]
+static HEAP_ALLOCATOR: TrackingHeap<LockedHeap<32>> = TrackingHeap::new(LockedHeap::empty());
+
+/// Tamaño del heap. El resto de la RAM queda disponible para stack.
+const HEAP_SIZE: usize = 64 * 1024;
+
+/// Frecuencia de interrupciones de SysTick, en Hz (pasos de 100us).
+const TICK_RATE: u32 = 10_000;
+
+extern "C" {
+    // Provisto por el linker script de cortex-m-rt
+    static __sheap: u8;
+}
+
+pub static SERIAL: Mutex<RefCell<Option<Tx<USART1>>>> = Mutex::new(RefCell::new(None));
+
+static HW: Mutex<RefCell<Option<Hw>>> = Mutex::new(RefCell::new(None));
+
+macro_rules! sys_debug {
+    ($($b:tt)*) => {
+        {
+            use core::fmt::Write;
+
+            cortex_m::interrupt::free(|cs| {
+                let mut serial = crate::stm32::SERIAL.borrow(cs).borrow_mut();
+                writeln!(serial.as_mut().unwrap(), $($b)*).unwrap();
+            });
+        }
+    }
+}
+
+//==================================================================================//
+//================================🄱🅄🄸🄻🅃🄸🄽🅂==================================//
+//==================================================================================//
+/// Detienen el programa por una cantidad de milisegundos.
+pub fn delay(duration: Duration) {
+    hw(|hw| hw.start_delay(Ticks::from_duration(duration)));
+
+    while !hw(Hw::delay_finished) {
+        continue;
+    }
+}
+
+pub const fn tick_count_for(duration: Duration) -> usize {
+    duration.as_millis() as usize * 10
+}
+
+/// Tiempo transcurrido desde el arranque.
+pub fn uptime() -> Duration {
+    chrono::elapsed(hw(|hw| hw.uptime))
+}
+
+pub fn with_display<F, R>(callback: F) -> R
+where
+    F: FnOnce(&mut Display) -> R,
+{
+    hw(|hw| callback(&mut hw.states))
+}
+
+/// Reporta un error en tiempo de ejecución y detiene el programa.
+///
+/// Al igual que en ESP8266, el error se imprime una única vez por el
+/// puerto serial y luego la matriz muestra una "X" parpadeante.
+pub fn fail(line: isize, error: RuntimeError) -> ! {
+    const BLINK_TICKS: Ticks = Ticks::from_duration(Duration::from_millis(250));
+
+    sys_debug!("\r\nRuntime error at line {}: {}\r", line, error);
+
+    with_display(|display| {
+        for row in 0..8 {
+            for col in 0..8 {
+                let light = &mut display[(row, col)];
+                let on_cross = row == col || row + col == 7;
+                let interval = if on_cross {
+                    BLINK_TICKS
+                } else {
+                    Ticks::default()
+                };
+
+                light.set(State::from_bool(on_cross));
+                light.blink(interval);
+            }
+        }
+
+        // Si el programa utiliza doble búfer, la "X" quedó en el trasero
+        display.present();
+    });
+
+    loop {
+        continue;
+    }
+}
+
+/// Envía una línea por el puerto serial.
+pub fn uart_write(line: &str) {
+    sys_debug!("{}\r", line);
+}
+
+/// La recepción no está implementada en esta plataforma.
+pub fn uart_read() -> Option<u8> {
+    None
+}
+
+/// Allocator global, ver `crate::heap`.
+pub fn heap() -> &'static TrackingHeap<LockedHeap<32>> {
+    &HEAP_ALLOCATOR
+}
+
+/// Esta plataforma carece de almacenamiento persistente, por lo cual
+/// los slots de `crate::storage` viven únicamente en RAM.
+pub const STORAGE_SECTORS: usize = 0;
+
+pub fn read_storage(_sector: usize, _record: &mut [u32; RECORD_WORDS]) -> bool {
+    false
+}
+
+pub fn write_storage(_sector: usize, _record: &[u32; RECORD_WORDS]) -> bool {
+    false
+}
+
+//==================================================================================//
+//===========================🅂🄸🅂🅃🄴🄼🄰 🄴🄼🄿🄾🅃🅁🄰🄳🄾======================//
+//==================================================================================//
+//Descripción de sistema MCU + Periféricos
+struct Hw {
+    led: gpioc::PC13<Output<PushPull>>, //activo en bajo
+    col_datapin: gpiob::PB12<Output<PushPull>>,
+    col_clockpin: gpiob::PB13<Output<PushPull>>,
+    row_datapin: gpiob::PB14<Output<PushPull>>,
+    row_clockpin: gpiob::PB15<Output<PushPull>>,
+    states: Display,
+    current_state: usize,
+    timeout: Ticks,
+
+    /// Ticks transcurridos desde el arranque.
+    uptime: u64,
+    draw_clock: Ticks,
+}
+
+impl Hw {
+    const DRAW_TICKS: Ticks = Ticks::from_duration(Duration::from_millis(3));
+
+    fn tick(&mut self) {
+        self.states.tick();
+        self.timeout.countdown();
+        self.uptime += 1;
+
+        if self.draw_clock.cycle_each(Self::DRAW_TICKS) {
+            self.draw();
+        }
+    }
+
+    fn draw(&mut self) {
+        let row_data = !(0b10000000 >> self.current_state);
+        let col_data = self.states.row_bits(self.current_state) as usize;
+
+        shift(row_data, &mut self.row_clockpin, &mut self.row_datapin);
+        shift(col_data, &mut self.col_clockpin, &mut self.col_datapin);
+
+        self.current_state += 1;
+        if self.current_state == 8 {
+            self.current_state = 0;
+        }
+    }
+
+    //======================timer functions====================
+    fn start_delay(&mut self, timeout: Ticks) {
+        self.timeout = timeout;
+    }
+
+    fn delay_finished(&mut self) -> bool {
+        self.timeout.done()
+    }
+}
+
+/// Punto de entrada para STM32.
+#[entry]
+fn main() -> ! {
+    let peripherals = pac::Peripherals::take().unwrap();
+    let mut core = cortex_m::Peripherals::take().unwrap();
+
+    // Cristal externo de 25MHz, como en la Black Pill
+    let clocks = peripherals
+        .RCC
+        .constrain()
+        .cfgr
+        .use_hse(25.mhz())
+        .sysclk(84.mhz())
+        .freeze();
+
+    let gpioa = peripherals.GPIOA.split();
+    let gpiob = peripherals.GPIOB.split();
+    let gpioc = peripherals.GPIOC.split();
+
+    let pins = (
+        gpioa.pa9.into_alternate_af7(),
+        gpioa.pa10.into_alternate_af7(),
+    );
+
+    let config = Config::default().baudrate(115_200.bps());
+    let (tx, _) = Serial::new(peripherals.USART1, pins, config, clocks)
+        .unwrap()
+        .split();
+
+    interrupt::free(|cs| SERIAL.borrow(cs).replace(Some(tx)));
+
+    {
+        let hw = Hw {
+            led: gpioc.pc13.into_push_pull_output(),
+            col_datapin: gpiob.pb12.into_push_pull_output(),
+            col_clockpin: gpiob.pb13.into_push_pull_output(),
+            row_datapin: gpiob.pb14.into_push_pull_output(),
+            row_clockpin: gpiob.pb15.into_push_pull_output(),
+            states: Default::default(),
+            current_state: 0,
+            timeout: Default::default(),
+            uptime: 0,
+            draw_clock: Default::default(),
+        };
+
+        // Esto no puede escribirse con hw() debido al unwrap
+        interrupt::free(|cs| HW.borrow(cs).replace(Some(hw)));
+    }
+
+    // HEAP allocation
+    unsafe {
+        let start = &__sheap as *const u8;
+        HEAP_ALLOCATOR
+            .inner()
+            .lock()
+            .init(start as usize, HEAP_SIZE);
+    }
+
+    let systick = &mut core.SYST;
+    systick.set_clock_source(SystClkSource::Core);
+    systick.set_reload(clocks.sysclk().0 / TICK_RATE - 1);
+    systick.clear_current();
+    systick.enable_counter();
+    systick.enable_interrupt();
+
+    hw(|hw| hw.led.set_low().unwrap());
+
+    crate::handover();
+
+    // Aquí no hay un sistema operativo que se encargue de hacer algo
+    // cuando un progrma finaliza, por lo cual eso no puede pasar
+    panic!("user_main() returned")
+}
+
+fn shift<Clock, Data>(data: usize, clock_pin: &mut Clock, data_pin: &mut Data)
+where
+    Data: OutputPin<Error = Infallible>,
+    Clock: OutputPin<Error = Infallible>,
+{
+    digital_write(clock_pin, 0);
+
+    for i in 0..9 {
+        //escribe un bit adicional para limpiar
+        digital_write(clock_pin, 1);
+        digital_write(data_pin, (data >> i) & 1);
+        digital_write(clock_pin, 0);
+    }
+}
+
+fn digital_write<Pin>(pin: &mut Pin, value: usize)
+where
+    Pin: OutputPin<Error = Infallible>,
+{
+    if value != 0 {
+        pin.set_high().unwrap();
+    } else {
+        pin.set_low().unwrap();
+    }
+}
+
+#[exception]
+fn SysTick() {
+    maybe_hw(Hw::tick);
+}
+
+/// Falla de hardware, usualmente por un acceso inválido a memoria.
+///
+/// Las direcciones reportadas se traducen a líneas del programa con
+/// `compiler decode`, a partir del mapa generado por `--emit-ledmap`.
+#[exception]
+fn HardFault(frame: &ExceptionFrame) -> ! {
+    let mut x = 0;
+    loop {
+        x += 1;
+        if x > 100_000_000 {
+            x = 0;
+            sys_debug!(
+                "\r\nHardFault at pc=0x{:08x} lr=0x{:08x}\r",
+                frame.pc,
+                frame.lr
+            );
+        }
+    }
+}
+
+/// Algo salió mal.
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    let mut x = 0;
+    loop {
+        x += 1;
+        if x > 100_000_000 {
+            x = 0;
+            sys_debug!(
+                "\r\n-----------Panic cause---------- \n{}\r\n-----This message repeats-----\n",
+                info
+            );
+        }
+    }
+}
+
+unsafe impl Trackable for LockedHeap<32> {
+    fn exclusive<F, R>(&self, callback: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        interrupt::free(|_| callback())
+    }
+
+    fn capacity(&self) -> usize {
+        self.lock().stats_total_bytes()
+    }
+}
+
+fn hw<F, R>(callback: F) -> R
+where
+    F: FnOnce(&mut Hw) -> R,
+{
+    interrupt::free(|cs| callback(HW.borrow(cs).borrow_mut().as_mut().unwrap()))
+}
+
+fn maybe_hw<F, R>(callback: F) -> Option<R>
+where
+    F: FnOnce(&mut Hw) -> R,
+{
+    interrupt::free(|cs| HW.borrow(cs).borrow_mut().as_mut().map(callback))
+}
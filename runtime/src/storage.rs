@@ -0,0 +1,139 @@
+//! Almacenamiento persistente.
+//!
+//! `Store` y `Load` operan sobre `SLOTS` enteros que sobreviven a
+//! reinicios. La plataforma provee `sys::STORAGE_SECTORS` sectores,
+//! cada uno con capacidad para un registro completo: la secuencia de
+//! escritura, todos los slots y una suma de verificación. Un registro
+//! con suma incorrecta, por ejemplo tras perder energía a mitad de una
+//! escritura o en un sector nunca escrito, se ignora.
+//!
+//! # Desgaste
+//! Cada escritura ocupa el sector siguiente al del último registro,
+//! de forma rotativa, por lo cual el desgaste se reparte entre todos
+//! los sectores. Al iniciar se elige el registro válido con la mayor
+//! secuencia. Almacenar el valor que un slot ya tiene no escribe nada.
+//!
+//! En plataformas sin sectores los slots viven únicamente en RAM.
+
+use crate::{
+    error::{self, RuntimeError},
+    global::Global,
+    sys,
+};
+
+/// Cantidad de slots.
+pub const SLOTS: usize = 16;
+
+/// Palabras de 32 bits que ocupa un registro.
+pub const RECORD_WORDS: usize = SLOTS + 3;
+
+/// Identifica registros escritos por este módulo.
+const MAGIC: u32 = 0x414c_4544;
+
+struct Storage {
+    values: [i32; SLOTS],
+    sequence: u32,
+    sector: usize,
+    loaded: bool,
+}
+
+static STORAGE: Global<Storage> = Global::new(Storage {
+    values: [0; SLOTS],
+    sequence: 0,
+    sector: 0,
+    loaded: false,
+});
+
+/// Lee un slot, `0` si nunca se ha escrito.
+pub fn load(slot: usize) -> i32 {
+    with_storage(|storage| storage.values[slot])
+}
+
+/// Escribe un slot y lo persiste.
+pub fn store(slot: usize, value: i32) {
+    let record = with_storage(|storage| {
+        if storage.values[slot] == value {
+            return None;
+        }
+
+        storage.values[slot] = value;
+        storage.sequence = storage.sequence.wrapping_add(1);
+        storage.sector = (storage.sector + 1) % sys::STORAGE_SECTORS.max(1);
+
+        Some((storage.sector, encode(storage)))
+    });
+
+    if let Some((sector, record)) = record.filter(|_| sys::STORAGE_SECTORS > 0) {
+        if !sys::write_storage(sector, &record) {
+            error::fail(RuntimeError::StorageFailure);
+        }
+    }
+}
+
+/// Recupera el registro más reciente de entre todos los sectores.
+fn restore(storage: &mut Storage) {
+    let mut latest = None;
+
+    for sector in 0..sys::STORAGE_SECTORS {
+        let mut record = [0; RECORD_WORDS];
+        if !sys::read_storage(sector, &mut record) || !is_valid(&record) {
+            continue;
+        }
+
+        let sequence = record[1];
+        match latest {
+            // La secuencia puede dar la vuelta, por lo cual se compara
+            // por diferencia en vez de por magnitud
+            Some((_, latest, _)) if (sequence.wrapping_sub(latest) as i32) <= 0 => (),
+            _ => latest = Some((sector, sequence, record)),
+        }
+    }
+
+    if let Some((sector, sequence, record)) = latest {
+        storage.sector = sector;
+        storage.sequence = sequence;
+
+        for (value, word) in storage.values.iter_mut().zip(&record[2..]) {
+            *value = *word as i32;
+        }
+    }
+}
+
+fn encode(storage: &Storage) -> [u32; RECORD_WORDS] {
+    let mut record = [0; RECORD_WORDS];
+    record[0] = MAGIC;
+    record[1] = storage.sequence;
+
+    for (word, value) in record[2..].iter_mut().zip(storage.values.iter()) {
+        *word = *value as u32;
+    }
+
+    record[RECORD_WORDS - 1] = checksum(&record[..RECORD_WORDS - 1]);
+    record
+}
+
+fn is_valid(record: &[u32; RECORD_WORDS]) -> bool {
+    let (body, sum) = record.split_at(RECORD_WORDS - 1);
+    body[0] == MAGIC && sum[0] == checksum(body)
+}
+
+/// FNV-1a sobre las palabras del registro.
+fn checksum(words: &[u32]) -> u32 {
+    words.iter().fold(0x811c_9dc5, |hash, word| {
+        (hash ^ word).wrapping_mul(0x0100_0193)
+    })
+}
+
+fn with_storage<F, R>(callback: F) -> R
+where
+    F: FnOnce(&mut Storage) -> R,
+{
+    STORAGE.with(|storage| {
+        if !storage.loaded {
+            restore(storage);
+            storage.loaded = true;
+        }
+
+        callback(storage)
+    })
+}
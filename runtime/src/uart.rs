@@ -0,0 +1,132 @@
+//! Comunicación serial con el huésped.
+//!
+//! Los enteros viajan como texto, uno por línea y en decimal, de forma
+//! que basta un monitor serial para interactuar con el programa. La
+//! plataforma entrega los bytes recibidos a través de `sys::uart_read()`
+//! sin bloquear, usualmente desde un [`RingBuffer`] que llena una
+//! interrupción. Las líneas que no son un entero se descartan.
+
+use crate::{global::Global, sys};
+
+/// Capacidad de un [`RingBuffer`]. Los bytes que no caben se pierden.
+const RX_CAPACITY: usize = 64;
+
+/// Longitud máxima de una línea recibida, suficiente para un `i32`.
+const MAX_LINE: usize = 12;
+
+/// Cola de bytes recibidos y aún no leídos.
+#[allow(dead_code)]
+pub struct RingBuffer {
+    bytes: [u8; RX_CAPACITY],
+    start: usize,
+    len: usize,
+}
+
+#[allow(dead_code)]
+impl RingBuffer {
+    pub const fn new() -> Self {
+        RingBuffer {
+            bytes: [0; RX_CAPACITY],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    /// Encola un byte, descartándolo si no hay espacio.
+    pub fn push(&mut self, byte: u8) {
+        if self.len < RX_CAPACITY {
+            self.bytes[(self.start + self.len) % RX_CAPACITY] = byte;
+            self.len += 1;
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let byte = self.bytes[self.start];
+        self.start = (self.start + 1) % RX_CAPACITY;
+        self.len -= 1;
+
+        Some(byte)
+    }
+}
+
+impl Default for RingBuffer {
+    fn default() -> Self {
+        RingBuffer::new()
+    }
+}
+
+/// Línea en recepción. Una línea demasiado larga no es un entero.
+struct Line {
+    bytes: [u8; MAX_LINE],
+    len: usize,
+    overflow: bool,
+}
+
+static LINE: Global<Line> = Global::new(Line {
+    bytes: [0; MAX_LINE],
+    len: 0,
+    overflow: false,
+});
+
+/// Envía un entero.
+pub fn write_int(value: isize) {
+    let mut digits = [0; 24];
+    let mut start = digits.len();
+    let mut magnitude = value.unsigned_abs();
+
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (magnitude % 10) as u8;
+        magnitude /= 10;
+
+        if magnitude == 0 {
+            break;
+        }
+    }
+
+    if value < 0 {
+        start -= 1;
+        digits[start] = b'-';
+    }
+
+    sys::uart_write(core::str::from_utf8(&digits[start..]).unwrap_or_default());
+}
+
+/// Siguiente entero recibido, si ya se completó su línea.
+pub fn read_int() -> Option<isize> {
+    LINE.with(|line| {
+        while let Some(byte) = sys::uart_read() {
+            match byte {
+                b'\r' => (),
+
+                b'\n' => {
+                    let complete = &line.bytes[..line.len];
+                    let value = core::str::from_utf8(complete)
+                        .ok()
+                        .filter(|_| !line.overflow)
+                        .and_then(|text| text.trim().parse::<i32>().ok());
+
+                    line.len = 0;
+                    line.overflow = false;
+
+                    if let Some(value) = value {
+                        return Some(value as isize);
+                    }
+                }
+
+                byte if line.len < MAX_LINE => {
+                    line.bytes[line.len] = byte;
+                    line.len += 1;
+                }
+
+                _ => line.overflow = true,
+            }
+        }
+
+        None
+    })
+}
@@ -0,0 +1,198 @@
+//! Implementación de `runtime::sys` para el simulador en navegador.
+//!
+//! En `wasm32-unknown-unknown` no existe sistema operativo ni hilos,
+//! por lo cual todo efecto observable se delega al entorno JavaScript
+//! mediante las funciones importadas del módulo `animationled`. Ver
+//! `runtime/web/animationled.js`.
+//!
+//! # Reloj virtual
+//! Al igual que con `ANIMATIONLED_TRACE` en plataformas hosted, el
+//! programa no se ejecuta en tiempo real: cada `delay()` avanza un
+//! reloj virtual en ticks de 10ms. Cada vez que el contenido de la
+//! matriz cambia se reporta un cuadro con el tiempo virtual en
+//! milisegundos y las ocho filas, cuatro por palabra, donde el bit
+//! más significativo de cada fila es la columna 0. El entorno luego
+//! reproduce los cuadros a la velocidad original.
+//!
+//! Tras cada tick se notifica el tiempo virtual. El entorno puede
+//! lanzar una excepción en ese momento para detener una animación
+//! que no termina.
+
+use std::{alloc::System, cell::RefCell, convert::TryInto};
+
+use crate::{
+    chrono::Duration,
+    error::RuntimeError,
+    heap::{Trackable, TrackingHeap},
+    matrix::Display,
+    storage::RECORD_WORDS,
+};
+
+/// Imprime un mensaje de depuración.
+macro_rules! sys_debug {
+    ($($b:tt)*) => {
+        $crate::sys::debug(&format!($($b)*))
+    }
+}
+
+#[global_allocator]
+static HEAP_ALLOCATOR: TrackingHeap<System> = TrackingHeap::new(System);
+
+#[link(wasm_import_module = "animationled")]
+extern "C" {
+    fn frame(millis: u32, high: u32, low: u32);
+    fn clock(millis: u32);
+
+    #[link_name = "debug"]
+    fn host_debug(message: *const u8, length: usize);
+
+    #[link_name = "fail"]
+    fn host_fail(line: isize, message: *const u8, length: usize) -> !;
+}
+
+/// Detiene el programa durante una cantidad de tiempo.
+pub fn delay(duration: Duration) {
+    let target = SIMULATOR.with(|simulator| simulator.borrow().now + duration);
+
+    loop {
+        let now = SIMULATOR.with(|simulator| {
+            let mut simulator = simulator.borrow_mut();
+            let next = TICK * (simulator.ticks + 1);
+
+            if next > target {
+                simulator.now = target;
+                return None;
+            }
+
+            simulator.ticks += 1;
+            simulator.now = next;
+
+            simulator.display.tick();
+            simulator.record();
+
+            Some(next)
+        });
+
+        // Se notifica sin préstamos activos, ya que el entorno puede
+        // abortar la ejecución desde clock()
+        match now {
+            Some(now) => notify(now),
+            None => break,
+        }
+    }
+
+    notify(target);
+}
+
+pub const fn tick_count_for(duration: Duration) -> usize {
+    duration.as_millis() as usize / 10
+}
+
+/// Tiempo del reloj virtual.
+pub fn uptime() -> Duration {
+    SIMULATOR.with(|simulator| simulator.borrow().now)
+}
+
+pub fn with_display<F, R>(callback: F) -> R
+where
+    F: FnOnce(&mut Display) -> R,
+{
+    SIMULATOR.with(|simulator| {
+        let mut simulator = simulator.borrow_mut();
+        let result = callback(&mut simulator.display);
+
+        simulator.record();
+        result
+    })
+}
+
+/// Reporta un error en tiempo de ejecución y detiene el programa.
+pub fn fail(line: isize, error: RuntimeError) -> ! {
+    let message = error.to_string();
+    unsafe { host_fail(line, message.as_ptr(), message.len()) }
+}
+
+pub fn debug(message: &str) {
+    unsafe { host_debug(message.as_ptr(), message.len()) }
+}
+
+/// Las líneas enviadas se reportan como mensajes de depuración.
+pub fn uart_write(line: &str) {
+    debug(line);
+}
+
+/// La recepción no está implementada en esta plataforma.
+pub fn uart_read() -> Option<u8> {
+    None
+}
+
+/// Allocator global, ver `crate::heap`.
+pub fn heap() -> &'static TrackingHeap<System> {
+    &HEAP_ALLOCATOR
+}
+
+/// Esta plataforma carece de almacenamiento persistente, por lo cual
+/// los slots de `crate::storage` viven únicamente en RAM.
+pub const STORAGE_SECTORS: usize = 0;
+
+pub fn read_storage(_sector: usize, _record: &mut [u32; RECORD_WORDS]) -> bool {
+    false
+}
+
+pub fn write_storage(_sector: usize, _record: &[u32; RECORD_WORDS]) -> bool {
+    false
+}
+
+/// Cada tick del reloj equivale a 10ms, ver `tick_count_for()`.
+const TICK: Duration = Duration::from_millis(10);
+
+thread_local! {
+    static SIMULATOR: RefCell<Simulator> = RefCell::new(Simulator::default());
+}
+
+/// Estado de la matriz y del reloj virtual.
+#[derive(Default)]
+struct Simulator {
+    display: Display,
+    now: Duration,
+    ticks: u32,
+
+    /// La matriz inicia apagada, lo cual el entorno ya conoce.
+    last_frame: [u8; 8],
+}
+
+impl Simulator {
+    /// Reporta el contenido actual de la matriz si cambió.
+    fn record(&mut self) {
+        let mut frame_rows = [0; 8];
+        for (row, bits) in frame_rows.iter_mut().enumerate() {
+            *bits = self.display.row_bits(row);
+        }
+
+        if self.last_frame != frame_rows {
+            let (high, low) = frame_rows.split_at(4);
+            let word = |rows: &[u8]| u32::from_be_bytes(rows.try_into().unwrap());
+
+            unsafe { frame(self.now.as_millis() as u32, word(high), word(low)) };
+            self.last_frame = frame_rows;
+        }
+    }
+}
+
+fn notify(now: Duration) {
+    unsafe { clock(now.as_millis() as u32) }
+}
+
+// Sin hilos ni interrupciones, toda asignación es exclusiva
+unsafe impl Trackable for System {
+    fn exclusive<F, R>(&self, callback: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        callback()
+    }
+
+    fn capacity(&self) -> usize {
+        usize::MAX
+    }
+}
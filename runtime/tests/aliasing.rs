@@ -0,0 +1,97 @@
+//! Builtins que reciben dos veces el mismo objeto, o dos objetos que
+//! comparten filas, no deben observar sus propias escrituras.
+
+mod common;
+
+use std::{env, process::Command};
+
+use common::*;
+use runtime::builtin::*;
+
+#[test]
+fn mutating_a_slice_keeps_the_source() {
+    let source = mat(&[&[true, false], &[false, true], &[true, true]]);
+    let slice = builtin_slice_mat(source, 0, 2);
+
+    assert!(same_row(source, 0, slice, 0));
+    assert!(same_row(source, 1, slice, 1));
+
+    builtin_set_entry_mat(slice, 0, 0, false);
+    builtin_set_entry_mat(slice, 1, 1, false);
+
+    assert_eq!(rows(slice), [[false, false], [false, false]]);
+    assert_eq!(rows(source), [[true, false], [false, true], [true, true]]);
+
+    builtin_drop_mat(slice);
+    builtin_drop_mat(source);
+}
+
+#[test]
+fn mutating_the_source_keeps_a_slice() {
+    let source = mat(&[&[true, false], &[false, true]]);
+    let slice = builtin_slice_mat(source, 1, 2);
+
+    let blank = list(&[false, false]);
+    builtin_set_column_mat(source, 1, blank);
+    builtin_drop_list(blank);
+
+    assert_eq!(rows(source), [[true, false], [false, false]]);
+    assert_eq!(rows(slice), [[false, true]]);
+
+    builtin_drop_mat(slice);
+    builtin_drop_mat(source);
+}
+
+#[test]
+fn set_slice_list_from_itself() {
+    let target = list(&[true, false, false, true]);
+    builtin_set_slice_list(target, 0, 4, target);
+
+    assert_eq!(entries(target), [true, false, false, true]);
+    builtin_drop_list(target);
+}
+
+#[test]
+fn set_slice_ilist_from_itself() {
+    let target = ilist(&[-3, 0, 7]);
+    builtin_set_slice_ilist(target, 0, 3, target);
+
+    assert_eq!(entries(target), [-3, 0, 7]);
+    builtin_drop_ilist(target);
+}
+
+#[test]
+fn set_slice_mat_from_itself() {
+    let target = mat(&[&[true, false], &[false, true]]);
+    builtin_set_slice_mat(target, 0, 2, target);
+
+    assert_eq!(rows(target), [[true, false], [false, true]]);
+    builtin_drop_mat(target);
+}
+
+/// `sys::fail()` termina el proceso, por lo cual la inserción se
+/// realiza en un proceso hijo que ejecuta solamente esta prueba.
+#[test]
+fn insert_mat_into_itself() {
+    const CHILD: &str = "RUNTIME_TEST_SELF_INSERTION";
+
+    if env::var_os(CHILD).is_some() {
+        let target = mat(&[&[true, false]]);
+        builtin_insert_mat(target, target, 0, 0);
+        unreachable!();
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args(&["--exact", "insert_mat_into_itself", "--nocapture"])
+        .env(CHILD, "1")
+        .output()
+        .expect("failed to run test process");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("attempted to insert a matrix into itself"),
+        "unexpected output: {}",
+        stderr
+    );
+}
@@ -0,0 +1,78 @@
+//! Utilidades compartidas por las pruebas de `libruntime`.
+//!
+//! Las pruebas invocan a los builtins igual que lo haría el código
+//! emitido, por lo cual los objetos se construyen y se leen mediante
+//! punteros crudos.
+
+#![allow(dead_code)]
+
+use std::rc::Rc;
+
+use runtime::builtin::*;
+
+pub type List = Vec<bool>;
+pub type IntList = Vec<isize>;
+pub type Mat = Vec<Rc<List>>;
+
+/// Ver la documentación top-level de `libruntime`.
+#[no_mangle]
+extern "C" fn user_main() {}
+
+pub fn list(entries: &[bool]) -> *mut List {
+    let list = builtin_new_list();
+    for (index, &entry) in entries.iter().enumerate() {
+        builtin_insert_list(list, index as isize, entry);
+    }
+
+    list
+}
+
+pub fn ilist(entries: &[isize]) -> *mut IntList {
+    let list = builtin_new_ilist();
+    for (index, &entry) in entries.iter().enumerate() {
+        builtin_insert_ilist(list, index as isize, entry);
+    }
+
+    list
+}
+
+pub fn mat(rows: &[&[bool]]) -> *mut Mat {
+    let mat = builtin_new_mat();
+    for row in rows {
+        let row = list(row);
+        builtin_push_mat(mat, row);
+        builtin_drop_list(row);
+    }
+
+    mat
+}
+
+/// Construye una matriz de `height` filas que comparten una misma lista.
+pub fn shared_rows(row: &[bool], height: usize) -> *mut Mat {
+    let mat = mat(&vec![row; height]);
+    let row = list(row);
+
+    for index in 0..height {
+        builtin_set_row_mat(mat, index as isize, row);
+    }
+
+    builtin_drop_list(row);
+    mat
+}
+
+pub fn entries<T: Clone>(list: *mut Vec<T>) -> Vec<T> {
+    borrow(list).clone()
+}
+
+pub fn rows(mat: *mut Mat) -> Vec<List> {
+    borrow(mat).iter().map(|row| List::clone(row)).collect()
+}
+
+/// Determina si dos filas son la misma lista y no solo listas iguales.
+pub fn same_row(first: *mut Mat, first_row: usize, second: *mut Mat, second_row: usize) -> bool {
+    Rc::ptr_eq(&borrow(first)[first_row], &borrow(second)[second_row])
+}
+
+fn borrow<'a, T>(object: *mut T) -> &'a T {
+    unsafe { &*object }
+}
@@ -0,0 +1,126 @@
+//! Desplazamientos de `builtin_shift_list()` y `builtin_shift_mat()`.
+
+mod common;
+
+use common::*;
+use runtime::builtin::*;
+
+const ENTRIES: [bool; 4] = [true, true, false, true];
+
+fn shifted(n: isize, wrap: bool) -> Vec<bool> {
+    let target = list(&ENTRIES);
+    builtin_shift_list(target, n, wrap);
+
+    let result = entries(target);
+    builtin_drop_list(target);
+    result
+}
+
+#[test]
+fn shift_list_forward() {
+    assert_eq!(shifted(1, false), [false, true, true, false]);
+    assert_eq!(shifted(1, true), [true, true, true, false]);
+}
+
+#[test]
+fn shift_list_backward() {
+    assert_eq!(shifted(-1, false), [true, false, true, false]);
+    assert_eq!(shifted(-1, true), [true, false, true, true]);
+}
+
+#[test]
+fn shift_list_zero() {
+    assert_eq!(shifted(0, false), ENTRIES);
+    assert_eq!(shifted(0, true), ENTRIES);
+}
+
+#[test]
+fn shift_list_past_length() {
+    for &n in &[4, 5, 100, -4, -5, -100, isize::MIN] {
+        assert_eq!(shifted(n, false), [false; 4], "n = {}", n);
+    }
+
+    assert_eq!(shifted(4, true), ENTRIES);
+    assert_eq!(shifted(-8, true), ENTRIES);
+    assert_eq!(shifted(5, true), shifted(1, true));
+    assert_eq!(shifted(-5, true), shifted(-1, true));
+}
+
+#[test]
+fn shift_empty_list() {
+    for &wrap in &[false, true] {
+        for &n in &[0, 1, -1, isize::MAX, isize::MIN] {
+            let target = list(&[]);
+            builtin_shift_list(target, n, wrap);
+
+            assert!(entries(target).is_empty());
+            builtin_drop_list(target);
+        }
+    }
+}
+
+#[test]
+fn shift_empty_mat() {
+    let target = mat(&[]);
+    builtin_shift_mat(target, 1, -1, false);
+    builtin_shift_mat(target, -3, 3, true);
+
+    assert!(rows(target).is_empty());
+    builtin_drop_mat(target);
+}
+
+#[test]
+fn shift_mat_columns_with_shared_rows() {
+    let target = shared_rows(&[true, false, false], 3);
+    let other = builtin_slice_mat(target, 0, 1);
+    assert!(same_row(target, 0, other, 0));
+
+    builtin_shift_mat(target, 1, 0, false);
+    assert_eq!(rows(target), [[false, true, false]; 3]);
+    assert_eq!(rows(other), [[true, false, false]]);
+
+    builtin_shift_mat(target, -4, 0, true);
+    assert_eq!(rows(target), [[true, false, false]; 3]);
+    assert_eq!(rows(other), [[true, false, false]]);
+
+    builtin_drop_mat(other);
+    builtin_drop_mat(target);
+}
+
+#[test]
+fn shift_mat_rows_with_shared_rows() {
+    let target = mat(&[&[true, true], &[false, true], &[true, false]]);
+    let other = builtin_slice_mat(target, 0, 3);
+
+    builtin_shift_mat(target, 0, 1, false);
+    assert_eq!(rows(target), [[false, false], [true, true], [false, true]]);
+    assert_eq!(rows(other), [[true, true], [false, true], [true, false]]);
+
+    builtin_shift_mat(target, 0, -2, true);
+    assert_eq!(rows(target), [[false, true], [false, false], [true, true]]);
+
+    // Las filas liberadas no son la fila que salió del otro extremo
+    builtin_shift_mat(target, 0, -2, false);
+    builtin_set_entry_mat(target, 2, 0, true);
+    assert_eq!(rows(target), [[true, true], [false, false], [true, false]]);
+    assert_eq!(rows(other), [[true, true], [false, true], [true, false]]);
+
+    builtin_drop_mat(other);
+    builtin_drop_mat(target);
+}
+
+#[test]
+fn shift_mat_diagonally() {
+    let target = shared_rows(&[true, false], 2);
+
+    builtin_shift_mat(target, -1, -1, false);
+    assert_eq!(rows(target), [[false, false], [false, false]]);
+
+    let target = mat(&[&[true, false], &[false, false]]);
+    builtin_shift_mat(target, 1, 1, false);
+    assert_eq!(rows(target), [[false, false], [false, true]]);
+
+    builtin_shift_mat(target, 3, -5, true);
+    assert_eq!(rows(target), [[true, false], [false, false]]);
+    builtin_drop_mat(target);
+}
@@ -0,0 +1,118 @@
+// Simulador en navegador para programas compilados con `-t web`.
+//
+// Un programa es un módulo WebAssembly que importa los builtins, la
+// memoria y `builtin_line` del módulo "runtime". Este último es
+// libruntime compilado para wasm32-unknown-unknown, el cual a su vez
+// importa del módulo "animationled" las funciones implementadas aquí.
+//
+// La ejecución ocurre sobre un reloj virtual: run() produce la lista
+// de cuadros de la matriz con su tiempo y play() los reproduce.
+
+const decoder = new TextDecoder();
+
+// Se lanza desde clock() para detener animaciones que no terminan
+class Stop extends Error {}
+
+// Un error en tiempo de ejecución reportado por libruntime
+export class RuntimeFailure extends Error {
+  constructor(line, message) {
+    super(`Runtime error at line ${line}: ${message}`);
+    this.line = line;
+  }
+}
+
+// Ejecuta un programa y retorna { frames, end, error }.
+//
+// `program` y `runtime` son los bytes de cada módulo. Cada cuadro es
+// { millis, rows }, donde rows contiene ocho enteros y el bit más
+// significativo de cada uno es la columna 0. La ejecución se detiene
+// sin error al alcanzar `stopAt` milisegundos de tiempo virtual.
+export async function run(program, runtime, { stopAt = 60000, debug = console.log } = {}) {
+  const frames = [{ millis: 0, rows: new Array(8).fill(0) }];
+
+  let memory = null;
+  let now = 0;
+
+  const text = (pointer, length) => decoder.decode(new Uint8Array(memory.buffer, pointer, length));
+
+  const host = {
+    frame(millis, high, low) {
+      const rows = [];
+      for (const word of [high, low]) {
+        for (let shift = 24; shift >= 0; shift -= 8) {
+          rows.push((word >>> shift) & 0xff);
+        }
+      }
+
+      frames.push({ millis, rows });
+    },
+
+    clock(millis) {
+      now = millis;
+      if (now >= stopAt) {
+        throw new Stop();
+      }
+    },
+
+    debug(pointer, length) {
+      debug(text(pointer, length));
+    },
+
+    fail(line, pointer, length) {
+      throw new RuntimeFailure(line, text(pointer, length));
+    },
+  };
+
+  const { instance: rt } = await WebAssembly.instantiate(runtime, { animationled: host });
+  memory = rt.exports.memory;
+
+  // Las firmas de builtins en el programa se deducen de su uso, por
+  // lo cual pueden omitir un valor de retorno que sí existe o esperar
+  // uno que no existe. Una función JavaScript intermedia admite ambos.
+  const imports = { memory, builtin_line: rt.exports.builtin_line };
+  for (const [name, value] of Object.entries(rt.exports)) {
+    if (name.startsWith("builtin_") && typeof value === "function") {
+      imports[name] = (...args) => value(...args) ?? 0;
+    }
+  }
+
+  const { instance } = await WebAssembly.instantiate(program, { runtime: imports });
+
+  let error = null;
+  try {
+    instance.exports.user_main();
+  } catch (caught) {
+    if (!(caught instanceof Stop)) {
+      error = caught;
+    }
+  }
+
+  return { frames, end: now, error };
+}
+
+// Reproduce cuadros en tiempo real sobre una matriz de 8x8 elementos.
+//
+// `lights` es un arreglo de 64 elementos ordenados por fila. Se agrega
+// o quita la clase "on" de cada uno. Retorna una función que cancela
+// la reproducción.
+export function play(frames, lights, { loop = false, end = 0 } = {}) {
+  const duration = Math.max(end, frames.length > 0 ? frames[frames.length - 1].millis : 0);
+  let timers = [];
+
+  const show = ({ rows }) => {
+    lights.forEach((light, index) => {
+      const bit = (rows[index >> 3] >> (7 - (index & 7))) & 1;
+      light.classList.toggle("on", bit === 1);
+    });
+  };
+
+  const start = () => {
+    timers = frames.map((frame) => setTimeout(() => show(frame), frame.millis));
+    if (loop && duration > 0) {
+      timers.push(setTimeout(start, duration));
+    }
+  };
+
+  start();
+  return () => timers.forEach(clearTimeout);
+}
@@ -0,0 +1,434 @@
+//! Implementación para AArch64 (ARMv8-A).
+//!
+//! # Manual de ISA
+//! <https://developer.arm.com/documentation/ddi0487/latest>
+//!
+//! La convención de llamada es la AAPCS64:
+//! <https://github.com/ARM-software/abi-aa/blob/main/aapcs64/aapcs64.rst>
+//!
+//! El código emitido sigue las convenciones de objetos ELF, como en
+//! Linux para Raspberry Pi. El formato Mach-O de macOS requiere otras
+//! relocalizaciones (`@PAGE`, `@PAGEOFF`) y el prefijo `_` en símbolos,
+//! por lo cual [`Platform::check_host()`] rechaza la plataforma nativa
+//! en ese sistema.
+//!
+//! [`Platform::check_host()`]: crate::link::Platform::check_host
+
+use crate::{
+    codegen::{
+        peephole::{self, Pattern},
+        regs::Allocations,
+        Context,
+    },
+    ir::{ArithmeticOp, BinOp, Function, Global, Instruction, Local, LogicOp},
+    source::Located,
+};
+
+use std::{fmt, io};
+
+/// Esta es una arquitectura de 64 bits. Los enteros se operan en los
+/// registros `w` de 32 bits y luego se extienden en signo con `sxtw`,
+/// ver [`super::Emitter::VALUE_SIZE`].
+const VALUE_SIZE: u32 = 8;
+
+/// Rango de inmediatos de `add` y `sub`, sin desplazar.
+const IMM_MAX: u32 = 4095;
+
+/// Máximo desplazamiento de `ldr` y `str` con inmediato sin signo,
+/// el cual se escala por el tamaño del acceso.
+const OFFSET_MAX: u32 = 4095 * VALUE_SIZE;
+
+/// Registro de procesador.
+///
+/// Existen 31 registros `x0`-`x30`, cuyas mitades inferiores se nombran
+/// `w0`-`w30`. Los temporales se toman de los registros caller-saved
+/// `x0`-`x7` y `x9`-`x15`. Las locales que sobreviven llamadas residen
+/// en `x19`-`x28`, que la AAPCS64 define como callee-saved, ver
+/// [`super::Register::CALLEE_SAVED`]. El prólogo guarda con
+/// `transfer_saved()` los que la función utiliza, justo bajo `x30`, y
+/// el epílogo los restaura. `x8` transporta direcciones de resultados
+/// indirectos y `x18` está reservado en algunas plataformas, por lo
+/// cual ninguno se reserva. `x29` (`fp`) tampoco se utiliza. `x16`
+/// (`ip0`) se utiliza para construir desplazamientos que no caben en un
+/// inmediato.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Reg(u8);
+
+impl Reg {
+    /// La AAPCS64 coloca los primeros ocho argumentos en `x0`-`x7`.
+    /// Los demás se colocan en stack, el primero de ellos en `[sp]`.
+    const MAX_ARGS: u32 = 8;
+
+    /// Registro auxiliar para desplazamientos grandes (`x16`).
+    const TEMP: Reg = Reg(16);
+
+    /// Secuencia de registros en los que se colocan los primeros argumentos.
+    fn argument_sequence() -> impl Iterator<Item = Reg> {
+        (0..=7).map(Reg)
+    }
+
+    /// Obtiene la forma de 32 bits de un registro.
+    fn as_word(self) -> String {
+        format!("w{}", self.0)
+    }
+}
+
+impl super::Register for Reg {
+    const RETURN: Self = Reg(0);
+    const FILE: &'static [Self] = &[
+        Reg(0),
+        Reg(1),
+        Reg(2),
+        Reg(3),
+        Reg(4),
+        Reg(5),
+        Reg(6),
+        Reg(7),
+        Reg(9),
+        Reg(10),
+        Reg(11),
+        Reg(12),
+        Reg(13),
+        Reg(14),
+        Reg(15),
+    ];
+    const CALLEE_SAVED: &'static [Self] = &[
+        Reg(19),
+        Reg(20),
+        Reg(21),
+        Reg(22),
+        Reg(23),
+        Reg(24),
+        Reg(25),
+        Reg(26),
+        Reg(27),
+        Reg(28),
+    ];
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "x{}", self.0)
+    }
+}
+
+/// Implementación de emisión de código para AArch64.
+pub struct Emitter<'a> {
+    cx: Context<'a, Self>,
+    regs: Allocations<'a, Self>,
+}
+
+/// Información de estado para cada frame.
+#[derive(Default)]
+pub struct FrameInfo {
+    /// Tamaño del frame, en valores.
+    size: u32,
+}
+
+impl<'a> super::Emitter<'a> for Emitter<'a> {
+    const VALUE_SIZE: u32 = VALUE_SIZE;
+    const PEEPHOLE: &'static [Pattern] = &[remove_self_move];
+
+    type Register = Reg;
+    type CallInfo = ();
+    type FrameInfo = FrameInfo;
+
+    fn new(cx: Context<'a, Self>, instructions: &[Located<Instruction>]) -> io::Result<Self> {
+        // Al igual que en Xtensa, se reserva espacio fijo para argumentos salientes
+        let max_call_spill = instructions
+            .iter()
+            .map(|instruction| match instruction.as_ref() {
+                Instruction::Call { arguments, .. } => {
+                    (arguments.len() as u32).max(Reg::MAX_ARGS) - Reg::MAX_ARGS
+                }
+
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0);
+
+        // "+ 1" debido a que se debe preservar lr (x30), junto a los registros callee-saved
+        let saved = cx.callee_saved().len() as u32;
+        let total_locals = cx.agnostic_locals() + 1 + saved + max_call_spill;
+
+        // La AAPCS64 exige alineamiento de 16 bytes (2 * 8 bytes) en sp
+        let size = total_locals + total_locals % 2;
+        let cx = cx.with_frame_info(FrameInfo { size });
+
+        let mut emitter = Emitter {
+            cx,
+            regs: Default::default(),
+        };
+
+        emitter.adjust_sp(-((size * VALUE_SIZE) as i32))?;
+
+        // Se preserva la dirección de retorno
+        let address = Self::stack_address(&emitter.cx, emitter.lr_offset())?;
+        emit!(emitter.cx, "str", "x30, {}", address)?;
+        emitter.transfer_saved("str")?;
+
+        // Se definen posiciones de argumentos en registros
+        emitter.bind_parameters(Reg::argument_sequence())?;
+
+        Ok(emitter)
+    }
+
+    fn cx_regs(&mut self) -> (&mut Context<'a, Self>, &mut Allocations<'a, Self>) {
+        (&mut self.cx, &mut self.regs)
+    }
+
+    fn frame_size(&self) -> u32 {
+        self.cx.frame_info().size * VALUE_SIZE
+    }
+
+    fn epilogue(&mut self) -> io::Result<()> {
+        let size = self.cx.frame_info().size;
+
+        self.transfer_saved("ldr")?;
+        let address = Self::stack_address(&self.cx, self.lr_offset())?;
+        emit!(self.cx, "ldr", "x30, {}", address)?;
+
+        self.adjust_sp((size * VALUE_SIZE) as i32)?;
+        emit!(self.cx, "ret")
+    }
+
+    fn jump_unconditional(&mut self, label: &str) -> io::Result<()> {
+        emit!(self.cx, "b", "{}", label)
+    }
+
+    fn jump_if_false(&mut self, reg: Reg, label: &str) -> io::Result<()> {
+        emit!(self.cx, "cbz", "{}, {}", reg.as_word(), label)
+    }
+
+    fn load_const(&mut self, value: i32, reg: Reg) -> io::Result<()> {
+        // El ensamblador sustituye mov por movz o movn
+        if (-0x10000..=0xffff).contains(&value) {
+            return emit!(self.cx, "mov", "{}, #{}", reg, value);
+        }
+
+        Self::move_immediate(&self.cx, value as u32, reg)?;
+        self.sign_extend(reg)
+    }
+
+    fn load_global(&mut self, global: &Global, reg: Reg) -> io::Result<()> {
+        let symbol = global.as_ref();
+        emit!(self.cx, "adrp", "{}, {}", reg, symbol)?;
+        emit!(self.cx, "ldr", "{0}, [{0}, :lo12:{1}]", reg, symbol)
+    }
+
+    fn load_address(&mut self, symbol: &str, reg: Reg) -> io::Result<()> {
+        emit!(self.cx, "adrp", "{}, {}", reg, symbol)?;
+        emit!(self.cx, "add", "{0}, {0}, :lo12:{1}", reg, symbol)
+    }
+
+    fn store_global(&mut self, reg: Reg, global: &Global) -> io::Result<()> {
+        let symbol = global.as_ref();
+        emit!(self.cx, "adrp", "{}, {}", Reg::TEMP, symbol)?;
+        emit!(self.cx, "str", "{}, [{}, :lo12:{}]", reg, Reg::TEMP, symbol)
+    }
+
+    fn not(&mut self, reg: Reg) -> io::Result<()> {
+        emit!(self.cx, "eor", "{0}, {0}, #1", reg)
+    }
+
+    fn negate(&mut self, reg: Reg) -> io::Result<()> {
+        emit!(self.cx, "neg", "{0}, {0}", reg.as_word())?;
+        self.sign_extend(reg)
+    }
+
+    fn binary(&mut self, lhs: Reg, op: BinOp, rhs: Reg) -> io::Result<()> {
+        match op {
+            BinOp::Arithmetic(op) => {
+                use ArithmeticOp::*;
+
+                let instruction = match op {
+                    Add => "add",
+                    Sub => "sub",
+                    Mul => "mul",
+                    Div => "sdiv",
+
+                    // a % b = a - (a / b) * b
+                    Mod => {
+                        let (lhs_word, rhs_word) = (lhs.as_word(), rhs.as_word());
+                        let temp = Reg::TEMP.as_word();
+
+                        emit!(self.cx, "sdiv", "{}, {}, {}", temp, lhs_word, rhs_word)?;
+                        emit!(
+                            self.cx,
+                            "msub",
+                            "{0}, {1}, {2}, {0}",
+                            lhs_word,
+                            temp,
+                            rhs_word
+                        )?;
+
+                        return self.sign_extend(lhs);
+                    }
+
+                    // Preservan la extensión de signo de ambos operandos
+                    BitAnd => return emit!(self.cx, "and", "{0}, {0}, {1}", lhs, rhs),
+                    BitOr => return emit!(self.cx, "orr", "{0}, {0}, {1}", lhs, rhs),
+                    BitXor => return emit!(self.cx, "eor", "{0}, {0}, {1}", lhs, rhs),
+
+                    // El conteo se toma módulo 32, al igual que en las demás arquitecturas
+                    ShiftLeft => "lsl",
+                    ShiftRight => "asr",
+                };
+
+                let (lhs_word, rhs_word) = (lhs.as_word(), rhs.as_word());
+                emit!(self.cx, instruction, "{0}, {0}, {1}", lhs_word, rhs_word)?;
+                self.sign_extend(lhs)
+            }
+
+            BinOp::Logic(op) => {
+                use LogicOp::*;
+
+                let condition = match op {
+                    Equal => "eq",
+                    NotEqual => "ne",
+                    Greater => "gt",
+                    GreaterOrEqual => "ge",
+                    Less => "lt",
+                    LessOrEqual => "le",
+                };
+
+                emit!(self.cx, "cmp", "{}, {}", lhs, rhs)?;
+                emit!(self.cx, "cset", "{}, {}", lhs, condition)
+            }
+        }
+    }
+
+    fn prepare_args(&mut self, arguments: &[Local]) -> io::Result<()> {
+        // Argumentos del noveno en adelante se colocan en stack
+        for (i, argument) in arguments.iter().skip(Reg::MAX_ARGS as usize).enumerate() {
+            let reg = self.read(*argument)?;
+            let address = Self::stack_address(&self.cx, i as u32 * VALUE_SIZE)?;
+
+            emit!(self.cx, "str", "{}, {}", reg, address)?;
+        }
+
+        // Los primeros ocho argumentos se colocan en registros específicos
+        for (argument, reg) in arguments.iter().zip(Reg::argument_sequence()) {
+            self.cx.read_into(&mut self.regs, reg, *argument)?;
+        }
+
+        Ok(())
+    }
+
+    fn call(&mut self, target: &Function, _call_info: ()) -> io::Result<()> {
+        match target {
+            // `x16` no transporta argumentos
+            Function::Indirect(local) => {
+                self.cx.copy_into(Reg::TEMP, *local)?;
+                emit!(self.cx, "blr", "{}", Reg::TEMP)
+            }
+
+            // El enlazador inserta un veneer si el destino está fuera de alcance
+            _ => emit!(self.cx, "bl", "{}", target.name().unwrap_or_default()),
+        }
+    }
+
+    fn reg_to_local(cx: &Context<'a, Self>, reg: Reg, local: Local) -> io::Result<()> {
+        let address = Self::stack_address(cx, Self::local_offset(cx, local))?;
+        emit!(cx, "str", "{}, {}", reg, address)
+    }
+
+    fn local_to_reg(cx: &Context<'a, Self>, local: Local, reg: Reg) -> io::Result<()> {
+        let address = Self::stack_address(cx, Self::local_offset(cx, local))?;
+        emit!(cx, "ldr", "{}, {}", reg, address)
+    }
+
+    fn reg_to_reg(cx: &Context<'a, Self>, source: Reg, target: Reg) -> io::Result<()> {
+        emit!(cx, "mov", "{}, {}", target, source)
+    }
+}
+
+impl<'a> Emitter<'a> {
+    /// Suma una constante a `sp`.
+    fn adjust_sp(&self, offset: i32) -> io::Result<()> {
+        let instruction = if offset < 0 { "sub" } else { "add" };
+
+        if offset.unsigned_abs() <= IMM_MAX {
+            emit!(self.cx, instruction, "sp, sp, #{}", offset.unsigned_abs())
+        } else {
+            Self::move_immediate(&self.cx, offset.unsigned_abs(), Reg::TEMP)?;
+            emit!(self.cx, instruction, "sp, sp, {}", Reg::TEMP)
+        }
+    }
+
+    /// Construye el operando de memoria para una posición relativa a `sp`.
+    ///
+    /// Si el desplazamiento no cabe en un inmediato, la dirección
+    /// se calcula primero en `x16`.
+    fn stack_address(cx: &Context<'a, Self>, offset: u32) -> io::Result<String> {
+        if offset <= OFFSET_MAX {
+            Ok(format!("[sp, #{}]", offset))
+        } else {
+            Self::move_immediate(cx, offset, Reg::TEMP)?;
+            emit!(cx, "add", "{0}, sp, {0}", Reg::TEMP)?;
+            Ok(format!("[{}]", Reg::TEMP))
+        }
+    }
+
+    /// Copia un inmediato de 32 bits a un registro, extendido con ceros.
+    fn move_immediate(cx: &Context<'a, Self>, value: u32, reg: Reg) -> io::Result<()> {
+        emit!(cx, "movz", "{}, #{}", reg.as_word(), value & 0xffff)?;
+
+        if value > 0xffff {
+            emit!(cx, "movk", "{}, #{}, lsl #16", reg.as_word(), value >> 16)?;
+        }
+
+        Ok(())
+    }
+
+    /// Extiende en signo los 32 bits inferiores de un registro.
+    fn sign_extend(&mut self, reg: Reg) -> io::Result<()> {
+        emit!(self.cx, "sxtw", "{}, {}", reg, reg.as_word())
+    }
+
+    /// Posición de la dirección de retorno en el frame.
+    fn lr_offset(&self) -> u32 {
+        (self.cx.frame_info().size - 1) * VALUE_SIZE
+    }
+
+    /// Guarda (`str`) o restaura (`ldr`) los registros callee-saved que
+    /// utiliza la función, los cuales se ubican justo bajo `lr`.
+    fn transfer_saved(&self, instruction: &str) -> io::Result<()> {
+        for (index, reg) in self.cx.callee_saved().iter().enumerate() {
+            let offset = self.lr_offset() - (index as u32 + 1) * VALUE_SIZE;
+            let address = Self::stack_address(&self.cx, offset)?;
+            emit!(self.cx, instruction, "{}, {}", reg, address)?;
+        }
+
+        Ok(())
+    }
+
+    /// Determina el desplazamiento de una local respecto a `sp`.
+    ///
+    /// El frame se dispone, de direcciones altas a bajas, como `lr`, los
+    /// registros callee-saved, las locales y finalmente el espacio para
+    /// argumentos salientes.
+    /// Los parámetros del noveno en adelante se encuentran sobre el
+    /// frame, donde los colocó quien invocó a la función.
+    fn local_offset(cx: &Context<'a, Self>, Local(local): Local) -> u32 {
+        let parameters = cx.function().parameters;
+        let size = cx.frame_info().size;
+        let saved = cx.callee_saved().len() as u32;
+        let on_stack = parameters.max(Reg::MAX_ARGS) - Reg::MAX_ARGS;
+
+        let value = if local < Reg::MAX_ARGS {
+            size - 2 - saved - local
+        } else if local < parameters {
+            size + local - Reg::MAX_ARGS
+        } else {
+            size - 2 - saved - (local - on_stack)
+        };
+
+        value * VALUE_SIZE
+    }
+}
+
+/// Elimina `mov xN, xN`.
+fn remove_self_move(lines: &mut Vec<String>, at: usize) -> bool {
+    peephole::remove_self_move(lines, at, "mov")
+}
@@ -0,0 +1,339 @@
+//! Implementación para ARM Cortex-M (Thumb-2, ARMv7E-M).
+//!
+//! # Manual de ISA
+//! <https://developer.arm.com/documentation/ddi0403/latest>
+//!
+//! La convención de llamada es la AAPCS:
+//! <https://github.com/ARM-software/abi-aa/blob/main/aapcs32/aapcs32.rst>
+
+use crate::{
+    codegen::{
+        peephole::{self, Pattern},
+        regs::Allocations,
+        Context,
+    },
+    ir::{ArithmeticOp, BinOp, Function, Global, Instruction, Local, LogicOp},
+    source::Located,
+};
+
+use std::{fmt, io};
+
+/// Esta es una arquitectura de 32 bits.
+const VALUE_SIZE: u32 = 4;
+
+/// Registro de procesador.
+///
+/// Existen 16 registros `r0`-`r15`, de los cuales `r13` (`sp`),
+/// `r14` (`lr`) y `r15` (`pc`) tienen usos especiales. Los
+/// temporales se asignan en `r0`-`r3` y `r12`, caller-saved según la
+/// AAPCS. `r4`-`r11` son callee-saved y alojan a las locales que
+/// sobreviven llamadas, ver [`super::Register::CALLEE_SAVED`]. Solo
+/// los que utiliza la función se guardan en el prólogo, junto a `lr`,
+/// y se restauran en el epílogo, ver `Emitter::transfer_saved()`.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Reg(u8);
+
+impl Reg {
+    /// La AAPCS coloca los primeros cuatro argumentos en `r0`-`r3`.
+    /// Los demás se colocan en stack en orden inverso.
+    const MAX_ARGS: u32 = 4;
+
+    /// Secuencia de registros en los que se colocan los primeros argumentos.
+    fn argument_sequence() -> impl Iterator<Item = Reg> {
+        (0..=3).map(Reg)
+    }
+}
+
+impl super::Register for Reg {
+    const RETURN: Self = Reg(0);
+    const FILE: &'static [Self] = &[Reg(0), Reg(1), Reg(2), Reg(3), Reg(12)];
+    const CALLEE_SAVED: &'static [Self] = &[
+        Reg(4),
+        Reg(5),
+        Reg(6),
+        Reg(7),
+        Reg(8),
+        Reg(9),
+        Reg(10),
+        Reg(11),
+    ];
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Reg(number) = self;
+        write!(formatter, "r{}", number)
+    }
+}
+
+/// Implementación de emisión de código para ARM.
+pub struct Emitter<'a> {
+    cx: Context<'a, Self>,
+    regs: Allocations<'a, Self>,
+}
+
+/// Información de estado para cada frame.
+#[derive(Default)]
+pub struct FrameInfo {
+    /// Tamaño del frame, en palabras.
+    size: u32,
+}
+
+impl<'a> super::Emitter<'a> for Emitter<'a> {
+    const VALUE_SIZE: u32 = VALUE_SIZE;
+    const PEEPHOLE: &'static [Pattern] = &[remove_self_move];
+
+    const HEADER: &'static [&'static str] = &[".syntax unified", ".cpu cortex-m4", ".thumb"];
+    const FUNCTION_HEADER: &'static [&'static str] = &[".thumb_func"];
+
+    type Register = Reg;
+    type CallInfo = ();
+    type FrameInfo = FrameInfo;
+
+    fn new(cx: Context<'a, Self>, instructions: &[Located<Instruction>]) -> io::Result<Self> {
+        // Al igual que en Xtensa, se reserva espacio fijo para argumentos salientes
+        let max_call_spill = instructions
+            .iter()
+            .map(|instruction| match instruction.as_ref() {
+                Instruction::Call { arguments, .. } => {
+                    (arguments.len() as u32).max(Reg::MAX_ARGS) - Reg::MAX_ARGS
+                }
+
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0);
+
+        // "+ 1" debido a que se debe preservar lr, junto a los registros callee-saved
+        let saved = cx.callee_saved().len() as u32;
+        let total_locals = cx.agnostic_locals() + 1 + saved + max_call_spill;
+
+        // La AAPCS exige alineamiento de 8 bytes (2 * 4 bytes) en llamadas
+        let size = total_locals + total_locals % 2;
+        let cx = cx.with_frame_info(FrameInfo { size });
+
+        let mut emitter = Emitter {
+            cx,
+            regs: Default::default(),
+        };
+
+        emit!(emitter.cx, "sub", "sp, sp, #{}", size * VALUE_SIZE)?;
+
+        // Se preserva la dirección de retorno
+        emit!(emitter.cx, "str", "lr, [sp, #{}]", emitter.lr_offset())?;
+        emitter.transfer_saved("str")?;
+
+        // Se definen posiciones de argumentos en registros
+        emitter.bind_parameters(Reg::argument_sequence())?;
+
+        Ok(emitter)
+    }
+
+    fn cx_regs(&mut self) -> (&mut Context<'a, Self>, &mut Allocations<'a, Self>) {
+        (&mut self.cx, &mut self.regs)
+    }
+
+    fn frame_size(&self) -> u32 {
+        self.cx.frame_info().size * VALUE_SIZE
+    }
+
+    fn epilogue(&mut self) -> io::Result<()> {
+        let size = self.cx.frame_info().size;
+
+        self.transfer_saved("ldr")?;
+        emit!(self.cx, "ldr", "lr, [sp, #{}]", self.lr_offset())?;
+        emit!(self.cx, "add", "sp, sp, #{}", size * VALUE_SIZE)?;
+        emit!(self.cx, "bx", "lr")
+    }
+
+    fn jump_unconditional(&mut self, label: &str) -> io::Result<()> {
+        emit!(self.cx, "b", "{}", label)
+    }
+
+    fn jump_if_false(&mut self, reg: Reg, label: &str) -> io::Result<()> {
+        // cbz no alcanza etiquetas previas ni lejanas
+        emit!(self.cx, "cmp", "{}, #0", reg)?;
+        emit!(self.cx, "beq", "{}", label)
+    }
+
+    fn load_const(&mut self, value: i32, reg: Reg) -> io::Result<()> {
+        let value = value as u32;
+        emit!(self.cx, "movw", "{}, #{}", reg, value & 0xffff)?;
+
+        if value > 0xffff {
+            emit!(self.cx, "movt", "{}, #{}", reg, value >> 16)?;
+        }
+
+        Ok(())
+    }
+
+    fn load_global(&mut self, global: &Global, reg: Reg) -> io::Result<()> {
+        self.move_address(global.as_ref(), reg)?;
+        emit!(self.cx, "ldr", "{0}, [{0}]", reg)
+    }
+
+    fn load_address(&mut self, symbol: &str, reg: Reg) -> io::Result<()> {
+        self.move_address(symbol, reg)
+    }
+
+    fn store_global(&mut self, reg: Reg, global: &Global) -> io::Result<()> {
+        let scratch = self.cx.scratch(&mut self.regs, &[reg])?;
+        self.move_address(global.as_ref(), scratch)?;
+        emit!(self.cx, "str", "{}, [{}]", reg, scratch)
+    }
+
+    fn not(&mut self, reg: Reg) -> io::Result<()> {
+        emit!(self.cx, "eor", "{0}, {0}, #1", reg)
+    }
+
+    fn negate(&mut self, reg: Reg) -> io::Result<()> {
+        emit!(self.cx, "rsb", "{0}, {0}, #0", reg)
+    }
+
+    fn binary(&mut self, lhs: Reg, op: BinOp, rhs: Reg) -> io::Result<()> {
+        match op {
+            BinOp::Arithmetic(op) => {
+                use ArithmeticOp::*;
+
+                let instruction = match op {
+                    Add => "add",
+                    Sub => "sub",
+                    Mul => "mul",
+                    Div => "sdiv",
+
+                    // a % b = a - (a / b) * b
+                    Mod => {
+                        let scratch = self.cx.scratch(&mut self.regs, &[lhs, rhs])?;
+                        emit!(self.cx, "sdiv", "{}, {}, {}", scratch, lhs, rhs)?;
+                        return emit!(self.cx, "mls", "{0}, {1}, {2}, {0}", lhs, scratch, rhs);
+                    }
+
+                    BitAnd => "and",
+                    BitOr => "orr",
+                    BitXor => "eor",
+                    ShiftLeft => "lsl",
+                    ShiftRight => "asr",
+                };
+
+                emit!(self.cx, instruction, "{0}, {0}, {1}", lhs, rhs)
+            }
+
+            BinOp::Logic(op) => {
+                use LogicOp::*;
+
+                // Condición que hace verdadera a la comparación y su negación
+                let (condition, otherwise) = match op {
+                    Equal => ("eq", "ne"),
+                    NotEqual => ("ne", "eq"),
+                    Greater => ("gt", "le"),
+                    GreaterOrEqual => ("ge", "lt"),
+                    Less => ("lt", "ge"),
+                    LessOrEqual => ("le", "gt"),
+                };
+
+                emit!(self.cx, "cmp", "{}, {}", lhs, rhs)?;
+                emit!(self.cx, "ite", "{}", condition)?;
+
+                let (set, clear) = (format!("mov{}", condition), format!("mov{}", otherwise));
+                emit!(self.cx, set, "{}, #1", lhs)?;
+                emit!(self.cx, clear, "{}, #0", lhs)
+            }
+        }
+    }
+
+    fn prepare_args(&mut self, arguments: &[Local]) -> io::Result<()> {
+        // Argumentos del quinto en adelante se colocan en stack en orden inverso
+        for (i, argument) in arguments.iter().skip(Reg::MAX_ARGS as usize).enumerate() {
+            let reg = self.read(*argument)?;
+            let offset = i as u32 * VALUE_SIZE;
+
+            emit!(self.cx, "str", "{}, [sp, #{}]", reg, offset)?;
+        }
+
+        // Los primeros cuatro argumentos se colocan en registros específicos
+        for (argument, reg) in arguments.iter().zip(Reg::argument_sequence()) {
+            self.cx.read_into(&mut self.regs, reg, *argument)?;
+        }
+
+        Ok(())
+    }
+
+    fn call(&mut self, target: &Function, _call_info: ()) -> io::Result<()> {
+        match target {
+            // `r12` (`ip`) no transporta argumentos
+            Function::Indirect(local) => {
+                self.cx.copy_into(Reg(12), *local)?;
+                emit!(self.cx, "blx", "{}", Reg(12))
+            }
+
+            _ => emit!(self.cx, "bl", "{}", target.name().unwrap_or_default()),
+        }
+    }
+
+    fn reg_to_local(cx: &Context<'a, Self>, reg: Reg, local: Local) -> io::Result<()> {
+        let offset = Self::local_offset(cx, local);
+        emit!(cx, "str", "{}, [sp, #{}]", reg, offset)
+    }
+
+    fn local_to_reg(cx: &Context<'a, Self>, local: Local, reg: Reg) -> io::Result<()> {
+        let offset = Self::local_offset(cx, local);
+        emit!(cx, "ldr", "{}, [sp, #{}]", reg, offset)
+    }
+
+    fn reg_to_reg(cx: &Context<'a, Self>, source: Reg, target: Reg) -> io::Result<()> {
+        emit!(cx, "mov", "{}, {}", target, source)
+    }
+}
+
+impl<'a> Emitter<'a> {
+    /// Carga la dirección de un símbolo en un registro.
+    fn move_address(&self, symbol: &str, reg: Reg) -> io::Result<()> {
+        emit!(self.cx, "movw", "{}, #:lower16:{}", reg, symbol)?;
+        emit!(self.cx, "movt", "{}, #:upper16:{}", reg, symbol)
+    }
+
+    /// Posición de la dirección de retorno en el frame.
+    fn lr_offset(&self) -> u32 {
+        (self.cx.frame_info().size - 1) * VALUE_SIZE
+    }
+
+    /// Guarda (`str`) o restaura (`ldr`) los registros callee-saved que
+    /// utiliza la función, los cuales se ubican justo bajo `lr`.
+    fn transfer_saved(&self, instruction: &str) -> io::Result<()> {
+        for (index, reg) in self.cx.callee_saved().iter().enumerate() {
+            let offset = self.lr_offset() - (index as u32 + 1) * VALUE_SIZE;
+            emit!(self.cx, instruction, "{}, [sp, #{}]", reg, offset)?;
+        }
+
+        Ok(())
+    }
+
+    /// Determina el desplazamiento de una local respecto a `sp`.
+    ///
+    /// El frame se dispone, de direcciones altas a bajas, como `lr`, los
+    /// registros callee-saved, las locales y finalmente el espacio para
+    /// argumentos salientes.
+    /// Los parámetros del quinto en adelante se encuentran sobre el
+    /// frame, donde los colocó quien invocó a la función.
+    fn local_offset(cx: &Context<'a, Self>, Local(local): Local) -> u32 {
+        let parameters = cx.function().parameters;
+        let size = cx.frame_info().size;
+        let saved = cx.callee_saved().len() as u32;
+        let on_stack = parameters.max(Reg::MAX_ARGS) - Reg::MAX_ARGS;
+
+        let word = if local < Reg::MAX_ARGS {
+            size - 2 - saved - local
+        } else if local < parameters {
+            size + local - Reg::MAX_ARGS
+        } else {
+            size - 2 - saved - (local - on_stack)
+        };
+
+        word * VALUE_SIZE
+    }
+}
+
+/// Elimina `mov rN, rN`.
+fn remove_self_move(lines: &mut Vec<String>, at: usize) -> bool {
+    peephole::remove_self_move(lines, at, "mov")
+}
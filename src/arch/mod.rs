@@ -4,26 +4,45 @@
 //! y de parámetros de arquitectura que son implementadas
 //! por sus propios submódulos. En general, debe utilizarse
 //! la macro `dispatch_arch!()` para acceder a estas
-//! implementaciones.
+//! implementaciones. La excepción es WebAssembly, cuyo
+//! emisor es independiente, ver [`wasm32`].
 
 use crate::{
-    codegen::{regs::Allocations, Context},
+    codegen::{peephole::Pattern, regs::Allocations, Context},
     ir::{BinOp, Function, Global, Instruction, Local},
+    source::Located,
 };
 
 use std::io;
 
 /// Arquitectura de procesador (ISA).
-#[derive(Copy, Clone)]
+///
+/// En x86-64 se distingue además la convención de llamada, ya que de
+/// ella depende el código que se emite: `X86_64` sigue System V y
+/// `Win64` la de Windows.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Arch {
     X86_64,
+    Win64,
+    Aarch64,
     Xtensa,
+    Arm,
+    RiscV,
+    Wasm32,
 }
 
+pub mod wasm32;
+
+mod aarch64;
+mod arm;
+mod riscv;
 mod x86_64;
 mod xtensa;
 
-pub use x86_64::Emitter as X86_64;
+pub use aarch64::Emitter as Aarch64;
+pub use arm::Emitter as Arm;
+pub use riscv::Emitter as RiscV;
+pub use x86_64::{SysVEmitter as X86_64, Win64Emitter as Win64};
 pub use xtensa::Emitter as Xtensa;
 
 /// Emisión de código ensamblador para una función.
@@ -33,8 +52,24 @@ pub use xtensa::Emitter as Xtensa;
 /// la arquitectura objetivo.
 pub trait Emitter<'a>: Sized {
     /// Tamaño natural de un valor no tipado, en bytes.
+    ///
+    /// Un valor es un puntero o bien un entero de 32 bits, aún si
+    /// `VALUE_SIZE` es mayor. En ese caso los enteros se mantienen
+    /// extendidos en signo a todo el registro, de forma que `libruntime`
+    /// los reciba como `isize` válidos y que las comparaciones no
+    /// dependan de bits superiores. Toda operación que pueda producir
+    /// un resultado fuera de 32 bits debe truncarlo y volver a extenderlo.
     const VALUE_SIZE: u32;
 
+    /// Patrones de optimización de mirilla para esta arquitectura.
+    const PEEPHOLE: &'static [Pattern];
+
+    /// Directivas que preceden a todo el código emitido.
+    const HEADER: &'static [&'static str] = &[];
+
+    /// Directivas que preceden a la etiqueta de cada función.
+    const FUNCTION_HEADER: &'static [&'static str] = &[];
+
     /// TIpo de registro.
     type Register: Register;
 
@@ -49,10 +84,17 @@ pub trait Emitter<'a>: Sized {
     ///
     /// AdeMás de construirse, el prólogo de la función debe emitirse
     /// aquí, ajustando la pila y creando un stack frame.
-    fn new(cx: Context<'a, Self>, instructions: &[Instruction]) -> io::Result<Self>;
+    fn new(cx: Context<'a, Self>, instructions: &[Located<Instruction>]) -> io::Result<Self>;
 
-    /// Emite el epílogo de la función, terminando su listado de código.
-    fn epilogue(self) -> io::Result<()>;
+    /// Bytes de pila que ocupa el marco de la función, incluyendo la
+    /// dirección de retorno. Solo es válido luego de [`Emitter::new()`].
+    fn frame_size(&self) -> u32;
+
+    /// Emite el epílogo de la función, retornando a quien la invocó.
+    ///
+    /// Esto puede ocurrir más de una vez por función si existen
+    /// retornos anticipados.
+    fn epilogue(&mut self) -> io::Result<()>;
 
     /// Obtiene el contexto de emisión y el estado de reservación
     /// de registros.
@@ -73,6 +115,9 @@ pub trait Emitter<'a>: Sized {
     /// Copiar los contenidos de una variable global a un registro.
     fn load_global(&mut self, global: &Global, reg: Self::Register) -> io::Result<()>;
 
+    /// Copiar la dirección de un símbolo o etiqueta a un registro.
+    fn load_address(&mut self, symbol: &str, reg: Self::Register) -> io::Result<()>;
+
     /// Copiar los contenidos de un registro a una vriable global.
     fn store_global(&mut self, reg: Self::Register, global: &Global) -> io::Result<()>;
 
@@ -138,9 +183,31 @@ pub trait Emitter<'a>: Sized {
     }
 
     /// Véase [Context::assert_dirty()]
-    fn assert_dirty(&mut self, reg: Self::Register, local: Local) {
+    fn assert_dirty(&mut self, reg: Self::Register, local: Local) -> io::Result<()> {
         let (cx, regs) = self.cx_regs();
-        cx.assert_dirty(regs, reg, local);
+        cx.assert_dirty(regs, reg, local)
+    }
+
+    /// Asocia los parámetros de la función a los registros en los que
+    /// se reciben, en orden. Los parámetros que se reciben en stack y
+    /// tienen un registro callee-saved asignado se cargan a este, por lo
+    /// cual los registros callee-saved ya deben haberse guardado.
+    fn bind_parameters<I>(&mut self, registers: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = Self::Register>,
+    {
+        let (cx, regs) = self.cx_regs();
+        let mut registers = registers.into_iter();
+
+        for local in (0..cx.function().parameters).map(Local) {
+            match (registers.next(), cx.home(local)) {
+                (Some(reg), _) => cx.assert_dirty(regs, reg, local)?,
+                (None, Some(home)) => Self::local_to_reg(cx, local, home)?,
+                (None, None) => (),
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -151,4 +218,10 @@ pub trait Register: 'static + Copy + PartialEq + Eq {
 
     /// Registros disponsibles para reservación.
     const FILE: &'static [Self];
+
+    /// Registros que quien es llamado debe preservar, ajenos a `FILE`.
+    /// Cada uno se asigna durante toda una función a una local que
+    /// sobrevive llamadas, ver [`Context::home()`], y solo se guarda en
+    /// el marco de las funciones que lo utilizan.
+    const CALLEE_SAVED: &'static [Self] = &[];
 }
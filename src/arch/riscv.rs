@@ -0,0 +1,392 @@
+//! Implementación para RISC-V (RV32IMC).
+//!
+//! # Manual de ISA
+//! <https://riscv.org/technical/specifications/>
+//!
+//! La convención de llamada es la ABI `ilp32`:
+//! <https://github.com/riscv-non-isa/riscv-elf-psabi-doc/blob/master/riscv-cc.adoc>
+
+use crate::{
+    codegen::{
+        peephole::{self, Pattern},
+        regs::Allocations,
+        Context,
+    },
+    ir::{ArithmeticOp, BinOp, Function, Global, Instruction, Local, LogicOp},
+    source::Located,
+};
+
+use std::{fmt, io};
+
+/// Esta es una arquitectura de 32 bits.
+const VALUE_SIZE: u32 = 4;
+
+/// Rango de inmediatos de 12 bits con signo en `addi`, `lw` y `sw`.
+const IMM_MAX: u32 = 2047;
+
+/// Registro de procesador.
+///
+/// Existen 32 registros `x0`-`x31`, que se nombran según su función
+/// en la ABI. Los temporales se asignan en `a0`-`a7` y `t0`-`t5`,
+/// que la ABI define como caller-saved. Las locales que sobreviven
+/// llamadas se asignan a `s1`-`s11`, que son callee-saved, ver
+/// [`super::Register::CALLEE_SAVED`]. `transfer_saved()` los guarda
+/// bajo `ra` en el prólogo y los restaura en el epílogo, pero solo si
+/// la función los utiliza. `s0` (`fp`) no se utiliza. `t6` no se
+/// reserva, ya que se utiliza para construir desplazamientos que no
+/// caben en un inmediato.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Reg(u8);
+
+impl Reg {
+    /// La ABI coloca los primeros ocho argumentos en `a0`-`a7`.
+    /// Los demás se colocan en stack, el primero de ellos en `0(sp)`.
+    const MAX_ARGS: u32 = 8;
+
+    /// Registro auxiliar para desplazamientos grandes (`t6`).
+    const TEMP: Reg = Reg(31);
+
+    /// Secuencia de registros en los que se colocan los primeros argumentos.
+    fn argument_sequence() -> impl Iterator<Item = Reg> {
+        (10..=17).map(Reg)
+    }
+}
+
+impl super::Register for Reg {
+    const RETURN: Self = Reg(10);
+    const FILE: &'static [Self] = &[
+        Reg(10),
+        Reg(11),
+        Reg(12),
+        Reg(13),
+        Reg(14),
+        Reg(15),
+        Reg(16),
+        Reg(17),
+        Reg(5),
+        Reg(6),
+        Reg(7),
+        Reg(28),
+        Reg(29),
+        Reg(30),
+    ];
+    const CALLEE_SAVED: &'static [Self] = &[
+        Reg(9),
+        Reg(18),
+        Reg(19),
+        Reg(20),
+        Reg(21),
+        Reg(22),
+        Reg(23),
+        Reg(24),
+        Reg(25),
+        Reg(26),
+        Reg(27),
+    ];
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            1 => write!(formatter, "ra"),
+            2 => write!(formatter, "sp"),
+            number @ 5..=7 => write!(formatter, "t{}", number - 5),
+            9 => write!(formatter, "s1"),
+            number @ 10..=17 => write!(formatter, "a{}", number - 10),
+            number @ 18..=27 => write!(formatter, "s{}", number - 16),
+            number @ 28..=31 => write!(formatter, "t{}", number - 25),
+            number => write!(formatter, "x{}", number),
+        }
+    }
+}
+
+/// Implementación de emisión de código para RISC-V.
+pub struct Emitter<'a> {
+    cx: Context<'a, Self>,
+    regs: Allocations<'a, Self>,
+}
+
+/// Información de estado para cada frame.
+#[derive(Default)]
+pub struct FrameInfo {
+    /// Tamaño del frame, en palabras.
+    size: u32,
+}
+
+impl<'a> super::Emitter<'a> for Emitter<'a> {
+    const VALUE_SIZE: u32 = VALUE_SIZE;
+    const PEEPHOLE: &'static [Pattern] = &[remove_self_move];
+
+    type Register = Reg;
+    type CallInfo = ();
+    type FrameInfo = FrameInfo;
+
+    fn new(cx: Context<'a, Self>, instructions: &[Located<Instruction>]) -> io::Result<Self> {
+        // Al igual que en Xtensa, se reserva espacio fijo para argumentos salientes
+        let max_call_spill = instructions
+            .iter()
+            .map(|instruction| match instruction.as_ref() {
+                Instruction::Call { arguments, .. } => {
+                    (arguments.len() as u32).max(Reg::MAX_ARGS) - Reg::MAX_ARGS
+                }
+
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0);
+
+        // "+ 1" debido a que se debe preservar ra, junto a los registros callee-saved
+        let saved = cx.callee_saved().len() as u32;
+        let total_locals = cx.agnostic_locals() + 1 + saved + max_call_spill;
+
+        // La ABI exige alineamiento de 16 bytes (4 * 4 bytes) en sp
+        let size = (total_locals + 3) / 4 * 4;
+        let cx = cx.with_frame_info(FrameInfo { size });
+
+        let mut emitter = Emitter {
+            cx,
+            regs: Default::default(),
+        };
+
+        emitter.adjust_sp(-((size * VALUE_SIZE) as i32))?;
+
+        // Se preserva la dirección de retorno
+        let address = Self::stack_address(&emitter.cx, emitter.ra_offset())?;
+        emit!(emitter.cx, "sw", "ra, {}", address)?;
+        emitter.transfer_saved("sw")?;
+
+        // Se definen posiciones de argumentos en registros
+        emitter.bind_parameters(Reg::argument_sequence())?;
+
+        Ok(emitter)
+    }
+
+    fn cx_regs(&mut self) -> (&mut Context<'a, Self>, &mut Allocations<'a, Self>) {
+        (&mut self.cx, &mut self.regs)
+    }
+
+    fn frame_size(&self) -> u32 {
+        self.cx.frame_info().size * VALUE_SIZE
+    }
+
+    fn epilogue(&mut self) -> io::Result<()> {
+        let size = self.cx.frame_info().size;
+
+        self.transfer_saved("lw")?;
+        let address = Self::stack_address(&self.cx, self.ra_offset())?;
+        emit!(self.cx, "lw", "ra, {}", address)?;
+
+        self.adjust_sp((size * VALUE_SIZE) as i32)?;
+        emit!(self.cx, "ret")
+    }
+
+    fn jump_unconditional(&mut self, label: &str) -> io::Result<()> {
+        emit!(self.cx, "j", "{}", label)
+    }
+
+    fn jump_if_false(&mut self, reg: Reg, label: &str) -> io::Result<()> {
+        // El ensamblador reemplaza por bnez + j si la etiqueta está lejos
+        emit!(self.cx, "beqz", "{}, {}", reg, label)
+    }
+
+    fn load_const(&mut self, value: i32, reg: Reg) -> io::Result<()> {
+        // Pseudoinstrucción que se expande a lui + addi si es necesario
+        emit!(self.cx, "li", "{}, {}", reg, value)
+    }
+
+    fn load_global(&mut self, global: &Global, reg: Reg) -> io::Result<()> {
+        emit!(self.cx, "lui", "{}, %hi({})", reg, global.as_ref())?;
+        emit!(self.cx, "lw", "{0}, %lo({1})({0})", reg, global.as_ref())
+    }
+
+    fn load_address(&mut self, symbol: &str, reg: Reg) -> io::Result<()> {
+        emit!(self.cx, "lui", "{}, %hi({})", reg, symbol)?;
+        emit!(self.cx, "addi", "{0}, {0}, %lo({1})", reg, symbol)
+    }
+
+    fn store_global(&mut self, reg: Reg, global: &Global) -> io::Result<()> {
+        let scratch = self.cx.scratch(&mut self.regs, &[reg])?;
+        emit!(self.cx, "lui", "{}, %hi({})", scratch, global.as_ref())?;
+        emit!(
+            self.cx,
+            "sw",
+            "{}, %lo({})({})",
+            reg,
+            global.as_ref(),
+            scratch
+        )
+    }
+
+    fn not(&mut self, reg: Reg) -> io::Result<()> {
+        emit!(self.cx, "xori", "{0}, {0}, 1", reg)
+    }
+
+    fn negate(&mut self, reg: Reg) -> io::Result<()> {
+        emit!(self.cx, "neg", "{0}, {0}", reg)
+    }
+
+    fn binary(&mut self, lhs: Reg, op: BinOp, rhs: Reg) -> io::Result<()> {
+        match op {
+            BinOp::Arithmetic(op) => {
+                use ArithmeticOp::*;
+
+                let instruction = match op {
+                    Add => "add",
+                    Sub => "sub",
+                    Mul => "mul",
+                    Div => "div",
+                    Mod => "rem",
+                    BitAnd => "and",
+                    BitOr => "or",
+                    BitXor => "xor",
+                    ShiftLeft => "sll",
+                    ShiftRight => "sra",
+                };
+
+                emit!(self.cx, instruction, "{0}, {0}, {1}", lhs, rhs)
+            }
+
+            BinOp::Logic(op) => {
+                use LogicOp::*;
+
+                // Solo existe slt, por lo cual las demás se derivan de esta o de
+                // la diferencia. `a <= b` es equivalente a `!(b < a)`.
+                match op {
+                    Equal | NotEqual => {
+                        let test = if let Equal = op { "seqz" } else { "snez" };
+                        emit!(self.cx, "sub", "{0}, {0}, {1}", lhs, rhs)?;
+                        emit!(self.cx, test, "{0}, {0}", lhs)
+                    }
+
+                    Less => emit!(self.cx, "slt", "{0}, {0}, {1}", lhs, rhs),
+                    Greater => emit!(self.cx, "slt", "{0}, {1}, {0}", lhs, rhs),
+
+                    GreaterOrEqual => {
+                        emit!(self.cx, "slt", "{0}, {0}, {1}", lhs, rhs)?;
+                        self.not(lhs)
+                    }
+
+                    LessOrEqual => {
+                        emit!(self.cx, "slt", "{0}, {1}, {0}", lhs, rhs)?;
+                        self.not(lhs)
+                    }
+                }
+            }
+        }
+    }
+
+    fn prepare_args(&mut self, arguments: &[Local]) -> io::Result<()> {
+        // Argumentos del noveno en adelante se colocan en stack
+        for (i, argument) in arguments.iter().skip(Reg::MAX_ARGS as usize).enumerate() {
+            let reg = self.read(*argument)?;
+            let address = Self::stack_address(&self.cx, i as u32 * VALUE_SIZE)?;
+
+            emit!(self.cx, "sw", "{}, {}", reg, address)?;
+        }
+
+        // Los primeros ocho argumentos se colocan en registros específicos
+        for (argument, reg) in arguments.iter().zip(Reg::argument_sequence()) {
+            self.cx.read_into(&mut self.regs, reg, *argument)?;
+        }
+
+        Ok(())
+    }
+
+    fn call(&mut self, target: &Function, _call_info: ()) -> io::Result<()> {
+        match target {
+            Function::Indirect(local) => {
+                self.cx.copy_into(Reg::TEMP, *local)?;
+                emit!(self.cx, "jalr", "{}", Reg::TEMP)
+            }
+
+            // auipc + jalr, que el enlazador relaja a jal si es posible
+            _ => emit!(self.cx, "call", "{}", target.name().unwrap_or_default()),
+        }
+    }
+
+    fn reg_to_local(cx: &Context<'a, Self>, reg: Reg, local: Local) -> io::Result<()> {
+        let address = Self::stack_address(cx, Self::local_offset(cx, local))?;
+        emit!(cx, "sw", "{}, {}", reg, address)
+    }
+
+    fn local_to_reg(cx: &Context<'a, Self>, local: Local, reg: Reg) -> io::Result<()> {
+        let address = Self::stack_address(cx, Self::local_offset(cx, local))?;
+        emit!(cx, "lw", "{}, {}", reg, address)
+    }
+
+    fn reg_to_reg(cx: &Context<'a, Self>, source: Reg, target: Reg) -> io::Result<()> {
+        emit!(cx, "mv", "{}, {}", target, source)
+    }
+}
+
+impl<'a> Emitter<'a> {
+    /// Suma una constante a `sp`.
+    fn adjust_sp(&self, offset: i32) -> io::Result<()> {
+        if offset.unsigned_abs() <= IMM_MAX {
+            emit!(self.cx, "addi", "sp, sp, {}", offset)
+        } else {
+            emit!(self.cx, "li", "{}, {}", Reg::TEMP, offset)?;
+            emit!(self.cx, "add", "sp, sp, {}", Reg::TEMP)
+        }
+    }
+
+    /// Construye el operando de memoria para una posición relativa a `sp`.
+    ///
+    /// Si el desplazamiento no cabe en un inmediato, la dirección
+    /// se calcula primero en `t6`.
+    fn stack_address(cx: &Context<'a, Self>, offset: u32) -> io::Result<String> {
+        if offset <= IMM_MAX {
+            Ok(format!("{}(sp)", offset))
+        } else {
+            emit!(cx, "li", "{}, {}", Reg::TEMP, offset)?;
+            emit!(cx, "add", "{0}, {0}, sp", Reg::TEMP)?;
+            Ok(format!("0({})", Reg::TEMP))
+        }
+    }
+
+    /// Posición de la dirección de retorno en el frame.
+    fn ra_offset(&self) -> u32 {
+        (self.cx.frame_info().size - 1) * VALUE_SIZE
+    }
+
+    /// Guarda (`sw`) o restaura (`lw`) los registros callee-saved que
+    /// utiliza la función, los cuales se ubican justo bajo `ra`.
+    fn transfer_saved(&self, instruction: &str) -> io::Result<()> {
+        for (index, reg) in self.cx.callee_saved().iter().enumerate() {
+            let offset = self.ra_offset() - (index as u32 + 1) * VALUE_SIZE;
+            let address = Self::stack_address(&self.cx, offset)?;
+            emit!(self.cx, instruction, "{}, {}", reg, address)?;
+        }
+
+        Ok(())
+    }
+
+    /// Determina el desplazamiento de una local respecto a `sp`.
+    ///
+    /// El frame se dispone, de direcciones altas a bajas, como `ra`, los
+    /// registros callee-saved, las locales y finalmente el espacio para
+    /// argumentos salientes.
+    /// Los parámetros del noveno en adelante se encuentran sobre el
+    /// frame, donde los colocó quien invocó a la función.
+    fn local_offset(cx: &Context<'a, Self>, Local(local): Local) -> u32 {
+        let parameters = cx.function().parameters;
+        let size = cx.frame_info().size;
+        let saved = cx.callee_saved().len() as u32;
+        let on_stack = parameters.max(Reg::MAX_ARGS) - Reg::MAX_ARGS;
+
+        let word = if local < Reg::MAX_ARGS {
+            size - 2 - saved - local
+        } else if local < parameters {
+            size + local - Reg::MAX_ARGS
+        } else {
+            size - 2 - saved - (local - on_stack)
+        };
+
+        word * VALUE_SIZE
+    }
+}
+
+/// Elimina `mv rN, rN`.
+fn remove_self_move(lines: &mut Vec<String>, at: usize) -> bool {
+    peephole::remove_self_move(lines, at, "mv")
+}
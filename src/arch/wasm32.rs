@@ -0,0 +1,480 @@
+//! Implementación para WebAssembly (wasm32).
+//!
+//! # Especificación
+//! <https://webassembly.github.io/spec/core/text/index.html>
+//!
+//! A diferencia de las demás arquitecturas, aquí no se implementa
+//! [`super::Emitter`]. WebAssembly es una máquina de pila con una
+//! cantidad arbitraria de locales, por lo cual cada local IR se
+//! traduce directamente a una local de WebAssembly y no existe
+//! reservación de registros. El resultado es un módulo en formato
+//! de texto (WAT), que [`crate::link`] ensambla a binario.
+//!
+//! # Flujo de control
+//! WebAssembly solo permite flujo de control estructurado, mientras
+//! que el IR salta libremente entre etiquetas. Cada etiqueta inicia
+//! un bloque básico y el cuerpo de una función se dispone como un
+//! despachador:
+//!
+//! ```text
+//! loop $dispatch
+//!   block $b2
+//!     block $b1
+//!       block $b0
+//!         local.get $pc
+//!         br_table $b0 $b1 $b2
+//!       end
+//!       ;; bloque 0 (entrada de la función)
+//!     end
+//!     ;; bloque 1
+//!   end
+//!   ;; bloque 2
+//! end
+//! ```
+//!
+//! Un salto asigna el número de bloque destino a `$pc` y reinicia el
+//! ciclo. Los bloques caen naturalmente en el siguiente, tal como las
+//! instrucciones que siguen a una etiqueta.
+//!
+//! # Enlazado
+//! El módulo importa los builtins de un módulo `runtime`, que es
+//! `libruntime` compilado para `wasm32-unknown-unknown`. Las globales
+//! de `libruntime` (como `builtin_line`) se importan como la dirección
+//! de la variable dentro de la memoria del runtime, que también se
+//! importa. El punto de entrada `user_main` se exporta para que el
+//! entorno JavaScript lo invoque.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Write},
+};
+
+use crate::ir::{
+    ArithmeticOp, BinOp, Function, GeneratedFunction, Global, Instruction, Label, Local, LogicOp,
+    Program,
+};
+
+/// Módulo de importación de builtins y globales del runtime.
+const RUNTIME_MODULE: &str = "runtime";
+
+/// Símbolo del punto de entrada.
+const ENTRY: &str = "user_main";
+
+/// Emite un módulo WAT para un programa IR.
+pub fn emit(program: &Program, output: &mut dyn Write) -> io::Result<()> {
+    let own_globals: HashSet<&str> = program.globals.iter().map(AsRef::as_ref).collect();
+    let imports = Imports::collect(program, &own_globals);
+
+    writeln!(output, "(module")?;
+
+    if !imports.globals.is_empty() {
+        writeln!(
+            output,
+            "  (import \"{}\" \"memory\" (memory 0))",
+            RUNTIME_MODULE
+        )?;
+    }
+
+    let mut globals: Vec<_> = imports.globals.iter().collect();
+    globals.sort_unstable();
+
+    for global in globals {
+        writeln!(
+            output,
+            "  (import \"{0}\" \"{1}\" (global ${1} i32))",
+            RUNTIME_MODULE, global
+        )?;
+    }
+
+    let mut functions: Vec<_> = imports.functions.iter().collect();
+    functions.sort_unstable();
+
+    for (name, (parameters, returns)) in functions {
+        write!(
+            output,
+            "  (import \"{0}\" \"{1}\" (func ${1}",
+            RUNTIME_MODULE, name
+        )?;
+
+        signature(output, *parameters, *returns)?;
+        writeln!(output, "))")?;
+    }
+
+    for global in program.globals.iter() {
+        writeln!(
+            output,
+            "  (global ${} (mut i32) (i32.const 0))",
+            global.as_ref()
+        )?;
+    }
+
+    for function in program.code.iter() {
+        let mut emitter = Emitter {
+            output: &mut *output,
+            function,
+            own_globals: &own_globals,
+            imports: &imports,
+            blocks: HashMap::new(),
+        };
+
+        emitter.function()?;
+    }
+
+    writeln!(output, ")")
+}
+
+/// Funciones y globales externas que utiliza un programa.
+struct Imports<'a> {
+    /// Cantidad de parámetros y si existe valor de retorno, por función.
+    functions: HashMap<&'a str, (usize, bool)>,
+
+    /// Globales de `libruntime`.
+    globals: HashSet<&'a str>,
+}
+
+impl<'a> Imports<'a> {
+    /// Las firmas de builtins se deducen de su uso. Un builtin retorna
+    /// un valor si alguna llamada lo utiliza.
+    fn collect(program: &'a Program, own_globals: &HashSet<&str>) -> Self {
+        let mut imports = Imports {
+            functions: HashMap::new(),
+            globals: HashSet::new(),
+        };
+
+        for instruction in program
+            .code
+            .iter()
+            .flat_map(|function| function.body.iter())
+        {
+            match instruction.as_ref() {
+                Instruction::LoadGlobal(global, _) | Instruction::StoreGlobal(_, global)
+                    if !own_globals.contains(global.as_ref()) =>
+                {
+                    imports.globals.insert(global.as_ref());
+                }
+
+                Instruction::Call {
+                    target: Function::External(name),
+                    arguments,
+                    output,
+                } => {
+                    let (_, returns) = imports
+                        .functions
+                        .entry(name)
+                        .or_insert((arguments.len(), false));
+
+                    *returns |= output.is_some();
+                }
+
+                _ => (),
+            }
+        }
+
+        imports
+    }
+}
+
+/// Emisión de una función.
+struct Emitter<'a, 'b> {
+    output: &'b mut dyn Write,
+    function: &'a GeneratedFunction,
+    own_globals: &'b HashSet<&'a str>,
+    imports: &'b Imports<'a>,
+
+    /// Bloque básico que inicia en cada etiqueta.
+    blocks: HashMap<u32, usize>,
+}
+
+impl Emitter<'_, '_> {
+    fn function(&mut self) -> io::Result<()> {
+        let function = self.function;
+
+        write!(self.output, "  (func ${}", function.name)?;
+        if function.name.as_str() == ENTRY {
+            write!(self.output, " (export \"{}\")", ENTRY)?;
+        }
+
+        for parameter in 0..function.parameters {
+            write!(self.output, " (param $l{} i32)", parameter)?;
+        }
+
+        writeln!(self.output, " (result i32)")?;
+
+        let locals = function
+            .body
+            .iter()
+            .flat_map(|instruction| used_locals(instruction))
+            .map(|Local(local)| local + 1)
+            .max()
+            .unwrap_or(0);
+
+        for local in function.parameters..locals {
+            writeln!(self.output, "    (local $l{} i32)", local)?;
+        }
+
+        let labels = function
+            .body
+            .iter()
+            .filter_map(|instruction| match instruction.as_ref() {
+                Instruction::SetLabel(Label(label)) => Some(*label),
+                _ => None,
+            });
+
+        self.blocks = labels
+            .enumerate()
+            .map(|(index, label)| (label, index + 1))
+            .collect();
+
+        // Sin etiquetas no hay saltos, por lo cual no hace falta despachar
+        let dispatch = !self.blocks.is_empty();
+        if dispatch {
+            self.open_dispatch()?;
+        }
+
+        for instruction in function.body.iter() {
+            self.instruction(instruction)?;
+        }
+
+        if dispatch {
+            self.line("end")?;
+        }
+
+        // Una función sin retorno explícito produce cero
+        self.line("i32.const 0")?;
+        writeln!(self.output, "  )")
+    }
+
+    fn open_dispatch(&mut self) -> io::Result<()> {
+        let count = self.blocks.len() + 1;
+
+        writeln!(self.output, "    (local $pc i32)")?;
+        self.line("loop $dispatch")?;
+
+        for block in (0..count).rev() {
+            self.line(&format!("block $b{}", block))?;
+        }
+
+        self.line("local.get $pc")?;
+
+        let targets: Vec<_> = (0..count).map(|block| format!("$b{}", block)).collect();
+        self.line(&format!("br_table {}", targets.join(" ")))?;
+        self.line("end")
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) -> io::Result<()> {
+        use Instruction::*;
+
+        match instruction {
+            Move(from, to) => {
+                self.get(*from)?;
+                self.set(*to)
+            }
+
+            // El bloque anterior termina donde inicia el de la etiqueta
+            SetLabel(_) => self.line("end"),
+
+            Jump(label) => self.jump(*label),
+
+            JumpIfFalse(local, label) => {
+                self.get(*local)?;
+                self.line("i32.eqz")?;
+                self.line("if")?;
+                self.jump(*label)?;
+                self.line("end")
+            }
+
+            LoadConst(value, local) => {
+                self.line(&format!("i32.const {}", value))?;
+                self.set(*local)
+            }
+
+            LoadGlobal(global, local) => {
+                self.line(&format!("global.get ${}", global.as_ref()))?;
+                if self.is_runtime_global(global) {
+                    self.line("i32.load")?;
+                }
+
+                self.set(*local)
+            }
+
+            // La memoria pertenece al runtime, el módulo no tiene segmentos propios
+            LoadData(..) => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Play is not supported on WebAssembly",
+            )),
+
+            // Los punteros a función requieren una tabla compartida con el runtime
+            LoadProcedure(..) => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "After, Every and procedure references are not supported on WebAssembly",
+            )),
+
+            StoreGlobal(local, global) => {
+                if self.is_runtime_global(global) {
+                    self.line(&format!("global.get ${}", global.as_ref()))?;
+                    self.get(*local)?;
+                    self.line("i32.store")
+                } else {
+                    self.get(*local)?;
+                    self.line(&format!("global.set ${}", global.as_ref()))
+                }
+            }
+
+            Not(local) => {
+                self.get(*local)?;
+                self.line("i32.const 1")?;
+                self.line("i32.xor")?;
+                self.set(*local)
+            }
+
+            Negate(local) => {
+                self.line("i32.const 0")?;
+                self.get(*local)?;
+                self.line("i32.sub")?;
+                self.set(*local)
+            }
+
+            Binary(lhs, op, rhs) => {
+                self.get(*lhs)?;
+                self.get(*rhs)?;
+                self.line(binary(*op))?;
+                self.set(*lhs)
+            }
+
+            Call {
+                target,
+                arguments,
+                output,
+            } => {
+                for argument in arguments.iter() {
+                    self.get(*argument)?;
+                }
+
+                let returns = match target {
+                    Function::Generated(_) => true,
+                    Function::External(name) => self.imports.functions[name].1,
+
+                    // Ver `LoadProcedure`
+                    Function::Indirect(_) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::Other,
+                            "Procedure references are not supported on WebAssembly",
+                        ))
+                    }
+                };
+
+                self.line(&format!("call ${}", target.name().unwrap_or_default()))?;
+
+                match output {
+                    Some(output) => self.set(*output),
+                    None if returns => self.line("drop"),
+                    None => Ok(()),
+                }
+            }
+
+            Return(value) => {
+                match value {
+                    Some(value) => self.get(*value)?,
+                    None => self.line("i32.const 0")?,
+                }
+
+                self.line("return")
+            }
+
+            // Los locales de WebAssembly no ocupan registros que liberar
+            Kill(_) => Ok(()),
+        }
+    }
+
+    fn jump(&mut self, Label(label): Label) -> io::Result<()> {
+        let block = self.blocks[&label];
+
+        self.line(&format!("i32.const {}", block))?;
+        self.line("local.set $pc")?;
+        self.line("br $dispatch")
+    }
+
+    fn get(&mut self, Local(local): Local) -> io::Result<()> {
+        self.line(&format!("local.get $l{}", local))
+    }
+
+    fn set(&mut self, Local(local): Local) -> io::Result<()> {
+        self.line(&format!("local.set $l{}", local))
+    }
+
+    fn line(&mut self, instruction: &str) -> io::Result<()> {
+        writeln!(self.output, "    {}", instruction)
+    }
+
+    fn is_runtime_global(&self, global: &Global) -> bool {
+        !self.own_globals.contains(global.as_ref())
+    }
+}
+
+fn signature(output: &mut dyn Write, parameters: usize, returns: bool) -> io::Result<()> {
+    for _ in 0..parameters {
+        write!(output, " (param i32)")?;
+    }
+
+    if returns {
+        write!(output, " (result i32)")?;
+    }
+
+    Ok(())
+}
+
+fn binary(op: BinOp) -> &'static str {
+    use ArithmeticOp::*;
+    use LogicOp::*;
+
+    match op {
+        BinOp::Arithmetic(Add) => "i32.add",
+        BinOp::Arithmetic(Sub) => "i32.sub",
+        BinOp::Arithmetic(Mul) => "i32.mul",
+        BinOp::Arithmetic(Div) => "i32.div_s",
+        BinOp::Arithmetic(Mod) => "i32.rem_s",
+        BinOp::Arithmetic(BitAnd) => "i32.and",
+        BinOp::Arithmetic(BitOr) => "i32.or",
+        BinOp::Arithmetic(BitXor) => "i32.xor",
+        BinOp::Arithmetic(ShiftLeft) => "i32.shl",
+        BinOp::Arithmetic(ShiftRight) => "i32.shr_s",
+        BinOp::Logic(Equal) => "i32.eq",
+        BinOp::Logic(NotEqual) => "i32.ne",
+        BinOp::Logic(Less) => "i32.lt_s",
+        BinOp::Logic(LessOrEqual) => "i32.le_s",
+        BinOp::Logic(Greater) => "i32.gt_s",
+        BinOp::Logic(GreaterOrEqual) => "i32.ge_s",
+    }
+}
+
+/// Locales que una instrucción lee o escribe.
+fn used_locals(instruction: &Instruction) -> Vec<Local> {
+    use Instruction::*;
+
+    match instruction {
+        Move(from, to) => vec![*from, *to],
+        SetLabel(_) | Jump(_) => vec![],
+        JumpIfFalse(local, _) | LoadConst(_, local) | LoadGlobal(_, local) => vec![*local],
+        LoadData(_, local) | LoadProcedure(_, local) => vec![*local],
+        StoreGlobal(local, _) | Not(local) | Negate(local) => vec![*local],
+        Kill(local) => vec![*local],
+        Binary(lhs, _, rhs) => vec![*lhs, *rhs],
+        Return(value) => value.iter().copied().collect(),
+
+        Call {
+            target,
+            arguments,
+            output,
+        } => {
+            let callee = match target {
+                Function::Indirect(local) => Some(*local),
+                _ => None,
+            };
+
+            callee
+                .into_iter()
+                .chain(arguments.iter().chain(output.iter()).copied())
+                .collect()
+        }
+    }
+}
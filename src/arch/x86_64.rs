@@ -1,13 +1,26 @@
 //! Implementación para x86-64.
+//!
+//! El emisor es genérico sobre la convención de llamada, ver
+//! [`Convention`]. Ambas retornan en `%rax` y alinean la pila a 16
+//! bytes en cada llamada, pero difieren en los registros de argumentos,
+//! en cuáles registros preserva quien es llamado y en que Win64 exige
+//! reservar 32 bytes de "shadow space" sobre los argumentos en pila.
 
 use crate::{
-    codegen::{regs::Allocations, Context},
+    codegen::{
+        peephole::{self, Effect, Line, Pattern},
+        regs::Allocations,
+        Context,
+    },
     ir::{ArithmeticOp, BinOp, Function, Global, Instruction, Local, LogicOp},
+    source::Located,
 };
 
-use std::{fmt, io};
+use std::{fmt, io, marker::PhantomData};
 
-/// Esta es una arquitectura de 64 bits
+/// Esta es una arquitectura de 64 bits. Los enteros se operan en su
+/// forma de 32 bits y luego se extienden en signo con `movslq`, ver
+/// [`super::Emitter::VALUE_SIZE`].
 const VALUE_SIZE: u32 = 8;
 
 /// Registro de procesador.
@@ -22,26 +35,29 @@ pub enum Reg {
     R9,
     R10,
     R11,
+    Rbx,
+    R12,
+    R13,
+    R14,
+    R15,
 }
 
 impl Reg {
-    /// La ABI indica que se coloquen los primeros 6 argumentos en los
-    /// registros `%rdi`, `%rsi`, `%rdx`, `%rcx`, `%r8` y `%r9`. Si hay
-    /// más se ponen en el stack en orden inverso.
-    const MAX_ARGS: u32 = 6;
-
-    /// Iterador sobre los registros donde se colocan los primeros argumentos.
-    fn argument_sequence() -> impl Iterator<Item = Reg> {
-        use Reg::*;
+    /// Identifica un registro reservable o callee-saved a partir de
+    /// cualquiera de sus nombres.
+    fn from_name(name: &str) -> Option<Reg> {
+        use super::Register;
+
+        Reg::FILE
+            .iter()
+            .chain(Reg::CALLEE_SAVED)
+            .copied()
+            .find(|reg| reg.names().contains(&name))
+    }
 
-        std::iter::successors(Some(Rdi), |last| match last {
-            Rdi => Some(Rsi),
-            Rsi => Some(Rdx),
-            Rdx => Some(Rcx),
-            Rcx => Some(R8),
-            R8 => Some(R9),
-            _ => None,
-        })
+    /// Nombres con los que puede aparecer un registro en un operando.
+    fn names(self) -> [&'static str; 3] {
+        [self.as_qword(), self.as_dword(), self.as_byte()]
     }
 
     /// Obtiene la forma de 64 bits de un registro x86.
@@ -58,6 +74,11 @@ impl Reg {
             R9 => "%r9",
             R10 => "%r10",
             R11 => "%r11",
+            Rbx => "%rbx",
+            R12 => "%r12",
+            R13 => "%r13",
+            R14 => "%r14",
+            R15 => "%r15",
         }
     }
 
@@ -75,6 +96,11 @@ impl Reg {
             R9 => "%r9d",
             R10 => "%r10d",
             R11 => "%r11d",
+            Rbx => "%ebx",
+            R12 => "%r12d",
+            R13 => "%r13d",
+            R14 => "%r14d",
+            R15 => "%r15d",
         }
     }
 
@@ -92,6 +118,11 @@ impl Reg {
             R9 => "%r9b",
             R10 => "%r10b",
             R11 => "%r11b",
+            Rbx => "%bl",
+            R12 => "%r12b",
+            R13 => "%r13b",
+            R14 => "%r14b",
+            R15 => "%r15b",
         }
     }
 }
@@ -109,6 +140,9 @@ impl super::Register for Reg {
         Reg::R10,
         Reg::R11,
     ];
+
+    // Ambas convenciones preservan estos registros
+    const CALLEE_SAVED: &'static [Self] = &[Reg::Rbx, Reg::R12, Reg::R13, Reg::R14, Reg::R15];
 }
 
 impl fmt::Display for Reg {
@@ -117,12 +151,66 @@ impl fmt::Display for Reg {
     }
 }
 
+/// Convención de llamada.
+pub trait Convention: 'static {
+    /// Registros donde se colocan los primeros argumentos, en orden. Si
+    /// hay más, se ponen en el stack en orden inverso.
+    const ARGUMENTS: &'static [Reg];
+
+    /// Valores que quien llama reserva en el stack justo sobre la dirección
+    /// de retorno, en los cuales quien es llamado puede guardar los
+    /// argumentos que recibió en registros.
+    const SHADOW_SPACE: u32;
+
+    /// Registros reservables que quien es llamado debe preservar. Como
+    /// se reservan igual que los demás, se guardan en el marco de cada
+    /// función y se restauran en su epílogo.
+    const PRESERVED: &'static [Reg];
+
+    /// Si las llamadas a símbolos pasan por la PLT, lo cual solo aplica
+    /// a ejecutables ELF.
+    const PLT: bool;
+
+    /// Patrones de mirilla, los cuales dependen de qué registros
+    /// preserva una llamada.
+    const PEEPHOLE: &'static [Pattern];
+}
+
+/// System V AMD64, la convención de Linux, macOS y los BSD.
+pub struct SysV;
+
+/// Microsoft x64, la convención de Windows.
+pub struct Win64;
+
+impl Convention for SysV {
+    const ARGUMENTS: &'static [Reg] = &[Reg::Rdi, Reg::Rsi, Reg::Rdx, Reg::Rcx, Reg::R8, Reg::R9];
+    const SHADOW_SPACE: u32 = 0;
+    const PRESERVED: &'static [Reg] = &[];
+    const PLT: bool = true;
+    const PEEPHOLE: &'static [Pattern] = &[remove_self_move, fold_zero_compare::<SysV>, merge_rsp];
+}
+
+impl Convention for Win64 {
+    const ARGUMENTS: &'static [Reg] = &[Reg::Rcx, Reg::Rdx, Reg::R8, Reg::R9];
+    const SHADOW_SPACE: u32 = 4;
+    const PRESERVED: &'static [Reg] = &[Reg::Rdi, Reg::Rsi];
+    const PLT: bool = false;
+    const PEEPHOLE: &'static [Pattern] = &[remove_self_move, fold_zero_compare::<Win64>, merge_rsp];
+}
+
 /// Emisión de código para x86-64.
-pub struct Emitter<'a> {
+pub struct Emitter<'a, C: Convention> {
     cx: Context<'a, Self>,
     regs: Allocations<'a, Self>,
+    convention: PhantomData<C>,
 }
 
+/// Emisor para la convención System V.
+pub type SysVEmitter<'a> = Emitter<'a, SysV>;
+
+/// Emisor para la convención de Windows.
+pub type Win64Emitter<'a> = Emitter<'a, Win64>;
+
 /// Información que debe preservarse durante una llamada.
 pub struct CallInfo {
     rsp_offset: u32,
@@ -133,37 +221,39 @@ enum Division {
     Remainder,
 }
 
-impl<'a> super::Emitter<'a> for Emitter<'a> {
+impl<'a, C: Convention> super::Emitter<'a> for Emitter<'a, C> {
     const VALUE_SIZE: u32 = VALUE_SIZE;
+    const PEEPHOLE: &'static [Pattern] = C::PEEPHOLE;
 
     type Register = Reg;
     type CallInfo = CallInfo;
     type FrameInfo = ();
 
-    fn new(cx: Context<'a, Self>, _: &[Instruction]) -> io::Result<Self> {
+    fn new(cx: Context<'a, Self>, _: &[Located<Instruction>]) -> io::Result<Self> {
         // Prólogo, se crea un stack frame
         emit!(cx, "push", "%rbp")?;
         emit!(cx, "mov", "%rsp, %rbp")?;
 
-        // Se reserva memoria para locales
-        let total_locals = cx.agnostic_locals();
-        let stack_allocation = total_locals + alignment_for(total_locals);
-
+        // Se reserva memoria para locales y registros callee-saved
+        let stack_allocation = Self::stack_allocation(&cx);
         let mut emitter = Emitter {
             cx,
             regs: Default::default(),
+            convention: PhantomData,
         };
 
         if stack_allocation > 0 {
             emitter.move_rsp(-(stack_allocation as i32))?;
         }
 
-        // Se definen posiciones de argumentos en registros
-        let parameters = emitter.cx.function().parameters;
-        for (reg, local) in Reg::argument_sequence().zip((0..parameters).map(Local)) {
-            emitter.assert_dirty(reg, local);
+        for (slot, reg) in Self::saved(&emitter.cx).enumerate() {
+            let address = frame_address(-1 - slot as i32);
+            emit!(emitter.cx, "mov", "{}, {}", reg, address)?;
         }
 
+        // Se definen posiciones de argumentos en registros
+        emitter.bind_parameters(C::ARGUMENTS.iter().copied())?;
+
         Ok(emitter)
     }
 
@@ -171,7 +261,17 @@ impl<'a> super::Emitter<'a> for Emitter<'a> {
         (&mut self.cx, &mut self.regs)
     }
 
-    fn epilogue(self) -> io::Result<()> {
+    fn frame_size(&self) -> u32 {
+        // Se agregan %rbp y la dirección de retorno
+        (Self::stack_allocation(&self.cx) + 2) * VALUE_SIZE
+    }
+
+    fn epilogue(&mut self) -> io::Result<()> {
+        for (slot, reg) in Self::saved(&self.cx).enumerate() {
+            let address = frame_address(-1 - slot as i32);
+            emit!(self.cx, "mov", "{}, {}", address, reg)?;
+        }
+
         // Revierte al estado justo antes de la llamada
         emit!(self.cx, "mov", "%rbp, %rsp")?;
         emit!(self.cx, "pop", "%rbp")?;
@@ -201,6 +301,10 @@ impl<'a> super::Emitter<'a> for Emitter<'a> {
         emit!(self.cx, "mov", "{}(%rip), {}", global.as_ref(), reg)
     }
 
+    fn load_address(&mut self, symbol: &str, reg: Reg) -> io::Result<()> {
+        emit!(self.cx, "lea", "{}(%rip), {}", symbol, reg)
+    }
+
     fn store_global(&mut self, reg: Reg, global: &Global) -> io::Result<()> {
         emit!(self.cx, "mov", "{}, {}(%rip)", reg, global.as_ref())
     }
@@ -210,7 +314,8 @@ impl<'a> super::Emitter<'a> for Emitter<'a> {
     }
 
     fn negate(&mut self, reg: Reg) -> io::Result<()> {
-        emit!(self.cx, "neg", "{}", reg)
+        emit!(self.cx, "neg", "{}", reg.as_dword())?;
+        self.sign_extend(reg)
     }
 
     fn binary(&mut self, lhs: Reg, op: BinOp, rhs: Reg) -> io::Result<()> {
@@ -224,9 +329,18 @@ impl<'a> super::Emitter<'a> for Emitter<'a> {
                     Mul => "imul",
                     Div => return self.div_or_mod(lhs, rhs, Division::Quotient),
                     Mod => return self.div_or_mod(lhs, rhs, Division::Remainder),
+                    ShiftLeft => return self.shift(lhs, rhs, "sal"),
+                    ShiftRight => return self.shift(lhs, rhs, "sar"),
+
+                    // Preservan la extensión de signo de ambos operandos
+                    BitAnd => return emit!(self.cx, "and", "{}, {}", rhs, lhs),
+                    BitOr => return emit!(self.cx, "or", "{}, {}", rhs, lhs),
+                    BitXor => return emit!(self.cx, "xor", "{}, {}", rhs, lhs),
                 };
 
-                emit!(self.cx, instruction, "{}, {}", rhs, lhs)
+                let (lhs_dword, rhs_dword) = (lhs.as_dword(), rhs.as_dword());
+                emit!(self.cx, instruction, "{}, {}", rhs_dword, lhs_dword)?;
+                self.sign_extend(lhs)
             }
 
             BinOp::Logic(op) => {
@@ -249,36 +363,55 @@ impl<'a> super::Emitter<'a> for Emitter<'a> {
     }
 
     fn prepare_args(&mut self, arguments: &[Local]) -> io::Result<CallInfo> {
-        // Argumentos del séptimo en adelante se colocan en stack en orden inverso
-        let pushed = (arguments.len() as u32).max(Reg::MAX_ARGS) - Reg::MAX_ARGS;
+        // Argumentos que no caben en registros se colocan en stack en orden inverso
+        let max_args = C::ARGUMENTS.len() as u32;
+        let pushed = (arguments.len() as u32).max(max_args) - max_args;
 
         // Corrección del stack pointer alrededor de la llamada, manteniendo el alineamiento de 16 bytes
-        let rsp_offset = if arguments.len() as u32 > Reg::MAX_ARGS {
-            let alignment = alignment_for(pushed);
-            if alignment > 0 {
-                self.move_rsp(-(alignment as i32))?;
-            }
-
-            pushed + alignment
-        } else {
-            0
-        };
+        let reserved = pushed + C::SHADOW_SPACE;
+        let alignment = alignment_for(reserved);
+        if alignment > 0 {
+            self.move_rsp(-(alignment as i32))?;
+        }
 
         for argument in arguments.iter().rev().take(pushed as usize) {
-            let address = Self::local_address(&self.cx, *argument);
-            emit!(self.cx, "push", "{}", address)?;
+            match self.cx.home(*argument) {
+                Some(home) => emit!(self.cx, "push", "{}", home)?,
+                None => {
+                    let address = Self::local_address(&self.cx, *argument);
+                    emit!(self.cx, "push", "{}", address)?;
+                }
+            }
+        }
+
+        if C::SHADOW_SPACE > 0 {
+            self.move_rsp(-(C::SHADOW_SPACE as i32))?;
         }
 
-        // Los primeros seis argumentos se colocan en registros específicos
-        for (argument, reg) in arguments.iter().zip(Reg::argument_sequence()) {
-            self.cx.read_into(&mut self.regs, reg, *argument)?;
+        // Los primeros argumentos se colocan en registros específicos
+        for (argument, reg) in arguments.iter().zip(C::ARGUMENTS) {
+            self.cx.read_into(&mut self.regs, *reg, *argument)?;
         }
 
+        let rsp_offset = reserved + alignment;
         Ok(CallInfo { rsp_offset })
     }
 
     fn call(&mut self, target: &Function, call_info: CallInfo) -> io::Result<()> {
-        emit!(self.cx, "call", "{}", target.name())?;
+        match target {
+            Function::Indirect(local) => match self.cx.home(*local) {
+                Some(home) => emit!(self.cx, "call", "*{}", home)?,
+                None => {
+                    let address = Self::local_address(&self.cx, *local);
+                    emit!(self.cx, "call", "*{}", address)?;
+                }
+            },
+
+            // Pasar por la PLT permite enlazar tanto PIE como no PIE, ya que
+            // el enlazador resuelve la llamada directamente si es posible
+            _ if C::PLT => emit!(self.cx, "call", "{}@PLT", target.name().unwrap_or_default())?,
+            _ => emit!(self.cx, "call", "{}", target.name().unwrap_or_default())?,
+        }
 
         // Se reclama memoria que fue usada para argumentos
         if call_info.rsp_offset > 0 {
@@ -303,7 +436,7 @@ impl<'a> super::Emitter<'a> for Emitter<'a> {
     }
 }
 
-impl<'a> Emitter<'a> {
+impl<'a, C: Convention> Emitter<'a, C> {
     fn div_or_mod(&mut self, lhs: Reg, rhs: Reg, mode: Division) -> io::Result<()> {
         emit!(self.cx, "push", "%rax")?;
         emit!(self.cx, "push", "%rdx")?;
@@ -325,7 +458,42 @@ impl<'a> Emitter<'a> {
 
         emit!(self.cx, "mov", "{}, {}", result, location(lhs))?;
         emit!(self.cx, "pop", "%rdx")?;
-        emit!(self.cx, "pop", "%rax")
+        emit!(self.cx, "pop", "%rax")?;
+
+        // Solo el cociente de -2^31 entre -1 excede 32 bits
+        match mode {
+            Division::Quotient => self.sign_extend(lhs),
+            Division::Remainder => Ok(()),
+        }
+    }
+
+    /// Desplaza `lhs` por `rhs`, cuyo conteo debe estar en `%cl`.
+    ///
+    /// El desplazamiento es de 32 bits, por lo cual el conteo se toma
+    /// módulo 32 al igual que en las demás arquitecturas.
+    fn shift(&mut self, lhs: Reg, rhs: Reg, instruction: &str) -> io::Result<()> {
+        if rhs == Reg::Rcx {
+            emit!(self.cx, instruction, "%cl, {}", lhs.as_dword())?;
+            return self.sign_extend(lhs);
+        }
+
+        // Si lhs es %rcx, se desplaza su copia en el stack
+        emit!(self.cx, "push", "%rcx")?;
+        emit!(self.cx, "mov", "{}, %rcx", rhs)?;
+
+        if lhs == Reg::Rcx {
+            emit!(self.cx, &format!("{}l", instruction), "%cl, (%rsp)")?;
+        } else {
+            emit!(self.cx, instruction, "%cl, {}", lhs.as_dword())?;
+        }
+
+        emit!(self.cx, "pop", "%rcx")?;
+        self.sign_extend(lhs)
+    }
+
+    /// Extiende en signo los 32 bits inferiores de un registro.
+    fn sign_extend(&mut self, reg: Reg) -> io::Result<()> {
+        emit!(self.cx, "movslq", "{}, {}", reg.as_dword(), reg)
     }
 
     /// Agrega un offset al puntero de stack.
@@ -335,21 +503,171 @@ impl<'a> Emitter<'a> {
         emit!(self.cx, instruction, "$0x{:x}, %rsp", offset)
     }
 
+    /// Registros que se guardan en el prólogo, tanto los que preserva la
+    /// convención como los callee-saved que utiliza la función.
+    fn saved(cx: &Context<'a, Self>) -> impl Iterator<Item = Reg> {
+        C::PRESERVED.iter().chain(cx.callee_saved()).copied()
+    }
+
+    /// Valores que se reservan en el stack en el prólogo, incluyendo
+    /// los registros que se guardan.
+    fn stack_allocation(cx: &Context<'a, Self>) -> u32 {
+        let slots = Self::saved(cx).count() as u32 + cx.agnostic_locals();
+        slots + alignment_for(slots)
+    }
+
     /// Obtiene el addressing relativo a `%rbp` de una local.
     fn local_address(cx: &Context<'a, Self>, Local(local): Local) -> String {
         let parameters = cx.function().parameters;
-        let value_offset = if local < Reg::MAX_ARGS || parameters < Reg::MAX_ARGS {
-            -1 - local as i32
+        let max_args = C::ARGUMENTS.len() as u32;
+
+        // Los registros que se guardan van justo bajo %rbp
+        let saved = Self::saved(cx).count() as u32;
+
+        let value_offset = if local < max_args || parameters < max_args {
+            -1 - (saved + local) as i32
         } else if local < parameters {
-            2 + (local - Reg::MAX_ARGS) as i32
+            (2 + C::SHADOW_SPACE + local - max_args) as i32
         } else {
-            -1 - (Reg::MAX_ARGS + local - parameters) as i32
+            -1 - (saved + max_args + local - parameters) as i32
         };
 
-        // Los offsets son relativos al frame pointer %rbp
-        let offset = value_offset * (VALUE_SIZE as i32);
-        let sign = if offset < 0 { "-" } else { "" };
-        format!("{}0x{:x}(%rbp)", sign, offset.abs())
+        frame_address(value_offset)
+    }
+}
+
+/// Addressing de un valor a partir de su offset en valores respecto
+/// al frame pointer `%rbp`.
+fn frame_address(value_offset: i32) -> String {
+    let offset = value_offset * (VALUE_SIZE as i32);
+    let sign = if offset < 0 { "-" } else { "" };
+    format!("{}0x{:x}(%rbp)", sign, offset.abs())
+}
+
+/// Elimina `mov %reg, %reg`.
+fn remove_self_move(lines: &mut Vec<String>, at: usize) -> bool {
+    peephole::remove_self_move(lines, at, "mov")
+}
+
+/// Sustituye `xor %eR, %eR` seguido de `cmp %rR, %rX` por `test %rX, %rX`.
+/// El `xor` se elimina solo si el cero no se vuelve a leer.
+fn fold_zero_compare<C: Convention>(lines: &mut Vec<String>, at: usize) -> bool {
+    let (zero, other) = match Line::parse(&lines[at]) {
+        Some(Line {
+            opcode: "cmp",
+            operands,
+        }) if operands.len() == 2 => {
+            match (Reg::from_name(operands[0]), Reg::from_name(operands[1])) {
+                (Some(zero), Some(other))
+                    if zero != other
+                        && operands[0] == zero.as_qword()
+                        && operands[1] == other.as_qword() =>
+                {
+                    (zero, other)
+                }
+
+                _ => return false,
+            }
+        }
+
+        _ => return false,
+    };
+
+    let zeroed = match peephole::last_mention(lines, at, |line| mentions(line, zero)) {
+        Some(zeroed) => zeroed,
+        None => return false,
+    };
+
+    match Line::parse(&lines[zeroed]) {
+        Some(Line {
+            opcode: "xor",
+            operands,
+        }) if operands == [zero.as_dword(), zero.as_dword()] => (),
+        _ => return false,
+    }
+
+    lines[at] = Line::format("test", &[other.as_qword(), other.as_qword()]);
+    if peephole::is_dead_from(lines, at + 1, |line| effect::<C>(line, zero)) {
+        lines.remove(zeroed);
+    }
+
+    true
+}
+
+/// Fusiona ajustes consecutivos de `%rsp`.
+fn merge_rsp(lines: &mut Vec<String>, at: usize) -> bool {
+    let parse = |line: &Line<'_>| {
+        let sign = match line.opcode {
+            "add" => 1,
+            "sub" => -1,
+            _ => return None,
+        };
+
+        match line.operands.as_slice() {
+            [amount, "%rsp"] => {
+                let amount = amount.strip_prefix('$')?;
+                let amount = match amount.strip_prefix("0x") {
+                    Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+                    None => amount.parse().ok()?,
+                };
+
+                Some(sign * amount)
+            }
+
+            _ => None,
+        }
+    };
+
+    let format = |total: i64| {
+        let instruction = if total < 0 { "sub" } else { "add" };
+        let amount = format!("$0x{:x}", total.abs());
+
+        Some(Line::format(instruction, &[&amount, "%rsp"]))
+    };
+
+    peephole::merge_stack_adjustments(lines, at, parse, format)
+}
+
+/// Determina si una instrucción menciona un registro.
+fn mentions(line: &Line<'_>, reg: Reg) -> bool {
+    line.operands
+        .iter()
+        .any(|operand| reg.names().iter().any(|name| operand.contains(name)))
+}
+
+/// Efecto de una instrucción sobre el valor de un registro.
+fn effect<C: Convention>(line: &Line<'_>, reg: Reg) -> Effect {
+    use super::Register;
+
+    // Un registro callee-saved puede ser el hogar de una local, cuyo
+    // valor sobrevive a saltos y llamadas
+    if Reg::CALLEE_SAVED.contains(&reg) {
+        return Effect::Reads;
+    }
+
+    // Una llamada destruye los registros reservables que no son callee-saved
+    match line.opcode {
+        "jmp" => return Effect::Kills,
+        "ret" if reg == Reg::Rax => return Effect::Reads,
+        "ret" => return Effect::Kills,
+        "call" if C::ARGUMENTS.contains(&reg) => return Effect::Reads,
+        "call" if !C::PRESERVED.contains(&reg) => return Effect::Kills,
+        "call" => return Effect::Ignores,
+        _ if !mentions(line, reg) => return Effect::Ignores,
+        _ => (),
+    }
+
+    match (line.opcode, line.operands.as_slice()) {
+        ("xor", [lhs, rhs]) if lhs == rhs => Effect::Kills,
+
+        ("mov", [source, target])
+            if !reg.names().iter().any(|name| source.contains(name))
+                && (*target == reg.as_qword() || *target == reg.as_dword()) =>
+        {
+            Effect::Kills
+        }
+
+        _ => Effect::Reads,
     }
 }
 
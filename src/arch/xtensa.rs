@@ -6,8 +6,13 @@
 //! La ABI `call0` está documentada en 8.1.2.
 
 use crate::{
-    codegen::{regs::Allocations, Context},
+    codegen::{
+        peephole::{self, Effect, Line, Pattern},
+        regs::Allocations,
+        Context,
+    },
     ir::{ArithmeticOp, BinOp, Function, Global, Instruction, Local, LogicOp},
+    source::Located,
 };
 
 use std::{fmt, io};
@@ -37,6 +42,7 @@ impl Reg {
 impl super::Register for Reg {
     const RETURN: Self = Reg(2);
     const FILE: &'static [Self] = &[Reg(2), Reg(3), Reg(4), Reg(5), Reg(6), Reg(7), Reg(8)];
+    const CALLEE_SAVED: &'static [Self] = &[Reg(12), Reg(13), Reg(14), Reg(15)];
 }
 
 impl fmt::Display for Reg {
@@ -60,16 +66,17 @@ pub struct FrameInfo {
 
 impl<'a> super::Emitter<'a> for Emitter<'a> {
     const VALUE_SIZE: u32 = VALUE_SIZE;
+    const PEEPHOLE: &'static [Pattern] = &[remove_self_move, fold_zero_branch, merge_sp];
 
     type Register = Reg;
     type CallInfo = ();
     type FrameInfo = FrameInfo;
 
-    fn new(cx: Context<'a, Self>, instructions: &[Instruction]) -> io::Result<Self> {
+    fn new(cx: Context<'a, Self>, instructions: &[Located<Instruction>]) -> io::Result<Self> {
         // Xtensa no tiene push/pop, por lo cual esto evita mucho trabajo sobre a1/sp
         let max_call_spill = instructions
             .iter()
-            .map(|instruction| match instruction {
+            .map(|instruction| match instruction.as_ref() {
                 Instruction::Call { arguments, .. } => {
                     (arguments.len() as u32).max(Reg::MAX_ARGS) - Reg::MAX_ARGS
                 }
@@ -79,8 +86,10 @@ impl<'a> super::Emitter<'a> for Emitter<'a> {
             .max()
             .unwrap_or(0);
 
-        // Se reserva memoria para locales. "+ 1" debido a que se debe preservar a0
-        let total_locals = cx.agnostic_locals() + 1 + max_call_spill;
+        // Se reserva memoria para locales y registros callee-saved. "+ 1" debido
+        // a que se debe preservar a0
+        let saved = cx.callee_saved().len() as u32;
+        let total_locals = cx.agnostic_locals() + 1 + saved + max_call_spill;
 
         // Alineamiento de 16 bytes (4 * 4 bytes)
         let padding = if total_locals % 4 == 0 {
@@ -105,12 +114,10 @@ impl<'a> super::Emitter<'a> for Emitter<'a> {
         // Se preserva la dirección de retorno
         let a0_offset = VALUE_SIZE as i32 * (frame_offset - 1);
         emit!(emitter.cx, "s32i", "a0, a1, {}", a0_offset)?;
+        emitter.transfer_saved("s32i")?;
 
         // Se definen posiciones de argumentos en registros
-        let parameters = emitter.cx.function().parameters;
-        for (reg, local) in Reg::argument_sequence().zip((0..parameters).map(Local)) {
-            emitter.assert_dirty(reg, local);
-        }
+        emitter.bind_parameters(Reg::argument_sequence())?;
 
         Ok(emitter)
     }
@@ -119,10 +126,19 @@ impl<'a> super::Emitter<'a> for Emitter<'a> {
         (&mut self.cx, &mut self.regs)
     }
 
-    fn epilogue(self) -> io::Result<()> {
-        // Revierte al estado justo antes de la llamada
-        self.move_sp(self.cx.frame_info().offset)?;
-        emit!(self.cx, "l32i", "a0, a1, -4")?;
+    fn frame_size(&self) -> u32 {
+        self.cx.frame_info().offset as u32 * VALUE_SIZE
+    }
+
+    fn epilogue(&mut self) -> io::Result<()> {
+        // l32i solo admite desplazamientos positivos, por lo cual a0 se
+        // restaura antes de revertir al estado justo antes de la llamada
+        let offset = self.cx.frame_info().offset;
+        let a0_offset = VALUE_SIZE as i32 * (offset - 1);
+
+        self.transfer_saved("l32i")?;
+        emit!(self.cx, "l32i", "a0, a1, {}", a0_offset)?;
+        self.move_sp(offset)?;
         emit!(self.cx, "ret.n")
     }
 
@@ -144,6 +160,10 @@ impl<'a> super::Emitter<'a> for Emitter<'a> {
         emit!(self.cx, "l32i", "{0}, {0}, 0", reg)
     }
 
+    fn load_address(&mut self, symbol: &str, reg: Reg) -> io::Result<()> {
+        emit!(self.cx, "movi", "{}, {}", reg, symbol)
+    }
+
     fn store_global(&mut self, reg: Reg, global: &Global) -> io::Result<()> {
         let scratch = self.cx.scratch(&mut self.regs, &[reg])?;
         emit!(self.cx, "movi", "{}, {}", scratch, global.as_ref())?;
@@ -171,6 +191,20 @@ impl<'a> super::Emitter<'a> for Emitter<'a> {
                     Mul => "mull",
                     Div => return self.runtime_op(lhs, rhs, "__divsi3"),
                     Mod => return self.runtime_op(lhs, rhs, "__modsi3"),
+                    BitAnd => "and",
+                    BitOr => "or",
+                    BitXor => "xor",
+
+                    // El conteo pasa por el registro especial SAR
+                    ShiftLeft => {
+                        emit!(self.cx, "ssl", "{}", rhs)?;
+                        return emit!(self.cx, "sll", "{0}, {0}", lhs);
+                    }
+
+                    ShiftRight => {
+                        emit!(self.cx, "ssr", "{}", rhs)?;
+                        return emit!(self.cx, "sra", "{0}, {0}", lhs);
+                    }
                 };
 
                 emit!(self.cx, instruction, "{}, {}, {}", lhs, lhs, rhs)
@@ -232,7 +266,15 @@ impl<'a> super::Emitter<'a> for Emitter<'a> {
     }
 
     fn call(&mut self, target: &Function, _call_info: ()) -> io::Result<()> {
-        emit!(self.cx, "call0", "{}", target.name())
+        match target {
+            // `a0` se sobreescribe de todas formas con la dirección de retorno
+            Function::Indirect(local) => {
+                self.cx.copy_into(Reg(0), *local)?;
+                emit!(self.cx, "callx0", "{}", Reg(0))
+            }
+
+            _ => emit!(self.cx, "call0", "{}", target.name().unwrap_or_default()),
+        }
     }
 
     fn reg_to_local(cx: &Context<'a, Self>, reg: Reg, local: Local) -> io::Result<()> {
@@ -271,6 +313,18 @@ impl<'a> Emitter<'a> {
         Ok(())
     }
 
+    /// Guarda (`s32i`) o restaura (`l32i`) los registros callee-saved que
+    /// utiliza la función, los cuales se ubican justo bajo `a0`.
+    fn transfer_saved(&self, instruction: &str) -> io::Result<()> {
+        let offset = self.cx.frame_info().offset;
+        for (index, reg) in self.cx.callee_saved().iter().enumerate() {
+            let saved_offset = VALUE_SIZE as i32 * (offset - 2 - index as i32);
+            emit!(self.cx, instruction, "{}, a1, {}", reg, saved_offset)?;
+        }
+
+        Ok(())
+    }
+
     /// Corrige el registro de puntero de stack.
     fn move_sp(&self, offset: i32) -> io::Result<()> {
         emit!(self.cx, "addi", "a1, a1, {}", offset * VALUE_SIZE as i32)
@@ -290,15 +344,131 @@ impl<'a> Emitter<'a> {
     /// Determina la posición de una
     fn local_address(cx: &Context<'a, Self>, Local(local): Local) -> String {
         let parameters = cx.function().parameters;
+        let saved = cx.callee_saved().len() as u32;
+
+        // Los registros callee-saved se ubican entre a0 y las locales
         let value_offset = if local < Reg::MAX_ARGS || parameters < Reg::MAX_ARGS {
-            -2 - local as i32
+            -2 - (saved + local) as i32
         } else if local < parameters {
             local as i32
         } else {
-            -2 - (Reg::MAX_ARGS + local - parameters) as i32
+            -2 - (saved + Reg::MAX_ARGS + local - parameters) as i32
         };
 
         let offset = (cx.frame_info().offset + value_offset) * (VALUE_SIZE as i32);
         format!("a1, {}", offset.abs())
     }
 }
+
+/// Elimina `mov.n aN, aN`.
+fn remove_self_move(lines: &mut Vec<String>, at: usize) -> bool {
+    peephole::remove_self_move(lines, at, "mov.n")
+}
+
+/// Sustituye una comparación contra un registro cargado con `movi aZ, 0`
+/// por la forma de la instrucción de salto que compara contra cero.
+/// El `movi` se elimina solo si el cero no se vuelve a leer.
+fn fold_zero_branch(lines: &mut Vec<String>, at: usize) -> bool {
+    let (opcode, lhs, rhs, label) = match Line::parse(&lines[at]) {
+        Some(Line { opcode, operands }) if operands.len() == 3 => {
+            let (lhs, rhs, label) = (operands[0], operands[1], operands[2]);
+            (opcode, lhs.to_owned(), rhs.to_owned(), label.to_owned())
+        }
+
+        _ => return false,
+    };
+
+    let is_zero = |reg: &str| {
+        let zeroed = peephole::last_mention(lines, at, |line| mentions(line, reg))?;
+        match Line::parse(&lines[zeroed]) {
+            Some(Line {
+                opcode: "movi",
+                operands,
+            }) if operands == [reg, "0"] => Some(zeroed),
+            _ => None,
+        }
+    };
+
+    // Solo beq y bne son simétricas si el cero es el operando izquierdo
+    let (replacement, zero, zeroed) = match (opcode, is_zero(&rhs), is_zero(&lhs)) {
+        ("beq", Some(zeroed), _) => ("beqz", &rhs, zeroed),
+        ("bne", Some(zeroed), _) => ("bnez", &rhs, zeroed),
+        ("bge", Some(zeroed), _) => ("bgez", &rhs, zeroed),
+        ("blt", Some(zeroed), _) => ("bltz", &rhs, zeroed),
+        ("beq", None, Some(zeroed)) => ("beqz", &lhs, zeroed),
+        ("bne", None, Some(zeroed)) => ("bnez", &lhs, zeroed),
+        _ => return false,
+    };
+
+    let compared = if zero == &rhs { &lhs } else { &rhs };
+    if compared == zero {
+        return false;
+    }
+
+    lines[at] = Line::format(replacement, &[compared, &label]);
+    if peephole::is_dead_from(lines, at + 1, |line| effect(line, zero)) {
+        lines.remove(zeroed);
+    }
+
+    true
+}
+
+/// Fusiona ajustes consecutivos de `a1` (puntero de pila).
+fn merge_sp(lines: &mut Vec<String>, at: usize) -> bool {
+    let parse = |line: &Line<'_>| match (line.opcode, line.operands.as_slice()) {
+        ("addi", ["a1", "a1", amount]) => amount.parse().ok(),
+        _ => None,
+    };
+
+    // addi codifica un inmediato de 8 bits con signo
+    let format = |total: i64| {
+        let total = total.to_string();
+        Some(Line::format("addi", &["a1", "a1", &total]))
+    };
+
+    let fits = |total: i64| Some(total).filter(|total| (-128..=127).contains(total));
+    peephole::merge_stack_adjustments(lines, at, parse, |total| fits(total).and_then(format))
+}
+
+/// Determina si una instrucción menciona un registro.
+fn mentions(line: &Line<'_>, reg: &str) -> bool {
+    line.operands.contains(&reg)
+}
+
+/// Efecto de una instrucción sobre el valor de un registro.
+fn effect(line: &Line<'_>, reg: &str) -> Effect {
+    use super::Register;
+
+    // Un registro callee-saved puede ser el hogar de una local, cuyo
+    // valor sobrevive a saltos y llamadas
+    if Reg::CALLEE_SAVED.iter().any(|saved| saved.to_string() == reg) {
+        return Effect::Reads;
+    }
+
+    // Los demás registros reservables son caller-saved para call0
+    let is_argument = Reg::argument_sequence().any(|arg| arg.to_string() == reg);
+    match line.opcode {
+        "j.l" => return Effect::Kills,
+        "ret.n" if reg == "a2" => return Effect::Reads,
+        "ret.n" => return Effect::Kills,
+        "call0" if is_argument => return Effect::Reads,
+        "call0" => return Effect::Kills,
+        _ if !mentions(line, reg) => return Effect::Ignores,
+        _ => (),
+    }
+
+    // Instrucciones cuyo primer operando es el destino
+    const WRITES_FIRST: &[&str] = &[
+        "movi", "l32i", "mov.n", "add", "addi", "sub", "mull", "neg", "xor",
+    ];
+
+    match line.operands.split_first() {
+        Some((target, sources))
+            if WRITES_FIRST.contains(&line.opcode) && *target == reg && !sources.contains(&reg) =>
+        {
+            Effect::Kills
+        }
+
+        _ => Effect::Reads,
+    }
+}
@@ -0,0 +1,355 @@
+//! Escritura de objetos ELF reubicables de 32 bits, little-endian.
+//!
+//! Solo se contempla lo que un objeto ensamblado necesita para
+//! enlazarse: secciones, una tabla de símbolos y relocalizaciones con
+//! addend explícito (`SHT_RELA`).
+
+use std::collections::HashMap;
+
+const EHDR_SIZE: u32 = 52;
+const SHDR_SIZE: u32 = 40;
+const SYM_SIZE: u32 = 16;
+const RELA_SIZE: u32 = 12;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+
+const SHF_WRITE: u32 = 0x01;
+const SHF_ALLOC: u32 = 0x02;
+const SHF_EXECINSTR: u32 = 0x04;
+const SHF_INFO_LINK: u32 = 0x40;
+
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STT_NOTYPE: u8 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_SECTION: u8 = 3;
+
+/// Contenido y permisos de una sección.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum SectionKind {
+    /// Código ejecutable.
+    Code,
+
+    /// Datos constantes.
+    ReadOnly,
+
+    /// Datos sin inicializar, ocupan memoria pero no espacio en el objeto.
+    Bss,
+
+    /// Sección que no se carga a memoria, como [`crate::ledmap::SECTION`].
+    Metadata,
+}
+
+/// Una sección del objeto.
+pub struct Section {
+    pub name: String,
+    pub kind: SectionKind,
+    pub align: u32,
+
+    /// Contenido, o ceros en el caso de [`SectionKind::Bss`].
+    pub bytes: Vec<u8>,
+
+    pub relocations: Vec<Relocation>,
+}
+
+/// Una relocalización sobre el contenido de una sección.
+pub struct Relocation {
+    pub offset: u32,
+    pub kind: u8,
+    pub target: Target,
+    pub addend: i32,
+}
+
+/// Símbolo respecto al cual se resuelve una relocalización.
+pub enum Target {
+    /// Inicio de una sección del mismo objeto, por índice en [`Object::sections`].
+    Section(usize),
+
+    /// Un símbolo por nombre. Si el objeto no lo define, se importa.
+    Symbol(String),
+}
+
+/// Un símbolo definido por el objeto.
+pub struct Symbol {
+    pub name: String,
+    pub section: usize,
+    pub value: u32,
+    pub global: bool,
+}
+
+/// Un objeto reubicable.
+pub struct Object {
+    pub machine: u16,
+    pub flags: u32,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+}
+
+impl Object {
+    /// Serializa el objeto.
+    pub fn write(&self) -> Vec<u8> {
+        let mut strtab = StringTable::default();
+        let mut shstrtab = StringTable::default();
+
+        // Orden requerido: nulo, secciones, locales y luego globales
+        let mut symbols = vec![[0; SYM_SIZE as usize]];
+        let mut indices = HashMap::new();
+
+        for index in 0..self.sections.len() {
+            symbols.push(symbol(0, 0, STB_LOCAL, STT_SECTION, index as u16 + 1));
+        }
+
+        let (globals, locals): (Vec<_>, Vec<_>) = self.symbols.iter().partition(|sym| sym.global);
+        let first_global = symbols.len() + locals.len();
+
+        for sym in locals.into_iter().chain(globals) {
+            let kind = match self.sections[sym.section].kind {
+                SectionKind::Code => STT_FUNC,
+                _ => STT_OBJECT,
+            };
+
+            let bind = if sym.global { STB_GLOBAL } else { STB_LOCAL };
+            let name = strtab.insert(&sym.name);
+
+            indices.insert(sym.name.as_str(), symbols.len());
+            symbols.push(symbol(name, sym.value, bind, kind, sym.section as u16 + 1));
+        }
+
+        // Símbolos referidos pero no definidos
+        let relocations = self
+            .sections
+            .iter()
+            .flat_map(|section| &section.relocations);
+        for relocation in relocations {
+            if let Target::Symbol(name) = &relocation.target {
+                if !indices.contains_key(name.as_str()) {
+                    let offset = strtab.insert(name);
+                    indices.insert(name.as_str(), symbols.len());
+                    symbols.push(symbol(offset, 0, STB_GLOBAL, STT_NOTYPE, 0));
+                }
+            }
+        }
+
+        let mut output = vec![0; EHDR_SIZE as usize];
+        let mut headers = vec![[0; SHDR_SIZE as usize]];
+
+        for section in &self.sections {
+            let (kind, flags) = match section.kind {
+                SectionKind::Code => (SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR),
+                SectionKind::ReadOnly => (SHT_PROGBITS, SHF_ALLOC),
+                SectionKind::Bss => (SHT_NOBITS, SHF_ALLOC | SHF_WRITE),
+                SectionKind::Metadata => (SHT_PROGBITS, 0),
+            };
+
+            let offset = pad(&mut output, section.align);
+            if kind != SHT_NOBITS {
+                output.extend_from_slice(&section.bytes);
+            }
+
+            headers.push(section_header(SectionHeader {
+                name: shstrtab.insert(&section.name),
+                kind,
+                flags,
+                offset,
+                size: section.bytes.len() as u32,
+                align: section.align,
+                ..Default::default()
+            }));
+        }
+
+        let symtab_index = (headers.len() + self.relocated().count()) as u32;
+        for (index, section) in self.relocated() {
+            let offset = pad(&mut output, 4);
+            for relocation in &section.relocations {
+                let symbol = match &relocation.target {
+                    Target::Section(section) => section + 1,
+                    Target::Symbol(name) => indices[name.as_str()],
+                };
+
+                let info = (symbol as u32) << 8 | u32::from(relocation.kind);
+                output.extend_from_slice(&relocation.offset.to_le_bytes());
+                output.extend_from_slice(&info.to_le_bytes());
+                output.extend_from_slice(&relocation.addend.to_le_bytes());
+            }
+
+            headers.push(section_header(SectionHeader {
+                name: shstrtab.insert(&format!(".rela{}", section.name)),
+                kind: SHT_RELA,
+                flags: SHF_INFO_LINK,
+                offset,
+                size: section.relocations.len() as u32 * RELA_SIZE,
+                link: symtab_index,
+                info: index as u32 + 1,
+                align: 4,
+                entry_size: RELA_SIZE,
+            }));
+        }
+
+        let offset = pad(&mut output, 4);
+        for entry in &symbols {
+            output.extend_from_slice(entry);
+        }
+
+        headers.push(section_header(SectionHeader {
+            name: shstrtab.insert(".symtab"),
+            kind: SHT_SYMTAB,
+            offset,
+            size: symbols.len() as u32 * SYM_SIZE,
+            link: symtab_index + 1,
+            info: first_global as u32,
+            align: 4,
+            entry_size: SYM_SIZE,
+            ..Default::default()
+        }));
+
+        let offset = output.len() as u32;
+        output.extend_from_slice(&strtab.bytes);
+
+        headers.push(section_header(SectionHeader {
+            name: shstrtab.insert(".strtab"),
+            kind: SHT_STRTAB,
+            offset,
+            size: strtab.bytes.len() as u32,
+            align: 1,
+            ..Default::default()
+        }));
+
+        let name = shstrtab.insert(".shstrtab");
+        let offset = output.len() as u32;
+        output.extend_from_slice(&shstrtab.bytes);
+
+        headers.push(section_header(SectionHeader {
+            name,
+            kind: SHT_STRTAB,
+            offset,
+            size: shstrtab.bytes.len() as u32,
+            align: 1,
+            ..Default::default()
+        }));
+
+        let header_offset = pad(&mut output, 4);
+        for header in &headers {
+            output.extend_from_slice(header);
+        }
+
+        let header = self.file_header(header_offset, headers.len() as u16);
+        output[..EHDR_SIZE as usize].copy_from_slice(&header);
+
+        output
+    }
+
+    /// Secciones que requieren una sección de relocalizaciones.
+    fn relocated(&self) -> impl Iterator<Item = (usize, &Section)> {
+        self.sections
+            .iter()
+            .enumerate()
+            .filter(|(_, section)| !section.relocations.is_empty())
+    }
+
+    fn file_header(&self, section_headers: u32, sections: u16) -> Vec<u8> {
+        // Clase de 32 bits, little-endian, versión 1
+        let mut header = vec![0x7f, b'E', b'L', b'F', 1, 1, 1];
+        header.resize(16, 0);
+
+        // ET_REL
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&self.machine.to_le_bytes());
+        header.extend_from_slice(&1u32.to_le_bytes());
+
+        // Sin punto de entrada ni program headers
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&section_headers.to_le_bytes());
+        header.extend_from_slice(&self.flags.to_le_bytes());
+
+        header.extend_from_slice(&(EHDR_SIZE as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&(SHDR_SIZE as u16).to_le_bytes());
+        header.extend_from_slice(&sections.to_le_bytes());
+
+        // .shstrtab siempre es la última sección
+        header.extend_from_slice(&(sections - 1).to_le_bytes());
+        header
+    }
+}
+
+/// Tabla de strings terminados en nulo. El primero es siempre vacío.
+struct StringTable {
+    bytes: Vec<u8>,
+}
+
+impl Default for StringTable {
+    fn default() -> Self {
+        StringTable { bytes: vec![0] }
+    }
+}
+
+impl StringTable {
+    fn insert(&mut self, string: &str) -> u32 {
+        let offset = self.bytes.len() as u32;
+        self.bytes.extend_from_slice(string.as_bytes());
+        self.bytes.push(0);
+
+        offset
+    }
+}
+
+#[derive(Default)]
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u32,
+    offset: u32,
+    size: u32,
+    link: u32,
+    info: u32,
+    align: u32,
+    entry_size: u32,
+}
+
+fn section_header(header: SectionHeader) -> [u8; SHDR_SIZE as usize] {
+    let fields = [
+        header.name,
+        header.kind,
+        header.flags,
+        0,
+        header.offset,
+        header.size,
+        header.link,
+        header.info,
+        header.align,
+        header.entry_size,
+    ];
+
+    let mut bytes = [0; SHDR_SIZE as usize];
+    for (chunk, field) in bytes.chunks_mut(4).zip(fields.iter()) {
+        chunk.copy_from_slice(&field.to_le_bytes());
+    }
+
+    bytes
+}
+
+fn symbol(name: u32, value: u32, bind: u8, kind: u8, section: u16) -> [u8; SYM_SIZE as usize] {
+    let mut bytes = [0; SYM_SIZE as usize];
+    bytes[0..4].copy_from_slice(&name.to_le_bytes());
+    bytes[4..8].copy_from_slice(&value.to_le_bytes());
+    bytes[12] = bind << 4 | kind;
+    bytes[14..16].copy_from_slice(&section.to_le_bytes());
+
+    bytes
+}
+
+/// Alinea el final de `output` con ceros, retornando el nuevo final.
+fn pad(output: &mut Vec<u8>, align: u32) -> u32 {
+    let align = align.max(1) as usize;
+    let padded = (output.len() + align - 1) / align * align;
+    output.resize(padded, 0);
+
+    padded as u32
+}
@@ -0,0 +1,45 @@
+//! Ensamblado dentro del proceso.
+//!
+//! Normalmente el código que emite [`crate::target`] se entrega al
+//! ensamblador de `binutils`, ver [`crate::link`]. Para Xtensa existe
+//! además [`xtensa`], que codifica directamente el subconjunto de
+//! instrucciones y directivas que emite el compilador y produce un
+//! objeto ELF reubicable. De esta forma solo el enlazado final requiere
+//! de una herramienta externa.
+//!
+//! Este ensamblador no pretende aceptar ensamblador arbitrario: toda
+//! línea que no corresponda a una forma que el compilador emite se
+//! rechaza en vez de interpretarse de forma aproximada.
+
+use thiserror::Error;
+
+mod elf;
+pub mod xtensa;
+
+/// Un error de ensamblado. Las líneas se cuentan desde 1.
+#[derive(Error, Debug)]
+pub enum AssemblerError {
+    /// La línea no corresponde a ninguna forma conocida.
+    #[error("Line {0}: cannot assemble `{1}`")]
+    Syntax(usize, String),
+
+    /// La línea es válida para GNU as, pero no para este ensamblador.
+    #[error("Line {0}: {1} is not supported by the internal assembler")]
+    Unsupported(usize, &'static str),
+
+    /// Un inmediato o desplazamiento no cabe en su instrucción.
+    #[error("Line {0}: operand out of range in `{1}`")]
+    OutOfRange(usize, String),
+
+    /// Un salto no alcanza su destino aún luego de relajarse.
+    #[error("Line {0}: label `{1}` is out of jump range")]
+    TooFar(usize, String),
+
+    /// Un salto refiere a una etiqueta que no existe en su sección.
+    #[error("Line {0}: undefined label `{1}`")]
+    Undefined(usize, String),
+
+    /// Una etiqueta se definió más de una vez.
+    #[error("Line {0}: label `{1}` is already defined")]
+    Redefined(usize, String),
+}
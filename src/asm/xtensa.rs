@@ -0,0 +1,942 @@
+//! Ensamblador interno para Xtensa.
+//!
+//! Codifica las instrucciones que emite [`crate::arch::xtensa`] y las
+//! directivas que emite [`crate::codegen`], produciendo un objeto
+//! equivalente al que generaría GNU as para ESP8266.
+//!
+//! # Literales
+//! `movi` solo codifica inmediatos de 12 bits. Para constantes mayores y
+//! direcciones de símbolos se emite en cambio un `l32r` contra un literal.
+//! Tal como en GNU as, los literales de `.text.<función>` residen en una
+//! sección `.literal.<función>` que la precede, lo cual el linker script
+//! de ESP8266 ya contempla.
+//!
+//! # Saltos
+//! Los saltos dentro de una misma sección se resuelven aquí. Si un salto
+//! condicional no alcanza su destino, se sustituye por el salto con la
+//! condición inversa sobre un `j` incondicional, igual que la relajación
+//! que realiza GNU as. `call0` y `l32r` se resuelven al enlazar.
+//!
+//! # Manual de ISA
+//! <https://0x04.net/~mwk/doc/xtensa.pdf>, capítulo 7 para formatos.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    elf::{Object, Relocation, Section, SectionKind, Symbol, Target},
+    AssemblerError,
+};
+
+/// `EM_XTENSA`.
+const MACHINE: u16 = 94;
+
+/// `EF_XTENSA_XT_INSN | EF_XTENSA_XT_LIT`, igual que GNU as.
+const FLAGS: u32 = 0x300;
+
+const R_XTENSA_32: u8 = 1;
+const R_XTENSA_SLOT0_OP: u8 = 20;
+
+/// Ensambla un listado completo, retornando el objeto ELF resultante.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssemblerError> {
+    let mut assembler = Assembler::default();
+    for (index, line) in source.lines().enumerate() {
+        assembler.line(index + 1, line)?;
+    }
+
+    assembler.relax()?;
+    assembler.finish().map(|object| object.write())
+}
+
+/// Estado de ensamblado.
+#[derive(Default)]
+struct Assembler {
+    sections: Vec<Pending>,
+    current: Option<usize>,
+    globals: HashSet<String>,
+    defined: HashSet<String>,
+}
+
+/// Sección en construcción.
+struct Pending {
+    name: String,
+    kind: SectionKind,
+    items: Vec<Item>,
+
+    /// Contenido de la sección `.literal` asociada.
+    literals: Vec<Expr>,
+}
+
+/// Elemento de una sección, en orden.
+enum Item {
+    Label(String),
+    Align(u32),
+    Bytes(Vec<u8>),
+    Words(usize, Vec<Expr>),
+    Op(usize, Op),
+}
+
+/// Operación ya decodificada, pendiente de conocer la posición de etiquetas.
+enum Op {
+    /// Una o más instrucciones de 24 bits ya codificadas.
+    Wide(Vec<u32>),
+
+    /// Instrucción de 16 bits ya codificada.
+    Narrow(u16),
+
+    /// Salto condicional a una etiqueta de la misma sección.
+    Branch {
+        condition: Condition,
+        s: u8,
+        t: u8,
+        target: String,
+        long: bool,
+    },
+
+    /// `j` a una etiqueta de la misma sección.
+    Jump(String),
+
+    /// `call0` a un símbolo, resuelto por el enlazador.
+    Call(String),
+
+    /// `l32r` de un literal en la sección `.literal` asociada.
+    LoadLiteral(u8, usize),
+}
+
+/// Valor de un dato de 32 bits.
+#[derive(Clone, PartialEq, Eq)]
+enum Expr {
+    Number(i64),
+    Symbol(String),
+
+    /// Diferencia entre dos etiquetas de la misma sección.
+    Difference(String, String),
+}
+
+/// Condición de un salto.
+#[derive(Copy, Clone)]
+enum Condition {
+    Eqz,
+    Nez,
+    Ltz,
+    Gez,
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+}
+
+impl Condition {
+    fn inverse(self) -> Self {
+        use Condition::*;
+
+        match self {
+            Eqz => Nez,
+            Nez => Eqz,
+            Ltz => Gez,
+            Gez => Ltz,
+            Eq => Ne,
+            Ne => Eq,
+            Lt => Ge,
+            Ge => Lt,
+        }
+    }
+
+    /// Codifica el salto con un desplazamiento relativo a `PC + 4`,
+    /// si este cabe en la instrucción.
+    fn encode(self, s: u8, t: u8, offset: i64) -> Option<u32> {
+        use Condition::*;
+
+        let (s, t) = (u32::from(s), u32::from(t));
+        match self {
+            // Formato BRI12
+            Eqz | Nez | Ltz | Gez => {
+                let m = match self {
+                    Eqz => 0,
+                    Nez => 1,
+                    Ltz => 2,
+                    _ => 3,
+                };
+
+                let imm = signed(offset, 12)?;
+                Some(imm << 12 | s << 8 | m << 6 | 1 << 4 | 0x6)
+            }
+
+            // Formato RRI8
+            Eq | Ne | Lt | Ge => {
+                let r = match self {
+                    Eq => 0x1,
+                    Ne => 0x9,
+                    Lt => 0x2,
+                    _ => 0xa,
+                };
+
+                let imm = signed(offset, 8)?;
+                Some(imm << 16 | r << 12 | s << 8 | t << 4 | 0x7)
+            }
+        }
+    }
+}
+
+impl Item {
+    /// Bytes que ocupa el elemento si inicia en `offset`.
+    fn size(&self, offset: u32) -> u32 {
+        match self {
+            Item::Label(_) => 0,
+            Item::Align(align) => (align - offset % align) % align,
+            Item::Bytes(bytes) => bytes.len() as u32,
+            Item::Words(_, words) => 4 * words.len() as u32,
+            Item::Op(_, op) => op.size(),
+        }
+    }
+}
+
+impl Op {
+    fn size(&self) -> u32 {
+        match self {
+            Op::Wide(words) => 3 * words.len() as u32,
+            Op::Narrow(_) => 2,
+            Op::Branch { long: true, .. } => 6,
+            _ => 3,
+        }
+    }
+}
+
+impl Assembler {
+    fn line(&mut self, number: usize, text: &str) -> Result<(), AssemblerError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(());
+        } else if let Some(label) = text.strip_suffix(':') {
+            if !is_symbol(label) {
+                return Err(AssemblerError::Syntax(number, text.to_owned()));
+            } else if !self.defined.insert(label.to_owned()) {
+                return Err(AssemblerError::Redefined(number, label.to_owned()));
+            }
+
+            let section = self.current();
+            self.sections[section]
+                .items
+                .push(Item::Label(label.to_owned()));
+            return Ok(());
+        }
+
+        let (mnemonic, operands) = split_mnemonic(text);
+        if mnemonic.starts_with('.') {
+            self.directive(number, text, mnemonic, operands)
+        } else {
+            self.instruction(number, text, mnemonic, operands)
+        }
+    }
+
+    fn directive(
+        &mut self,
+        number: usize,
+        text: &str,
+        directive: &str,
+        operands: &str,
+    ) -> Result<(), AssemblerError> {
+        let syntax = || AssemblerError::Syntax(number, text.to_owned());
+
+        let item = match directive {
+            ".text" => {
+                self.current = Some(self.section(".text", None));
+                return Ok(());
+            }
+
+            ".section" => {
+                let (name, flags) = match operands.split_once(',') {
+                    Some((name, flags)) => {
+                        let flags = parse_string(flags.trim())
+                            .and_then(|flags| String::from_utf8(flags).ok())
+                            .ok_or_else(syntax)?;
+
+                        (name.trim(), Some(flags))
+                    }
+
+                    None => (operands, None),
+                };
+
+                if !is_symbol(name) {
+                    return Err(syntax());
+                }
+
+                self.current = Some(self.section(name, flags.as_deref()));
+                return Ok(());
+            }
+
+            ".global" | ".globl" if is_symbol(operands) => {
+                self.globals.insert(operands.to_owned());
+                return Ok(());
+            }
+
+            // Los símbolos de .lcomm son locales y residen en .bss
+            ".lcomm" => {
+                let (name, size) = operands.split_once(',').ok_or_else(syntax)?;
+                let name = name.trim();
+
+                let size = match parse_number(size.trim()) {
+                    Some(size) if is_symbol(name) && (0..=0xffff).contains(&size) => size,
+                    _ => return Err(syntax()),
+                };
+
+                if !self.defined.insert(name.to_owned()) {
+                    return Err(AssemblerError::Redefined(number, name.to_owned()));
+                }
+
+                let bss = self.section(".bss", None);
+                let items = &mut self.sections[bss].items;
+
+                items.push(Item::Align(4));
+                items.push(Item::Label(name.to_owned()));
+                items.push(Item::Bytes(vec![0; size as usize]));
+                return Ok(());
+            }
+
+            ".balign" => match parse_number(operands) {
+                Some(align) if align > 0 && align <= 4096 && align.count_ones() == 1 => {
+                    Item::Align(align as u32)
+                }
+
+                _ => return Err(syntax()),
+            },
+
+            ".asciz" => {
+                let mut bytes = parse_string(operands).ok_or_else(syntax)?;
+                bytes.push(0);
+
+                Item::Bytes(bytes)
+            }
+
+            ".byte" => {
+                let bytes = operands
+                    .split(',')
+                    .map(|byte| {
+                        parse_number(byte.trim()).filter(|byte| (-128..=255).contains(byte))
+                    })
+                    .map(|byte| byte.map(|byte| byte as u8))
+                    .collect::<Option<_>>()
+                    .ok_or_else(syntax)?;
+
+                Item::Bytes(bytes)
+            }
+
+            ".4byte" => {
+                let words = operands
+                    .split(',')
+                    .map(|word| parse_expr(word.trim()))
+                    .collect::<Option<_>>()
+                    .ok_or_else(syntax)?;
+
+                Item::Words(number, words)
+            }
+
+            ".file" | ".loc" => {
+                return Err(AssemblerError::Unsupported(number, "debug information"));
+            }
+
+            _ => return Err(syntax()),
+        };
+
+        let section = self.current();
+        self.sections[section].items.push(item);
+
+        Ok(())
+    }
+
+    fn instruction(
+        &mut self,
+        number: usize,
+        text: &str,
+        mnemonic: &str,
+        operands: &str,
+    ) -> Result<(), AssemblerError> {
+        let operands: Vec<&str> = match operands {
+            "" => Vec::new(),
+            operands => operands.split(',').map(str::trim).collect(),
+        };
+
+        let syntax = || AssemblerError::Syntax(number, text.to_owned());
+        let out_of_range = || AssemblerError::OutOfRange(number, text.to_owned());
+
+        let reg = |index: usize| parse_reg(operands[index]).ok_or_else(syntax);
+        let imm = |index: usize| parse_number(operands[index]).ok_or_else(syntax);
+        let label = |index: usize| match operands[index] {
+            label if is_symbol(label) => Ok(label.to_owned()),
+            _ => Err(syntax()),
+        };
+
+        let branch = |condition, s, t, target| Op::Branch {
+            condition,
+            s,
+            t,
+            target,
+            long: false,
+        };
+
+        let op = match (mnemonic, operands.len()) {
+            ("add", 3) => rrr(0x8, 0x0, reg(0)?, reg(1)?, reg(2)?),
+            ("sub", 3) => rrr(0xc, 0x0, reg(0)?, reg(1)?, reg(2)?),
+            ("and", 3) => rrr(0x1, 0x0, reg(0)?, reg(1)?, reg(2)?),
+            ("or", 3) => rrr(0x2, 0x0, reg(0)?, reg(1)?, reg(2)?),
+            ("xor", 3) => rrr(0x3, 0x0, reg(0)?, reg(1)?, reg(2)?),
+            ("mull", 3) => rrr(0x8, 0x2, reg(0)?, reg(1)?, reg(2)?),
+            ("neg", 2) => rrr(0x6, 0x0, reg(0)?, 0, reg(1)?),
+            ("ssl", 1) => rrr(0x4, 0x0, 0x1, reg(0)?, 0),
+            ("ssr", 1) => rrr(0x4, 0x0, 0x0, reg(0)?, 0),
+            ("sll", 2) => rrr(0xa, 0x1, reg(0)?, reg(1)?, 0),
+            ("sra", 2) => rrr(0xb, 0x1, reg(0)?, 0, reg(1)?),
+            ("callx0", 1) => rrr(0x0, 0x0, 0x0, reg(0)?, 0xc),
+
+            // Formato RRRN, op0 = 1101
+            ("mov.n", 2) => Op::Narrow(u16::from(reg(1)?) << 8 | u16::from(reg(0)?) << 4 | 0xd),
+            ("ret.n", 0) => Op::Narrow(0xf00d),
+
+            // El desplazamiento se codifica en palabras
+            ("l32i", 3) | ("s32i", 3) => {
+                let r = if mnemonic == "l32i" { 0x2 } else { 0x6 };
+                let offset = imm(2)?;
+
+                if offset % 4 != 0 || !(0..=1020).contains(&offset) {
+                    return Err(out_of_range());
+                }
+
+                Op::Wide(vec![rri8(offset as u32 / 4, r, reg(1)?, reg(0)?)])
+            }
+
+            // Un inmediato de más de 8 bits requiere de un addmi previo
+            ("addi", 3) => {
+                let (t, s, value) = (reg(0)?, reg(1)?, imm(2)?);
+                let low = ((value + 128) & 0xff) - 128;
+                let high = (value - low) >> 8;
+
+                match (signed(low, 8), signed(high, 8)) {
+                    (Some(low), Some(0)) => Op::Wide(vec![rri8(low, 0xc, s, t)]),
+                    (Some(0), Some(high)) => Op::Wide(vec![rri8(high, 0xd, s, t)]),
+                    (Some(low), Some(high)) => {
+                        Op::Wide(vec![rri8(high, 0xd, s, t), rri8(low, 0xc, t, t)])
+                    }
+
+                    _ => return Err(out_of_range()),
+                }
+            }
+
+            ("movi", 2) => {
+                let t = reg(0)?;
+                match parse_number(operands[1]) {
+                    // La parte alta del inmediato se codifica en el campo s
+                    Some(value) => match signed(value, 12) {
+                        Some(imm) => Op::Wide(vec![rri8(imm & 0xff, 0xa, (imm >> 8) as u8, t)]),
+                        None if (-(1 << 31)..1 << 32).contains(&value) => {
+                            Op::LoadLiteral(t, self.literal(Expr::Number(value)))
+                        }
+
+                        None => return Err(out_of_range()),
+                    },
+
+                    None => Op::LoadLiteral(t, self.literal(Expr::Symbol(label(1)?))),
+                }
+            }
+
+            ("call0", 1) => Op::Call(label(0)?),
+
+            // j.l es una macro de GNU as, su registro temporal no se utiliza
+            ("j", 1) | ("j.l", 2) => Op::Jump(label(0)?),
+
+            ("beqz", 2) => branch(Condition::Eqz, reg(0)?, 0, label(1)?),
+            ("bnez", 2) => branch(Condition::Nez, reg(0)?, 0, label(1)?),
+            ("bltz", 2) => branch(Condition::Ltz, reg(0)?, 0, label(1)?),
+            ("bgez", 2) => branch(Condition::Gez, reg(0)?, 0, label(1)?),
+            ("beq", 3) => branch(Condition::Eq, reg(0)?, reg(1)?, label(2)?),
+            ("bne", 3) => branch(Condition::Ne, reg(0)?, reg(1)?, label(2)?),
+            ("blt", 3) => branch(Condition::Lt, reg(0)?, reg(1)?, label(2)?),
+            ("bge", 3) => branch(Condition::Ge, reg(0)?, reg(1)?, label(2)?),
+
+            _ => return Err(syntax()),
+        };
+
+        self.push_op(number, op)
+    }
+
+    fn push_op(&mut self, number: usize, op: Op) -> Result<(), AssemblerError> {
+        let section = self.current();
+        self.sections[section].items.push(Item::Op(number, op));
+
+        Ok(())
+    }
+
+    /// Índice de un literal de la sección actual, agregándolo si no existe.
+    fn literal(&mut self, value: Expr) -> usize {
+        let section = self.current();
+        let literals = &mut self.sections[section].literals;
+
+        match literals.iter().position(|literal| *literal == value) {
+            Some(index) => index,
+            None => {
+                literals.push(value);
+                literals.len() - 1
+            }
+        }
+    }
+
+    /// Sección actual. Al igual que en GNU as, inicialmente es `.text`.
+    fn current(&mut self) -> usize {
+        match self.current {
+            Some(section) => section,
+            None => {
+                let section = self.section(".text", None);
+                self.current = Some(section);
+
+                section
+            }
+        }
+    }
+
+    /// Encuentra o crea una sección. Sin banderas, estas se deducen del nombre.
+    fn section(&mut self, name: &str, flags: Option<&str>) -> usize {
+        if let Some(index) = self
+            .sections
+            .iter()
+            .position(|section| section.name == name)
+        {
+            return index;
+        }
+
+        let has_prefix = |prefix: &str| name == prefix || name.starts_with(&format!("{}.", prefix));
+        let kind = match flags {
+            Some(flags) if flags.contains('x') => SectionKind::Code,
+            Some(flags) if flags.contains('a') => SectionKind::ReadOnly,
+            Some(_) => SectionKind::Metadata,
+            None if has_prefix(".text") => SectionKind::Code,
+            None if has_prefix(".rodata") => SectionKind::ReadOnly,
+            None if has_prefix(".bss") => SectionKind::Bss,
+            None => SectionKind::Metadata,
+        };
+
+        self.sections.push(Pending {
+            name: name.to_owned(),
+            kind,
+            items: Vec::new(),
+            literals: Vec::new(),
+        });
+
+        self.sections.len() - 1
+    }
+
+    /// Extiende los saltos condicionales que no alcanzan su destino.
+    ///
+    /// Extender un salto solo puede alejar a otros de su destino, por
+    /// lo cual basta con repetir hasta que ningún salto cambie.
+    fn relax(&mut self) -> Result<(), AssemblerError> {
+        loop {
+            let labels = self.labels();
+            let mut relaxed = Vec::new();
+
+            for (index, section) in self.sections.iter().enumerate() {
+                for (position, (offset, item)) in layout(&section.items).enumerate() {
+                    if let Item::Op(
+                        number,
+                        Op::Branch {
+                            condition,
+                            target,
+                            long: false,
+                            ..
+                        },
+                    ) = item
+                    {
+                        let target = local(&labels, index, *number, target)?;
+                        let displacement = i64::from(target) - i64::from(offset) - 4;
+
+                        if condition.encode(0, 0, displacement).is_none() {
+                            relaxed.push((index, position));
+                        }
+                    }
+                }
+            }
+
+            if relaxed.is_empty() {
+                return Ok(());
+            }
+
+            for (section, position) in relaxed {
+                if let Item::Op(_, Op::Branch { long, .. }) =
+                    &mut self.sections[section].items[position]
+                {
+                    *long = true;
+                }
+            }
+        }
+    }
+
+    /// Posición de cada etiqueta como sección y desplazamiento.
+    fn labels(&self) -> HashMap<&str, (usize, u32)> {
+        let mut labels = HashMap::new();
+        for (index, section) in self.sections.iter().enumerate() {
+            for (offset, item) in layout(&section.items) {
+                if let Item::Label(label) = item {
+                    labels.insert(label.as_str(), (index, offset));
+                }
+            }
+        }
+
+        labels
+    }
+
+    /// Codifica todas las secciones, cada una precedida por su sección
+    /// de literales si la tiene.
+    fn finish(self) -> Result<Object, AssemblerError> {
+        let labels = self.labels();
+
+        let mut indices = Vec::new();
+        let mut next = 0;
+
+        for section in &self.sections {
+            let literals = if section.literals.is_empty() {
+                None
+            } else {
+                next += 1;
+                Some(next - 1)
+            };
+
+            indices.push((literals, next));
+            next += 1;
+        }
+
+        // Las etiquetas .L no llegan a la tabla de símbolos, ni tampoco los
+        // símbolos locales, por lo cual se refieren respecto a su sección
+        let target = |name: &str| match labels.get(name) {
+            Some((section, offset)) if !self.globals.contains(name) => {
+                (Target::Section(indices[*section].1), *offset as i32)
+            }
+
+            _ => (Target::Symbol(name.to_owned()), 0),
+        };
+
+        let mut sections = Vec::new();
+        for (index, section) in self.sections.iter().enumerate() {
+            let (literals, _) = indices[index];
+
+            if literals.is_some() {
+                let mut pool = Section {
+                    name: literal_section(&section.name),
+                    kind: SectionKind::Code,
+                    align: 4,
+                    bytes: Vec::new(),
+                    relocations: Vec::new(),
+                };
+
+                for literal in &section.literals {
+                    word(&mut pool, literal, &labels, 0, &target)?;
+                }
+
+                sections.push(pool);
+            }
+
+            let mut encoded = Section {
+                name: section.name.clone(),
+                kind: section.kind,
+                align: 1,
+                bytes: Vec::new(),
+                relocations: Vec::new(),
+            };
+
+            for (offset, item) in layout(&section.items) {
+                match item {
+                    Item::Label(_) => (),
+
+                    Item::Align(align) => {
+                        encoded.align = encoded.align.max(*align);
+                        encoded
+                            .bytes
+                            .resize((offset + item.size(offset)) as usize, 0);
+                    }
+
+                    Item::Bytes(bytes) => encoded.bytes.extend_from_slice(bytes),
+
+                    Item::Words(number, words) => {
+                        for value in words {
+                            word(&mut encoded, value, &labels, *number, &target)?;
+                        }
+                    }
+
+                    Item::Op(number, op) => {
+                        let number = *number;
+                        let too_far =
+                            |target: &str| AssemblerError::TooFar(number, target.to_owned());
+
+                        let relative = |target: &str, next: u32| -> Result<i64, AssemblerError> {
+                            let target = local(&labels, index, number, target)?;
+                            Ok(i64::from(target) - i64::from(offset + next))
+                        };
+
+                        match op {
+                            Op::Wide(words) => {
+                                for word in words {
+                                    wide(&mut encoded, *word);
+                                }
+                            }
+
+                            Op::Narrow(halfword) => {
+                                encoded.bytes.extend_from_slice(&halfword.to_le_bytes())
+                            }
+
+                            Op::Branch {
+                                condition,
+                                s,
+                                t,
+                                target,
+                                long: false,
+                            } => {
+                                let displacement = relative(target, 4)?;
+                                let branch = condition.encode(*s, *t, displacement);
+                                wide(&mut encoded, branch.ok_or_else(|| too_far(target))?);
+                            }
+
+                            // Condición inversa que salta sobre el j siguiente
+                            Op::Branch {
+                                condition,
+                                s,
+                                t,
+                                target,
+                                long: true,
+                            } => {
+                                let skip = condition.inverse().encode(*s, *t, 2).unwrap();
+                                let jump =
+                                    jump(relative(target, 7)?).ok_or_else(|| too_far(target))?;
+
+                                wide(&mut encoded, skip);
+                                wide(&mut encoded, jump);
+                            }
+
+                            Op::Jump(target) => {
+                                let jump =
+                                    jump(relative(target, 4)?).ok_or_else(|| too_far(target))?;
+                                wide(&mut encoded, jump);
+                            }
+
+                            // Formato CALL, el desplazamiento lo coloca el enlazador
+                            Op::Call(symbol) => {
+                                let (target, addend) = target(symbol);
+                                encoded.relocations.push(Relocation {
+                                    offset: encoded.bytes.len() as u32,
+                                    kind: R_XTENSA_SLOT0_OP,
+                                    target,
+                                    addend,
+                                });
+
+                                wide(&mut encoded, 0x5);
+                            }
+
+                            // Formato RI16, el desplazamiento lo coloca el enlazador
+                            Op::LoadLiteral(t, literal) => {
+                                encoded.relocations.push(Relocation {
+                                    offset: encoded.bytes.len() as u32,
+                                    kind: R_XTENSA_SLOT0_OP,
+                                    target: Target::Section(literals.unwrap()),
+                                    addend: 4 * *literal as i32,
+                                });
+
+                                wide(&mut encoded, u32::from(*t) << 4 | 0x1);
+                            }
+                        }
+                    }
+                }
+            }
+
+            sections.push(encoded);
+        }
+
+        let symbols = labels
+            .iter()
+            .filter(|(name, _)| !name.starts_with(".L"))
+            .map(|(name, (section, offset))| Symbol {
+                name: name.to_string(),
+                section: indices[*section].1,
+                value: *offset,
+                global: self.globals.contains(*name),
+            })
+            .collect();
+
+        Ok(Object {
+            machine: MACHINE,
+            flags: FLAGS,
+            sections,
+            symbols,
+        })
+    }
+}
+
+/// Desplazamiento de inicio de cada elemento de una sección.
+fn layout(items: &[Item]) -> impl Iterator<Item = (u32, &Item)> {
+    items.iter().scan(0, |offset, item| {
+        let start = *offset;
+        *offset += item.size(start);
+
+        Some((start, item))
+    })
+}
+
+/// Posición de una etiqueta que debe encontrarse en la sección `section`.
+fn local(
+    labels: &HashMap<&str, (usize, u32)>,
+    section: usize,
+    number: usize,
+    label: &str,
+) -> Result<u32, AssemblerError> {
+    match labels.get(label) {
+        Some((found, offset)) if *found == section => Ok(*offset),
+        _ => Err(AssemblerError::Undefined(number, label.to_owned())),
+    }
+}
+
+/// Agrega un dato de 32 bits, relocalizado si refiere a un símbolo.
+fn word<F>(
+    section: &mut Section,
+    value: &Expr,
+    labels: &HashMap<&str, (usize, u32)>,
+    number: usize,
+    target: &F,
+) -> Result<(), AssemblerError>
+where
+    F: Fn(&str) -> (Target, i32),
+{
+    let value = match value {
+        Expr::Number(value) => *value as u32,
+
+        Expr::Symbol(symbol) => {
+            let (target, addend) = target(symbol);
+            section.relocations.push(Relocation {
+                offset: section.bytes.len() as u32,
+                kind: R_XTENSA_32,
+                target,
+                addend,
+            });
+
+            0
+        }
+
+        Expr::Difference(lhs, rhs) => match (labels.get(lhs.as_str()), labels.get(rhs.as_str())) {
+            (Some((lhs_section, lhs)), Some((rhs_section, rhs))) if lhs_section == rhs_section => {
+                lhs.wrapping_sub(*rhs)
+            }
+
+            (None, _) => return Err(AssemblerError::Undefined(number, lhs.clone())),
+            (_, None) => return Err(AssemblerError::Undefined(number, rhs.clone())),
+            _ => {
+                return Err(AssemblerError::Unsupported(
+                    number,
+                    "cross-section differences",
+                ))
+            }
+        },
+    };
+
+    section.bytes.extend_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+/// Sección de literales que corresponde a una sección de código.
+fn literal_section(name: &str) -> String {
+    match name.strip_prefix(".text") {
+        Some(suffix) => format!(".literal{}", suffix),
+        None => format!("{}.literal", name),
+    }
+}
+
+/// Agrega una instrucción de 24 bits.
+fn wide(section: &mut Section, word: u32) {
+    section.bytes.extend_from_slice(&word.to_le_bytes()[..3]);
+}
+
+/// Formato RRR, op0 = 0000.
+fn rrr(op2: u32, op1: u32, r: u8, s: u8, t: u8) -> Op {
+    let (r, s, t) = (u32::from(r), u32::from(s), u32::from(t));
+    Op::Wide(vec![op2 << 20 | op1 << 16 | r << 12 | s << 8 | t << 4])
+}
+
+/// Formato RRI8, op0 = 0010.
+fn rri8(imm: u32, r: u32, s: u8, t: u8) -> u32 {
+    imm << 16 | r << 12 | u32::from(s) << 8 | u32::from(t) << 4 | 0x2
+}
+
+/// Formato CALL con op0 = 0110 y n = 00, relativo a `PC + 4`.
+fn jump(offset: i64) -> Option<u32> {
+    Some(signed(offset, 18)? << 6 | 0x6)
+}
+
+/// Trunca un entero con signo a `bits`, si cabe.
+fn signed(value: i64, bits: u32) -> Option<u32> {
+    let limit = 1 << (bits - 1);
+    if (-limit..limit).contains(&value) {
+        Some((value as u32) & ((1 << bits) - 1))
+    } else {
+        None
+    }
+}
+
+fn split_mnemonic(text: &str) -> (&str, &str) {
+    match text.find(char::is_whitespace) {
+        Some(end) => (&text[..end], text[end..].trim()),
+        None => (text, ""),
+    }
+}
+
+fn is_symbol(text: &str) -> bool {
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$';
+    match text.chars().next() {
+        Some(first) if !first.is_ascii_digit() => text.chars().all(valid),
+        _ => false,
+    }
+}
+
+fn parse_reg(text: &str) -> Option<u8> {
+    let number = text.strip_prefix('a')?.parse().ok()?;
+    Some(number).filter(|number| *number < 16)
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, magnitude) = match text.strip_prefix('-') {
+        Some(magnitude) => (true, magnitude),
+        None => (false, text),
+    };
+
+    let magnitude = match magnitude.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None if magnitude.starts_with(|c: char| c.is_ascii_digit()) => magnitude.parse().ok()?,
+        None => return None,
+    };
+
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+fn parse_expr(text: &str) -> Option<Expr> {
+    match text.split_once(" - ") {
+        Some((lhs, rhs)) if is_symbol(lhs) && is_symbol(rhs) => {
+            Some(Expr::Difference(lhs.to_owned(), rhs.to_owned()))
+        }
+
+        Some(_) => None,
+        None if is_symbol(text) => Some(Expr::Symbol(text.to_owned())),
+        None => parse_number(text).map(Expr::Number),
+    }
+}
+
+/// Decodifica un string entre comillas con escapes `\\` y `\"`.
+fn parse_string(text: &str) -> Option<Vec<u8>> {
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut bytes = Vec::new();
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => chars.next()?,
+            '"' => return None,
+            c => c,
+        };
+
+        let mut buffer = [0; 4];
+        bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+    }
+
+    Some(bytes)
+}
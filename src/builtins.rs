@@ -0,0 +1,297 @@
+//! Funciones que exporta `libruntime`.
+//!
+//! Toda llamada a [`crate::ir::Function::External`] refiere a una de
+//! estas funciones. Las rutinas se enlazan por nombre, por lo cual un
+//! nombre mal escrito o una rutina eliminada del runtime no se
+//! detectaría sino hasta el enlazado, o bien al ejecutar el programa
+//! en [`crate::interp`]. Este registro es la única declaración de las
+//! rutinas del lado del compilador: el análisis semántico construye
+//! sus llamadas mediante [`call()`], y `tests/builtins.rs` verifica que
+//! `libruntime` exporte exactamente las rutinas que aquí se declaran.
+
+use crate::ir::{Function, Instruction, Local};
+
+/// Representación de un valor en la interfaz con `libruntime`.
+///
+/// Todos ocupan una local. Los flotantes viajan como los bits de un
+/// `f32`, y las listas, matrices y strings como punteros.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Abi {
+    Int,
+    Float,
+    Bool,
+    List,
+    IntList,
+    Mat,
+    Str,
+
+    /// Dirección de un procedimiento generado, ver
+    /// [`Instruction::LoadProcedure`].
+    Procedure,
+
+    /// Dirección de datos constantes, ver [`Instruction::LoadData`].
+    Data,
+}
+
+/// Firma de una rutina de `libruntime`.
+#[derive(Copy, Clone, Debug)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub parameters: &'static [Abi],
+
+    /// Valor de retorno, si existe.
+    pub returns: Option<Abi>,
+}
+
+macro_rules! builtins {
+    (@returns) => { None };
+    (@returns $returns:ident) => { Some(Abi::$returns) };
+
+    ($($name:ident($($parameter:ident),*) $(-> $returns:ident)?;)*) => {
+        /// Rutinas de `libruntime` que el compilador puede invocar.
+        pub const BUILTINS: &[Builtin] = &[
+            $(
+                Builtin {
+                    name: concat!("builtin_", stringify!($name)),
+                    parameters: &[$(Abi::$parameter),*],
+                    returns: builtins!(@returns $($returns)?),
+                },
+            )*
+        ];
+    };
+}
+
+builtins! {
+    // Depuración, el primer argumento es la línea
+    debug(Int);
+    debug_bool(Int, Bool);
+    debug_int(Int, Int);
+    debug_float(Int, Float);
+    debug_list(Int, List);
+    debug_ilist(Int, IntList);
+    debug_mat(Int, Mat);
+    debug_str(Int, Str);
+
+    // Construcción, conteo de referencias y comparación
+    new_list() -> List;
+    new_ilist() -> IntList;
+    new_mat() -> Mat;
+    new_str() -> Str;
+    ref_list(List) -> List;
+    ref_ilist(IntList) -> IntList;
+    ref_mat(Mat) -> Mat;
+    ref_str(Str) -> Str;
+    drop_list(List);
+    drop_ilist(IntList);
+    drop_mat(Mat);
+    drop_str(Str);
+    eq_list(List, List) -> Bool;
+    eq_ilist(IntList, IntList) -> Bool;
+    eq_mat(Mat, Mat) -> Bool;
+    eq_str(Str, Str) -> Bool;
+
+    // Operaciones sobre strings, listas y matrices
+    and_mat(Mat, Mat) -> Mat;
+    or_mat(Mat, Mat) -> Mat;
+    xor_mat(Mat, Mat) -> Mat;
+    push_str(Str, Int);
+    concat_str(Str, Str) -> Str;
+    index_list(List, Int) -> Bool;
+    index_ilist(IntList, Int) -> Int;
+    index_entry_mat(Mat, Int, Int) -> Bool;
+    index_row_mat(Mat, Int) -> List;
+    index_column_mat(Mat, Int) -> List;
+    insert_list(List, Int, Bool);
+    insert_ilist(IntList, Int, Int);
+    insert_mat(Mat, Mat, Int, Int);
+    insert_end_mat(Mat, Mat, Int);
+    delete_list(List, Int);
+    delete_ilist(IntList, Int);
+    delete_mat(Mat, Int, Int);
+    push_mat(Mat, List);
+    shift_list(List, Int, Bool);
+    shift_mat(Mat, Int, Int, Bool);
+    transpose_mat(Mat);
+    rotate_mat(Mat, Int);
+    mirror_mat(Mat, Int);
+    len_list(List) -> Int;
+    len_ilist(IntList) -> Int;
+    slice_list(List, Int, Int) -> List;
+    slice_ilist(IntList, Int, Int) -> IntList;
+    slice_mat(Mat, Int, Int) -> Mat;
+    set_entry_list(List, Int, Bool);
+    set_entry_ilist(IntList, Int, Int);
+    set_entry_mat(Mat, Int, Int, Bool);
+    set_row_mat(Mat, Int, List);
+    set_column_mat(Mat, Int, List);
+    set_slice_list(List, Int, Int, List);
+    set_slice_ilist(IntList, Int, Int, IntList);
+    set_slice_mat(Mat, Int, Int, Mat);
+    shapef(Mat) -> Int;
+    shapec(Mat) -> Int;
+    range(Int, Bool) -> List;
+
+    // Aritmética
+    cast_int_float(Int) -> Float;
+    cast_float_int(Float) -> Int;
+    div_int(Int, Int) -> Float;
+    mul_int(Int, Int) -> Int;
+    intdiv_int(Int, Int) -> Int;
+    mod_int(Int, Int) -> Int;
+    trap_div_zero(Int);
+    pow_int(Int, Int) -> Float;
+    add_float(Float, Float) -> Float;
+    sub_float(Float, Float) -> Float;
+    mul_float(Float, Float) -> Float;
+    div_float(Float, Float) -> Float;
+    pow_float(Float, Float) -> Float;
+    cmp_float(Float, Float) -> Int;
+    seed(Int);
+    random(Int) -> Int;
+
+    // Persistencia, UART y memoria
+    store(Int, Int);
+    load(Int) -> Int;
+    uart_write_int(Int);
+    uart_read_int() -> Int;
+    heap_used() -> Int;
+    heap_free() -> Int;
+    frame_end();
+
+    // Mutadores de booleanos, ver `mutator!` en `libruntime`
+    neg_list(List);
+    neg_mat(Mat);
+    neg_entry_list(List, Int);
+    neg_entry_mat(Mat, Int, Int);
+    neg_row_mat(Mat, Int);
+    neg_column_mat(Mat, Int);
+    neg_slice_list(List, Int, Int);
+    neg_slice_mat(Mat, Int, Int);
+    f_list(List);
+    f_mat(Mat);
+    f_entry_list(List, Int);
+    f_entry_mat(Mat, Int, Int);
+    f_row_mat(Mat, Int);
+    f_column_mat(Mat, Int);
+    f_slice_list(List, Int, Int);
+    f_slice_mat(Mat, Int, Int);
+    t_list(List);
+    t_mat(Mat);
+    t_entry_list(List, Int);
+    t_entry_mat(Mat, Int, Int);
+    t_row_mat(Mat, Int);
+    t_column_mat(Mat, Int);
+    t_slice_list(List, Int, Int);
+    t_slice_mat(Mat, Int, Int);
+
+    // Temporización y pantalla
+    after(Int, Procedure);
+    every(Int, Procedure);
+    delay_mil(Int);
+    delay_seg(Int);
+    delay_min(Int);
+    frame_sync(Int);
+    millis() -> Int;
+    blink_mil(Int, Int, Int, Bool);
+    blink_seg(Int, Int, Int, Bool);
+    blink_min(Int, Int, Int, Bool);
+    printled(Int, Int, Bool);
+    printled_intensity(Int, Int, Int);
+    printledx_f(Int, List);
+    printledx_c(Int, List);
+    printledx_m(Int, Mat);
+    present();
+    set_display_size(Int, Int);
+    scroll_text(Str, Int);
+    play_frames(Data, Int, Bool);
+}
+
+/// Rutinas que pueden ejecutar tareas programadas con `after` o `every`
+/// antes de retornar, y con ello cualquier procedimiento del programa.
+const RUNS_TASKS: &[&str] = &[
+    "builtin_delay_mil",
+    "builtin_delay_seg",
+    "builtin_delay_min",
+    "builtin_frame_sync",
+    "builtin_scroll_text",
+    "builtin_play_frames",
+];
+
+/// Rutinas que nunca reportan un error en tiempo de ejecución, ni
+/// siquiera por falta de memoria. `trap_div_zero` lo reporta, pero
+/// recibe su línea como argumento.
+const INFALLIBLE: &[&str] = &[
+    "builtin_cast_int_float",
+    "builtin_cast_float_int",
+    "builtin_div_int",
+    "builtin_mul_int",
+    "builtin_trap_div_zero",
+    "builtin_pow_int",
+    "builtin_add_float",
+    "builtin_sub_float",
+    "builtin_mul_float",
+    "builtin_div_float",
+    "builtin_pow_float",
+    "builtin_cmp_float",
+    "builtin_seed",
+    "builtin_uart_write_int",
+    "builtin_uart_read_int",
+    "builtin_heap_used",
+    "builtin_heap_free",
+    "builtin_frame_end",
+    "builtin_millis",
+    "builtin_present",
+];
+
+/// Busca una rutina por nombre.
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+/// Determina si `libruntime` exporta una rutina con el nombre dado.
+pub fn is_builtin(name: &str) -> bool {
+    lookup(name).is_some()
+}
+
+/// Determina si una rutina puede ejecutar procedimientos del programa
+/// antes de retornar, ya que espera y mientras tanto atiende tareas
+/// programadas.
+pub fn runs_tasks(name: &str) -> bool {
+    RUNS_TASKS.contains(&name)
+}
+
+/// Determina si una rutina puede detener el programa con un error en
+/// tiempo de ejecución, el cual reporta la línea en `builtin_line`.
+pub fn can_fail(name: &str) -> bool {
+    !INFALLIBLE.contains(&name)
+}
+
+/// Construye una llamada a una rutina de `libruntime`.
+///
+/// # Panics
+/// Si la rutina no está declarada, si la cantidad de argumentos no
+/// corresponde a su firma o si se espera un valor de retorno de una
+/// rutina que no lo produce. Cualquiera de estos casos es un error del
+/// compilador.
+pub fn call(name: &'static str, arguments: Vec<Local>, output: Option<Local>) -> Instruction {
+    let builtin = lookup(name).unwrap_or_else(|| panic!("undeclared builtin `{}`", name));
+
+    assert_eq!(
+        arguments.len(),
+        builtin.parameters.len(),
+        "bad argument count for `{}`",
+        name
+    );
+
+    assert!(
+        output.is_none() || builtin.returns.is_some(),
+        "`{}` returns no value",
+        name
+    );
+
+    Instruction::Call {
+        target: Function::External(name),
+        arguments,
+        output,
+    }
+}
@@ -0,0 +1,110 @@
+//! Verificaciones en tiempo de ejecución.
+//!
+//! El análisis semántico solo rechaza denominadores que son cero en
+//! tiempo de compilación. Con `--checked-arithmetic`, cada división
+//! y módulo entero se precede de una comparación contra cero que, de
+//! fallar, invoca a `builtin_trap_div_zero` con la línea de código
+//! fuente en ejecución. Sin esta verificación el resultado depende de
+//! la arquitectura: en x86-64 el proceso recibe `SIGFPE`, mientras que
+//! ARM y RISC-V producen algún valor sin reportar error alguno.
+
+use crate::{
+    ir::{ArithmeticOp, BinOp, Function, GeneratedFunction, Instruction, Label, Local, Program},
+    source::Located,
+};
+
+use super::required_locals_and_labels;
+
+/// Builtin que reporta una división entre cero y no retorna.
+const TRAP: &str = "builtin_trap_div_zero";
+
+/// Inserta verificaciones de división entre cero en todo el programa.
+pub fn check_division(program: &mut Program) {
+    for function in &mut program.code {
+        check_function(function);
+    }
+}
+
+/// Inserta verificaciones de división entre cero en una función.
+///
+/// El código resultante para cada `Div` o `Mod` es:
+///
+/// ```text
+///     JumpIfFalse(rhs, trap)
+///     Jump(ok)
+/// trap:
+///     LoadConst(line, scratch)
+///     Call builtin_trap_div_zero(scratch)
+/// ok:
+///     Binary(lhs, op, rhs)
+/// ```
+///
+/// La línea es la de la ubicación de la operación, la cual heredan
+/// también las instrucciones insertadas.
+fn check_function(function: &mut GeneratedFunction) {
+    let needs_check = |instruction: &Instruction| {
+        matches!(
+            instruction,
+            Instruction::Binary(
+                _,
+                BinOp::Arithmetic(ArithmeticOp::Div) | BinOp::Arithmetic(ArithmeticOp::Mod),
+                _
+            )
+        )
+    };
+
+    if !function
+        .body
+        .iter()
+        .any(|instruction| needs_check(instruction))
+    {
+        return;
+    }
+
+    // Locales y etiquetas nuevas no deben colisionar con las existentes
+    let (locals, labels) = function
+        .body
+        .iter()
+        .map(|instruction| required_locals_and_labels(instruction))
+        .fold(
+            (function.parameters, 0),
+            |(max_locals, max_labels), (locals, labels)| {
+                (max_locals.max(locals), max_labels.max(labels))
+            },
+        );
+
+    let scratch = Local(locals);
+    let mut next_label = labels;
+    let mut fresh_label = || {
+        next_label += 1;
+        Label(next_label - 1)
+    };
+
+    let body = std::mem::take(&mut function.body);
+    let mut checked = Vec::with_capacity(body.len());
+
+    for instruction in body.iter() {
+        if let Instruction::Binary(_, _, rhs) = instruction.as_ref() {
+            if needs_check(instruction) {
+                let line = instruction.location().start().line() as i32;
+                let (trap, ok) = (fresh_label(), fresh_label());
+                let located = |check| Located::at(check, instruction.location().clone());
+
+                checked.push(located(Instruction::JumpIfFalse(*rhs, trap)));
+                checked.push(located(Instruction::Jump(ok)));
+                checked.push(located(Instruction::SetLabel(trap)));
+                checked.push(located(Instruction::LoadConst(line, scratch)));
+                checked.push(located(Instruction::Call {
+                    target: Function::External(TRAP),
+                    arguments: vec![scratch],
+                    output: None,
+                }));
+                checked.push(located(Instruction::SetLabel(ok)));
+            }
+        }
+
+        checked.push(instruction.clone());
+    }
+
+    function.body = checked;
+}
@@ -0,0 +1,56 @@
+//! Delimitación de cuadros de animación.
+//!
+//! Con `--arena`, `libruntime` toma listas y matrices temporales de una
+//! región que se reinicia al final de cada cuadro, en vez de fragmentar
+//! el heap. Se considera que un cuadro termina cada vez que un ciclo
+//! regresa a su inicio, es decir, en cada salto incondicional hacia una
+//! etiqueta que ya se estableció antes en la función.
+
+use std::collections::HashSet;
+
+use crate::{
+    ir::{Function, GeneratedFunction, Instruction, Program},
+    source::Located,
+};
+
+/// Builtin que finaliza un cuadro.
+const FRAME_END: &str = "builtin_frame_end";
+
+/// Marca el final de cuadro en todos los ciclos del programa.
+pub fn mark_frames(program: &mut Program) {
+    for function in &mut program.code {
+        mark_function(function);
+    }
+}
+
+/// Inserta una llamada a `builtin_frame_end` antes de cada salto hacia
+/// atrás. La llamada hereda la ubicación del salto.
+fn mark_function(function: &mut GeneratedFunction) {
+    let body = std::mem::take(&mut function.body);
+    let mut marked = Vec::with_capacity(body.len());
+    let mut seen = HashSet::new();
+
+    for instruction in body {
+        match instruction.as_ref() {
+            Instruction::SetLabel(label) => {
+                seen.insert(label.0);
+            }
+
+            Instruction::Jump(label) if seen.contains(&label.0) => {
+                let call = Instruction::Call {
+                    target: Function::External(FRAME_END),
+                    arguments: vec![],
+                    output: None,
+                };
+
+                marked.push(Located::at(call, instruction.location().clone()));
+            }
+
+            _ => (),
+        }
+
+        marked.push(instruction);
+    }
+
+    function.body = marked;
+}
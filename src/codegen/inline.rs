@@ -0,0 +1,207 @@
+//! Expansión en línea de procedimientos.
+//!
+//! Los procedimientos pequeños que se invocan dentro de ciclos de
+//! animación pagan en cada llamada el costo de prólogo, epílogo y
+//! paso de argumentos, el cual es considerable en Xtensa. Con `-O2`,
+//! cada llamada a un procedimiento generado cuyo cuerpo no excede
+//! [`THRESHOLD`] instrucciones IR se sustituye por una copia de dicho
+//! cuerpo. Las locales y etiquetas de la copia se renumeran por encima
+//! de las que ya utiliza la función que llama, por lo que no es posible
+//! que colisionen.
+//!
+//! Los cuerpos que se copian son siempre los originales, de modo que
+//! una única pasada no puede expandir indefinidamente una cadena de
+//! llamadas. Los procedimientos que se llaman a sí mismos nunca se
+//! expanden. La función original se sigue emitiendo, ya que el enlazador
+//! descarta las secciones que no se usan. Las instrucciones copiadas
+//! conservan su ubicación en el procedimiento original.
+
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    ir::{Function, GeneratedFunction, Instruction, Label, Local, Program},
+    source::Located,
+};
+
+use super::required_locals_and_labels;
+
+/// Máxima cantidad de instrucciones IR de un procedimiento expandible,
+/// sin contar `Kill`, que no genera código.
+pub const THRESHOLD: usize = 32;
+
+/// Expande en línea las llamadas a procedimientos pequeños.
+pub fn inline_calls(program: &mut Program) {
+    let candidates: HashMap<Rc<String>, GeneratedFunction> = program
+        .code
+        .iter()
+        .filter(|function| is_candidate(function))
+        .map(|function| (Rc::clone(&function.name), function.clone()))
+        .collect();
+
+    if candidates.is_empty() {
+        return;
+    }
+
+    for function in &mut program.code {
+        inline_function(function, &candidates);
+    }
+}
+
+/// Determina si un procedimiento es lo suficientemente pequeño y no
+/// recursivo como para expandirse en línea.
+fn is_candidate(function: &GeneratedFunction) -> bool {
+    let calls_itself = function
+        .body
+        .iter()
+        .any(|instruction| match instruction.as_ref() {
+            Instruction::Call {
+                target: Function::Generated(name),
+                ..
+            } => *name == function.name,
+
+            _ => false,
+        });
+
+    let size = function
+        .body
+        .iter()
+        .filter(|instruction| !matches!(instruction.as_ref(), Instruction::Kill(_)))
+        .count();
+
+    size <= THRESHOLD && !calls_itself
+}
+
+/// Expande las llamadas a candidatos dentro de una función.
+///
+/// El código resultante para `Call { target, arguments, output }` es:
+///
+/// ```text
+///     Move(argumento, parámetro)      ; por cada argumento
+///     ...                             ; cuerpo renumerado
+///     Move(valor, output)             ; por cada Return(Some(valor))
+///     Jump(end)                       ; por cada Return
+/// end:
+/// ```
+fn inline_function(
+    function: &mut GeneratedFunction,
+    candidates: &HashMap<Rc<String>, GeneratedFunction>,
+) {
+    let callee = |instruction: &Instruction| match instruction {
+        Instruction::Call {
+            target: Function::Generated(name),
+            ..
+        } if *name != function.name => candidates.get(name),
+
+        _ => None,
+    };
+
+    if !function
+        .body
+        .iter()
+        .any(|instruction| callee(instruction).is_some())
+    {
+        return;
+    }
+
+    let (mut next_local, mut next_label) = usage(&function.body, function.parameters);
+
+    let mut inlined = Vec::with_capacity(function.body.len());
+    for instruction in &function.body {
+        let (arguments, output, callee) = match (instruction.as_ref(), callee(instruction)) {
+            (
+                Instruction::Call {
+                    arguments, output, ..
+                },
+                Some(callee),
+            ) => (arguments, *output, callee),
+
+            _ => {
+                inlined.push(instruction.clone());
+                continue;
+            }
+        };
+
+        let (locals, labels) = usage(&callee.body, callee.parameters);
+        let local = |Local(local)| Local(next_local + local);
+        let label = |Label(label)| Label(next_label + label);
+        let end = Label(next_label + labels);
+
+        let call = instruction.location();
+        for (parameter, argument) in arguments.iter().enumerate() {
+            let parameter = local(Local(parameter as u32));
+            inlined.push(Located::at(
+                Instruction::Move(*argument, parameter),
+                call.clone(),
+            ));
+        }
+
+        let last = callee.body.len().saturating_sub(1);
+        for (position, instruction) in callee.body.iter().enumerate() {
+            use Instruction::*;
+
+            let located = |renamed| Located::at(renamed, instruction.location().clone());
+            let renamed = match instruction.as_ref() {
+                Move(from, to) => Move(local(*from), local(*to)),
+                SetLabel(target) => SetLabel(label(*target)),
+                Jump(target) => Jump(label(*target)),
+                JumpIfFalse(value, target) => JumpIfFalse(local(*value), label(*target)),
+                LoadConst(value, target) => LoadConst(*value, local(*target)),
+                LoadGlobal(global, target) => LoadGlobal(global.clone(), local(*target)),
+                LoadData(data, target) => LoadData(data.clone(), local(*target)),
+                LoadProcedure(name, target) => LoadProcedure(name.clone(), local(*target)),
+                StoreGlobal(value, global) => StoreGlobal(local(*value), global.clone()),
+                Not(value) => Not(local(*value)),
+                Negate(value) => Negate(local(*value)),
+                Binary(lhs, op, rhs) => Binary(local(*lhs), *op, local(*rhs)),
+                Kill(value) => Kill(local(*value)),
+
+                Call {
+                    target,
+                    arguments,
+                    output,
+                } => Call {
+                    target: match target {
+                        Function::Indirect(callee) => Function::Indirect(local(*callee)),
+                        _ => target.clone(),
+                    },
+                    arguments: arguments.iter().copied().map(local).collect(),
+                    output: output.map(local),
+                },
+
+                Return(value) => {
+                    if let (Some(value), Some(output)) = (value, output) {
+                        inlined.push(located(Move(local(*value), output)));
+                    }
+
+                    // El último retorno cae directamente en la etiqueta final
+                    if position != last {
+                        inlined.push(located(Jump(end)));
+                    }
+
+                    continue;
+                }
+            };
+
+            inlined.push(located(renamed));
+        }
+
+        inlined.push(Located::at(Instruction::SetLabel(end), call.clone()));
+
+        next_local += locals;
+        next_label += labels + 1;
+    }
+
+    function.body = inlined;
+}
+
+/// Cantidad de locales y etiquetas que utiliza un cuerpo de función.
+fn usage(body: &[Located<Instruction>], parameters: u32) -> (u32, u32) {
+    body.iter()
+        .map(|instruction| required_locals_and_labels(instruction))
+        .fold(
+            (parameters, 0),
+            |(max_locals, max_labels), (locals, labels)| {
+                (max_locals.max(locals), max_labels.max(labels))
+            },
+        )
+}
@@ -0,0 +1,274 @@
+//! Análisis de vida de locales.
+//!
+//! Una local está "viva" en un punto del programa si su valor
+//! actual puede ser leído por alguna instrucción posterior antes
+//! de ser sobreescrito. Este análisis permite al reservador de
+//! registros omitir escrituras a memoria de valores que nunca se
+//! vuelven a leer y elegir víctimas de spill con mejor criterio
+//! que simplemente el registro usado menos recientemente.
+//!
+//! El análisis es el clásico flujo de datos hacia atrás sobre el
+//! grafo de control de flujo de una función, iterado hasta alcanzar
+//! un punto fijo. Los nodos del grafo son instrucciones individuales.
+//! Una instrucción `Kill` termina la vida de una local al igual que
+//! una escritura, aunque ninguna instrucción posterior la sobreescriba.
+
+use crate::{
+    ir::{Function, Instruction, Label, Local},
+    source::Located,
+};
+
+/// Resultado del análisis de vida para una función.
+pub struct Liveness {
+    live_in: Vec<LocalSet>,
+    live_out: Vec<LocalSet>,
+    interval_ends: Vec<usize>,
+}
+
+impl Liveness {
+    /// Analiza el cuerpo de una función que utiliza `locals` locales.
+    pub fn analyze(body: &[Located<Instruction>], locals: u32) -> Self {
+        let successors = successors(body);
+
+        let empty = LocalSet::new(locals);
+        let mut live_in = vec![empty.clone(); body.len()];
+        let mut live_out = vec![empty; body.len()];
+
+        // Las instrucciones se recorren en reversa para converger más rápido
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for (position, instruction) in body.iter().enumerate().rev() {
+                let mut output = LocalSet::new(locals);
+                for successor in successors[position].iter().flatten() {
+                    output.union(&live_in[*successor]);
+                }
+
+                let mut input = output.clone();
+                accesses(instruction, |local, access| match access {
+                    Access::Def | Access::Kill => input.remove(local),
+                    Access::Use => (),
+                });
+
+                accesses(instruction, |local, access| match access {
+                    Access::Use => input.insert(local),
+                    Access::Def | Access::Kill => (),
+                });
+
+                if input != live_in[position] || output != live_out[position] {
+                    live_in[position] = input;
+                    live_out[position] = output;
+                    changed = true;
+                }
+            }
+        }
+
+        // Un intervalo termina en la última posición donde la local es referida o está viva
+        let mut interval_ends = vec![0; locals as usize];
+        for (position, instruction) in body.iter().enumerate() {
+            accesses(instruction, |Local(local), access| {
+                if !matches!(access, Access::Kill) {
+                    interval_ends[local as usize] = position;
+                }
+            });
+
+            for local in live_out[position].iter() {
+                interval_ends[local.0 as usize] = position;
+            }
+        }
+
+        Liveness {
+            live_in,
+            live_out,
+            interval_ends,
+        }
+    }
+
+    /// Determina si el valor de una local debe preservarse en una
+    /// posición dada, ya sea porque la instrucción en esa posición
+    /// lo lee o porque alguna instrucción posterior lo hará.
+    pub fn is_needed(&self, position: usize, local: Local) -> bool {
+        self.live_in[position].contains(local) || self.live_out[position].contains(local)
+    }
+
+    /// Determina si alguna instrucción posterior a una posición dada
+    /// puede leer el valor que tiene una local tras esa posición.
+    pub fn is_live_after(&self, position: usize, local: Local) -> bool {
+        self.live_out[position].contains(local)
+    }
+
+    /// Posición de la última instrucción en la que una local está viva.
+    pub fn interval_end(&self, Local(local): Local) -> usize {
+        self.interval_ends[local as usize]
+    }
+}
+
+/// Determina si una instrucción lee o escribe una local.
+pub fn is_referenced(instruction: &Instruction, local: Local) -> bool {
+    let mut referenced = false;
+    accesses(instruction, |accessed, _| referenced |= accessed == local);
+
+    referenced
+}
+
+/// Sucesores de cada instrucción en el grafo de control de flujo.
+///
+/// Todo salto debe referir a una etiqueta definida en `body`.
+pub(crate) fn successors(body: &[Located<Instruction>]) -> Vec<[Option<usize>; 2]> {
+    let mut labels = Vec::new();
+    for (position, instruction) in body.iter().enumerate() {
+        if let Instruction::SetLabel(Label(label)) = instruction.as_ref() {
+            let label = *label as usize;
+            if labels.len() <= label {
+                labels.resize(label + 1, None);
+            }
+
+            labels[label] = Some(position);
+        }
+    }
+
+    let target = |Label(label): Label| {
+        labels
+            .get(label as usize)
+            .copied()
+            .flatten()
+            .expect("jump to undefined label")
+    };
+
+    body.iter()
+        .enumerate()
+        .map(|(position, instruction)| {
+            let next = Some(position + 1).filter(|next| *next < body.len());
+
+            match instruction.as_ref() {
+                Instruction::Jump(label) => [Some(target(*label)), None],
+                Instruction::JumpIfFalse(_, label) => [next, Some(target(*label))],
+                Instruction::Return(_) => [None, None],
+                _ => [next, None],
+            }
+        })
+        .collect()
+}
+
+/// Forma en que una instrucción accede a una local.
+#[derive(Copy, Clone)]
+pub(crate) enum Access {
+    Use,
+    Def,
+
+    /// El valor deja de ser necesario, ver [`Instruction::Kill`].
+    Kill,
+}
+
+/// Enumera los accesos a locales que realiza una instrucción.
+pub(crate) fn accesses<F: FnMut(Local, Access)>(instruction: &Instruction, mut callback: F) {
+    use Instruction::*;
+
+    match instruction {
+        Move(from, to) => {
+            callback(*from, Access::Use);
+            callback(*to, Access::Def);
+        }
+
+        SetLabel(_) | Jump(_) | Return(None) => (),
+        JumpIfFalse(local, _) | StoreGlobal(local, _) | Return(Some(local)) => {
+            callback(*local, Access::Use)
+        }
+
+        LoadConst(_, local) | LoadGlobal(_, local) => callback(*local, Access::Def),
+        Kill(local) => callback(*local, Access::Kill),
+        LoadData(_, local) | LoadProcedure(_, local) => callback(*local, Access::Def),
+
+        Not(local) | Negate(local) => {
+            callback(*local, Access::Use);
+            callback(*local, Access::Def);
+        }
+
+        Binary(lhs, _, rhs) => {
+            callback(*lhs, Access::Use);
+            callback(*rhs, Access::Use);
+            callback(*lhs, Access::Def);
+        }
+
+        Call {
+            target,
+            arguments,
+            output,
+        } => {
+            if let Function::Indirect(local) = target {
+                callback(*local, Access::Use);
+            }
+
+            for argument in arguments {
+                callback(*argument, Access::Use);
+            }
+
+            if let Some(output) = output {
+                callback(*output, Access::Def);
+            }
+        }
+    }
+}
+
+/// Conjunto de locales, representado como un mapa de bits.
+#[derive(Clone, PartialEq, Eq)]
+pub(super) struct LocalSet(Vec<u64>);
+
+impl LocalSet {
+    pub(super) fn new(locals: u32) -> Self {
+        LocalSet(vec![0; (locals as usize + 63) / 64])
+    }
+
+    /// Conjunto de todas las locales en `[0, locals)`.
+    pub(super) fn full(locals: u32) -> Self {
+        let mut set = LocalSet::new(locals);
+        for local in 0..locals {
+            set.insert(Local(local));
+        }
+
+        set
+    }
+
+    pub(super) fn contains(&self, Local(local): Local) -> bool {
+        let (word, bit) = Self::split(local);
+        self.0
+            .get(word)
+            .map(|word| word & bit != 0)
+            .unwrap_or(false)
+    }
+
+    pub(super) fn insert(&mut self, Local(local): Local) {
+        let (word, bit) = Self::split(local);
+        self.0[word] |= bit;
+    }
+
+    fn remove(&mut self, Local(local): Local) {
+        let (word, bit) = Self::split(local);
+        self.0[word] &= !bit;
+    }
+
+    fn union(&mut self, other: &LocalSet) {
+        for (word, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *word |= other;
+        }
+    }
+
+    pub(super) fn intersect(&mut self, other: &LocalSet) {
+        for (word, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *word &= other;
+        }
+    }
+
+    fn iter(&self) -> impl '_ + Iterator<Item = Local> {
+        self.0.iter().enumerate().flat_map(|(index, word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| Local((index * 64 + bit) as u32))
+        })
+    }
+
+    fn split(local: u32) -> (usize, u64) {
+        ((local / 64) as usize, 1 << (local % 64))
+    }
+}
@@ -6,16 +6,27 @@
 
 use crate::{
     arch::{Arch, Emitter, Register},
-    ir::{GeneratedFunction, Instruction, Label, Local, Program},
+    ir::{Function, GeneratedFunction, Instruction, Label, Local, Program},
+    ledmap,
 };
 
+use self::{liveness::Liveness, peephole::Peephole};
+
 use std::{
     cell::RefCell,
     fmt,
     io::{self, Write},
 };
 
+pub mod checks;
+pub mod frames;
+pub mod inline;
+pub mod liveness;
+pub mod peephole;
 pub mod regs;
+pub mod soft;
+pub mod stack;
+pub mod verify;
 
 /// Emite código ensamblador para un programa IR.
 ///
@@ -25,7 +36,62 @@ pub mod regs;
 /// a ser utilizada directamente por el GNU assembler y no se esperan
 /// otras interpretaciones o manipulaciones antes de ello.
 pub fn emit(program: &Program, arch: Arch, output: &mut dyn Write) -> io::Result<()> {
-    let value_size = dispatch_arch!(Emitter: arch => Emitter::VALUE_SIZE);
+    emit_program(program, arch, None, output)
+}
+
+/// Como [`emit()`], pero intercalando directivas `.file` y `.loc` que
+/// relacionan el código emitido con las líneas del archivo `source`.
+///
+/// A partir de estas directivas, el ensamblador genera la tabla de
+/// líneas DWARF (`.debug_line`) que utilizan depuradores como gdb.
+/// WebAssembly no admite esta información.
+pub fn emit_with_debug_info(
+    program: &Program,
+    arch: Arch,
+    source: &str,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    emit_program(program, arch, Some(source), output)
+}
+
+fn emit_program(
+    program: &Program,
+    arch: Arch,
+    source: Option<&str>,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    // WebAssembly no se ensambla con GNU as, ver arch::wasm32
+    if let Arch::Wasm32 = arch {
+        return crate::arch::wasm32::emit(program, output);
+    }
+
+    emit_header(program, arch, output)?;
+
+    if let Some(source) = source {
+        let escaped = source.replace('\\', "\\\\").replace('"', "\\\"");
+        writeln!(output, ".file 1 \"{}\"", escaped)?;
+    }
+
+    // Se emite propiamente cada función no externa
+    for function in &program.code {
+        emit_function_with_lines(function, arch, source.is_some(), output)?;
+    }
+
+    Ok(())
+}
+
+/// Emite las directivas iniciales y las variables globales de un programa.
+///
+/// Junto a [`emit_function()`], esto permite emitir cada función por
+/// separado, ya que una función nunca depende del código de otra. Esto
+/// no aplica a WebAssembly.
+pub fn emit_header(program: &Program, arch: Arch, output: &mut dyn Write) -> io::Result<()> {
+    let (value_size, header) =
+        dispatch_arch!(Emitter: arch => (Emitter::VALUE_SIZE, Emitter::HEADER));
+
+    for directive in header {
+        writeln!(output, "{}", directive)?;
+    }
 
     // Variables globales van en .bss
     for global in &program.globals {
@@ -33,13 +99,88 @@ pub fn emit(program: &Program, arch: Arch, output: &mut dyn Write) -> io::Result
     }
 
     // Inicio de las secciones de código
-    writeln!(output, ".text")?;
+    writeln!(output, ".text")
+}
 
-    // Se emite propiamente cada función no externa
-    for function in &program.code {
-        dispatch_arch!(Emitter: arch => {
-            emit_body::<Emitter>(output, function)?;
+/// Emite una función no externa junto a su tabla de líneas.
+pub fn emit_function(
+    function: &GeneratedFunction,
+    arch: Arch,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    emit_function_with_lines(function, arch, false, output)
+}
+
+/// Emite una función, opcionalmente con directivas `.loc` en cada línea.
+fn emit_function_with_lines(
+    function: &GeneratedFunction,
+    arch: Arch,
+    debug_lines: bool,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    let lines = dispatch_arch!(Emitter: arch => {
+        let mut peephole = Peephole::new(output, Emitter::PEEPHOLE);
+        let (lines, _) = emit_body::<Emitter>(&mut peephole, function, debug_lines)?;
+        peephole.finish()?;
+
+        lines
+    });
+
+    emit_line_table(output, function, &lines)?;
+    emit_data(output, function)
+}
+
+/// Emite la tabla de líneas de una función, ver [`crate::ledmap`].
+///
+/// `lines` contiene la línea de cada marca emitida por [`emit_body()`].
+fn emit_line_table(
+    output: &mut dyn Write,
+    function: &GeneratedFunction,
+    lines: &[i32],
+) -> io::Result<()> {
+    if lines.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(output, ".section {},\"\"", ledmap::SECTION)?;
+    writeln!(output, ".asciz \"{}\"", function.name)?;
+
+    for (mark, line) in lines.iter().enumerate() {
+        writeln!(
+            output,
+            ".4byte .L{0}.line.{1} - {0}, {2}",
+            function.name, mark, line
+        )?;
+    }
+
+    writeln!(output, ".4byte {:#x}", ledmap::END_OF_TABLE)
+}
+
+/// Emite los bloques de datos constantes de una función.
+///
+/// Cada instrucción `LoadData` recibe, en orden de aparición, una
+/// etiqueta `.L<función>.data.<n>` que [`emit_body()`] referencia.
+fn emit_data(output: &mut dyn Write, function: &GeneratedFunction) -> io::Result<()> {
+    let blocks = function
+        .body
+        .iter()
+        .filter_map(|instruction| match instruction.as_ref() {
+            Instruction::LoadData(data, _) => Some(data),
+            _ => None,
         });
+
+    for (index, data) in blocks.enumerate() {
+        writeln!(
+            output,
+            ".section .rodata.{}.data.{},\"a\"",
+            function.name, index
+        )?;
+        writeln!(output, ".balign 4\n.L{}.data.{}:", function.name, index)?;
+
+        for chunk in data.chunks(16) {
+            let bytes: Vec<_> = chunk.iter().map(u8::to_string).collect();
+            writeln!(output, ".byte {}", bytes.join(", "))?;
+        }
     }
 
     Ok(())
@@ -56,6 +197,10 @@ pub struct Context<'a, E: Emitter<'a>> {
     locals: u32,
     next_label: u32,
     frame_info: E::FrameInfo,
+    liveness: Liveness,
+    position: usize,
+    homes: Vec<Option<E::Register>>,
+    callee_saved: &'static [E::Register],
 }
 
 impl<'a, E: Emitter<'a>> Context<'a, E> {
@@ -90,6 +235,34 @@ impl<'a, E: Emitter<'a>> Context<'a, E> {
         Context { frame_info, ..self }
     }
 
+    /// Análisis de vida de locales para la función actual.
+    pub fn liveness(&self) -> &Liveness {
+        &self.liveness
+    }
+
+    /// Índice de la instrucción IR que está siendo generada.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Instrucción IR que está siendo generada.
+    pub fn current(&self) -> Option<&Instruction> {
+        self.function.body.get(self.position).map(AsRef::as_ref)
+    }
+
+    /// Registro callee-saved en el que reside una local durante toda la
+    /// función, si se le asignó alguno. Esto ocurre con las locales que
+    /// sobreviven más llamadas, ver [`Register::CALLEE_SAVED`].
+    pub fn home(&self, Local(local): Local) -> Option<E::Register> {
+        self.homes.get(local as usize).copied().flatten()
+    }
+
+    /// Registros callee-saved que utiliza la función, los cuales deben
+    /// guardarse en el prólogo y restaurarse en el epílogo.
+    pub fn callee_saved(&self) -> &'static [E::Register] {
+        self.callee_saved
+    }
+
     pub fn next_label(&mut self) -> Label {
         let next_label = self.next_label;
         self.next_label += 1;
@@ -100,46 +273,78 @@ impl<'a, E: Emitter<'a>> Context<'a, E> {
 
 /// Emite cada una de las instrucciones de una función no externa.
 ///
-/// La correspondencia IR:ensamblador es siempre 1:N.
+/// La correspondencia IR:ensamblador es siempre 1:N. Se retorna la
+/// línea de código fuente que inicia en cada marca `.L<función>.line.<n>`,
+/// junto al tamaño en bytes del marco de llamada. Con `debug_lines`, se
+/// emite una directiva `.loc` cada vez que cambia la ubicación en código
+/// fuente de las instrucciones IR.
 fn emit_body<'a, E: Emitter<'a>>(
     output: &'a mut dyn Write,
     function: &'a GeneratedFunction,
-) -> io::Result<()> {
-    let (locals, agnostic_labels) = function.body.iter().map(required_locals_and_labels).fold(
-        (0, 0),
-        |(max_locals, max_labels), (locals, labels)| {
-            (max_locals.max(locals), max_labels.max(labels))
-        },
-    );
-
-    let locals = locals.max(function.parameters);
+    debug_lines: bool,
+) -> io::Result<(Vec<i32>, u32)> {
+    let (locals, agnostic_labels) = locals_and_labels(function);
 
     // Colocar cada función en su propia sección permite eliminar
     // código muerto con -Wl,--gc-sections en la fase de enlazado
     writeln!(
         output,
-        ".section .text.{0}\n.align {1}\n.global {0}\n{0}:",
+        ".section .text.{0}\n.balign {1}\n.global {0}",
         function.name,
         E::VALUE_SIZE
     )?;
 
+    for directive in E::FUNCTION_HEADER {
+        writeln!(output, "{}", directive)?;
+    }
+
+    writeln!(output, "{}:", function.name)?;
+
+    let liveness = Liveness::analyze(&function.body, locals);
+    let homes = regs::assign_homes(function, &liveness, locals);
+    let used = homes.iter().filter(|home| home.is_some()).count();
+
     let context = Context {
         function,
         output: RefCell::new(output),
         locals,
         next_label: agnostic_labels,
         frame_info: Default::default(),
+        liveness,
+        position: 0,
+        homes,
+        callee_saved: &E::Register::CALLEE_SAVED[..used],
     };
 
     let mut emitter = E::new(context, &function.body)?;
+    let frame_size = emitter.frame_size();
     let mut last_was_unconditional_jump = false;
+    let mut lines = Vec::new();
+    let mut data = 0;
+    let mut last_location = None;
 
-    for instruction in &function.body {
+    for (position, instruction) in function.body.iter().enumerate() {
         use Instruction::*;
 
-        last_was_unconditional_jump = false;
+        let after_jump = std::mem::replace(&mut last_was_unconditional_jump, false);
+
+        let (cx, _) = emitter.cx_regs();
+        cx.position = position;
+
+        let start = instruction.location().start();
+        if debug_lines && last_location != Some(start) {
+            writeln!(cx, "\t.loc 1 {} {}", start.line(), start.column())?;
+        }
+
+        // Una línea inicia donde cambia la ubicación de las instrucciones
+        if last_location.map(|last| last.line()) != Some(start.line()) {
+            writeln!(cx, "\t.L{}.line.{}:", function.name, lines.len())?;
+            lines.push(start.line() as i32);
+        }
+
+        last_location = Some(start);
 
-        match instruction {
+        match instruction.as_ref() {
             Move(from, to) => {
                 if *from != *to {
                     let from = emitter.read(*from)?;
@@ -186,6 +391,19 @@ fn emit_body<'a, E: Emitter<'a>>(
                 emitter.load_global(global, reg)?;
             }
 
+            LoadData(_, local) => {
+                let label = format!(".L{}.data.{}", function.name, data);
+                data += 1;
+
+                let reg = emitter.write(*local)?;
+                emitter.load_address(&label, reg)?;
+            }
+
+            LoadProcedure(name, local) => {
+                let reg = emitter.write(*local)?;
+                emitter.load_address(name, reg)?;
+            }
+
             StoreGlobal(local, global) => {
                 let reg = emitter.read(*local)?;
                 emitter.store_global(reg, global)?;
@@ -194,13 +412,13 @@ fn emit_body<'a, E: Emitter<'a>>(
             Not(local) => {
                 let reg = emitter.read(*local)?;
                 emitter.not(reg)?;
-                emitter.assert_dirty(reg, *local);
+                emitter.assert_dirty(reg, *local)?;
             }
 
             Negate(local) => {
                 let reg = emitter.read(*local)?;
                 emitter.negate(reg)?;
-                emitter.assert_dirty(reg, *local);
+                emitter.assert_dirty(reg, *local)?;
             }
 
             Binary(lhs, op, rhs) => {
@@ -208,7 +426,7 @@ fn emit_body<'a, E: Emitter<'a>>(
                 let rhs_reg = emitter.read(*rhs)?;
 
                 emitter.binary(lhs_reg, *op, rhs_reg)?;
-                emitter.assert_dirty(lhs_reg, *lhs);
+                emitter.assert_dirty(lhs_reg, *lhs)?;
             }
 
             Call {
@@ -223,8 +441,26 @@ fn emit_body<'a, E: Emitter<'a>>(
                 emitter.call(&target, call_info)?;
 
                 if let Some(output) = output {
-                    emitter.assert_dirty(E::Register::RETURN, *output);
+                    emitter.assert_dirty(E::Register::RETURN, *output)?;
+                }
+            }
+
+            Return(value) => {
+                if let Some(value) = value {
+                    let (cx, regs) = emitter.cx_regs();
+                    cx.read_into(regs, E::Register::RETURN, *value)?;
                 }
+
+                emitter.epilogue()?;
+                last_was_unconditional_jump = true;
+            }
+
+            Kill(local) => {
+                // No se emite código, por lo que un salto previo sigue siendo el último
+                last_was_unconditional_jump = after_jump;
+
+                let (cx, regs) = emitter.cx_regs();
+                cx.kill(regs, *local);
             }
         }
     }
@@ -233,12 +469,26 @@ fn emit_body<'a, E: Emitter<'a>>(
         emitter.epilogue()?;
     }
 
-    Ok(())
+    Ok((lines, frame_size))
+}
+
+/// Cantidad de locales, incluyendo parámetros, y de etiquetas que
+/// utiliza una función.
+pub(crate) fn locals_and_labels(function: &GeneratedFunction) -> (u32, u32) {
+    let (locals, labels) = function
+        .body
+        .iter()
+        .map(|instruction| required_locals_and_labels(instruction))
+        .fold((0, 0), |(max_locals, max_labels), (locals, labels)| {
+            (max_locals.max(locals), max_labels.max(labels))
+        });
+
+    (locals.max(function.parameters), labels)
 }
 
 /// Cuenta la mínima cantidad de locales y etiquetas que una instrucción exige
 /// que se encuentren disponibles y/o en uso.
-fn required_locals_and_labels(instruction: &Instruction) -> (u32, u32) {
+pub(super) fn required_locals_and_labels(instruction: &Instruction) -> (u32, u32) {
     use Instruction::*;
 
     let locals = |Local(local)| local + 1;
@@ -251,19 +501,29 @@ fn required_locals_and_labels(instruction: &Instruction) -> (u32, u32) {
         JumpIfFalse(local, label) => (locals(*local), labels(*label)),
         LoadConst(_, local) => (locals(*local), 0),
         LoadGlobal(_, local) => (locals(*local), 0),
+        LoadData(_, local) => (locals(*local), 0),
+        LoadProcedure(_, local) => (locals(*local), 0),
         StoreGlobal(local, _) => (locals(*local), 0),
         Not(local) => (locals(*local), 0),
         Negate(local) => (locals(*local), 0),
         Binary(lhs, _, rhs) => (locals(*lhs).max(locals(*rhs)), 0),
+        Return(value) => (value.map(locals).unwrap_or(0), 0),
+        Kill(local) => (locals(*local), 0),
 
         Call {
-            arguments, output, ..
+            target,
+            arguments,
+            output,
         } => arguments
             .iter()
             .copied()
+            .chain(output.iter().copied())
+            .chain(match target {
+                Function::Indirect(local) => Some(*local),
+                _ => None,
+            })
             .map(locals)
             .max()
-            .or(output.map(locals))
             .map(|required| (required, 0))
             .unwrap_or((0, 0)),
     }
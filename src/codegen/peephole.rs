@@ -0,0 +1,230 @@
+//! Optimización de mirilla ("peephole").
+//!
+//! El código ensamblador de cada función se retiene en un búfer en
+//! vez de escribirse directamente al flujo de salida. Al finalizar
+//! la función se aplican repetidamente patrones de reescritura sobre
+//! secuencias cortas de líneas hasta que ninguno tenga efecto, y solo
+//! entonces se escribe el resultado.
+//!
+//! Los patrones dependen de la arquitectura, por lo cual cada
+//! implementación de [`crate::arch::Emitter`] expone los suyos. Este
+//! módulo únicamente provee el mecanismo y utilidades comunes.
+
+use std::io::{self, Write};
+
+/// Un patrón de reescritura.
+///
+/// Recibe las líneas de una función y la posición en la que se intenta
+/// aplicar. Retorna `true` si modificó las líneas.
+pub type Pattern = fn(&mut Vec<String>, usize) -> bool;
+
+/// Flujo de salida que aplica patrones de mirilla.
+pub struct Peephole<'a> {
+    output: &'a mut dyn Write,
+    buffer: Vec<u8>,
+    patterns: &'static [Pattern],
+}
+
+impl<'a> Peephole<'a> {
+    pub fn new(output: &'a mut dyn Write, patterns: &'static [Pattern]) -> Self {
+        Peephole {
+            output,
+            buffer: Vec::new(),
+            patterns,
+        }
+    }
+
+    /// Optimiza lo retenido y lo escribe al flujo de salida.
+    pub fn finish(self) -> io::Result<()> {
+        let text = String::from_utf8(self.buffer)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        let mut lines: Vec<String> = text.lines().map(String::from).collect();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            let mut at = 0;
+            while at < lines.len() {
+                for pattern in self.patterns {
+                    if at < lines.len() {
+                        changed |= pattern(&mut lines, at);
+                    }
+                }
+
+                at += 1;
+            }
+        }
+
+        for line in lines {
+            writeln!(self.output, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for Peephole<'_> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Una instrucción ensamblable, separada en opcode y operandos.
+pub struct Line<'l> {
+    pub opcode: &'l str,
+    pub operands: Vec<&'l str>,
+}
+
+impl<'l> Line<'l> {
+    /// Interpreta una línea emitida por `emit!()`. Etiquetas y
+    /// directivas no son instrucciones y resultan en `None`.
+    pub fn parse(line: &'l str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.ends_with(':') || line.starts_with('.') {
+            return None;
+        }
+
+        let (opcode, operands) = match line.find(char::is_whitespace) {
+            Some(split) => (&line[..split], line[split..].trim()),
+            None => (line, ""),
+        };
+
+        let operands = match operands {
+            "" => Vec::new(),
+            _ => operands.split(',').map(str::trim).collect(),
+        };
+
+        Some(Line { opcode, operands })
+    }
+
+    /// Reconstruye la línea en el mismo formato tabular de `emit!()`.
+    pub fn format(opcode: &str, operands: &[&str]) -> String {
+        if operands.is_empty() {
+            format!("\t{}", opcode)
+        } else {
+            format!("\t{:8}{}", opcode, operands.join(", "))
+        }
+    }
+}
+
+/// Efecto de una instrucción sobre un registro en particular.
+pub enum Effect {
+    /// La instrucción no involucra al registro.
+    Ignores,
+
+    /// La instrucción podría leer el registro.
+    Reads,
+
+    /// La instrucción sobreescribe el registro sin leerlo antes, o
+    /// el flujo de control abandona la región en la que es visible.
+    Kills,
+}
+
+/// Determina si el valor de un registro ya no se lee a partir de
+/// una línea, según la clasificación de efectos de la arquitectura.
+///
+/// El recorrido es lineal y conservador: se asume que el valor se
+/// lee si se alcanza el final de lo retenido sin determinar lo contrario
+/// mediante una instrucción que lo sobreescriba o abandone la función.
+pub fn is_dead_from<F>(lines: &[String], from: usize, effect: F) -> bool
+where
+    F: Fn(&Line<'_>) -> Effect,
+{
+    for line in lines.iter().skip(from) {
+        let line = match Line::parse(line) {
+            Some(line) => line,
+            None => continue,
+        };
+
+        match effect(&line) {
+            Effect::Ignores => continue,
+            Effect::Reads => return false,
+            Effect::Kills => return true,
+        }
+    }
+
+    false
+}
+
+/// Busca hacia atrás la instrucción más cercana que menciona un registro,
+/// siempre que entre ella y `before` no haya etiquetas, saltos o llamadas.
+pub fn last_mention<F>(lines: &[String], before: usize, mentions: F) -> Option<usize>
+where
+    F: Fn(&Line<'_>) -> bool,
+{
+    for at in (0..before).rev() {
+        let line = Line::parse(&lines[at])?;
+        if mentions(&line) {
+            return Some(at);
+        } else if ["j", "b", "call"]
+            .iter()
+            .any(|prefix| line.opcode.starts_with(prefix))
+        {
+            return None;
+        }
+    }
+
+    None
+}
+
+/// Fusiona dos ajustes consecutivos al puntero de pila.
+///
+/// `parse` extrae el desplazamiento de una línea si es un ajuste de pila,
+/// mientras que `format` genera el ajuste combinado o `None` si este no
+/// puede codificarse en una sola instrucción.
+pub fn merge_stack_adjustments<P, F>(
+    lines: &mut Vec<String>,
+    at: usize,
+    parse: P,
+    format: F,
+) -> bool
+where
+    P: Fn(&Line<'_>) -> Option<i64>,
+    F: Fn(i64) -> Option<String>,
+{
+    let offset = |at: usize| lines.get(at).and_then(|line| parse(&Line::parse(line)?));
+
+    let total = match (offset(at), offset(at + 1)) {
+        (Some(first), Some(second)) => first + second,
+        _ => return false,
+    };
+
+    if total == 0 {
+        lines.drain(at..at + 2);
+        return true;
+    }
+
+    match format(total) {
+        Some(merged) => {
+            lines.splice(at..at + 2, std::iter::once(merged));
+            true
+        }
+
+        None => false,
+    }
+}
+
+/// Elimina copias de un registro a sí mismo.
+pub fn remove_self_move(lines: &mut Vec<String>, at: usize, opcode: &str) -> bool {
+    let is_self_move = match Line::parse(&lines[at]) {
+        Some(Line {
+            opcode: found,
+            operands,
+        }) => found == opcode && operands.len() == 2 && operands[0] == operands[1],
+
+        None => false,
+    };
+
+    if is_self_move {
+        lines.remove(at);
+    }
+
+    is_self_move
+}
@@ -1,9 +1,12 @@
-use super::Context;
+use super::{
+    liveness::{self, Liveness},
+    Context,
+};
 use crate::{
     arch::{Emitter, Register},
-    ir::Local,
+    ir::{GeneratedFunction, Instruction, Local},
 };
-use std::io;
+use std::{cmp::Reverse, io};
 
 pub struct Allocations<'a, E: Emitter<'a>> {
     slots: Vec<Slot<E::Register>>,
@@ -27,10 +30,12 @@ impl<'a, E: Emitter<'a>> Context<'a, E> {
             let reg = slot.reg;
 
             if let Some(entry) = &mut slot.entry {
-                if entry.dirty {
+                // Valores que no se volverán a leer no requieren copiarse a memoria
+                if entry.dirty && self.is_needed(entry.local) {
                     E::reg_to_local(self, reg, entry.local)?;
-                    entry.dirty = false;
                 }
+
+                entry.dirty = false;
             }
         }
 
@@ -47,6 +52,10 @@ impl<'a, E: Emitter<'a>> Context<'a, E> {
     }
 
     pub fn read(&self, regs: &mut Allocations<'a, E>, local: Local) -> io::Result<E::Register> {
+        if let Some(home) = self.home(local) {
+            return Ok(home);
+        }
+
         if let Some((reg, _)) = regs.find_local(local) {
             return Ok(reg);
         }
@@ -64,6 +73,10 @@ impl<'a, E: Emitter<'a>> Context<'a, E> {
     }
 
     pub fn write(&self, regs: &mut Allocations<'a, E>, local: Local) -> io::Result<E::Register> {
+        if let Some(home) = self.home(local) {
+            return Ok(home);
+        }
+
         if let Some((reg, entry)) = regs.find_local(local) {
             entry.as_mut().unwrap().dirty = true;
             return Ok(reg);
@@ -92,6 +105,12 @@ impl<'a, E: Emitter<'a>> Context<'a, E> {
         reg: E::Register,
         local: Local,
     ) -> io::Result<()> {
+        // El registro queda libre, ya que la local permanece en su home
+        if let Some(home) = self.home(local) {
+            self.evict(regs, reg)?;
+            return E::reg_to_reg(self, home, reg);
+        }
+
         let move_from = match regs.find_local(local) {
             Some((old_reg, old_entry)) if old_reg != reg => {
                 *old_entry = None;
@@ -134,7 +153,20 @@ impl<'a, E: Emitter<'a>> Context<'a, E> {
         }
     }
 
-    pub fn assert_dirty(&self, regs: &mut Allocations<'a, E>, reg: E::Register, local: Local) {
+    pub fn assert_dirty(
+        &self,
+        regs: &mut Allocations<'a, E>,
+        reg: E::Register,
+        local: Local,
+    ) -> io::Result<()> {
+        if let Some(home) = self.home(local) {
+            return if reg != home {
+                E::reg_to_reg(self, reg, home)
+            } else {
+                Ok(())
+            };
+        }
+
         regs.slots
             .iter()
             .filter_map(|slot| match &slot.entry {
@@ -163,6 +195,44 @@ impl<'a, E: Emitter<'a>> Context<'a, E> {
             dirty: true,
             sequence,
         });
+
+        Ok(())
+    }
+
+    /// Desocupa el registro de una local cuyo valor no se volverá a leer,
+    /// sin copiarla a memoria. Ver [`crate::ir::Instruction::Kill`].
+    pub fn kill(&self, regs: &mut Allocations<'a, E>, local: Local) {
+        if let Some((_, entry)) = regs.find_local(local) {
+            *entry = None;
+        }
+    }
+
+    /// Copia una local a un registro ajeno a la reservación, como un
+    /// temporal propio de la arquitectura. Ninguna otra copia de la
+    /// local puede estar dirty, lo cual se cumple tras [`Context::spill()`].
+    pub fn copy_into(&self, reg: E::Register, local: Local) -> io::Result<()> {
+        match self.home(local) {
+            Some(home) => E::reg_to_reg(self, home, reg),
+            None => E::local_to_reg(self, local, reg),
+        }
+    }
+
+    /// Libera un registro, copiando a memoria la local que contenía si
+    /// es necesario.
+    fn evict(&self, regs: &mut Allocations<'a, E>, reg: E::Register) -> io::Result<()> {
+        let slot = regs
+            .slots
+            .iter_mut()
+            .find(|slot| slot.reg == reg)
+            .expect("register not in file");
+
+        match slot.entry.take() {
+            Some(entry) if entry.dirty && self.is_needed(entry.local) => {
+                E::reg_to_local(self, reg, entry.local)
+            }
+
+            _ => Ok(()),
+        }
     }
 
     fn take_slot<'b>(
@@ -205,17 +275,83 @@ impl<'a, E: Emitter<'a>> Context<'a, E> {
                 .slots
                 .iter_mut()
                 .filter(|slot| locked.iter().find(|locked| **locked == slot.reg).is_none())
-                .min_by_key(|slot| slot.entry.as_ref().unwrap().sequence)
+                .min_by_key(|slot| self.spill_cost(slot.entry.as_ref().unwrap()))
                 .expect("register file exhaustion");
 
             let entry = slot.entry.as_ref().unwrap();
-            if entry.dirty {
+            if entry.dirty && self.is_needed(entry.local) {
                 E::reg_to_local(self, slot.reg, entry.local)?;
             }
 
             Ok(slot)
         }
     }
+
+    /// Determina si el valor de una local debe preservarse en este punto.
+    fn is_needed(&self, local: Local) -> bool {
+        self.liveness().is_needed(self.position(), local)
+    }
+
+    /// Ordena candidatos de spill, siendo preferibles los de menor costo.
+    ///
+    /// Primero se eligen locales muertas, cuyo registro puede reutilizarse
+    /// sin escribir a memoria. Le siguen locales vivas que no participan
+    /// en la instrucción actual, en cuyo caso se elige aquella cuyo intervalo
+    /// de vida termina más lejos, como en linear scan. Como último recurso
+    /// se elige por LRU entre las locales de la instrucción actual.
+    fn spill_cost(&self, entry: &Entry) -> (u8, usize) {
+        let referenced = self
+            .current()
+            .map(|instruction| liveness::is_referenced(instruction, entry.local))
+            .unwrap_or(true);
+
+        if referenced {
+            (2, entry.sequence)
+        } else if !self.is_needed(entry.local) {
+            (0, 0)
+        } else {
+            let end = self.liveness().interval_end(entry.local);
+            (1, usize::MAX - end)
+        }
+    }
+}
+
+/// Asigna los registros callee-saved de la arquitectura, ver
+/// [`Register::CALLEE_SAVED`], a las locales que sobreviven más llamadas.
+///
+/// Cada una de estas locales reside en su registro durante toda la
+/// función y nunca ocupa un slot de reservación, por lo cual no se copia
+/// a memoria alrededor de cada llamada. Los registros se asignan en el
+/// orden en que la arquitectura los lista.
+pub(super) fn assign_homes<R: Register>(
+    function: &GeneratedFunction,
+    liveness: &Liveness,
+    locals: u32,
+) -> Vec<Option<R>> {
+    let mut crossed = vec![0; locals as usize];
+    for (position, instruction) in function.body.iter().enumerate() {
+        if let Instruction::Call { output, .. } = instruction.as_ref() {
+            let survivors = (0..locals).map(Local).filter(|local| {
+                Some(*local) != *output && liveness.is_live_after(position, *local)
+            });
+
+            for Local(local) in survivors {
+                crossed[local as usize] += 1;
+            }
+        }
+    }
+
+    let mut candidates: Vec<_> = (0..locals)
+        .filter(|local| crossed[*local as usize] > 0)
+        .collect();
+    candidates.sort_by_key(|local| Reverse(crossed[*local as usize]));
+
+    let mut homes = vec![None; locals as usize];
+    for (local, reg) in candidates.into_iter().zip(R::CALLEE_SAVED) {
+        homes[local as usize] = Some(*reg);
+    }
+
+    homes
 }
 
 impl<'a, E: Emitter<'a>> Allocations<'a, E> {
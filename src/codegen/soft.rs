@@ -0,0 +1,42 @@
+//! Aritmética entera por software.
+//!
+//! Algunas variantes de Xtensa carecen de `mull`, y ninguna de las que
+//! se soportan divide en hardware. Con `--no-hw-mul`, cada
+//! multiplicación, división y módulo entero se reemplaza por una
+//! llamada a una rutina de `libruntime`, de forma que el código emitido
+//! no dependa de esas instrucciones ni de `libgcc`. Estas rutinas
+//! reportan la división entre cero como un error en tiempo de ejecución
+//! aún sin `--checked-arithmetic`.
+
+use crate::ir::{ArithmeticOp, BinOp, Function, Instruction, Program};
+
+/// Sustituye operaciones aritméticas por llamadas en todo el programa.
+///
+/// Debe aplicarse luego de [`super::checks::check_division()`], ya que
+/// este último solo reconoce divisiones en forma de `Binary`.
+pub fn soft_arithmetic(program: &mut Program) {
+    let instructions = program
+        .code
+        .iter_mut()
+        .flat_map(|function| function.body.iter_mut());
+
+    for instruction in instructions {
+        if let Instruction::Binary(lhs, BinOp::Arithmetic(op), rhs) = instruction.as_ref() {
+            let (lhs, rhs) = (*lhs, *rhs);
+
+            let routine = match op {
+                ArithmeticOp::Mul => "builtin_mul_int",
+                ArithmeticOp::Div => "builtin_intdiv_int",
+                ArithmeticOp::Mod => "builtin_mod_int",
+                _ => continue,
+            };
+
+            // `Binary` opera en su lugar sobre `lhs`
+            **instruction = Instruction::Call {
+                target: Function::External(routine),
+                arguments: vec![lhs, rhs],
+                output: Some(lhs),
+            };
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Estimación de profundidad de pila.
+//!
+//! El ESP8266 dispone de apenas unos cuantos KiB de pila, y un
+//! desbordamiento corrompe silenciosamente el heap que se encuentra
+//! inmediatamente debajo. Este análisis recorre el grafo de llamadas
+//! del programa a partir de `user_main` y suma los tamaños de marco que
+//! dispone el emisor de cada función a lo largo de la cadena de llamadas
+//! más costosa. Toda recursión, directa o indirecta, hace que la
+//! profundidad no tenga cota.
+//!
+//! Los builtins de `libruntime` no se consideran, por lo que el
+//! resultado es una cota inferior del uso real.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    io,
+    rc::Rc,
+};
+
+use crate::{
+    arch::Arch,
+    ir::{Function, GeneratedFunction, Instruction, Program},
+};
+
+use super::emit_body;
+
+/// Punto de entrada del programa.
+const ENTRY: &str = "user_main";
+
+/// Uso de pila en el peor caso.
+pub enum StackUsage {
+    /// Cadena de llamadas más costosa, con el tamaño de marco de cada función.
+    Bounded(Vec<(Rc<String>, u32)>),
+
+    /// Ciclo de llamadas recursivas, iniciando y terminando en la misma función.
+    Unbounded(Vec<Rc<String>>),
+}
+
+impl StackUsage {
+    /// Total de bytes en el peor caso, si existe una cota.
+    pub fn bytes(&self) -> Option<u32> {
+        match self {
+            StackUsage::Bounded(path) => Some(path.iter().map(|(_, frame)| frame).sum()),
+            StackUsage::Unbounded(_) => None,
+        }
+    }
+
+    /// Determina si el uso de pila puede exceder un límite.
+    pub fn exceeds(&self, limit: u64) -> bool {
+        match self.bytes() {
+            Some(bytes) => u64::from(bytes) > limit,
+            None => true,
+        }
+    }
+}
+
+impl Display for StackUsage {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StackUsage::Bounded(path) => {
+                write!(fmt, "{} bytes in the worst case", self.bytes().unwrap())?;
+                for (function, frame) in path {
+                    write!(fmt, "\n  {}: {} bytes", function, frame)?;
+                }
+            }
+
+            StackUsage::Unbounded(cycle) => {
+                write!(fmt, "unbounded due to recursion")?;
+                for function in cycle {
+                    write!(fmt, "\n  {}", function)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Estado de una función durante el recorrido del grafo de llamadas.
+enum Visit {
+    /// La función se encuentra en la cadena de llamadas actual.
+    Active,
+
+    /// Profundidad de pila a partir de esta función, junto a la
+    /// llamada que la determina.
+    Done(u32, Option<Rc<String>>),
+}
+
+/// Estima el uso de pila de un programa en una arquitectura.
+///
+/// WebAssembly no tiene una pila direccionable, por lo cual no
+/// admite este análisis.
+pub fn stack_usage(program: &Program, arch: Arch) -> StackUsage {
+    let functions: HashMap<&str, &GeneratedFunction> = program
+        .code
+        .iter()
+        .map(|function| (function.name.as_str(), function))
+        .collect();
+
+    let mut frames = HashMap::new();
+    for function in &program.code {
+        frames.insert(function.name.as_str(), frame_size(function, arch));
+    }
+
+    let entry = match functions.get(ENTRY) {
+        Some(entry) => Rc::clone(&entry.name),
+        None => return StackUsage::Bounded(Vec::new()),
+    };
+
+    let mut visits = HashMap::new();
+    let mut active = Vec::new();
+
+    if let Err(cycle) = visit(&entry, &functions, &frames, &mut visits, &mut active) {
+        return StackUsage::Unbounded(cycle);
+    }
+
+    // Se reconstruye la cadena siguiendo la llamada más costosa de cada función
+    let mut path = Vec::new();
+    let mut next = Some(entry);
+
+    while let Some(function) = next {
+        next = match visits.get(function.as_str()) {
+            Some(Visit::Done(_, callee)) => callee.clone(),
+            _ => None,
+        };
+
+        path.push((Rc::clone(&function), frames[function.as_str()]));
+    }
+
+    StackUsage::Bounded(path)
+}
+
+/// Calcula la profundidad de pila a partir de una función.
+///
+/// En caso de recursión, se retorna el ciclo encontrado.
+fn visit<'a>(
+    function: &Rc<String>,
+    functions: &HashMap<&str, &'a GeneratedFunction>,
+    frames: &HashMap<&str, u32>,
+    visits: &mut HashMap<&'a str, Visit>,
+    active: &mut Vec<Rc<String>>,
+) -> Result<u32, Vec<Rc<String>>> {
+    let generated = match functions.get(function.as_str()) {
+        Some(generated) => *generated,
+        None => return Ok(0),
+    };
+
+    match visits.get(function.as_str()) {
+        Some(Visit::Done(depth, _)) => return Ok(*depth),
+
+        Some(Visit::Active) => {
+            let start = active.iter().position(|other| other == function).unwrap();
+            let mut cycle = active[start..].to_vec();
+            cycle.push(Rc::clone(function));
+
+            return Err(cycle);
+        }
+
+        None => (),
+    }
+
+    visits.insert(generated.name.as_str(), Visit::Active);
+    active.push(Rc::clone(function));
+
+    let mut deepest = (0, None);
+    for instruction in &generated.body {
+        // Un procedimiento planificado se ejecuta durante alguna espera
+        // posterior, lo cual se aproxima como una llamada desde este punto.
+        // Lo mismo aplica a las llamadas indirectas a través de `@`
+        let callee = match instruction.as_ref() {
+            Instruction::Call {
+                target: Function::Generated(callee),
+                ..
+            } => Some(callee),
+
+            Instruction::LoadProcedure(callee, _) => Some(callee),
+            _ => None,
+        };
+
+        if let Some(callee) = callee {
+            let depth = visit(callee, functions, frames, visits, active)?;
+            if depth > deepest.0 {
+                deepest = (depth, Some(Rc::clone(callee)));
+            }
+        }
+    }
+
+    active.pop();
+
+    let depth = frames[function.as_str()] + deepest.0;
+    visits.insert(generated.name.as_str(), Visit::Done(depth, deepest.1));
+
+    Ok(depth)
+}
+
+/// Tamaño del marco de llamada de una función, según su emisor.
+fn frame_size(function: &GeneratedFunction, arch: Arch) -> u32 {
+    dispatch_arch!(Emitter: arch => {
+        let (_, frame_size) = emit_body::<Emitter>(&mut io::sink(), function, false)
+            .expect("writes to io::sink() cannot fail");
+
+        frame_size
+    })
+}
@@ -0,0 +1,206 @@
+//! Verificación de representación intermedia.
+//!
+//! Las fases que producen o transforman IR asumen una serie de
+//! invariantes que ninguna de ellas comprueba. Una violación no
+//! suele fallar de inmediato, sino que produce código incorrecto o
+//! un pánico lejos de su origen. Con `--verify-ir`, cada programa se
+//! verifica antes de generar código:
+//!
+//! - Cada etiqueta se define exactamente una vez.
+//! - Todo salto refiere a una etiqueta definida.
+//! - Toda local referida está dentro del límite que calcula la
+//!   generación de código para el marco de la función.
+//! - Toda función externa es una rutina de `libruntime`, según
+//!   [`crate::builtins`].
+//! - Ningún `Move` lee una local que, en algún camino desde el inicio
+//!   de la función, no se ha escrito. Los parámetros se consideran
+//!   escritos desde el inicio.
+//!
+//! Una violación es siempre un error del compilador, no del programa.
+
+use std::rc::Rc;
+
+use thiserror::Error;
+
+use crate::{
+    builtins,
+    ir::{cfg::Cfg, Function, GeneratedFunction, Instruction, Label, Local, Program},
+};
+
+use super::{
+    liveness::{accesses, Access, LocalSet},
+    locals_and_labels,
+};
+
+/// Una violación de los invariantes de IR.
+#[derive(Error, Debug)]
+#[error("Invalid IR in `{function}`, instruction {index}: {violation}")]
+pub struct VerifyError {
+    /// Función que contiene la instrucción.
+    pub function: Rc<String>,
+
+    /// Posición de la instrucción en el cuerpo de la función.
+    pub index: usize,
+
+    pub violation: Violation,
+}
+
+/// Invariante que viola una instrucción.
+#[derive(Error, Debug)]
+pub enum Violation {
+    #[error("label {0} is already defined")]
+    RedefinedLabel(u32),
+
+    #[error("jump to undefined label {0}")]
+    UndefinedLabel(u32),
+
+    #[error("local {0} exceeds the frame bound of {1} locals")]
+    LocalOutOfBounds(u32, u32),
+
+    #[error("call to unknown external `{0}`")]
+    UnknownExternal(&'static str),
+
+    #[error("move reads local {0}, which may be uninitialized")]
+    Uninitialized(u32),
+}
+
+/// Verifica todas las funciones de un programa.
+pub fn verify(program: &Program) -> Result<(), VerifyError> {
+    program.code.iter().try_for_each(verify_function)
+}
+
+fn verify_function(function: &GeneratedFunction) -> Result<(), VerifyError> {
+    let error = |index, violation| VerifyError {
+        function: Rc::clone(&function.name),
+        index,
+        violation,
+    };
+
+    let (locals, labels) = locals_and_labels(function);
+
+    let mut defined = vec![false; labels as usize];
+    for (index, instruction) in function.body.iter().enumerate() {
+        if let Instruction::SetLabel(Label(label)) = instruction.as_ref() {
+            let defined = &mut defined[*label as usize];
+            if *defined {
+                return Err(error(index, Violation::RedefinedLabel(*label)));
+            }
+
+            *defined = true;
+        }
+    }
+
+    for (index, instruction) in function.body.iter().enumerate() {
+        match instruction.as_ref() {
+            Instruction::Jump(Label(label)) | Instruction::JumpIfFalse(_, Label(label))
+                if !defined[*label as usize] =>
+            {
+                return Err(error(index, Violation::UndefinedLabel(*label)));
+            }
+
+            Instruction::Call {
+                target: Function::External(name),
+                ..
+            } if !builtins::is_builtin(name) => {
+                return Err(error(index, Violation::UnknownExternal(name)));
+            }
+
+            instruction => {
+                let mut out_of_bounds = None;
+                accesses(instruction, |Local(local), _| {
+                    if local >= locals {
+                        out_of_bounds.get_or_insert(local);
+                    }
+                });
+
+                if let Some(local) = out_of_bounds {
+                    let violation = Violation::LocalOutOfBounds(local, locals);
+                    return Err(error(index, violation));
+                }
+            }
+        }
+    }
+
+    match uninitialized_move(function, locals) {
+        Some((index, Local(local))) => Err(error(index, Violation::Uninitialized(local))),
+        None => Ok(()),
+    }
+}
+
+/// Busca un `Move` cuyo origen no se ha escrito en todo camino que
+/// llega a él.
+///
+/// Es el flujo de datos hacia adelante de asignación definitiva: una
+/// local está inicializada al inicio de un bloque básico si lo está al
+/// final de todos sus predecesores. Las instrucciones inalcanzables
+/// conservan el conjunto completo y por tanto no se reportan.
+fn uninitialized_move(function: &GeneratedFunction, locals: u32) -> Option<(usize, Local)> {
+    let body = &function.body;
+    if body.is_empty() {
+        return None;
+    }
+
+    let cfg = Cfg::build(function);
+    let blocks = cfg.blocks();
+
+    let mut parameters = LocalSet::new(locals);
+    for parameter in 0..function.parameters {
+        parameters.insert(Local(parameter));
+    }
+
+    let full = LocalSet::full(locals);
+    let mut init_in = vec![full.clone(); blocks.len()];
+    let mut init_out = vec![full; blocks.len()];
+
+    let define = |initialized: &mut LocalSet, instruction: &Instruction| {
+        accesses(instruction, |local, access| match access {
+            Access::Def => initialized.insert(local),
+            Access::Use | Access::Kill => (),
+        });
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for (index, block) in blocks.iter().enumerate() {
+            let mut input = match index {
+                0 => parameters.clone(),
+                _ => LocalSet::full(locals),
+            };
+
+            for predecessor in &block.predecessors {
+                input.intersect(&init_out[*predecessor]);
+            }
+
+            let mut output = input.clone();
+            for instruction in &body[block.instructions.clone()] {
+                define(&mut output, instruction);
+            }
+
+            if input != init_in[index] || output != init_out[index] {
+                init_in[index] = input;
+                init_out[index] = output;
+                changed = true;
+            }
+        }
+    }
+
+    blocks
+        .iter()
+        .zip(init_in)
+        .find_map(|(block, mut initialized)| {
+            for position in block.instructions.clone() {
+                let instruction = body[position].as_ref();
+                match instruction {
+                    Instruction::Move(from, _) if !initialized.contains(*from) => {
+                        return Some((position, *from));
+                    }
+
+                    _ => define(&mut initialized, instruction),
+                }
+            }
+
+            None
+        })
+}
@@ -0,0 +1,412 @@
+//! Servidor de compilación persistente.
+//!
+//! Iniciar el compilador por cada cambio en el editor implica
+//! cargar el proceso, releer el código fuente y repetir todas las
+//! fases aunque nada haya cambiado. Con `--daemon SOCKET` el
+//! compilador permanece en ejecución y atiende solicitudes por
+//! un socket Unix local.
+//!
+//! # Protocolo
+//! Cada solicitud es un objeto JSON en una única línea. Cada
+//! respuesta es también un objeto JSON en una única línea, en el
+//! mismo orden que las solicitudes. El campo `command` selecciona
+//! la operación:
+//!
+//! - `check`: análisis léxico, sintáctico y semántico.
+//! - `compile`: lo anterior, generación de código y enlazado a `output`.
+//! - `forget`: descarta lo que se recuerda de `path`.
+//! - `shutdown`: detiene el servidor.
+//!
+//! Si el programa es rechazado, `diagnostics` contiene los errores
+//! en el mismo formato que la CLI y `structured` los mismos errores
+//! como arreglo de objetos, con la forma que produce
+//! `--error-format=json`.
+//!
+//! Un campo opcional `id` se repite sin cambios en la respuesta.
+//! Si `source` está presente, este reemplaza al contenido del
+//! archivo `path`, lo cual permite verificar buffers sin guardar.
+//! Las rutas relativas se resuelven desde el directorio de trabajo
+//! del servidor.
+//!
+//! Las conexiones se atienden una a la vez, por lo cual los clientes
+//! deben cerrar la suya una vez que obtengan sus respuestas.
+//!
+//! # Sesión
+//! Por cada archivo se recuerda un hash de su último contenido junto
+//! al resultado del front end y el ensamblador emitido por plataforma.
+//! Solicitudes sobre contenido sin cambios reutilizan ese trabajo.
+//! Si el contenido cambió, un [`Cache`] por archivo evita repetir el
+//! análisis semántico y la generación de código de los procedimientos
+//! que no fueron modificados.
+
+use anyhow::{self, bail, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs,
+    hash::{Hash, Hasher},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
+    time::Instant,
+};
+
+use compiler::{
+    error::Diagnostic,
+    incremental::{Build, Cache},
+    link::{LinkOptions, OutputFormat, Platform, ScriptOptions},
+};
+
+/// Solicitud junto a su identificador.
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(default)]
+    id: Value,
+
+    #[serde(flatten)]
+    request: Request,
+}
+
+/// Operaciones que acepta el servidor.
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+enum Request {
+    Check {
+        path: PathBuf,
+        source: Option<String>,
+    },
+
+    Compile {
+        path: PathBuf,
+        source: Option<String>,
+        output: PathBuf,
+
+        #[serde(default = "default_target")]
+        target: String,
+
+        #[serde(default = "default_format")]
+        format: String,
+
+        #[serde(default)]
+        strip: bool,
+    },
+
+    Forget {
+        path: PathBuf,
+    },
+
+    Shutdown,
+}
+
+/// Respuesta a una solicitud.
+#[derive(Serialize)]
+struct Response {
+    id: Value,
+
+    /// Si la operación concluyó sin errores de ninguna clase.
+    ok: bool,
+
+    /// Si el front end no se volvió a ejecutar.
+    cached: bool,
+
+    /// Errores de compilación, en el mismo formato que la CLI.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diagnostics: Option<String>,
+
+    /// Los mismos errores, en forma estructurada.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    structured: Option<Vec<Diagnostic>>,
+
+    /// Errores ajenos al programa: E/S, enlazado, solicitudes inválidas.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+
+    /// Tiempo que tomó atender la solicitud.
+    elapsed_ms: f64,
+}
+
+/// Lo que se recuerda de un archivo.
+struct Entry {
+    hash: u64,
+    frontend: Result<Rc<Build>, Rejected>,
+    assembly: HashMap<Platform, Rc<Vec<u8>>>,
+}
+
+/// Errores de un programa rechazado por el front end.
+#[derive(Clone)]
+struct Rejected {
+    text: String,
+    structured: Vec<Diagnostic>,
+}
+
+/// Estado compartido entre solicitudes.
+#[derive(Default)]
+struct Session {
+    entries: HashMap<PathBuf, Entry>,
+    caches: HashMap<PathBuf, Cache>,
+}
+
+/// Resultado de una operación sobre la sesión.
+enum Outcome {
+    Done,
+    Diagnostics(Rejected),
+}
+
+/// Atiende solicitudes en un socket hasta recibir `shutdown`.
+pub fn serve(socket: &Path) -> anyhow::Result<()> {
+    let listener = bind(socket)?;
+    eprintln!("Listening on {}", socket.display());
+
+    let mut session = Session::default();
+    let mut result = Ok(());
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                result = Err(error).context("Failed to accept connection");
+                break;
+            }
+        };
+
+        // Un cliente que falla no debe derribar al servidor
+        match session.converse(stream) {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(error) => eprintln!("Connection dropped: {:#}", error),
+        }
+    }
+
+    let _ = fs::remove_file(socket);
+    result
+}
+
+/// Crea el socket, reemplazando uno abandonado por un servidor anterior.
+fn bind(socket: &Path) -> anyhow::Result<UnixListener> {
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            bail!("Another daemon is listening on {}", socket.display());
+        }
+
+        fs::remove_file(socket)
+            .with_context(|| format!("Failed to remove stale socket: {}", socket.display()))?;
+    }
+
+    UnixListener::bind(socket)
+        .with_context(|| format!("Failed to bind socket: {}", socket.display()))
+}
+
+impl Session {
+    /// Atiende una conexión. Retorna `false` si se solicitó detener el servidor.
+    fn converse(&mut self, stream: UnixStream) -> anyhow::Result<bool> {
+        let mut writer = stream.try_clone()?;
+        let reader = BufReader::new(stream);
+
+        for line in reader.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(error) if error.kind() == ErrorKind::ConnectionReset => break,
+                Err(error) => return Err(error.into()),
+            };
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let start_time = Instant::now();
+            let (id, request) = match serde_json::from_str::<Envelope>(&line) {
+                Ok(Envelope { id, request }) => (id, Ok(request)),
+                Err(error) => (Value::Null, Err(error)),
+            };
+
+            let shutdown = matches!(request, Ok(Request::Shutdown));
+            let (cached, outcome) = match request {
+                Ok(request) => self.handle(request),
+                Err(error) => (
+                    false,
+                    Err(anyhow::Error::new(error).context("Malformed request")),
+                ),
+            };
+
+            let (rejected, error) = match outcome {
+                Ok(Outcome::Done) => (None, None),
+                Ok(Outcome::Diagnostics(rejected)) => (Some(rejected), None),
+                Err(error) => (None, Some(format!("{:#}", error))),
+            };
+
+            let (diagnostics, structured) = match rejected {
+                Some(Rejected { text, structured }) => (Some(text), Some(structured)),
+                None => (None, None),
+            };
+
+            let response = Response {
+                id,
+                ok: diagnostics.is_none() && error.is_none(),
+                cached,
+                diagnostics,
+                structured,
+                error,
+                elapsed_ms: start_time.elapsed().as_secs_f64() * 1000.0,
+            };
+
+            serde_json::to_writer(&mut writer, &response)?;
+            writer.write_all(b"\n")?;
+
+            if shutdown {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Ejecuta una solicitud. El primer valor indica si se reutilizó la sesión.
+    fn handle(&mut self, request: Request) -> (bool, anyhow::Result<Outcome>) {
+        match request {
+            Request::Check { path, source } => match self.frontend(&path, source) {
+                Ok((cached, entry, _)) => {
+                    let outcome = match &entry.frontend {
+                        Ok(_) => Outcome::Done,
+                        Err(diagnostics) => Outcome::Diagnostics(diagnostics.clone()),
+                    };
+
+                    (cached, Ok(outcome))
+                }
+
+                Err(error) => (false, Err(error)),
+            },
+
+            Request::Compile {
+                path,
+                source,
+                output,
+                target,
+                format,
+                strip,
+            } => {
+                let (cached, entry, cache) = match self.frontend(&path, source) {
+                    Ok(result) => result,
+                    Err(error) => return (false, Err(error)),
+                };
+
+                let outcome = compile(entry, cache, &output, &target, &format, strip);
+                (cached, outcome)
+            }
+
+            Request::Forget { path } => {
+                self.entries.remove(&path);
+                self.caches.remove(&path);
+                (false, Ok(Outcome::Done))
+            }
+
+            Request::Shutdown => (false, Ok(Outcome::Done)),
+        }
+    }
+
+    /// Ejecuta el front end, a menos que el contenido no haya cambiado.
+    fn frontend(
+        &mut self,
+        path: &Path,
+        source: Option<String>,
+    ) -> anyhow::Result<(bool, &mut Entry, &mut Cache)> {
+        let source = match source {
+            Some(source) => source,
+            None => fs::read_to_string(path)
+                .with_context(|| format!("Failed to open for reading: {}", path.display()))?,
+        };
+
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let cache = self.caches.entry(path.to_path_buf()).or_default();
+        let cached = matches!(self.entries.get(path), Some(entry) if entry.hash == hash);
+        if !cached {
+            let name = path.to_string_lossy();
+            let frontend = cache
+                .frontend(&mut source.as_bytes(), &name)
+                .map(|(build, _)| Rc::new(build))
+                .map_err(|diagnostics| Rejected {
+                    text: diagnostics.to_string(),
+                    structured: diagnostics.structured().collect(),
+                });
+
+            let entry = Entry {
+                hash,
+                frontend,
+                assembly: HashMap::new(),
+            };
+
+            self.entries.insert(path.to_path_buf(), entry);
+        }
+
+        Ok((cached, self.entries.get_mut(path).unwrap(), cache))
+    }
+}
+
+/// Genera código para un programa ya analizado y lo enlaza.
+fn compile(
+    entry: &mut Entry,
+    cache: &mut Cache,
+    output: &Path,
+    platform: &str,
+    format: &str,
+    strip: bool,
+) -> anyhow::Result<Outcome> {
+    let build = match &entry.frontend {
+        Ok(build) => Rc::clone(build),
+        Err(diagnostics) => return Ok(Outcome::Diagnostics(diagnostics.clone())),
+    };
+
+    let platform = match Platform::from_str(platform) {
+        Ok(platform) => platform,
+        Err(()) => bail!("Unknown target platform: {}", platform),
+    };
+
+    let format = match OutputFormat::from_str(format) {
+        Ok(format) => format,
+        Err(()) => bail!("Unknown output format: {}", format),
+    };
+
+    let assembly = match entry.assembly.get(&platform) {
+        Some(assembly) => Rc::clone(assembly),
+        None => {
+            let mut assembly = Vec::new();
+            cache
+                .emit(&build, platform.arch(), &mut assembly)
+                .context("Failed to emit assembly")?;
+
+            let assembly = Rc::new(assembly);
+            entry.assembly.insert(platform, Rc::clone(&assembly));
+            assembly
+        }
+    };
+
+    let mut options = LinkOptions::empty();
+    if strip {
+        options |= LinkOptions::STRIP;
+    }
+
+    crate::link_executable(
+        platform,
+        output,
+        format,
+        ScriptOptions::default(),
+        (None, None),
+        options,
+        &assembly,
+    )?;
+    Ok(Outcome::Done)
+}
+
+fn default_target() -> String {
+    String::from("native")
+}
+
+fn default_format() -> String {
+    String::from("elf")
+}
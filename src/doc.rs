@@ -0,0 +1,329 @@
+//! Documentación de programas.
+//!
+//! [`document()`] resume las constantes, enumeraciones y procedimientos
+//! de un programa a partir de su AST. La documentación de cada
+//! declaración es el bloque de comentarios que la precede inmediatamente,
+//! sin líneas en blanco ni código de por medio:
+//!
+//! ```text
+//! ## Enciende una columna completa.
+//! ##
+//! ## `column` se cuenta desde cero.
+//! procedure fill(column: int) {
+//! ```
+//!
+//! Los comentarios vacíos separan párrafos. En Markdown, el texto de los
+//! comentarios se copia tal cual, por lo cual puede contener a su vez
+//! Markdown. En HTML se escapa.
+
+use std::{collections::HashSet, fmt::Write, io::BufRead, str::FromStr};
+
+use crate::{
+    error::Diagnostics,
+    format,
+    lex::{Lexer, Token},
+    parse,
+    source::{self, Located},
+};
+
+/// Formato de salida.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+impl FromStr for DocFormat {
+    type Err = ();
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "markdown" => Ok(DocFormat::Markdown),
+            "html" => Ok(DocFormat::Html),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Una constante, enumeración o procedimiento documentado.
+struct Entry {
+    name: String,
+
+    /// Declaración en formato canónico, ver [`crate::format`].
+    declaration: String,
+
+    /// Líneas del comentario que precede a la declaración.
+    doc: Vec<String>,
+
+    /// Nombre, tipo y si se toma por referencia, para cada parámetro.
+    parameters: Vec<(String, String, bool)>,
+}
+
+/// Genera la documentación de un programa.
+///
+/// `name` identifica al código fuente tanto en diagnósticos como en el
+/// título del documento. Las declaraciones se listan en el orden en que
+/// aparecen. Solo se requiere que el programa sea sintácticamente
+/// correcto.
+pub fn document<R: BufRead>(
+    reader: &mut R,
+    name: &str,
+    doc_format: DocFormat,
+) -> Result<String, Diagnostics> {
+    let (start, stream) = source::consume(reader, name);
+
+    let tokens = match Lexer::new(start.clone(), stream)
+        .with_comments()
+        .try_exhaustive()
+    {
+        Ok(tokens) => tokens,
+        Err(errors) => return Err(Diagnostics::from(errors).kind("Lexical error")),
+    };
+
+    let ast = match parse::parse(tokens.iter(), start) {
+        Ok(ast) => ast,
+        Err(error) => return Err(Diagnostics::from(error).kind("Syntax error")),
+    };
+
+    let comments = Comments::new(&tokens);
+
+    let constants: Vec<_> = ast
+        .constants()
+        .iter()
+        .map(|constant| {
+            let name = constant.name();
+            Entry {
+                name: name.as_ref().to_string(),
+                declaration: format!(
+                    "const {} = {};",
+                    name.as_ref(),
+                    format::expr(constant.value())
+                ),
+                doc: comments.leading(name.location().start().line()),
+                parameters: Vec::new(),
+            }
+        })
+        .collect();
+
+    let enums: Vec<_> = ast
+        .enums()
+        .iter()
+        .map(|enumeration| {
+            let variants: Vec<_> = enumeration
+                .variants()
+                .iter()
+                .map(|variant| match variant.value() {
+                    Some(value) => format!("{} = {}", variant.name().as_ref(), format::expr(value)),
+                    None => variant.name().as_ref().to_string(),
+                })
+                .collect();
+
+            let name = enumeration.name();
+            Entry {
+                name: name.as_ref().to_string(),
+                declaration: format!("enum {} {{ {} }}", name.as_ref(), variants.join(", ")),
+                doc: comments.leading(name.location().start().line()),
+                parameters: Vec::new(),
+            }
+        })
+        .collect();
+
+    let procedures: Vec<_> = ast
+        .iter()
+        .map(|procedure| {
+            let parameters = procedure
+                .parameters()
+                .iter()
+                .map(|parameter| {
+                    let name = parameter.name().as_ref().to_string();
+                    (name, format::typ(parameter.of()), parameter.is_ref())
+                })
+                .collect();
+
+            let name = procedure.name();
+            Entry {
+                name: name.as_ref().to_string(),
+                declaration: format::signature(procedure),
+                doc: comments.leading(name.location().start().line()),
+                parameters,
+            }
+        })
+        .collect();
+
+    let output = match doc_format {
+        DocFormat::Markdown => markdown(name, &constants, &enums, &procedures),
+        DocFormat::Html => html(name, &constants, &enums, &procedures),
+    };
+
+    Ok(output)
+}
+
+/// Comentarios que ocupan líneas completas.
+struct Comments {
+    /// Texto de cada comentario por línea, sin `##` ni un espacio inicial.
+    lines: Vec<(u32, String)>,
+}
+
+impl Comments {
+    fn new(tokens: &[Located<Token>]) -> Self {
+        let code: HashSet<_> = tokens
+            .iter()
+            .filter(|token| !matches!(token.as_ref(), Token::Comment(_)))
+            .map(|token| token.location().start().line())
+            .collect();
+
+        let lines = tokens
+            .iter()
+            .filter_map(|token| match token.as_ref() {
+                Token::Comment(text) => Some((token.location().start().line(), text)),
+                _ => None,
+            })
+            .filter(|(line, _)| !code.contains(line))
+            .map(|(line, text)| {
+                let text = text.strip_prefix(' ').unwrap_or(text.as_str());
+                (line, text.trim_end().to_string())
+            })
+            .collect();
+
+        Comments { lines }
+    }
+
+    /// Bloque de comentarios que termina justo antes de `line`.
+    fn leading(&self, line: u32) -> Vec<String> {
+        let mut doc = Vec::new();
+        let mut expected = line;
+
+        for (line, text) in self.lines.iter().rev() {
+            if *line >= expected {
+                continue;
+            } else if *line + 1 != expected {
+                break;
+            }
+
+            doc.push(text.clone());
+            expected = *line;
+        }
+
+        doc.reverse();
+        doc
+    }
+}
+
+/// Agrupa líneas de documentación en párrafos.
+fn paragraphs(doc: &[String]) -> impl Iterator<Item = &[String]> {
+    doc.split(|line| line.is_empty())
+        .filter(|paragraph| !paragraph.is_empty())
+}
+
+fn markdown(name: &str, constants: &[Entry], enums: &[Entry], procedures: &[Entry]) -> String {
+    let mut output = format!("# {}\n", name);
+
+    let sections = [
+        ("Constants", constants),
+        ("Enumerations", enums),
+        ("Procedures", procedures),
+    ];
+
+    for (title, entries) in sections.iter().filter(|(_, entries)| !entries.is_empty()) {
+        write!(output, "\n## {}\n", title).unwrap();
+
+        for entry in *entries {
+            write!(output, "\n### `{}`\n\n", entry.name).unwrap();
+            write!(output, "```\n{}\n```\n", entry.declaration).unwrap();
+
+            for paragraph in paragraphs(&entry.doc) {
+                write!(output, "\n{}\n", paragraph.join("\n")).unwrap();
+            }
+
+            if !entry.parameters.is_empty() {
+                output.push_str("\n| Parameter | Type | By reference |\n|---|---|---|\n");
+                for (name, of, by_ref) in &entry.parameters {
+                    let by_ref = if *by_ref { "yes" } else { "no" };
+                    writeln!(output, "| `{}` | `{}` | {} |", name, of, by_ref).unwrap();
+                }
+            }
+        }
+    }
+
+    output
+}
+
+fn html(name: &str, constants: &[Entry], enums: &[Entry], procedures: &[Entry]) -> String {
+    let name = escape(name);
+    let mut output = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{0}</title>\n</head>\n<body>\n<h1>{0}</h1>\n",
+        name
+    );
+
+    let sections = [
+        ("Constants", "const", constants),
+        ("Enumerations", "enum", enums),
+        ("Procedures", "procedure", procedures),
+    ];
+
+    for (title, prefix, entries) in sections
+        .iter()
+        .filter(|(_, _, entries)| !entries.is_empty())
+    {
+        writeln!(output, "<h2>{}</h2>", title).unwrap();
+
+        for entry in *entries {
+            let name = escape(&entry.name);
+            writeln!(
+                output,
+                "<h3 id=\"{}-{}\"><code>{}</code></h3>",
+                prefix, name, name
+            )
+            .unwrap();
+            writeln!(
+                output,
+                "<pre><code>{}</code></pre>",
+                escape(&entry.declaration)
+            )
+            .unwrap();
+
+            for paragraph in paragraphs(&entry.doc) {
+                writeln!(output, "<p>{}</p>", escape(&paragraph.join("\n"))).unwrap();
+            }
+
+            if !entry.parameters.is_empty() {
+                output.push_str(
+                    "<table>\n<tr><th>Parameter</th><th>Type</th><th>By reference</th></tr>\n",
+                );
+
+                for (name, of, by_ref) in &entry.parameters {
+                    let by_ref = if *by_ref { "yes" } else { "no" };
+                    writeln!(
+                        output,
+                        "<tr><td><code>{}</code></td><td><code>{}</code></td><td>{}</td></tr>",
+                        escape(name),
+                        escape(of),
+                        by_ref
+                    )
+                    .unwrap();
+                }
+
+                output.push_str("</table>\n");
+            }
+        }
+    }
+
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
@@ -1,9 +1,27 @@
-use crate::source::{Located, Location};
+//! Presentación de diagnósticos.
+//!
+//! Un [`Diagnostics`] agrupa errores o advertencias con ubicación
+//! conocida. Cada uno se presenta junto a las líneas de código fuente
+//! que señala, subrayando el rango exacto aún si abarca varias líneas,
+//! y opcionalmente seguido de una sugerencia `help:`. Los colores ANSI
+//! se habilitan con [`Diagnostics::color()`], ya que solo quien imprime
+//! sabe si la salida es una terminal.
+//!
+//! Para integración con editores, [`Diagnostics::json()`] cambia la
+//! presentación a un objeto JSON por diagnóstico y por línea, con la
+//! forma de [`Diagnostic`].
+
+use crate::source::{Located, Location, Position};
+use serde::Serialize;
 use std::{
     error::Error,
     fmt::{self, Display},
 };
 
+/// Máximo de líneas de un rango que se muestran completas. De exceder
+/// este límite se omiten las líneas intermedias.
+const MAX_EXCERPT_LINES: usize = 4;
+
 mod sealed {
     pub trait Sealed {}
 }
@@ -11,17 +29,113 @@ mod sealed {
 pub trait LocatedError: sealed::Sealed {
     fn source(&self) -> &dyn Error;
     fn location(&self) -> &Location;
+    fn help(&self) -> Option<String>;
+}
+
+/// Errores que pueden sugerir una corrección.
+pub trait Help {
+    /// Sugerencia que acompaña al error, si alguna aplica.
+    fn help(&self) -> Option<String> {
+        None
+    }
 }
 
 pub struct Diagnostics {
     kind: &'static str,
     errors: Vec<Box<dyn 'static + LocatedError>>,
+
+    /// Falso si se trata de advertencias, las cuales no detienen la compilación.
+    fatal: bool,
+    color: bool,
+    json: bool,
+}
+
+/// Forma estructurada de un único error o advertencia.
+#[derive(Clone, Serialize)]
+pub struct Diagnostic {
+    /// Fase que reporta, como `"Syntax error"`.
+    pub kind: &'static str,
+
+    /// `"error"` o `"warning"`.
+    pub severity: &'static str,
+
+    pub message: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+
+    pub file: String,
+    pub start: LineColumn,
+
+    /// Posición inmediatamente posterior al rango señalado.
+    pub end: LineColumn,
+}
+
+/// Posición en un archivo, iniciando desde 1.
+#[derive(Copy, Clone, Serialize)]
+pub struct LineColumn {
+    pub line: u32,
+    pub column: u32,
+}
+
+impl From<Position> for LineColumn {
+    fn from(position: Position) -> Self {
+        LineColumn {
+            line: position.line(),
+            column: position.column(),
+        }
+    }
 }
 
 impl Diagnostics {
     pub fn kind(self, kind: &'static str) -> Self {
         Diagnostics { kind, ..self }
     }
+
+    pub fn fatal(self, fatal: bool) -> Self {
+        Diagnostics { fatal, ..self }
+    }
+
+    /// Habilita o deshabilita colores ANSI.
+    pub fn color(self, color: bool) -> Self {
+        Diagnostics { color, ..self }
+    }
+
+    /// Presenta cada diagnóstico como un objeto JSON en su propia línea,
+    /// sin fragmentos de código fuente ni resumen final.
+    pub fn json(self, json: bool) -> Self {
+        Diagnostics { json, ..self }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Itera sobre los errores o advertencias, en el orden en que se presentan.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn LocatedError> {
+        self.errors
+            .iter()
+            .map(|error| &**error as &dyn LocatedError)
+    }
+
+    /// Forma estructurada de cada error o advertencia.
+    pub fn structured(&self) -> impl Iterator<Item = Diagnostic> + '_ {
+        let severity = if self.fatal { "error" } else { "warning" };
+
+        self.iter().map(move |error| {
+            let location = error.location();
+
+            Diagnostic {
+                kind: self.kind,
+                severity,
+                message: error.source().to_string(),
+                help: error.help(),
+                file: location.source().name().to_owned(),
+                start: location.start().into(),
+                end: location.end().into(),
+            }
+        })
+    }
 }
 
 impl Default for Diagnostics {
@@ -29,6 +143,9 @@ impl Default for Diagnostics {
         Diagnostics {
             kind: "error",
             errors: Default::default(),
+            fatal: true,
+            color: false,
+            json: false,
         }
     }
 }
@@ -61,62 +178,92 @@ impl<E: 'static + LocatedError> From<Vec<E>> for Diagnostics {
 
 impl Display for Diagnostics {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Diagnostics { kind, errors } = self;
+        let Diagnostics {
+            kind,
+            errors,
+            fatal,
+            color,
+            json,
+        } = self;
+
+        if *json {
+            for diagnostic in self.structured() {
+                let line = serde_json::to_string(&diagnostic).map_err(|_| fmt::Error)?;
+                writeln!(fmt, "{}", line)?;
+            }
+
+            return Ok(());
+        }
 
         if errors.is_empty() {
             return writeln!(fmt, "No errors were reported");
         }
 
+        let style = if *color { Style::ANSI } else { Style::PLAIN };
+        let severity = if *fatal { style.error } else { style.warning };
+
         for error in errors {
-            writeln!(fmt, "{}: {}", kind, error.source())?;
+            writeln!(
+                fmt,
+                "{}{}:{} {}{}{}",
+                severity,
+                kind,
+                style.reset,
+                style.bold,
+                error.source(),
+                style.reset
+            )?;
 
             let location = error.location();
-            writeln!(fmt, " --> {}", location)?;
-
-            //FIXME: Demasiado indecente
             let digits = location.end().line().to_string().chars().count();
-            writeln!(fmt, "{:digits$} |", "", digits = digits)?;
-
-            for line_number in location.start().line()..=location.end().line() {
-                location.source().with_line(line_number, |line| {
-                    writeln!(fmt, "{:>digits$} | {}", line_number, line, digits = digits)
-                })?
-            }
-
-            let (from, to) = (location.start().column(), location.end().column() - 1);
-            let min = from.min(to);
-            let max = from.max(to);
-
-            let skip = (min - 1) as usize;
-            let highlight = (max - min + 1) as usize;
+            let gutter = Gutter { digits, style };
 
             writeln!(
                 fmt,
-                "{:digits$} | {:skip$}{:^<highlight$}",
-                "",
+                "{}{:digits$}-->{} {}",
+                style.gutter,
                 "",
-                "",
-                digits = digits,
-                skip = skip,
-                highlight = highlight
+                style.reset,
+                location,
+                digits = digits
             )?;
 
+            gutter.write(fmt, None)?;
+            writeln!(fmt)?;
+
+            excerpt(fmt, location, &gutter, severity)?;
+
+            if let Some(help) = error.help() {
+                writeln!(
+                    fmt,
+                    "{}{:digits$} ={} {}help{}: {}",
+                    style.gutter,
+                    "",
+                    style.reset,
+                    style.help,
+                    style.reset,
+                    help,
+                    digits = digits
+                )?;
+            }
+
             writeln!(fmt)?;
         }
 
-        let error_or_errors = if errors.len() == 1 { "error" } else { "errors" };
-        writeln!(
-            fmt,
-            "Build failed with {} {}",
-            errors.len(),
-            error_or_errors
-        )
+        let count = errors.len();
+        let plural = if count == 1 { "" } else { "s" };
+
+        if *fatal {
+            writeln!(fmt, "Build failed with {} error{}", count, plural)
+        } else {
+            writeln!(fmt, "Emitted {} warning{}", count, plural)
+        }
     }
 }
 
-impl<E: Error> sealed::Sealed for Located<E> {}
+impl<E: Error + Help> sealed::Sealed for Located<E> {}
 
-impl<E: Error> LocatedError for Located<E> {
+impl<E: Error + Help> LocatedError for Located<E> {
     fn source(&self) -> &dyn Error {
         self.as_ref()
     }
@@ -124,4 +271,126 @@ impl<E: Error> LocatedError for Located<E> {
     fn location(&self) -> &Location {
         Located::location(self)
     }
+
+    fn help(&self) -> Option<String> {
+        Help::help(self.as_ref())
+    }
+}
+
+/// Secuencias de escape para cada elemento de un diagnóstico.
+#[derive(Copy, Clone)]
+struct Style {
+    error: &'static str,
+    warning: &'static str,
+    help: &'static str,
+    gutter: &'static str,
+    bold: &'static str,
+    reset: &'static str,
+}
+
+impl Style {
+    const PLAIN: Style = Style {
+        error: "",
+        warning: "",
+        help: "",
+        gutter: "",
+        bold: "",
+        reset: "",
+    };
+
+    const ANSI: Style = Style {
+        error: "\x1b[1;31m",
+        warning: "\x1b[1;33m",
+        help: "\x1b[1;36m",
+        gutter: "\x1b[1;34m",
+        bold: "\x1b[1m",
+        reset: "\x1b[0m",
+    };
+}
+
+/// Margen izquierdo de un fragmento.
+struct Gutter {
+    digits: usize,
+    style: Style,
+}
+
+impl Gutter {
+    /// Escribe el margen, con número de línea si se indica uno.
+    fn write(&self, fmt: &mut fmt::Formatter<'_>, line: Option<u32>) -> fmt::Result {
+        let Gutter { digits, style } = self;
+        let line = line.map(|line| line.to_string()).unwrap_or_default();
+
+        write!(
+            fmt,
+            "{}{:>digits$} |{}",
+            style.gutter,
+            line,
+            style.reset,
+            digits = digits
+        )
+    }
+}
+
+/// Imprime las líneas que abarca una ubicación, subrayando bajo cada una
+/// la porción que le corresponde.
+fn excerpt(
+    fmt: &mut fmt::Formatter<'_>,
+    location: &Location,
+    gutter: &Gutter,
+    highlight: &str,
+) -> fmt::Result {
+    let (start, end) = (location.start(), location.end());
+    let lines = start.line()..=end.line().max(start.line());
+
+    let count = lines.clone().count();
+    let elided = count > MAX_EXCERPT_LINES;
+
+    for (index, number) in lines.enumerate() {
+        // Se conservan las primeras líneas y la última
+        if elided && index >= MAX_EXCERPT_LINES - 1 && index + 1 < count {
+            if index == MAX_EXCERPT_LINES - 1 {
+                writeln!(fmt, "{}...", gutter.style.gutter)?;
+            }
+
+            continue;
+        }
+
+        location.source().with_line(number, |text| {
+            let text = text.trim_end();
+            let length = text.chars().count() as u32;
+
+            // Columnas de inicio (inclusiva) y fin (exclusiva) en esta línea
+            let from = if number == start.line() {
+                start.column()
+            } else {
+                text.chars().take_while(|c| c.is_whitespace()).count() as u32 + 1
+            };
+
+            let to = if number == end.line() {
+                end.column()
+            } else {
+                length + 1
+            };
+
+            let carets = to.saturating_sub(from).max(1) as usize;
+
+            let indent = " ".repeat(from.saturating_sub(1) as usize);
+
+            gutter.write(fmt, Some(number))?;
+            writeln!(fmt, " {}", text)?;
+
+            gutter.write(fmt, None)?;
+            writeln!(
+                fmt,
+                " {}{}{:^<carets$}{}",
+                indent,
+                highlight,
+                "",
+                gutter.style.reset,
+                carets = carets
+            )
+        })?;
+    }
+
+    Ok(())
 }
@@ -0,0 +1,644 @@
+//! Flasheo de ejecutables a un ESP8266.
+//!
+//! El ELF enlazado se convierte al formato de imagen que espera el
+//! bootloader de la ROM y se transmite por el puerto serial, de forma
+//! que no sea necesario instalar `esptool` ni `espflash`.
+//!
+//! # Imagen
+//! Los segmentos que residen en IRAM y DRAM forman una imagen v1 que se
+//! escribe al inicio de la flash: un encabezado con el número de
+//! segmentos, modo y tamaño de flash y punto de entrada, seguido por
+//! cada segmento (dirección, tamaño y contenido) y una suma de
+//! verificación. El código que se ejecuta directamente desde flash
+//! (`irom_seg`) se escribe sin encabezado en el desplazamiento que le
+//! corresponde según su dirección.
+//!
+//! # Protocolo
+//! La ROM recibe comandos en paquetes SLIP. Primero se sincroniza con
+//! `SYNC`, cuyas repeticiones de `0x55` le permiten detectar la
+//! velocidad del puerto, y luego cada región se escribe con
+//! `FLASH_BEGIN`, una serie de `FLASH_DATA` y finalmente `FLASH_END`.
+//! Para entrar al bootloader se utilizan las líneas DTR y RTS, tal como
+//! las conectan los adaptadores USB-serial de las tarjetas NodeMCU.
+//!
+//! Para identificar una tarjeta se leen sus registros con `READ_REG`: el
+//! ID del chip proviene de los eFuses y el tamaño de la flash del JEDEC
+//! ID que responde la flash al comando `RDID` por SPI.
+//!
+//! El acceso al puerto serial solo está implementado para Linux. En
+//! otras plataformas es posible construir imágenes, pero no flashearlas.
+
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::link;
+use thiserror::Error;
+
+/// Dirección en la que se mapea el inicio de la flash.
+const IROM_BASE: u64 = 0x4020_0000;
+
+/// Tamaño del espacio de direcciones mapeado a flash.
+const IROM_LENGTH: u64 = 0x10_0000;
+
+/// Modo DIO, soportado por prácticamente todos los módulos.
+const FLASH_MODE: u8 = 0x02;
+
+/// 4 MiB de flash a 40 MHz.
+const FLASH_SIZE_FREQ: u8 = 0x40;
+
+/// Tamaño de cada bloque de `FLASH_DATA`.
+const BLOCK_SIZE: usize = 0x400;
+
+/// Tamaño de un sector borrable.
+const SECTOR_SIZE: usize = 0x1000;
+
+/// Valor inicial de las sumas de verificación de la ROM.
+const CHECKSUM_SEED: u8 = 0xef;
+
+/// Intentos de sincronización antes de desistir.
+const SYNC_ATTEMPTS: u32 = 10;
+
+/// Tiempo de espera de respuestas ordinarias.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Tiempo de espera por cada MiB a borrar en `FLASH_BEGIN`.
+const ERASE_TIMEOUT_PER_MIB: Duration = Duration::from_secs(30);
+
+/// Prefijos en `/dev` de los adaptadores USB-serial usuales en Linux,
+/// la única plataforma con acceso a puertos seriales.
+const PORT_PREFIXES: &[&str] = &["ttyUSB", "ttyACM"];
+
+/// Velocidades que se prueban al buscar una tarjeta, de la usual a la
+/// menos frecuente. Todas son soportadas por [`Loader::connect()`].
+pub const BAUD_RATES: &[u32] = &[115200, 230400, 460800, 921600, 57600, 9600];
+
+/// eFuses que contienen el ID del chip, ver [`Loader::chip_id()`].
+const EFUSE_MAC0: u32 = 0x3ff0_0050;
+const EFUSE_MAC1: u32 = 0x3ff0_0054;
+
+/// Registros del controlador SPI de la flash.
+const SPI_CMD: u32 = 0x6000_0200;
+const SPI_USR: u32 = 0x6000_021c;
+const SPI_USR1: u32 = 0x6000_0220;
+const SPI_USR2: u32 = 0x6000_0224;
+const SPI_W0: u32 = 0x6000_0240;
+
+/// Comando SPI que lee el JEDEC ID de la flash.
+const SPIFLASH_RDID: u32 = 0x9f;
+
+/// Un error durante el flasheo.
+#[non_exhaustive]
+#[derive(Error, Debug)]
+pub enum FlashError {
+    /// Ocurrió un error de E/S con el puerto o el ejecutable.
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+
+    /// El ejecutable no contiene segmentos que cargar.
+    #[error("Executable has no loadable segments")]
+    Empty,
+
+    /// Un segmento no corresponde a memoria del ESP8266.
+    #[error("Segment at {0:#010x} is outside ESP8266 memory")]
+    BadSegment(u64),
+
+    /// La ROM nunca respondió a `SYNC`.
+    #[error("Failed to connect to the ESP8266 bootloader, is the board in flash mode?")]
+    NoSync,
+
+    /// Se agotó el tiempo de espera de una respuesta.
+    #[error("Timed out waiting for the bootloader")]
+    Timeout,
+
+    /// La ROM rechazó un comando.
+    #[error("Bootloader rejected command {command:#04x} with error {error:#04x}")]
+    Rejected { command: u8, error: u8 },
+}
+
+/// Contenido a escribir en la flash, por desplazamiento.
+pub struct Image {
+    regions: Vec<(u32, Vec<u8>)>,
+}
+
+impl Image {
+    /// Convierte un ejecutable ELF de ESP8266 en una imagen.
+    pub fn from_elf(elf: &[u8]) -> Result<Self, FlashError> {
+        let (entry, segments) = link::load_segments(elf)?;
+        if segments.is_empty() {
+            return Err(FlashError::Empty);
+        }
+
+        let mut ram = Vec::new();
+        let mut regions = Vec::new();
+
+        for (address, contents) in segments {
+            if address >= IROM_BASE && address + contents.len() as u64 <= IROM_BASE + IROM_LENGTH {
+                regions.push(((address - IROM_BASE) as u32, contents.to_vec()));
+            } else if address <= u32::MAX as u64 {
+                ram.push((address as u32, contents));
+            } else {
+                return Err(FlashError::BadSegment(address));
+            }
+        }
+
+        let mut header = vec![0xe9, ram.len() as u8, FLASH_MODE, FLASH_SIZE_FREQ];
+        header.extend_from_slice(&(entry as u32).to_le_bytes());
+
+        let mut checksum = CHECKSUM_SEED;
+        for (address, contents) in ram {
+            let padded = (contents.len() + 3) & !3;
+
+            header.extend_from_slice(&address.to_le_bytes());
+            header.extend_from_slice(&(padded as u32).to_le_bytes());
+            header.extend_from_slice(contents);
+            header.resize(header.len() + padded - contents.len(), 0);
+
+            checksum = contents.iter().fold(checksum, |sum, byte| sum ^ byte);
+        }
+
+        // La suma ocupa el último byte de un bloque de 16
+        header.resize(header.len() + 15 - header.len() % 16, 0);
+        header.push(checksum);
+
+        regions.insert(0, (0, header));
+        Ok(Image { regions })
+    }
+
+    /// Cantidad total de bytes a escribir.
+    pub fn size(&self) -> usize {
+        self.regions
+            .iter()
+            .map(|(_, contents)| contents.len())
+            .sum()
+    }
+
+    /// Desplazamiento y contenido de cada región.
+    pub fn regions(&self) -> impl Iterator<Item = (u32, &[u8])> {
+        self.regions
+            .iter()
+            .map(|(offset, contents)| (*offset, contents.as_slice()))
+    }
+}
+
+/// Puertos seriales en los que probablemente hay una tarjeta conectada.
+pub fn likely_ports() -> Vec<PathBuf> {
+    let entries = match fs::read_dir("/dev") {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut ports: Vec<_> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            PORT_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+        })
+        .map(|entry| entry.path())
+        .collect();
+
+    ports.sort();
+    ports
+}
+
+/// Conexión con el bootloader de la ROM.
+pub struct Loader {
+    port: File,
+}
+
+impl Loader {
+    const FLASH_BEGIN: u8 = 0x02;
+    const FLASH_DATA: u8 = 0x03;
+    const FLASH_END: u8 = 0x04;
+    const SYNC: u8 = 0x08;
+    const WRITE_REG: u8 = 0x09;
+    const READ_REG: u8 = 0x0a;
+
+    /// Abre el puerto, reinicia la tarjeta en modo de flasheo y se
+    /// sincroniza con la ROM.
+    pub fn connect(port: &Path, baud: u32) -> Result<Self, FlashError> {
+        let port = port::open(port, baud)?;
+
+        let mut loader = Loader { port };
+        for _ in 0..SYNC_ATTEMPTS {
+            loader.reset_into_bootloader()?;
+
+            match loader.command(Self::SYNC, &sync_payload(), 0, Duration::from_millis(100)) {
+                Ok(_) => {
+                    // La ROM responde varias veces a un mismo SYNC
+                    thread::sleep(Duration::from_millis(50));
+                    loader.drain()?;
+
+                    return Ok(loader);
+                }
+
+                Err(FlashError::Timeout) | Err(FlashError::Rejected { .. }) => continue,
+                Err(error) => return Err(error),
+            }
+        }
+
+        Err(FlashError::NoSync)
+    }
+
+    /// Escribe una imagen completa.
+    ///
+    /// `progress` recibe la cantidad de bytes escritos y el total.
+    pub fn write_image<F>(&mut self, image: &Image, mut progress: F) -> Result<(), FlashError>
+    where
+        F: FnMut(usize, usize),
+    {
+        let (mut written, total) = (0, image.size());
+
+        for (offset, contents) in image.regions() {
+            let blocks = (contents.len() + BLOCK_SIZE - 1) / BLOCK_SIZE;
+            let erase = erase_size(offset as usize, contents.len());
+
+            let mut begin = Vec::new();
+            for value in &[erase, blocks, BLOCK_SIZE, offset as usize] {
+                begin.extend_from_slice(&(*value as u32).to_le_bytes());
+            }
+
+            let mib = (erase as u32 + 0xfffff) / 0x100000;
+            let timeout = COMMAND_TIMEOUT + ERASE_TIMEOUT_PER_MIB * mib;
+            self.command(Self::FLASH_BEGIN, &begin, 0, timeout)?;
+
+            for (sequence, block) in contents.chunks(BLOCK_SIZE).enumerate() {
+                let mut data = block.to_vec();
+                data.resize(BLOCK_SIZE, 0xff);
+
+                let mut payload = Vec::with_capacity(16 + BLOCK_SIZE);
+                for value in &[BLOCK_SIZE, sequence, 0, 0] {
+                    payload.extend_from_slice(&(*value as u32).to_le_bytes());
+                }
+
+                payload.extend_from_slice(&data);
+
+                let checksum = data.iter().fold(CHECKSUM_SEED, |sum, byte| sum ^ byte);
+                self.command(Self::FLASH_DATA, &payload, checksum as u32, COMMAND_TIMEOUT)?;
+
+                written += block.len();
+                progress(written, total);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Concluye el flasheo y reinicia la tarjeta para ejecutar el programa.
+    pub fn finish(mut self) -> Result<(), FlashError> {
+        // Un 0 solicita reiniciar, pero algunas versiones de la ROM lo ignoran
+        self.command(Self::FLASH_END, &0u32.to_le_bytes(), 0, COMMAND_TIMEOUT)?;
+        self.reset()
+    }
+
+    /// Reinicia la tarjeta sin escribir, de forma que ejecute el
+    /// programa que ya contiene.
+    pub fn reset(mut self) -> Result<(), FlashError> {
+        self.set_lines(false, true)?;
+        thread::sleep(Duration::from_millis(100));
+        self.set_lines(false, false)?;
+
+        Ok(())
+    }
+
+    /// ID del chip, el mismo que reporta `esptool chip_id`.
+    pub fn chip_id(&mut self) -> Result<u32, FlashError> {
+        let mac0 = self.read_reg(EFUSE_MAC0)?;
+        let mac1 = self.read_reg(EFUSE_MAC1)?;
+
+        Ok(mac0 >> 24 | (mac1 & 0x00ff_ffff) << 8)
+    }
+
+    /// Tamaño de la flash en bytes según su JEDEC ID, o `None` si la
+    /// flash reporta una capacidad desconocida.
+    pub fn flash_size(&mut self) -> Result<Option<u64>, FlashError> {
+        const CMD_USR: u32 = 1 << 18;
+        const USR_COMMAND: u32 = 1 << 31;
+        const USR_MISO: u32 = 1 << 28;
+        const MISO_BITLEN_SHIFT: u32 = 8;
+        const COMMAND_BITLEN_SHIFT: u32 = 28;
+
+        // Una escritura vacía hace que la ROM inicialice la interfaz SPI
+        let mut begin = Vec::new();
+        for value in &[0, 0, BLOCK_SIZE as u32, 0] {
+            begin.extend_from_slice(&value.to_le_bytes());
+        }
+
+        self.command(Self::FLASH_BEGIN, &begin, 0, COMMAND_TIMEOUT)?;
+
+        let usr = self.read_reg(SPI_USR)?;
+        let usr2 = self.read_reg(SPI_USR2)?;
+
+        // Comando de 8 bits seguido de una lectura de 24 bits
+        self.write_reg(SPI_USR1, 23 << MISO_BITLEN_SHIFT)?;
+        self.write_reg(SPI_USR, USR_COMMAND | USR_MISO)?;
+        self.write_reg(SPI_USR2, 7 << COMMAND_BITLEN_SHIFT | SPIFLASH_RDID)?;
+        self.write_reg(SPI_W0, 0)?;
+        self.write_reg(SPI_CMD, CMD_USR)?;
+
+        let deadline = Instant::now() + COMMAND_TIMEOUT;
+        while self.read_reg(SPI_CMD)? & CMD_USR != 0 {
+            if Instant::now() >= deadline {
+                return Err(FlashError::Timeout);
+            }
+        }
+
+        let jedec_id = self.read_reg(SPI_W0)?;
+
+        self.write_reg(SPI_USR, usr)?;
+        self.write_reg(SPI_USR2, usr2)?;
+
+        // El tercer byte es el logaritmo base 2 de la capacidad
+        match (jedec_id >> 16) & 0xff {
+            log2 @ 0x10..=0x1a => Ok(Some(1 << log2)),
+            _ => Ok(None),
+        }
+    }
+
+    fn read_reg(&mut self, address: u32) -> Result<u32, FlashError> {
+        self.command(Self::READ_REG, &address.to_le_bytes(), 0, COMMAND_TIMEOUT)
+    }
+
+    fn write_reg(&mut self, address: u32, value: u32) -> Result<(), FlashError> {
+        // Dirección, valor, máscara y retardo en microsegundos
+        let mut data = Vec::new();
+        for field in &[address, value, u32::MAX, 0] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+
+        self.command(Self::WRITE_REG, &data, 0, COMMAND_TIMEOUT)?;
+        Ok(())
+    }
+
+    /// Reinicia con GPIO0 en bajo, lo cual inicia el bootloader.
+    ///
+    /// DTR controla GPIO0 y RTS controla EN, ambos invertidos.
+    fn reset_into_bootloader(&mut self) -> io::Result<()> {
+        self.set_lines(false, true)?;
+        thread::sleep(Duration::from_millis(100));
+        self.set_lines(true, false)?;
+        thread::sleep(Duration::from_millis(50));
+        self.set_lines(false, false)?;
+
+        self.drain()
+    }
+
+    fn set_lines(&mut self, dtr: bool, rts: bool) -> io::Result<()> {
+        port::set_lines(&self.port, dtr, rts)
+    }
+
+    /// Descarta lo que se haya recibido hasta el momento.
+    fn drain(&mut self) -> io::Result<()> {
+        port::drain(&self.port)
+    }
+
+    /// Envía un comando y espera su respuesta.
+    ///
+    /// Se retorna el campo de valor de la respuesta.
+    fn command(
+        &mut self,
+        command: u8,
+        data: &[u8],
+        checksum: u32,
+        timeout: Duration,
+    ) -> Result<u32, FlashError> {
+        let mut packet = vec![0x00, command];
+        packet.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        packet.extend_from_slice(&checksum.to_le_bytes());
+        packet.extend_from_slice(data);
+
+        self.port.write_all(&slip_encode(&packet))?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let response = self.read_frame(deadline)?;
+
+            // Respuestas rezagadas de comandos anteriores se ignoran
+            if response.len() < 10 || response[0] != 0x01 || response[1] != command {
+                continue;
+            }
+
+            let value = u32::from_le_bytes([response[4], response[5], response[6], response[7]]);
+            let size = u16::from_le_bytes([response[2], response[3]]) as usize;
+
+            // En ESP8266 los últimos dos bytes son estado y código de error
+            let status = &response[8..8 + size.min(response.len() - 8)];
+            match status {
+                [.., 0, _] => return Ok(value),
+                [.., _, error] => {
+                    return Err(FlashError::Rejected {
+                        command,
+                        error: *error,
+                    })
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    /// Lee un paquete SLIP completo.
+    fn read_frame(&mut self, deadline: Instant) -> Result<Vec<u8>, FlashError> {
+        let mut frame = Vec::new();
+        let (mut started, mut escaped) = (false, false);
+
+        loop {
+            if Instant::now() >= deadline {
+                return Err(FlashError::Timeout);
+            }
+
+            let mut byte = [0];
+            match self.port.read(&mut byte) {
+                Ok(0) => continue,
+                Ok(_) => (),
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error.into()),
+            }
+
+            match (byte[0], started, escaped) {
+                (0xc0, false, _) => started = true,
+                (0xc0, true, _) if frame.is_empty() => (),
+                (0xc0, true, _) => return Ok(frame),
+                (_, false, _) => (),
+
+                (0xdb, true, false) => escaped = true,
+                (0xdc, true, true) => {
+                    frame.push(0xc0);
+                    escaped = false;
+                }
+
+                (0xdd, true, true) => {
+                    frame.push(0xdb);
+                    escaped = false;
+                }
+
+                (other, true, _) => {
+                    frame.push(other);
+                    escaped = false;
+                }
+            }
+        }
+    }
+}
+
+/// Contenido del comando `SYNC`.
+fn sync_payload() -> Vec<u8> {
+    let mut payload = vec![0x07, 0x07, 0x12, 0x20];
+    payload.resize(36, 0x55);
+    payload
+}
+
+/// Codifica un paquete en SLIP.
+fn slip_encode(packet: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![0xc0];
+    for byte in packet {
+        match byte {
+            0xc0 => encoded.extend_from_slice(&[0xdb, 0xdc]),
+            0xdb => encoded.extend_from_slice(&[0xdb, 0xdd]),
+            _ => encoded.push(*byte),
+        }
+    }
+
+    encoded.push(0xc0);
+    encoded
+}
+
+/// Tamaño a solicitar en `FLASH_BEGIN` para borrar una región.
+///
+/// La ROM del ESP8266 borra de más cuando la región cruza bloques de
+/// 16 sectores, por lo cual se le solicita un tamaño corregido, al
+/// igual que hace `esptool`.
+fn erase_size(offset: usize, size: usize) -> usize {
+    const SECTORS_PER_BLOCK: usize = 16;
+
+    let sectors = (size + SECTOR_SIZE - 1) / SECTOR_SIZE;
+    let start = offset / SECTOR_SIZE;
+
+    let head = (SECTORS_PER_BLOCK - start % SECTORS_PER_BLOCK).min(sectors);
+    if sectors < 2 * head {
+        (sectors + 1) / 2 * SECTOR_SIZE
+    } else {
+        (sectors - head) * SECTOR_SIZE
+    }
+}
+
+/// Acceso a puertos seriales mediante termios.
+#[cfg(target_os = "linux")]
+mod port {
+    use std::{
+        fs::{File, OpenOptions},
+        io,
+        mem::MaybeUninit,
+        os::unix::{fs::OpenOptionsExt, io::AsRawFd},
+        path::Path,
+    };
+
+    /// Abre un puerto serial y lo configura a `baud` baudios.
+    pub fn open(path: &Path, baud: u32) -> io::Result<File> {
+        let port = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(path)?;
+
+        configure(&port, baud)?;
+        Ok(port)
+    }
+
+    /// Activa o desactiva las líneas DTR y RTS.
+    pub fn set_lines(port: &File, dtr: bool, rts: bool) -> io::Result<()> {
+        let fd = port.as_raw_fd();
+
+        for (line, active) in &[(libc::TIOCM_DTR, dtr), (libc::TIOCM_RTS, rts)] {
+            let request = if *active {
+                libc::TIOCMBIS
+            } else {
+                libc::TIOCMBIC
+            };
+
+            if unsafe { libc::ioctl(fd, request, line as *const libc::c_int) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Descarta lo que se haya recibido hasta el momento.
+    pub fn drain(port: &File) -> io::Result<()> {
+        if unsafe { libc::tcflush(port.as_raw_fd(), libc::TCIFLUSH) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    /// Configura el puerto en modo crudo.
+    ///
+    /// Cada lectura espera a lo sumo una décima de segundo, de forma que
+    /// los tiempos de espera se verifiquen con frecuencia.
+    fn configure(port: &File, baud: u32) -> io::Result<()> {
+        let speed = match baud {
+            9600 => libc::B9600,
+            57600 => libc::B57600,
+            115200 => libc::B115200,
+            230400 => libc::B230400,
+            460800 => libc::B460800,
+            921600 => libc::B921600,
+            _ => {
+                let message = format!("unsupported baud rate: {}", baud);
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+            }
+        };
+
+        let fd = port.as_raw_fd();
+        let mut termios = MaybeUninit::<libc::termios>::uninit();
+
+        unsafe {
+            if libc::tcgetattr(fd, termios.as_mut_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut termios = termios.assume_init();
+            libc::cfmakeraw(&mut termios);
+            libc::cfsetspeed(&mut termios, speed);
+
+            termios.c_cflag |= libc::CLOCAL | libc::CREAD;
+            termios.c_cc[libc::VMIN] = 0;
+            termios.c_cc[libc::VTIME] = 1;
+
+            if libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Sin termios no hay acceso a puertos seriales.
+#[cfg(not(target_os = "linux"))]
+mod port {
+    use std::{fs::File, io, path::Path};
+
+    fn unsupported() -> io::Error {
+        let message = "serial ports are unsupported on this platform";
+        io::Error::new(io::ErrorKind::Other, message)
+    }
+
+    pub fn open(_: &Path, _: u32) -> io::Result<File> {
+        Err(unsupported())
+    }
+
+    pub fn set_lines(_: &File, _: bool, _: bool) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn drain(_: &File) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
@@ -0,0 +1,760 @@
+//! Formato canónico de código fuente.
+//!
+//! [`format()`] reconstruye un programa a partir de su AST, de forma
+//! que el resultado depende únicamente de la estructura del programa y
+//! no de cómo se escribió. Las reglas son pocas:
+//!
+//! - Cada bloque se indenta con cuatro espacios, cada enunciado ocupa
+//!   su propia línea y las llaves abren en la línea de su encabezado.
+//! - Los operadores binarios y las comas se separan con espacios. Se
+//!   preservan exactamente los paréntesis que agrupan una operación
+//!   binaria, ya que estos determinan la estructura del árbol.
+//! - Las palabras clave se escriben como en [`Keyword`], sin importar
+//!   mayúsculas y minúsculas en el original.
+//! - Se conserva a lo sumo una línea en blanco entre enunciados, y
+//!   siempre una entre procedimientos y enumeraciones. Cada variante de
+//!   una enumeración ocupa su propia línea.
+//! - Los comentarios se conservan, ya sea en su propia línea antes del
+//!   siguiente enunciado o al final de la línea donde ocurrían.
+//!
+//! El AST no registra dónde terminan los enunciados ni dónde están las
+//! llaves, por lo cual esas posiciones se recuperan del flujo de tokens.
+//!
+//! [`Keyword`]: crate::lex::Keyword
+
+use std::{fmt::Write, io::BufRead};
+
+use crate::{
+    error::Diagnostics,
+    lex::{Keyword, Lexer, Token},
+    parse::{
+        self, Ast, Constant, Enum, Expr, ExprLimits, Import, Index, ObjectKind, Procedure,
+        Statement, Target, TimeUnit, Type,
+    },
+    source::{self, Located, Position},
+};
+
+/// Indentación de un nivel de bloque.
+const INDENT: &str = "    ";
+
+/// Aplica formato canónico a un programa.
+///
+/// `name` identifica al código fuente en diagnósticos. Solo se requiere
+/// que el programa sea sintácticamente correcto.
+pub fn format<R: BufRead>(reader: &mut R, name: &str) -> Result<String, Diagnostics> {
+    let (start, stream) = source::consume(reader, name);
+
+    let tokens = match Lexer::new(start.clone(), stream)
+        .with_comments()
+        .try_exhaustive()
+    {
+        Ok(tokens) => tokens,
+        Err(errors) => return Err(Diagnostics::from(errors).kind("Lexical error")),
+    };
+
+    let ast = match parse::parse(tokens.iter(), start) {
+        Ok(ast) => ast,
+        Err(error) => return Err(Diagnostics::from(error).kind("Syntax error")),
+    };
+
+    let (comments, tokens): (Vec<_>, Vec<_>) = tokens
+        .into_iter()
+        .partition(|token| matches!(token.as_ref(), Token::Comment(_)));
+
+    let comments = comments
+        .into_iter()
+        .map(|comment| match comment.split() {
+            (location, Token::Comment(text)) => (location.start(), text),
+            _ => unreachable!(),
+        })
+        .collect();
+
+    let mut formatter = Formatter {
+        tokens: &tokens,
+        comments,
+        next_comment: 0,
+        output: String::new(),
+        indent: 0,
+        last_line: None,
+    };
+
+    formatter.program(&ast);
+    Ok(formatter.output)
+}
+
+/// Encabezado canónico de un procedimiento, sin la llave de apertura.
+pub(crate) fn signature(procedure: &Procedure) -> String {
+    Formatter::detached().signature(procedure)
+}
+
+/// Texto canónico de una expresión.
+pub(crate) fn expr(expr: &Expr) -> String {
+    Formatter::detached().expr(expr)
+}
+
+/// Texto canónico de un tipo.
+pub(crate) fn typ(typ: &Type) -> String {
+    Formatter::detached().typ(typ)
+}
+
+struct Formatter<'a> {
+    /// Tokens del programa, sin comentarios.
+    tokens: &'a [Located<Token>],
+
+    /// Comentarios en orden de aparición.
+    comments: Vec<(Position, String)>,
+
+    /// Primer comentario que aún no se escribe.
+    next_comment: usize,
+
+    output: String,
+    indent: usize,
+
+    /// Línea original de lo último que se escribió en el bloque actual.
+    last_line: Option<u32>,
+}
+
+/// Declaración global.
+enum Item<'a> {
+    Import(&'a Import),
+    Enum(&'a Enum),
+    Constant(&'a Constant),
+    Procedure(&'a Procedure),
+}
+
+impl Formatter<'_> {
+    /// Un formateador sin tokens ni comentarios, el cual solo puede
+    /// producir texto de nodos aislados.
+    fn detached() -> Self {
+        Formatter {
+            tokens: &[],
+            comments: Vec::new(),
+            next_comment: 0,
+            output: String::new(),
+            indent: 0,
+            last_line: None,
+        }
+    }
+
+    fn program(&mut self, ast: &Ast) {
+        let imports = ast.imports().iter().map(Item::Import);
+        let enums = ast.enums().iter().map(Item::Enum);
+        let constants = ast.constants().iter().map(Item::Constant);
+        let procedures = ast.iter().map(Item::Procedure);
+
+        let mut items: Vec<_> = imports
+            .chain(enums)
+            .chain(constants)
+            .chain(procedures)
+            .collect();
+
+        items.sort_by_key(|item| match item {
+            Item::Import(import) => import.path().location().start(),
+            Item::Enum(enumeration) => enumeration.name().location().start(),
+            Item::Constant(constant) => constant.name().location().start(),
+            Item::Procedure(procedure) => procedure.name().location().start(),
+        });
+
+        let mut after_block = false;
+        for item in items {
+            let is_block = matches!(item, Item::Enum(_) | Item::Procedure(_));
+            if !self.output.is_empty() && (after_block || is_block) {
+                self.output.push('\n');
+                self.last_line = None;
+            }
+
+            match item {
+                Item::Import(import) => self.import(import),
+                Item::Enum(enumeration) => self.enumeration(enumeration),
+                Item::Constant(constant) => self.constant(constant),
+                Item::Procedure(procedure) => self.procedure(procedure),
+            }
+
+            after_block = is_block;
+        }
+
+        self.comments_before(None);
+    }
+
+    fn import(&mut self, import: &Import) {
+        let start = import.path().location().start();
+        self.comments_before(Some(start));
+        self.separate(start.line());
+
+        self.line(&format!("import {};", string(import.path())));
+
+        let end = self.find(start, Token::Semicolon);
+        self.end(end);
+    }
+
+    fn enumeration(&mut self, enumeration: &Enum) {
+        let start = enumeration.name().location().start();
+        self.comments_before(Some(start));
+        self.separate(start.line());
+
+        let open = self.find(start, Token::OpenCurly);
+        let close = self.matching(open);
+        self.open(&format!("enum {}", enumeration.name().as_ref()), open);
+
+        self.indent += 1;
+        self.last_line = None;
+
+        let variants = enumeration.variants();
+        for (index, variant) in variants.iter().enumerate() {
+            let start = variant.name().location().start();
+            self.comments_before(Some(start));
+            self.separate(start.line());
+
+            let mut text = variant.name().as_ref().to_string();
+            if let Some(value) = variant.value() {
+                write!(text, " = {}", self.expr(value)).unwrap();
+            }
+
+            // La variante termina justo antes de la siguiente o de la llave
+            let end = match variants.get(index + 1) {
+                Some(next) => {
+                    text.push(',');
+                    self.at(next.name().location().start()) - 1
+                }
+
+                None => close - 1,
+            };
+
+            self.line(&text);
+            self.end(end);
+        }
+
+        self.comments_before(Some(self.position(close)));
+        self.indent -= 1;
+
+        self.close("}", close);
+    }
+
+    fn constant(&mut self, constant: &Constant) {
+        let start = constant.name().location().start();
+        self.comments_before(Some(start));
+        self.separate(start.line());
+
+        let value = self.expr(constant.value());
+        self.line(&format!("const {} = {};", constant.name().as_ref(), value));
+
+        let end = self.find(start, Token::Semicolon);
+        self.end(end);
+    }
+
+    fn procedure(&mut self, procedure: &Procedure) {
+        let start = procedure.name().location().start();
+        self.comments_before(Some(start));
+        self.separate(start.line());
+
+        let open = self.find(start, Token::OpenCurly);
+        self.open(&self.signature(procedure), open);
+
+        let close = self.body(open, procedure.statements());
+        self.close("}", close);
+    }
+
+    fn signature(&self, procedure: &Procedure) -> String {
+        let parameters: Vec<_> = procedure
+            .parameters()
+            .iter()
+            .map(|parameter| {
+                let by_ref = if parameter.is_ref() { "ref " } else { "" };
+                let of = self.typ(parameter.of());
+                format!("{}{}: {}", by_ref, parameter.name().as_ref(), of)
+            })
+            .collect();
+
+        let mut head = format!(
+            "procedure {}({})",
+            procedure.name().as_ref(),
+            parameters.join(", ")
+        );
+
+        if let Some(returns) = procedure.returns() {
+            write!(head, ": {}", self.typ(returns)).unwrap();
+        }
+
+        head
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        use Statement::*;
+
+        let start = statement.location().start();
+        self.comments_before(Some(start));
+        self.separate(start.line());
+
+        match statement {
+            If {
+                condition,
+                body,
+                otherwise,
+            } => self.conditional("if", condition, body, otherwise.as_deref()),
+
+            For {
+                variable,
+                iterable,
+                step,
+                body,
+            } => {
+                let mut head = format!("for {} in {}", variable.as_ref(), self.expr(iterable));
+                if let Some(step) = step {
+                    write!(head, " step {}", self.expr(step)).unwrap();
+                }
+
+                let open = self.find(start, Token::OpenCurly);
+                self.open(&head, open);
+
+                let close = self.body(open, body);
+                self.close("}", close);
+            }
+
+            Loop { rate, body } => {
+                let open = self.find(start, Token::OpenCurly);
+                self.open(&format!("loop at {}", self.expr(rate)), open);
+
+                let close = self.body(open, body);
+                self.close("}", close);
+            }
+
+            statement => {
+                let text = self.simple(statement);
+                self.line(&format!("{};", text));
+
+                let end = self.find(start, Token::Semicolon);
+                self.end(end);
+            }
+        }
+    }
+
+    /// Escribe un `if` y sus alternativas. `head` precede a la condición.
+    ///
+    /// Un `else` cuyo único enunciado es otro `if` se escribe como `elif`
+    /// solamente si así se escribió en el original.
+    fn conditional(
+        &mut self,
+        head: &str,
+        condition: &Located<Expr>,
+        body: &[Statement],
+        otherwise: Option<&[Statement]>,
+    ) {
+        let open = self.find(condition.location().start(), Token::OpenCurly);
+        self.open(&format!("{} {}", head, self.expr(condition)), open);
+
+        let close = self.body(open, body);
+        let next = self.tokens.get(close + 1).map(Located::as_ref);
+
+        match (otherwise, next) {
+            (None, _) => self.close("}", close),
+
+            (
+                Some(
+                    [Statement::If {
+                        condition,
+                        body,
+                        otherwise,
+                    }],
+                ),
+                Some(Token::Keyword(Keyword::Elif)),
+            ) => self.conditional("} elif", condition, body, otherwise.as_deref()),
+
+            (Some(otherwise), _) => {
+                let open = self.find(self.position(close), Token::OpenCurly);
+                self.open("} else", open);
+
+                let close = self.body(open, otherwise);
+                self.close("}", close);
+            }
+        }
+    }
+
+    /// Texto de un enunciado que no contiene bloques, sin el `;` final.
+    fn simple(&self, statement: &Statement) -> String {
+        use Statement::*;
+
+        match statement {
+            UserCall { procedure, args } => {
+                format!("call {}({})", procedure.as_ref(), self.exprs(args))
+            }
+
+            GlobalLift(id) => format!("global {}", id.as_ref()),
+
+            Return { value: None, .. } => String::from("return"),
+            Break(_) => String::from("break"),
+            Continue(_) => String::from("continue"),
+            Return {
+                value: Some(value), ..
+            } => format!("return {}", self.expr(value)),
+
+            Assignment { targets, values } => {
+                let targets: Vec<_> = targets.iter().map(|target| self.target(target)).collect();
+                format!("{} = {}", targets.join(", "), self.exprs(values))
+            }
+
+            MethodCall {
+                target,
+                method,
+                args,
+            } if args.is_empty() => format!("{}.{}", self.target(target), method.as_ref()),
+
+            MethodCall {
+                target,
+                method,
+                args,
+            } => format!(
+                "{}.{}({})",
+                self.target(target),
+                method.as_ref(),
+                self.exprs(args)
+            ),
+
+            Debug { hint, .. } => {
+                let hint = hint.as_ref().map(|hint| self.expr(hint));
+                format!("debug({})", hint.unwrap_or_default())
+            }
+
+            Blink {
+                column,
+                row,
+                count,
+                unit,
+                state,
+            } => format!(
+                "blink({}, {}, {}, {}, {})",
+                self.expr(column),
+                self.expr(row),
+                self.expr(count),
+                time_unit(*unit),
+                self.expr(state)
+            ),
+
+            Delay { count, unit } => format!("delay({}, {})", self.expr(count), time_unit(*unit)),
+
+            PrintLed { column, row, value } => format!(
+                "PrintLed({}, {}, {})",
+                self.expr(column),
+                self.expr(row),
+                self.expr(value)
+            ),
+
+            PrintLedX {
+                kind,
+                index,
+                object,
+            } => {
+                let kind = match kind {
+                    ObjectKind::Column => "\"C\"",
+                    ObjectKind::Row => "\"F\"",
+                    ObjectKind::Matrix => "\"M\"",
+                };
+
+                format!(
+                    "PrintLedX({}, {}, {})",
+                    kind,
+                    self.expr(index),
+                    self.expr(object)
+                )
+            }
+
+            PrintLedB { column, row, level } => format!(
+                "PrintLedB({}, {}, {})",
+                self.expr(column),
+                self.expr(row),
+                self.expr(level)
+            ),
+
+            PrintText { text, speed } => {
+                format!("PrintText({}, {})", self.expr(text), self.expr(speed))
+            }
+
+            Show(_) => String::from("Show"),
+
+            DisplaySize { width, height } => {
+                format!("DisplaySize({}, {})", self.expr(width), self.expr(height))
+            }
+
+            Play {
+                frames, looping, ..
+            } => {
+                let mut play = format!("Play [{}]", self.exprs(frames));
+                if let Some(looping) = looping {
+                    write!(play, ", {}", self.expr(looping)).unwrap();
+                }
+
+                play
+            }
+
+            Seed(seed) => format!("Seed({})", self.expr(seed)),
+            Store { slot, value } => format!("Store({}, {})", self.expr(slot), self.expr(value)),
+            UartWrite(value) => format!("UartWrite({})", self.expr(value)),
+
+            Schedule {
+                delay,
+                procedure,
+                repeat,
+            } => {
+                let keyword = if *repeat { "Every" } else { "After" };
+                format!("{}({}, {})", keyword, self.expr(delay), procedure.as_ref())
+            }
+
+            If { .. } | For { .. } | Loop { .. } => unreachable!(),
+        }
+    }
+
+    fn target(&self, target: &Target) -> String {
+        let mut text = target.var().as_ref().to_string();
+        for index in target.indices() {
+            text.push_str(&self.index(index));
+        }
+
+        text
+    }
+
+    fn index(&self, index: &Index) -> String {
+        match index {
+            Index::Single(index) => format!("[{}]", self.expr(index)),
+            Index::Range(start, end) => format!("[{}:{}]", self.expr(start), self.expr(end)),
+            Index::Indirect(row, column) => format!("[{}, {}]", self.expr(row), self.expr(column)),
+            Index::Transposed(column) => format!("[:, {}]", self.expr(column)),
+        }
+    }
+
+    fn typ(&self, typ: &Type) -> String {
+        let name = match typ {
+            Type::Int => "int",
+            Type::Bool => "bool",
+            Type::List => "list",
+            Type::IntList => "ilist",
+            Type::Mat => "mat",
+            Type::Float => "float",
+            Type::Str => "string",
+            Type::Of(expr) => return format!("type({})", self.expr(expr)),
+        };
+
+        String::from(name)
+    }
+
+    fn exprs(&self, exprs: &[Located<Expr>]) -> String {
+        let exprs: Vec<_> = exprs.iter().map(|expr| self.expr(expr)).collect();
+        exprs.join(", ")
+    }
+
+    fn expr(&self, expr: &Expr) -> String {
+        use Expr::*;
+
+        match expr {
+            True => String::from("true"),
+            False => String::from("false"),
+            Integer(integer) => integer.to_string(),
+
+            Float(float) => {
+                let mut text = float.to_string();
+                if !text.contains('.') {
+                    text.push_str(".0");
+                }
+
+                text
+            }
+
+            Str(literal) => string(AsRef::<str>::as_ref(literal.as_ref())),
+            Read(id) => id.as_ref().to_string(),
+            Attr(expr, attr) => format!("{}.{}", self.expr(expr), attr.as_ref()),
+            Index(expr, index) => format!("{}{}", self.expr(expr), self.index(index)),
+            Len(expr) => format!("len({})", self.expr(expr)),
+            Range(start, end) => format!("range({}, {})", self.expr(start), self.expr(end)),
+            Random(max) => format!("Random({})", self.expr(max)),
+            Load(slot) => format!("Load({})", self.expr(slot)),
+            UartRead => String::from("UartRead()"),
+            HeapUsed => String::from("HeapUsed()"),
+            HeapFree => String::from("HeapFree()"),
+            Millis => String::from("Millis()"),
+
+            // Una matriz literal se escribe con una fila por línea
+            List(rows)
+                if rows.len() > 1 && rows.iter().all(|row| matches!(row.as_ref(), List(_))) =>
+            {
+                let indent = INDENT.repeat(self.indent);
+
+                let mut text = String::from("[\n");
+                for row in rows {
+                    writeln!(text, "{}{}{},", indent, INDENT, self.expr(row)).unwrap();
+                }
+
+                text.pop();
+                text.pop();
+                write!(text, "\n{}]", indent).unwrap();
+
+                text
+            }
+
+            List(items) => format!("[{}]", self.exprs(items)),
+            New(of) => format!("{}()", self.typ(of)),
+            Cast(of, expr) => format!("{}({})", self.typ(of), self.expr(expr)),
+            Negate(expr) => format!("-{}", self.expr(expr)),
+            Not(expr) => format!("!{}", self.expr(expr)),
+            Call(procedure, args) => format!("call {}({})", procedure.as_ref(), self.exprs(args)),
+            ProcRef(procedure) => format!("@{}", procedure.as_ref()),
+
+            Binary {
+                limits,
+                lhs,
+                op,
+                rhs,
+            } => {
+                let binary = format!("{} {} {}", self.expr(lhs), op, self.expr(rhs));
+                match limits {
+                    ExprLimits::Free => binary,
+                    ExprLimits::Enclosed => format!("({})", binary),
+                }
+            }
+        }
+    }
+
+    /// Escribe el encabezado de un bloque, cuya llave de apertura es el
+    /// token `open`.
+    fn open(&mut self, head: &str, open: usize) {
+        self.line(&format!("{} {{", head));
+        self.last_line = Some(self.position(open).line());
+        self.trailing(self.position(open));
+    }
+
+    /// Escribe los enunciados de un bloque y los comentarios que
+    /// preceden a su cierre. Retorna el índice de la llave de cierre.
+    fn body(&mut self, open: usize, statements: &[Statement]) -> usize {
+        let close = self.matching(open);
+
+        self.indent += 1;
+        self.last_line = None;
+
+        for statement in statements {
+            self.statement(statement);
+        }
+
+        self.comments_before(Some(self.position(close)));
+        self.indent -= 1;
+
+        close
+    }
+
+    /// Escribe una línea que cierra un bloque en el token `close`.
+    fn close(&mut self, text: &str, close: usize) {
+        self.line(text);
+        self.end(close);
+    }
+
+    /// Registra que lo último que se escribió termina en el token `end`.
+    fn end(&mut self, end: usize) {
+        let position = self.position(end);
+        self.last_line = Some(position.line());
+        self.trailing(position);
+    }
+
+    /// Escribe los comentarios que preceden a `before`, o bien todos los
+    /// restantes.
+    fn comments_before(&mut self, before: Option<Position>) {
+        while let Some((position, text)) = self.comments.get(self.next_comment) {
+            if before.map_or(false, |before| *position >= before) {
+                break;
+            }
+
+            let (line, text) = (position.line(), format!("##{}", text.trim_end()));
+            self.next_comment += 1;
+
+            self.separate(line);
+            self.line(&text);
+            self.last_line = Some(line);
+        }
+    }
+
+    /// Mueve al final de la última línea escrita un comentario que, en
+    /// el original, sigue a `end` en su misma línea.
+    fn trailing(&mut self, end: Position) {
+        if let Some((position, text)) = self.comments.get(self.next_comment) {
+            if position.line() == end.line() && *position > end {
+                let text = format!("  ##{}\n", text.trim_end());
+                self.next_comment += 1;
+
+                self.output.pop();
+                self.output.push_str(&text);
+            }
+        }
+    }
+
+    /// Preserva una línea en blanco antes de algo que inicia en `line`.
+    fn separate(&mut self, line: u32) {
+        if self.last_line.map_or(false, |last| line > last + 1) {
+            self.output.push('\n');
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.indent {
+            self.output.push_str(INDENT);
+        }
+
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    /// Índice del primer `token` que inicia en o después de `from`.
+    fn find(&self, from: Position, token: Token) -> usize {
+        let start = self.at(from);
+        let offset = self.tokens[start..]
+            .iter()
+            .position(|other| *other.as_ref() == token)
+            .expect("parsed program lacks an expected token");
+
+        start + offset
+    }
+
+    /// Índice del primer token que inicia en o después de `from`.
+    fn at(&self, from: Position) -> usize {
+        self.tokens
+            .binary_search_by(|other| other.location().start().cmp(&from))
+            .unwrap_or_else(|index| index)
+    }
+
+    /// Índice de la llave que cierra a la llave `open`.
+    fn matching(&self, open: usize) -> usize {
+        let mut depth = 0;
+        for (index, token) in self.tokens.iter().enumerate().skip(open) {
+            match token.as_ref() {
+                Token::OpenCurly => depth += 1,
+                Token::CloseCurly if depth == 1 => return index,
+                Token::CloseCurly => depth -= 1,
+                _ => (),
+            }
+        }
+
+        unreachable!("parsed program has unbalanced braces")
+    }
+
+    fn position(&self, index: usize) -> Position {
+        self.tokens[index].location().start()
+    }
+}
+
+fn time_unit(unit: TimeUnit) -> &'static str {
+    match unit {
+        TimeUnit::Millis => "\"mil\"",
+        TimeUnit::Seconds => "\"seg\"",
+        TimeUnit::Minutes => "\"min\"",
+    }
+}
+
+/// Literal de cadena, con las secuencias de escape que acepta el lexer.
+fn string(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            c => literal.push(c),
+        }
+    }
+
+    literal.push('"');
+    literal
+}
@@ -0,0 +1,587 @@
+//! Evaluación automática de programas.
+//!
+//! Un escenario describe los cuadros que se espera observar en la
+//! matriz de LEDs en ciertos instantes. [`grade()`] compila un programa
+//! para la plataforma nativa, lo ejecuta bajo los límites de
+//! [`crate::sandbox`] con el reloj virtual de `libruntime` y compara la
+//! traza resultante contra el escenario. Como el reloj es virtual, el
+//! resultado no depende de la carga de la máquina que evalúa.
+//!
+//! # Escenarios
+//! Los escenarios se escriben en TOML:
+//!
+//! ```toml
+//! # Opcional: tiempo virtual en ms tras el cual se detiene el
+//! # programa. Por omisión, el instante del último cuadro.
+//! stop_at = 1500
+//!
+//! # Opcional: límites de ejecución, ver `sandbox::Limits`
+//! [limits]
+//! cpu = 5
+//! wall = 30
+//! memory = 256
+//!
+//! # Opcional: desviaciones toleradas para todos los cuadros
+//! [tolerance]
+//! time = 20   # ms de adelanto o atraso
+//! lights = 0  # LEDs que pueden diferir
+//!
+//! [[frame]]
+//! at = 1000
+//! rows = [
+//!     "#.......",
+//!     "........",
+//!     "........",
+//!     "........",
+//!     "........",
+//!     "........",
+//!     "........",
+//!     ".......#",
+//! ]
+//! # Opcional: reemplaza a [tolerance] para este cuadro
+//! lights = 1
+//! ```
+//!
+//! Cada fila es una cadena de ocho caracteres, donde `#` o `1` indican
+//! un LED encendido y `.` o `0` uno apagado. Un cuadro se satisface si
+//! en algún instante dentro de `at ± time` la matriz difiere del cuadro
+//! esperado en a lo sumo `lights` LEDs.
+
+use std::{
+    fmt::{self, Display},
+    fs, io,
+    path::{Path, PathBuf},
+    process::{self, Stdio},
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    link::{LinkOptions, Linker, LinkerError, OutputFormat, Platform, ScriptOptions},
+    sandbox::{self, Limits, Termination},
+    target,
+};
+
+/// Un error que impide evaluar un programa.
+///
+/// Los errores del programa evaluado no son de esta clase, sino
+/// que se reportan en [`Report`].
+#[derive(Error, Debug)]
+pub enum GradeError {
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+
+    #[error("Failed to link")]
+    Link(#[from] LinkerError),
+
+    #[error("Malformed trace: {0}")]
+    Trace(String),
+}
+
+/// Un escenario mal formado.
+#[derive(Error, Debug)]
+pub enum ScenarioError {
+    #[error("{0}")]
+    Toml(#[from] toml::de::Error),
+
+    #[error("Frame {index}: {reason}")]
+    BadFrame { index: usize, reason: String },
+
+    #[error("Scenario has no frames")]
+    Empty,
+}
+
+/// Contenido de la matriz de LEDs, una fila por byte.
+///
+/// El bit más significativo de cada fila corresponde a la columna 0.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct Frame(pub [u8; 8]);
+
+impl Frame {
+    /// Cantidad de LEDs en los que difieren dos cuadros.
+    pub fn distance(&self, other: &Frame) -> u32 {
+        let (Frame(this), Frame(other)) = (self, other);
+        this.iter()
+            .zip(other.iter())
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum()
+    }
+}
+
+impl Display for Frame {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Frame(rows) = self;
+        for row in rows.iter() {
+            for column in 0..8 {
+                let symbol = if row & (0x80 >> column) != 0 {
+                    '#'
+                } else {
+                    '.'
+                };
+                write!(fmt, "{}", symbol)?;
+            }
+
+            writeln!(fmt)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Desviaciones toleradas respecto a un cuadro esperado.
+#[derive(Copy, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tolerance {
+    /// Adelanto o atraso, en milisegundos.
+    #[serde(default)]
+    pub time: u64,
+
+    /// LEDs que pueden diferir.
+    #[serde(default)]
+    pub lights: u32,
+}
+
+/// Un cuadro que se espera observar.
+pub struct Expected {
+    /// Instante en milisegundos de tiempo virtual.
+    pub at: u64,
+    pub frame: Frame,
+    pub tolerance: Tolerance,
+}
+
+/// Un escenario de evaluación.
+pub struct Scenario {
+    pub limits: Limits,
+    pub stop_at: u64,
+    pub frames: Vec<Expected>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawScenario {
+    stop_at: Option<u64>,
+
+    #[serde(default)]
+    limits: RawLimits,
+
+    #[serde(default)]
+    tolerance: Tolerance,
+
+    #[serde(default, rename = "frame")]
+    frames: Vec<RawFrame>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawLimits {
+    cpu: Option<u64>,
+    wall: Option<u64>,
+    memory: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawFrame {
+    at: u64,
+    rows: Vec<String>,
+    time: Option<u64>,
+    lights: Option<u32>,
+}
+
+impl FromStr for Scenario {
+    type Err = ScenarioError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let raw: RawScenario = toml::from_str(text)?;
+
+        let mut frames = Vec::new();
+        for (index, frame) in raw.frames.into_iter().enumerate() {
+            let bad_frame = |reason: &str| ScenarioError::BadFrame {
+                index,
+                reason: reason.to_string(),
+            };
+
+            if frame.rows.len() != 8 {
+                return Err(bad_frame("expected 8 rows"));
+            }
+
+            let mut rows = [0; 8];
+            for (bits, row) in rows.iter_mut().zip(frame.rows.iter()) {
+                if row.chars().count() != 8 {
+                    return Err(bad_frame("expected 8 columns per row"));
+                }
+
+                for symbol in row.chars() {
+                    let on = match symbol {
+                        '#' | '1' => true,
+                        '.' | '0' => false,
+                        _ => return Err(bad_frame("rows may only contain '#', '.', '1' or '0'")),
+                    };
+
+                    *bits = *bits << 1 | on as u8;
+                }
+            }
+
+            let tolerance = Tolerance {
+                time: frame.time.unwrap_or(raw.tolerance.time),
+                lights: frame.lights.unwrap_or(raw.tolerance.lights),
+            };
+
+            frames.push(Expected {
+                at: frame.at,
+                frame: Frame(rows),
+                tolerance,
+            });
+        }
+
+        let last = frames
+            .iter()
+            .map(|frame| frame.at + frame.tolerance.time)
+            .max();
+        let stop_at = match (raw.stop_at, last) {
+            (Some(stop_at), _) => stop_at,
+            (None, Some(last)) => last + 1,
+            (None, None) => return Err(ScenarioError::Empty),
+        };
+
+        let defaults = Limits::default();
+        let limits = Limits {
+            cpu: raw.limits.cpu.unwrap_or(defaults.cpu),
+            wall: raw.limits.wall.unwrap_or(defaults.wall),
+            memory: raw.limits.memory.unwrap_or(defaults.memory),
+        };
+
+        Ok(Scenario {
+            limits,
+            stop_at,
+            frames,
+        })
+    }
+}
+
+/// Resultado de evaluar un programa.
+pub struct Report {
+    pub outcome: Outcome,
+
+    /// Un resultado por cada cuadro del escenario, vacío si no compiló.
+    pub frames: Vec<FrameCheck>,
+}
+
+/// Lo que ocurrió con el programa.
+pub enum Outcome {
+    /// El programa no compiló.
+    Rejected(String),
+
+    /// El programa terminó con un error o excedió algún límite.
+    Crashed { exit_code: i32, message: String },
+
+    /// El programa terminó normalmente o alcanzó `stop_at`.
+    Finished,
+}
+
+/// Resultado de buscar un cuadro esperado en la traza.
+pub struct FrameCheck {
+    pub at: u64,
+    pub expected: Frame,
+
+    /// Cuadro más cercano al esperado dentro de la ventana tolerada.
+    pub closest: Frame,
+
+    /// Diferencia entre `closest` y `expected`, en LEDs.
+    pub distance: u32,
+    pub passed: bool,
+}
+
+impl Report {
+    /// Si el programa terminó correctamente y mostró todos los cuadros.
+    pub fn passed(&self) -> bool {
+        matches!(self.outcome, Outcome::Finished) && self.frames.iter().all(|frame| frame.passed)
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.outcome {
+            Outcome::Rejected(diagnostics) => {
+                write!(fmt, "{}", diagnostics)?;
+                return writeln!(fmt, "FAIL: program was rejected");
+            }
+
+            Outcome::Crashed { exit_code, message } => {
+                writeln!(fmt, "Program exited with code {}", exit_code)?;
+                write!(fmt, "{}", message)?;
+            }
+
+            Outcome::Finished => (),
+        }
+
+        for check in self.frames.iter().filter(|check| !check.passed) {
+            let light_or_lights = if check.distance == 1 {
+                "light"
+            } else {
+                "lights"
+            };
+
+            writeln!(
+                fmt,
+                "Frame at {}ms differs in {} {}",
+                check.at, check.distance, light_or_lights
+            )?;
+
+            let (expected, closest) = (check.expected.to_string(), check.closest.to_string());
+            writeln!(fmt, "  expected  closest")?;
+
+            for (expected, closest) in expected.lines().zip(closest.lines()) {
+                writeln!(fmt, "  {}  {}", expected, closest)?;
+            }
+        }
+
+        let passed = self.frames.iter().filter(|check| check.passed).count();
+        let verdict = if self.passed() { "PASS" } else { "FAIL" };
+
+        writeln!(
+            fmt,
+            "{}: {}/{} frames matched",
+            verdict,
+            passed,
+            self.frames.len()
+        )
+    }
+}
+
+/// Compila, ejecuta y evalúa un programa contra un escenario.
+///
+/// `name` identifica al programa en diagnósticos. El programa se
+/// ejecuta con los límites del escenario, con el reloj virtual y hasta
+/// `stop_at`. Los errores de compilación y de ejecución del programa
+/// forman parte del reporte; un `Err` indica que no fue posible evaluar.
+pub fn grade(name: &str, program_source: &str, scenario: &Scenario) -> Result<Report, GradeError> {
+    let recording = record(
+        name,
+        program_source,
+        scenario.stop_at,
+        &scenario.limits,
+        false,
+    )?;
+
+    let frames = match recording.outcome {
+        Outcome::Rejected(_) => Vec::new(),
+        _ => scenario
+            .frames
+            .iter()
+            .map(|expected| check(expected, &recording.samples))
+            .collect(),
+    };
+
+    Ok(Report {
+        outcome: recording.outcome,
+        frames,
+    })
+}
+
+/// Un cambio en la matriz observado por el reloj virtual.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Sample {
+    /// Instante en milisegundos de tiempo virtual.
+    pub at: u64,
+    pub frame: Frame,
+
+    /// Intensidad de cada LED por fila, si se solicitaron.
+    pub levels: Option<[[u8; 8]; 8]>,
+}
+
+/// Ejecución completa de un programa bajo el reloj virtual.
+pub struct Recording {
+    pub outcome: Outcome,
+
+    /// Cada cambio de la matriz en orden, vacío si no compiló.
+    pub samples: Vec<Sample>,
+}
+
+/// Compila un programa y lo ejecuta con el reloj virtual hasta `stop_at`.
+///
+/// A diferencia de [`grade()`], no compara contra un escenario sino
+/// que retorna todos los cambios de la matriz. Si `levels` es verdadero
+/// también se registran las intensidades de cada LED.
+pub fn record(
+    name: &str,
+    program_source: &str,
+    stop_at: u64,
+    limits: &Limits,
+    levels: bool,
+) -> Result<Recording, GradeError> {
+    let program = match crate::frontend(&mut program_source.as_bytes(), name) {
+        Ok(program) => program,
+        Err(diagnostics) => {
+            return Ok(Recording {
+                outcome: Outcome::Rejected(diagnostics.to_string()),
+                samples: Vec::new(),
+            })
+        }
+    };
+
+    let workspace = Workspace::new()?;
+    let executable = workspace.path("program");
+    let trace = workspace.path("trace");
+    let errors = workspace.path("stderr");
+
+    let platform = Platform::Native;
+    let mut assembly = Vec::new();
+    target::emit(&program, platform.arch(), &mut assembly)?;
+
+    let options = LinkOptions::empty();
+    let mut linker = Linker::spawn(
+        platform,
+        &executable,
+        OutputFormat::Elf,
+        ScriptOptions::default(),
+        None,
+        None,
+        options,
+    )?;
+    linker.stdin().write_all(&assembly)?;
+    linker.finish()?;
+
+    let mut command = sandbox::command(&executable);
+    command
+        .env("ANIMATIONLED_TRACE", &trace)
+        .env("ANIMATIONLED_STOP_AT", stop_at.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(fs::File::create(&errors)?);
+
+    if levels {
+        command.env("ANIMATIONLED_TRACE_LEVELS", "1");
+    }
+
+    let termination = sandbox::run(command, &executable, Some(limits))?;
+    let outcome = outcome(&termination, name, program_source, &errors);
+
+    let samples = read_trace(&trace)?;
+    Ok(Recording { outcome, samples })
+}
+
+/// Interpreta la forma en que terminó el programa.
+fn outcome(termination: &Termination, name: &str, source: &str, errors: &Path) -> Outcome {
+    let exit_code = termination.exit_code();
+    if exit_code == 0 {
+        return Outcome::Finished;
+    }
+
+    // Errores en tiempo de ejecución de libruntime u otros mensajes en stderr
+    let message = termination
+        .diagnostic(name, Some(source))
+        .or_else(|| fs::read_to_string(errors).ok())
+        .unwrap_or_default();
+
+    Outcome::Crashed { exit_code, message }
+}
+
+/// Lee una traza emitida por el reloj virtual de `libruntime`.
+fn read_trace(path: &Path) -> Result<Vec<Sample>, GradeError> {
+    // Un programa que falla antes de iniciar no crea la traza
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(error.into()),
+    };
+
+    text.lines()
+        .map(|line| {
+            let bad_line = || GradeError::Trace(line.to_string());
+
+            let mut fields = line.split_whitespace();
+            let (at, rows) = match (fields.next(), fields.next()) {
+                (Some(at), Some(rows)) if rows.len() == 16 => (at, rows),
+                _ => return Err(bad_line()),
+            };
+
+            let at = at.parse().map_err(|_| bad_line())?;
+
+            let mut frame = [0; 8];
+            for (i, bits) in frame.iter_mut().enumerate() {
+                let digits = rows.get(2 * i..2 * i + 2).ok_or_else(bad_line)?;
+                *bits = u8::from_str_radix(digits, 16).map_err(|_| bad_line())?;
+            }
+
+            let levels = match (fields.next(), fields.next()) {
+                (None, _) => None,
+                (Some(digits), None) if digits.len() == 64 => {
+                    let mut levels = [[0; 8]; 8];
+                    for (i, digit) in digits.chars().enumerate() {
+                        let level = digit.to_digit(16).ok_or_else(bad_line)?;
+                        levels[i / 8][i % 8] = level as u8;
+                    }
+
+                    Some(levels)
+                }
+
+                _ => return Err(bad_line()),
+            };
+
+            Ok(Sample {
+                at,
+                frame: Frame(frame),
+                levels,
+            })
+        })
+        .collect()
+}
+
+/// Busca un cuadro esperado dentro de su ventana de tolerancia.
+fn check(expected: &Expected, trace: &[Sample]) -> FrameCheck {
+    let from = expected.at.saturating_sub(expected.tolerance.time);
+    let to = expected.at + expected.tolerance.time;
+
+    // El cuadro vigente al inicio de la ventana y todo cambio dentro de ella
+    let before = trace.iter().rev().find(|sample| sample.at <= from);
+    let within = trace
+        .iter()
+        .filter(|sample| sample.at > from && sample.at <= to);
+
+    let closest = before
+        .into_iter()
+        .chain(within)
+        .map(|sample| sample.frame)
+        .min_by_key(|frame| frame.distance(&expected.frame))
+        .unwrap_or_default();
+
+    let distance = closest.distance(&expected.frame);
+
+    FrameCheck {
+        at: expected.at,
+        expected: expected.frame,
+        closest,
+        distance,
+        passed: distance <= expected.tolerance.lights,
+    }
+}
+
+/// Directorio temporal para los archivos de una evaluación.
+struct Workspace(PathBuf);
+
+impl Workspace {
+    fn new() -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let mut dir = std::env::temp_dir();
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+        dir.push(format!("compiler-grade.{}.{}", process::id(), count));
+
+        fs::create_dir_all(&dir)?;
+        Ok(Workspace(dir))
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,151 @@
+//! Importación de módulos.
+//!
+//! Un programa puede incorporar las constantes y procedimientos de otros
+//! archivos mediante `import "ruta";` fuera de todo procedimiento. Las
+//! rutas relativas se buscan primero en el directorio del archivo que
+//! importa y luego, en orden, en cada directorio de la ruta de búsqueda
+//! que se indique con `-I`.
+//!
+//! No existen espacios de nombres por módulo: las declaraciones de todos
+//! los módulos se combinan en un solo AST antes del análisis semántico,
+//! el cual registra todos los procedimientos antes de resolver cualquier
+//! sobrecarga. Un nombre definido en dos módulos se reporta como una
+//! redefinición con las ubicaciones de ambas definiciones.
+//!
+//! Cada módulo se incorpora una sola vez aunque se importe desde varios
+//! archivos, y sus declaraciones preceden a las de quienes lo importan.
+//! Un ciclo de importaciones es un error. El punto de entrada de un
+//! módulo importado se descarta.
+
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+    error::{Diagnostics, Help},
+    parse::{Ast, Import},
+    source::Located,
+};
+
+#[non_exhaustive]
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("Module `{0}` not found")]
+    NotFound(String),
+
+    #[error("Failed to read module `{0}`")]
+    Input(String, #[source] io::Error),
+
+    #[error("Import of `{0}` forms a cycle")]
+    Cycle(String),
+}
+
+impl Help for ImportError {
+    fn help(&self) -> Option<String> {
+        match self {
+            ImportError::NotFound(_) => Some(String::from(
+                "add the directory that contains it to the search path with `-I`",
+            )),
+
+            _ => None,
+        }
+    }
+}
+
+/// Incorpora a un programa los módulos que importa, recursivamente.
+///
+/// `name` identifica al programa como en [`crate::frontend()`]. Si
+/// corresponde a un archivo, sus importaciones son relativas al
+/// directorio de este, y de lo contrario al directorio actual.
+pub fn resolve(mut ast: Ast, name: &str, search_path: &[PathBuf]) -> Result<Ast, Diagnostics> {
+    if ast.imports().is_empty() {
+        return Ok(ast);
+    }
+
+    let root = Path::new(name);
+    let (directory, stack) = match root.canonicalize() {
+        Ok(root) if root.is_file() => (root.parent().map(Path::to_path_buf), vec![root]),
+        _ => (None, Vec::new()),
+    };
+
+    let mut loader = Loader {
+        search_path,
+        stack,
+        loaded: HashSet::new(),
+        modules: Vec::new(),
+    };
+
+    let directory = directory.unwrap_or_default();
+    loader.load_imports(&ast, &directory)?;
+
+    ast.merge(loader.modules);
+    Ok(ast)
+}
+
+struct Loader<'a> {
+    search_path: &'a [PathBuf],
+
+    /// Módulos cuyas importaciones se están cargando, para detectar ciclos.
+    stack: Vec<PathBuf>,
+
+    /// Módulos ya cargados o en carga.
+    loaded: HashSet<PathBuf>,
+
+    /// Módulos cargados, cada uno después de los que importa.
+    modules: Vec<Ast>,
+}
+
+impl Loader<'_> {
+    fn load_imports(&mut self, ast: &Ast, directory: &Path) -> Result<(), Diagnostics> {
+        for import in ast.imports() {
+            let path = self.find(import, directory)?;
+            let fail = |error: ImportError| {
+                Diagnostics::from(Located::at(error, import.path().location().clone()))
+            };
+
+            if self.stack.contains(&path) {
+                let error = ImportError::Cycle(import.path().as_str().to_string());
+                return Err(fail(error).kind("Import error"));
+            } else if !self.loaded.insert(path.clone()) {
+                continue;
+            }
+
+            let file = File::open(&path).map_err(|error| {
+                let error = ImportError::Input(import.path().as_str().to_string(), error);
+                fail(error).kind("Import error")
+            })?;
+
+            let name = path.to_string_lossy();
+            let (_, module) = crate::lex_and_parse(&mut BufReader::new(file), &name)?;
+
+            self.stack.push(path.clone());
+            self.load_imports(&module, path.parent().unwrap_or(directory))?;
+            self.stack.pop();
+
+            self.modules.push(module);
+        }
+
+        Ok(())
+    }
+
+    /// Ruta canónica de un módulo importado desde `directory`.
+    fn find(&self, import: &Import, directory: &Path) -> Result<PathBuf, Diagnostics> {
+        let path = import.path();
+        let directories =
+            std::iter::once(directory).chain(self.search_path.iter().map(PathBuf::as_path));
+
+        directories
+            .map(|candidate| candidate.join(path.as_str()))
+            .find(|candidate| candidate.is_file())
+            .and_then(|candidate| candidate.canonicalize().ok())
+            .ok_or_else(|| {
+                let error = ImportError::NotFound(path.as_str().to_string());
+                Diagnostics::from(Located::at(error, path.location().clone())).kind("Import error")
+            })
+    }
+}
@@ -0,0 +1,265 @@
+//! Recompilación incremental.
+//!
+//! Un [`Cache`] recuerda el análisis semántico y el ensamblador emitido
+//! para cada procedimiento de un programa. Al recompilar el mismo
+//! programa, los procedimientos sin cambios reutilizan ese trabajo, de
+//! forma que solo los modificados recorren el pipeline completo antes
+//! de reenlazar. Las fases léxica y sintáctica, así como el análisis de
+//! declaraciones y globales, se repiten siempre.
+//!
+//! # Invalidación
+//! La clave de un procedimiento se deriva de sus tokens junto a la línea
+//! de cada uno, ya que el IR registra números de línea. La clave incluye
+//! además la interfaz del programa: los encabezados de todos los
+//! procedimientos, las constantes, las enumeraciones y el cuerpo
+//! completo de `main()`, que es donde se definen las globales. Un cambio
+//! en la interfaz invalida todo.
+//!
+//! Las claves se derivan solo de los tokens del archivo principal, por
+//! lo cual nada se reutiliza en programas que importan otros módulos.
+
+use std::{
+    collections::{
+        hash_map::{self, DefaultHasher},
+        HashMap,
+    },
+    hash::{Hash, Hasher},
+    io::{self, BufRead, Write},
+};
+
+use crate::{
+    arch::Arch,
+    codegen,
+    error::Diagnostics,
+    import,
+    ir::Program,
+    lex::{Keyword, Token},
+    parse,
+    semantic::Analyzed,
+    source::Located,
+};
+
+/// Trabajo previo sobre los procedimientos de un programa.
+#[derive(Default)]
+pub struct Cache {
+    entries: HashMap<u64, Entry>,
+}
+
+/// Lo que se recuerda de un procedimiento.
+struct Entry {
+    analyzed: Analyzed,
+    assembly: HashMap<Arch, Vec<u8>>,
+}
+
+/// Un programa que atravesó las fases delanteras por medio de un [`Cache`].
+pub struct Build {
+    program: Program,
+
+    /// Clave de cada función de `program`, en el mismo orden, si esta
+    /// puede reutilizarse.
+    keys: Vec<Option<u64>>,
+    reused: usize,
+}
+
+impl Build {
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Cantidad de procedimientos cuyo análisis semántico se reutilizó.
+    pub fn reused(&self) -> usize {
+        self.reused
+    }
+}
+
+impl Cache {
+    /// Como [`crate::frontend_with_warnings()`], pero reutilizando el
+    /// análisis de procedimientos sin cambios.
+    ///
+    /// Se descartan las entradas de procedimientos que ya no existen.
+    pub fn frontend<R: BufRead>(
+        &mut self,
+        reader: &mut R,
+        name: &str,
+    ) -> Result<(Build, Diagnostics), Diagnostics> {
+        let (tokens, ast) = crate::lex_and_parse(reader, name)?;
+        let modular = !ast.imports().is_empty();
+        let ast = import::resolve(ast, name, &[])?;
+
+        let keys = procedure_keys(&tokens);
+        let key_of = |procedure: &parse::Procedure| {
+            if modular {
+                return None;
+            }
+
+            let start = procedure.name().location().start();
+            Some(keys[&(start.line(), start.column())])
+        };
+
+        let mut reused = 0;
+        let entries = &self.entries;
+
+        let resolution = ast
+            .resolve_reusing(|procedure| {
+                let entry = entries.get(&key_of(procedure)?)?;
+                reused += 1;
+
+                Some(entry.analyzed.clone())
+            })
+            .map_err(|error| Diagnostics::from(error).kind("Semantic error"))?;
+
+        let mut entries = HashMap::new();
+        let mut keys = Vec::new();
+        let mut code = Vec::new();
+
+        for (procedure, analyzed) in ast.iter().zip(resolution.procedures) {
+            let key = key_of(procedure);
+            if let Some(key) = key {
+                let entry = self.entries.remove(&key).unwrap_or_else(|| Entry {
+                    analyzed: analyzed.clone(),
+                    assembly: HashMap::new(),
+                });
+
+                entries.insert(key, entry);
+            }
+
+            keys.push(key);
+            code.push(analyzed.function);
+        }
+
+        self.entries = entries;
+
+        let program = Program {
+            globals: resolution.globals,
+            code,
+        };
+
+        let warnings = Diagnostics::from(resolution.warnings)
+            .kind("Semantic warning")
+            .fatal(false);
+
+        let build = Build {
+            program,
+            keys,
+            reused,
+        };
+
+        Ok((build, warnings))
+    }
+
+    /// Como [`crate::target::emit()`], pero reutilizando el ensamblador
+    /// de procedimientos sin cambios.
+    pub fn emit(&mut self, build: &Build, arch: Arch, output: &mut dyn Write) -> io::Result<()> {
+        // El módulo WebAssembly se emite como un todo
+        if let Arch::Wasm32 = arch {
+            return codegen::emit(&build.program, arch, output);
+        }
+
+        codegen::emit_header(&build.program, arch, output)?;
+
+        for (key, function) in build.keys.iter().zip(build.program.code.iter()) {
+            let entry = match key.and_then(|key| self.entries.get_mut(&key)) {
+                Some(entry) => entry,
+                None => {
+                    codegen::emit_function(function, arch, output)?;
+                    continue;
+                }
+            };
+
+            if let hash_map::Entry::Vacant(slot) = entry.assembly.entry(arch) {
+                let mut assembly = Vec::new();
+                codegen::emit_function(function, arch, &mut assembly)?;
+                slot.insert(assembly);
+            }
+
+            output.write_all(&entry.assembly[&arch])?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Calcula la clave de cada procedimiento, indexada por la línea y
+/// columna de su nombre.
+fn procedure_keys(tokens: &[Located<Token>]) -> HashMap<(u32, u32), u64> {
+    let procedures = split_procedures(tokens);
+
+    // Encabezados de todos los procedimientos, el cuerpo de main() y las
+    // constantes, que carecen de llaves y se consideran completas, al
+    // igual que las enumeraciones
+    let mut interface = DefaultHasher::new();
+    for procedure in procedures.iter() {
+        let is_main = match procedure.get(1).map(Located::as_ref) {
+            Some(Token::Id(id)) => unicase::eq_ascii(AsRef::<str>::as_ref(id), "main"),
+            _ => false,
+        };
+
+        let is_enum = matches!(
+            procedure.first().map(Located::as_ref),
+            Some(Token::Keyword(Keyword::Enum))
+        );
+
+        let header = procedure
+            .iter()
+            .position(|token| *token.as_ref() == Token::OpenCurly)
+            .unwrap_or(procedure.len());
+
+        let hashed = if is_main || is_enum {
+            procedure
+        } else {
+            &procedure[..header]
+        };
+        hash_tokens(hashed, &mut interface);
+    }
+
+    let interface = interface.finish();
+    procedures
+        .iter()
+        .filter_map(|procedure| {
+            let start = procedure.get(1)?.location().start();
+
+            let mut hasher = DefaultHasher::new();
+            interface.hash(&mut hasher);
+            hash_tokens(procedure, &mut hasher);
+
+            Some(((start.line(), start.column()), hasher.finish()))
+        })
+        .collect()
+}
+
+/// Divide un flujo de tokens en los de cada procedimiento, constante o
+/// enumeración.
+fn split_procedures(tokens: &[Located<Token>]) -> Vec<&[Located<Token>]> {
+    let mut procedures = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match token.as_ref() {
+            Token::OpenCurly => depth += 1,
+            Token::CloseCurly => depth -= 1,
+
+            Token::Keyword(Keyword::Procedure | Keyword::Const | Keyword::Enum)
+                if depth == 0 && i > start =>
+            {
+                procedures.push(&tokens[start..i]);
+                start = i;
+            }
+
+            _ => (),
+        }
+    }
+
+    if start < tokens.len() {
+        procedures.push(&tokens[start..]);
+    }
+
+    procedures
+}
+
+fn hash_tokens(tokens: &[Located<Token>], hasher: &mut DefaultHasher) {
+    for token in tokens {
+        token.location().start().line().hash(hasher);
+        format!("{:?}", token.as_ref()).hash(hasher);
+    }
+}
@@ -0,0 +1,993 @@
+//! Funciones externas.
+//!
+//! Cada builtin de `libruntime` tiene aquí un equivalente que opera
+//! sobre el heap y la matriz de una [`Machine`](super::Machine). El
+//! orden de las validaciones es el mismo que en el runtime, de forma
+//! que un programa erróneo falle con el mismo error en ambos casos.
+
+use std::{convert::TryInto, iter, ops::Range, rc::Rc, time::Duration};
+
+use super::{
+    display::{scroll_columns, ticks_for, Display, MAX_LEVEL, MAX_MATRICES},
+    heap::{Heap, Object},
+    random::Xorshift,
+    Execution, Fault, Host, Value,
+};
+
+#[derive(Copy, Clone)]
+enum Orientation {
+    Rows,
+    Columns,
+}
+
+/// Invoca un builtin por su nombre de símbolo.
+///
+/// Los builtins sin valor de retorno producen `0`.
+pub(super) fn call<H: Host>(
+    execution: &mut Execution<'_, H>,
+    name: &str,
+    args: &[isize],
+) -> Result<isize, Fault> {
+    let arg = |index: usize| args.get(index).copied().unwrap_or(0);
+    let flag = |index: usize| arg(index) != 0;
+
+    let name = name
+        .strip_prefix("builtin_")
+        .ok_or_else(|| Fault::UndefinedFunction(name.to_owned()))?;
+
+    if let Some(result) = timed(execution, name, args)? {
+        return Ok(result);
+    }
+
+    let Execution {
+        machine,
+        host,
+        line,
+        ..
+    } = execution;
+    let heap = &mut machine.heap;
+
+    let result = match name {
+        "debug" => debug(*host, arg(0), Value::Unit),
+        "debug_bool" => debug(*host, arg(0), Value::Bool(flag(1))),
+        "debug_int" => debug(*host, arg(0), Value::Int(arg(1))),
+        "debug_float" => debug(*host, arg(0), Value::Float(f32_from_ffi(arg(1)))),
+        "debug_list" => debug(*host, arg(0), Value::List(heap.list(arg(1))?)),
+        "debug_ilist" => debug(*host, arg(0), Value::IntList(heap.int_list(arg(1))?)),
+        "debug_str" => debug(*host, arg(0), Value::Str(heap.string(arg(1))?)),
+
+        "debug_mat" => {
+            let rows = heap
+                .mat(arg(1))?
+                .iter()
+                .map(|&row| heap.list(row).map(Vec::as_slice))
+                .collect::<Result<_, _>>()?;
+
+            debug(*host, arg(0), Value::Mat(rows))
+        }
+
+        "seed" => {
+            machine.random = Xorshift::new(arg(0) as u32);
+            0
+        }
+
+        "random" => match arg(0) {
+            max if max > 0 => machine.random.below(max as u32) as isize,
+            max => return Err(Fault::BadRandomBound(max)),
+        },
+
+        "uart_write_int" => {
+            host.uart_write(arg(0));
+            0
+        }
+
+        "uart_read_int" => host.uart_read().unwrap_or(-1),
+
+        // El heap del intérprete no tiene capacidad fija
+        "heap_used" => heap.bytes() as isize,
+        "heap_free" => isize::MAX,
+
+        // El reloj del intérprete arranca junto con el programa
+        "millis" => machine.now.as_millis().min(isize::MAX as u128) as isize,
+
+        // Sin arena, no hay nada que reiniciar
+        "frame_end" => 0,
+
+        "store" => {
+            machine.storage[slot(arg(0))?] = arg(1) as i32 as isize;
+            0
+        }
+
+        "load" => machine.storage[slot(arg(0))?],
+
+        "trap_div_zero" => {
+            *line = arg(0);
+            return Err(Fault::DivisionByZero);
+        }
+
+        "new_list" => heap.alloc(Object::List(Vec::new())),
+        "new_ilist" => heap.alloc(Object::IntList(Vec::new())),
+        "new_mat" => heap.alloc(Object::Mat(Vec::new())),
+        "new_str" => heap.alloc(Object::Str(String::new())),
+
+        "ref_list" | "ref_ilist" | "ref_mat" | "ref_str" => {
+            heap.retain(arg(0))?;
+            0
+        }
+        "drop_list" | "drop_ilist" | "drop_mat" | "drop_str" => {
+            heap.release(arg(0))?;
+            0
+        }
+
+        "eq_list" => (heap.list(arg(0))? == heap.list(arg(1))?) as isize,
+        "eq_ilist" => (heap.int_list(arg(0))? == heap.int_list(arg(1))?) as isize,
+        "eq_mat" => (rows(heap, arg(0))? == rows(heap, arg(1))?) as isize,
+        "eq_str" => (heap.string(arg(0))? == heap.string(arg(1))?) as isize,
+
+        "push_str" => {
+            let c = std::char::from_u32(arg(1) as u32).unwrap_or(std::char::REPLACEMENT_CHARACTER);
+            heap.string_mut(arg(0))?.push(c);
+            0
+        }
+
+        "concat_str" => {
+            let concat = format!("{}{}", heap.string(arg(0))?, heap.string(arg(1))?);
+            heap.alloc(Object::Str(concat))
+        }
+
+        "index_list" => {
+            let list = heap.list(arg(0))?;
+            list[index(arg(1), list.len())?] as isize
+        }
+
+        "index_ilist" => {
+            let list = heap.int_list(arg(0))?;
+            list[index(arg(1), list.len())?]
+        }
+
+        "index_entry_mat" => {
+            let row = row(heap, arg(0), arg(1))?;
+            let row = heap.list(row)?;
+            row[index(arg(2), row.len())?] as isize
+        }
+
+        "index_row_mat" => {
+            let row = row(heap, arg(0), arg(1))?;
+            heap.retain(row)?;
+            row
+        }
+
+        "index_column_mat" => {
+            let column = index(arg(1), shapec(heap, arg(0))?)?;
+            let column = rows(heap, arg(0))?
+                .into_iter()
+                .map(|row| row[column])
+                .collect();
+
+            heap.alloc(Object::List(column))
+        }
+
+        "insert_list" => {
+            let list = heap.list_mut(arg(0))?;
+            let position = position(arg(1), list.len())?;
+            list.insert(position, flag(2));
+            0
+        }
+
+        "insert_ilist" => {
+            let list = heap.int_list_mut(arg(0))?;
+            let position = position(arg(1), list.len())?;
+            list.insert(position, arg(2));
+            0
+        }
+
+        "insert_mat" => {
+            let (mat, vectors, mode) = (arg(0), arg(1), arg(2));
+            let length = length_along(heap, mat, orientation(mode)?)?;
+            let position = position(arg(3), length)?;
+            insert_in_mat(heap, mat, vectors, mode, position)?;
+            0
+        }
+
+        "insert_end_mat" => {
+            let (mat, vectors, mode) = (arg(0), arg(1), arg(2));
+            let length = length_along(heap, mat, orientation(mode)?)?;
+            insert_in_mat(heap, mat, vectors, mode, length)?;
+            0
+        }
+
+        "delete_list" => {
+            let list = heap.list_mut(arg(0))?;
+            let index = index(arg(1), list.len())?;
+            list.remove(index);
+            0
+        }
+
+        "delete_ilist" => {
+            let list = heap.int_list_mut(arg(0))?;
+            let index = index(arg(1), list.len())?;
+            list.remove(index);
+            0
+        }
+
+        "delete_mat" => {
+            let mat = arg(0);
+            let orientation = orientation(arg(2))?;
+            let index = index(arg(1), length_along(heap, mat, orientation)?)?;
+
+            match orientation {
+                Orientation::Rows => {
+                    let row = heap.mat_mut(mat)?.remove(index);
+                    heap.release(row)?;
+                }
+
+                Orientation::Columns => {
+                    for row in 0..heap.mat(mat)?.len() {
+                        heap.row_mut(mat, row)?.remove(index);
+                    }
+                }
+            }
+
+            0
+        }
+
+        "push_mat" => {
+            let (mat, item) = (arg(0), arg(1));
+            let length = heap.mat(mat)?.len();
+            insert_vectors(heap, mat, &[item], Orientation::Rows, length)?;
+            0
+        }
+
+        "len_list" => heap.list(arg(0))?.len() as isize,
+        "len_ilist" => heap.int_list(arg(0))?.len() as isize,
+
+        "slice_list" => {
+            let list = heap.list(arg(0))?;
+            let slice = list[range(arg(1), arg(2), list.len())?].to_vec();
+            heap.alloc(Object::List(slice))
+        }
+
+        "slice_ilist" => {
+            let list = heap.int_list(arg(0))?;
+            let slice = list[range(arg(1), arg(2), list.len())?].to_vec();
+            heap.alloc(Object::IntList(slice))
+        }
+
+        "slice_mat" => {
+            let mat = heap.mat(arg(0))?;
+            let slice = mat[range(arg(1), arg(2), mat.len())?].to_vec();
+            for &row in slice.iter() {
+                heap.retain(row)?;
+            }
+
+            heap.alloc(Object::Mat(slice))
+        }
+
+        "set_entry_list" => {
+            let list = heap.list_mut(arg(0))?;
+            let index = index(arg(1), list.len())?;
+            list[index] = flag(2);
+            0
+        }
+
+        "set_entry_ilist" => {
+            let list = heap.int_list_mut(arg(0))?;
+            let index = index(arg(1), list.len())?;
+            list[index] = arg(2);
+            0
+        }
+
+        "set_entry_mat" => {
+            let row = index(arg(1), heap.mat(arg(0))?.len())?;
+            let row = heap.row_mut(arg(0), row)?;
+            let column = index(arg(2), row.len())?;
+            row[column] = flag(3);
+            0
+        }
+
+        "set_row_mat" => {
+            let (mat, entry) = (arg(0), arg(2));
+            shape(shapec(heap, mat)?, heap.list(entry)?.len())?;
+
+            let rows = heap.mat_mut(mat)?;
+            let row = index(arg(1), rows.len())?;
+            let old = std::mem::replace(&mut rows[row], entry);
+
+            heap.retain(entry)?;
+            heap.release(old)?;
+            0
+        }
+
+        "set_column_mat" => {
+            let (mat, entry) = (arg(0), heap.list(arg(2))?.clone());
+            shape(heap.mat(mat)?.len(), entry.len())?;
+            let column = index(arg(1), shapec(heap, mat)?)?;
+
+            for (row, value) in entry.into_iter().enumerate() {
+                heap.row_mut(mat, row)?[column] = value;
+            }
+
+            0
+        }
+
+        "set_slice_list" => {
+            let values = heap.list(arg(3))?.clone();
+            let list = heap.list_mut(arg(0))?;
+
+            let range = range(arg(1), arg(2), list.len())?;
+            shape(range.len(), values.len())?;
+
+            list[range].copy_from_slice(&values);
+
+            0
+        }
+
+        "set_slice_ilist" => {
+            let values = heap.int_list(arg(3))?.clone();
+            let list = heap.int_list_mut(arg(0))?;
+
+            let range = range(arg(1), arg(2), list.len())?;
+            shape(range.len(), values.len())?;
+
+            list[range].copy_from_slice(&values);
+
+            0
+        }
+
+        "set_slice_mat" => {
+            let (mat, rows) = (arg(0), arg(3));
+            shape(shapec(heap, mat)?, shapec(heap, rows)?)?;
+
+            let range = range(arg(1), arg(2), heap.mat(mat)?.len())?;
+            let sources = heap.mat(rows)?.clone();
+            shape(range.len(), sources.len())?;
+
+            for (target, source) in range.zip(sources) {
+                heap.retain(source)?;
+                let old = std::mem::replace(&mut heap.mat_mut(mat)?[target], source);
+                heap.release(old)?;
+            }
+
+            0
+        }
+
+        "and_mat" => zip_mat(heap, arg(0), arg(1), |a, b| a & b)?,
+        "or_mat" => zip_mat(heap, arg(0), arg(1), |a, b| a | b)?,
+        "xor_mat" => zip_mat(heap, arg(0), arg(1), |a, b| a ^ b)?,
+
+        "shift_list" => {
+            let list = heap.list_mut(arg(0))?;
+
+            let vacated = shift(list, arg(1), flag(2));
+            list[vacated].iter_mut().for_each(|entry| *entry = false);
+            0
+        }
+
+        "shift_mat" => {
+            shift_mat(heap, arg(0), arg(1), arg(2), flag(3))?;
+            0
+        }
+
+        "transpose_mat" => {
+            transpose(heap, arg(0))?;
+            0
+        }
+
+        "rotate_mat" => {
+            let mat = arg(0);
+            match arg(1).rem_euclid(4) {
+                1 => {
+                    transpose(heap, mat)?;
+                    mirror(heap, mat, Orientation::Columns)?;
+                }
+
+                2 => {
+                    mirror(heap, mat, Orientation::Rows)?;
+                    mirror(heap, mat, Orientation::Columns)?;
+                }
+
+                3 => {
+                    transpose(heap, mat)?;
+                    mirror(heap, mat, Orientation::Rows)?;
+                }
+
+                _ => {
+                    heap.mat(mat)?;
+                }
+            }
+
+            0
+        }
+
+        "mirror_mat" => {
+            let mat = arg(0);
+            heap.mat(mat)?;
+
+            mirror(heap, mat, orientation(arg(1))?)?;
+            0
+        }
+
+        "shapef" => heap.mat(arg(0))?.len() as isize,
+        "shapec" => shapec(heap, arg(0))? as isize,
+        "range" => heap.alloc(Object::List(vec![flag(1); arg(0).max(0) as usize])),
+
+        "cast_int_float" => f32_to_ffi(arg(0) as f32),
+        "cast_float_int" => f32_from_ffi(arg(0)) as isize,
+        "div_int" => f32_to_ffi(arg(0) as f32 / arg(1) as f32),
+        "pow_int" => f32_to_ffi((arg(0) as f32).powf(arg(1) as f32)),
+
+        "add_float" => float(args, |a, b| a + b),
+        "sub_float" => float(args, |a, b| a - b),
+        "mul_float" => float(args, |a, b| a * b),
+        "div_float" => float(args, |a, b| a / b),
+        "pow_float" => float(args, f32::powf),
+
+        "cmp_float" => {
+            use std::cmp::Ordering::*;
+
+            match f32_from_ffi(arg(0))
+                .partial_cmp(&f32_from_ffi(arg(1)))
+                .unwrap_or(Greater)
+            {
+                Less => -1,
+                Equal => 0,
+                Greater => 1,
+            }
+        }
+
+        "blink_mil" => blink(machine, arg(0), arg(1), milliseconds(arg(2)), flag(3))?,
+        "blink_seg" => blink(machine, arg(0), arg(1), seconds(arg(2)), flag(3))?,
+        "blink_min" => blink(machine, arg(0), arg(1), minutes(arg(2)), flag(3))?,
+
+        "printled" => {
+            let (column, row) = (arg(0), arg(1));
+            check_display(&machine.display, row, column)?;
+            machine.display.light_mut(row, column).set(flag(2));
+            0
+        }
+
+        "printled_intensity" => {
+            let (column, row, level) = (arg(0), arg(1), arg(2));
+            check_display(&machine.display, row, column)?;
+
+            if !(0..=MAX_LEVEL as isize).contains(&level) {
+                return Err(Fault::BadIntensity(level));
+            }
+
+            machine
+                .display
+                .light_mut(row, column)
+                .set_level(level as u8);
+            0
+        }
+
+        "printledx_f" => {
+            let row = arg(0);
+            check_display(&machine.display, row, 0)?;
+
+            let width = machine.display.width();
+            for (column, value) in list_bits(machine.heap.list(arg(1))?, width) {
+                machine.display.light_mut(row, column).set(value);
+            }
+
+            0
+        }
+
+        "printledx_c" => {
+            let column = arg(0);
+            check_display(&machine.display, 0, column)?;
+
+            let height = machine.display.height();
+            for (row, value) in list_bits(machine.heap.list(arg(1))?, height) {
+                machine.display.light_mut(row, column).set(value);
+            }
+
+            0
+        }
+
+        "printledx_m" => {
+            if arg(0) != 0 {
+                return Err(Fault::BadDisplayIndex(arg(0)));
+            }
+
+            let rows = rows(&machine.heap, arg(1))?;
+            let empty = Vec::new();
+
+            let (width, height) = (machine.display.width(), machine.display.height());
+            let rows = rows.iter().chain(iter::repeat(&empty)).take(height);
+            for (row, bits) in rows.enumerate() {
+                for (column, value) in list_bits(bits, width) {
+                    machine.display.light_mut(row as isize, column).set(value);
+                }
+            }
+
+            0
+        }
+
+        "set_display_size" => {
+            let (width, height) = (arg(0), arg(1));
+            let matrices = |length: isize| match length {
+                length if length > 0 && length % 8 == 0 => Some(length as usize / 8),
+                _ => None,
+            };
+
+            match (matrices(width), matrices(height)) {
+                (Some(columns), Some(rows)) if columns * rows <= MAX_MATRICES => {
+                    machine.display.resize(width as usize, height as usize);
+                }
+
+                _ => return Err(Fault::BadDisplaySize { width, height }),
+            }
+
+            0
+        }
+
+        "present" => {
+            machine.display.present();
+            0
+        }
+
+        _ => mutator(heap, name, args)?,
+    };
+
+    Ok(result)
+}
+
+/// Implementa los builtins que esperan o planifican tareas.
+///
+/// Estos requieren la ejecución completa, ya que una espera puede
+/// ejecutar procedimientos del usuario. Retorna `None` si `name` no
+/// corresponde a uno de ellos.
+fn timed<H: Host>(
+    execution: &mut Execution<'_, H>,
+    name: &str,
+    args: &[isize],
+) -> Result<Option<isize>, Fault> {
+    let arg = |index: usize| args.get(index).copied().unwrap_or(0);
+
+    match name {
+        "delay_mil" => execution.delay(milliseconds(arg(0)))?,
+        "delay_seg" => execution.delay(seconds(arg(0)))?,
+        "delay_min" => execution.delay(minutes(arg(0)))?,
+        "frame_sync" => execution.frame_sync(milliseconds(arg(0)))?,
+
+        "after" => execution.schedule(arg(1), milliseconds(arg(0)), None)?,
+
+        "every" => {
+            let millis = arg(0);
+            if millis <= 0 {
+                return Err(Fault::BadPeriod(millis));
+            }
+
+            let period = milliseconds(millis);
+            execution.schedule(arg(1), period, Some(period))?;
+        }
+
+        "scroll_text" => {
+            let step = milliseconds(arg(1));
+            let width = execution.machine.display.width();
+            let columns = scroll_columns(execution.machine.heap.string(arg(0))?, width);
+
+            for frame in columns.windows(width).skip(1) {
+                execution.machine.display.draw_columns(frame);
+                execution.delay(step)?;
+            }
+        }
+
+        "play_frames" => {
+            const FRAME_SIZE: usize = 10;
+
+            let frames = execution.data.get(&arg(0)).ok_or(Fault::BadHandle)?;
+            let frames = Rc::clone(frames);
+            let len = (arg(1).max(0) as usize * FRAME_SIZE).min(frames.len());
+
+            loop {
+                for frame in frames[..len].chunks_exact(FRAME_SIZE) {
+                    let (rows, duration) = frame.split_at(8);
+                    let duration = u16::from_le_bytes([duration[0], duration[1]]);
+
+                    execution.machine.display.draw_rows(rows);
+                    execution.delay(Duration::from_millis(duration.into()))?;
+                }
+
+                if arg(2) == 0 {
+                    break;
+                }
+            }
+        }
+
+        _ => return Ok(None),
+    }
+
+    Ok(Some(0))
+}
+
+/// Implementa `builtin_{neg,f,t}_*`.
+fn mutator(heap: &mut Heap, name: &str, args: &[isize]) -> Result<isize, Fault> {
+    let arg = |index: usize| args.get(index).copied().unwrap_or(0);
+    let undefined = || Fault::UndefinedFunction(format!("builtin_{}", name));
+
+    let (op, target) = name.split_once('_').ok_or_else(undefined)?;
+    let op: fn(&mut bool) = match op {
+        "neg" => |entry| *entry = !*entry,
+        "f" => |entry| *entry = false,
+        "t" => |entry| *entry = true,
+        _ => return Err(undefined()),
+    };
+
+    let (list, mat) = (arg(0), arg(0));
+    match target {
+        "list" => heap.list_mut(list)?.iter_mut().for_each(op),
+        "mat" => {
+            let rows = heap.mat(mat)?.len();
+            mutate_rows(heap, mat, 0..rows, op)?;
+        }
+
+        "entry_list" => {
+            let list = heap.list_mut(list)?;
+            let index = index(arg(1), list.len())?;
+            op(&mut list[index]);
+        }
+
+        "entry_mat" => {
+            let row = index(arg(1), heap.mat(mat)?.len())?;
+            let row = heap.row_mut(mat, row)?;
+            let column = index(arg(2), row.len())?;
+            op(&mut row[column]);
+        }
+
+        "row_mat" => {
+            let row = index(arg(1), heap.mat(mat)?.len())?;
+            heap.row_mut(mat, row)?.iter_mut().for_each(op);
+        }
+
+        "column_mat" => {
+            let column = index(arg(1), shapec(heap, mat)?)?;
+            for row in 0..heap.mat(mat)?.len() {
+                op(&mut heap.row_mut(mat, row)?[column]);
+            }
+        }
+
+        "slice_list" => {
+            let list = heap.list_mut(list)?;
+            let range = range(arg(1), arg(2), list.len())?;
+            list[range].iter_mut().for_each(op);
+        }
+
+        "slice_mat" => {
+            let range = range(arg(1), arg(2), heap.mat(mat)?.len())?;
+            mutate_rows(heap, mat, range, op)?;
+        }
+
+        _ => return Err(undefined()),
+    }
+
+    Ok(0)
+}
+
+fn mutate_rows(
+    heap: &mut Heap,
+    mat: isize,
+    rows: Range<usize>,
+    op: fn(&mut bool),
+) -> Result<(), Fault> {
+    // Cada aparición de una fila compartida se copia y modifica una vez, igual que en el runtime
+    for row in rows {
+        heap.row_mut(mat, row)?.iter_mut().for_each(op);
+    }
+
+    Ok(())
+}
+
+fn debug<H: Host>(host: &mut H, line: isize, value: Value<'_>) -> isize {
+    host.debug(line, value);
+    0
+}
+
+fn float(args: &[isize], op: fn(f32, f32) -> f32) -> isize {
+    let arg = |index: usize| f32_from_ffi(args.get(index).copied().unwrap_or(0));
+    f32_to_ffi(op(arg(0), arg(1)))
+}
+
+fn blink(
+    machine: &mut super::Machine,
+    column: isize,
+    row: isize,
+    duration: Duration,
+    cond: bool,
+) -> Result<isize, Fault> {
+    check_display(&machine.display, row, column)?;
+
+    let ticks = if cond { ticks_for(duration) } else { 0 };
+    machine.display.light_mut(row, column).blink(ticks);
+
+    Ok(0)
+}
+
+/// Copia las filas de una matriz.
+fn rows(heap: &Heap, mat: isize) -> Result<Vec<Vec<bool>>, Fault> {
+    heap.mat(mat)?
+        .iter()
+        .map(|&row| heap.list(row).cloned())
+        .collect()
+}
+
+/// Obtiene el handle de una fila, validando el índice.
+fn row(heap: &Heap, mat: isize, row: isize) -> Result<isize, Fault> {
+    let rows = heap.mat(mat)?;
+    Ok(rows[index(row, rows.len())?])
+}
+
+fn shapec(heap: &Heap, mat: isize) -> Result<usize, Fault> {
+    match heap.mat(mat)?.first() {
+        Some(&row) => Ok(heap.list(row)?.len()),
+        None => Ok(0),
+    }
+}
+
+fn zip_mat(
+    heap: &mut Heap,
+    first: isize,
+    second: isize,
+    op: fn(bool, bool) -> bool,
+) -> Result<isize, Fault> {
+    let (first, second) = (rows(heap, first)?, rows(heap, second)?);
+    let columns = |rows: &[Vec<bool>]| rows.first().map(Vec::len).unwrap_or(0);
+
+    shape(first.len(), second.len())?;
+    shape(columns(&first), columns(&second))?;
+
+    let mat = first
+        .iter()
+        .zip(second.iter())
+        .map(|(first, second)| {
+            let row = first.iter().zip(second.iter());
+            heap.alloc(Object::List(row.map(|(&a, &b)| op(a, b)).collect()))
+        })
+        .collect();
+
+    Ok(heap.alloc(Object::Mat(mat)))
+}
+
+/// Equivalente a `shift()` del runtime.
+fn shift<T>(items: &mut [T], n: isize, wrap: bool) -> Range<usize> {
+    let length = items.len();
+    if length == 0 {
+        return 0..0;
+    }
+
+    let amount = if wrap {
+        n.unsigned_abs() % length
+    } else {
+        n.unsigned_abs().min(length)
+    };
+
+    let vacated = if n >= 0 {
+        items.rotate_right(amount);
+        0..amount
+    } else {
+        items.rotate_left(amount);
+        length - amount..length
+    };
+
+    if wrap {
+        0..0
+    } else {
+        vacated
+    }
+}
+
+fn shift_mat(heap: &mut Heap, mat: isize, dx: isize, dy: isize, wrap: bool) -> Result<(), Fault> {
+    let width = shapec(heap, mat)?;
+
+    if dy != 0 {
+        let rows = heap.mat_mut(mat)?;
+        let vacated = shift(rows, dy, wrap);
+
+        for row in vacated {
+            let blank = heap.alloc(Object::List(vec![false; width]));
+            let old = std::mem::replace(&mut heap.mat_mut(mat)?[row], blank);
+            heap.release(old)?;
+        }
+    }
+
+    if dx != 0 {
+        for row in 0..heap.mat(mat)?.len() {
+            let row = heap.row_mut(mat, row)?;
+
+            let vacated = shift(row, dx, wrap);
+            row[vacated].iter_mut().for_each(|entry| *entry = false);
+        }
+    }
+
+    Ok(())
+}
+
+fn transpose(heap: &mut Heap, mat: isize) -> Result<(), Fault> {
+    let rows = rows(heap, mat)?;
+    let columns = rows.first().map(Vec::len).unwrap_or(0);
+
+    let transposed = (0..columns)
+        .map(|column| heap.alloc(Object::List(rows.iter().map(|row| row[column]).collect())))
+        .collect();
+
+    for row in std::mem::replace(heap.mat_mut(mat)?, transposed) {
+        heap.release(row)?;
+    }
+
+    Ok(())
+}
+
+fn mirror(heap: &mut Heap, mat: isize, axis: Orientation) -> Result<(), Fault> {
+    match axis {
+        Orientation::Rows => heap.mat_mut(mat)?.reverse(),
+        Orientation::Columns => {
+            for row in 0..heap.mat(mat)?.len() {
+                heap.row_mut(mat, row)?.reverse();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn length_along(heap: &Heap, mat: isize, orientation: Orientation) -> Result<usize, Fault> {
+    match orientation {
+        Orientation::Rows => Ok(heap.mat(mat)?.len()),
+        Orientation::Columns => shapec(heap, mat),
+    }
+}
+
+fn insert_in_mat(
+    heap: &mut Heap,
+    mat: isize,
+    vectors: isize,
+    mode: isize,
+    index: usize,
+) -> Result<(), Fault> {
+    if mat == vectors {
+        return Err(Fault::SelfInsertion);
+    }
+
+    let vectors = heap.mat(vectors)?.clone();
+    insert_vectors(heap, mat, &vectors, orientation(mode)?, index)
+}
+
+fn insert_vectors(
+    heap: &mut Heap,
+    mat: isize,
+    vectors: &[isize],
+    orientation: Orientation,
+    index: usize,
+) -> Result<(), Fault> {
+    let row_count = heap.mat(mat)?.len();
+    let column_count = shapec(heap, mat)?;
+    let mut corrected_rows = false;
+
+    for &item in vectors.iter().rev() {
+        let item = heap.list(item)?.clone();
+
+        match orientation {
+            Orientation::Rows => {
+                if row_count > 0 {
+                    shape(column_count, item.len())?;
+                }
+
+                let row = heap.alloc(Object::List(item));
+                heap.mat_mut(mat)?.insert(index, row);
+            }
+
+            Orientation::Columns => {
+                if row_count > 0 {
+                    shape(row_count, item.len())?;
+                }
+
+                if row_count == 0 && !corrected_rows {
+                    for _ in 0..item.len() {
+                        let row = heap.alloc(Object::List(Vec::new()));
+                        heap.mat_mut(mat)?.push(row);
+                    }
+
+                    corrected_rows = true;
+                }
+
+                for (row, entry) in item.into_iter().enumerate() {
+                    heap.row_mut(mat, row)?.insert(index, entry);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn orientation(mode: isize) -> Result<Orientation, Fault> {
+    match mode {
+        0 => Ok(Orientation::Rows),
+        1 => Ok(Orientation::Columns),
+        _ => Err(Fault::BadMatMode(mode)),
+    }
+}
+
+fn list_bits(list: &[bool], length: usize) -> impl '_ + Iterator<Item = (isize, bool)> {
+    list.iter()
+        .copied()
+        .chain(iter::repeat(false))
+        .enumerate()
+        .map(|(i, value)| (i as isize, value))
+        .take(length)
+}
+
+fn index(index: isize, length: usize) -> Result<usize, Fault> {
+    match position(index, length)? {
+        index if index < length => Ok(index),
+        index => Err(Fault::OutOfBounds { index, length }),
+    }
+}
+
+fn position(index: isize, length: usize) -> Result<usize, Fault> {
+    if index < 0 {
+        return Err(Fault::NegativeIndex(index));
+    }
+
+    let index = index as usize;
+    if index > length {
+        return Err(Fault::OutOfBounds { index, length });
+    }
+
+    Ok(index)
+}
+
+fn range(from: isize, to: isize, length: usize) -> Result<Range<usize>, Fault> {
+    let (from, to) = (position(from, length)?, position(to, length)?);
+    if from > to {
+        return Err(Fault::BadRange { from, to, length });
+    }
+
+    Ok(from..to)
+}
+
+fn shape(expected: usize, found: usize) -> Result<(), Fault> {
+    if expected != found {
+        return Err(Fault::ShapeMismatch { expected, found });
+    }
+
+    Ok(())
+}
+
+fn slot(slot: isize) -> Result<usize, Fault> {
+    match slot.try_into() {
+        Ok(slot) if slot < super::STORAGE_SLOTS => Ok(slot),
+        _ => Err(Fault::BadSlot(slot)),
+    }
+}
+
+fn check_display(display: &Display, row: isize, column: isize) -> Result<(), Fault> {
+    let (width, height) = (display.width() as isize, display.height() as isize);
+    if !(0..height).contains(&row) || !(0..width).contains(&column) {
+        return Err(Fault::DisplayBounds { row, column });
+    }
+
+    Ok(())
+}
+
+fn milliseconds(millis: isize) -> Duration {
+    Duration::from_millis(millis.try_into().unwrap_or_default())
+}
+
+fn seconds(secs: isize) -> Duration {
+    Duration::from_secs(secs.try_into().unwrap_or_default())
+}
+
+fn minutes(mins: isize) -> Duration {
+    let mins: u64 = mins.try_into().unwrap_or_default();
+    Duration::from_secs(mins.saturating_mul(60))
+}
+
+fn f32_from_ffi(arg: isize) -> f32 {
+    f32::from_bits(arg as u32)
+}
+
+fn f32_to_ffi(float: f32) -> isize {
+    float.to_bits() as i32 as isize
+}
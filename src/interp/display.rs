@@ -0,0 +1,225 @@
+//! Matriz de LEDs simulada.
+//!
+//! Reproduce `runtime::matrix` con ticks de 10ms, al igual que la
+//! plataforma nativa.
+
+use std::{fmt, iter, time::Duration};
+
+use super::font;
+
+/// Duración de un tick del reloj.
+pub const TICK: Duration = Duration::from_millis(10);
+
+/// Intensidad máxima de un LED, como en `runtime::matrix`.
+pub const MAX_LEVEL: u8 = 15;
+
+/// Cantidad máxima de matrices de 8x8 encadenadas, como en `runtime::matrix`.
+pub const MAX_MATRICES: usize = 4;
+
+/// Luces de todas las matrices, fila por fila.
+type Frame = Vec<Light>;
+
+/// Estado de la pantalla, inicialmente una única matriz de 8x8.
+///
+/// Al igual que en `runtime::matrix`, el doble búfer se activa con el
+/// primer `present()`. Las consultas siempre observan el búfer frontal.
+pub struct Display {
+    front: Frame,
+    back: Option<Frame>,
+    width: usize,
+    height: usize,
+}
+
+impl Display {
+    /// Ancho de la pantalla en LEDs.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Alto de la pantalla en LEDs.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Estado de un LED.
+    pub fn light(&self, row: usize, col: usize) -> &Light {
+        &self.front[row * self.width + col]
+    }
+
+    /// Obtiene una fila de la primera matriz como bits, donde el más
+    /// significativo es la columna 0.
+    pub fn row_bits(&self, row: usize) -> u8 {
+        self.front[row * self.width..][..8]
+            .iter()
+            .fold(0, |acc, light| acc << 1 | light.on as u8)
+    }
+
+    pub(super) fn light_mut(&mut self, row: isize, col: isize) -> &mut Light {
+        let offset = row as usize * self.width + col as usize;
+        let frame = self.back.as_mut().unwrap_or(&mut self.front);
+        &mut frame[offset]
+    }
+
+    /// Cambia las dimensiones y apaga la pantalla por completo.
+    pub(super) fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+
+        self.front = vec![Light::default(); width * height];
+        if let Some(back) = &mut self.back {
+            *back = self.front.clone();
+        }
+    }
+
+    /// Muestra el búfer trasero, o bien activa el doble búfer.
+    pub(super) fn present(&mut self) {
+        match &self.back {
+            Some(back) => self.front = back.clone(),
+            None => self.back = Some(self.front.clone()),
+        }
+    }
+
+    /// Reemplaza el contenido por columnas de bits, como en
+    /// `runtime::matrix`.
+    pub(super) fn draw_columns(&mut self, columns: &[u8]) {
+        for (col, bits) in columns.iter().take(self.width).enumerate() {
+            for row in 0..self.height {
+                let light = self.light_mut(row as isize, col as isize);
+                *light = Light::default();
+                light.set(row < 8 && bits & 1 << row != 0);
+            }
+        }
+
+        if self.back.is_some() {
+            self.present();
+        }
+    }
+
+    /// Reemplaza el contenido de la primera matriz por ocho filas de
+    /// bits y apaga el resto, como en `runtime::matrix`.
+    pub(super) fn draw_rows(&mut self, rows: &[u8]) {
+        for row in 0..self.height {
+            let bits = rows.get(row).copied().filter(|_| row < 8).unwrap_or(0);
+            for col in 0..self.width {
+                let light = self.light_mut(row as isize, col as isize);
+                *light = Light::default();
+                light.set(col < 8 && bits & 0x80 >> col != 0);
+            }
+        }
+
+        if self.back.is_some() {
+            self.present();
+        }
+    }
+
+    pub(super) fn tick(&mut self) {
+        let frames = iter::once(&mut self.front).chain(self.back.as_mut());
+        for light in frames.flatten() {
+            light.tick();
+        }
+    }
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Display {
+            front: vec![Light::default(); 64],
+            back: None,
+            width: 8,
+            height: 8,
+        }
+    }
+}
+
+impl fmt::Display for Display {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.front.chunks(self.width) {
+            for light in row.iter() {
+                fmt.write_str(if light.on { "●" } else { "○" })?;
+            }
+
+            writeln!(fmt)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Un LED, posiblemente parpadeante.
+#[derive(Copy, Clone)]
+pub struct Light {
+    on: bool,
+    clock: u32,
+    interval: u32,
+    level: u8,
+}
+
+impl Light {
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// Intensidad visible, `0` si está apagado.
+    pub fn level(&self) -> u8 {
+        if self.on {
+            self.level
+        } else {
+            0
+        }
+    }
+
+    pub(super) fn set(&mut self, on: bool) {
+        self.on = on;
+        self.level = MAX_LEVEL;
+    }
+
+    /// Enciende el LED con una intensidad dada, o lo apaga si es cero.
+    pub(super) fn set_level(&mut self, level: u8) {
+        self.set(level > 0);
+        if level > 0 {
+            self.level = level;
+        }
+    }
+
+    /// Parpadea cada `interval` ticks, o deja de parpadear si es cero.
+    pub(super) fn blink(&mut self, interval: u32) {
+        self.clock = interval;
+        self.interval = interval;
+    }
+
+    fn tick(&mut self) {
+        match self.interval {
+            0 => (),
+
+            _ if self.clock <= 1 => {
+                self.clock = self.interval;
+                self.on = !self.on;
+            }
+
+            _ => self.clock -= 1,
+        }
+    }
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Light {
+            on: false,
+            clock: 0,
+            interval: 0,
+            level: MAX_LEVEL,
+        }
+    }
+}
+
+/// Columnas que recorre un texto desplazable, como en `runtime::matrix`.
+pub(super) fn scroll_columns(text: &str, width: usize) -> Vec<u8> {
+    let blank = || iter::repeat(0).take(width);
+
+    blank().chain(font::columns(text)).chain(blank()).collect()
+}
+
+/// Cantidad de ticks que equivalen a una duración.
+pub(super) fn ticks_for(duration: Duration) -> u32 {
+    (duration.as_millis() / TICK.as_millis()) as u32
+}
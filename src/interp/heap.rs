@@ -0,0 +1,192 @@
+//! Objetos con conteo de referencias.
+//!
+//! Equivale a los `Rc<List>`, `Rc<IntList>`, `Rc<Mat>` y `Rc<Str>` de
+//! `libruntime`.
+//! Las filas de una matriz son a su vez listas en el heap, por lo cual
+//! dos matrices (o una matriz y una lista) pueden compartir una fila
+//! de la misma forma que ocurre con `Rc::clone()` en el runtime. Al
+//! igual que con `Rc::make_mut()`, una matriz nunca modifica una fila
+//! compartida, sino que primero la reemplaza por una copia propia, ver
+//! [`Heap::row_mut()`].
+
+use super::Fault;
+
+/// Contenido de un objeto.
+pub enum Object {
+    List(Vec<bool>),
+    IntList(Vec<isize>),
+    Mat(Vec<isize>),
+    Str(String),
+}
+
+/// Heap de objetos, indexado por handles.
+///
+/// Un handle es el índice de su ranura más uno, de forma que `0`
+/// nunca se refiere a un objeto válido.
+#[derive(Default)]
+pub struct Heap {
+    slots: Vec<Option<(usize, Object)>>,
+    free: Vec<usize>,
+}
+
+impl Heap {
+    /// Crea un objeto con una única referencia.
+    pub fn alloc(&mut self, object: Object) -> isize {
+        let slot = Some((1, object));
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.slots[index] = slot;
+                index
+            }
+
+            None => {
+                self.slots.push(slot);
+                self.slots.len() - 1
+            }
+        };
+
+        index as isize + 1
+    }
+
+    /// Incrementa el conteo de referencias. El handle nulo se ignora.
+    pub fn retain(&mut self, handle: isize) -> Result<(), Fault> {
+        if handle != 0 {
+            self.slot(handle)?.0 += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Decrementa el conteo de referencias, liberando el objeto si
+    /// llega a cero. El handle nulo se ignora.
+    pub fn release(&mut self, handle: isize) -> Result<(), Fault> {
+        if handle == 0 {
+            return Ok(());
+        }
+
+        let (refs, _) = self.slot(handle)?;
+        *refs -= 1;
+
+        if *refs == 0 {
+            let index = handle as usize - 1;
+            let (_, object) = self.slots[index].take().unwrap();
+            self.free.push(index);
+
+            if let Object::Mat(rows) = object {
+                for row in rows {
+                    self.release(row)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Cantidad de objetos vivos.
+    pub fn live(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    /// Bytes que ocuparía el contenido de los objetos vivos en el runtime,
+    /// sin contar capacidad de reserva ni encabezados.
+    pub fn bytes(&self) -> usize {
+        let word = std::mem::size_of::<isize>();
+
+        self.slots
+            .iter()
+            .flatten()
+            .map(|(_, object)| match object {
+                Object::List(list) => list.len(),
+                Object::IntList(list) | Object::Mat(list) => list.len() * word,
+                Object::Str(string) => string.len(),
+            })
+            .sum()
+    }
+
+    pub fn list(&self, handle: isize) -> Result<&Vec<bool>, Fault> {
+        match self.object(handle)? {
+            Object::List(list) => Ok(list),
+            _ => Err(Fault::BadHandle),
+        }
+    }
+
+    pub fn list_mut(&mut self, handle: isize) -> Result<&mut Vec<bool>, Fault> {
+        match &mut self.slot(handle)?.1 {
+            Object::List(list) => Ok(list),
+            _ => Err(Fault::BadHandle),
+        }
+    }
+
+    pub fn int_list(&self, handle: isize) -> Result<&Vec<isize>, Fault> {
+        match self.object(handle)? {
+            Object::IntList(list) => Ok(list),
+            _ => Err(Fault::BadHandle),
+        }
+    }
+
+    pub fn int_list_mut(&mut self, handle: isize) -> Result<&mut Vec<isize>, Fault> {
+        match &mut self.slot(handle)?.1 {
+            Object::IntList(list) => Ok(list),
+            _ => Err(Fault::BadHandle),
+        }
+    }
+
+    pub fn mat(&self, handle: isize) -> Result<&Vec<isize>, Fault> {
+        match self.object(handle)? {
+            Object::Mat(mat) => Ok(mat),
+            _ => Err(Fault::BadHandle),
+        }
+    }
+
+    /// Fila de una matriz a modificar, copiándola antes si es compartida.
+    pub fn row_mut(&mut self, mat: isize, row: usize) -> Result<&mut Vec<bool>, Fault> {
+        let handle = self.mat(mat)?[row];
+        if self.slot(handle)?.0 == 1 {
+            return self.list_mut(handle);
+        }
+
+        let copy = self.list(handle)?.clone();
+        let copy = self.alloc(Object::List(copy));
+
+        self.mat_mut(mat)?[row] = copy;
+        self.release(handle)?;
+        self.list_mut(copy)
+    }
+
+    pub fn mat_mut(&mut self, handle: isize) -> Result<&mut Vec<isize>, Fault> {
+        match &mut self.slot(handle)?.1 {
+            Object::Mat(mat) => Ok(mat),
+            _ => Err(Fault::BadHandle),
+        }
+    }
+
+    pub fn string(&self, handle: isize) -> Result<&String, Fault> {
+        match self.object(handle)? {
+            Object::Str(string) => Ok(string),
+            _ => Err(Fault::BadHandle),
+        }
+    }
+
+    pub fn string_mut(&mut self, handle: isize) -> Result<&mut String, Fault> {
+        match &mut self.slot(handle)?.1 {
+            Object::Str(string) => Ok(string),
+            _ => Err(Fault::BadHandle),
+        }
+    }
+
+    fn object(&self, handle: isize) -> Result<&Object, Fault> {
+        let index = (handle as usize).wrapping_sub(1);
+        match self.slots.get(index) {
+            Some(Some((_, object))) => Ok(object),
+            _ => Err(Fault::BadHandle),
+        }
+    }
+
+    fn slot(&mut self, handle: isize) -> Result<&mut (usize, Object), Fault> {
+        let index = (handle as usize).wrapping_sub(1);
+        match self.slots.get_mut(index) {
+            Some(Some(slot)) => Ok(slot),
+            _ => Err(Fault::BadHandle),
+        }
+    }
+}
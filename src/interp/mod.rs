@@ -0,0 +1,638 @@
+//! Intérprete de representación intermedia.
+//!
+//! Este módulo ejecuta un [`Program`] directamente, sin pasar por
+//! generación de código, ensamblado ni enlazado. Su propósito es la
+//! evaluación interactiva de [`crate::repl`], por lo cual el estado de
+//! una [`Machine`] (globales, objetos y matriz de LEDs) persiste entre
+//! ejecuciones de distintos programas. También permite ejecutar
+//! programas completos en equipos sin `binutils` ni `gcc`.
+//!
+//! # Valores
+//! Locales y globales son celdas `isize`, al igual que en la plataforma
+//! nativa. Listas, matrices y strings viven en un heap propio con conteo
+//! de referencias y se representan en las celdas por medio de handles.
+//! El handle `0` es nulo, lo cual permite que una global aún sin valor
+//! se libere sin efecto alguno.
+//!
+//! # Builtins
+//! Las llamadas a funciones externas se resuelven en [`builtin`], el
+//! cual replica la semántica de `libruntime`, incluyendo sus errores en
+//! tiempo de ejecución. El reloj es virtual: un `delay()` no detiene el
+//! intérprete, sino que avanza el reloj en ticks de 10ms y notifica cada
+//! uno al [`Host`], quien decide qué hacer con el tiempo real.
+//! Del mismo modo, el almacenamiento persistente de `Store` y `Load`
+//! se simula en memoria y solo persiste entre ejecuciones.
+//!
+//! Los procedimientos planificados con `After` y `Every` se ejecutan
+//! durante estas esperas y al finalizar el programa, con las mismas
+//! reglas que `libruntime`. Las tareas pendientes no sobreviven a la
+//! ejecución que las planificó.
+
+use std::{collections::HashMap, fmt, rc::Rc, time::Duration};
+
+use thiserror::Error;
+
+use crate::ir::{ArithmeticOp, BinOp, Function, GeneratedFunction, Instruction, LogicOp, Program};
+
+mod builtin;
+mod display;
+mod heap;
+
+/// Misma fuente que utiliza `libruntime`.
+#[path = "../../runtime/src/font.rs"]
+mod font;
+
+/// Mismo generador que utiliza `libruntime`.
+#[path = "../../runtime/src/random.rs"]
+mod random;
+
+pub use display::{Display, Light, MAX_LEVEL, TICK};
+
+use heap::Heap;
+
+/// Profundidad máxima de la pila de llamadas.
+const MAX_DEPTH: usize = 2048;
+
+/// Cantidad de slots de `Store` y `Load`, igual que en `libruntime`.
+const STORAGE_SLOTS: usize = 16;
+
+/// Un error en tiempo de ejecución.
+#[derive(Error, Debug)]
+#[error("Runtime error at line {line}: {fault}")]
+pub struct Error {
+    pub line: isize,
+    pub fault: Fault,
+}
+
+/// Causa de un error en tiempo de ejecución.
+///
+/// Las primeras variantes corresponden a las de `libruntime`. Las
+/// demás señalan condiciones que en código nativo no se detectan.
+#[derive(Error, Debug)]
+pub enum Fault {
+    #[error("negative index {0}")]
+    NegativeIndex(isize),
+
+    #[error("index {index} is out of bounds for length {length}")]
+    OutOfBounds { index: usize, length: usize },
+
+    #[error("range [{from}:{to}] is invalid for length {length}")]
+    BadRange {
+        from: usize,
+        to: usize,
+        length: usize,
+    },
+
+    #[error("expected length {expected}, found {found}")]
+    ShapeMismatch { expected: usize, found: usize },
+
+    #[error("matrix mode must be 0 or 1, found {0}")]
+    BadMatMode(isize),
+
+    #[error("attempted to insert a matrix into itself")]
+    SelfInsertion,
+
+    #[error("display position [{row}, {column}] is out of bounds")]
+    DisplayBounds { row: isize, column: isize },
+
+    #[error(
+        "display size {width}x{height} is not a grid of up to {max} 8x8 matrices",
+        max = display::MAX_MATRICES
+    )]
+    BadDisplaySize { width: isize, height: isize },
+
+    #[error("PrintLedX(\"M\", index, ...) requires index 0, found {0}")]
+    BadDisplayIndex(isize),
+
+    #[error("intensity must be from 0 to {max}, found {0}", max = display::MAX_LEVEL)]
+    BadIntensity(isize),
+
+    #[error("period must be positive, found {0}ms")]
+    BadPeriod(isize),
+
+    #[error("random bound must be positive, found {0}")]
+    BadRandomBound(isize),
+
+    #[error("storage slot must be from 0 to {max}, found {0}", max = STORAGE_SLOTS - 1)]
+    BadSlot(isize),
+
+    #[error("division by zero")]
+    DivisionByZero,
+
+    #[error("use of an uninitialized object")]
+    BadHandle,
+
+    #[error("call stack exceeded {0} frames")]
+    StackOverflow(usize),
+
+    #[error("undefined function `{0}`")]
+    UndefinedFunction(String),
+
+    #[error("undefined label {0}")]
+    UndefinedLabel(u32),
+}
+
+/// Entorno que observa la ejecución.
+pub trait Host {
+    /// Reporta un valor mostrado con `debug()`.
+    fn debug(&mut self, line: isize, value: Value<'_>);
+
+    /// Notifica que transcurrió un tick del reloj virtual.
+    fn tick(&mut self, _display: &Display) {}
+
+    /// Recibe un entero enviado por el puerto serial.
+    fn uart_write(&mut self, _value: isize) {}
+
+    /// Provee el siguiente entero recibido por el puerto serial, si lo hay.
+    fn uart_read(&mut self) -> Option<isize> {
+        None
+    }
+}
+
+/// Un valor observable por el [`Host`].
+pub enum Value<'a> {
+    Unit,
+    Bool(bool),
+    Int(isize),
+    Float(f32),
+    List(&'a [bool]),
+    IntList(&'a [isize]),
+    Mat(Vec<&'a [bool]>),
+    Str(&'a str),
+}
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Unit => fmt.write_str("()"),
+            Value::Bool(value) => write!(fmt, "{:?}", value),
+            Value::Int(value) => write!(fmt, "{}", value),
+            Value::Float(value) => write!(fmt, "{}", value),
+            Value::List(list) => write!(fmt, "{:?}", list),
+            Value::IntList(list) => write!(fmt, "{:?}", list),
+            Value::Mat(mat) => write!(fmt, "{:?}", mat),
+            Value::Str(string) => fmt.write_str(string),
+        }
+    }
+}
+
+/// Estado persistente del intérprete.
+#[derive(Default)]
+pub struct Machine {
+    globals: HashMap<String, isize>,
+    heap: Heap,
+    display: Display,
+    random: random::Xorshift,
+    storage: [isize; STORAGE_SLOTS],
+    ticks: u32,
+    now: Duration,
+}
+
+impl Machine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Estado actual de la matriz de LEDs.
+    pub fn display(&self) -> &Display {
+        &self.display
+    }
+
+    /// Tiempo virtual transcurrido.
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    /// Cantidad de objetos que aún no han sido liberados.
+    pub fn live_objects(&self) -> usize {
+        self.heap.live()
+    }
+
+    /// Ejecuta una función sin argumentos de un programa.
+    ///
+    /// `entry` es el símbolo de la función, ya transformado según las
+    /// reglas de [`crate::semantic`]. Las globales del programa que ya
+    /// existían en ejecuciones anteriores conservan su valor.
+    pub fn run<H: Host>(
+        &mut self,
+        program: &Program,
+        entry: &str,
+        host: &mut H,
+    ) -> Result<(), Error> {
+        let functions: HashMap<&str, Compiled> = program
+            .code
+            .iter()
+            .map(|function| (function.name.as_str(), Compiled::new(function)))
+            .collect();
+
+        let mut execution = Execution {
+            machine: self,
+            functions: &functions,
+            host,
+            depth: 0,
+            data: HashMap::new(),
+            procedures: HashMap::new(),
+            tasks: Vec::new(),
+            running: false,
+            frame: None,
+            line: 0,
+        };
+
+        let result = match functions.get(entry) {
+            Some(function) => execution
+                .call(function, &[])
+                .and_then(|_| execution.run_pending()),
+
+            None => Err(Fault::UndefinedFunction(entry.to_owned())),
+        };
+
+        let line = execution.line;
+        result.map_err(|fault| Error { line, fault })
+    }
+
+    fn global(&self, name: &str) -> isize {
+        self.globals.get(name).copied().unwrap_or(0)
+    }
+
+    /// Avanza el reloj virtual, notificando cada tick completado.
+    fn delay<H: Host>(&mut self, duration: Duration, host: &mut H) {
+        let target = self.now + duration;
+        while TICK * (self.ticks + 1) <= target {
+            self.ticks += 1;
+            self.now = TICK * self.ticks;
+
+            self.display.tick();
+            host.tick(&self.display);
+        }
+
+        self.now = target;
+    }
+}
+
+/// Una función junto a la ubicación de sus etiquetas.
+struct Compiled<'a> {
+    function: &'a GeneratedFunction,
+    labels: HashMap<u32, usize>,
+    locals: usize,
+}
+
+impl<'a> Compiled<'a> {
+    fn new(function: &'a GeneratedFunction) -> Self {
+        let mut labels = HashMap::new();
+        let mut locals = function.parameters as usize;
+
+        for (index, instruction) in function.body.iter().enumerate() {
+            use Instruction::*;
+
+            let used = match instruction.as_ref() {
+                SetLabel(label) => {
+                    labels.insert(label.0, index);
+                    vec![]
+                }
+
+                Move(from, to) => vec![from, to],
+                JumpIfFalse(local, _) | LoadConst(_, local) | LoadGlobal(_, local) => vec![local],
+                LoadData(_, local) | LoadProcedure(_, local) => vec![local],
+                StoreGlobal(local, _) | Not(local) | Negate(local) => vec![local],
+                Kill(local) => vec![local],
+                Binary(lhs, _, rhs) => vec![lhs, rhs],
+                Call {
+                    target,
+                    arguments,
+                    output,
+                } => {
+                    let callee = match target {
+                        Function::Indirect(local) => Some(local),
+                        _ => None,
+                    };
+
+                    arguments
+                        .iter()
+                        .chain(output.iter())
+                        .chain(callee)
+                        .collect()
+                }
+                Return(value) => value.iter().collect(),
+                Jump(_) => vec![],
+            };
+
+            let max = used.into_iter().map(|local| local.0 as usize + 1).max();
+            locals = locals.max(max.unwrap_or(0));
+        }
+
+        Compiled {
+            function,
+            labels,
+            locals,
+        }
+    }
+
+    fn target(&self, label: u32) -> Result<usize, Fault> {
+        self.labels
+            .get(&label)
+            .copied()
+            .ok_or(Fault::UndefinedLabel(label))
+    }
+}
+
+/// Una ejecución en curso.
+struct Execution<'a, H> {
+    machine: &'a mut Machine,
+    functions: &'a HashMap<&'a str, Compiled<'a>>,
+    host: &'a mut H,
+    depth: usize,
+
+    /// Bloques de `LoadData` ya cargados, por dirección.
+    data: HashMap<isize, Rc<[u8]>>,
+
+    /// Procedimientos referidos por `LoadProcedure`, por dirección.
+    procedures: HashMap<isize, Rc<String>>,
+
+    /// Procedimientos planificados con `After` y `Every`.
+    tasks: Vec<Task>,
+
+    /// Si se está ejecutando una tarea, en cuyo caso no se inician otras.
+    running: bool,
+
+    /// Inicio del cuadro en curso de `loop at`, ver `frame_sync()`.
+    frame: Option<Duration>,
+
+    /// Línea de código fuente de la última instrucción ejecutada. A
+    /// diferencia de `builtin_line`, se conoce también fuera de las
+    /// llamadas a rutinas que pueden fallar.
+    line: isize,
+}
+
+/// Un procedimiento planificado.
+struct Task {
+    procedure: Rc<String>,
+    due: Duration,
+    period: Option<Duration>,
+}
+
+impl<H: Host> Execution<'_, H> {
+    fn call(&mut self, function: &Compiled, arguments: &[isize]) -> Result<isize, Fault> {
+        if self.depth == MAX_DEPTH {
+            return Err(Fault::StackOverflow(MAX_DEPTH));
+        }
+
+        self.depth += 1;
+        let result = self.execute(function, arguments);
+        self.depth -= 1;
+
+        result
+    }
+
+    /// Planifica el procedimiento cuya dirección es `address`.
+    fn schedule(
+        &mut self,
+        address: isize,
+        delay: Duration,
+        period: Option<Duration>,
+    ) -> Result<(), Fault> {
+        let procedure = self.procedures.get(&address).ok_or(Fault::BadHandle)?;
+
+        self.tasks.push(Task {
+            procedure: Rc::clone(procedure),
+            due: self.machine.now + delay,
+            period,
+        });
+
+        Ok(())
+    }
+
+    /// Avanza el reloj virtual, ejecutando las tareas que venzan mientras tanto.
+    fn delay(&mut self, duration: Duration) -> Result<(), Fault> {
+        let target = self.machine.now + duration;
+
+        loop {
+            self.run_due()?;
+
+            let now = self.machine.now;
+            if now >= target {
+                return Ok(());
+            }
+
+            let next = if self.running {
+                None
+            } else {
+                self.tasks.iter().map(|task| task.due).min()
+            };
+
+            let next = next.map_or(target, |next| next.min(target));
+            self.machine.delay(next - now, self.host);
+        }
+    }
+
+    /// Espera a que termine el cuadro en curso de `loop at`.
+    ///
+    /// El primer cuadro se considera iniciado en la primera llamada. Un
+    /// cuadro que excede `period` no se compensa acortando los siguientes.
+    fn frame_sync(&mut self, period: Duration) -> Result<(), Fault> {
+        let now = self.machine.now;
+        let deadline = self.frame.unwrap_or(now) + period;
+
+        if now < deadline {
+            self.delay(deadline - now)?;
+        }
+
+        self.frame = Some(deadline.max(now));
+        Ok(())
+    }
+
+    /// Ejecuta tareas hasta que no quede ninguna pendiente.
+    fn run_pending(&mut self) -> Result<(), Fault> {
+        let now = self.machine.now;
+        while let Some(next) = self
+            .tasks
+            .iter()
+            .map(|task| task.due.saturating_sub(now))
+            .min()
+        {
+            self.delay(next)?;
+        }
+
+        Ok(())
+    }
+
+    /// Ejecuta, en orden de vencimiento, cada tarea vencida.
+    fn run_due(&mut self) -> Result<(), Fault> {
+        while !self.running {
+            let now = self.machine.now;
+            let next = self
+                .tasks
+                .iter()
+                .enumerate()
+                .filter(|(_, task)| task.due <= now)
+                .min_by_key(|(_, task)| task.due);
+
+            let index = match next {
+                Some((index, _)) => index,
+                None => break,
+            };
+
+            let mut task = self.tasks.swap_remove(index);
+            let procedure = Rc::clone(&task.procedure);
+
+            if let Some(period) = task.period {
+                task.due += period;
+                self.tasks.push(task);
+            }
+
+            let functions = self.functions;
+            let callee = functions
+                .get(procedure.as_str())
+                .ok_or_else(|| Fault::UndefinedFunction(procedure.to_string()))?;
+
+            self.running = true;
+            let result = self.call(callee, &[]);
+            self.running = false;
+
+            result?;
+        }
+
+        Ok(())
+    }
+
+    fn execute(&mut self, function: &Compiled, arguments: &[isize]) -> Result<isize, Fault> {
+        use Instruction::*;
+
+        let mut locals = vec![0; function.locals];
+        locals[..arguments.len()].copy_from_slice(arguments);
+
+        let local = |local: &crate::ir::Local| local.0 as usize;
+
+        let mut next = 0;
+        while let Some(instruction) = function.function.body.get(next) {
+            next += 1;
+            self.line = instruction.location().start().line() as isize;
+
+            match instruction.as_ref() {
+                Move(from, to) => locals[local(to)] = locals[local(from)],
+                SetLabel(_) | Kill(_) => (),
+                Jump(label) => next = function.target(label.0)?,
+
+                JumpIfFalse(condition, label) => {
+                    if locals[local(condition)] == 0 {
+                        next = function.target(label.0)?;
+                    }
+                }
+
+                LoadConst(value, target) => locals[local(target)] = *value as isize,
+
+                LoadGlobal(global, target) => {
+                    locals[local(target)] = self.machine.global(global.as_ref());
+                }
+
+                LoadData(data, target) => {
+                    let address = data.as_ptr() as isize;
+                    self.data.insert(address, Rc::clone(data));
+                    locals[local(target)] = address;
+                }
+
+                LoadProcedure(procedure, target) => {
+                    let address = Rc::as_ptr(procedure) as isize;
+                    self.procedures.insert(address, Rc::clone(procedure));
+                    locals[local(target)] = address;
+                }
+
+                StoreGlobal(source, global) => {
+                    let value = locals[local(source)];
+                    self.machine
+                        .globals
+                        .insert(global.as_ref().to_owned(), value);
+                }
+
+                Not(target) => locals[local(target)] ^= 1,
+                Negate(target) => {
+                    let value = locals[local(target)] as i32;
+                    locals[local(target)] = value.wrapping_neg() as isize;
+                }
+
+                Binary(lhs, op, rhs) => {
+                    let (a, b) = (locals[local(lhs)], locals[local(rhs)]);
+                    locals[local(lhs)] = binary(a, *op, b)?;
+                }
+
+                Call {
+                    target,
+                    arguments,
+                    output,
+                } => {
+                    let arguments: Vec<isize> = arguments
+                        .iter()
+                        .map(|argument| locals[local(argument)])
+                        .collect();
+
+                    let result = match target {
+                        Function::External(name) => builtin::call(self, name, &arguments)?,
+
+                        Function::Generated(name) => {
+                            let functions = self.functions;
+                            let callee = functions
+                                .get(name.as_str())
+                                .ok_or_else(|| Fault::UndefinedFunction(name.to_string()))?;
+
+                            self.call(callee, &arguments)?
+                        }
+
+                        Function::Indirect(procedure) => {
+                            let address = locals[local(procedure)];
+                            let name = self.procedures.get(&address).ok_or(Fault::BadHandle)?;
+                            let name = Rc::clone(name);
+
+                            let functions = self.functions;
+                            let callee = functions
+                                .get(name.as_str())
+                                .ok_or_else(|| Fault::UndefinedFunction(name.to_string()))?;
+
+                            self.call(callee, &arguments)?
+                        }
+                    };
+
+                    if let Some(output) = output {
+                        locals[local(output)] = result;
+                    }
+                }
+
+                Return(value) => return Ok(value.map(|value| locals[local(&value)]).unwrap_or(0)),
+            }
+        }
+
+        Ok(0)
+    }
+}
+
+/// La aritmética es de 32 bits, al igual que en código compilado.
+fn binary(a: isize, op: BinOp, b: isize) -> Result<isize, Fault> {
+    use ArithmeticOp::*;
+    use LogicOp::*;
+
+    let (x, y) = (a as i32, b as i32);
+    let result = match op {
+        BinOp::Arithmetic(Add) => x.wrapping_add(y) as isize,
+        BinOp::Arithmetic(Sub) => x.wrapping_sub(y) as isize,
+        BinOp::Arithmetic(Mul) => x.wrapping_mul(y) as isize,
+
+        BinOp::Arithmetic(Div) | BinOp::Arithmetic(Mod) if b == 0 => {
+            return Err(Fault::DivisionByZero)
+        }
+
+        BinOp::Arithmetic(Div) => x.wrapping_div(y) as isize,
+        BinOp::Arithmetic(Mod) => x.wrapping_rem(y) as isize,
+        BinOp::Arithmetic(BitAnd) => a & b,
+        BinOp::Arithmetic(BitOr) => a | b,
+        BinOp::Arithmetic(BitXor) => a ^ b,
+        BinOp::Arithmetic(ShiftLeft) => x.wrapping_shl(y as u32) as isize,
+        BinOp::Arithmetic(ShiftRight) => x.wrapping_shr(y as u32) as isize,
+
+        BinOp::Logic(Equal) => (a == b) as isize,
+        BinOp::Logic(NotEqual) => (a != b) as isize,
+        BinOp::Logic(Less) => (a < b) as isize,
+        BinOp::Logic(LessOrEqual) => (a <= b) as isize,
+        BinOp::Logic(Greater) => (a > b) as isize,
+        BinOp::Logic(GreaterOrEqual) => (a >= b) as isize,
+    };
+
+    Ok(result)
+}
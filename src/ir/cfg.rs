@@ -0,0 +1,178 @@
+//! Grafo de control de flujo.
+//!
+//! [`Cfg::build()`] divide el cuerpo de una función en bloques básicos,
+//! es decir, secuencias de instrucciones que siempre se ejecutan
+//! completas y en orden. Un bloque inicia al comienzo de la función, en
+//! cada `SetLabel` y tras cada salto o `Return`. Los bloques se numeran
+//! en el orden en que aparecen en la función, por lo cual el bloque `0`
+//! es siempre el de entrada.
+//!
+//! Tanto el optimizador como la verificación de IR operan sobre este
+//! grafo. Es además público para que herramientas externas, como
+//! visualizadores o scripts de calificación, puedan analizar programas
+//! compilados. [`Cfg::dot()`] lo describe en el formato de Graphviz, el
+//! cual emite el compilador con `--dump-cfg`.
+
+use std::{collections::HashMap, fmt::Write, ops::Range};
+
+use super::{GeneratedFunction, Instruction, Label, Names};
+
+/// Un bloque básico.
+#[derive(Clone, Debug)]
+pub struct Block {
+    /// Posiciones de las instrucciones del bloque en la función.
+    pub instructions: Range<usize>,
+
+    /// Bloques a los que puede pasar el control al final de este.
+    pub successors: Vec<usize>,
+
+    /// Bloques desde los cuales se puede llegar a este.
+    pub predecessors: Vec<usize>,
+}
+
+/// Grafo de control de flujo de una función.
+#[derive(Clone, Debug)]
+pub struct Cfg {
+    blocks: Vec<Block>,
+}
+
+impl Cfg {
+    /// Construye el grafo de una función.
+    ///
+    /// Un salto a una etiqueta que no se establece en la función no
+    /// produce arista alguna, ver [`crate::target::verify_ir()`].
+    pub fn build(function: &GeneratedFunction) -> Self {
+        use Instruction::*;
+
+        let body = &function.body;
+
+        let mut starts = Vec::new();
+        let mut ended = true;
+
+        for (position, instruction) in body.iter().enumerate() {
+            if ended || matches!(instruction.as_ref(), SetLabel(_)) {
+                starts.push(position);
+            }
+
+            ended = matches!(instruction.as_ref(), Jump(_) | JumpIfFalse(..) | Return(_));
+        }
+
+        let ends = starts.iter().skip(1).copied().chain(Some(body.len()));
+        let mut blocks: Vec<_> = starts
+            .iter()
+            .zip(ends)
+            .map(|(start, end)| Block {
+                instructions: *start..end,
+                successors: Vec::new(),
+                predecessors: Vec::new(),
+            })
+            .collect();
+
+        let labels: HashMap<_, _> = blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, block)| {
+                let first = &body[block.instructions.start];
+                match first.as_ref() {
+                    SetLabel(Label(label)) => Some((*label, index)),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        let count = blocks.len();
+        for (index, block) in blocks.iter_mut().enumerate() {
+            let next = Some(index + 1).filter(|next| *next < count);
+            let target = |Label(label): Label| labels.get(&label).copied();
+
+            let last = body[block.instructions.end - 1].as_ref();
+            block.successors = match last {
+                Jump(label) => target(*label).into_iter().collect(),
+                JumpIfFalse(_, label) => next.into_iter().chain(target(*label)).collect(),
+                Return(_) => Vec::new(),
+                _ => next.into_iter().collect(),
+            };
+
+            block.successors.dedup();
+        }
+
+        let edges: Vec<_> = blocks
+            .iter()
+            .enumerate()
+            .flat_map(|(index, block)| block.successors.iter().map(move |to| (index, *to)))
+            .collect();
+
+        for (from, to) in edges {
+            blocks[to].predecessors.push(from);
+        }
+
+        Cfg { blocks }
+    }
+
+    /// Bloques del grafo, en el orden en que aparecen en la función.
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Bloque que contiene la instrucción en una posición dada.
+    pub fn block_at(&self, position: usize) -> Option<usize> {
+        let index = self
+            .blocks
+            .partition_point(|block| block.instructions.end <= position);
+
+        self.blocks
+            .get(index)
+            .filter(|block| block.instructions.contains(&position))
+            .map(|_| index)
+    }
+
+    /// Determina para cada bloque si es alcanzable desde la entrada.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut pending = if self.blocks.is_empty() {
+            Vec::new()
+        } else {
+            vec![0]
+        };
+
+        while let Some(index) = pending.pop() {
+            if !std::mem::replace(&mut reachable[index], true) {
+                pending.extend(self.blocks[index].successors.iter().copied());
+            }
+        }
+
+        reachable
+    }
+
+    /// Describe el grafo de `function`, a partir de la cual se construyó,
+    /// como un `digraph` de Graphviz. Cada nodo lista las instrucciones
+    /// de su bloque tal como en el listado de la función.
+    pub fn dot(&self, function: &GeneratedFunction) -> String {
+        let names = Names::of(function);
+        let mut dot = format!("digraph \"{}\" {{\n", escape(&function.name));
+        dot.push_str("\tnode [shape=box, fontname=monospace];\n");
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            let mut label = String::new();
+            for position in block.instructions.clone() {
+                let text = names.instruction(function.body[position].as_ref());
+                write!(label, "{:03x} {}\\l", position, escape(&text)).unwrap();
+            }
+
+            writeln!(dot, "\tb{} [label=\"{}\"];", index, label).unwrap();
+        }
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            for successor in &block.successors {
+                writeln!(dot, "\tb{} -> b{};", index, successor).unwrap();
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,348 @@
+//! Representación intermedia de código.
+//!
+//! La representación intermedia es lineal y recuerda ligeramente
+//! a lenguajes ensambladores. Idealmente, deben ser simple
+//! traducir un programa semánticamente analizado a representación
+//! intermedia, y a su vez debe ser simple traducir código IR al
+//! lenguaje ensamblador objetivo.
+//!
+//! # Locales
+//! Toda función generada dispone de un número de "locales", cada
+//! una de las cuales de identifica por índice. Las locales son celdas
+//! de memoria no tipadas cuyo tamaño es constante pero depende de la
+//! arquitectura objetivo. Las primeras locales corresponden uno a uno
+//! a los parámetros de la función.
+//!
+//! # Etiquetas
+//! El control de flujo se realiza a través de etiquetas y saltos. Las
+//! etiquetas, al igual que las locales, existen por el hecho de ser
+//! identificadas numéricamente y no se declaran de alguna otra manera.
+//! Todas las instrucciones de IR involucran a locales, etiquetas o
+//! ambas.
+//!
+//! # Símbolos
+//! Para este punto del proceso de compilación, tanto variables
+//! globales como funciones externas han sido reducidas a símbolos
+//! ensamblables.
+//!
+//! # Ubicaciones
+//! Cada instrucción conserva la ubicación del enunciado de código
+//! fuente que la originó, o bien la del procedimiento para aquellas
+//! que no corresponden a enunciado alguno. Las transformaciones sobre
+//! IR deben preservar estas ubicaciones, pero ninguna fase posterior
+//! depende de ellas para producir código correcto.
+//!
+//! # Control de flujo
+//! El grafo de control de flujo de una función, con bloques básicos
+//! como nodos, se construye en [`cfg`].
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    rc::Rc,
+};
+
+use crate::{semantic, source::Located};
+
+pub mod cfg;
+
+/// Un programa en representación intermedia.
+#[derive(Debug)]
+pub struct Program {
+    pub globals: Vec<Global>,
+    pub code: Vec<GeneratedFunction>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Function {
+    External(&'static str),
+    Generated(Rc<String>),
+
+    /// Función generada cuya dirección, obtenida mediante
+    /// [`Instruction::LoadProcedure`], se encuentra en una local.
+    Indirect(Local),
+}
+
+impl Function {
+    /// Símbolo de la función, excepto en llamadas indirectas.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Function::External(name) => Some(name),
+            Function::Generated(name) => Some(name),
+            Function::Indirect(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GeneratedFunction {
+    pub name: Rc<String>,
+    pub body: Vec<Located<Instruction>>,
+    pub parameters: u32,
+}
+
+/// Las etiquetas están constituidas por identificadores arbitrarios,
+/// no necesariamente secuenciales pero sí únicos.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Label(pub u32);
+
+/// Las locales se identifican por índices secuenciales.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct Local(pub u32);
+
+/// Una variable global se identifica únicamente por su símbolo.
+#[derive(Clone, Debug)]
+pub struct Global(Rc<String>);
+
+impl AsRef<str> for Global {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl<S: Into<String>> From<S> for Global {
+    fn from(string: S) -> Self {
+        Global(Rc::new(string.into()))
+    }
+}
+
+/// Operaciones aritméticas y de bits sobre enteros.
+///
+/// El conteo de `ShiftLeft` y `ShiftRight` debe encontrarse en `[0, 31]`,
+/// de lo contrario el resultado depende de la arquitectura. `ShiftRight`
+/// es un desplazamiento aritmético, es decir, preserva el signo.
+#[derive(Copy, Clone, Debug)]
+pub enum ArithmeticOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum LogicOp {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum BinOp {
+    Arithmetic(ArithmeticOp),
+    Logic(LogicOp),
+}
+
+/// Una instrucción de representación intermedia.
+#[derive(Clone, Debug)]
+pub enum Instruction {
+    /// Copia contenidos de una local a otra.
+    Move(Local, Local),
+
+    /// Establecer la ubicación de una etiqueta al punto donde ocurre
+    /// esta instrucción la secuencia del programa.
+    SetLabel(Label),
+
+    /// Saltar incondicionalmente a una etiqueta.
+    Jump(Label),
+
+    /// Saltar a una etiqueta si y solo si el valor de una local es cero.
+    /// De lo contrario, no se realiza ninguna acción.
+    JumpIfFalse(Local, Label),
+
+    /// Sobreescribir los contenidos de una local con una constante.
+    LoadConst(i32, Local),
+
+    /// Copiar los contenidos de una variable global a una local.
+    LoadGlobal(Global, Local),
+
+    /// Sobreescribir los contenidos de una local con la dirección de
+    /// un bloque de datos constantes. Los datos no se modifican durante
+    /// la ejecución y su alineamiento es el de una local.
+    LoadData(Rc<[u8]>, Local),
+
+    /// Sobreescribir los contenidos de una local con la dirección de
+    /// una función generada, la cual debe existir en el programa.
+    LoadProcedure(Rc<String>, Local),
+
+    /// Copiar los contenidos de una local a una variable global.
+    StoreGlobal(Local, Global),
+
+    Not(Local),
+
+    Negate(Local),
+
+    Binary(Local, BinOp, Local),
+
+    /// Llamar a una función, copiando los argumentos de las locales
+    /// indicadas para ese efecto. Opcionalmente, el valor de retorno
+    /// de la función se escribe a una local. Los contenidos de locales
+    /// se preservan tras llamadas a funciones arbitrarias.
+    Call {
+        target: Function,
+        arguments: Vec<Local>,
+        output: Option<Local>,
+    },
+
+    /// Finalizar la función en este punto, retornando opcionalmente
+    /// el valor de una local.
+    Return(Option<Local>),
+
+    /// Indica que el valor actual de una local no se volverá a leer,
+    /// ya que el análisis semántico la liberó. No tiene efecto sobre la
+    /// ejecución, pero permite a la generación de código desocupar el
+    /// registro que la contiene sin copiarla a memoria.
+    Kill(Local),
+}
+
+/// Listado legible de una función, con una instrucción por línea.
+///
+/// Las locales se escriben como `%n`, con `%n:arg` para los parámetros.
+/// Cada salto indica junto a su etiqueta la posición de la instrucción
+/// `SetLabel` correspondiente, o `?` si ninguna la ubica.
+impl Display for GeneratedFunction {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "[PROC {}/{}]", self.name, self.parameters)?;
+        match semantic::demangle(&self.name) {
+            Some(demangled) => writeln!(fmt, " {}", demangled)?,
+            None => writeln!(fmt)?,
+        }
+
+        let names = Names::of(self);
+        for (i, instruction) in self.body.iter().enumerate() {
+            let text = names.instruction(instruction.as_ref());
+            let start = instruction.location().start();
+            writeln!(fmt, "\t{:03x} {:<40} @ {}", i, text, start)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Nombres de locales y etiquetas de una función, ver la
+/// implementación de [`Display`] para [`GeneratedFunction`].
+struct Names {
+    parameters: u32,
+
+    /// Posición en la que se establece cada etiqueta.
+    labels: HashMap<u32, usize>,
+}
+
+impl Names {
+    fn of(function: &GeneratedFunction) -> Self {
+        let labels = function
+            .body
+            .iter()
+            .enumerate()
+            .filter_map(|(i, instruction)| match instruction.as_ref() {
+                Instruction::SetLabel(Label(label)) => Some((*label, i)),
+                _ => None,
+            })
+            .collect();
+
+        Names {
+            parameters: function.parameters,
+            labels,
+        }
+    }
+
+    fn local(&self, Local(local): Local) -> String {
+        if local < self.parameters {
+            format!("%{}:arg", local)
+        } else {
+            format!("%{}", local)
+        }
+    }
+
+    fn label(&self, Label(label): Label) -> String {
+        match self.labels.get(&label) {
+            Some(position) => format!("L{} ({:03x})", label, position),
+            None => format!("L{} (?)", label),
+        }
+    }
+
+    fn instruction(&self, instruction: &Instruction) -> String {
+        use Instruction::*;
+
+        match instruction {
+            Move(from, to) => format!("{} <- {}", self.local(*to), self.local(*from)),
+            SetLabel(Label(label)) => format!("L{}:", label),
+            Jump(label) => format!("jump {}", self.label(*label)),
+
+            JumpIfFalse(local, label) => {
+                format!("jump {} unless {}", self.label(*label), self.local(*local))
+            }
+
+            LoadConst(value, to) => format!("{} <- {}", self.local(*to), value),
+            LoadGlobal(global, to) => format!("{} <- {}", self.local(*to), global.as_ref()),
+            LoadData(data, to) => format!("{} <- data[{}]", self.local(*to), data.len()),
+            LoadProcedure(name, to) => format!("{} <- @{}", self.local(*to), name),
+            StoreGlobal(from, global) => format!("{} <- {}", global.as_ref(), self.local(*from)),
+            Not(local) => format!("{0} <- not {0}", self.local(*local)),
+            Negate(local) => format!("{0} <- -{0}", self.local(*local)),
+
+            Binary(lhs, op, rhs) => {
+                let lhs = self.local(*lhs);
+                format!("{} <- {} {} {}", lhs, lhs, op, self.local(*rhs))
+            }
+
+            Call {
+                target,
+                arguments,
+                output,
+            } => {
+                let arguments: Vec<_> = arguments.iter().map(|arg| self.local(*arg)).collect();
+                let target = match target {
+                    Function::Indirect(local) => format!("*{}", self.local(*local)),
+                    _ => target.name().unwrap_or_default().to_string(),
+                };
+
+                let call = format!("call {}({})", target, arguments.join(", "));
+                match output {
+                    Some(output) => format!("{} <- {}", self.local(*output), call),
+                    None => call,
+                }
+            }
+
+            Return(Some(local)) => format!("return {}", self.local(*local)),
+            Return(None) => String::from("return"),
+            Kill(local) => format!("kill {}", self.local(*local)),
+        }
+    }
+}
+
+impl Display for BinOp {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ArithmeticOp::*;
+        use LogicOp::*;
+
+        let op = match self {
+            BinOp::Arithmetic(Add) => "+",
+            BinOp::Arithmetic(Sub) => "-",
+            BinOp::Arithmetic(Mul) => "*",
+            BinOp::Arithmetic(Div) => "/",
+            BinOp::Arithmetic(Mod) => "%",
+            BinOp::Arithmetic(BitAnd) => "&",
+            BinOp::Arithmetic(BitOr) => "|",
+            BinOp::Arithmetic(BitXor) => "^",
+            BinOp::Arithmetic(ShiftLeft) => "<<",
+            BinOp::Arithmetic(ShiftRight) => ">>",
+            BinOp::Logic(Equal) => "==",
+            BinOp::Logic(NotEqual) => "<>",
+            BinOp::Logic(Less) => "<",
+            BinOp::Logic(LessOrEqual) => "<=",
+            BinOp::Logic(Greater) => ">",
+            BinOp::Logic(GreaterOrEqual) => ">=",
+        };
+
+        fmt.write_str(op)
+    }
+}
@@ -0,0 +1,265 @@
+//! Mapas de código fuente (`.ledmap`).
+//!
+//! Un mapa relaciona rangos de direcciones de un ejecutable enlazado
+//! con líneas del programa fuente, en ambos sentidos. Su propósito es
+//! traducir direcciones reportadas por un dispositivo que falló, como
+//! el contador de programa de un `HardFault`, a líneas de código.
+//!
+//! # Construcción
+//! Durante generación de código se marca el inicio de cada línea, es
+//! decir, cada punto en el que cambia la ubicación en código fuente de
+//! las instrucciones IR. Por cada función se emite una tabla a la
+//! sección no cargable [`SECTION`]:
+//!
+//! ```text
+//! .asciz "user_main"
+//! .4byte <marca> - user_main, <línea>
+//! ...
+//! .4byte 0xffffffff
+//! ```
+//!
+//! El ensamblador resuelve cada desplazamiento respecto al símbolo de
+//! la función. Luego de enlazar, la dirección final de cada función se
+//! obtiene del mapa de enlazado de GNU ld, ya que cada una vive en su
+//! propia sección `.text.<símbolo>`. Esto funciona aún para ejecutables
+//! despojados de símbolos.
+//!
+//! # Formato
+//! El archivo resultante es JSON compacto con la forma de [`SourceMap`].
+//! Los rangos de líneas son relativos al inicio de cada función.
+
+use std::{collections::HashMap, io, ops::Range};
+
+use serde::{Deserialize, Serialize};
+
+/// Sección que contiene las tablas de líneas.
+pub const SECTION: &str = ".ledmap";
+
+/// Marca el final de la tabla de una función.
+pub const END_OF_TABLE: u32 = 0xffff_ffff;
+
+/// Mapa de código fuente de un ejecutable.
+#[derive(Serialize, Deserialize)]
+pub struct SourceMap {
+    pub functions: Vec<FunctionMap>,
+}
+
+/// Ubicación final y tabla de líneas de una función.
+#[derive(Serialize, Deserialize)]
+pub struct FunctionMap {
+    pub symbol: String,
+    pub address: u64,
+    pub size: u64,
+    pub lines: Vec<LineRange>,
+}
+
+/// Rango de desplazamientos dentro de una función que pertenece a una línea.
+#[derive(Serialize, Deserialize)]
+pub struct LineRange {
+    pub start: u64,
+    pub end: u64,
+    pub line: u32,
+}
+
+/// Resultado de traducir una dirección.
+pub struct Location<'a> {
+    pub symbol: &'a str,
+    pub offset: u64,
+
+    /// Ausente si la dirección precede a la primera línea de la
+    /// función, como ocurre en su prólogo.
+    pub line: Option<u32>,
+}
+
+impl SourceMap {
+    /// Construye un mapa a partir del contenido de [`SECTION`] en el
+    /// ejecutable enlazado y del mapa de enlazado.
+    ///
+    /// Las funciones descartadas por `--gc-sections` no aparecen en el
+    /// mapa de enlazado, por lo cual se omiten.
+    pub fn build(table: &[u8], is_le: bool, linker_map: &str) -> io::Result<Self> {
+        let sections = text_sections(linker_map);
+        let mut functions = Vec::new();
+        let mut table = table;
+
+        while !table.is_empty() {
+            let name_end = table.iter().position(|byte| *byte == 0);
+            let name_end = name_end.ok_or_else(bad_table)?;
+
+            let symbol = std::str::from_utf8(&table[..name_end]).map_err(|_| bad_table())?;
+            table = &table[name_end + 1..];
+
+            let mut marks = Vec::new();
+            loop {
+                let offset = read_u32(&mut table, is_le)?;
+                if offset == END_OF_TABLE {
+                    break;
+                }
+
+                marks.push((offset as u64, read_u32(&mut table, is_le)?));
+            }
+
+            if let Some((address, size)) = sections.get(symbol) {
+                let ends = marks.iter().skip(1).map(|(offset, _)| *offset);
+                let lines = marks
+                    .iter()
+                    .zip(ends.chain(std::iter::once(*size)))
+                    .filter(|((start, _), end)| start < end)
+                    .map(|((start, line), end)| LineRange {
+                        start: *start,
+                        end,
+                        line: *line,
+                    })
+                    .collect();
+
+                functions.push(FunctionMap {
+                    symbol: symbol.to_owned(),
+                    address: *address,
+                    size: *size,
+                    lines,
+                });
+            }
+        }
+
+        functions.sort_by_key(|function| function.address);
+        Ok(SourceMap { functions })
+    }
+
+    /// Traduce una dirección a función, desplazamiento y línea.
+    pub fn lookup(&self, address: u64) -> Option<Location<'_>> {
+        let function = self.functions.iter().find(|function| {
+            address >= function.address && address < function.address + function.size
+        })?;
+
+        let offset = address - function.address;
+        let line = function
+            .lines
+            .iter()
+            .find(|range| offset >= range.start && offset < range.end)
+            .map(|range| range.line);
+
+        Some(Location {
+            symbol: &function.symbol,
+            offset,
+            line,
+        })
+    }
+
+    /// Rangos de direcciones que implementan una línea. Rangos contiguos
+    /// se combinan en uno solo.
+    pub fn addresses(&self, line: u32) -> Vec<Range<u64>> {
+        let ranges = self.functions.iter().flat_map(|function| {
+            function
+                .lines
+                .iter()
+                .filter(move |range| range.line == line)
+                .map(move |range| function.address + range.start..function.address + range.end)
+        });
+
+        let mut merged: Vec<Range<u64>> = Vec::new();
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => merged.push(range),
+            }
+        }
+
+        merged
+    }
+
+    /// Anota cada dirección hexadecimal de un texto que caiga dentro de
+    /// una función conocida, por ejemplo `0x08000a1c (user_main+0x1c, line 7)`.
+    pub fn annotate(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(start) = rest.find("0x") {
+            let digits = rest[start + 2..]
+                .find(|c: char| !c.is_ascii_hexdigit())
+                .unwrap_or(rest.len() - start - 2);
+
+            let end = start + 2 + digits;
+            output.push_str(&rest[..end]);
+
+            let location = u64::from_str_radix(&rest[start + 2..end], 16)
+                .ok()
+                .and_then(|address| self.lookup(address));
+
+            if let Some(location) = location {
+                output.push_str(&format!(" ({}+{:#x}", location.symbol, location.offset));
+                if let Some(line) = location.line {
+                    output.push_str(&format!(", line {}", line));
+                }
+
+                output.push(')');
+            }
+
+            rest = &rest[end..];
+        }
+
+        output.push_str(rest);
+        output
+    }
+}
+
+/// Direcciones y tamaños de las secciones `.text.<símbolo>` según un
+/// mapa de enlazado de GNU ld.
+///
+/// Una sección de entrada aparece indentada por un espacio. Si su nombre
+/// es largo, la dirección y el tamaño se encuentran en la línea siguiente.
+fn text_sections(linker_map: &str) -> HashMap<&str, (u64, u64)> {
+    // Antes de este encabezado se listan las secciones descartadas
+    let memory_map = linker_map
+        .find("Linker script and memory map")
+        .map_or(linker_map, |start| &linker_map[start..]);
+
+    let hex = |field: &str| u64::from_str_radix(field.strip_prefix("0x")?, 16).ok();
+
+    let mut sections = HashMap::new();
+    let mut lines = memory_map.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let mut fields = line.split_whitespace();
+        let symbol = match fields.next().and_then(|name| name.strip_prefix(".text.")) {
+            Some(symbol) if line.starts_with(' ') && !line.starts_with("  ") => symbol,
+            _ => continue,
+        };
+
+        let mut fields: Vec<&str> = fields.collect();
+        if fields.is_empty() {
+            fields = lines
+                .peek()
+                .map_or(vec![], |next| next.split_whitespace().collect());
+        }
+
+        if let [address, size, ..] = fields.as_slice() {
+            if let (Some(address), Some(size)) = (hex(address), hex(size)) {
+                if size > 0 {
+                    sections.insert(symbol, (address, size));
+                }
+            }
+        }
+    }
+
+    sections
+}
+
+fn read_u32(table: &mut &[u8], is_le: bool) -> io::Result<u32> {
+    if table.len() < 4 {
+        return Err(bad_table());
+    }
+
+    let (bytes, rest) = table.split_at(4);
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    *table = rest;
+
+    Ok(if is_le {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    })
+}
+
+fn bad_table() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed line table")
+}
@@ -3,7 +3,8 @@
 //! # Tokenization
 //! Esta es la primera fase del compilador. Descompone un [`InputStream`]
 //! (flujo de caracteres) en unidades léxicas denominadas tokens. Los espacios
-//! en blanco y los comentarios se descartan durante esta operación. Cada
+//! en blanco y los comentarios se descartan durante esta operación, aunque
+//! [`Lexer::with_comments()`] conserva a estos últimos. Cada
 //! token emitido esta asociado a una ubicación en el código fuente original,
 //! lo cual permite rastrear errores en tanto los mismos como constructos
 //! más elevados de fases posteriores.
@@ -17,7 +18,8 @@
 //!
 //! # Reglas importantes del lenguaje
 //! - Los identificadores tienen un límite de longitud.
-//! - Los identificadores pueden incluir `'@'` y `'?'`.
+//! - Los identificadores pueden incluir `'@'` y `'?'`. Un `'@'` inicial
+//!   no es parte de un identificador, sino el token [`Token::At`].
 //! - Los identificadores deben empezar con una letra minúscula.
 //! - Con excepción de la regla anterior, el lenguaje es case-insensitive,
 //!   por lo cual tanto `procedure` como `PROCEDURE` y `ProcEDure`
@@ -29,7 +31,10 @@
 //! ejecución, pero no lo suficiente como para permitir el avance a las
 //! demás fases de la compilación.
 
-use crate::source::{InputStream, Located, Location};
+use crate::{
+    error::Help,
+    source::{InputStream, Located, Location},
+};
 use std::{
     fmt::{self, Display},
     rc::Rc,
@@ -78,12 +83,34 @@ pub enum LexerError {
     /// Una constante entera se encuentra fuera de rango.
     #[error("Integer literal overflow, valid range is [0, {INT_MAX}]")]
     IntOverflow,
+
+    /// Un prefijo `0x` o `0b` no está seguido de dígitos.
+    #[error("Expected digits after radix prefix")]
+    MissingDigits,
+
+    /// Un dígito no es válido en la base de una constante entera.
+    #[error("Invalid digit {0:?} in base {1} literal")]
+    BadDigit(char, u32),
+
+    /// Un literal flotante termina en el punto decimal.
+    #[error("Expected digits after decimal point")]
+    MissingFraction,
     ///
     /// Se trató de comenzar un identificador con una letra mayúscula.
     #[error("Identifiers must begin with a lowercase letter")]
     UppercaseId,
 }
 
+impl Help for LexerError {
+    fn help(&self) -> Option<String> {
+        match self {
+            LexerError::BadEscape => Some(String::from(r#"valid escapes are `\"`, `\\` and `\n`"#)),
+            LexerError::UnterminatedString => Some(String::from(r#"close the string with `"`"#)),
+            _ => None,
+        }
+    }
+}
+
 /// Un identificador.
 ///
 /// Los identificadores cumplen ciertas reglas de contenido y longitud.
@@ -116,7 +143,7 @@ impl AsRef<NoCase<String>> for StrLiteral {
 ///
 /// Un token contiene suficiente información para describir completamente
 /// a una entidad léxica en el programa fuente.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     /// Identificador.
     Id(Identifier),
@@ -130,6 +157,9 @@ pub enum Token {
     /// Literal de entero.
     IntLiteral(i32),
 
+    /// Literal de punto flotante.
+    FloatLiteral(f32),
+
     /// `=`
     Assign,
 
@@ -184,6 +214,33 @@ pub enum Token {
     /// `>=`
     GreaterOrEqual,
 
+    /// `&&`
+    And,
+
+    /// `||`
+    Or,
+
+    /// `!`
+    Not,
+
+    /// `&`
+    BitAnd,
+
+    /// `|`
+    BitOr,
+
+    /// `^`
+    BitXor,
+
+    /// `<<`
+    ShiftLeft,
+
+    /// `>>`
+    ShiftRight,
+
+    /// `@`
+    At,
+
     /// `(`
     OpenParen,
 
@@ -201,6 +258,10 @@ pub enum Token {
 
     /// `}`
     CloseCurly,
+
+    /// Comentario de línea, sin el `##` inicial ni el fin de línea. Solo
+    /// se emite con [`Lexer::with_comments()`], y el parser lo ignora.
+    Comment(String),
 }
 
 impl Display for Token {
@@ -212,6 +273,7 @@ impl Display for Token {
             Keyword(keyword) => write!(fmt, "keyword `{}`", keyword),
             StrLiteral(string) => write!(fmt, "literal \"{}\"", string.0),
             IntLiteral(integer) => write!(fmt, "literal `{}`", integer),
+            FloatLiteral(float) => write!(fmt, "literal `{:?}`", float),
             Assign => fmt.write_str("`=`"),
             Comma => fmt.write_str("`,`"),
             Period => fmt.write_str("`.`"),
@@ -230,12 +292,22 @@ impl Display for Token {
             LessOrEqual => fmt.write_str("`<=`"),
             Greater => fmt.write_str("`>`"),
             GreaterOrEqual => fmt.write_str("`>=`"),
+            And => fmt.write_str("`&&`"),
+            Or => fmt.write_str("`||`"),
+            Not => fmt.write_str("`!`"),
+            BitAnd => fmt.write_str("`&`"),
+            BitOr => fmt.write_str("`|`"),
+            BitXor => fmt.write_str("`^`"),
+            ShiftLeft => fmt.write_str("`<<`"),
+            ShiftRight => fmt.write_str("`>>`"),
+            At => fmt.write_str("`@`"),
             OpenParen => fmt.write_str("`(`"),
             OpenSquare => fmt.write_str("`[`"),
             OpenCurly => fmt.write_str("`{`"),
             CloseParen => fmt.write_str("`)`"),
             CloseSquare => fmt.write_str("]`"),
             CloseCurly => fmt.write_str("`}`"),
+            Comment(_) => fmt.write_str("comment"),
         }
     }
 }
@@ -247,24 +319,53 @@ pub enum Keyword {
     False,
     Type,
     Float,
+    String,
     List,
+    IntList,
     Bool,
     Mat,
     Int,
     If,
+    Elif,
+    Else,
     For,
     In,
     Step,
+    Loop,
+    At,
     Len,
     Range,
+    Random,
+    Load,
+    UartRead,
+    HeapUsed,
+    HeapFree,
+    Millis,
     Call,
     Global,
+    Const,
+    Enum,
+    Import,
     Procedure,
+    Ref,
+    Return,
+    Break,
+    Continue,
     Debug,
     Blink,
     Delay,
     PrintLed,
     PrintLedX,
+    PrintLedB,
+    PrintText,
+    Show,
+    Play,
+    DisplaySize,
+    After,
+    Every,
+    Seed,
+    Store,
+    UartWrite,
 }
 
 impl Display for Keyword {
@@ -275,24 +376,53 @@ impl Display for Keyword {
             False => "false",
             Type => "type",
             Float => "float",
+            String => "string",
             List => "list",
+            IntList => "ilist",
             Bool => "bool",
             Mat => "mat",
             Int => "int",
             If => "if",
+            Elif => "elif",
+            Else => "else",
             For => "for",
             In => "in",
+            Loop => "loop",
+            At => "at",
             Len => "len",
             Range => "range",
+            Random => "Random",
+            Load => "Load",
+            UartRead => "UartRead",
+            HeapUsed => "HeapUsed",
+            HeapFree => "HeapFree",
+            Millis => "Millis",
             Step => "step",
             Call => "call",
             Global => "global",
+            Const => "const",
+            Enum => "enum",
+            Import => "import",
             Procedure => "procedure",
+            Ref => "ref",
+            Return => "return",
+            Break => "break",
+            Continue => "continue",
             Debug => "debug",
             Blink => "blink",
             Delay => "delay",
             PrintLed => "PrintLed",
             PrintLedX => "PrintLedX",
+            PrintLedB => "PrintLedB",
+            PrintText => "PrintText",
+            Show => "Show",
+            Play => "Play",
+            DisplaySize => "DisplaySize",
+            After => "After",
+            Every => "Every",
+            Seed => "Seed",
+            Store => "Store",
+            UartWrite => "UartWrite",
         };
 
         fmt.write_str(string)
@@ -310,24 +440,53 @@ impl FromStr for Keyword {
             (NoCase::new("false"), False),
             (NoCase::new("type"), Type),
             (NoCase::new("float"), Float),
+            (NoCase::new("string"), String),
             (NoCase::new("list"), List),
+            (NoCase::new("ilist"), IntList),
             (NoCase::new("bool"), Bool),
             (NoCase::new("mat"), Mat),
             (NoCase::new("int"), Int),
             (NoCase::new("if"), If),
+            (NoCase::new("elif"), Elif),
+            (NoCase::new("else"), Else),
             (NoCase::new("for"), For),
             (NoCase::new("in"), In),
+            (NoCase::new("loop"), Loop),
+            (NoCase::new("at"), At),
             (NoCase::new("len"), Len),
             (NoCase::new("range"), Range),
+            (NoCase::new("Random"), Random),
+            (NoCase::new("Load"), Load),
+            (NoCase::new("UartRead"), UartRead),
+            (NoCase::new("HeapUsed"), HeapUsed),
+            (NoCase::new("HeapFree"), HeapFree),
+            (NoCase::new("Millis"), Millis),
             (NoCase::new("step"), Step),
             (NoCase::new("call"), Call),
             (NoCase::new("global"), Global),
+            (NoCase::new("const"), Const),
+            (NoCase::new("enum"), Enum),
+            (NoCase::new("import"), Import),
             (NoCase::new("procedure"), Procedure),
+            (NoCase::new("ref"), Ref),
+            (NoCase::new("return"), Return),
+            (NoCase::new("break"), Break),
+            (NoCase::new("continue"), Continue),
             (NoCase::new("debug"), Debug),
             (NoCase::new("Blink"), Blink),
             (NoCase::new("Delay"), Delay),
             (NoCase::new("PrintLed"), PrintLed),
             (NoCase::new("PrintLedX"), PrintLedX),
+            (NoCase::new("PrintLedB"), PrintLedB),
+            (NoCase::new("PrintText"), PrintText),
+            (NoCase::new("Show"), Show),
+            (NoCase::new("Play"), Play),
+            (NoCase::new("DisplaySize"), DisplaySize),
+            (NoCase::new("After"), After),
+            (NoCase::new("Every"), Every),
+            (NoCase::new("Seed"), Seed),
+            (NoCase::new("Store"), Store),
+            (NoCase::new("UartWrite"), UartWrite),
         ];
 
         KEYWORDS
@@ -349,6 +508,7 @@ pub struct Lexer<S: Iterator> {
     state: State,
     start: Location,
     next: Location,
+    comments: bool,
 }
 
 /// Posibles estados del lexer.
@@ -385,29 +545,55 @@ enum State {
 
     /// Se encontró `<`.
     ///
-    /// Puede resultar en [`Token::Less`], [`Token::LessOrEqual`]
-    /// o [`Token::NotEqual`].
+    /// Puede resultar en [`Token::Less`], [`Token::LessOrEqual`],
+    /// [`Token::NotEqual`] o [`Token::ShiftLeft`].
     LeftAngle,
 
     /// Se encontró `>`.
     ///
-    /// Puede resultar en [`Token::Greater`] o [`Token::GreaterOrEqual`].
+    /// Puede resultar en [`Token::Greater`], [`Token::GreaterOrEqual`]
+    /// o [`Token::ShiftRight`].
     RightAngle,
 
+    /// Se encontró `&`.
+    ///
+    /// Puede resultar en [`Token::BitAnd`] o [`Token::And`].
+    Ampersand,
+
+    /// Se encontró `|`.
+    ///
+    /// Puede resultar en [`Token::BitOr`] o [`Token::Or`].
+    Pipe,
+
     /// Comentario de línea.
     ///
     /// Este estado vuelve a [`State::Start`] al encontrar `'\n'`.
-    Comment,
+    /// Contiene el texto que sigue a `##`.
+    Comment(String),
 
     /// Constante entera.
     ///
-    /// Este estado incluirá dígitos en el token mientras que
-    /// el siguiente carácter sea un dígito.
-    Integer(i32),
+    /// Este estado incluirá dígitos en el token mientras que el
+    /// siguiente carácter sea un dígito en la base `radix`. Un `0`
+    /// inicial seguido de `x` o `b` cambia la base a 16 o 2,
+    /// respectivamente. En base 10, un `.` inicia la parte
+    /// fraccionaria de una constante flotante.
+    Integer { value: i32, radix: u32, digits: u32 },
+
+    /// Parte fraccionaria de una constante flotante.
+    ///
+    /// Contiene el texto del literal, incluyendo el punto decimal.
+    /// Debe seguir al menos un dígito al punto.
+    Fraction(String),
 
     /// Literal de cadena.
     StringChars(String),
 
+    /// Se encontró `\` dentro de un literal de cadena.
+    ///
+    /// El siguiente carácter determina la secuencia de escape.
+    StringEscape(String),
+
     /// Término que puede ser un identificador o una palabra clave.
     Word(String),
 }
@@ -421,9 +607,17 @@ impl<S: InputStream> Lexer<S> {
             state: State::Start,
             start,
             next,
+            comments: false,
         }
     }
 
+    /// Emite cada comentario como un [`Token::Comment`] en vez de
+    /// descartarlo, para herramientas que deben preservarlos.
+    pub fn with_comments(mut self) -> Self {
+        self.comments = true;
+        self
+    }
+
     /// Reduce la entrada a sea una secuencia conocida de tokens
     /// infalibles o una secuencia de errores.
     ///
@@ -498,12 +692,17 @@ impl<S: InputStream> Lexer<S> {
                 (Start, Some(')')) => self.state = Complete(CloseParen),
                 (Start, Some(']')) => self.state = Complete(CloseSquare),
                 (Start, Some('}')) => self.state = Complete(CloseCurly),
+                (Start, Some('!')) => self.state = Complete(Not),
+                (Start, Some('^')) => self.state = Complete(BitXor),
+                (Start, Some('@')) => self.state = Complete(At),
                 (Start, Some('#')) => self.state = Hash,
                 (Start, Some('*')) => self.state = Star,
                 (Start, Some('/')) => self.state = Slash,
                 (Start, Some('=')) => self.state = AssignOrEqual,
                 (Start, Some('<')) => self.state = LeftAngle,
                 (Start, Some('>')) => self.state = RightAngle,
+                (Start, Some('&')) => self.state = Ampersand,
+                (Start, Some('|')) => self.state = Pipe,
 
                 // Cadenas, identificadores y palabras clave
                 (Start, Some('"')) => self.state = StringChars(String::new()),
@@ -515,7 +714,11 @@ impl<S: InputStream> Lexer<S> {
                 // entera para el cual el siguiente carácter es un
                 // dígito. Por tanto, la constante es inicialmente cero.
                 (Start, Some(c)) if c.is_ascii_digit() => {
-                    self.state = Integer(0);
+                    self.state = Integer {
+                        value: 0,
+                        radix: 10,
+                        digits: 0,
+                    };
                     continue;
                 }
 
@@ -527,7 +730,7 @@ impl<S: InputStream> Lexer<S> {
                 (Complete(value), _) => break Ok(std::mem::replace(value, Plus)),
 
                 // `#` siempre debería iniciar un comentario de la forma `##`
-                (Hash, Some('#')) => self.state = Comment,
+                (Hash, Some('#')) => self.state = State::Comment(String::new()),
                 (Hash, _) => break Err(LexerError::Expected('#')),
 
                 // Multiplicación `*` y potencia `**`
@@ -542,35 +745,102 @@ impl<S: InputStream> Lexer<S> {
                 (AssignOrEqual, Some('=')) => self.state = Complete(Equal),
                 (AssignOrEqual, _) => break Ok(Assign),
 
-                // Comparaciones `<` y `<=` y desigualdad `<>`
+                // Comparaciones `<` y `<=`, desigualdad `<>` y desplazamiento `<<`
                 (LeftAngle, Some('=')) => self.state = Complete(LessOrEqual),
                 (LeftAngle, Some('>')) => self.state = Complete(NotEqual),
+                (LeftAngle, Some('<')) => self.state = Complete(ShiftLeft),
                 (LeftAngle, _) => break Ok(Less),
 
-                // Comparaciones `>` y `>=`
+                // Comparaciones `>` y `>=` y desplazamiento `>>`
                 (RightAngle, Some('=')) => self.state = Complete(GreaterOrEqual),
+                (RightAngle, Some('>')) => self.state = Complete(ShiftRight),
                 (RightAngle, _) => break Ok(Greater),
 
+                // Conectivos lógicos `&&` y `||`, operaciones de bits `&` y `|`
+                (Ampersand, Some('&')) => self.state = Complete(And),
+                (Ampersand, _) => break Ok(BitAnd),
+                (Pipe, Some('|')) => self.state = Complete(Or),
+                (Pipe, _) => break Ok(BitOr),
+
                 // Los comentarios descartan la línea donde ocurren
-                (Comment, Some('\n')) => self.state = Start,
-                (Comment, Some(_)) => (),
-                (Comment, None) => self.state = Start,
+                (State::Comment(text), Some('\n')) | (State::Comment(text), None) if self.comments => {
+                    break Ok(Token::Comment(std::mem::take(text)));
+                }
 
-                // Acumulación dígito por dígito de constantes enteras
-                (Integer(accumulated), Some(digit)) if digit.is_ascii_digit() => {
-                    let digit = digit.to_digit(10).unwrap() as i32;
+                (State::Comment(_), Some('\n')) | (State::Comment(_), None) => self.state = Start,
+                (State::Comment(text), Some(c)) => {
+                    if self.comments {
+                        text.push(c);
+                    }
+                }
+
+                // Prefijos de base hexadecimal `0x` y binaria `0b`
+                (
+                    Integer {
+                        value: 0,
+                        radix: 10,
+                        digits: 1,
+                    },
+                    Some(c @ ('x' | 'X' | 'b' | 'B')),
+                ) => {
+                    let radix = if c.eq_ignore_ascii_case(&'x') { 16 } else { 2 };
+                    self.state = Integer {
+                        value: 0,
+                        radix,
+                        digits: 0,
+                    };
+                }
 
-                    match accumulated
-                        .checked_mul(10)
+                // Acumulación dígito por dígito de constantes enteras
+                (
+                    Integer {
+                        value,
+                        radix,
+                        digits,
+                    },
+                    Some(c),
+                ) if c.is_digit(*radix) => {
+                    let digit = c.to_digit(*radix).unwrap() as i32;
+
+                    match value
+                        .checked_mul(*radix as i32)
                         .and_then(|n| n.checked_add(digit))
                     {
-                        Some(result) => *accumulated = result,
+                        Some(result) => *value = result,
                         None => break Err(LexerError::IntOverflow),
                     }
+
+                    *digits += 1;
                 }
 
+                // Un prefijo de base debe estar seguido de al menos un dígito
+                (Integer { digits: 0, .. }, _) => break Err(LexerError::MissingDigits),
+
+                // `0b12` no debe leerse como `0b1` seguido de `2`, ni `0xfg` como `0xf g`
+                (Integer { radix, .. }, Some(c)) if *radix != 10 && c.is_ascii_alphanumeric() => {
+                    break Err(LexerError::BadDigit(c, *radix))
+                }
+
+                // Punto decimal de una constante flotante
+                (
+                    Integer {
+                        value, radix: 10, ..
+                    },
+                    Some('.'),
+                ) => self.state = Fraction(format!("{}.", value)),
+
                 // Si sigue algo que no es un dígito, la constante a terminado
-                (Integer(integer), _) => break Ok(IntLiteral(*integer)),
+                (Integer { value, .. }, _) => break Ok(IntLiteral(*value)),
+
+                // Dígitos de la parte fraccionaria
+                (Fraction(text), Some(digit)) if digit.is_ascii_digit() => text.push(digit),
+                (Fraction(text), _) if text.ends_with('.') => {
+                    break Err(LexerError::MissingFraction)
+                }
+
+                // La parte entera nunca excede INT_MAX, por lo que el
+                // resultado siempre es finito
+                (Fraction(text), _) => break Ok(FloatLiteral(text.parse().unwrap())),
 
                 // Fin de literales de cadena
                 (StringChars(string), Some('"')) => {
@@ -579,10 +849,22 @@ impl<S: InputStream> Lexer<S> {
                 }
 
                 // Casos entre comillas para literales de cadena
-                (StringChars(_), Some('\\')) => break Err(LexerError::BadEscape),
+                (StringChars(string), Some('\\')) => {
+                    self.state = StringEscape(std::mem::take(string));
+                }
+
                 (StringChars(string), Some(c)) if is_string_char(c) => string.push(c),
                 (StringChars(_), _) => break Err(LexerError::UnterminatedString),
 
+                // Secuencias de escape
+                (StringEscape(string), Some(c @ ('"' | '\\' | 'n'))) => {
+                    string.push(if c == 'n' { '\n' } else { c });
+                    self.state = StringChars(std::mem::take(string));
+                }
+
+                (StringEscape(_), Some(_)) => break Err(LexerError::BadEscape),
+                (StringEscape(_), None) => break Err(LexerError::UnterminatedString),
+
                 // Extensión de términos
                 (Word(word), Some(c)) if is_word_char(c) => {
                     if word.len() == MAX_ID_LENGTH {
@@ -640,7 +922,7 @@ impl<S: InputStream> Iterator for Lexer<S> {
 /// Determina si un carácter puede pertenecer al interior de
 /// un literal de cadena.
 fn is_string_char(c: char) -> bool {
-    c == '_' || (!c.is_control() && !c.is_whitespace())
+    c == ' ' || c == '_' || (!c.is_control() && !c.is_whitespace())
 }
 
 /// Determina si un carácter puede pertenecer a un término.
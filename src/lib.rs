@@ -1,14 +1,16 @@
 //! Compilador para CE3104 AnimationLed.
 //!
 //! # Front end
-//! Cada programa deriva de un único archivo de código fuente.
+//! Cada programa deriva de un único archivo de código fuente, junto a
+//! los módulos que este importe mediante [`import`].
 //! Este archivo se somete primero a análisis léxico en [`lex`], de
 //! lo cual se obtiene un flujo de tokens. El flujo de tokens se
 //! dispone en un AST por medio de análisis sintáctico en [`parse`].
 //! El árbol sintáctico es procesado por análisis semántico en
 //! [`semantic`], de lo cual eventualmente se genera una representación
 //! intermedia descrita en [`ir`], con lo cual concluyen las fases
-//! delanteras del compilador.
+//! delanteras del compilador. Antes de emitir código, [`opt`] aplica al
+//! IR las optimizaciones y demás transformaciones solicitadas.
 //!
 //! # Back end
 //! En esta sección el compilador deja de ser agnóstico al sistema
@@ -18,20 +20,64 @@
 //! llamada en las distintas implementaciones por arquitectura,
 //! concluyendo con ensamblado, enlazado y emisión del ejecutable final
 //! en [`link`]. Los aspectos de ensamblado y enlazado se delegan
-//! a la toolchain de `binutils` que distribuye Espressif.
+//! a la toolchain de `binutils` que distribuye Espressif, aunque para
+//! Xtensa [`asm`] puede ensamblar dentro del proceso. Opcionalmente,
+//! [`ledmap`] relaciona direcciones del ejecutable con líneas de código.
+//! El mapa de memoria de cada dispositivo se describe en [`memory`].
+//! Las rutinas de `libruntime` que invoca el código se listan en [`builtins`].
+//! Para ESP8266, [`flash`] escribe el ejecutable a la tarjeta.
+//! Cuando un mismo programa se recompila con frecuencia, [`incremental`]
+//! evita repetir ambas mitades para los procedimientos sin cambios.
+//! El tiempo y memoria que consume cada fase se registran en [`stats`].
+//!
+//! # Evaluación interactiva
+//! Como alternativa al back end, [`interp`] ejecuta directamente la
+//! representación intermedia, ya sea de programas completos o bien de
+//! fragmentos de código que [`repl`] evalúa en un ámbito persistente.
+//!
+//! # Herramientas
+//! [`format`] reescribe programas con un formato canónico a partir de su
+//! árbol sintáctico, y [`doc`] resume sus procedimientos y constantes.
 
 #![feature(trait_alias)]
 
+use std::{io::BufRead, path::PathBuf};
+
+use crate::{
+    error::Diagnostics,
+    ir::Program,
+    lex::{Lexer, Token},
+    parse::Ast,
+    source::Located,
+    stats::Statistics,
+};
+
 #[macro_use]
 mod macros;
 
+pub mod asm;
+pub mod builtins;
+pub mod doc;
 pub mod error;
+pub mod flash;
+pub mod format;
+pub mod grade;
+pub mod import;
+pub mod incremental;
+pub mod interp;
 pub mod ir;
+pub mod ledmap;
 pub mod lex;
 pub mod link;
+pub mod memory;
+pub mod opt;
 pub mod parse;
+pub mod repl;
+pub mod sandbox;
 pub mod semantic;
+pub mod snapshot;
 pub mod source;
+pub mod stats;
 
 mod arch;
 mod codegen;
@@ -42,5 +88,152 @@ mod codegen;
 /// traducir IR a alguna arquitectura en específico.
 pub mod target {
     pub use crate::arch::Arch;
-    pub use crate::codegen::emit;
+    pub use crate::codegen::{
+        checks::check_division,
+        emit, emit_function, emit_header, emit_with_debug_info,
+        frames::mark_frames,
+        inline::inline_calls,
+        soft::soft_arithmetic,
+        stack::{stack_usage, StackUsage},
+        verify::{verify as verify_ir, VerifyError, Violation},
+    };
+}
+
+/// Ejecuta las fases delanteras sobre un programa.
+///
+/// `name` identifica al código fuente en diagnósticos.
+pub fn frontend<R: BufRead>(reader: &mut R, name: &str) -> Result<Program, Diagnostics> {
+    frontend_with_warnings(reader, name).map(|(program, _)| program)
+}
+
+/// Como [`frontend()`], pero además retorna las advertencias del
+/// análisis semántico. Estas nunca son fatales.
+pub fn frontend_with_warnings<R: BufRead>(
+    reader: &mut R,
+    name: &str,
+) -> Result<(Program, Diagnostics), Diagnostics> {
+    frontend_with_stats(reader, name, &mut Statistics::default())
+}
+
+/// Como [`frontend_with_warnings()`], pero midiendo cada fase en `stats`.
+///
+/// Se registran también las cantidades de tokens, nodos del AST e
+/// instrucciones IR.
+pub fn frontend_with_stats<R: BufRead>(
+    reader: &mut R,
+    name: &str,
+    stats: &mut Statistics,
+) -> Result<(Program, Diagnostics), Diagnostics> {
+    frontend_with_options(reader, name, &FrontendOptions::default(), stats)
+}
+
+/// Opciones de las fases delanteras.
+#[derive(Clone, Debug, Default)]
+pub struct FrontendOptions {
+    /// Directorios donde también se buscan los módulos importados, ver
+    /// [`import`].
+    pub search_path: Vec<PathBuf>,
+
+    /// Constantes definidas desde fuera del programa, ver [`define()`].
+    pub defines: Vec<String>,
+}
+
+/// Como [`frontend_with_stats()`], pero con las opciones dadas.
+pub fn frontend_with_options<R: BufRead>(
+    reader: &mut R,
+    name: &str,
+    options: &FrontendOptions,
+    stats: &mut Statistics,
+) -> Result<(Program, Diagnostics), Diagnostics> {
+    let (_, ast) = lex_and_parse_with_stats(reader, name, stats)?;
+    let mut ast = import::resolve(ast, name, &options.search_path)?;
+
+    for definition in &options.defines {
+        define(&mut ast, definition)?;
+    }
+
+    stats.count("AST nodes", ast.node_count());
+
+    let (program, warnings) = stats
+        .measure("semantic", || ast.resolve_with_warnings())
+        .map_err(|error| Diagnostics::from(error).kind("Semantic error"))?;
+
+    let instructions = program.code.iter().map(|function| function.body.len());
+    stats.count("IR instructions", instructions.sum());
+
+    let warnings = Diagnostics::from(warnings)
+        .kind("Semantic warning")
+        .fatal(false);
+
+    Ok((program, warnings))
+}
+
+/// Compila un programa completo a ensamblador para `arch`.
+///
+/// Equivale a [`frontend()`] seguido de [`target::emit()`], sin
+/// transformaciones opcionales ni enlazado. Permite inspeccionar el
+/// código emitido sin invocar al ejecutable del compilador.
+pub fn compile_to_string(source: &str, arch: target::Arch) -> Result<String, Diagnostics> {
+    let program = frontend(&mut source.as_bytes(), "<string>")?;
+
+    let mut assembly = Vec::new();
+    target::emit(&program, arch, &mut assembly).expect("writing to memory cannot fail");
+
+    Ok(String::from_utf8(assembly).expect("emitted assembly is not UTF-8"))
+}
+
+/// Define una constante global a partir de `NOMBRE=valor`.
+///
+/// El valor es cualquier expresión constante. Sin `=valor` la constante
+/// vale `true`, a la manera de `-D` en C. Si el programa o alguno de sus
+/// módulos ya define la constante, su valor se reemplaza. Así un mismo
+/// programa puede declarar valores por omisión y ajustarlos por
+/// objetivo al compilar, por ejemplo para incluir depuración solo en el
+/// host:
+///
+/// ```text
+/// const HOSTED = false;
+/// ...
+/// if HOSTED {
+///     debug(frame);
+/// }
+/// ```
+pub fn define(ast: &mut Ast, definition: &str) -> Result<(), Diagnostics> {
+    let (name, value) = definition.split_once('=').unwrap_or((definition, "true"));
+    let source = format!("const {} = {};", name.trim(), value.trim());
+
+    let (_, defined) = lex_and_parse(&mut source.as_bytes(), "<define>")?;
+    ast.define(defined);
+
+    Ok(())
+}
+
+/// Análisis léxico y sintáctico. Se retornan también los tokens.
+fn lex_and_parse<R: BufRead>(
+    reader: &mut R,
+    name: &str,
+) -> Result<(Vec<Located<Token>>, Ast), Diagnostics> {
+    lex_and_parse_with_stats(reader, name, &mut Statistics::default())
+}
+
+/// Como [`lex_and_parse()`], pero midiendo ambas fases.
+fn lex_and_parse_with_stats<R: BufRead>(
+    reader: &mut R,
+    name: &str,
+    stats: &mut Statistics,
+) -> Result<(Vec<Located<Token>>, Ast), Diagnostics> {
+    let (start, stream) = source::consume(reader, name);
+
+    let lexer = Lexer::new(start.clone(), stream);
+    let tokens = match stats.measure("lex", || lexer.try_exhaustive()) {
+        Ok(tokens) => tokens,
+        Err(errors) => return Err(Diagnostics::from(errors).kind("Lexical error")),
+    };
+
+    stats.count("tokens", tokens.len());
+
+    match stats.measure("parse", || parse::parse(tokens.iter(), start)) {
+        Ok(ast) => Ok((tokens, ast)),
+        Err(error) => Err(Diagnostics::from(error).kind("Syntax error")),
+    }
 }
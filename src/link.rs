@@ -3,16 +3,33 @@
 //! Una vez que se ha emitido código ensamblador, este debe ser
 //! ensamblado y enlazado contra `libruntime` para producir in binario
 //! ejecutable.
+//!
+//! WebAssembly es la excepción: el módulo en formato de texto que
+//! emite [`crate::arch::wasm32`] se ensambla dentro del mismo proceso
+//! y el enlazado contra `libruntime` ocurre al instanciarlo desde
+//! JavaScript, ver `runtime/web/`.
 
 use std::{
-    fs,
-    io::BufWriter,
-    path::Path,
-    process::{Child, ChildStdin, Command, ExitStatus, Stdio},
+    convert::TryInto,
+    env,
+    ffi::OsString,
+    fmt::{self, Display},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    process::{self, Child, ChildStdin, Command, ExitStatus, Stdio},
     str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread::{self, JoinHandle},
 };
 
-use crate::arch::Arch;
+use crate::{
+    arch::Arch,
+    asm::{self, AssemblerError},
+    ledmap::{self, SourceMap},
+    memory::{MemoryLayout, Region},
+    semantic,
+};
 use bitflags::bitflags;
 use thiserror::Error;
 
@@ -25,6 +42,23 @@ bitflags! {
         /// ejecutable en muchos casos. Es buena práctica utilizarla
         /// para distribuir binarios release.
         const STRIP = 0x01;
+
+        /// Ensamblar dentro del proceso con [`crate::asm`], de forma que
+        /// el driver de `gcc` solo enlaza. Solo se admite para Xtensa.
+        const INTERNAL_ASSEMBLER = 0x02;
+
+        /// Reportar el tamaño del ejecutable al finalizar, ver [`SizeReport`].
+        const SIZE_REPORT = 0x04;
+
+        /// Enlazar un ejecutable independiente de posición (PIE). Solo
+        /// se admite para la plataforma nativa. Si no se indica `PIE` ni
+        /// `NO_PIE`, se respeta el comportamiento por defecto de `gcc`,
+        /// que varía entre distribuciones. El código que se emite para
+        /// x86-64 es compatible con ambos modos.
+        const PIE = 0x08;
+
+        /// Enlazar un ejecutable de direcciones fijas, ver [`LinkOptions::PIE`].
+        const NO_PIE = 0x10;
     }
 }
 
@@ -40,6 +74,153 @@ pub enum LinkerError {
     /// El enlazador inició su ejecución, pero falló en enlazar.
     #[error("Linker exited with status code {0:?}")]
     Failed(ExitStatus),
+
+    /// El ejecutable se enlazó, pero no se pudo convertir al
+    /// formato de salida solicitado.
+    #[error("Image conversion exited with status code {0:?}")]
+    ConversionFailed(ExitStatus),
+
+    /// El ejecutable no cabe en las memorias del dispositivo.
+    #[error(
+        "Program does not fit in target memory\n{0}\
+         Consider simplifying the program or avoiding float operations, \
+         which pull in software floating point routines"
+    )]
+    Overflow(MemoryReport),
+
+    /// El módulo WebAssembly en formato de texto es inválido.
+    #[error("WebAssembly assembly failed")]
+    Wasm(#[from] wat::Error),
+
+    /// El ensamblador interno rechazó el código emitido.
+    #[error("Internal assembler failed")]
+    Assembler(#[from] AssemblerError),
+
+    /// La plataforma no admite la opción de enlazado solicitada.
+    #[error("Target platform does not support {0}")]
+    Unsupported(&'static str),
+
+    /// La flash indicada no alcanza para las regiones que la mapean.
+    #[error("Flash size of {0} bytes is too small for the target")]
+    FlashTooSmall(u64),
+
+    /// No se encontró el directorio de `libruntime`, ver [`library_dir()`].
+    #[error("Runtime libraries not found, set {} to their directory", LIB_DIR_VAR)]
+    NoLibraries,
+
+    /// El directorio de bibliotecas no contiene `libruntime` para la
+    /// plataforma.
+    #[error("Runtime library not found in {}", .0.display())]
+    MissingRuntime(PathBuf),
+
+    /// Un comando externo que requiere la plataforma no está instalado.
+    #[error("Command `{0}` not found")]
+    MissingTool(&'static str),
+
+    /// El sistema anfitrión no admite la plataforma nativa, ver
+    /// [`Platform::check_host()`].
+    #[error("Native target is not supported on {0}")]
+    UnsupportedHost(&'static str),
+}
+
+/// Variable de entorno que indica el directorio `lib/` de las
+/// bibliotecas de soporte. La misma variable, si está definida al
+/// compilar el compilador, provee un valor por defecto.
+pub const LIB_DIR_VAR: &str = "ANIMLED_LIB_DIR";
+
+/// Uso de memoria de un ejecutable, por región del dispositivo.
+#[derive(Debug)]
+pub struct MemoryReport {
+    usage: Vec<(Region, u64)>,
+}
+
+impl MemoryReport {
+    fn overflows(&self) -> bool {
+        self.usage
+            .iter()
+            .any(|(region, used)| *used > region.length)
+    }
+}
+
+impl Display for MemoryReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kib = |bytes: u64| (bytes + 1023) / 1024;
+
+        for (region, used) in self.usage.iter() {
+            let (used, available) = (*used, region.length);
+            write!(
+                fmt,
+                "  {} ({}): program needs {} KiB, device has {} KiB",
+                region.name,
+                region.kind.unwrap_or_default(),
+                kib(used),
+                kib(available)
+            )?;
+
+            if used > available {
+                write!(fmt, " <- exceeded by {} bytes", used - available)?;
+            }
+
+            writeln!(fmt)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Tamaño de un ejecutable por tipo de contenido, al estilo de `size`.
+#[derive(Debug)]
+pub struct SizeReport {
+    /// Código y datos constantes.
+    pub text: u64,
+
+    /// Datos inicializados.
+    pub data: u64,
+
+    /// Datos inicializados en cero, que no ocupan espacio en la imagen.
+    pub bss: u64,
+
+    /// Uso de las memorias del dispositivo, vacío si se desconocen.
+    memory: MemoryReport,
+}
+
+impl Display for SizeReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(fmt, "{:>10} {:>10} {:>10}", "text", "data", "bss")?;
+        writeln!(fmt, "{:>10} {:>10} {:>10}", self.text, self.data, self.bss)?;
+        write!(fmt, "{}", self.memory)
+    }
+}
+
+/// Formato del archivo de salida luego de enlazar.
+///
+/// El enlazador siempre produce un ELF. Los demás formatos se obtienen
+/// al postprocesar ese ELF con `objcopy`, respetando las direcciones de
+/// carga que dispone el linker script de la plataforma.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Ejecutable ELF, tal como lo emite el enlazador.
+    Elf,
+
+    /// Intel HEX, útil para herramientas de flasheo y bootloaders
+    /// que esperan direcciones explícitas por registro.
+    Hex,
+
+    /// Imagen binaria cruda, sin metadatos.
+    Bin,
+}
+
+impl FromStr for OutputFormat {
+    type Err = ();
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "elf" => Ok(OutputFormat::Elf),
+            "hex" => Ok(OutputFormat::Hex),
+            "bin" => Ok(OutputFormat::Bin),
+            _ => Err(()),
+        }
+    }
 }
 
 /// Plataforma objetivo.
@@ -50,24 +231,124 @@ pub enum LinkerError {
 /// específica. Las diferentes configuraciones de `libruntime`
 /// toman esto en cuenta, por lo cual no es suficiente discriminar
 /// el sistema objetivo a partir de únicamente su ISA de procesador.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Platform {
     /// Plataforma sobre la que core el compilador ("hosted").
     Native,
 
     /// Espressif ESP8266.
     Esp8266,
+
+    /// STMicroelectronics STM32F411 (ARM Cortex-M4).
+    Stm32,
+
+    /// Espressif ESP32-C3 (RISC-V).
+    Esp32c3,
+
+    /// Simulador en navegador (WebAssembly).
+    Web,
 }
 
 impl Platform {
+    /// Todas las plataformas, en el orden en que se listan en la CLI.
+    pub const ALL: &'static [Platform] = &[
+        Platform::Native,
+        Platform::Esp8266,
+        Platform::Stm32,
+        Platform::Esp32c3,
+        Platform::Web,
+    ];
+
+    /// Verifica que `libruntime` y los comandos externos de enlazado y
+    /// conversión de la plataforma estén instalados, sin enlazar nada.
+    pub fn check_toolchain(self) -> Result<(), LinkerError> {
+        // Los módulos WebAssembly se ensamblan dentro del compilador
+        if let Platform::Web = self {
+            return Ok(());
+        }
+
+        self.check_host()?;
+        let params = self.link_params();
+        let library_path = library_dir()?.join(params.name);
+
+        let names = ["libruntime.a", "runtime.lib"];
+        if !names.iter().any(|name| library_path.join(name).is_file()) {
+            return Err(LinkerError::MissingRuntime(library_path));
+        }
+
+        for &tool in &[params.command, params.objcopy] {
+            let status = Command::new(tool)
+                .arg("--version")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+
+            match status {
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                    return Err(LinkerError::MissingTool(tool))
+                }
+
+                result => result.map(|_| ())?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verifica que el compilador pueda generar código nativo para el
+    /// sistema anfitrión.
+    ///
+    /// El backend AArch64 emite la sintaxis de ELF (`:lo12:`, símbolos
+    /// sin prefijo `_`), que el ensamblador de Mach-O rechaza, por lo
+    /// cual macOS sobre Apple Silicon solo puede compilar para las demás
+    /// plataformas.
+    pub fn check_host(self) -> Result<(), LinkerError> {
+        let mach_o = cfg!(all(target_os = "macos", target_arch = "aarch64"));
+        match self {
+            Platform::Native if mach_o => Err(LinkerError::UnsupportedHost("macOS on AArch64")),
+            _ => Ok(()),
+        }
+    }
+
+    /// Nombre de la plataforma en la CLI.
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::Native => "native",
+            Platform::Esp8266 => "esp8266",
+            Platform::Stm32 => "stm32",
+            Platform::Esp32c3 => "esp32c3",
+            Platform::Web => "web",
+        }
+    }
+
     /// Obtiene la ISA asociada a esta plataforma.
+    ///
+    /// La plataforma nativa corresponde a la arquitectura del anfitrión
+    /// y, en x86-64, a la convención de llamada de su sistema operativo.
+    /// Antes de emitir código nativo debe llamarse a
+    /// [`Platform::check_host()`].
     pub fn arch(self) -> Arch {
-        #[cfg(not(target_arch = "x86_64"))]
-        error!("Native target is not x86-64");
+        #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+        error!("Native target is neither x86-64 nor AArch64");
 
         match self {
+            Platform::Native if cfg!(target_arch = "aarch64") => Arch::Aarch64,
+            Platform::Native if cfg!(windows) => Arch::Win64,
             Platform::Native => Arch::X86_64,
             Platform::Esp8266 => Arch::Xtensa,
+            Platform::Stm32 => Arch::Arm,
+            Platform::Esp32c3 => Arch::RiscV,
+            Platform::Web => Arch::Wasm32,
+        }
+    }
+
+    /// Bytes de pila disponibles para el programa, si la plataforma
+    /// es lo suficientemente limitada como para que esto importe.
+    pub fn stack_size(self) -> Option<u64> {
+        match self {
+            // STACK_SIZE en memory.x
+            Platform::Esp8266 => Some(8 * 1024),
+            _ => None,
         }
     }
 }
@@ -79,83 +360,1103 @@ impl FromStr for Platform {
         match string {
             "native" => Ok(Platform::Native),
             "esp8266" => Ok(Platform::Esp8266),
+            "stm32" => Ok(Platform::Stm32),
+            "esp32c3" => Ok(Platform::Esp32c3),
+            "web" => Ok(Platform::Web),
             _ => Err(()),
         }
     }
 }
 
+/// Linker scripts con los que se enlaza.
+#[derive(Copy, Clone, Default)]
+pub struct ScriptOptions<'a> {
+    /// Script que reemplaza al script principal de la plataforma.
+    pub script: Option<&'a Path>,
+
+    /// Tamaño de la flash, si difiere del de la placa usual de la
+    /// plataforma, ver [`Platform::memory_layout()`].
+    pub flash_size: Option<u64>,
+}
+
 /// Instancia del enlazador para un ejecutable definido.
 ///
 /// Las operaciones de ensamblado y enlazado se delegan a ejecutables
-/// externos del paquete `binutils`.
+/// externos del paquete `binutils`, excepto para [`Platform::Web`] y
+/// para el ensamblado con [`LinkOptions::INTERNAL_ASSEMBLER`].
 pub struct Linker {
-    child: Child,
-    stdin: BufWriter<ChildStdin>,
+    backend: Backend,
+}
+
+/// Mecanismo de ensamblado y enlazado.
+enum Backend {
+    /// Ensamblador y enlazador externos.
+    External {
+        assembler: Box<Assembler>,
+        linked: PathBuf,
+        regions: Vec<Region>,
+        conversion: Option<Conversion>,
+        scripts: Option<PathBuf>,
+        sourcemap: Option<SourceMapRequest>,
+        size_report: bool,
+    },
+
+    /// Módulo WebAssembly en formato de texto, ensamblado al finalizar.
+    Wasm { text: Vec<u8>, output: PathBuf },
+}
+
+/// Forma en que el ensamblador recibe el código emitido.
+///
+/// En todos los casos, los mensajes de error del driver se reenvían
+/// con los símbolos de procedimientos ya decodificados, ver
+/// [`forward_stderr()`].
+enum Assembler {
+    /// El proceso ya inició y recibe el código por pipe.
+    Piped {
+        child: Child,
+        stdin: BufWriter<ChildStdin>,
+        stdout: JoinHandle<String>,
+        stderr: JoinHandle<()>,
+    },
+
+    /// En Windows no se depende de que el driver de `gcc` lea código
+    /// de un pipe. El código se escribe a un archivo temporal y el
+    /// proceso inicia hasta que el código está completo.
+    Deferred {
+        command: Command,
+        source: PathBuf,
+        file: BufWriter<File>,
+    },
+
+    /// El código se acumula y se ensambla dentro del proceso. El objeto
+    /// resultante se escribe a un archivo temporal que recibe el driver.
+    Internal {
+        command: Command,
+        source: Vec<u8>,
+        libraries: &'static [&'static str],
+    },
+}
+
+/// Mapa de código fuente pendiente, ver [`crate::ledmap`].
+struct SourceMapRequest {
+    output: PathBuf,
+    linker_map: PathBuf,
+
+    /// El mapa de enlazado no fue solicitado, solo se requiere aquí.
+    temporary: bool,
+}
+
+/// Conversión pendiente de un ELF intermedio al formato final.
+struct Conversion {
+    platform: Platform,
+    format: OutputFormat,
+    elf: PathBuf,
+    output: PathBuf,
 }
 
 impl Linker {
     /// Inicia una instancia del enlazador.
     ///
     /// El enlazador tratará de emitir un ejecutable y escribirlo a
-    /// la ruta indicada por `output`.
-    pub fn spawn<O>(platform: Platform, output: &O, opts: LinkOptions) -> Result<Self, LinkerError>
+    /// la ruta indicada por `output`. Si `format` no es ELF, el
+    /// ejecutable se enlaza a un archivo intermedio que se convierte
+    /// y elimina en [`.finish()`].
+    ///
+    /// Los linker scripts de la plataforma vienen incluidos en el
+    /// compilador, excepto `memory.x`, que se genera a partir del mapa
+    /// de memoria de la plataforma con el tamaño de flash que indique
+    /// `scripts`, ver [`crate::memory`]. El script de `scripts` reemplaza
+    /// al script principal para placas con otros mapas de memoria; su
+    /// directorio tiene precedencia en la búsqueda de `INCLUDE`, por lo
+    /// que un `memory.x` ubicado junto a él sustituye al generado. Al
+    /// finalizar se verifica que el ejecutable quepa en las memorias del
+    /// dispositivo, excepto con un script propio, ya que en ese caso se
+    /// desconoce el mapa de memoria.
+    ///
+    /// Si se indica `map`, el enlazador escribe allí un mapa de enlazado
+    /// que describe la ubicación final de cada sección y símbolo. Si se
+    /// indica `sourcemap`, se escribe allí un mapa `.ledmap` construido a
+    /// partir del mapa de enlazado, ver [`crate::ledmap`].
+    ///
+    /// Para [`Platform::Web`] solo se admite el formato ELF, que en este
+    /// caso significa un módulo `.wasm`, y ni `scripts`, `map` ni
+    /// `sourcemap` tienen sentido. La opción [`LinkOptions::STRIP`] no
+    /// tiene efecto, ya que el módulo no incluye información de depuración.
+    pub fn spawn<O>(
+        platform: Platform,
+        output: &O,
+        format: OutputFormat,
+        scripts: ScriptOptions<'_>,
+        map: Option<&Path>,
+        sourcemap: Option<&Path>,
+        opts: LinkOptions,
+    ) -> Result<Self, LinkerError>
     where
         O: AsRef<Path>,
     {
+        platform.check_host()?;
+
+        let output = output.as_ref();
+        if opts.contains(LinkOptions::INTERNAL_ASSEMBLER) && platform.arch() != Arch::Xtensa {
+            return Err(LinkerError::Unsupported("the internal assembler"));
+        }
+
+        let pie = opts.intersection(LinkOptions::PIE | LinkOptions::NO_PIE);
+        if !pie.is_empty() && platform != Platform::Native {
+            return Err(LinkerError::Unsupported("position-independent executables"));
+        }
+
+        if let Platform::Web = platform {
+            let unsupported = match (format, scripts.script, map, sourcemap) {
+                (OutputFormat::Hex, ..) => Some("Intel HEX"),
+                (OutputFormat::Bin, ..) => Some("raw binaries"),
+                (_, Some(_), _, _) => Some("linker scripts"),
+                _ if scripts.flash_size.is_some() => Some("flash sizes"),
+                _ if opts.contains(LinkOptions::SIZE_REPORT) => Some("size reports"),
+                (_, _, Some(_), _) => Some("linker maps"),
+                (_, _, _, Some(_)) => Some("source maps"),
+                _ => None,
+            };
+
+            if let Some(unsupported) = unsupported {
+                return Err(LinkerError::Unsupported(unsupported));
+            }
+
+            let backend = Backend::Wasm {
+                text: Vec::new(),
+                output: output.to_path_buf(),
+            };
+
+            return Ok(Linker { backend });
+        }
+
         let params = platform.link_params();
+        if params.driver != Driver::Gnu {
+            // Solo se analizan ejecutables ELF
+            let unsupported = match (format, scripts.script, sourcemap) {
+                (OutputFormat::Hex, ..) => Some("Intel HEX"),
+                (OutputFormat::Bin, ..) => Some("raw binaries"),
+                (_, Some(_), _) => Some("linker scripts"),
+                (_, _, Some(_)) => Some("source maps"),
+                _ if opts.contains(LinkOptions::SIZE_REPORT) => Some("size reports"),
+                _ if !pie.is_empty() => Some("position-independent executables"),
+                _ => None,
+            };
 
-        let mut library_path = fs::read_link("/proc/self/exe").expect("Failed to read symlink");
-        library_path.pop(); // "<...>/compiler" => "<...>"
-        library_path.push("lib");
-        library_path.push(params.name);
+            if let Some(unsupported) = unsupported {
+                return Err(LinkerError::Unsupported(unsupported));
+            }
+        }
+
+        let layout = platform.memory_layout(scripts.flash_size)?;
+        let script = scripts.script;
+
+        let (linked, conversion) = match format {
+            OutputFormat::Elf => (output.to_path_buf(), None),
+
+            _ => {
+                let mut elf = OsString::from(output);
+                elf.push(".elf");
+
+                let elf = PathBuf::from(elf);
+                let conversion = Conversion {
+                    platform,
+                    format,
+                    elf: elf.clone(),
+                    output: output.to_path_buf(),
+                };
+
+                (elf, Some(conversion))
+            }
+        };
+
+        let library_path = library_dir()?.join(params.name);
 
         // Para ensamblar el código máquina generador por codegen,
         // se hace pipe del mismo al stdin del linker.
         let mut command = Command::new(params.command);
+        command.args(params.extra_args);
+
+        if opts.contains(LinkOptions::PIE) {
+            command.arg("-pie");
+        } else if opts.contains(LinkOptions::NO_PIE) {
+            command.arg("-no-pie");
+        }
+
+        if let Some(script) = script {
+            if let Some(parent) = script.parent().filter(|dir| *dir != Path::new("")) {
+                command.arg("-L").arg(parent);
+            }
+        }
+
+        let scripts = materialize_scripts(params.scripts, &layout)?;
+        if let Some(scripts) = &scripts {
+            command.arg("-L").arg(scripts);
+        }
+
+        let entry_script = script
+            .map(Path::to_path_buf)
+            .or_else(|| Some(scripts.as_ref()?.join(params.scripts.first()?.0)));
+
+        if let Some(entry_script) = entry_script {
+            let mut arg = OsString::from("-Wl,-T,");
+            arg.push(entry_script);
+            command.arg(arg);
+        }
+
+        // El mapa de código fuente requiere un mapa de enlazado
+        let sourcemap = sourcemap.map(|output| {
+            let (linker_map, temporary) = match map {
+                Some(map) => (map.to_path_buf(), false),
+                None => {
+                    (temporary("compiler-map", ""), true)
+                }
+            };
+
+            SourceMapRequest {
+                output: output.to_path_buf(),
+                linker_map,
+                temporary,
+            }
+        });
+
+        let map = map.or_else(|| Some(&sourcemap.as_ref()?.linker_map));
+        if let Some(map) = map {
+            let mut arg = OsString::from(params.driver.map_arg());
+            arg.push(map);
+            command.arg(arg);
+        }
+
         command
-            .args(params.extra_args)
             // Ruta de búsqueda de bibliotecas en lib/{platform}
             .arg("-L")
             .arg(&library_path)
             .arg("-o")
-            .arg(output.as_ref())
-            // Se descarta código muerto, se asume entrada en asm y se enlaza
-            // contra la biblioteca de soporte libruntime
-            .args(&["-Wl,--gc-sections", "-xassembler", "-", "-lruntime"])
-            .stdin(Stdio::piped());
+            .arg(&linked)
+            .arg(params.driver.gc_sections_arg());
 
         if opts.contains(LinkOptions::STRIP) {
-            command.arg("-s");
+            command.args(params.driver.strip_args());
         }
 
-        let mut child = command.spawn().map_err(LinkerError::Io)?;
-        let stdin = BufWriter::new(child.stdin.take().unwrap());
+        let regions: Vec<_> = match script {
+            None => layout.checked().cloned().collect(),
+            Some(_) => Vec::new(),
+        };
+
+        // Si el ejecutable no cabe, ld falla sin producirlo, pero aún
+        // así imprime el uso de cada región
+        if !regions.is_empty() {
+            command
+                .arg("-Wl,--print-memory-usage")
+                .stdout(Stdio::piped());
+        }
+
+        let internal = opts.contains(LinkOptions::INTERNAL_ASSEMBLER);
+        let assembler = match Assembler::spawn(command, internal, params.libraries) {
+            Ok(assembler) => assembler,
+            Err(error) => {
+                if let Some(scripts) = &scripts {
+                    let _ = fs::remove_dir_all(scripts);
+                }
 
-        Ok(Linker { child, stdin })
+                return Err(LinkerError::Io(error));
+            }
+        };
+
+        let backend = Backend::External {
+            assembler: Box::new(assembler),
+            linked,
+            regions,
+            conversion,
+            scripts,
+            sourcemap,
+            size_report: opts.contains(LinkOptions::SIZE_REPORT),
+        };
+
+        Ok(Linker { backend })
     }
 
-    /// Obtiene la entrada estándar del rproceso que espera recibir ensamblador.
+    /// Obtiene el flujo que espera recibir ensamblador, usualmente la
+    /// entrada estándar del proceso enlazador.
     ///
     /// Luego de crear una instancia con [`.spawn()`], se debe escribir código
     /// ensamblador en la forma exacta en que fue emitido por las fases de
     /// generación de código.
-    pub fn stdin(&mut self) -> &mut BufWriter<ChildStdin> {
-        &mut self.stdin
+    pub fn stdin(&mut self) -> &mut dyn Write {
+        match &mut self.backend {
+            Backend::External { assembler, .. } => assembler.input(),
+            Backend::Wasm { text, .. } => text,
+        }
     }
 
     /// Indica el fin del flujo de código y finaliza el enlazado.
-    pub fn finish(mut self) -> Result<(), LinkerError> {
-        drop(self.stdin);
+    ///
+    /// Con [`LinkOptions::SIZE_REPORT`] se retorna además el tamaño del
+    /// ejecutable y el uso de cada memoria del dispositivo.
+    pub fn finish(self) -> Result<Option<SizeReport>, LinkerError> {
+        let (assembler, linked, regions, conversion, scripts, sourcemap, size_report) =
+            match self.backend {
+                Backend::External {
+                    assembler,
+                    linked,
+                    regions,
+                    conversion,
+                    scripts,
+                    sourcemap,
+                    size_report,
+                } => (
+                    *assembler,
+                    linked,
+                    regions,
+                    conversion,
+                    scripts,
+                    sourcemap,
+                    size_report,
+                ),
+
+                Backend::Wasm { text, output } => {
+                    let binary = wat::parse_bytes(&text)?;
+                    fs::write(output, binary)?;
+
+                    return Ok(None);
+                }
+            };
+
+        let result = assembler.wait();
+        if let Some(scripts) = &scripts {
+            fs::remove_dir_all(scripts)?;
+        }
+
+        let (status, usage) = result?;
+        if !status.success() {
+            if let Some(sourcemap) = &sourcemap {
+                sourcemap.discard();
+            }
+
+            let memory = printed_memory_usage(&usage, &regions).filter(MemoryReport::overflows);
+            return Err(match memory {
+                Some(memory) => LinkerError::Overflow(memory),
+                None => LinkerError::Failed(status),
+            });
+        }
+
+        let sections = if regions.is_empty() && !size_report {
+            Vec::new()
+        } else {
+            allocated_sections(&fs::read(&linked)?)?
+        };
+
+        let memory = memory_report(&sections, &regions);
+        if memory.overflows() {
+            if let Some(sourcemap) = &sourcemap {
+                sourcemap.discard();
+            }
+
+            return Err(LinkerError::Overflow(memory));
+        }
+
+        let report = if size_report {
+            Some(size_report_of(&sections, memory))
+        } else {
+            None
+        };
+
+        // El mapa de código fuente se construye sobre el ELF, antes de convertirlo
+        if let Some(sourcemap) = sourcemap {
+            sourcemap.write(&linked)?;
+        }
+
+        if let Some(conversion) = conversion {
+            conversion.run()?;
+        }
+
+        Ok(report)
+    }
+}
+
+impl Assembler {
+    /// Completa la invocación del driver, que ensambla el código emitido
+    /// y lo enlaza contra la biblioteca de soporte `libruntime` y luego
+    /// contra `libraries`. Con `internal`, el driver recibe en cambio un
+    /// objeto ya ensamblado.
+    fn spawn(
+        mut command: Command,
+        internal: bool,
+        libraries: &'static [&'static str],
+    ) -> io::Result<Self> {
+        if internal {
+            return Ok(Assembler::Internal {
+                command,
+                source: Vec::new(),
+                libraries,
+            });
+        }
+
+        if cfg!(windows) {
+            let source = temporary("compiler-asm", ".s");
+            let file = BufWriter::new(File::create(&source)?);
+
+            command.arg("-xassembler").arg(&source).arg("-lruntime");
+            command.args(libraries);
+            return Ok(Assembler::Deferred {
+                command,
+                source,
+                file,
+            });
+        }
+
+        command
+            .args(["-xassembler", "-", "-lruntime"])
+            .args(libraries)
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut child = command.spawn()?;
+        let stdin = BufWriter::new(child.stdin.take().unwrap());
+        let stdout = capture_stdout(&mut child);
+        let stderr = forward_stderr(&mut child);
+
+        Ok(Assembler::Piped {
+            child,
+            stdin,
+            stdout,
+            stderr,
+        })
+    }
+
+    fn input(&mut self) -> &mut dyn Write {
+        match self {
+            Assembler::Piped { stdin, .. } => stdin,
+            Assembler::Deferred { file, .. } => file,
+            Assembler::Internal { source, .. } => source,
+        }
+    }
+
+    /// Espera a que finalice el enlazado, ver [`capture_stdout()`].
+    fn wait(self) -> Result<(ExitStatus, String), LinkerError> {
+        match self {
+            Assembler::Piped {
+                mut child,
+                stdin,
+                stdout,
+                stderr,
+            } => {
+                drop(stdin);
+
+                let status = child.wait();
+                let _ = stderr.join();
+                let stdout = stdout.join().unwrap_or_default();
+
+                Ok((status?, stdout))
+            }
+
+            Assembler::Deferred {
+                mut command,
+                source,
+                file,
+            } => {
+                let result = file
+                    .into_inner()
+                    .map_err(io::Error::from)
+                    .and_then(|_| run_demangled(&mut command));
+
+                let _ = fs::remove_file(&source);
+                Ok(result?)
+            }
+
+            Assembler::Internal {
+                mut command,
+                source,
+                libraries,
+            } => {
+                let object = asm::xtensa::assemble(&String::from_utf8_lossy(&source))?;
+
+                let path = temporary("compiler-obj", ".o");
+                fs::write(&path, object)?;
+
+                let result = run_demangled(command.arg(&path).arg("-lruntime").args(libraries));
+                let _ = fs::remove_file(&path);
+
+                Ok(result?)
+            }
+        }
+    }
+}
+
+/// Reenvía los mensajes de error de un proceso al stderr del compilador,
+/// sustituyendo símbolos por los procedimientos que representan, ver
+/// [`semantic::demangle_text()`]. La lectura ocurre en otro hilo para
+/// que el proceso nunca se bloquee al escribir.
+fn forward_stderr(child: &mut Child) -> JoinHandle<()> {
+    let stderr = child.stderr.take().expect("stderr is not piped");
+
+    thread::spawn(move || {
+        let mut stderr = BufReader::new(stderr);
+        let mut line = Vec::new();
+
+        loop {
+            match stderr.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let text = String::from_utf8_lossy(&line);
+                    eprint!("{}", semantic::demangle_text(&text));
+
+                    line.clear();
+                }
+            }
+        }
+    })
+}
+
+/// Lee en otro hilo la salida estándar de un proceso, la cual es vacía
+/// si no se redirigió. Solo el uso de memoria que imprime ld se redirige,
+/// ver [`printed_memory_usage()`].
+fn capture_stdout(child: &mut Child) -> JoinHandle<String> {
+    let stdout = child.stdout.take();
 
-        let status = self.child.wait().map_err(LinkerError::Io)?;
+    thread::spawn(move || {
+        let mut output = String::new();
+        if let Some(mut stdout) = stdout {
+            let _ = stdout.read_to_string(&mut output);
+        }
+
+        output
+    })
+}
+
+/// Ejecuta un comando hasta que finalice, ver [`forward_stderr()`] y
+/// [`capture_stdout()`].
+fn run_demangled(command: &mut Command) -> io::Result<(ExitStatus, String)> {
+    let mut child = command.stderr(Stdio::piped()).spawn()?;
+    let stdout = capture_stdout(&mut child);
+    let stderr = forward_stderr(&mut child);
+
+    let status = child.wait();
+    let _ = stderr.join();
+    let stdout = stdout.join().unwrap_or_default();
+
+    Ok((status?, stdout))
+}
+
+impl SourceMapRequest {
+    /// Escribe el mapa de código fuente de un ELF ya enlazado.
+    fn write(&self, elf: &Path) -> Result<(), LinkerError> {
+        let linker_map = fs::read_to_string(&self.linker_map);
+        self.discard();
+
+        let linker_map = linker_map?;
+        let elf = fs::read(elf)?;
+
+        // Un programa sin líneas no tiene tablas
+        let (table, is_le) = section_contents(&elf, ledmap::SECTION)?.unwrap_or((&[], true));
+        let sourcemap = SourceMap::build(table, is_le, &linker_map)?;
+
+        let mut output = BufWriter::new(File::create(&self.output)?);
+        serde_json::to_writer(&mut output, &sourcemap).map_err(io::Error::from)?;
+        writeln!(output)?;
+
+        Ok(())
+    }
+
+    /// Elimina el mapa de enlazado si este fue creado únicamente para
+    /// construir el mapa de código fuente.
+    fn discard(&self) {
+        if self.temporary {
+            let _ = fs::remove_file(&self.linker_map);
+        }
+    }
+}
+
+/// Sección de un ejecutable que ocupa memoria del dispositivo.
+struct Allocated {
+    address: u64,
+    size: u64,
+    contents: Contents,
+}
+
+/// Clasificación de secciones según `size`.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Contents {
+    Text,
+    Data,
+    Bss,
+}
+
+/// Determina el uso de cada región de memoria por parte de un ejecutable.
+///
+/// Cada sección ocupada se atribuye a la región que contiene su dirección.
+fn memory_report(sections: &[Allocated], regions: &[Region]) -> MemoryReport {
+    let mut usage: Vec<_> = regions.iter().map(|region| (region.clone(), 0)).collect();
+
+    for section in sections {
+        let address = section.address;
+        let region = usage.iter_mut().find(|(region, _)| {
+            address >= region.origin && address < region.origin + region.length
+        });
+
+        if let Some((_, used)) = region {
+            *used += section.size;
+        }
+    }
+
+    MemoryReport { usage }
+}
+
+/// Interpreta la tabla que imprime ld con `--print-memory-usage`:
+///
+/// ```text
+/// Memory region         Used Size  Region Size  %age Used
+///      irom0_0_seg:      1012 KB      1008 KB    100.40%
+/// ```
+///
+/// Se retorna `None` si alguna de las regiones no aparece en la tabla.
+fn printed_memory_usage(table: &str, regions: &[Region]) -> Option<MemoryReport> {
+    let used = |name: &str| {
+        table.lines().find_map(|line| {
+            let mut fields = line.split_whitespace();
+            if fields.next()?.strip_suffix(':')? != name {
+                return None;
+            }
+
+            let (amount, unit) = (fields.next()?, fields.next()?);
+            let shift = match unit {
+                "B" => 0,
+                "KB" => 10,
+                "MB" => 20,
+                "GB" => 30,
+                _ => return None,
+            };
+
+            Some(amount.parse::<u64>().ok()? << shift)
+        })
+    };
+
+    let usage = regions
+        .iter()
+        .map(|region| Some((region.clone(), used(region.name)?)))
+        .collect::<Option<_>>()?;
+
+    Some(MemoryReport { usage })
+}
+
+/// Totaliza las secciones de un ejecutable por tipo de contenido.
+fn size_report_of(sections: &[Allocated], memory: MemoryReport) -> SizeReport {
+    let total = |contents| {
+        sections
+            .iter()
+            .filter(|section| section.contents == contents)
+            .map(|section| section.size)
+            .sum()
+    };
+
+    SizeReport {
+        text: total(Contents::Text),
+        data: total(Contents::Data),
+        bss: total(Contents::Bss),
+        memory,
+    }
+}
+
+/// Enumera las secciones `SHF_ALLOC` de un ELF.
+fn allocated_sections(elf: &[u8]) -> io::Result<Vec<Allocated>> {
+    const SHT_NOBITS: u64 = 8;
+    const SHF_WRITE: u64 = 0x1;
+    const SHF_ALLOC: u64 = 0x2;
+
+    let elf = Elf::parse(elf)?;
+    let mut sections = Vec::new();
+
+    for header in elf.section_headers()? {
+        let flags = elf.read(header + elf.layout.sh_flags, elf.word)?;
+        if flags & SHF_ALLOC == 0 {
+            continue;
+        }
+
+        let contents = if elf.read(header + elf.layout.sh_type, 4)? == SHT_NOBITS {
+            Contents::Bss
+        } else if flags & SHF_WRITE != 0 {
+            Contents::Data
+        } else {
+            Contents::Text
+        };
+
+        sections.push(Allocated {
+            address: elf.read(header + elf.layout.sh_addr, elf.word)?,
+            size: elf.read(header + elf.layout.sh_size, elf.word)?,
+            contents,
+        });
+    }
+
+    Ok(sections)
+}
+
+/// Busca un símbolo en la tabla `.symtab` de un ejecutable.
+///
+/// Se retorna la distancia entre el símbolo y el inicio del primer
+/// segmento cargable, de forma que la dirección en tiempo de ejecución
+/// se obtenga al sumarla a la base en la que se cargó el ejecutable.
+/// Esto es válido tanto para ejecutables fijos como para PIE. Si el
+/// ejecutable fue despojado de símbolos se retorna `None`.
+pub fn symbol_offset(elf: &[u8], name: &str) -> io::Result<Option<u64>> {
+    const SHT_SYMTAB: u64 = 2;
+    const PT_LOAD: u64 = 1;
+    const PAGE_MASK: u64 = 0xfff;
+
+    let elf = Elf::parse(elf)?;
+    let layout = &elf.layout;
+
+    let mut load_base = None;
+    let (table, entry_size, count) = (
+        elf.read(layout.e_phoff, elf.word)?,
+        elf.read(layout.e_phentsize, 2)?,
+        elf.read(layout.e_phnum, 2)?,
+    );
+
+    for i in 0..count {
+        let header = elf.offset(table + i * entry_size)?;
+        if elf.read(header, 4)? == PT_LOAD {
+            let address = elf.read(header + layout.p_vaddr, elf.word)? & !PAGE_MASK;
+            load_base = Some(load_base.map_or(address, |base: u64| base.min(address)));
+        }
+    }
+
+    let load_base = match load_base {
+        Some(load_base) => load_base,
+        None => return Ok(None),
+    };
+
+    let headers = elf.section_headers()?;
+    let symtab = headers
+        .iter()
+        .copied()
+        .find(|header| elf.read(header + layout.sh_type, 4).ok() == Some(SHT_SYMTAB));
+
+    let symtab = match symtab {
+        Some(symtab) => symtab,
+        None => return Ok(None),
+    };
+
+    let strtab = elf.read(symtab + layout.sh_link, 4)?;
+    let strtab = *headers.get(elf.offset(strtab)?).ok_or_else(bad_elf)?;
+    let strings = elf.offset(elf.read(strtab + layout.sh_offset, elf.word)?)?;
+
+    let symbols = elf.offset(elf.read(symtab + layout.sh_offset, elf.word)?)?;
+    let size = elf.offset(elf.read(symtab + layout.sh_size, elf.word)?)?;
+
+    for symbol in (symbols..symbols + size).step_by(layout.sym_size) {
+        let start = strings + elf.offset(elf.read(symbol, 4)?)?;
+        let symbol_name = elf.bytes.get(start..).ok_or_else(bad_elf)?;
+        let symbol_name = symbol_name.split(|byte| *byte == 0).next().unwrap();
+
+        if symbol_name == name.as_bytes() {
+            let value = elf.read(symbol + layout.st_value, elf.word)?;
+            return Ok(value.checked_sub(load_base));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Obtiene el contenido de una sección de un ELF por su nombre, junto
+/// a si el archivo es little-endian.
+pub fn section_contents<'a>(elf: &'a [u8], name: &str) -> io::Result<Option<(&'a [u8], bool)>> {
+    let elf = Elf::parse(elf)?;
+    let layout = &elf.layout;
+
+    let headers = elf.section_headers()?;
+    let names = elf.offset(elf.read(layout.e_shstrndx, 2)?)?;
+    let names = *headers.get(names).ok_or_else(bad_elf)?;
+    let names = elf.offset(elf.read(names + layout.sh_offset, elf.word)?)?;
+
+    for header in headers {
+        let start = names + elf.offset(elf.read(header, 4)?)?;
+        let section_name = elf.bytes.get(start..).ok_or_else(bad_elf)?;
+        let section_name = section_name.split(|byte| *byte == 0).next().unwrap();
+
+        if section_name == name.as_bytes() {
+            let offset = elf.offset(elf.read(header + layout.sh_offset, elf.word)?)?;
+            let size = elf.offset(elf.read(header + layout.sh_size, elf.word)?)?;
+            let contents = elf.bytes.get(offset..offset + size).ok_or_else(bad_elf)?;
+
+            return Ok(Some((contents, elf.is_le)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Dirección de carga y contenido de un segmento.
+pub type Segment<'a> = (u64, &'a [u8]);
+
+/// Enumera dirección y contenido de los segmentos `PT_LOAD` de un ELF
+/// que ocupan espacio en el archivo, junto al punto de entrada.
+pub fn load_segments(elf: &[u8]) -> io::Result<(u64, Vec<Segment<'_>>)> {
+    const PT_LOAD: u64 = 1;
+
+    let elf = Elf::parse(elf)?;
+    let layout = &elf.layout;
+
+    let entry = elf.read(layout.e_entry, elf.word)?;
+    let (table, entry_size, count) = (
+        elf.read(layout.e_phoff, elf.word)?,
+        elf.read(layout.e_phentsize, 2)?,
+        elf.read(layout.e_phnum, 2)?,
+    );
+
+    let mut segments = Vec::new();
+    for i in 0..count {
+        let header = elf.offset(table + i * entry_size)?;
+        if elf.read(header, 4)? != PT_LOAD {
+            continue;
+        }
+
+        let address = elf.read(header + layout.p_vaddr, elf.word)?;
+        let offset = elf.offset(elf.read(header + layout.p_offset, elf.word)?)?;
+        let size = elf.offset(elf.read(header + layout.p_filesz, elf.word)?)?;
+
+        if size > 0 {
+            let contents = elf.bytes.get(offset..offset + size).ok_or_else(bad_elf)?;
+            segments.push((address, contents));
+        }
+    }
+
+    Ok((entry, segments))
+}
+
+/// Un archivo ELF del cual se leen encabezados.
+///
+/// Solo se lee lo mínimo necesario de los encabezados de ELF32 y ELF64,
+/// en cualquier orden de bytes.
+struct Elf<'a> {
+    bytes: &'a [u8],
+    is_le: bool,
+    word: usize,
+    layout: &'static ElfLayout,
+}
+
+/// Desplazamientos de campos que dependen de la clase de ELF.
+struct ElfLayout {
+    e_entry: usize,
+    e_phoff: usize,
+    e_shoff: usize,
+    e_phentsize: usize,
+    e_phnum: usize,
+    e_shentsize: usize,
+    e_shnum: usize,
+    e_shstrndx: usize,
+    p_offset: usize,
+    p_vaddr: usize,
+    p_filesz: usize,
+    sh_type: usize,
+    sh_flags: usize,
+    sh_addr: usize,
+    sh_offset: usize,
+    sh_size: usize,
+    sh_link: usize,
+    st_value: usize,
+    sym_size: usize,
+}
+
+impl<'a> Elf<'a> {
+    const LAYOUT_32: ElfLayout = ElfLayout {
+        e_entry: 0x18,
+        e_phoff: 0x1c,
+        e_shoff: 0x20,
+        e_phentsize: 0x2a,
+        e_phnum: 0x2c,
+        e_shentsize: 0x2e,
+        e_shnum: 0x30,
+        e_shstrndx: 0x32,
+        p_offset: 0x04,
+        p_vaddr: 0x08,
+        p_filesz: 0x10,
+        sh_type: 0x04,
+        sh_flags: 0x08,
+        sh_addr: 0x0c,
+        sh_offset: 0x10,
+        sh_size: 0x14,
+        sh_link: 0x18,
+        st_value: 0x04,
+        sym_size: 0x10,
+    };
+
+    const LAYOUT_64: ElfLayout = ElfLayout {
+        e_entry: 0x18,
+        e_phoff: 0x20,
+        e_shoff: 0x28,
+        e_phentsize: 0x36,
+        e_phnum: 0x38,
+        e_shentsize: 0x3a,
+        e_shnum: 0x3c,
+        e_shstrndx: 0x3e,
+        p_offset: 0x08,
+        p_vaddr: 0x10,
+        p_filesz: 0x20,
+        sh_type: 0x04,
+        sh_flags: 0x08,
+        sh_addr: 0x10,
+        sh_offset: 0x18,
+        sh_size: 0x20,
+        sh_link: 0x28,
+        st_value: 0x08,
+        sym_size: 0x18,
+    };
+
+    fn parse(bytes: &'a [u8]) -> io::Result<Self> {
+        if bytes.get(..4) != Some(b"\x7fELF") {
+            return Err(bad_elf());
+        }
+
+        let is_64 = bytes.get(4) == Some(&2);
+        let (word, layout) = if is_64 {
+            (8, &Self::LAYOUT_64)
+        } else {
+            (4, &Self::LAYOUT_32)
+        };
+
+        Ok(Elf {
+            bytes,
+            is_le: bytes.get(5) == Some(&1),
+            word,
+            layout,
+        })
+    }
+
+    /// Lee un entero de `width` bytes en el orden de bytes del archivo.
+    fn read<O>(&self, offset: O, width: usize) -> io::Result<u64>
+    where
+        O: TryInto<usize>,
+    {
+        let offset = offset.try_into().map_err(|_| bad_elf())?;
+        let bytes = self.bytes.get(offset..offset + width).ok_or_else(bad_elf)?;
+
+        let mut padded = [0; 8];
+        if self.is_le {
+            padded[..width].copy_from_slice(bytes);
+            Ok(u64::from_le_bytes(padded))
+        } else {
+            padded[8 - width..].copy_from_slice(bytes);
+            Ok(u64::from_be_bytes(padded))
+        }
+    }
+
+    /// Convierte un valor leído del archivo en un desplazamiento.
+    fn offset(&self, value: u64) -> io::Result<usize> {
+        value.try_into().map_err(|_| bad_elf())
+    }
+
+    /// Desplazamientos de cada encabezado de sección.
+    fn section_headers(&self) -> io::Result<Vec<usize>> {
+        let layout = self.layout;
+        let table = self.offset(self.read(layout.e_shoff, self.word)?)?;
+        let entry_size = self.offset(self.read(layout.e_shentsize, 2)?)?;
+        let count = self.offset(self.read(layout.e_shnum, 2)?)?;
+
+        Ok((0..count).map(|i| table + i * entry_size).collect())
+    }
+}
+
+fn bad_elf() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed ELF file")
+}
+
+/// Directorio `lib/`, con un subdirectorio por plataforma.
+///
+/// Se busca, en orden, en [`LIB_DIR_VAR`], junto al ejecutable del
+/// compilador y finalmente en el valor de [`LIB_DIR_VAR`] al compilar.
+fn library_dir() -> Result<PathBuf, LinkerError> {
+    if let Some(dir) = env::var_os(LIB_DIR_VAR) {
+        return Ok(PathBuf::from(dir));
+    }
+
+    // En macOS `current_exe()` no resuelve enlaces simbólicos
+    let beside_exe = env::current_exe()
+        .and_then(fs::canonicalize)
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join("lib")))
+        .filter(|dir| dir.is_dir());
+
+    beside_exe
+        .or_else(|| option_env!("ANIMLED_LIB_DIR").map(PathBuf::from))
+        .ok_or(LinkerError::NoLibraries)
+}
+
+/// Genera una ruta temporal que no comparte ningún otro enlazado.
+///
+/// Varias plataformas pueden enlazarse en paralelo dentro del mismo
+/// proceso, por lo que el identificador de proceso no basta.
+fn temporary(prefix: &str, suffix: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    env::temp_dir().join(format!("{}.{}.{}{}", prefix, process::id(), count, suffix))
+}
+
+/// Escribe los linker scripts incluidos y el `memory.x` generado a
+/// partir de `layout` a un directorio temporal.
+///
+/// Se retorna `None` si la plataforma no requiere scripts propios.
+fn materialize_scripts(
+    scripts: &[(&'static str, &'static str)],
+    layout: &MemoryLayout,
+) -> Result<Option<PathBuf>, LinkerError> {
+    if scripts.is_empty() {
+        return Ok(None);
+    }
+
+    let dir = temporary("compiler-ld", "");
+    fs::create_dir_all(&dir)?;
+
+    for (name, contents) in scripts.iter() {
+        fs::write(dir.join(name), contents)?;
+    }
+
+    if let Some(memory) = layout.script() {
+        fs::write(dir.join("memory.x"), memory)?;
+    }
+
+    Ok(Some(dir))
+}
+
+impl Conversion {
+    /// Convierte el ELF intermedio y lo elimina, aún si la conversión falla.
+    fn run(self) -> Result<(), LinkerError> {
+        let params = self.platform.link_params();
+        let bfd_name = match self.format {
+            OutputFormat::Elf => unreachable!(),
+            OutputFormat::Hex => "ihex",
+            OutputFormat::Bin => "binary",
+        };
+
+        let mut command = Command::new(params.objcopy);
+        command.args(["-O", bfd_name]);
+
+        match self.format {
+            OutputFormat::Hex => command.args(params.hex_args),
+            _ => command.args(params.bin_args),
+        };
+
+        let status = command.arg(&self.elf).arg(&self.output).status();
+        fs::remove_file(&self.elf)?;
+
+        let status = status?;
         if status.success() {
             Ok(())
         } else {
-            Err(LinkerError::Failed(status))
+            Err(LinkerError::ConversionFailed(status))
         }
     }
 }
 
+/// El ELF intermedio no debe quedar atrás si el enlazado falla o si
+/// la conversión nunca se ejecuta.
+impl Drop for Conversion {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.elf);
+    }
+}
+
 /// Información acerca del enlazador requerido para cada plataforma.
 struct Parameters {
     /// Nombre de la plataforma.
@@ -167,35 +1468,236 @@ struct Parameters {
     /// Comando de enlazado.
     command: &'static str,
 
+    /// Forma en que el comando de enlazado recibe sus argumentos.
+    driver: Driver,
+
     /// Argumentos adicionales al comando de enlazado que se necesitan
     /// para esta plataforma.
     extra_args: &'static [&'static str],
+
+    /// Bibliotecas de las que depende `libruntime`, las cuales se
+    /// indican luego de esta.
+    libraries: &'static [&'static str],
+
+    /// Linker scripts incluidos, como pares de nombre y contenido.
+    ///
+    /// El primero es el script principal, los demás son alcanzados
+    /// mediante `INCLUDE` desde este.
+    scripts: &'static [(&'static str, &'static str)],
+
+    /// Comando de conversión de formatos de objeto.
+    objcopy: &'static str,
+
+    /// Argumentos adicionales a `objcopy` para emitir Intel HEX.
+    hex_args: &'static [&'static str],
+
+    /// Argumentos adicionales a `objcopy` para emitir binario crudo.
+    bin_args: &'static [&'static str],
+}
+
+/// Dialecto de argumentos de un comando de enlazado.
+///
+/// En todos los casos el comando es un driver compatible con `gcc`,
+/// ya que debe ensamblar la sintaxis de GNU as. Con MSVC, `cl.exe` no
+/// la admite, por lo cual se utiliza `clang`, el cual a su vez invoca
+/// a `link.exe`.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Driver {
+    /// `gcc` con GNU ld, el cual produce ejecutables ELF.
+    Gnu,
+
+    /// `gcc` de MinGW-w64, con GNU ld pero produciendo ejecutables PE.
+    MinGw,
+
+    /// `clang` con el enlazador de MSVC.
+    Msvc,
+}
+
+impl Driver {
+    /// Argumento que precede a la ruta del mapa de enlazado.
+    fn map_arg(self) -> &'static str {
+        match self {
+            Driver::Gnu | Driver::MinGw => "-Wl,-Map,",
+            Driver::Msvc => "-Wl,/MAP:",
+        }
+    }
+
+    /// Argumento que elimina secciones a las que nada refiere.
+    fn gc_sections_arg(self) -> &'static str {
+        match self {
+            Driver::Gnu | Driver::MinGw => "-Wl,--gc-sections",
+            Driver::Msvc => "-Wl,/OPT:REF",
+        }
+    }
+
+    /// Argumentos que remueven símbolos de depuración. `link.exe` solo
+    /// los emite, aparte del ejecutable, si se le indica `/DEBUG`.
+    fn strip_args(self) -> &'static [&'static str] {
+        match self {
+            Driver::Gnu | Driver::MinGw => &["-s"],
+            Driver::Msvc => &[],
+        }
+    }
 }
 
 impl Platform {
     /// Enumera los detalles del comano de enlazado por plataforma.
     fn link_params(self) -> Parameters {
         match self {
+            // El runtime de Rust en Windows depende de estas bibliotecas
+            // del sistema. Se enlaza contra el CRT dinámico (msvcrt), que
+            // es el que utiliza rustc, en vez del estático que clang
+            // solicita por omisión.
+            Platform::Native if cfg!(all(windows, target_env = "msvc")) => Parameters {
+                name: "native",
+                command: "clang",
+                driver: Driver::Msvc,
+                extra_args: &["-Wl,/NODEFAULTLIB:libcmt"],
+                libraries: &[
+                    "-lmsvcrt",
+                    "-lws2_32",
+                    "-luserenv",
+                    "-lbcrypt",
+                    "-lntdll",
+                    "-ladvapi32",
+                ],
+                scripts: &[],
+
+                objcopy: "objcopy",
+                hex_args: &[],
+                bin_args: &[],
+            },
+
+            Platform::Native if cfg!(windows) => Parameters {
+                name: "native",
+                command: "gcc",
+                driver: Driver::MinGw,
+                extra_args: &[],
+                libraries: &["-lws2_32", "-luserenv", "-lbcrypt", "-lntdll", "-ladvapi32"],
+                scripts: &[],
+
+                objcopy: "objcopy",
+                hex_args: &[],
+                bin_args: &[],
+            },
+
+            // Tanto en x86-64 como en AArch64, lib/native contiene
+            // libruntime compilada para el anfitrión
             Platform::Native => Parameters {
                 name: "native",
                 command: "gcc",
+                driver: Driver::Gnu,
 
                 // rustc usa libpthread para hilos, libdl para enlazado
-                // lazy en tiempo de ejecución y libm para floats
-                extra_args: &["-pthread", "-ldl", "-lm"],
+                // lazy en tiempo de ejecución y libm para floats. Las
+                // bibliotecas deben seguir a libruntime, de lo contrario
+                // ld no resuelve símbolos como `powf()`
+                extra_args: &["-pthread"],
+                libraries: &["-ldl", "-lm"],
+                scripts: &[],
+
+                objcopy: "objcopy",
+                hex_args: &[],
+                bin_args: &[],
             },
 
             Platform::Esp8266 => Parameters {
                 name: "esp8266",
                 command: "xtensa-lx106-elf-gcc",
+                driver: Driver::Gnu,
 
                 // Esta es una plataforma #![no_std], por lo cual -nostartfiles
                 // evita enlazar objetos de bootstrap que asumen un entorno
-                // hosted. El linker script link.x dispone las secciones del
-                // ejecutable en la distribución de rangos de flash y RAM particulares
-                // al ESP8266.
-                extra_args: &["-nostartfiles", "-Wl,-Tlink.x"],
+                // hosted.
+                extra_args: &["-nostartfiles"],
+                libraries: &[],
+
+                // El linker script link.x dispone las secciones del ejecutable
+                // en la distribución de rangos de flash y RAM particulares al
+                // ESP8266. Provienen de xtensa-lx-rt y esp8266-hal.
+                scripts: &[
+                    ("link.x", include_str!("../ld/esp8266/link.x")),
+                    ("exception.x", include_str!("../ld/esp8266/exception.x")),
+                    ("alias.x", include_str!("../ld/esp8266/alias.x")),
+                ],
+
+                // La flash se mapea en memoria a partir de 0x40200000, por lo
+                // cual .text (irom_seg) se reubica a su desplazamiento real
+                // dentro de la flash. Las secciones de RAM conservan sus
+                // direcciones, que son las que espera el bootloader. Un binario
+                // crudo solo puede contener la flash, ya que incluir RAM
+                // implicaría un hueco de cerca de 1GB, y se rellena con 0xff
+                // por ser el estado de la flash borrada.
+                objcopy: "xtensa-lx106-elf-objcopy",
+                hex_args: &["--change-section-lma", ".text-0x40200000"],
+                bin_args: &[
+                    "--only-section",
+                    ".text",
+                    "--change-section-lma",
+                    ".text-0x40200000",
+                    "--gap-fill",
+                    "0xff",
+                ],
+            },
+
+            Platform::Stm32 => Parameters {
+                name: "stm32",
+                command: "arm-none-eabi-gcc",
+                driver: Driver::Gnu,
+
+                // El código emitido es Thumb-2 sin instrucciones de punto
+                // flotante, lo cual concuerda con thumbv7em-none-eabi
+                extra_args: &["-mcpu=cortex-m4", "-mthumb", "-nostartfiles"],
+                libraries: &[],
+
+                // link.x proviene de cortex-m-rt y device.x de stm32f4,
+                // este último con los vectores de interrupción del chip
+                scripts: &[
+                    ("link.x", include_str!("../ld/stm32/link.x")),
+                    ("device.x", include_str!("../ld/stm32/device.x")),
+                ],
+
+                // .data se carga desde flash (AT>FLASH) y .bss no ocupa
+                // espacio en la imagen, por lo cual objcopy ya produce una
+                // imagen que inicia en 0x08000000 sin ajustes adicionales
+                objcopy: "arm-none-eabi-objcopy",
+                hex_args: &[],
+                bin_args: &["--gap-fill", "0xff"],
             },
+
+            Platform::Esp32c3 => Parameters {
+                name: "esp32c3",
+                command: "riscv32-esp-elf-gcc",
+                driver: Driver::Gnu,
+
+                // RV32IMC sin atómicos ni punto flotante, al igual que
+                // riscv32imc-unknown-none-elf
+                extra_args: &["-march=rv32imc", "-mabi=ilp32", "-nostartfiles"],
+                libraries: &[],
+
+                // Provienen de esp32c3-hal (variante para arranque mediante
+                // bootloader), que a su vez incluye el script de riscv-rt
+                scripts: &[
+                    ("linkall.x", include_str!("../ld/esp32c3/linkall.x")),
+                    (
+                        "bl-riscv-link.x",
+                        include_str!("../ld/esp32c3/bl-riscv-link.x"),
+                    ),
+                    (
+                        "hal-defaults.x",
+                        include_str!("../ld/esp32c3/hal-defaults.x"),
+                    ),
+                ],
+
+                // El bootloader espera una imagen en el formato de esptool
+                // (`esptool.py elf2image`), no un binario crudo. Aún así, el
+                // binario que se produce aquí es útil para inspección.
+                objcopy: "riscv32-esp-elf-objcopy",
+                hex_args: &[],
+                bin_args: &["--gap-fill", "0xff"],
+            },
+
+            Platform::Web => unreachable!("WebAssembly is assembled in-process"),
         }
     }
 }
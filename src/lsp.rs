@@ -0,0 +1,524 @@
+//! Servidor de lenguaje.
+//!
+//! `compiler lsp` implementa un subconjunto del Language Server
+//! Protocol sobre stdin y stdout, de forma que cualquier editor con
+//! un cliente LSP pueda reutilizar las fases delanteras:
+//!
+//! - Diagnósticos tras cada cambio a un documento abierto.
+//! - Ir a la definición de un procedimiento. Si el cursor está sobre
+//!   una llamada ya resuelta, solo se ofrece la sobrecarga invocada.
+//! - Tipos de variables y firmas de procedimientos al posar el cursor.
+//!
+//! Los documentos se sincronizan completos en cada cambio. Todo
+//! análisis se repite desde cero, lo cual es aceptable para programas
+//! del tamaño usual.
+//!
+//! # Posiciones
+//! LSP cuenta líneas y columnas desde cero, estas últimas en unidades
+//! UTF-16. Una [`Location`] las cuenta desde uno y en caracteres,
+//! tras expandir tabulaciones. Las conversiones requieren el texto
+//! original de cada línea.
+
+use anyhow::{self, bail, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use compiler::{
+    lex::{Lexer, Token},
+    parse::{self, Ast},
+    semantic::Symbol,
+    source::{self, Located, Location},
+};
+
+/// Misma distancia entre paradas de tabulación que usa [`source`].
+const TAB_STOP: u32 = 4;
+
+const SEVERITY_ERROR: u32 = 1;
+const SEVERITY_WARNING: u32 = 2;
+
+/// Sincronización de documentos completos.
+const SYNC_FULL: u32 = 1;
+
+/// Error de JSON-RPC.
+#[derive(Serialize)]
+struct RpcError {
+    code: i32,
+    message: String,
+}
+
+impl RpcError {
+    const INVALID_REQUEST: i32 = -32600;
+    const METHOD_NOT_FOUND: i32 = -32601;
+    const INVALID_PARAMS: i32 = -32602;
+
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+type Rpc<T> = Result<T, RpcError>;
+
+#[derive(Deserialize)]
+struct TextDocumentIdentifier {
+    uri: String,
+}
+
+#[derive(Deserialize)]
+struct TextDocumentItem {
+    uri: String,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct ContentChange {
+    text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidOpen {
+    text_document: TextDocumentItem,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidChange {
+    text_document: TextDocumentIdentifier,
+    content_changes: Vec<ContentChange>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidClose {
+    text_document: TextDocumentIdentifier,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TextDocumentPosition {
+    text_document: TextDocumentIdentifier,
+    position: Position,
+}
+
+/// Posición según LSP.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+struct Position {
+    line: u32,
+    character: u32,
+}
+
+/// Documentos abiertos y estado del protocolo.
+#[derive(Default)]
+struct Server {
+    documents: HashMap<String, String>,
+    shutdown: bool,
+}
+
+/// Atiende a un cliente por stdin y stdout hasta recibir `exit`.
+pub fn serve() -> anyhow::Result<()> {
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+
+    let stdout = std::io::stdout();
+    let mut output = stdout.lock();
+
+    let mut server = Server::default();
+    while let Some(message) = receive(&mut input)? {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        match message.get("id") {
+            // Solicitudes
+            Some(id) => {
+                let response = match server.request(method, params) {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+                };
+
+                send(&mut output, &response)?;
+            }
+
+            // Notificaciones
+            None if method == "exit" => break,
+            None => {
+                if let Some(notification) = server.notify(method, params) {
+                    send(&mut output, &notification)?;
+                }
+            }
+        }
+    }
+
+    if !server.shutdown {
+        bail!("Client exited without requesting shutdown");
+    }
+
+    Ok(())
+}
+
+/// Lee un mensaje. Retorna `None` al cerrarse la entrada.
+fn receive<R: BufRead>(input: &mut R) -> anyhow::Result<Option<Value>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            let value = value.trim().parse().context("Bad Content-Length header")?;
+            length = Some(value);
+        }
+    }
+
+    let length = match length {
+        Some(length) => length,
+        None => bail!("Message lacks a Content-Length header"),
+    };
+
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+
+    let message = serde_json::from_slice(&body).context("Malformed message")?;
+    Ok(Some(message))
+}
+
+fn send<W: Write>(output: &mut W, message: &Value) -> anyhow::Result<()> {
+    let body = serde_json::to_vec(message)?;
+
+    write!(output, "Content-Length: {}\r\n\r\n", body.len())?;
+    output.write_all(&body)?;
+    output.flush()?;
+
+    Ok(())
+}
+
+fn params<T: for<'de> Deserialize<'de>>(params: Value) -> Rpc<T> {
+    serde_json::from_value(params)
+        .map_err(|error| RpcError::new(RpcError::INVALID_PARAMS, error.to_string()))
+}
+
+impl Server {
+    fn request(&mut self, method: &str, params: Value) -> Rpc<Value> {
+        if self.shutdown {
+            let message = "Server is shutting down";
+            return Err(RpcError::new(RpcError::INVALID_REQUEST, message));
+        }
+
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": SYNC_FULL,
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                },
+
+                "serverInfo": {
+                    "name": "AnimationLed",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            })),
+
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+
+            "textDocument/definition" => self.definition(self::params(params)?),
+            "textDocument/hover" => self.hover(self::params(params)?),
+
+            _ => {
+                let message = format!("Unsupported method: {}", method);
+                Err(RpcError::new(RpcError::METHOD_NOT_FOUND, message))
+            }
+        }
+    }
+
+    /// Atiende una notificación, posiblemente respondiendo con otra.
+    fn notify(&mut self, method: &str, params: Value) -> Option<Value> {
+        // Las notificaciones no tienen forma de reportar errores
+        let uri = match method {
+            "textDocument/didOpen" => {
+                let DidOpen { text_document } = self::params(params).ok()?;
+                let TextDocumentItem { uri, text } = text_document;
+
+                self.documents.insert(uri.clone(), text);
+                uri
+            }
+
+            "textDocument/didChange" => {
+                let DidChange {
+                    text_document,
+                    content_changes,
+                } = self::params(params).ok()?;
+
+                let text = content_changes.into_iter().last()?.text;
+                self.documents.insert(text_document.uri.clone(), text);
+                text_document.uri
+            }
+
+            "textDocument/didClose" => {
+                let DidClose { text_document } = self::params(params).ok()?;
+                self.documents.remove(&text_document.uri);
+
+                // Se retiran los diagnósticos del documento
+                return Some(publish(&text_document.uri, Vec::new()));
+            }
+
+            _ => return None,
+        };
+
+        let text = &self.documents[&uri];
+        Some(publish(&uri, diagnostics(&uri, text)))
+    }
+
+    fn definition(&self, params: TextDocumentPosition) -> Rpc<Value> {
+        let (uri, text, line, column) = self.locate(&params)?;
+        let (tokens, ast) = match analyze(text) {
+            Some(analysis) => analysis,
+            None => return Ok(Value::Null),
+        };
+
+        let name = tokens
+            .iter()
+            .find(|token| contains(token.location(), line, column))
+            .and_then(|token| match token.as_ref() {
+                Token::Id(id) => Some(id),
+                _ => None,
+            });
+
+        let name = match name {
+            Some(name) => name,
+            None => return Ok(Value::Null),
+        };
+
+        let references = ast.references();
+        let signature_at = |location: &Location| {
+            references
+                .iter()
+                .filter(|reference| same(reference.location(), location))
+                .find_map(|reference| match &reference.symbol {
+                    Symbol::Proc {
+                        parameters,
+                        returns,
+                    } => Some((parameters, returns)),
+                    Symbol::Var(_) => None,
+                })
+        };
+
+        let called = references
+            .iter()
+            .find(|reference| contains(reference.location(), line, column))
+            .and_then(|reference| signature_at(reference.location()));
+
+        let definitions: Vec<_> = ast
+            .iter()
+            .map(parse::Procedure::name)
+            .filter(|procedure| procedure.as_ref() == name)
+            .filter(
+                |procedure| match (called, signature_at(procedure.location())) {
+                    (Some(called), Some(defined)) => called == defined,
+                    _ => true,
+                },
+            )
+            .map(|procedure| {
+                json!({
+                    "uri": uri,
+                    "range": range(text, procedure.location()),
+                })
+            })
+            .collect();
+
+        Ok(json!(definitions))
+    }
+
+    fn hover(&self, params: TextDocumentPosition) -> Rpc<Value> {
+        let (_, text, line, column) = self.locate(&params)?;
+        let reference = analyze(text).and_then(|(_, ast)| {
+            ast.references()
+                .into_iter()
+                .find(|reference| contains(reference.location(), line, column))
+        });
+
+        let reference = match reference {
+            Some(reference) => reference,
+            None => return Ok(Value::Null),
+        };
+
+        let name = &reference.name;
+        let hover = match &reference.symbol {
+            Symbol::Var(typ) => format!("{}: {}", name, typ),
+
+            Symbol::Proc {
+                parameters,
+                returns,
+            } => {
+                let parameters: Vec<_> = parameters.iter().map(ToString::to_string).collect();
+                let returns = returns.map(|typ| format!(": {}", typ));
+
+                format!(
+                    "procedure {}({}){}",
+                    name,
+                    parameters.join(", "),
+                    returns.unwrap_or_default()
+                )
+            }
+        };
+
+        Ok(json!({
+            "contents": {
+                "kind": "markdown",
+                "value": format!("```\n{}\n```", hover),
+            },
+
+            "range": range(text, reference.location()),
+        }))
+    }
+
+    /// Obtiene el documento al que se refiere una solicitud y la posición
+    /// equivalente en términos de [`Location`].
+    fn locate<'a>(&'a self, params: &'a TextDocumentPosition) -> Rpc<(&'a str, &'a str, u32, u32)> {
+        let uri = &params.text_document.uri;
+        let text = self.documents.get(uri).ok_or_else(|| {
+            let message = format!("Document is not open: {}", uri);
+            RpcError::new(RpcError::INVALID_PARAMS, message)
+        })?;
+
+        let Position { line, character } = params.position;
+        let column = to_column(line_text(text, line + 1), character);
+
+        Ok((uri, text, line + 1, column))
+    }
+}
+
+/// Ejecuta el front end y convierte sus errores o advertencias.
+fn diagnostics(uri: &str, text: &str) -> Vec<Value> {
+    let (diagnostics, severity) = match compiler::frontend_with_warnings(&mut text.as_bytes(), uri)
+    {
+        Ok((_, warnings)) => (warnings, SEVERITY_WARNING),
+        Err(errors) => (errors, SEVERITY_ERROR),
+    };
+
+    diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let mut message = diagnostic.source().to_string();
+            if let Some(help) = diagnostic.help() {
+                message.push_str("\nhelp: ");
+                message.push_str(&help);
+            }
+
+            json!({
+                "range": range(text, diagnostic.location()),
+                "severity": severity,
+                "source": "animationled",
+                "message": message,
+            })
+        })
+        .collect()
+}
+
+fn publish(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": {
+            "uri": uri,
+            "diagnostics": diagnostics,
+        },
+    })
+}
+
+/// Obtiene los tokens y el AST de un documento, si no tiene errores
+/// léxicos ni sintácticos.
+fn analyze(text: &str) -> Option<(Vec<Located<Token>>, Ast)> {
+    let (start, stream) = source::consume(text.as_bytes(), "");
+    let tokens = Lexer::new(start.clone(), stream).try_exhaustive().ok()?;
+    let ast = parse::parse(tokens.iter(), start).ok()?;
+
+    Some((tokens, ast))
+}
+
+/// Convierte una ubicación a un rango de LSP.
+fn range(text: &str, location: &Location) -> Value {
+    let position = |line: u32, column: u32| Position {
+        line: line.saturating_sub(1),
+        character: to_character(line_text(text, line), column),
+    };
+
+    let (start, end) = (location.start(), location.end());
+    json!({
+        "start": position(start.line(), start.column()),
+        "end": position(end.line(), end.column()),
+    })
+}
+
+/// Determina si una línea y columna se encuentran dentro de una ubicación.
+fn contains(location: &Location, line: u32, column: u32) -> bool {
+    let (start, end) = (location.start(), location.end());
+    let here = (line, column);
+
+    (start.line(), start.column()) <= here && here < (end.line(), end.column())
+}
+
+fn same(a: &Location, b: &Location) -> bool {
+    a.start() == b.start() && a.end() == b.end()
+}
+
+/// Texto de una línea, contando desde uno.
+fn line_text(text: &str, line: u32) -> &str {
+    text.lines()
+        .nth(line.saturating_sub(1) as usize)
+        .unwrap_or_default()
+}
+
+/// Columnas que ocupa un carácter tras expandir tabulaciones.
+fn width(c: char, column: u32) -> u32 {
+    match c {
+        '\t' => TAB_STOP - (column - 1) % TAB_STOP,
+        _ => 1,
+    }
+}
+
+/// Convierte una columna de LSP a una de [`Location`].
+fn to_column(line: &str, character: u32) -> u32 {
+    let (mut units, mut column) = (0, 1);
+    for c in line.chars() {
+        if units >= character {
+            break;
+        }
+
+        units += c.len_utf16() as u32;
+        column += width(c, column);
+    }
+
+    column
+}
+
+/// Convierte una columna de [`Location`] a una de LSP.
+fn to_character(line: &str, column: u32) -> u32 {
+    let (mut units, mut current) = (0, 1);
+    for c in line.chars() {
+        if current >= column {
+            break;
+        }
+
+        units += c.len_utf16() as u32;
+        current += width(c, current);
+    }
+
+    units
+}
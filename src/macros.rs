@@ -25,7 +25,7 @@
 /// ```
 macro_rules! dispatch_arch {
     ($type:ident: $arch:expr => $expr:expr) => {{
-        use crate::arch::{Arch, Xtensa, X86_64};
+        use crate::arch::{Aarch64, Arch, Arm, RiscV, Win64, Xtensa, X86_64};
 
         match $arch {
             Arch::X86_64 => {
@@ -33,10 +33,32 @@ macro_rules! dispatch_arch {
                 $expr
             }
 
+            Arch::Win64 => {
+                type $type<'target> = Win64<'target>;
+                $expr
+            }
+
+            Arch::Aarch64 => {
+                type $type<'target> = Aarch64<'target>;
+                $expr
+            }
+
             Arch::Xtensa => {
                 type $type<'target> = Xtensa<'target>;
                 $expr
             }
+
+            Arch::Arm => {
+                type $type<'target> = Arm<'target>;
+                $expr
+            }
+
+            Arch::RiscV => {
+                type $type<'target> = RiscV<'target>;
+                $expr
+            }
+
+            Arch::Wasm32 => unreachable!("WebAssembly has no register-based emitter"),
         }
     }};
 }
@@ -4,36 +4,238 @@
 //! compilación y expone una CLI.
 
 use anyhow::{self, bail, Context};
-use clap::{self, crate_version, Arg};
+use clap::{self, crate_version, App, AppSettings, Arg};
 
 use std::{
-    fs::File,
-    io::{BufRead, BufReader},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
     str::FromStr,
+    thread,
     time::Instant,
 };
 
+#[cfg(unix)]
+mod daemon;
+mod lsp;
+mod watch;
+
 use compiler::{
-    error::Diagnostics,
-    ir::Program,
-    lex::Lexer,
-    link::{LinkOptions, Linker, Platform},
-    parse, source, target,
+    doc::{self, DocFormat},
+    flash::{self, FlashError, Image, Loader},
+    grade::{self, Scenario},
+    interp::{self, Display, Host, Machine, Value},
+    ir::{cfg::Cfg, Program},
+    ledmap::SourceMap,
+    link::{LinkOptions, Linker, OutputFormat, Platform, ScriptOptions},
+    opt::{IrStage, PassManager, Transform},
+    repl::{self, EvalError, Evaluation, Session},
+    sandbox, semantic,
+    snapshot::Snapshot,
+    stats::Statistics,
+    target, FrontendOptions,
 };
 
 fn main() -> anyhow::Result<()> {
     // Parsing de CLI
     let args = clap::App::new("AnimationLed compiler")
         .version(crate_version!())
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(
+            App::new("grade")
+                .about("Evaluate programs against an expected animation")
+                .arg(
+                    Arg::new("scenario")
+                        .required(true)
+                        .value_name("SCENARIO")
+                        .about("Scenario file (TOML)"),
+                )
+                .arg(
+                    Arg::new("programs")
+                        .required(true)
+                        .multiple(true)
+                        .value_name("PROGRAM")
+                        .about("Programs to evaluate"),
+                ),
+        )
+        .subcommand(
+            App::new("test")
+                .about("Run programs and compare their frames against .expected snapshots")
+                .arg(
+                    Arg::new("programs")
+                        .required(true)
+                        .multiple(true)
+                        .value_name("PROGRAM")
+                        .about("Programs to test"),
+                )
+                .arg(
+                    Arg::new("bless")
+                        .long("bless")
+                        .about("Overwrite snapshots with the frames that were observed"),
+                )
+                .arg(
+                    Arg::new("stop-at")
+                        .long("stop-at")
+                        .value_name("MS")
+                        .takes_value(true)
+                        .default_value("10000")
+                        .about("Virtual time after which each program is stopped"),
+                ),
+        )
+        .subcommand(
+            App::new("run")
+                .about("Interpret a program without assembling or linking it")
+                .arg(
+                    Arg::new("input")
+                        .required(true)
+                        .value_name("INPUT")
+                        .about("Input file"),
+                ),
+        )
+        .subcommand(
+            App::new("fmt")
+                .about("Rewrite programs in canonical format")
+                .arg(
+                    Arg::new("files")
+                        .required(true)
+                        .multiple(true)
+                        .value_name("FILE")
+                        .about("Programs to format"),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .about("List unformatted programs instead of rewriting them"),
+                ),
+        )
+        .subcommand(
+            App::new("doc")
+                .about("Summarize the procedures and constants of a program")
+                .arg(
+                    Arg::new("input")
+                        .required(true)
+                        .value_name("INPUT")
+                        .about("Input file"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .takes_value(true)
+                        .default_value("markdown")
+                        .possible_values(&["markdown", "html"])
+                        .about("Output format"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .value_name("OUTPUT")
+                        .takes_value(true)
+                        .about("Output file (defaults to stdout)"),
+                ),
+        )
+        .subcommand(App::new("repl").about("Evaluate statements and expressions interactively"))
+        .subcommand(App::new("lsp").about("Run a language server over stdin and stdout"))
+        .subcommand(
+            App::new("decode")
+                .about("Translate addresses in a crash log to source lines")
+                .arg(
+                    Arg::new("ledmap")
+                        .required(true)
+                        .value_name("LEDMAP")
+                        .about("Source map written by --emit-ledmap"),
+                )
+                .arg(
+                    Arg::new("log")
+                        .value_name("LOG")
+                        .about("Crash log (defaults to stdin)"),
+                )
+                .arg(
+                    Arg::new("line")
+                        .long("line")
+                        .value_name("LINE")
+                        .takes_value(true)
+                        .conflicts_with("log")
+                        .about("List address ranges of a source line instead"),
+                ),
+        )
+        .subcommand(
+            App::new("demangle")
+                .about("Translate compiler-generated symbols back to procedure signatures")
+                .arg(
+                    Arg::new("symbols")
+                        .multiple(true)
+                        .value_name("SYMBOL")
+                        .about("Symbols to translate (filters stdin if omitted)"),
+                ),
+        )
+        .subcommand(
+            App::new("flash")
+                .about("Write an ESP8266 executable to a board over its serial bootloader")
+                .arg(
+                    Arg::new("executable")
+                        .required(true)
+                        .value_name("EXECUTABLE")
+                        .about("Executable linked with --target esp8266"),
+                )
+                .arg(
+                    Arg::new("port")
+                        .long("port")
+                        .short('p')
+                        .value_name("PORT")
+                        .takes_value(true)
+                        .default_value("/dev/ttyUSB0")
+                        .about("Serial port of the board"),
+                )
+                .arg(
+                    Arg::new("baud")
+                        .long("baud")
+                        .short('b')
+                        .value_name("RATE")
+                        .takes_value(true)
+                        .default_value("115200")
+                        .possible_values(&["9600", "57600", "115200", "230400", "460800", "921600"])
+                        .about("Baud rate for flashing"),
+                ),
+        )
+        .subcommand(
+            App::new("doctor")
+                .about("Check the toolchain of each platform and look for ESP8266 boards")
+                .arg(
+                    Arg::new("serial")
+                        .long("serial")
+                        .about("Probe serial ports for ESP8266 boards"),
+                )
+                .arg(
+                    Arg::new("port")
+                        .long("port")
+                        .short('p')
+                        .value_name("PORT")
+                        .takes_value(true)
+                        .requires("serial")
+                        .about("Serial port to probe instead of likely ones"),
+                )
+                .arg(
+                    Arg::new("baud")
+                        .long("baud")
+                        .short('b')
+                        .value_name("RATE")
+                        .takes_value(true)
+                        .requires("serial")
+                        .possible_values(&["9600", "57600", "115200", "230400", "460800", "921600"])
+                        .about("Baud rate for the bootloader handshake instead of trying each one"),
+                ),
+        )
         .arg(
             Arg::new("target")
                 .short('t')
                 .long("target")
                 .value_name("PLATFORM")
                 .takes_value(true)
+                .multiple_occurrences(true)
                 .default_value("native")
-                .possible_values(&["native", "esp8266"])
-                .about("Target platform"),
+                .possible_values(&["native", "esp8266", "stm32", "esp32c3", "web"])
+                .about("Target platform, may be repeated to build for several at once"),
         )
         .arg(
             Arg::new("asm")
@@ -46,37 +248,448 @@ fn main() -> anyhow::Result<()> {
                 .long("ir")
                 .about("Show IR instead of linking"),
         )
+        .arg(
+            Arg::new("dump-cfg")
+                .long("dump-cfg")
+                .conflicts_with("ir")
+                .about("Show control-flow graphs in Graphviz format instead of linking"),
+        )
         .arg(Arg::new("strip").short('s').about("Strip executables"))
+        .arg(
+            Arg::new("debug")
+                .short('g')
+                .conflicts_with("strip")
+                .about("Emit DWARF line information for debuggers"),
+        )
+        .arg(
+            Arg::new("internal-as")
+                .long("internal-as")
+                .conflicts_with("debug")
+                .about("Assemble ESP8266 code in-process instead of invoking binutils"),
+        )
+        .arg(
+            Arg::new("pie")
+                .long("pie")
+                .conflicts_with("no-pie")
+                .about("Link a position-independent executable (native only)"),
+        )
+        .arg(
+            Arg::new("no-pie")
+                .long("no-pie")
+                .about("Link a fixed-address executable (native only)"),
+        )
+        .arg(
+            Arg::new("size-report")
+                .long("size-report")
+                .about("Report text/data/bss sizes and device memory usage after linking"),
+        )
+        .arg(
+            Arg::new("opt-level")
+                .short('O')
+                .value_name("LEVEL")
+                .takes_value(true)
+                .default_value("0")
+                .possible_values(&["0", "1", "2"])
+                .about("Optimization level, -O1 folds constants and -O2 also inlines and hoists"),
+        )
+        .arg(
+            Arg::new("enable-pass")
+                .long("enable-pass")
+                .value_name("PASS")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .about("Enable an optimization regardless of -O, may be repeated"),
+        )
+        .arg(
+            Arg::new("disable-pass")
+                .long("disable-pass")
+                .value_name("PASS")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .about("Disable an optimization regardless of -O, may be repeated"),
+        )
+        .arg(
+            Arg::new("checked-arithmetic")
+                .long("checked-arithmetic")
+                .about("Report integer division by zero at run time"),
+        )
+        .arg(
+            Arg::new("no-hw-mul")
+                .long("no-hw-mul")
+                .about("Multiply and divide integers through runtime routines"),
+        )
+        .arg(
+            Arg::new("arena")
+                .long("arena")
+                .about("Allocate loop temporaries from a per-frame arena"),
+        )
+        .arg(
+            Arg::new("verify-ir")
+                .long("verify-ir")
+                .about("Check IR invariants before generating code"),
+        )
+        .arg(
+            Arg::new("dump-ir")
+                .long("dump-ir")
+                .value_name("STAGES")
+                .takes_value(true)
+                .use_delimiter(true)
+                .possible_values(&["before-opt", "after-opt", "final"])
+                .about("Print IR to stderr at these stages, separated by commas"),
+        )
+        .arg(
+            Arg::new("dump-fn")
+                .long("dump-fn")
+                .value_name("NAME")
+                .takes_value(true)
+                .about("Restrict --dump-ir and --dump-cfg to one procedure, by name or symbol"),
+        )
+        .arg(
+            Arg::new("max-stack")
+                .long("max-stack")
+                .value_name("BYTES")
+                .takes_value(true)
+                .about("Fail if worst-case stack usage may exceed this limit"),
+        )
+        .arg(
+            Arg::new("warnings")
+                .short('W')
+                .value_name("MODE")
+                .takes_value(true)
+                .default_value("all")
+                .possible_values(&["all", "none", "error"])
+                .about("Report warnings, silence them or turn them into errors (-Werror)"),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("WHEN")
+                .takes_value(true)
+                .default_value("auto")
+                .possible_values(&["auto", "always", "never"])
+                .about("Use colors in diagnostics"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("output-format")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .default_value("elf")
+                .possible_values(&["elf", "hex", "bin"])
+                .about("Format of the linked image"),
+        )
+        .arg(
+            Arg::new("linker-script")
+                .long("linker-script")
+                .value_name("SCRIPT")
+                .takes_value(true)
+                .about("Override the platform's linker script"),
+        )
+        .arg(
+            Arg::new("flash-size")
+                .long("flash-size")
+                .value_name("SIZE")
+                .takes_value(true)
+                .about("Size of the board's flash, in bytes or with a K/M suffix"),
+        )
+        .arg(
+            Arg::new("emit-asm")
+                .long("emit-asm")
+                .value_name("FILE")
+                .takes_value(true)
+                .about("Also write the assembly listing ('-' for stdout)"),
+        )
+        .arg(
+            Arg::new("emit-map")
+                .long("emit-map")
+                .value_name("FILE")
+                .takes_value(true)
+                .about("Also write the linker map"),
+        )
+        .arg(
+            Arg::new("emit-ledmap")
+                .long("emit-ledmap")
+                .value_name("FILE")
+                .takes_value(true)
+                .about("Also write a source map of the executable"),
+        )
+        .arg(
+            Arg::new("daemon")
+                .long("daemon")
+                .value_name("SOCKET")
+                .takes_value(true)
+                .about("Serve compilation requests on a Unix socket"),
+        )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .conflicts_with_all(&["asm", "ir", "dump-cfg", "run", "daemon"])
+                .about("Rebuild whenever the input file changes"),
+        )
+        .arg(
+            Arg::new("reflash")
+                .long("reflash")
+                .value_name("PORT")
+                .takes_value(true)
+                .requires("watch")
+                .about("Flash each successful --watch build to an ESP8266 board"),
+        )
+        .arg(
+            Arg::new("run")
+                .long("run")
+                .about("Run the executable after linking (native only)"),
+        )
+        .arg(
+            Arg::new("sandbox")
+                .long("sandbox")
+                .requires("run")
+                .about("Run under resource limits and without subprocesses"),
+        )
+        .arg(
+            Arg::new("cpu-limit")
+                .long("cpu-limit")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .default_value("5")
+                .about("CPU time limit for --sandbox"),
+        )
+        .arg(
+            Arg::new("wall-limit")
+                .long("wall-limit")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .default_value("30")
+                .about("Wall time limit for --sandbox"),
+        )
+        .arg(
+            Arg::new("memory-limit")
+                .long("memory-limit")
+                .value_name("MIB")
+                .takes_value(true)
+                .default_value("256")
+                .about("Memory limit for --sandbox"),
+        )
         .arg(
             Arg::new("verbose")
                 .short('v')
                 .long("verbose")
                 .about("Report compilation statistics"),
         )
+        .arg(
+            Arg::new("error-format")
+                .long("error-format")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .default_value("human")
+                .possible_values(&["human", "json"])
+                .about("Report diagnostics as text or as one JSON object per line"),
+        )
+        .arg(
+            Arg::new("time-passes")
+                .long("time-passes")
+                .about("Report time and memory used by each compilation phase"),
+        )
+        .arg(
+            Arg::new("stats-format")
+                .long("stats-format")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .default_value("table")
+                .possible_values(&["table", "json"])
+                .about("Format for --time-passes"),
+        )
         .arg(
             Arg::new("output")
                 .short('o')
                 .takes_value(true)
-                .required(true)
+                .required_unless_present("daemon")
                 .value_name("FILE")
                 .about("Output file ('-' along with -S for stdout)"),
         )
+        .arg(
+            Arg::new("include")
+                .short('I')
+                .value_name("DIR")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .about("Search for imported modules in this directory, may be repeated"),
+        )
+        .arg(
+            Arg::new("define")
+                .short('D')
+                .long("define")
+                .value_name("NAME[=VALUE]")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .about("Define or override a global constant (default value: true)"),
+        )
         .arg(
             Arg::new("input")
-                .required(true)
+                .required_unless_present("daemon")
                 .value_name("INPUT")
                 .about("Input file ('-' for stdin)"),
         )
         .get_matches();
 
+    if let Some(socket) = args.value_of("daemon") {
+        #[cfg(unix)]
+        return daemon::serve(Path::new(socket));
+
+        #[cfg(not(unix))]
+        bail!("Cannot listen on {}: daemons are unsupported on this platform", socket);
+    }
+
+    if let Some(("grade", args)) = args.subcommand() {
+        let scenario = Path::new(args.value_of("scenario").unwrap());
+        let programs = args.values_of("programs").unwrap().map(Path::new);
+        return grade_all(scenario, programs);
+    }
+
+    if let Some(("test", args)) = args.subcommand() {
+        let stop_at = parse_limit(args, "stop-at")?;
+        let programs = args.values_of("programs").unwrap().map(Path::new);
+        return test_all(programs, stop_at, args.is_present("bless"));
+    }
+
+    if let Some(("run", run_args)) = args.subcommand() {
+        let input = Path::new(run_args.value_of("input").unwrap());
+        return interpret(input, use_color(&args));
+    }
+
+    if let Some(("fmt", fmt_args)) = args.subcommand() {
+        let files = fmt_args.values_of("files").unwrap().map(Path::new);
+        return format_all(files, fmt_args.is_present("check"), use_color(&args));
+    }
+
+    if let Some(("doc", doc_args)) = args.subcommand() {
+        let input = Path::new(doc_args.value_of("input").unwrap());
+        let format = doc_args.value_of("format").unwrap();
+        let format = DocFormat::from_str(format).expect("main.rs allowed a bad format");
+
+        let output = doc_args.value_of("output").map(Path::new);
+        return document(input, format, output, use_color(&args));
+    }
+
+    if let Some(("repl", _)) = args.subcommand() {
+        return run_repl(use_color(&args));
+    }
+
+    if let Some(("lsp", _)) = args.subcommand() {
+        return lsp::serve();
+    }
+
+    if let Some(("flash", args)) = args.subcommand() {
+        let executable = Path::new(args.value_of("executable").unwrap());
+        let port = Path::new(args.value_of("port").unwrap());
+        let baud = parse_limit(args, "baud")? as u32;
+        return flash(executable, port, baud);
+    }
+
+    if let Some(("doctor", args)) = args.subcommand() {
+        let serial = args.is_present("serial");
+        let port = args.value_of("port").map(Path::new);
+        let baud = match args.value_of("baud") {
+            Some(_) => Some(parse_limit(args, "baud")? as u32),
+            None => None,
+        };
+
+        return doctor(serial, port, baud);
+    }
+
+    if let Some(("demangle", args)) = args.subcommand() {
+        return demangle(args.values_of("symbols"));
+    }
+
+    if let Some(("decode", args)) = args.subcommand() {
+        let ledmap = Path::new(args.value_of("ledmap").unwrap());
+        let line = match args.value_of("line") {
+            Some(line) => Some(line.parse().context("Bad line number")?),
+            None => None,
+        };
+
+        return decode(ledmap, args.value_of("log").map(Path::new), line);
+    }
+
     // Se extraen argumentos necesarios
-    let platform = args.value_of("target").unwrap();
-    let platform = Platform::from_str(&platform).expect("main.rs allowed a bad target");
-    let arch = platform.arch();
+    let mut platforms = Vec::new();
+    for platform in args.values_of("target").unwrap() {
+        let platform = Platform::from_str(platform).expect("main.rs allowed a bad target");
+        if !platforms.contains(&platform) {
+            platforms.push(platform);
+        }
+    }
+
+    let platform = platforms[0];
+    let format = args.value_of("format").unwrap();
+    let format = OutputFormat::from_str(format).expect("main.rs allowed a bad format");
     let output = args.value_of("output").unwrap();
     let input = args.value_of("input").unwrap();
 
+    let color = use_color(&args);
+    let json = args.value_of("error-format") == Some("json");
+
+    if args.is_present("watch") {
+        let reflash = args.value_of("reflash").map(Path::new);
+
+        match (input, output) {
+            _ if platforms.len() > 1 => bail!("Cannot watch for several targets at once"),
+            ("-", _) => bail!("Cannot watch stdin"),
+            (_, "-") => bail!("Refusing to write executable to stdout"),
+            _ if reflash.is_some() && platform != Platform::Esp8266 => {
+                bail!("Only ESP8266 executables can be flashed")
+            }
+
+            _ => (),
+        }
+
+        let mut link_options = LinkOptions::empty();
+        if args.is_present("strip") {
+            link_options |= LinkOptions::STRIP;
+        }
+
+        if args.is_present("internal-as") {
+            link_options |= LinkOptions::INTERNAL_ASSEMBLER;
+        }
+
+        if args.is_present("size-report") {
+            link_options |= LinkOptions::SIZE_REPORT;
+        }
+
+        if args.is_present("pie") {
+            link_options |= LinkOptions::PIE;
+        } else if args.is_present("no-pie") {
+            link_options |= LinkOptions::NO_PIE;
+        }
+
+        let options = watch::Options {
+            platform,
+            output: Path::new(output),
+            format,
+            scripts: ScriptOptions {
+                script: args.value_of("linker-script").map(Path::new),
+                flash_size: parse_size(&args, "flash-size")?,
+            },
+            link_options,
+            flash: reflash,
+            warnings: args.value_of("warnings") != Some("none"),
+            color,
+            json,
+        };
+
+        return watch::watch(Path::new(input), &options);
+    }
+
     let start_time = Instant::now();
+    let mut stats = Statistics::default();
+
+    let mut options = FrontendOptions::default();
+    if let Some(directories) = args.values_of("include") {
+        options.search_path = directories.map(PathBuf::from).collect();
+    }
+
+    if let Some(defines) = args.values_of("define") {
+        options.defines = defines.map(String::from).collect();
+    }
 
     // Lexer->parser->magia
     let program = match input {
@@ -84,7 +697,7 @@ fn main() -> anyhow::Result<()> {
             let stdin = std::io::stdin();
             let mut stdin = stdin.lock();
 
-            frontend_pipeline(&mut stdin, "<stdin>")
+            compiler::frontend_with_options(&mut stdin, "<stdin>", &options, &mut stats)
         }
 
         _ => {
@@ -92,86 +705,975 @@ fn main() -> anyhow::Result<()> {
                 .with_context(|| format!("Failed to open for reading: {}", input))?;
 
             let mut file = BufReader::new(file);
-            frontend_pipeline(&mut file, input)
+            compiler::frontend_with_options(&mut file, input, &options, &mut stats)
         }
     };
 
-    let program = match program {
-        Ok(program) => program,
+    let mut program = match program {
+        Ok((_, warnings)) if args.value_of("warnings") == Some("error") && !warnings.is_empty() => {
+            let errors = warnings.kind("Semantic error").fatal(true);
+            eprint!("{}", errors.color(color).json(json));
+
+            // -Werror debe poder detener un build o un job de CI
+            std::process::exit(1);
+        }
+
+        Ok((program, warnings)) => {
+            if args.value_of("warnings") != Some("none") && !warnings.is_empty() {
+                eprint!("{}", warnings.color(color).json(json));
+            }
+
+            program
+        }
 
         Err(diagnostics) => {
-            eprint!("{}", diagnostics);
+            eprint!("{}", diagnostics.color(color).json(json));
 
             //FIXME
             return Ok(());
         }
     };
 
+    let level = args.value_of("opt-level").unwrap_or("0");
+    let mut passes = PassManager::new(level.parse().expect("main.rs allowed a bad level"));
+
+    let toggles = [("enable-pass", true), ("disable-pass", false)];
+    for &(option, enabled) in &toggles {
+        for name in args.values_of(option).into_iter().flatten() {
+            if !passes.enable(name, enabled) {
+                bail!("Unknown optimization: {}", name);
+            }
+        }
+    }
+
+    if args.is_present("checked-arithmetic") {
+        passes.lower(Transform("check-division", target::check_division));
+    }
+
+    if args.is_present("no-hw-mul") {
+        passes.lower(Transform("soft-arithmetic", target::soft_arithmetic));
+    }
+
+    if args.is_present("arena") {
+        passes.lower(Transform("mark-frames", target::mark_frames));
+    }
+
+    for stage in args.values_of("dump-ir").into_iter().flatten() {
+        passes.dump(IrStage::from_str(stage).expect("main.rs allowed a bad stage"));
+    }
+
+    if let Some(name) = args.value_of("dump-fn") {
+        passes.dump_function(name);
+    }
+
+    passes
+        .run(&mut program, &mut stats, &mut io::stderr())
+        .context("Failed to dump IR")?;
+
+    if args.is_present("verify-ir") {
+        if let Err(error) = target::verify_ir(&program) {
+            bail!("Internal compiler error: {}", error);
+        }
+    }
+
     if args.is_present("ir") {
         dump_ir(&program);
         return Ok(());
     }
 
-    match (args.is_present("asm"), output) {
-        // Salida a stdout sin enlazado
-        (true, "-") => {
-            let mut stdout = std::io::stdout();
-            target::emit(&program, arch, &mut stdout).context("Failed to emit to stdin")?;
+    if args.is_present("dump-cfg") {
+        for function in program.code.iter() {
+            if passes.selects(function) {
+                print!("{}", Cfg::build(function).dot(function));
+            }
         }
 
-        // Salida a archivo sin enlazado
-        (true, path) => {
-            let mut file = File::create(path)
-                .with_context(|| format!("Failed to open for writing: {}", path))?;
+        return Ok(());
+    }
+
+    let link = !args.is_present("asm");
+    let map = args.value_of("emit-map").map(Path::new);
+    let ledmap = args.value_of("emit-ledmap").map(Path::new);
 
-            target::emit(&program, arch, &mut file)
-                .with_context(|| format!("Failed to emit to file: {}", path))?;
+    match (link, output) {
+        // Los formatos alternativos se derivan del ejecutable enlazado
+        (false, _) if format != OutputFormat::Elf => {
+            bail!("Output formats other than ELF require linking")
         }
 
-        // Salida a stdout con enlazado
-        (false, "-") => bail!("Refusing to write executable to stdout"),
+        (false, _) if map.is_some() => bail!("Linker maps require linking"),
+        (false, _) if ledmap.is_some() => bail!("Source maps require linking"),
+        (false, _) if args.is_present("run") => bail!("Running requires linking"),
+        (true, "-") => bail!("Refusing to write executable to stdout"),
 
-        // Salida a archivo con enlazado
-        (false, path) => {
-            let mut options = LinkOptions::empty();
-            if args.is_present("strip") {
-                options |= LinkOptions::STRIP;
-            }
+        _ => (),
+    }
 
-            let mut linker = Linker::spawn(platform, &path, options).context("Failed to link")?;
-            target::emit(&program, arch, linker.stdin())
-                .context("Failed to emit assembly to assembler")?;
+    // Cada plataforma escribe a `<output>.<plataforma>`, por lo cual las
+    // opciones que nombran un único archivo o dispositivo no aplican
+    if platforms.len() > 1 {
+        let single = [
+            "emit-asm",
+            "emit-map",
+            "emit-ledmap",
+            "linker-script",
+            "flash-size",
+            "run",
+        ];
 
-            linker
-                .finish()
-                .with_context(|| format!("Failed to generate executable: {}", path))?;
+        if let Some(arg) = single.iter().find(|arg| args.is_present(arg)) {
+            bail!("--{} requires a single target", arg);
+        } else if output == "-" {
+            bail!("Refusing to write several listings to stdout");
         }
+    }
+
+    if args.is_present("debug") && platforms.contains(&Platform::Web) {
+        bail!("Debug information is not available for WebAssembly");
+    }
+
+    if args.is_present("internal-as") && platforms.iter().any(|&p| p != Platform::Esp8266) {
+        bail!("The internal assembler only supports ESP8266");
+    }
+
+    if args.is_present("run") && platform != Platform::Native {
+        bail!("Only native executables can be run");
+    }
+
+    let limits = if args.is_present("sandbox") {
+        Some(sandbox::Limits {
+            cpu: parse_limit(&args, "cpu-limit")?,
+            wall: parse_limit(&args, "wall-limit")?,
+            memory: parse_limit(&args, "memory-limit")?,
+        })
+    } else {
+        None
     };
 
+    let max_stack = match args.value_of("max-stack") {
+        Some(_) if platforms.contains(&Platform::Web) => {
+            bail!("Stack limits do not apply to WebAssembly")
+        }
+
+        Some(_) => Some(parse_limit(&args, "max-stack")?),
+        None => None,
+    };
+
+    // En ESP8266 se advierte sobre desbordamientos aunque no se pida un límite
+    for &platform in &platforms {
+        if let Some(limit) = max_stack.or_else(|| platform.stack_size()) {
+            let usage = target::stack_usage(&program, platform.arch());
+            if usage.exceeds(limit) {
+                if max_stack.is_some() {
+                    bail!("Stack usage exceeds {} bytes: {}", limit, usage);
+                }
+
+                eprintln!(
+                    "Warning: stack usage may exceed the {} bytes available: {}",
+                    limit, usage
+                );
+            }
+        }
+    }
+
+    // El ensamblador se genera una única vez por plataforma, sin importar a
+    // cuántos destinos vaya. Todas parten del mismo programa IR.
+    let mut builds = Vec::new();
+    for &platform in &platforms {
+        platform.check_host()?;
+
+        let arch = platform.arch();
+        let output = match platforms.len() {
+            1 => output.to_owned(),
+            _ => format!("{}.{}", output, platform.name()),
+        };
+
+        let mut assembly = Vec::new();
+        stats
+            .measure("codegen", || {
+                if args.is_present("debug") {
+                    let source = match input {
+                        "-" => "<stdin>",
+                        _ => input,
+                    };
+
+                    target::emit_with_debug_info(&program, arch, source, &mut assembly)
+                } else {
+                    target::emit(&program, arch, &mut assembly)
+                }
+            })
+            .context("Failed to emit assembly")?;
+
+        let lines = assembly.iter().filter(|&&byte| byte == b'\n').count();
+        stats.count("assembly lines", lines);
+
+        let listings = (!link)
+            .then(|| output.as_str())
+            .into_iter()
+            .chain(args.value_of("emit-asm"));
+
+        for path in listings {
+            write_listing(&assembly, path)?;
+        }
+
+        builds.push((platform, output, assembly));
+    }
+
+    if link {
+        let mut options = LinkOptions::empty();
+        if args.is_present("strip") {
+            options |= LinkOptions::STRIP;
+        }
+
+        if args.is_present("internal-as") {
+            options |= LinkOptions::INTERNAL_ASSEMBLER;
+        }
+
+        if args.is_present("size-report") {
+            options |= LinkOptions::SIZE_REPORT;
+        }
+
+        if args.is_present("pie") {
+            options |= LinkOptions::PIE;
+        } else if args.is_present("no-pie") {
+            options |= LinkOptions::NO_PIE;
+        }
+
+        let script = args.value_of("linker-script").map(PathBuf::from);
+        let flash_size = parse_size(&args, "flash-size")?;
+        let (map, ledmap) = (map.map(Path::to_path_buf), ledmap.map(Path::to_path_buf));
+
+        // Los enlazadores de cada plataforma corren en paralelo
+        stats.measure("link", || {
+            let linkers: Vec<_> = builds
+                .into_iter()
+                .map(|(platform, output, assembly)| {
+                    let (script, map, ledmap) = (script.clone(), map.clone(), ledmap.clone());
+
+                    thread::spawn(move || {
+                        let scripts = ScriptOptions {
+                            script: script.as_deref(),
+                            flash_size,
+                        };
+
+                        let maps = (map.as_deref(), ledmap.as_deref());
+                        link_executable(platform, output, format, scripts, maps, options, &assembly)
+                    })
+                })
+                .collect();
+
+            let results: Vec<_> = linkers
+                .into_iter()
+                .map(|linker| linker.join().expect("linker thread panicked"))
+                .collect();
+
+            results.into_iter().collect::<anyhow::Result<()>>()
+        })?;
+    }
+
+    if args.is_present("time-passes") {
+        match args.value_of("stats-format") {
+            Some("json") => eprintln!("{}", stats.to_json()),
+            _ => eprint!("{}", stats),
+        }
+    }
+
     if args.is_present("verbose") {
         let duration = Instant::now().duration_since(start_time).as_secs_f32();
         eprintln!("Finished successful build in {:.03}s", duration);
     }
 
+    if args.is_present("run") {
+        let executable = Path::new(output);
+        let command = sandbox::command(executable);
+        let termination = sandbox::run(command, executable, limits.as_ref())
+            .with_context(|| format!("Failed to run: {}", output))?;
+
+        let (name, source) = match input {
+            "-" => ("<stdin>", None),
+            _ => (input, fs::read_to_string(input).ok()),
+        };
+
+        if let Some(diagnostic) = termination.diagnostic(name, source.as_deref()) {
+            eprint!("{}", diagnostic);
+        }
+
+        std::process::exit(termination.exit_code());
+    }
+
     Ok(())
 }
 
-fn frontend_pipeline<R: BufRead>(reader: &mut R, name: &str) -> Result<Program, Diagnostics> {
-    let (start, stream) = source::consume(reader, name);
+/// Determina si los diagnósticos deben llevar colores.
+///
+/// En modo `auto` se usan colores solo si stderr es una terminal y
+/// `NO_COLOR` no está definida.
+fn use_color(args: &clap::ArgMatches) -> bool {
+    match args.value_of("color") {
+        Some("always") => true,
+        Some("never") => false,
+        _ => {
+            atty::is(atty::Stream::Stderr) && std::env::var_os("NO_COLOR").is_none()
+        }
+    }
+}
 
-    let lexer = Lexer::new(start.clone(), stream);
-    let tokens = match lexer.try_exhaustive() {
-        Ok(tokens) => tokens,
-        Err(errors) => return Err(Diagnostics::from(errors).kind("Lexical error")),
+/// Extrae un límite numérico de la CLI.
+fn parse_limit(args: &clap::ArgMatches, name: &str) -> anyhow::Result<u64> {
+    let value = args.value_of(name).unwrap();
+    value
+        .parse()
+        .with_context(|| format!("Invalid value for --{}: {}", name, value))
+}
+
+/// Interpreta un tamaño en bytes, opcionalmente con sufijo `K` o `M`.
+fn parse_size(args: &clap::ArgMatches, name: &str) -> anyhow::Result<Option<u64>> {
+    let value = match args.value_of(name) {
+        Some(value) => value,
+        None => return Ok(None),
     };
 
-    let ast = match parse::parse(tokens.iter(), start) {
-        Ok(ast) => ast,
-        Err(error) => return Err(Diagnostics::from(error).kind("Syntax error")),
+    let (digits, shift) = match value.chars().last() {
+        Some('K' | 'k') => (&value[..value.len() - 1], 10),
+        Some('M' | 'm') => (&value[..value.len() - 1], 20),
+        _ => (value, 0),
     };
 
-    ast.resolve()
-        .map_err(|error| Diagnostics::from(error).kind("Semantic error"))
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|size| size.checked_mul(1 << shift))
+        .map(Some)
+        .with_context(|| format!("Invalid value for --{}: {}", name, value))
+}
+
+/// Evalúa varios programas contra un mismo escenario.
+///
+/// Termina con estado de error si alguno de los programas falla.
+fn grade_all<'a, I>(scenario: &Path, programs: I) -> anyhow::Result<()>
+where
+    I: Iterator<Item = &'a Path>,
+{
+    let text = fs::read_to_string(scenario)
+        .with_context(|| format!("Failed to open for reading: {}", scenario.display()))?;
+
+    let scenario: Scenario = text
+        .parse()
+        .with_context(|| format!("Bad scenario: {}", scenario.display()))?;
+
+    let (mut total, mut passed) = (0, 0);
+    for program in programs {
+        let name = program.to_string_lossy();
+        let source = fs::read_to_string(program)
+            .with_context(|| format!("Failed to open for reading: {}", name))?;
+
+        let report = grade::grade(&name, &source, &scenario)
+            .with_context(|| format!("Failed to evaluate: {}", name))?;
+
+        println!("==> {} <==\n{}", name, report);
+
+        total += 1;
+        if report.passed() {
+            passed += 1;
+        }
+    }
+
+    println!("{} of {} programs passed", passed, total);
+    if passed < total {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Compara la ejecución de varios programas contra sus instantáneas.
+///
+/// La instantánea de `foo.led` es `foo.expected`. Con `bless` las
+/// instantáneas se reemplazan en vez de compararse. Termina con estado
+/// de error si alguno de los programas no coincide con la suya.
+fn test_all<'a, I>(programs: I, stop_at: u64, bless: bool) -> anyhow::Result<()>
+where
+    I: Iterator<Item = &'a Path>,
+{
+    let (mut total, mut passed) = (0, 0);
+    for program in programs {
+        let name = program.to_string_lossy();
+        let source = fs::read_to_string(program)
+            .with_context(|| format!("Failed to open for reading: {}", name))?;
+
+        // Solo el nombre del archivo, para que la instantánea no dependa del directorio
+        let short_name = program
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_else(|| name.clone());
+
+        let recording = grade::record(&short_name, &source, stop_at, &Default::default(), true)
+            .with_context(|| format!("Failed to run: {}", name))?;
+
+        let snapshot = Snapshot::new(&recording);
+        let expected_path = program.with_extension("expected");
+
+        total += 1;
+        if bless {
+            fs::write(&expected_path, snapshot.as_str()).with_context(|| {
+                format!("Failed to open for writing: {}", expected_path.display())
+            })?;
+
+            println!("{} ... blessed", name);
+            passed += 1;
+            continue;
+        }
+
+        let expected = match fs::read_to_string(&expected_path) {
+            Ok(expected) => expected,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                println!("{} ... FAIL (missing {})", name, expected_path.display());
+                continue;
+            }
+
+            Err(error) => {
+                return Err(error).with_context(|| {
+                    format!("Failed to open for reading: {}", expected_path.display())
+                })
+            }
+        };
+
+        match snapshot.compare(&expected) {
+            None => {
+                println!("{} ... ok", name);
+                passed += 1;
+            }
+
+            Some(mismatch) => println!("{} ... FAIL\n{}", name, mismatch),
+        }
+    }
+
+    println!("{} of {} programs passed", passed, total);
+    if passed < total {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Aplica formato canónico a varios programas.
+///
+/// Con `check` no se reescribe ningún archivo, sino que se listan los que
+/// difieren de su formato canónico y se termina con estado de error si
+/// hay alguno.
+fn format_all<'a, I>(files: I, check: bool, color: bool) -> anyhow::Result<()>
+where
+    I: Iterator<Item = &'a Path>,
+{
+    let mut unformatted = 0;
+    for file in files {
+        let name = file.to_string_lossy();
+        let source = fs::read_to_string(file)
+            .with_context(|| format!("Failed to open for reading: {}", name))?;
+
+        let formatted = match compiler::format::format(&mut source.as_bytes(), &name) {
+            Ok(formatted) => formatted,
+            Err(diagnostics) => {
+                eprint!("{}", diagnostics.color(color));
+                std::process::exit(1);
+            }
+        };
+
+        if formatted == source {
+            continue;
+        } else if check {
+            println!("{} ... needs formatting", name);
+            unformatted += 1;
+        } else {
+            fs::write(file, formatted)
+                .with_context(|| format!("Failed to open for writing: {}", name))?;
+        }
+    }
+
+    if unformatted > 0 {
+        println!("{} programs need formatting", unformatted);
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Genera la documentación de un programa, ver [`compiler::doc`].
+fn document(
+    input: &Path,
+    format: DocFormat,
+    output: Option<&Path>,
+    color: bool,
+) -> anyhow::Result<()> {
+    let name = input.to_string_lossy();
+    let source = fs::read_to_string(input)
+        .with_context(|| format!("Failed to open for reading: {}", name))?;
+
+    let document = match doc::document(&mut source.as_bytes(), &name, format) {
+        Ok(document) => document,
+        Err(diagnostics) => {
+            eprint!("{}", diagnostics.color(color));
+            std::process::exit(1);
+        }
+    };
+
+    match output {
+        None => print!("{}", document),
+        Some(output) => fs::write(output, document)
+            .with_context(|| format!("Failed to open for writing: {}", output.display()))?,
+    }
+
+    Ok(())
+}
+
+/// Ejecuta un programa en el intérprete, el cual no requiere `binutils`.
+///
+/// La matriz se dibuja en la terminal igual que en [`run_repl()`]. Al
+/// igual que en la plataforma nativa, el programa termina cuando
+/// `main()` retorna y no quedan tareas planificadas.
+fn interpret(input: &Path, color: bool) -> anyhow::Result<()> {
+    let name = input.to_string_lossy();
+    let source = fs::read_to_string(input)
+        .with_context(|| format!("Failed to open for reading: {}", name))?;
+
+    let program = match compiler::frontend_with_warnings(&mut source.as_bytes(), &name) {
+        Ok((program, warnings)) => {
+            if !warnings.is_empty() {
+                eprint!("{}", warnings.color(color));
+            }
+
+            program
+        }
+
+        Err(diagnostics) => {
+            eprint!("{}", diagnostics.color(color));
+            std::process::exit(1);
+        }
+    };
+
+    let mut machine = Machine::new();
+    let mut terminal = Terminal::default();
+
+    let result = machine.run(&program, "user_main", &mut terminal);
+    terminal.finish(machine.display(), true);
+
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Sesión interactiva sobre stdin.
+///
+/// Una entrada continúa en líneas siguientes mientras tenga llaves o
+/// paréntesis sin cerrar. Las líneas que inician con `:` son comandos
+/// de la sesión y no código.
+fn run_repl(color: bool) -> anyhow::Result<()> {
+    const HELP: &str = "\
+Enter statements, expressions or procedure definitions.
+Assigning to an unknown variable defines a new global.
+
+:display   Show the LED matrix
+:globals   List defined globals
+:procs     List defined procedures
+:reset     Start a new session
+:quit      Exit";
+
+    println!(
+        "AnimationLed {} REPL, type :help for help",
+        crate_version!()
+    );
+
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    let mut session = Session::new();
+    let mut terminal = Terminal::default();
+    let mut input = String::new();
+
+    loop {
+        print!("{}", if input.is_empty() { ">>> " } else { "... " });
+        std::io::stdout().flush()?;
+
+        let line = match lines.next() {
+            Some(line) => line.context("Failed to read from stdin")?,
+            None => break,
+        };
+
+        if input.is_empty() {
+            match line.trim() {
+                ":quit" | ":q" => break,
+                ":help" => println!("{}", HELP),
+                ":display" => print!("{}", session.display()),
+                ":reset" => session = Session::new(),
+
+                ":globals" => {
+                    for name in session.globals() {
+                        println!("{}", name);
+                    }
+                }
+
+                ":procs" => {
+                    for name in session.procedures() {
+                        println!("{}", name);
+                    }
+                }
+
+                command if command.starts_with(':') => {
+                    eprintln!("Unknown command: {}", command);
+                }
+
+                _ => (),
+            }
+
+            if line.trim().starts_with(':') {
+                continue;
+            }
+        }
+
+        input.push_str(&line);
+        input.push('\n');
+
+        if repl::is_incomplete(&input) {
+            continue;
+        }
+
+        let before = display_bits(session.display());
+        match session.eval(&input, &mut terminal) {
+            Ok(Evaluation::Defined(names)) => {
+                for name in names {
+                    println!("Defined procedure {}", name);
+                }
+            }
+
+            Ok(_) => (),
+            Err(EvalError::Rejected(diagnostics)) => eprint!("{}", diagnostics.color(color)),
+            Err(error) => eprint!("{}", error),
+        }
+
+        let changed = display_bits(session.display()) != before;
+        terminal.finish(session.display(), changed);
+
+        input.clear();
+    }
+
+    println!();
+    Ok(())
+}
+
+/// Salida de la sesión interactiva en la terminal.
+///
+/// Durante un `delay()` la matriz se redibuja en el mismo lugar cada
+/// cierto número de ticks, de forma que las animaciones se observen en
+/// tiempo real.
+#[derive(Default)]
+struct Terminal {
+    drawn: bool,
+    ticks: u32,
+}
+
+impl Terminal {
+    /// Ticks entre cada redibujo.
+    const REDRAW_TICKS: u32 = 5;
+
+    fn draw(&mut self, display: &Display) {
+        if self.drawn {
+            // Regresa al inicio del dibujo anterior
+            print!("\x1b[8A");
+        }
+
+        print!("{}", display);
+        let _ = std::io::stdout().flush();
+
+        self.drawn = true;
+    }
+
+    /// Muestra el estado final de la matriz tras una entrada.
+    fn finish(&mut self, display: &Display, changed: bool) {
+        if self.drawn || changed {
+            self.draw(display);
+        }
+
+        self.drawn = false;
+        self.ticks = 0;
+    }
+}
+
+impl Host for Terminal {
+    fn debug(&mut self, _line: isize, value: Value<'_>) {
+        // Un dibujo en curso se perdería al mover el cursor
+        self.drawn = false;
+        println!("{}", value);
+    }
+
+    fn uart_write(&mut self, value: isize) {
+        self.drawn = false;
+        println!("{}", value);
+    }
+
+    fn tick(&mut self, display: &Display) {
+        thread::sleep(interp::TICK);
+
+        self.ticks += 1;
+        if self.ticks % Self::REDRAW_TICKS == 0 {
+            self.draw(display);
+        }
+    }
+}
+
+fn display_bits(display: &Display) -> [u8; 8] {
+    let mut bits = [0; 8];
+    for (row, bits) in bits.iter_mut().enumerate() {
+        *bits = display.row_bits(row);
+    }
+
+    bits
+}
+
+/// Ensambla y enlaza un ejecutable a partir de ensamblador ya emitido.
+fn link_executable<O: AsRef<Path>>(
+    platform: Platform,
+    output: O,
+    format: OutputFormat,
+    scripts: ScriptOptions<'_>,
+    (map, ledmap): (Option<&Path>, Option<&Path>),
+    options: LinkOptions,
+    assembly: &[u8],
+) -> anyhow::Result<()> {
+    let output = output.as_ref();
+    let mut linker = Linker::spawn(platform, &output, format, scripts, map, ledmap, options)
+        .context("Failed to link")?;
+
+    linker
+        .stdin()
+        .write_all(assembly)
+        .context("Failed to emit assembly to assembler")?;
+
+    let report = linker
+        .finish()
+        .with_context(|| format!("Failed to generate executable: {}", output.display()))?;
+
+    if let Some(report) = report {
+        eprint!("{}", report);
+    }
+
+    Ok(())
+}
+
+/// Escribe un ejecutable a una tarjeta ESP8266, reportando el avance.
+fn flash(executable: &Path, port: &Path, baud: u32) -> anyhow::Result<()> {
+    let elf = fs::read(executable)
+        .with_context(|| format!("Failed to open for reading: {}", executable.display()))?;
+
+    let image = Image::from_elf(&elf)
+        .with_context(|| format!("Bad executable: {}", executable.display()))?;
+
+    eprintln!("Connecting to {}...", port.display());
+    let mut loader = Loader::connect(port, baud)
+        .with_context(|| format!("Failed to connect: {}", port.display()))?;
+
+    let start_time = Instant::now();
+    loader
+        .write_image(&image, |written, total| {
+            eprint!(
+                "\rWriting {} of {} bytes ({}%)",
+                written,
+                total,
+                100 * written / total
+            );
+        })
+        .context("Failed to flash")?;
+
+    loader.finish().context("Failed to restart the board")?;
+
+    let duration = Instant::now().duration_since(start_time).as_secs_f32();
+    eprintln!("\nFlashed {} bytes in {:.02}s", image.size(), duration);
+
+    Ok(())
+}
+
+/// Verifica las herramientas de cada plataforma y, con `serial`, busca
+/// tarjetas ESP8266 que respondan en modo de flasheo.
+///
+/// Por cada tarjeta se reportan la velocidad a la que respondió, el ID
+/// del chip y el tamaño de su flash, junto a los argumentos con los
+/// cuales compilar y flashear para ella. Sin `baud`, se prueba cada una
+/// de [`flash::BAUD_RATES`].
+fn doctor(serial: bool, port: Option<&Path>, baud: Option<u32>) -> anyhow::Result<()> {
+    println!("Toolchain:");
+    for platform in Platform::ALL {
+        match platform.check_toolchain() {
+            Ok(()) => println!("  {}: ok", platform.name()),
+            Err(error) => println!("  {}: {}", platform.name(), error),
+        }
+    }
+
+    if !serial {
+        return Ok(());
+    }
+
+    let ports = match port {
+        Some(port) => vec![port.to_path_buf()],
+        None => flash::likely_ports(),
+    };
+
+    if ports.is_empty() {
+        bail!("No serial ports found, is the board connected?");
+    }
+
+    let bauds = match &baud {
+        Some(baud) => std::slice::from_ref(baud),
+        None => flash::BAUD_RATES,
+    };
+
+    let mut found = false;
+    for port in &ports {
+        eprintln!("Checking {}...", port.display());
+
+        let (baud, chip_id, flash_size) = match probe_board(port, bauds) {
+            Ok(board) => board,
+            Err(error) => {
+                eprintln!("  {:#}", error);
+                continue;
+            }
+        };
+
+        found = true;
+        println!(
+            "{}: ESP8266 at {} baud with chip ID {:#010x}",
+            port.display(),
+            baud,
+            chip_id
+        );
+
+        match flash_size {
+            Some(size) => {
+                let size = if size % (1 << 20) == 0 {
+                    format!("{}M", size >> 20)
+                } else {
+                    format!("{}K", size >> 10)
+                };
+
+                println!("  Flash size: {}", size);
+                println!(
+                    "  Build with: compiler --target esp8266 --flash-size {} <PROGRAM>",
+                    size
+                );
+            }
+
+            None => println!("  Flash size: unknown"),
+        }
+
+        println!(
+            "  Flash with: compiler flash --port {} --baud {} <EXECUTABLE>",
+            port.display(),
+            baud
+        );
+    }
+
+    if !found {
+        bail!("No ESP8266 responded, hold FLASH while pressing RST or check the cable");
+    }
+
+    Ok(())
+}
+
+/// Se sincroniza con el bootloader a la primera velocidad de `bauds`
+/// que responda y retorna esta velocidad, el ID del chip y el tamaño de
+/// la flash. La tarjeta se reinicia al terminar.
+fn probe_board(port: &Path, bauds: &[u32]) -> anyhow::Result<(u32, u32, Option<u64>)> {
+    for &baud in bauds {
+        let mut loader = match Loader::connect(port, baud) {
+            Ok(loader) => loader,
+            Err(FlashError::NoSync) => continue,
+            Err(error) => return Err(error.into()),
+        };
+
+        let chip_id = loader.chip_id().context("Failed to read the chip ID")?;
+        let flash_size = loader
+            .flash_size()
+            .context("Failed to read the flash size")?;
+
+        loader.reset().context("Failed to restart the board")?;
+        return Ok((baud, chip_id, flash_size));
+    }
+
+    Err(FlashError::NoSync.into())
+}
+
+/// Anota las direcciones de un registro de fallo con funciones y líneas.
+///
+/// Con `line`, se listan en cambio los rangos de direcciones de esa línea.
+fn decode(ledmap: &Path, log: Option<&Path>, line: Option<u32>) -> anyhow::Result<()> {
+    let file = File::open(ledmap)
+        .with_context(|| format!("Failed to open for reading: {}", ledmap.display()))?;
+
+    let sourcemap: SourceMap = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Malformed source map: {}", ledmap.display()))?;
+
+    if let Some(line) = line {
+        for range in sourcemap.addresses(line) {
+            println!("{:#010x}..{:#010x}", range.start, range.end);
+        }
+
+        return Ok(());
+    }
+
+    let log = match log {
+        Some(log) => fs::read_to_string(log)
+            .with_context(|| format!("Failed to open for reading: {}", log.display()))?,
+
+        None => {
+            let mut log = String::new();
+            std::io::stdin()
+                .read_to_string(&mut log)
+                .context("Failed to read from stdin")?;
+
+            log
+        }
+    };
+
+    print!("{}", sourcemap.annotate(&log));
+    Ok(())
+}
+
+/// Decodifica símbolos de procedimientos, o bien todos los que aparezcan
+/// en stdin, al estilo de `c++filt`.
+fn demangle<'a, I>(symbols: Option<I>) -> anyhow::Result<()>
+where
+    I: Iterator<Item = &'a str>,
+{
+    if let Some(symbols) = symbols {
+        for symbol in symbols {
+            let demangled = semantic::demangle(symbol);
+            println!("{}", demangled.as_deref().unwrap_or(symbol));
+        }
+
+        return Ok(());
+    }
+
+    let stdin = std::io::stdin();
+    for line in stdin.lock().lines() {
+        let line = line.context("Failed to read from stdin")?;
+        println!("{}", semantic::demangle_text(&line));
+    }
+
+    Ok(())
+}
+
+/// Escribe un listado de ensamblador a un archivo o a stdout.
+fn write_listing(assembly: &[u8], path: &str) -> anyhow::Result<()> {
+    match path {
+        "-" => std::io::stdout()
+            .write_all(assembly)
+            .context("Failed to emit to stdout"),
+
+        _ => File::create(path)
+            .and_then(|mut file| file.write_all(assembly))
+            .with_context(|| format!("Failed to emit to file: {}", path)),
+    }
 }
 
 fn dump_ir(ir: &Program) {
@@ -180,9 +1682,6 @@ fn dump_ir(ir: &Program) {
     }
 
     for procedure in ir.code.iter() {
-        println!("[PROC {}/{}]", procedure.name, procedure.parameters);
-        for (i, instruction) in procedure.body.iter().enumerate() {
-            println!("\t{:03x} {:?}", i, instruction);
-        }
+        print!("{}", procedure);
     }
 }
@@ -0,0 +1,202 @@
+//! Mapas de memoria de los dispositivos.
+//!
+//! Cada plataforma describe sus regiones de memoria mediante un
+//! [`MemoryLayout`]. A partir de este se genera el script `memory.x`
+//! que incluyen los linker scripts de la plataforma, y se obtienen los
+//! límites contra los cuales [`crate::link`] verifica que el ejecutable
+//! quepa en el dispositivo.
+//!
+//! Las regiones que mapean la flash externa dependen de su tamaño. Así,
+//! una placa con otra flash, como un ESP-01 de 1 MiB en vez de un NodeMCU
+//! de 4 MiB, no requiere más que indicar ese tamaño. Una placa con otro
+//! mapa de memoria es solo otra lista de regiones.
+
+use std::fmt::Write;
+
+use crate::link::{LinkerError, Platform};
+
+/// Regiones de memoria de un dispositivo.
+#[derive(Clone, Debug)]
+pub struct MemoryLayout {
+    /// Regiones en el orden en que se declaran en `MEMORY`.
+    pub regions: Vec<Region>,
+
+    /// Resto de `memory.x`, que refiere a las regiones por nombre.
+    definitions: &'static str,
+}
+
+/// Región de memoria de un dispositivo.
+#[derive(Clone, Debug)]
+pub struct Region {
+    /// Nombre de la región en el linker script.
+    pub name: &'static str,
+
+    /// Descripción del tipo de memoria. Solo las regiones descritas se
+    /// verifican contra el tamaño del ejecutable, las demás se reservan
+    /// para el ROM, el cache o el runtime.
+    pub kind: Option<&'static str>,
+
+    /// Atributos en la notación de ld, como `RX`, o vacío.
+    pub attributes: &'static str,
+
+    /// Dirección base.
+    pub origin: u64,
+
+    /// Tamaño en bytes.
+    pub length: u64,
+}
+
+/// Flash de un NodeMCU, la placa usual para ESP8266.
+const ESP8266_FLASH: u64 = 4 << 20;
+
+/// ESP8266 mapea a lo sumo 1 MiB de flash en memoria.
+const ESP8266_WINDOW: u64 = 1 << 20;
+
+/// Flash del STM32F411CE.
+const STM32_FLASH: u64 = 512 << 10;
+
+/// Flash de un módulo ESP32-C3-MINI-1.
+const ESP32C3_FLASH: u64 = 4 << 20;
+
+/// ESP32-C3 mapea a lo sumo 8 MiB de flash en cada bus.
+const ESP32C3_WINDOW: u64 = 8 << 20;
+
+impl MemoryLayout {
+    /// Regiones que se verifican contra el tamaño del ejecutable.
+    pub fn checked(&self) -> impl Iterator<Item = &Region> {
+        self.regions.iter().filter(|region| region.kind.is_some())
+    }
+
+    /// Contenido de `memory.x`, o `None` si la plataforma no lo utiliza.
+    pub fn script(&self) -> Option<String> {
+        if self.regions.is_empty() {
+            return None;
+        }
+
+        let mut script = String::from("MEMORY\n{\n");
+        for region in &self.regions {
+            let attributes = match region.attributes {
+                "" => String::new(),
+                attributes => format!(" ({})", attributes),
+            };
+
+            writeln!(
+                script,
+                "  {}{} : ORIGIN = {:#x}, LENGTH = {:#x}",
+                region.name, attributes, region.origin, region.length
+            )
+            .unwrap();
+        }
+
+        script.push_str("}\n\n");
+        script.push_str(self.definitions);
+
+        Some(script)
+    }
+}
+
+impl Region {
+    const fn new(name: &'static str, attributes: &'static str, origin: u64, length: u64) -> Self {
+        Region {
+            name,
+            kind: None,
+            attributes,
+            origin,
+            length,
+        }
+    }
+
+    const fn checked(self, kind: &'static str) -> Self {
+        Region {
+            kind: Some(kind),
+            ..self
+        }
+    }
+}
+
+impl Platform {
+    /// Mapa de memoria de la plataforma con una flash de `flash_size`
+    /// bytes, o bien de la placa usual si no se indica un tamaño.
+    pub fn memory_layout(self, flash_size: Option<u64>) -> Result<MemoryLayout, LinkerError> {
+        // Bytes de flash que mapea una región a partir de `offset`
+        let mapped = |default: u64, offset: u64, window: u64| {
+            let flash = flash_size.unwrap_or(default).min(window);
+            match flash.checked_sub(offset) {
+                Some(length) if length > 0 => Ok(length),
+                _ => Err(LinkerError::FlashTooSmall(flash)),
+            }
+        };
+
+        let (regions, definitions) = match self {
+            Platform::Native | Platform::Web if flash_size.is_some() => {
+                return Err(LinkerError::Unsupported("flash sizes"));
+            }
+
+            Platform::Native | Platform::Web => (vec![], ""),
+
+            // Proviene de esp8266-hal. El programa inicia tras los primeros
+            // 64 KiB de flash, que ocupa el bootloader
+            Platform::Esp8266 => (
+                vec![
+                    Region::new("reserved_cache_seg", "", 0x40070000, 64 << 10),
+                    Region::new("vectors_seg", "RX", 0x40100000, 0x100),
+                    Region::new("iram_seg", "RX", 0x40100100, 0x8000 - 0x100)
+                        .checked("instruction RAM"),
+                    Region::new("reserved_for_rom_seg", "", 0x3ffae000, 8 << 10),
+                    Region::new("dram_seg", "RW", 0x3ffe8000, 0x14000).checked("data RAM"),
+                    Region::new("reserved_for_boot_seg", "", 0x3ffdc200, 144 << 10),
+                    Region::new(
+                        "irom_seg",
+                        "RX",
+                        0x40210000,
+                        mapped(ESP8266_FLASH, 0x10000, ESP8266_WINDOW)?,
+                    )
+                    .checked("flash"),
+                    Region::new("drom_seg", "R", 0x3f400000, (4 << 20) - 0x20),
+                    Region::new("rtc_seg", "RW", 0x60001000, 768),
+                ],
+                include_str!("../ld/esp8266/memory.x"),
+            ),
+
+            Platform::Stm32 => (
+                vec![
+                    Region::new("FLASH", "", 0x08000000, mapped(STM32_FLASH, 0, u64::MAX)?)
+                        .checked("flash"),
+                    Region::new("RAM", "", 0x20000000, 128 << 10).checked("RAM"),
+                ],
+                include_str!("../ld/stm32/memory.x"),
+            ),
+
+            // Proviene de esp32c3-hal. Los primeros 32 bytes de IROM
+            // corresponden al encabezado de la imagen
+            Platform::Esp32c3 => (
+                vec![
+                    Region::new("ICACHE", "", 0x4037c000, 0x4000),
+                    Region::new("IRAM", "", 0x4037c000 + 0x4000, (400 << 10) - 0x4000),
+                    Region::new("DRAM", "", 0x3fc80000, 0x50000).checked("data RAM"),
+                    Region::new(
+                        "IROM",
+                        "",
+                        0x42000000 + 0x20,
+                        mapped(ESP32C3_FLASH, 0x20, ESP32C3_WINDOW)?,
+                    )
+                    .checked("flash (instructions)"),
+                    Region::new(
+                        "DROM",
+                        "",
+                        0x3c000000,
+                        mapped(ESP32C3_FLASH, 0, ESP32C3_WINDOW)?,
+                    )
+                    .checked("flash (data)"),
+                    Region::new("RTC_FAST", "", 0x50000000, 0x2000),
+                ],
+                include_str!("../ld/esp32c3/memory.x"),
+            ),
+        };
+
+        Ok(MemoryLayout {
+            regions,
+            definitions,
+        })
+    }
+}
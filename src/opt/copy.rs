@@ -0,0 +1,153 @@
+//! Propagación de copias.
+//!
+//! El análisis semántico evalúa cada expresión hacia una local temporal
+//! y luego la copia a su destino, por lo cual abundan pares de `Move`
+//! cuyo resultado se lee una única vez. Dentro de cada bloque básico se
+//! registra qué locales son copias de otras, y cada lectura de una copia
+//! se reescribe para leer directamente de su origen. Un `Move` entre
+//! locales que ya contienen el mismo valor se elimina.
+//!
+//! Tras la propagación, muchas copias dejan de leerse. Mediante el
+//! análisis de vida de [`crate::codegen::liveness`] se eliminan las
+//! cargas y copias hacia locales cuyo valor ninguna instrucción
+//! posterior lee, como un `LoadConst` a una local que se sobreescribe
+//! de inmediato. Ambos pasos se repiten hasta que ninguno tenga efecto.
+//!
+//! Las instrucciones que leen y escriben una misma local, como `Not` o
+//! el operando izquierdo de `Binary`, no se reescriben.
+
+use std::collections::HashMap;
+
+use crate::{
+    codegen::{
+        liveness::{accesses, Access, Liveness},
+        locals_and_labels,
+    },
+    ir::{Function, GeneratedFunction, Instruction, Local, Program},
+};
+
+use super::Pass;
+
+/// Propagación de copias, ver el módulo.
+pub struct CopyProp;
+
+impl Pass for CopyProp {
+    fn name(&self) -> &'static str {
+        "copy-prop"
+    }
+
+    fn run(&self, program: &mut Program) {
+        for function in &mut program.code {
+            loop {
+                let propagated = propagate(function);
+                let eliminated = eliminate(function);
+
+                if !propagated && !eliminated {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Reescribe lecturas de copias y elimina copias redundantes. Retorna
+/// `true` si hubo cambios.
+fn propagate(function: &mut GeneratedFunction) -> bool {
+    use Instruction::*;
+
+    // Cada copia junto a la local de la cual se copió
+    let mut copies: HashMap<u32, Local> = HashMap::new();
+    let mut changed = false;
+
+    for instruction in &mut function.body {
+        if let SetLabel(_) = instruction.as_ref() {
+            copies.clear();
+            continue;
+        }
+
+        for local in uses(instruction) {
+            if let Some(origin) = copies.get(&local.0) {
+                *local = *origin;
+                changed = true;
+            }
+        }
+
+        // Una copia redundante se convierte en `Move(x, x)`, que luego se elimina
+        if let Move(from, to) = instruction.as_ref() {
+            if from == to || copies.get(&to.0) == Some(from) {
+                **instruction = Move(*to, *to);
+                continue;
+            }
+        }
+
+        // Tras un `Kill`, la local ya no es un origen válido
+        accesses(instruction, |defined, access| {
+            if let Access::Def | Access::Kill = access {
+                copies.remove(&defined.0);
+                copies.retain(|_, origin| *origin != defined);
+            }
+        });
+
+        if let Move(from, to) = instruction.as_ref() {
+            copies.insert(to.0, *from);
+        }
+    }
+
+    let length = function.body.len();
+    function
+        .body
+        .retain(|instruction| !matches!(instruction.as_ref(), Move(from, to) if from == to));
+
+    changed || function.body.len() != length
+}
+
+/// Elimina cargas y copias cuyo resultado nunca se lee. Retorna `true`
+/// si hubo cambios.
+fn eliminate(function: &mut GeneratedFunction) -> bool {
+    use Instruction::*;
+
+    let (locals, _) = locals_and_labels(function);
+    let liveness = Liveness::analyze(&function.body, locals);
+
+    let length = function.body.len();
+    let mut position = 0;
+
+    function.body.retain(|instruction| {
+        let defined = match instruction.as_ref() {
+            Move(_, to) | LoadConst(_, to) | LoadGlobal(_, to) => Some(*to),
+            LoadData(_, to) | LoadProcedure(_, to) => Some(*to),
+            _ => None,
+        };
+
+        let keep = defined.map_or(true, |local| liveness.is_live_after(position, local));
+        position += 1;
+
+        keep
+    });
+
+    function.body.len() != length
+}
+
+/// Locales que una instrucción solo lee, sin sobreescribirlas.
+fn uses(instruction: &mut Instruction) -> Vec<&mut Local> {
+    use Instruction::*;
+
+    match instruction {
+        Move(from, _) => vec![from],
+        JumpIfFalse(local, _) | StoreGlobal(local, _) | Return(Some(local)) => vec![local],
+        Binary(_, _, rhs) => vec![rhs],
+
+        Call {
+            target, arguments, ..
+        } => {
+            let mut uses: Vec<_> = arguments.iter_mut().collect();
+            if let Function::Indirect(local) = target {
+                uses.push(local);
+            }
+
+            uses
+        }
+
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,79 @@
+//! Eliminación de código muerto.
+//!
+//! Se descartan las instrucciones que no son alcanzables desde el
+//! inicio de la función, los saltos hacia la instrucción inmediatamente
+//! siguiente y las etiquetas a las que ningún salto refiere. Cada una
+//! de estas eliminaciones puede habilitar otras, por lo cual se repiten
+//! hasta que ninguna tenga efecto. Es usual que queden instrucciones
+//! inalcanzables tras [`super::fold`], el cual convierte condiciones
+//! constantes en saltos incondicionales.
+
+use std::collections::HashSet;
+
+use crate::ir::{cfg::Cfg, GeneratedFunction, Instruction, Label, Program};
+
+use super::Pass;
+
+/// Eliminación de código muerto, ver el módulo.
+pub struct DeadCode;
+
+impl Pass for DeadCode {
+    fn name(&self) -> &'static str {
+        "dce"
+    }
+
+    fn run(&self, program: &mut Program) {
+        for function in &mut program.code {
+            while eliminate(function) {}
+        }
+    }
+}
+
+/// Aplica una ronda de eliminaciones. Retorna `true` si hubo cambios.
+fn eliminate(function: &mut GeneratedFunction) -> bool {
+    use Instruction::*;
+
+    let cfg = Cfg::build(function);
+    let body = &mut function.body;
+    let length = body.len();
+
+    let reachable = cfg.reachable();
+    let mut position = 0;
+
+    body.retain(|_| {
+        let keep = cfg
+            .block_at(position)
+            .map_or(false, |block| reachable[block]);
+        position += 1;
+
+        keep
+    });
+
+    // Ambos caminos de un salto hacia la siguiente instrucción coinciden
+    let redundant: Vec<_> = body
+        .windows(2)
+        .map(|pair| match (pair[0].as_ref(), pair[1].as_ref()) {
+            (Jump(Label(target)), SetLabel(Label(next)))
+            | (JumpIfFalse(_, Label(target)), SetLabel(Label(next))) => target == next,
+            _ => false,
+        })
+        .collect();
+
+    let mut redundant = redundant.into_iter();
+    body.retain(|_| !redundant.next().unwrap_or(false));
+
+    let targets: HashSet<_> = body
+        .iter()
+        .filter_map(|instruction| match instruction.as_ref() {
+            Jump(Label(label)) | JumpIfFalse(_, Label(label)) => Some(*label),
+            _ => None,
+        })
+        .collect();
+
+    body.retain(|instruction| match instruction.as_ref() {
+        SetLabel(Label(label)) => targets.contains(label),
+        _ => true,
+    });
+
+    body.len() != length
+}
@@ -0,0 +1,133 @@
+//! Plegado de constantes.
+//!
+//! Dentro de cada bloque básico se registra qué locales contienen una
+//! constante conocida. Una operación cuyos operandos son todos conocidos
+//! se reemplaza por un `LoadConst` de su resultado, al igual que un
+//! `Move` desde una local conocida. Un `JumpIfFalse` sobre una local
+//! conocida se convierte en `Jump` o se elimina, según corresponda.
+//! Al alcanzar una etiqueta se descarta todo lo conocido, ya que esta
+//! puede alcanzarse desde otros puntos de la función.
+//!
+//! No se pliegan operaciones cuyo resultado no es representable en 32
+//! bits, divisiones entre cero ni desplazamientos fuera de `[0, 31]`,
+//! ya que su resultado depende de la arquitectura o bien es un error en
+//! tiempo de ejecución.
+
+use std::{collections::HashMap, convert::TryFrom};
+
+use crate::{
+    codegen::liveness::{accesses, Access},
+    ir::{ArithmeticOp, BinOp, Instruction, Local, LogicOp, Program},
+    source::Located,
+};
+
+use super::Pass;
+
+/// Plegado de constantes, ver el módulo.
+pub struct ConstFold;
+
+impl Pass for ConstFold {
+    fn name(&self) -> &'static str {
+        "const-fold"
+    }
+
+    fn run(&self, program: &mut Program) {
+        for function in &mut program.code {
+            let body = std::mem::take(&mut function.body);
+            let mut known = HashMap::new();
+
+            function.body = body
+                .into_iter()
+                .filter_map(|instruction| {
+                    let (location, instruction) = instruction.split();
+                    let folded = fold(&mut known, instruction)?;
+
+                    Some(Located::at(folded, location))
+                })
+                .collect();
+        }
+    }
+}
+
+/// Pliega una instrucción dadas las locales de valor conocido, las cuales
+/// se actualizan según su efecto. Retorna `None` si la instrucción se
+/// elimina.
+fn fold(known: &mut HashMap<u32, i32>, instruction: Instruction) -> Option<Instruction> {
+    use Instruction::*;
+
+    let value = |Local(local)| known.get(&local).copied();
+
+    if let JumpIfFalse(local, label) = instruction {
+        return match value(local) {
+            Some(0) => Some(Jump(label)),
+            Some(_) => None,
+            None => Some(instruction),
+        };
+    }
+
+    let folded = match &instruction {
+        Move(from, to) => value(*from).map(|from| (from, *to)),
+        Not(local) => value(*local).map(|value| (value ^ 1, *local)),
+        Negate(local) => value(*local)
+            .and_then(i32::checked_neg)
+            .map(|value| (value, *local)),
+
+        Binary(lhs, op, rhs) => value(*lhs)
+            .zip(value(*rhs))
+            .and_then(|(a, b)| evaluate(a, *op, b))
+            .map(|value| (value, *lhs)),
+
+        _ => None,
+    };
+
+    if let Some((value, Local(local))) = folded {
+        known.insert(local, value);
+        return Some(LoadConst(value, Local(local)));
+    }
+
+    match &instruction {
+        SetLabel(_) => known.clear(),
+
+        LoadConst(value, Local(local)) => {
+            known.insert(*local, *value);
+        }
+
+        _ => accesses(&instruction, |Local(local), access| {
+            if let Access::Def = access {
+                known.remove(&local);
+            }
+        }),
+    }
+
+    Some(instruction)
+}
+
+/// Resultado de una operación entre constantes, si es que se puede plegar.
+fn evaluate(a: i32, op: BinOp, b: i32) -> Option<i32> {
+    use ArithmeticOp::*;
+    use LogicOp::*;
+
+    let (a, b) = (i64::from(a), i64::from(b));
+    let result = match op {
+        BinOp::Arithmetic(Add) => a + b,
+        BinOp::Arithmetic(Sub) => a - b,
+        BinOp::Arithmetic(Mul) => a * b,
+        BinOp::Arithmetic(Div) => a.checked_div(b)?,
+        BinOp::Arithmetic(Mod) => a.checked_rem(b)?,
+        BinOp::Arithmetic(BitAnd) => a & b,
+        BinOp::Arithmetic(BitOr) => a | b,
+        BinOp::Arithmetic(BitXor) => a ^ b,
+        BinOp::Arithmetic(ShiftLeft) if (0..32).contains(&b) => a << b,
+        BinOp::Arithmetic(ShiftRight) if (0..32).contains(&b) => a >> b,
+        BinOp::Arithmetic(ShiftLeft) | BinOp::Arithmetic(ShiftRight) => return None,
+
+        BinOp::Logic(Equal) => (a == b) as i64,
+        BinOp::Logic(NotEqual) => (a != b) as i64,
+        BinOp::Logic(Less) => (a < b) as i64,
+        BinOp::Logic(LessOrEqual) => (a <= b) as i64,
+        BinOp::Logic(Greater) => (a > b) as i64,
+        BinOp::Logic(GreaterOrEqual) => (a >= b) as i64,
+    };
+
+    i32::try_from(result).ok()
+}
@@ -0,0 +1,263 @@
+//! Movimiento de cargas invariantes fuera de ciclos.
+//!
+//! Un ciclo se reconoce como un rango `[h, j]` del cuerpo de una
+//! función tal que en `h` se establece una etiqueta, la cabecera, hacia
+//! la cual salta la instrucción en `j`. Si varios saltos hacia atrás
+//! llegan a la misma cabecera, el ciclo termina en el último de ellos.
+//! Solo se consideran ciclos naturales, es decir, aquellos a los que no
+//! se entra más que por la cabecera.
+//!
+//! Las instrucciones `LoadConst` y `LoadGlobal` cuyo resultado no varía
+//! entre iteraciones se mueven a un bloque previo a la cabecera. Si
+//! algún salto desde fuera del ciclo llega a la cabecera, este se
+//! redirige a una nueva etiqueta al inicio de dicho bloque. Una carga
+//! es invariante si:
+//!
+//! - Es la única escritura a su local dentro del ciclo.
+//! - La local no está viva al entrar al ciclo ni al salir de él.
+//! - En el caso de `LoadGlobal`, el ciclo no escribe a la global ni
+//!   llama a nada que pueda hacerlo: procedimientos generados, llamadas
+//!   indirectas o rutinas que ejecutan tareas programadas, ver
+//!   [`crate::builtins::runs_tasks()`].
+//!
+//! Las instrucciones `Kill` de la local dentro del ciclo se descartan,
+//! ya que tras mover la carga su valor debe sobrevivir entre iteraciones.
+//!
+//! Los ciclos más internos se tratan primero, por lo que una carga
+//! puede salir de varios ciclos anidados.
+
+use std::collections::HashMap;
+
+use crate::{
+    builtins,
+    codegen::{
+        liveness::{accesses, successors, Access, Liveness},
+        locals_and_labels,
+    },
+    ir::{Function, GeneratedFunction, Instruction, Label, Program},
+    source::Located,
+};
+
+use super::Pass;
+
+/// Movimiento de cargas invariantes, ver el módulo.
+pub struct Licm;
+
+impl Pass for Licm {
+    fn name(&self) -> &'static str {
+        "licm"
+    }
+
+    fn run(&self, program: &mut Program) {
+        for function in &mut program.code {
+            while hoist(function) {}
+        }
+    }
+}
+
+/// Un ciclo natural.
+#[derive(Copy, Clone)]
+struct Loop {
+    /// Etiqueta de la cabecera.
+    label: u32,
+
+    /// Posición de la cabecera.
+    header: usize,
+
+    /// Posición del último salto hacia la cabecera.
+    latch: usize,
+}
+
+impl Loop {
+    fn contains(&self, position: usize) -> bool {
+        (self.header..=self.latch).contains(&position)
+    }
+}
+
+/// Mueve una carga invariante fuera de algún ciclo. Retorna `true` si
+/// lo hizo.
+fn hoist(function: &mut GeneratedFunction) -> bool {
+    let (locals, labels) = locals_and_labels(function);
+    let body = &function.body;
+
+    let liveness = Liveness::analyze(body, locals);
+    let successors = successors(body);
+
+    for natural in loops(body) {
+        let found = (natural.header..=natural.latch)
+            .find(|position| is_invariant(body, &successors, &liveness, natural, *position));
+
+        if let Some(position) = found {
+            move_to_preheader(function, natural, position, Label(labels));
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Ciclos naturales de una función, de menor a mayor extensión.
+fn loops(body: &[Located<Instruction>]) -> Vec<Loop> {
+    let headers: HashMap<_, _> = body
+        .iter()
+        .enumerate()
+        .filter_map(|(position, instruction)| match instruction.as_ref() {
+            Instruction::SetLabel(Label(label)) => Some((*label, position)),
+            _ => None,
+        })
+        .collect();
+
+    let mut loops: HashMap<u32, Loop> = HashMap::new();
+    for (position, target) in jumps(body) {
+        match headers.get(&target) {
+            Some(&header) if header < position => {
+                let natural = loops.entry(target).or_insert(Loop {
+                    label: target,
+                    header,
+                    latch: position,
+                });
+
+                natural.latch = natural.latch.max(position);
+            }
+
+            _ => (),
+        }
+    }
+
+    // Desde fuera solo puede saltarse a la cabecera
+    let mut loops: Vec<_> = loops
+        .values()
+        .copied()
+        .filter(|natural| {
+            jumps(body).all(|(position, target)| {
+                let inside = headers
+                    .get(&target)
+                    .map_or(false, |at| natural.contains(*at));
+                natural.contains(position) || !inside || target == natural.label
+            })
+        })
+        .collect();
+
+    loops.sort_by_key(|natural| (natural.latch - natural.header, natural.header));
+    loops
+}
+
+/// Posición y etiqueta destino de cada salto.
+fn jumps(body: &[Located<Instruction>]) -> impl '_ + Iterator<Item = (usize, u32)> {
+    body.iter()
+        .enumerate()
+        .filter_map(|(position, instruction)| match instruction.as_ref() {
+            Instruction::Jump(Label(label)) | Instruction::JumpIfFalse(_, Label(label)) => {
+                Some((position, *label))
+            }
+
+            _ => None,
+        })
+}
+
+fn is_invariant(
+    body: &[Located<Instruction>],
+    successors: &[[Option<usize>; 2]],
+    liveness: &Liveness,
+    natural: Loop,
+    position: usize,
+) -> bool {
+    use Instruction::*;
+
+    let (local, global) = match body[position].as_ref() {
+        LoadConst(_, local) => (*local, None),
+        LoadGlobal(global, local) => (*local, Some(global.as_ref())),
+        _ => return false,
+    };
+
+    let range = natural.header..=natural.latch;
+    let instructions = || body[range.clone()].iter().map(AsRef::as_ref);
+
+    let mut definitions = 0;
+    for instruction in instructions() {
+        accesses(instruction, |accessed, access| {
+            if matches!(access, Access::Def) && accessed == local {
+                definitions += 1;
+            }
+        });
+    }
+
+    let exits = range
+        .clone()
+        .flat_map(|position| successors[position].iter().flatten().copied())
+        .filter(|successor| !natural.contains(*successor));
+
+    let mut live = exits.chain(std::iter::once(natural.header));
+    if definitions != 1 || live.any(|position| liveness.is_needed(position, local)) {
+        return false;
+    }
+
+    let global = match global {
+        Some(global) => global,
+        None => return true,
+    };
+
+    instructions().all(|instruction| match instruction {
+        StoreGlobal(_, stored) => stored.as_ref() != global,
+
+        Call { target, .. } => match target {
+            Function::External(name) => !builtins::runs_tasks(name),
+            Function::Generated(_) | Function::Indirect(_) => false,
+        },
+
+        _ => true,
+    })
+}
+
+/// Mueve la instrucción en `position` a un bloque previo a la cabecera
+/// de un ciclo. `preheader` es una etiqueta libre para dicho bloque.
+fn move_to_preheader(
+    function: &mut GeneratedFunction,
+    natural: Loop,
+    position: usize,
+    preheader: Label,
+) {
+    let body = &mut function.body;
+
+    let mut entered = false;
+    for (at, instruction) in body.iter_mut().enumerate() {
+        if natural.contains(at) {
+            continue;
+        }
+
+        match &mut **instruction {
+            Instruction::Jump(label) | Instruction::JumpIfFalse(_, label)
+                if label.0 == natural.label =>
+            {
+                *label = preheader;
+                entered = true;
+            }
+
+            _ => (),
+        }
+    }
+
+    let hoisted = body.remove(position);
+    let local = match hoisted.as_ref() {
+        Instruction::LoadConst(_, local) | Instruction::LoadGlobal(_, local) => *local,
+        _ => unreachable!(),
+    };
+
+    body.insert(natural.header, hoisted);
+
+    let mut at = 0;
+    body.retain(|instruction| {
+        let inside = natural.contains(at);
+        at += 1;
+
+        !inside || !matches!(instruction.as_ref(), Instruction::Kill(killed) if *killed == local)
+    });
+
+    if entered {
+        let location = body[natural.header].location().clone();
+        body.insert(
+            natural.header,
+            Located::at(Instruction::SetLabel(preheader), location),
+        );
+    }
+}
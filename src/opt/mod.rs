@@ -0,0 +1,238 @@
+//! Transformaciones sobre representación intermedia.
+//!
+//! Entre el análisis semántico y la generación de código, un
+//! [`PassManager`] aplica en secuencia una serie de transformaciones
+//! ([`Pass`]) a un programa completo. Estas se dividen en dos grupos:
+//!
+//! - Optimizaciones, las cuales no alteran el comportamiento observable
+//!   del programa. Cada una se habilita o deshabilita por nombre, y el
+//!   nivel de optimización determina cuáles lo están por omisión.
+//! - Transformaciones que alteran el modelo de ejecución, como
+//!   [`crate::target::check_division()`] o [`crate::target::mark_frames()`].
+//!   Estas se solicitan explícitamente y se aplican siempre tras las
+//!   optimizaciones.
+//!
+//! Cada transformación se mide por separado en las estadísticas de
+//! compilación. En cada [`IrStage`] solicitada se escribe el listado del
+//! IR, ya sea completo o solo de un procedimiento.
+
+use std::{
+    fmt::{self, Display},
+    io::{self, Write},
+    str::FromStr,
+};
+
+use crate::{
+    codegen::inline::inline_calls,
+    ir::{GeneratedFunction, Program},
+    semantic,
+    stats::Statistics,
+};
+
+use self::{copy::CopyProp, dce::DeadCode, fold::ConstFold, licm::Licm};
+
+pub mod copy;
+pub mod dce;
+pub mod fold;
+pub mod licm;
+
+/// Una transformación sobre el IR de un programa completo.
+pub trait Pass {
+    /// Nombre con el que se identifica la transformación, tanto al
+    /// habilitarla como en las estadísticas.
+    fn name(&self) -> &'static str;
+
+    fn run(&self, program: &mut Program);
+}
+
+/// Transformación definida por una función, como las que reexporta
+/// [`crate::target`].
+#[derive(Copy, Clone)]
+pub struct Transform(pub &'static str, pub fn(&mut Program));
+
+impl Pass for Transform {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn run(&self, program: &mut Program) {
+        (self.1)(program)
+    }
+}
+
+/// Etapa en la que se inspecciona el IR de un programa.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IrStage {
+    /// Tal como resulta del análisis semántico.
+    BeforeOpt,
+
+    /// Tras las optimizaciones.
+    AfterOpt,
+
+    /// Tras todas las transformaciones, justo antes de emitir código.
+    Final,
+}
+
+impl FromStr for IrStage {
+    type Err = ();
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "before-opt" => Ok(IrStage::BeforeOpt),
+            "after-opt" => Ok(IrStage::AfterOpt),
+            "final" => Ok(IrStage::Final),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for IrStage {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stage = match self {
+            IrStage::BeforeOpt => "before-opt",
+            IrStage::AfterOpt => "after-opt",
+            IrStage::Final => "final",
+        };
+
+        fmt.write_str(stage)
+    }
+}
+
+/// Secuencia de transformaciones entre el análisis semántico y la
+/// emisión de código.
+pub struct PassManager {
+    /// Optimizaciones en orden de aplicación, junto a si están habilitadas.
+    optimizations: Vec<(Box<dyn Pass>, bool)>,
+
+    lowerings: Vec<Box<dyn Pass>>,
+    dumps: Vec<IrStage>,
+    dump_function: Option<String>,
+}
+
+impl PassManager {
+    /// Registra todas las optimizaciones conocidas, habilitando las que
+    /// corresponden al nivel de optimización: con `1` se pliegan
+    /// constantes, se propagan copias y se elimina código muerto, y con
+    /// `2` además se expanden en línea los procedimientos pequeños y se
+    /// mueven cargas invariantes fuera de los ciclos.
+    pub fn new(level: u32) -> Self {
+        let optimizations: Vec<(Box<dyn Pass>, bool)> = vec![
+            (Box::new(Transform("inline", inline_calls)), level >= 2),
+            (Box::new(ConstFold), level >= 1),
+            (Box::new(CopyProp), level >= 1),
+            (Box::new(Licm), level >= 2),
+            (Box::new(DeadCode), level >= 1),
+        ];
+
+        PassManager {
+            optimizations,
+            lowerings: Vec::new(),
+            dumps: Vec::new(),
+            dump_function: None,
+        }
+    }
+
+    /// Habilita o deshabilita una optimización por nombre. Retorna
+    /// `false` si no existe ninguna con ese nombre.
+    pub fn enable(&mut self, name: &str, enabled: bool) -> bool {
+        let optimization = self
+            .optimizations
+            .iter_mut()
+            .find(|(pass, _)| pass.name() == name);
+
+        match optimization {
+            Some((_, optimization)) => {
+                *optimization = enabled;
+                true
+            }
+
+            None => false,
+        }
+    }
+
+    /// Agrega una transformación posterior a las optimizaciones.
+    pub fn lower<P: Pass + 'static>(&mut self, pass: P) {
+        self.lowerings.push(Box::new(pass));
+    }
+
+    /// Escribe el IR al alcanzar `stage`.
+    pub fn dump(&mut self, stage: IrStage) {
+        self.dumps.push(stage);
+    }
+
+    /// Restringe los listados a un procedimiento, identificado por su
+    /// símbolo o bien por su nombre en el código fuente. En el segundo
+    /// caso se incluyen todas sus sobrecargas.
+    pub fn dump_function(&mut self, name: &str) {
+        self.dump_function = Some(name.to_string());
+    }
+
+    /// Aplica todas las transformaciones habilitadas a `program`,
+    /// midiéndolas en `stats` y escribiendo los listados solicitados a
+    /// `output`.
+    pub fn run<W: Write>(
+        &self,
+        program: &mut Program,
+        stats: &mut Statistics,
+        output: &mut W,
+    ) -> io::Result<()> {
+        self.dump_stage(IrStage::BeforeOpt, program, output)?;
+
+        let optimizations = self
+            .optimizations
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(pass, _)| pass);
+
+        for pass in optimizations {
+            stats.measure(pass.name(), || pass.run(program));
+        }
+
+        self.dump_stage(IrStage::AfterOpt, program, output)?;
+        for pass in &self.lowerings {
+            stats.measure(pass.name(), || pass.run(program));
+        }
+
+        self.dump_stage(IrStage::Final, program, output)
+    }
+
+    fn dump_stage<W: Write>(
+        &self,
+        stage: IrStage,
+        program: &Program,
+        output: &mut W,
+    ) -> io::Result<()> {
+        if !self.dumps.contains(&stage) {
+            return Ok(());
+        }
+
+        writeln!(output, ";; IR {}", stage)?;
+        if self.dump_function.is_none() {
+            for global in program.globals.iter() {
+                writeln!(output, "[GLOBAL {}]", global.as_ref())?;
+            }
+        }
+
+        for function in program.code.iter() {
+            if self.selects(function) {
+                write!(output, "{}", function)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Determina si los listados incluyen a una función, ver
+    /// [`PassManager::dump_function()`].
+    pub fn selects(&self, function: &GeneratedFunction) -> bool {
+        let name = match &self.dump_function {
+            Some(name) => name,
+            None => return true,
+        };
+
+        let source_name = semantic::demangle(&function.name)
+            .and_then(|demangled| demangled.split('(').next().map(str::to_string));
+
+        *function.name == *name || source_name == Some(name.to_lowercase())
+    }
+}
@@ -7,14 +7,19 @@ use std::{
 };
 
 use crate::{
-    lex::{Identifier, Keyword, NoCase, Token},
+    error::Help,
+    lex::{Identifier, Keyword, NoCase, StrLiteral, Token},
     source::{Located, Location},
 };
 
 #[derive(Debug)]
 pub struct Ast {
+    imports: Vec<Import>,
+    enums: Vec<Enum>,
+    constants: Vec<Constant>,
     procedures: Vec<Procedure>,
     eof: Location,
+    max_depth: u32,
 }
 
 impl Ast {
@@ -22,15 +27,193 @@ impl Ast {
         self.procedures.iter()
     }
 
+    /// Constantes globales en orden de declaración.
+    pub fn constants(&self) -> &[Constant] {
+        &self.constants
+    }
+
+    /// Enumeraciones en orden de declaración.
+    pub fn enums(&self) -> &[Enum] {
+        &self.enums
+    }
+
+    /// Módulos que importa el programa, ver [`crate::import`].
+    pub fn imports(&self) -> &[Import] {
+        &self.imports
+    }
+
+    /// Incorpora las constantes y procedimientos de módulos importados.
+    ///
+    /// Las declaraciones de los módulos preceden a las propias, en el
+    /// orden dado. Se descarta el punto de entrada de cada módulo, de
+    /// forma que una biblioteca pueda incluir su propio `main()`.
+    pub(crate) fn merge(&mut self, modules: Vec<Ast>) {
+        let mut enums = Vec::new();
+        let mut constants = Vec::new();
+        let mut procedures = Vec::new();
+
+        for module in modules {
+            enums.extend(module.enums);
+            constants.extend(module.constants);
+            procedures.extend(
+                module
+                    .procedures
+                    .into_iter()
+                    .filter(|procedure| !procedure.is_entrypoint()),
+            );
+        }
+
+        enums.append(&mut self.enums);
+        constants.append(&mut self.constants);
+        procedures.append(&mut self.procedures);
+
+        self.enums = enums;
+        self.constants = constants;
+        self.procedures = procedures;
+    }
+
+    /// Reemplaza o antepone las constantes de `defined`, ver
+    /// [`crate::define()`]. Cualquier otra declaración se ignora.
+    pub(crate) fn define(&mut self, defined: Ast) {
+        for constant in defined.constants {
+            let name = constant.name.as_ref();
+            let previous = self
+                .constants
+                .iter()
+                .position(|other| other.name.as_ref() == name);
+
+            match previous {
+                Some(index) => self.constants[index] = constant,
+                None => self.constants.insert(0, constant),
+            }
+        }
+    }
+
     pub fn eof(&self) -> &Location {
         &self.eof
     }
+
+    /// Límite de anidamiento con el que se analizó el programa. El
+    /// análisis semántico lo respeta también, ver [`MAX_DEPTH`].
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    /// Cantidad de nodos del árbol: procedimientos, enumeraciones y sus
+    /// variantes, constantes, parámetros, tipos, enunciados, objetivos,
+    /// índices y expresiones.
+    pub fn node_count(&self) -> usize {
+        let enums: usize = self
+            .enums
+            .iter()
+            .map(|enumeration| {
+                let variants = enumeration.variants.iter().map(|variant| {
+                    let value = variant.value.as_ref().map_or(0, |value| value.node_count());
+                    1 + value
+                });
+
+                1 + variants.sum::<usize>()
+            })
+            .sum();
+
+        let constants: usize = self
+            .constants
+            .iter()
+            .map(|constant| 1 + constant.value.node_count())
+            .sum();
+
+        let procedures: usize = self
+            .procedures
+            .iter()
+            .map(|procedure| {
+                let parameters: usize = procedure
+                    .parameters
+                    .iter()
+                    .map(|parameter| 1 + parameter.of.node_count())
+                    .sum();
+
+                let returns = procedure.returns.as_ref().map_or(0, |of| of.node_count());
+                1 + parameters + returns + Statement::node_count_all(&procedure.statements)
+            })
+            .sum();
+
+        enums + constants + procedures
+    }
+}
+
+/// Declaración `import "ruta";` fuera de todo procedimiento.
+#[derive(Debug)]
+pub struct Import {
+    path: Located<String>,
+}
+
+impl Import {
+    /// Ruta del módulo, tal como se escribió.
+    pub fn path(&self) -> &Located<String> {
+        &self.path
+    }
+}
+
+/// Declaración `enum Nombre { A = expr, B, ... }` fuera de todo
+/// procedimiento.
+///
+/// Cada variante es una constante entera global. Una variante sin valor
+/// explícito vale uno más que la anterior, o cero si es la primera.
+#[derive(Debug)]
+pub struct Enum {
+    name: Located<Identifier>,
+    variants: Vec<Variant>,
+}
+
+impl Enum {
+    pub fn name(&self) -> &Located<Identifier> {
+        &self.name
+    }
+
+    pub fn variants(&self) -> &[Variant] {
+        &self.variants
+    }
+}
+
+#[derive(Debug)]
+pub struct Variant {
+    name: Located<Identifier>,
+    value: Option<Located<Expr>>,
+}
+
+impl Variant {
+    pub fn name(&self) -> &Located<Identifier> {
+        &self.name
+    }
+
+    /// Valor explícito, si se indicó.
+    pub fn value(&self) -> Option<&Located<Expr>> {
+        self.value.as_ref()
+    }
+}
+
+/// Declaración `const NOMBRE = expr;` fuera de todo procedimiento.
+#[derive(Debug)]
+pub struct Constant {
+    name: Located<Identifier>,
+    value: Located<Expr>,
+}
+
+impl Constant {
+    pub fn name(&self) -> &Located<Identifier> {
+        &self.name
+    }
+
+    pub fn value(&self) -> &Located<Expr> {
+        &self.value
+    }
 }
 
 #[derive(Debug)]
 pub struct Procedure {
     name: Located<Identifier>,
     parameters: Vec<Parameter>,
+    returns: Option<Located<Type>>,
     statements: Vec<Statement>,
 }
 
@@ -43,6 +226,10 @@ impl Procedure {
         &self.parameters
     }
 
+    pub fn returns(&self) -> Option<&Located<Type>> {
+        self.returns.as_ref()
+    }
+
     pub fn statements(&self) -> &[Statement] {
         &self.statements
     }
@@ -52,6 +239,7 @@ impl Procedure {
 pub struct Parameter {
     name: Located<Identifier>,
     of: Located<Type>,
+    by_ref: bool,
 }
 
 impl Parameter {
@@ -59,6 +247,12 @@ impl Parameter {
         &self.name
     }
 
+    /// Indica si el parámetro se declaró con `ref`, en cuyo caso el
+    /// procedimiento toma prestado el objeto de quien llama.
+    pub fn is_ref(&self) -> bool {
+        self.by_ref
+    }
+
     pub fn of(&self) -> &Located<Type> {
         &self.of
     }
@@ -69,16 +263,28 @@ pub enum Type {
     Int,
     Bool,
     List,
+    IntList,
     Mat,
     Float,
+    Str,
     Of(Box<Located<Expr>>),
 }
 
+impl Type {
+    fn node_count(&self) -> usize {
+        match self {
+            Type::Of(expr) => 1 + expr.node_count(),
+            _ => 1,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Statement {
     If {
         condition: Located<Expr>,
         body: Vec<Statement>,
+        otherwise: Option<Vec<Statement>>,
     },
 
     For {
@@ -88,6 +294,13 @@ pub enum Statement {
         body: Vec<Statement>,
     },
 
+    /// Ciclo infinito `loop at fps { ... }`, con iteraciones espaciadas
+    /// por el runtime a la tasa de cuadros indicada.
+    Loop {
+        rate: Located<Expr>,
+        body: Vec<Statement>,
+    },
+
     UserCall {
         procedure: Located<Identifier>,
         args: Vec<Located<Expr>>,
@@ -95,6 +308,17 @@ pub enum Statement {
 
     GlobalLift(Located<Identifier>),
 
+    Return {
+        location: Location,
+        value: Option<Located<Expr>>,
+    },
+
+    /// Termina el ciclo `for` más interno.
+    Break(Location),
+
+    /// Salta a la siguiente iteración del ciclo `for` más interno.
+    Continue(Location),
+
     Assignment {
         targets: Vec<Located<Target>>,
         values: Vec<Located<Expr>>,
@@ -135,6 +359,160 @@ pub enum Statement {
         index: Located<Expr>,
         object: Located<Expr>,
     },
+
+    PrintLedB {
+        column: Located<Expr>,
+        row: Located<Expr>,
+        level: Located<Expr>,
+    },
+
+    PrintText {
+        text: Located<Expr>,
+        speed: Located<Expr>,
+    },
+
+    Show(Location),
+
+    DisplaySize {
+        width: Located<Expr>,
+        height: Located<Expr>,
+    },
+
+    /// Cada cuadro es una lista de ocho filas y una duración en
+    /// milisegundos, ver [`crate::semantic`].
+    Play {
+        location: Location,
+        frames: Vec<Located<Expr>>,
+        looping: Option<Located<Expr>>,
+    },
+
+    Seed(Located<Expr>),
+
+    Store {
+        slot: Located<Expr>,
+        value: Located<Expr>,
+    },
+
+    UartWrite(Located<Expr>),
+
+    /// Planifica un procedimiento sin parámetros, ver [`crate::semantic`].
+    Schedule {
+        delay: Located<Expr>,
+        procedure: Located<Identifier>,
+        repeat: bool,
+    },
+}
+
+impl Statement {
+    /// Ubicación representativa de un enunciado.
+    ///
+    /// Corresponde a la primera subexpresión o identificador con
+    /// ubicación conocida, lo cual basta para determinar la línea.
+    pub fn location(&self) -> &Location {
+        use Statement::*;
+
+        match self {
+            If { condition, .. } => condition.location(),
+            For { iterable, .. } => iterable.location(),
+            Loop { rate, .. } => rate.location(),
+            UserCall { procedure, .. } => procedure.location(),
+            GlobalLift(id) => id.location(),
+            Return { location, .. } => location,
+            Break(location) | Continue(location) => location,
+            Assignment { targets, .. } => targets[0].location(),
+            MethodCall { target, .. } => target.location(),
+            Debug { location, .. } => location,
+            Blink { column, .. } => column.location(),
+            Delay { count, .. } => count.location(),
+            PrintLed { column, .. } => column.location(),
+            PrintLedX { index, .. } => index.location(),
+            PrintLedB { column, .. } => column.location(),
+            PrintText { text, .. } => text.location(),
+            Show(location) => location,
+            DisplaySize { width, .. } => width.location(),
+            Play { location, .. } => location,
+            Seed(seed) => seed.location(),
+            Store { slot, .. } => slot.location(),
+            UartWrite(value) => value.location(),
+            Schedule { delay, .. } => delay.location(),
+        }
+    }
+
+    fn node_count(&self) -> usize {
+        use Statement::*;
+
+        let exprs = |exprs: &[&Located<Expr>]| -> usize {
+            exprs.iter().map(|expr| expr.node_count()).sum()
+        };
+
+        let children = match self {
+            If {
+                condition,
+                body,
+                otherwise,
+            } => {
+                let otherwise = otherwise.as_deref().map_or(0, Statement::node_count_all);
+                condition.node_count() + Statement::node_count_all(body) + otherwise
+            }
+
+            For {
+                iterable,
+                step,
+                body,
+                ..
+            } => {
+                let step = step.as_ref().map_or(0, |step| step.node_count());
+                iterable.node_count() + step + Statement::node_count_all(body)
+            }
+
+            Loop { rate, body } => rate.node_count() + Statement::node_count_all(body),
+
+            UserCall { args, .. } => Expr::node_count_all(args),
+            GlobalLift(_) | Break(_) | Continue(_) | Show(_) => 0,
+            Return { value, .. } => value.as_ref().map_or(0, |value| value.node_count()),
+
+            Assignment { targets, values } => {
+                let targets: usize = targets.iter().map(|target| target.node_count()).sum();
+                targets + Expr::node_count_all(values)
+            }
+
+            MethodCall { target, args, .. } => target.node_count() + Expr::node_count_all(args),
+            Debug { hint, .. } => hint.as_ref().map_or(0, |hint| hint.node_count()),
+
+            Blink {
+                column,
+                row,
+                count,
+                state,
+                ..
+            } => exprs(&[column, row, count, state]),
+
+            Delay { count, .. } => count.node_count(),
+            PrintLed { column, row, value } => exprs(&[column, row, value]),
+            PrintLedX { index, object, .. } => exprs(&[index, object]),
+            PrintLedB { column, row, level } => exprs(&[column, row, level]),
+            PrintText { text, speed } => exprs(&[text, speed]),
+            DisplaySize { width, height } => exprs(&[width, height]),
+
+            Play {
+                frames, looping, ..
+            } => {
+                let looping = looping.as_ref().map_or(0, |looping| looping.node_count());
+                Expr::node_count_all(frames) + looping
+            }
+
+            Seed(seed) => seed.node_count(),
+            Store { slot, value } => exprs(&[slot, value]),
+            UartWrite(value) => value.node_count(),
+            Schedule { delay, .. } => delay.node_count(),
+        };
+
+        1 + children
+    }
+
+    fn node_count_all(statements: &[Statement]) -> usize {
+        statements.iter().map(Statement::node_count).sum()
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -156,15 +534,32 @@ pub enum Expr {
     True,
     False,
     Integer(i32),
+    Float(f32),
+    Str(StrLiteral),
     Read(Located<Identifier>),
     Attr(Box<Located<Expr>>, Located<Identifier>),
     Index(Box<Located<Expr>>, Box<Located<Index>>),
     Len(Box<Located<Expr>>),
     Range(Box<Located<Expr>>, Box<Located<Expr>>),
+    Random(Box<Located<Expr>>),
+    Load(Box<Located<Expr>>),
+    UartRead,
+    HeapUsed,
+    HeapFree,
+
+    /// Milisegundos transcurridos desde el inicio del programa.
+    Millis,
+
     List(Vec<Located<Expr>>),
     New(Located<Type>),
     Cast(Located<Type>, Box<Located<Expr>>),
     Negate(Box<Located<Expr>>),
+    Not(Box<Located<Expr>>),
+    Call(Located<Identifier>, Vec<Located<Expr>>),
+
+    /// Referencia `@procedimiento`, la cual puede invocarse con `call`.
+    ProcRef(Located<Identifier>),
+
     Binary {
         limits: ExprLimits,
         lhs: Box<Located<Expr>>,
@@ -173,6 +568,31 @@ pub enum Expr {
     },
 }
 
+impl Expr {
+    fn node_count(&self) -> usize {
+        use Expr::*;
+
+        let children = match self {
+            True | False | Integer(_) | Float(_) | Str(_) | Read(_) | ProcRef(_) => 0,
+            UartRead | HeapUsed | HeapFree | Millis => 0,
+            Attr(expr, _) | Len(expr) | Random(expr) | Load(expr) => expr.node_count(),
+            Negate(expr) | Not(expr) => expr.node_count(),
+            Index(expr, index) => expr.node_count() + index.node_count(),
+            Range(start, end) => start.node_count() + end.node_count(),
+            List(items) | Call(_, items) => Expr::node_count_all(items),
+            New(of) => of.node_count(),
+            Cast(of, expr) => of.node_count() + expr.node_count(),
+            Binary { lhs, rhs, .. } => lhs.node_count() + rhs.node_count(),
+        };
+
+        1 + children
+    }
+
+    fn node_count_all(exprs: &[Located<Expr>]) -> usize {
+        exprs.iter().map(|expr| expr.node_count()).sum()
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum ExprLimits {
     Free,
@@ -194,6 +614,13 @@ pub enum BinOp {
     LessOrEqual,
     Greater,
     GreaterOrEqual,
+    And,
+    Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
 }
 
 impl Display for BinOp {
@@ -214,6 +641,13 @@ impl Display for BinOp {
             LessOrEqual => "<=",
             Greater => ">",
             GreaterOrEqual => ">=",
+            And => "&&",
+            Or => "||",
+            BitAnd => "&",
+            BitOr => "|",
+            BitXor => "^",
+            ShiftLeft => "<<",
+            ShiftRight => ">>",
         };
 
         fmt.write_str(string)
@@ -234,6 +668,14 @@ impl Target {
     pub fn indices(&self) -> &[Located<Index>] {
         &self.indices
     }
+
+    fn node_count(&self) -> usize {
+        1 + self
+            .indices
+            .iter()
+            .map(|index| index.node_count())
+            .sum::<usize>()
+    }
 }
 
 #[derive(Debug)]
@@ -244,6 +686,18 @@ pub enum Index {
     Transposed(Located<Expr>),
 }
 
+impl Index {
+    fn node_count(&self) -> usize {
+        let children = match self {
+            Index::Single(expr) | Index::Transposed(expr) => expr.node_count(),
+            Index::Range(start, end) => start.node_count() + end.node_count(),
+            Index::Indirect(row, column) => row.node_count() + column.node_count(),
+        };
+
+        1 + children
+    }
+}
+
 #[non_exhaustive]
 #[derive(Error, Debug)]
 pub enum ParserError {
@@ -271,22 +725,78 @@ pub enum ParserError {
     #[error("Expected one of {0}")]
     ExpectedOption(String),
 
+    #[error("Expected module path as a string literal, found {0}")]
+    ExpectedPath(Token),
+
     #[error("Missing type annotation for procedure parameter")]
     MissingParameterType,
 
     #[error("Abrupt end of program")]
     UnexpectedEof,
+
+    #[error("Nesting exceeds the limit of {0} levels")]
+    TooDeep(u32),
+}
+
+impl Help for ParserError {
+    fn help(&self) -> Option<String> {
+        match self {
+            ParserError::MissingParameterType => Some(String::from(
+                "annotate the parameter with its type, as in `name: int`",
+            )),
+
+            ParserError::TooDeep(_) => Some(String::from(
+                "move inner expressions or blocks to variables or procedures",
+            )),
+
+            _ => None,
+        }
+    }
 }
 
 pub trait TokenStream<'a> = Iterator<Item = &'a Located<Token>> + Clone;
 
+/// Límite predeterminado de anidamiento.
+///
+/// Tanto el parser como el análisis semántico son recursivos, por lo
+/// cual una entrada patológica, como miles de paréntesis anidados,
+/// agotaría la pila del compilador. Cada expresión delimitada y cada
+/// bloque de enunciados cuenta como un nivel al analizarse
+/// sintácticamente, y cada subexpresión como un nivel al analizarse
+/// semánticamente.
+pub const MAX_DEPTH: u32 = 256;
+
+/// Analiza sintácticamente un flujo de tokens.
+///
+/// Se ignora todo [`Token::Comment`], por lo cual el flujo puede
+/// provenir tanto de un lexer ordinario como de uno que preserva
+/// comentarios.
 pub fn parse<'a, T>(tokens: T, empty_location: Location) -> Result<Ast, Located<ParserError>>
 where
     T: TokenStream<'a>,
 {
+    parse_with_max_depth(tokens, empty_location, MAX_DEPTH)
+}
+
+/// Como [`parse()`], pero con un límite de anidamiento distinto a
+/// [`MAX_DEPTH`].
+pub fn parse_with_max_depth<'a, T>(
+    tokens: T,
+    empty_location: Location,
+    max_depth: u32,
+) -> Result<Ast, Located<ParserError>>
+where
+    T: TokenStream<'a>,
+{
+    // Los comentarios solo existen con `Lexer::with_comments()`, y no
+    // son parte de la sintaxis
+    let tokens = tokens.filter(|token| !matches!(token.as_ref(), Token::Comment(_)));
+
     let parser = Parser {
         tokens: tokens.peekable(),
         last_known: empty_location,
+        depth: 0,
+        max_depth,
         lifetime_hack: PhantomData,
     };
 
@@ -297,6 +807,8 @@ where
 struct Parser<'a, I: TokenStream<'a>> {
     tokens: Peekable<I>,
     last_known: Location,
+    depth: u32,
+    max_depth: u32,
     lifetime_hack: PhantomData<&'a ()>,
 }
 
@@ -372,19 +884,26 @@ impl BinOp {
         use BinOp::*;
 
         match self {
-            Equal => 0,
-            NotEqual => 0,
-            Less => 0,
-            LessOrEqual => 0,
-            Greater => 0,
-            GreaterOrEqual => 0,
-            Add => 1,
-            Sub => 1,
-            Mul => 2,
-            Div => 2,
-            Mod => 2,
-            IntegerDiv => 2,
-            Pow => 3,
+            Or => 0,
+            And => 1,
+            Equal => 2,
+            NotEqual => 2,
+            Less => 2,
+            LessOrEqual => 2,
+            Greater => 2,
+            GreaterOrEqual => 2,
+            BitOr => 3,
+            BitXor => 4,
+            BitAnd => 5,
+            ShiftLeft => 6,
+            ShiftRight => 6,
+            Add => 7,
+            Sub => 7,
+            Mul => 8,
+            Div => 8,
+            Mod => 8,
+            IntegerDiv => 8,
+            Pow => 9,
         }
     }
 
@@ -415,17 +934,77 @@ impl<T> ParseExt for Parse<T> {
 
 impl<'a, I: TokenStream<'a>> Parser<'a, I> {
     fn program(mut self) -> Parse<Ast> {
+        let mut imports = Vec::new();
+        let mut enums = Vec::new();
+        let mut constants = Vec::new();
         let mut procedures = Vec::new();
-        while self.tokens.peek().is_some() {
-            procedures.push(self.procedure()?);
+
+        while let Some(token) = self.tokens.peek() {
+            match token.as_ref() {
+                Token::Keyword(Keyword::Import) => imports.push(self.import()?),
+                Token::Keyword(Keyword::Enum) => enums.push(self.enumeration()?),
+                Token::Keyword(Keyword::Const) => constants.push(self.constant()?),
+                _ => procedures.push(self.procedure()?),
+            }
         }
 
         Ok(Ast {
+            imports,
+            enums,
+            constants,
             procedures,
             eof: self.last_known,
+            max_depth: self.max_depth,
         })
     }
 
+    fn import(&mut self) -> Parse<Import> {
+        self.keyword(Keyword::Import)?;
+
+        let (location, token) = self.next()?.split();
+        let path = match token {
+            Token::StrLiteral(literal) => AsRef::<str>::as_ref(literal.as_ref()).to_string(),
+            token => return self.fail(ParserError::ExpectedPath(token)),
+        };
+
+        self.expect(Token::Semicolon)?;
+        Ok(Import {
+            path: Located::at(path, location),
+        })
+    }
+
+    fn enumeration(&mut self) -> Parse<Enum> {
+        self.keyword(Keyword::Enum)?;
+        let name = self.id()?;
+
+        self.expect(Token::OpenCurly)?;
+        let variants = self.comma_separated(Self::variant, false)?;
+        self.expect(Token::CloseCurly)?;
+
+        Ok(Enum { name, variants })
+    }
+
+    fn variant(&mut self) -> Parse<Variant> {
+        let name = self.id()?;
+        let value = match self.optional(|s| s.expect(Token::Assign).weak())? {
+            None => None,
+            Some(()) => Some(self.expr().strict()?),
+        };
+
+        Ok(Variant { name, value })
+    }
+
+    fn constant(&mut self) -> Parse<Constant> {
+        self.keyword(Keyword::Const)?;
+        let name = self.id()?;
+
+        self.expect(Token::Assign)?;
+        let value = self.expr().strict()?;
+        self.expect(Token::Semicolon)?;
+
+        Ok(Constant { name, value })
+    }
+
     fn procedure(&mut self) -> Parse<Procedure> {
         self.keyword(Keyword::Procedure)?;
         let name = self.id()?;
@@ -434,17 +1013,28 @@ impl<'a, I: TokenStream<'a>> Parser<'a, I> {
         let parameters = self.comma_separated(Self::parameter, true)?;
         self.expect(Token::CloseParen)?;
 
+        let returns = match self.optional(|s| s.expect(Token::Colon).weak())? {
+            None => None,
+            Some(()) => Some(self.typ()?),
+        };
+
         let statements = self.statement_block()?;
 
         Ok(Procedure {
             name,
             parameters,
+            returns,
             statements,
         })
     }
 
     fn parameter(&mut self) -> Parse<Parameter> {
-        let name = self.id().weak()?;
+        let by_ref = self.optional(|s| s.keyword(Keyword::Ref).weak())?.is_some();
+        let name = if by_ref {
+            self.id()?
+        } else {
+            self.id().weak()?
+        };
 
         self.expect(Token::Colon).map_err(|_| {
             Failure::Strict(Located::at(
@@ -454,39 +1044,55 @@ impl<'a, I: TokenStream<'a>> Parser<'a, I> {
         })?;
 
         let of = self.typ()?;
-        Ok(Parameter { name, of })
+        Ok(Parameter { name, of, by_ref })
     }
 
     fn statement_block(&mut self) -> Parse<Vec<Statement>> {
         self.expect(Token::OpenCurly)?;
 
-        let mut statements = Vec::new();
-        loop {
-            match self.attempt(Self::statement) {
-                Ok(statement) => statements.push(statement),
-                Err(Failure::Weak(error)) => {
-                    self.expect(Token::CloseCurly)
-                        .map_err(|_| Failure::Strict(error))?;
+        self.nested(|s| {
+            let mut statements = Vec::new();
+            loop {
+                match s.attempt(Self::statement) {
+                    Ok(statement) => statements.push(statement),
+                    Err(Failure::Weak(error)) => {
+                        s.expect(Token::CloseCurly)
+                            .map_err(|_| Failure::Strict(error))?;
 
-                    break Ok(statements);
-                }
+                        break Ok(statements);
+                    }
 
-                Err(error) => break Err(error),
+                    Err(error) => break Err(error),
+                }
             }
-        }
+        })
     }
 
     fn statement(&mut self) -> Parse<Statement> {
         match self.lookahead(Self::next)?.into_inner() {
             Token::Keyword(Keyword::If) => self.if_statement(),
             Token::Keyword(Keyword::For) => self.for_statement(),
+            Token::Keyword(Keyword::Loop) => self.loop_statement(),
             Token::Keyword(Keyword::Call) => self.user_call(),
             Token::Keyword(Keyword::Global) => self.global_lift(),
+            Token::Keyword(Keyword::Return) => self.return_statement(),
+            Token::Keyword(Keyword::Break) => self.jump(Keyword::Break, Statement::Break),
+            Token::Keyword(Keyword::Continue) => self.jump(Keyword::Continue, Statement::Continue),
             Token::Keyword(Keyword::Debug) => self.debug(),
             Token::Keyword(Keyword::Blink) => self.blink(),
             Token::Keyword(Keyword::Delay) => self.delay(),
             Token::Keyword(Keyword::PrintLed) => self.print_led(),
             Token::Keyword(Keyword::PrintLedX) => self.print_led_x(),
+            Token::Keyword(Keyword::PrintLedB) => self.print_led_b(),
+            Token::Keyword(Keyword::PrintText) => self.print_text(),
+            Token::Keyword(Keyword::Show) => self.show(),
+            Token::Keyword(Keyword::Play) => self.play(),
+            Token::Keyword(Keyword::DisplaySize) => self.display_size(),
+            Token::Keyword(Keyword::Seed) => self.seed(),
+            Token::Keyword(Keyword::Store) => self.store(),
+            Token::Keyword(Keyword::UartWrite) => self.uart_write(),
+            Token::Keyword(Keyword::After) => self.schedule(Keyword::After, false),
+            Token::Keyword(Keyword::Every) => self.schedule(Keyword::Every, true),
 
             Token::Id(_) => {
                 let targets = self.comma_separated(Self::target, false)?;
@@ -511,10 +1117,27 @@ impl<'a, I: TokenStream<'a>> Parser<'a, I> {
 
     fn if_statement(&mut self) -> Parse<Statement> {
         self.keyword(Keyword::If)?;
+        self.conditional()
+    }
+
+    fn conditional(&mut self) -> Parse<Statement> {
         let condition = self.expr().strict()?;
         let body = self.statement_block()?;
 
-        Ok(Statement::If { condition, body })
+        // `elif` equivale a un `else` cuyo único enunciado es otro `if`
+        let otherwise = match self.optional(|s| s.keyword(Keyword::Else).weak())? {
+            Some(()) => Some(self.statement_block()?),
+            None => match self.optional(|s| s.keyword(Keyword::Elif).weak())? {
+                Some(()) => Some(vec![self.conditional()?]),
+                None => None,
+            },
+        };
+
+        Ok(Statement::If {
+            condition,
+            body,
+            otherwise,
+        })
     }
 
     fn for_statement(&mut self) -> Parse<Statement> {
@@ -539,6 +1162,16 @@ impl<'a, I: TokenStream<'a>> Parser<'a, I> {
         })
     }
 
+    fn loop_statement(&mut self) -> Parse<Statement> {
+        self.keyword(Keyword::Loop)?;
+        self.keyword(Keyword::At)?;
+
+        let rate = self.expr().strict()?;
+        let body = self.statement_block()?;
+
+        Ok(Statement::Loop { rate, body })
+    }
+
     fn user_call(&mut self) -> Parse<Statement> {
         self.keyword(Keyword::Call)?;
         let (procedure, args) = self.id_call()?;
@@ -555,6 +1188,28 @@ impl<'a, I: TokenStream<'a>> Parser<'a, I> {
         Ok(Statement::GlobalLift(id))
     }
 
+    fn return_statement(&mut self) -> Parse<Statement> {
+        self.keyword(Keyword::Return)?;
+        let location = self.last_known.clone();
+
+        let value = self.optional(Self::expr)?;
+        self.expect(Token::Semicolon)?;
+
+        Ok(Statement::Return { location, value })
+    }
+
+    /// `break;` o `continue;`.
+    fn jump<F>(&mut self, keyword: Keyword, statement: F) -> Parse<Statement>
+    where
+        F: FnOnce(Location) -> Statement,
+    {
+        self.keyword(keyword)?;
+        let location = self.last_known.clone();
+
+        self.expect(Token::Semicolon)?;
+        Ok(statement(location))
+    }
+
     fn debug(&mut self) -> Parse<Statement> {
         self.keyword(Keyword::Debug)?;
         let location = self.last_known.clone();
@@ -655,6 +1310,135 @@ impl<'a, I: TokenStream<'a>> Parser<'a, I> {
         })
     }
 
+    fn print_led_b(&mut self) -> Parse<Statement> {
+        self.keyword(Keyword::PrintLedB)?;
+        self.expect(Token::OpenParen)?;
+
+        let column = self.expr().strict()?;
+        self.expect(Token::Comma)?;
+
+        let row = self.expr().strict()?;
+        self.expect(Token::Comma)?;
+
+        let level = self.expr().strict()?;
+        self.expect(Token::CloseParen)?;
+        self.expect(Token::Semicolon)?;
+
+        Ok(Statement::PrintLedB { column, row, level })
+    }
+
+    fn print_text(&mut self) -> Parse<Statement> {
+        self.keyword(Keyword::PrintText)?;
+        self.expect(Token::OpenParen)?;
+
+        let text = self.expr().strict()?;
+        self.expect(Token::Comma)?;
+
+        let speed = self.expr().strict()?;
+        self.expect(Token::CloseParen)?;
+        self.expect(Token::Semicolon)?;
+
+        Ok(Statement::PrintText { text, speed })
+    }
+
+    fn show(&mut self) -> Parse<Statement> {
+        self.keyword(Keyword::Show)?;
+        let location = self.last_known.clone();
+
+        self.expect(Token::Semicolon)?;
+        Ok(Statement::Show(location))
+    }
+
+    fn display_size(&mut self) -> Parse<Statement> {
+        self.keyword(Keyword::DisplaySize)?;
+        self.expect(Token::OpenParen)?;
+
+        let width = self.expr().strict()?;
+        self.expect(Token::Comma)?;
+
+        let height = self.expr().strict()?;
+        self.expect(Token::CloseParen)?;
+        self.expect(Token::Semicolon)?;
+
+        Ok(Statement::DisplaySize { width, height })
+    }
+
+    fn play(&mut self) -> Parse<Statement> {
+        self.keyword(Keyword::Play)?;
+        let location = self.last_known.clone();
+
+        self.expect(Token::OpenSquare)?;
+        let frames = self.comma_separated(Self::expr, false)?;
+        self.expect(Token::CloseSquare)?;
+
+        let looping = match self.optional(|s| s.expect(Token::Comma).weak())? {
+            Some(()) => Some(self.expr().strict()?),
+            None => None,
+        };
+
+        self.expect(Token::Semicolon)?;
+
+        Ok(Statement::Play {
+            location,
+            frames,
+            looping,
+        })
+    }
+
+    fn seed(&mut self) -> Parse<Statement> {
+        self.keyword(Keyword::Seed)?;
+        self.expect(Token::OpenParen)?;
+
+        let seed = self.expr().strict()?;
+        self.expect(Token::CloseParen)?;
+        self.expect(Token::Semicolon)?;
+
+        Ok(Statement::Seed(seed))
+    }
+
+    fn store(&mut self) -> Parse<Statement> {
+        self.keyword(Keyword::Store)?;
+        self.expect(Token::OpenParen)?;
+
+        let slot = self.expr().strict()?;
+        self.expect(Token::Comma)?;
+
+        let value = self.expr().strict()?;
+        self.expect(Token::CloseParen)?;
+        self.expect(Token::Semicolon)?;
+
+        Ok(Statement::Store { slot, value })
+    }
+
+    fn uart_write(&mut self) -> Parse<Statement> {
+        self.keyword(Keyword::UartWrite)?;
+        self.expect(Token::OpenParen)?;
+
+        let value = self.expr().strict()?;
+        self.expect(Token::CloseParen)?;
+        self.expect(Token::Semicolon)?;
+
+        Ok(Statement::UartWrite(value))
+    }
+
+    fn schedule(&mut self, keyword: Keyword, repeat: bool) -> Parse<Statement> {
+        self.keyword(keyword)?;
+        self.expect(Token::OpenParen)?;
+
+        let delay = self.expr().strict()?;
+        self.expect(Token::Comma)?;
+
+        let procedure = self.id()?;
+        self.expect(Token::CloseParen)?;
+        self.expect(Token::Semicolon)?;
+
+        Ok(Statement::Schedule {
+            delay,
+            procedure,
+            repeat,
+        })
+    }
+
     fn time_unit(&mut self) -> Parse<TimeUnit> {
         const UNITS: &'static [(NoCase<&'static str>, TimeUnit)] = &[
             (NoCase::new("mil"), TimeUnit::Millis),
@@ -810,8 +1594,10 @@ impl<'a, I: TokenStream<'a>> Parser<'a, I> {
             Token::Keyword(Keyword::Int) => Type::Int,
             Token::Keyword(Keyword::Bool) => Type::Bool,
             Token::Keyword(Keyword::List) => Type::List,
+            Token::Keyword(Keyword::IntList) => Type::IntList,
             Token::Keyword(Keyword::Mat) => Type::Mat,
             Token::Keyword(Keyword::Float) => Type::Float,
+            Token::Keyword(Keyword::String) => Type::Str,
 
             Token::Keyword(Keyword::Type) => {
                 self.expect(Token::OpenParen)?;
@@ -838,6 +1624,10 @@ impl<'a, I: TokenStream<'a>> Parser<'a, I> {
     }
 
     fn delimited_expr(&mut self) -> Parse<Located<Expr>> {
+        self.nested(Self::delimited_expr_unbounded)
+    }
+
+    fn delimited_expr_unbounded(&mut self) -> Parse<Located<Expr>> {
         let terminal = |s: &mut _, expr| {
             let (location, _) = Self::next(s)?.split();
             Ok((location, expr))
@@ -847,13 +1637,17 @@ impl<'a, I: TokenStream<'a>> Parser<'a, I> {
             Token::Keyword(Keyword::True) => terminal(self, Expr::True)?,
             Token::Keyword(Keyword::False) => terminal(self, Expr::False)?,
             Token::IntLiteral(integer) => terminal(self, Expr::Integer(integer))?,
+            Token::FloatLiteral(float) => terminal(self, Expr::Float(float))?,
+            Token::StrLiteral(literal) => terminal(self, Expr::Str(literal))?,
 
             Token::Keyword(
                 Keyword::Int
                 | Keyword::Bool
                 | Keyword::List
+                | Keyword::IntList
                 | Keyword::Mat
                 | Keyword::Float
+                | Keyword::String
                 | Keyword::Type,
             ) => self.new_or_cast()?.split(),
 
@@ -870,6 +1664,58 @@ impl<'a, I: TokenStream<'a>> Parser<'a, I> {
                 (location, call)
             }
 
+            Token::Keyword(Keyword::Random) => {
+                let (start, _) = self.next()?.split();
+                self.expect(Token::OpenParen)?;
+
+                let max = self.expr().strict()?;
+                self.expect(Token::CloseParen)?;
+
+                let call = Expr::Random(Box::new(max));
+                let location = Location::span(start, &self.last_known);
+
+                (location, call)
+            }
+
+            Token::Keyword(Keyword::Load) => {
+                let (start, _) = self.next()?.split();
+                self.expect(Token::OpenParen)?;
+
+                let slot = self.expr().strict()?;
+                self.expect(Token::CloseParen)?;
+
+                let call = Expr::Load(Box::new(slot));
+                let location = Location::span(start, &self.last_known);
+
+                (location, call)
+            }
+
+            Token::Keyword(Keyword::UartRead) => {
+                let (start, _) = self.next()?.split();
+                self.expect(Token::OpenParen)?;
+                self.expect(Token::CloseParen)?;
+
+                let location = Location::span(start, &self.last_known);
+                (location, Expr::UartRead)
+            }
+
+            Token::Keyword(keyword @ Keyword::HeapUsed)
+            | Token::Keyword(keyword @ Keyword::HeapFree)
+            | Token::Keyword(keyword @ Keyword::Millis) => {
+                let (start, _) = self.next()?.split();
+                self.expect(Token::OpenParen)?;
+                self.expect(Token::CloseParen)?;
+
+                let expr = match keyword {
+                    Keyword::HeapUsed => Expr::HeapUsed,
+                    Keyword::HeapFree => Expr::HeapFree,
+                    _ => Expr::Millis,
+                };
+
+                let location = Location::span(start, &self.last_known);
+                (location, expr)
+            }
+
             Token::Keyword(Keyword::Range) => {
                 let (start, _) = self.next()?.split();
                 self.expect(Token::OpenParen)?;
@@ -886,6 +1732,24 @@ impl<'a, I: TokenStream<'a>> Parser<'a, I> {
                 (location, call)
             }
 
+            Token::Keyword(Keyword::Call) => {
+                let (start, _) = self.next()?.split();
+                let (procedure, args) = self.id_call()?;
+
+                let call = Expr::Call(procedure, args);
+                let location = Location::span(start, &self.last_known);
+
+                (location, call)
+            }
+
+            Token::At => {
+                let (start, _) = self.next()?.split();
+                let procedure = self.id()?;
+                let location = Location::span(start, procedure.location());
+
+                (location, Expr::ProcRef(procedure))
+            }
+
             Token::Minus => {
                 let (start, _) = self.next()?.split();
                 let inner = self.delimited_expr().strict()?;
@@ -894,6 +1758,14 @@ impl<'a, I: TokenStream<'a>> Parser<'a, I> {
                 (location, Expr::Negate(Box::new(inner)))
             }
 
+            Token::Not => {
+                let (start, _) = self.next()?.split();
+                let inner = self.delimited_expr().strict()?;
+                let location = Location::span(start, inner.location());
+
+                (location, Expr::Not(Box::new(inner)))
+            }
+
             Token::OpenParen => {
                 let (start, _) = self.next()?.split();
                 let expr = match self.expr().strict()?.into_inner() {
@@ -975,10 +1847,33 @@ impl<'a, I: TokenStream<'a>> Parser<'a, I> {
             Token::LessOrEqual => Ok(LessOrEqual),
             Token::Greater => Ok(Greater),
             Token::GreaterOrEqual => Ok(GreaterOrEqual),
+            Token::And => Ok(And),
+            Token::Or => Ok(Or),
+            Token::BitAnd => Ok(BitAnd),
+            Token::BitOr => Ok(BitOr),
+            Token::BitXor => Ok(BitXor),
+            Token::ShiftLeft => Ok(ShiftLeft),
+            Token::ShiftRight => Ok(ShiftRight),
             token => self.fail(ParserError::ExpectedOperator(token)).weak(),
         }
     }
 
+    /// Aplica una regla un nivel de anidamiento más adentro.
+    fn nested<T, F>(&mut self, rule: F) -> Parse<T>
+    where
+        F: FnOnce(&mut Self) -> Parse<T>,
+    {
+        if self.depth >= self.max_depth {
+            return self.fail(ParserError::TooDeep(self.max_depth));
+        }
+
+        self.depth += 1;
+        let result = rule(self);
+        self.depth -= 1;
+
+        result
+    }
+
     fn optional<T, F>(&mut self, rule: F) -> Parse<Option<T>>
     where
         F: FnOnce(&mut Self) -> Parse<T>,
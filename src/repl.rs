@@ -0,0 +1,412 @@
+//! Evaluación interactiva.
+//!
+//! Una [`Session`] mantiene un ámbito persistente de variables globales
+//! y procedimientos. Cada entrada atraviesa las mismas fases delanteras
+//! que un programa completo y luego se ejecuta en el intérprete de
+//! [`crate::interp`], cuyo estado también persiste entre entradas.
+//!
+//! # Síntesis de programas
+//! Una entrada que inicia con `procedure` define procedimientos. Un
+//! procedimiento redefinido reemplaza a todos los anteriores del mismo
+//! nombre. Cualquier otra entrada se traduce a un programa completo:
+//!
+//! ```text
+//! procedure main() {
+//!     x = <valor con el que se definió x>;
+//!     ...
+//! }
+//!
+//! <procedimientos definidos durante la sesión>
+//!
+//! procedure repl_input() {
+//!     global x;
+//!     ...
+//!     <entrada>
+//! }
+//! ```
+//!
+//! `main()` nunca se ejecuta, solo existe para que el análisis semántico
+//! declare las globales y sus tipos. Una asignación en el nivel superior
+//! de la entrada a una variable desconocida define una nueva global. Si
+//! la entrada no es sintácticamente un enunciado, se evalúa en cambio
+//! `debug(<entrada>);` para mostrar su valor.
+//!
+//! Los tokens de cada fragmento conservan sus ubicaciones originales,
+//! por lo cual los diagnósticos señalan la entrada donde se escribió
+//! el código en cuestión.
+
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::{
+    error::{Diagnostics, Help},
+    interp::{self, Display, Host, Machine},
+    lex::{Identifier, Keyword, Lexer, Token},
+    parse::{self, Ast, Statement},
+    source::{self, Located, Location},
+};
+
+/// Nombre del procedimiento que contiene cada entrada.
+const ENTRY: &str = "repl_input";
+
+/// Nombre de origen de las entradas en diagnósticos.
+const SOURCE_NAME: &str = "<repl>";
+
+/// Resultado de una entrada exitosa.
+pub enum Evaluation {
+    /// La entrada no contenía tokens.
+    Empty,
+
+    /// Se definieron procedimientos.
+    Defined(Vec<Identifier>),
+
+    /// Se ejecutaron enunciados.
+    Executed,
+}
+
+/// Una entrada fallida.
+pub enum EvalError {
+    /// La entrada no superó las fases delanteras.
+    Rejected(Diagnostics),
+
+    /// La entrada falló durante su ejecución.
+    Runtime(interp::Error),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Rejected(diagnostics) => diagnostics.fmt(fmt),
+            EvalError::Runtime(error) => writeln!(fmt, "{}", error),
+        }
+    }
+}
+
+impl From<Diagnostics> for EvalError {
+    fn from(diagnostics: Diagnostics) -> Self {
+        EvalError::Rejected(diagnostics)
+    }
+}
+
+/// Errores propios de la sesión.
+#[derive(Error, Debug)]
+enum SessionError {
+    #[error("`{0}` is reserved by the REPL")]
+    Reserved(Identifier),
+}
+
+impl Help for SessionError {}
+
+/// Una global de la sesión.
+struct Declaration {
+    name: Identifier,
+
+    /// Enunciado de declaración en `main()`.
+    tokens: Vec<Located<Token>>,
+}
+
+/// Un procedimiento de la sesión.
+struct Definition {
+    name: Identifier,
+    tokens: Vec<Located<Token>>,
+}
+
+/// Sesión interactiva.
+#[derive(Default)]
+pub struct Session {
+    globals: Vec<Declaration>,
+    procedures: Vec<Definition>,
+    machine: Machine,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Estado actual de la matriz de LEDs.
+    pub fn display(&self) -> &Display {
+        self.machine.display()
+    }
+
+    /// Nombres de las globales definidas hasta el momento.
+    pub fn globals(&self) -> impl Iterator<Item = &Identifier> {
+        self.globals.iter().map(|global| &global.name)
+    }
+
+    /// Nombres de los procedimientos definidos hasta el momento.
+    pub fn procedures(&self) -> impl Iterator<Item = &Identifier> {
+        self.procedures.iter().map(|procedure| &procedure.name)
+    }
+
+    /// Evalúa una entrada.
+    pub fn eval<H: Host>(&mut self, input: &str, host: &mut H) -> Result<Evaluation, EvalError> {
+        let (start, tokens) = lex(input, SOURCE_NAME)?;
+
+        match tokens.first().map(Located::as_ref) {
+            None => Ok(Evaluation::Empty),
+            Some(Token::Keyword(Keyword::Procedure)) => self.define(tokens, start),
+            Some(_) => self.execute(tokens, start, host),
+        }
+    }
+
+    fn define(
+        &mut self,
+        tokens: Vec<Located<Token>>,
+        start: Location,
+    ) -> Result<Evaluation, EvalError> {
+        let ast = parse::parse(tokens.iter(), start)
+            .map_err(|error| Diagnostics::from(error).kind("Syntax error"))?;
+
+        let names: Vec<_> = ast.iter().map(|procedure| procedure.name()).collect();
+        for name in names.iter() {
+            if is_reserved(name) {
+                let error = Located::at(
+                    SessionError::Reserved(name.as_ref().clone()),
+                    name.location().clone(),
+                );
+                return Err(Diagnostics::from(error).kind("Semantic error").into());
+            }
+        }
+
+        // Cada procedimiento inicia con su palabra clave
+        let mut chunks: Vec<Vec<Located<Token>>> = Vec::new();
+        for token in tokens {
+            match (token.as_ref(), chunks.last_mut()) {
+                (Token::Keyword(Keyword::Procedure), _) | (_, None) => chunks.push(vec![token]),
+                (_, Some(chunk)) => chunk.push(token),
+            }
+        }
+
+        let names: Vec<Identifier> = names
+            .into_iter()
+            .map(|name| name.as_ref().clone())
+            .collect();
+        let mut procedures: Vec<Definition> = self
+            .procedures
+            .iter()
+            .filter(|procedure| !names.contains(&procedure.name))
+            .map(|procedure| Definition {
+                name: procedure.name.clone(),
+                tokens: procedure.tokens.clone(),
+            })
+            .collect();
+
+        procedures.extend(names.iter().zip(chunks).map(|(name, tokens)| Definition {
+            name: name.clone(),
+            tokens,
+        }));
+
+        // El programa debe seguir siendo válido con los nuevos procedimientos
+        let program = self.synthesize(&procedures, &[], &[]);
+        resolve(&program)?;
+
+        self.procedures = procedures;
+        Ok(Evaluation::Defined(names))
+    }
+
+    fn execute<H: Host>(
+        &mut self,
+        tokens: Vec<Located<Token>>,
+        start: Location,
+        host: &mut H,
+    ) -> Result<Evaluation, EvalError> {
+        let mut body = tokens.clone();
+        match body.last().map(Located::as_ref) {
+            Some(Token::Semicolon) | Some(Token::CloseCurly) => (),
+            _ => body.extend(synthetic(";")),
+        }
+
+        // Se intenta primero como enunciados y luego como expresión
+        let (body, ast) = match self.parse_entry(&body, &[], &start) {
+            Ok(ast) => (body, ast),
+
+            Err(error) => {
+                let mut expr = synthetic("debug(");
+                expr.extend(tokens.iter().cloned());
+                if let Some(Token::Semicolon) = expr.last().map(Located::as_ref) {
+                    expr.pop();
+                }
+
+                expr.extend(synthetic(");"));
+                match self.parse_entry(&expr, &[], &start) {
+                    Ok(ast) => (expr, ast),
+                    Err(_) => return Err(Diagnostics::from(error).kind("Syntax error").into()),
+                }
+            }
+        };
+
+        let declarations = self.new_globals(&ast, &body);
+        let ast = match declarations.is_empty() {
+            true => ast,
+            false => self
+                .parse_entry(&body, &declarations, &start)
+                .map_err(|error| Diagnostics::from(error).kind("Syntax error"))?,
+        };
+
+        let program = ast
+            .resolve()
+            .map_err(|error| Diagnostics::from(error).kind("Semantic error"))?;
+
+        self.globals.extend(declarations);
+
+        let entry = format!("user_{}", ENTRY);
+        self.machine
+            .run(&program, &entry, host)
+            .map_err(EvalError::Runtime)?;
+
+        Ok(Evaluation::Executed)
+    }
+
+    fn parse_entry(
+        &self,
+        body: &[Located<Token>],
+        declarations: &[Declaration],
+        start: &Location,
+    ) -> Result<Ast, Located<parse::ParserError>> {
+        let tokens = self.synthesize(&self.procedures, declarations, body);
+        parse::parse(tokens.iter(), start.clone())
+    }
+
+    /// Determina las globales que define una entrada.
+    fn new_globals(&self, ast: &Ast, body: &[Located<Token>]) -> Vec<Declaration> {
+        let entry = ast.iter().find(|procedure| {
+            procedure
+                .name()
+                .as_ref()
+                .as_ref()
+                .eq_ignore_ascii_case(ENTRY)
+        });
+
+        let statements = entry.map(|entry| entry.statements()).unwrap_or(&[]);
+        let mut declarations: Vec<Declaration> = Vec::new();
+
+        for statement in statements {
+            let (targets, values) = match statement {
+                Statement::Assignment { targets, values } if targets.len() == values.len() => {
+                    (targets, values)
+                }
+
+                _ => continue,
+            };
+
+            for (target, value) in targets.iter().zip(values.iter()) {
+                let name = target.var().as_ref();
+                let known = self.globals().chain(declarations.iter().map(|d| &d.name));
+
+                if !target.indices().is_empty() || known.into_iter().any(|known| known == name) {
+                    continue;
+                }
+
+                let mut tokens = synthetic(&format!("{} =", name));
+                tokens.extend(within(body, value.location()).cloned());
+                tokens.extend(synthetic(";"));
+
+                declarations.push(Declaration {
+                    name: name.clone(),
+                    tokens,
+                });
+            }
+        }
+
+        declarations
+    }
+
+    /// Construye los tokens del programa que corresponde a una entrada.
+    fn synthesize(
+        &self,
+        procedures: &[Definition],
+        declarations: &[Declaration],
+        body: &[Located<Token>],
+    ) -> Vec<Located<Token>> {
+        let globals = || self.globals.iter().chain(declarations);
+
+        let mut tokens = synthetic("procedure main() {");
+        for global in globals() {
+            tokens.extend(global.tokens.iter().cloned());
+        }
+
+        tokens.extend(synthetic("}"));
+        for procedure in procedures {
+            tokens.extend(procedure.tokens.iter().cloned());
+        }
+
+        if !body.is_empty() {
+            tokens.extend(synthetic(&format!("procedure {}() {{", ENTRY)));
+            for global in globals() {
+                tokens.extend(synthetic(&format!("global {};", global.name)));
+            }
+
+            tokens.extend(body.iter().cloned());
+            tokens.extend(synthetic("}"));
+        }
+
+        tokens
+    }
+}
+
+/// Determina si una entrada está incompleta, es decir, si tiene llaves
+/// o paréntesis sin cerrar y por tanto debe continuar en otra línea.
+pub fn is_incomplete(input: &str) -> bool {
+    let tokens = match lex(input, SOURCE_NAME) {
+        Ok((_, tokens)) => tokens,
+        Err(_) => return false,
+    };
+
+    let depth = tokens.iter().fold(0, |depth, token| match token.as_ref() {
+        Token::OpenCurly | Token::OpenParen | Token::OpenSquare => depth + 1,
+        Token::CloseCurly | Token::CloseParen | Token::CloseSquare => depth - 1,
+        _ => depth,
+    });
+
+    depth > 0
+}
+
+fn lex(input: &str, name: &str) -> Result<(Location, Vec<Located<Token>>), Diagnostics> {
+    let (start, stream) = source::consume(input.as_bytes(), name);
+    let tokens = Lexer::new(start.clone(), stream)
+        .try_exhaustive()
+        .map_err(|errors| Diagnostics::from(errors).kind("Lexical error"))?;
+
+    Ok((start, tokens))
+}
+
+/// Tokens de código que no proviene del usuario.
+fn synthetic(code: &str) -> Vec<Located<Token>> {
+    match lex(code, SOURCE_NAME) {
+        Ok((_, tokens)) => tokens,
+        Err(_) => unreachable!("bad synthetic code: {}", code),
+    }
+}
+
+fn resolve(tokens: &[Located<Token>]) -> Result<(), Diagnostics> {
+    let start = tokens[0].location().clone();
+    let ast = parse::parse(tokens.iter(), start)
+        .map_err(|error| Diagnostics::from(error).kind("Syntax error"))?;
+
+    ast.resolve()
+        .map(drop)
+        .map_err(|error| Diagnostics::from(error).kind("Semantic error"))
+}
+
+fn is_reserved(name: &Identifier) -> bool {
+    let name = name.as_ref();
+    name.eq_ignore_ascii_case("main") || name.eq_ignore_ascii_case(ENTRY)
+}
+
+/// Tokens contenidos en una ubicación.
+fn within<'a>(
+    tokens: &'a [Located<Token>],
+    location: &'a Location,
+) -> impl 'a + Iterator<Item = &'a Located<Token>> {
+    let key = |position: source::Position| (position.line(), position.column());
+    let (start, end) = (key(location.start()), key(location.end()));
+
+    tokens.iter().filter(move |token| {
+        let token = token.location();
+        std::ptr::eq(token.source(), location.source())
+            && key(token.start()) >= start
+            && key(token.end()) <= end
+    })
+}
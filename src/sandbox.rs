@@ -0,0 +1,422 @@
+//! Ejecución de programas nativos.
+//!
+//! Con límites el programa se ejecuta bajo restricciones de tiempo
+//! de CPU, tiempo real y memoria, y sin la posibilidad de crear otros
+//! procesos. Esto protege a quien evalúa programas ajenos de ciclos
+//! infinitos, consumo desmedido de memoria y "fork bombs".
+//!
+//! # Mecanismo
+//! Entre `fork()` y `execve()` el proceso hijo aplica `setrlimit()`
+//! y un filtro seccomp que rechaza `fork()`, `vfork()` y `clone()`
+//! salvo para crear hilos. Los límites de CPU y tiempo real se vigilan
+//! además desde quien invoca, de forma que el hijo pueda detenerse
+//! antes de que el kernel lo termine. Así es posible leer de su memoria
+//! el valor de `builtin_line` y reportar una línea de código fuente
+//! cercana a la que se encontraba en ejecución. Esta es la de la última
+//! llamada a una rutina de `libruntime` que podía fallar, ya que el
+//! compilador solo actualiza `builtin_line` antes de estas.
+//!
+//! Este mecanismo solo existe en Linux x86-64. En otras plataformas los
+//! programas pueden ejecutarse, pero no con límites.
+
+use std::{
+    fmt::{self, Display},
+    io,
+    path::Path,
+    process::{Command, ExitStatus},
+};
+
+/// Código de salida cuando se excede un límite, al igual que `timeout(1)`.
+const LIMIT_EXIT_CODE: i32 = 124;
+
+/// Límites impuestos sobre el programa.
+#[derive(Clone)]
+pub struct Limits {
+    /// Tiempo de CPU, en segundos.
+    pub cpu: u64,
+
+    /// Tiempo real, en segundos.
+    pub wall: u64,
+
+    /// Espacio de direcciones, en MiB.
+    pub memory: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            cpu: 5,
+            wall: 30,
+            memory: 256,
+        }
+    }
+}
+
+/// Motivo por el cual se detuvo un programa.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    Cpu(u64),
+    Wall(u64),
+    Memory(u64),
+    Signal(i32),
+}
+
+/// Forma en la que concluyó un programa.
+pub struct Termination {
+    pub status: ExitStatus,
+
+    /// Límite excedido o señal que terminó al programa, solo con límites.
+    pub violation: Option<Violation>,
+
+    /// Línea en ejecución al momento de detener el programa, si se conoce.
+    pub line: Option<usize>,
+}
+
+impl Termination {
+    /// Código de salida al estilo de una shell: `128 + N` para la señal `N`.
+    pub fn exit_code(&self) -> i32 {
+        if let Some(Violation::Cpu(_)) | Some(Violation::Wall(_)) | Some(Violation::Memory(_)) =
+            self.violation
+        {
+            return LIMIT_EXIT_CODE;
+        }
+
+        match (self.status.code(), signal(&self.status)) {
+            (Some(code), _) => code,
+            (None, Some(signal)) => 128 + signal,
+            (None, None) => 1,
+        }
+    }
+
+    /// Describe la terminación de la misma forma que un error de compilación.
+    ///
+    /// `name` y `source` son el nombre y contenido del código fuente del
+    /// cual proviene el ejecutable. Se retorna `None` si no hay nada anormal.
+    pub fn diagnostic(&self, name: &str, source: Option<&str>) -> Option<String> {
+        use fmt::Write;
+
+        let mut output = format!("Runtime error: {}\n", self.violation?);
+
+        let line = match self.line {
+            Some(line) if line > 0 => line,
+            _ => return Some(output),
+        };
+
+        writeln!(output, " --> {}:{}", name, line).unwrap();
+
+        let text = source.and_then(|source| source.lines().nth(line - 1));
+        if let Some(text) = text {
+            let digits = line.to_string().chars().count();
+            writeln!(output, "{:digits$} |", "", digits = digits).unwrap();
+            writeln!(output, "{:>digits$} | {}", line, text, digits = digits).unwrap();
+        }
+
+        Some(output)
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Cpu(seconds) => write!(fmt, "CPU time limit of {}s exceeded", seconds),
+            Violation::Wall(seconds) => write!(fmt, "wall time limit of {}s exceeded", seconds),
+            Violation::Memory(mib) => write!(
+                fmt,
+                "program aborted, memory limit of {} MiB may have been exceeded",
+                mib
+            ),
+
+            Violation::Signal(signal) => write!(fmt, "terminated by signal {}", signal),
+        }
+    }
+}
+
+/// Prepara la ejecución de un programa.
+///
+/// A diferencia de `Command::new()`, un nombre sin separadores
+/// se refiere al directorio actual y no se busca en `PATH`.
+pub fn command(executable: &Path) -> Command {
+    match executable.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => {
+            Command::new(Path::new(".").join(executable))
+        }
+
+        _ => Command::new(executable),
+    }
+}
+
+/// Ejecuta un programa y espera a que concluya.
+///
+/// `command` debe provenir de [`command()`] para el mismo `executable`.
+pub fn run(
+    mut command: Command,
+    executable: &Path,
+    limits: Option<&Limits>,
+) -> io::Result<Termination> {
+    let limits = match limits {
+        Some(limits) => limits,
+        None => {
+            return Ok(Termination {
+                status: command.status()?,
+                violation: None,
+                line: None,
+            })
+        }
+    };
+
+    limited::run(command, executable, limits)
+}
+
+/// Señal que terminó a un proceso.
+#[cfg(unix)]
+fn signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn signal(_: &ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Sin seccomp ni `/proc` no hay forma de imponer los límites.
+#[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+mod limited {
+    use super::{Limits, Termination};
+    use std::{io, path::Path, process::Command};
+
+    pub fn run(_: Command, _: &Path, _: &Limits) -> io::Result<Termination> {
+        let message = "execution limits are unsupported on this platform";
+        Err(io::Error::new(io::ErrorKind::Other, message))
+    }
+}
+
+/// Ejecución con límites mediante `setrlimit()`, seccomp y `/proc`.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+mod limited {
+    use super::{Limits, Termination, Violation};
+    use crate::link;
+
+    use std::{
+        convert::TryInto,
+        fs::{self, File},
+        io::{self, Read, Seek, SeekFrom},
+        os::unix::process::{CommandExt, ExitStatusExt},
+        path::{Path, PathBuf},
+        process::{Child, Command},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    /// Intervalo entre revisiones del estado del proceso hijo.
+    const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+    pub fn run(
+        mut command: Command,
+        executable: &Path,
+        limits: &Limits,
+    ) -> io::Result<Termination> {
+        let filter = seccomp_filter();
+        let (cpu, memory) = (limits.cpu, limits.memory << 20);
+
+        // Solo se invocan syscalls, que son async-signal-safe
+        unsafe {
+            command.pre_exec(move || {
+                // El kernel envía SIGXCPU al alcanzar el límite suave y
+                // SIGKILL al alcanzar el duro. Se deja un segundo de margen
+                // para que el driver detenga al proceso primero.
+                set_limit(libc::RLIMIT_CPU, cpu + 1)?;
+                set_limit(libc::RLIMIT_AS, memory)?;
+                set_limit(libc::RLIMIT_CORE, 0)?;
+
+                install_filter(&filter)
+            });
+        }
+
+        let mut child = command.spawn()?;
+
+        let start_time = Instant::now();
+        loop {
+            if let Some(status) = child.try_wait()? {
+                let violation = match status.signal() {
+                    Some(libc::SIGXCPU) | Some(libc::SIGKILL) => Some(Violation::Cpu(limits.cpu)),
+                    Some(libc::SIGABRT) => Some(Violation::Memory(limits.memory)),
+                    Some(signal) => Some(Violation::Signal(signal)),
+                    None => None,
+                };
+
+                return Ok(Termination {
+                    status,
+                    violation,
+                    line: None,
+                });
+            }
+
+            let violation =
+                if cpu_time(&child).unwrap_or_default() >= Duration::from_secs(limits.cpu) {
+                    Violation::Cpu(limits.cpu)
+                } else if start_time.elapsed() >= Duration::from_secs(limits.wall) {
+                    Violation::Wall(limits.wall)
+                } else {
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                };
+
+            let line = stop_at_line(&child, executable);
+            let _ = child.kill();
+
+            return Ok(Termination {
+                status: child.wait()?,
+                violation: Some(violation),
+                line,
+            });
+        }
+    }
+
+    /// Tiempo de CPU consumido por un proceso, según `/proc/PID/stat`.
+    fn cpu_time(child: &Child) -> Option<Duration> {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", child.id())).ok()?;
+
+        // El nombre del proceso puede contener espacios y paréntesis
+        let fields: Vec<&str> = stat[stat.rfind(')')? + 1..].split_whitespace().collect();
+        let utime: u64 = fields.get(11)?.parse().ok()?;
+        let stime: u64 = fields.get(12)?.parse().ok()?;
+
+        let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if ticks_per_second <= 0 {
+            return None;
+        }
+
+        let millis = (utime + stime) * 1000 / ticks_per_second as u64;
+        Some(Duration::from_millis(millis))
+    }
+
+    /// Detiene un proceso y lee el valor de `builtin_line` en su memoria.
+    ///
+    /// Retorna `None` si el ejecutable carece de símbolos o si no es
+    /// posible leer la memoria del proceso.
+    fn stop_at_line(child: &Child, executable: &Path) -> Option<usize> {
+        let pid = child.id() as libc::pid_t;
+        unsafe {
+            libc::kill(pid, libc::SIGSTOP);
+        }
+
+        let elf = fs::read(executable).ok()?;
+        let offset = link::symbol_offset(&elf, "builtin_line").ok()??;
+        let base = load_base(pid, executable)?;
+
+        let mut mem = File::open(format!("/proc/{}/mem", pid)).ok()?;
+        let mut value = [0; 8];
+
+        mem.seek(SeekFrom::Start(base + offset)).ok()?;
+        mem.read_exact(&mut value).ok()?;
+
+        Some(i64::from_ne_bytes(value)).and_then(|line| line.try_into().ok())
+    }
+
+    /// Dirección en la que se cargó el inicio de un ejecutable, según `/proc/PID/maps`.
+    fn load_base(pid: libc::pid_t, executable: &Path) -> Option<u64> {
+        let executable: PathBuf = executable.canonicalize().ok()?;
+        let maps = fs::read_to_string(format!("/proc/{}/maps", pid)).ok()?;
+
+        // Formato: "inicio-fin permisos offset dispositivo inodo ruta"
+        maps.lines().find_map(|mapping| {
+            let fields: Vec<&str> = mapping.split_whitespace().collect();
+            match fields.as_slice() {
+                [range, _, "00000000", _, _, path] if Path::new(path) == executable => {
+                    let start = range.split('-').next()?;
+                    u64::from_str_radix(start, 16).ok()
+                }
+
+                _ => None,
+            }
+        })
+    }
+
+    fn set_limit(resource: libc::__rlimit_resource_t, value: u64) -> io::Result<()> {
+        let limit = libc::rlimit {
+            rlim_cur: value,
+            rlim_max: value,
+        };
+
+        match unsafe { libc::setrlimit(resource, &limit) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    /// Construye un filtro seccomp que impide crear procesos.
+    ///
+    /// Se permiten hilos (`clone()` con `CLONE_THREAD`). `clone3()` recibe
+    /// sus banderas en memoria, donde un filtro BPF no puede leerlas, por
+    /// lo cual se rechaza con `ENOSYS` y glibc recurre a `clone()`.
+    fn seccomp_filter() -> Vec<libc::sock_filter> {
+        const AUDIT_ARCH_X86_64: u32 = 0xc000_003e;
+        const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+        // Desplazamientos en struct seccomp_data
+        const NR: u32 = 0;
+        const ARCH: u32 = 4;
+        const ARG0: u32 = 16;
+
+        let statement = |code: u32, k: u32| libc::sock_filter {
+            code: code as u16,
+            jt: 0,
+            jf: 0,
+            k,
+        };
+
+        let jump = |code: u32, k: u32, jt: u8, jf: u8| libc::sock_filter {
+            code: (libc::BPF_JMP | code | libc::BPF_K) as u16,
+            jt,
+            jf,
+            k,
+        };
+
+        let load = |offset| statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, offset);
+        let ret = |value| statement(libc::BPF_RET | libc::BPF_K, value);
+
+        let eperm = libc::SECCOMP_RET_ERRNO | libc::EPERM as u32;
+        let enosys = libc::SECCOMP_RET_ERRNO | libc::ENOSYS as u32;
+
+        // Los saltos son relativos a la siguiente instrucción
+        vec![
+            /* 00 */ load(ARCH),
+            /* 01 */ jump(libc::BPF_JEQ, AUDIT_ARCH_X86_64, 0, 11),
+            /* 02 */ load(NR),
+            /* 03 */ jump(libc::BPF_JSET, X32_SYSCALL_BIT, 7, 0),
+            /* 04 */ jump(libc::BPF_JEQ, libc::SYS_clone3 as u32, 7, 0),
+            /* 05 */ jump(libc::BPF_JEQ, libc::SYS_fork as u32, 5, 0),
+            /* 06 */ jump(libc::BPF_JEQ, libc::SYS_vfork as u32, 4, 0),
+            /* 07 */ jump(libc::BPF_JEQ, libc::SYS_clone as u32, 0, 2),
+            /* 08 */ load(ARG0),
+            /* 09 */ jump(libc::BPF_JSET, libc::CLONE_THREAD as u32, 0, 1),
+            /* 10 */ ret(libc::SECCOMP_RET_ALLOW),
+            /* 11 */ ret(eperm),
+            /* 12 */ ret(enosys),
+            /* 13 */ ret(libc::SECCOMP_RET_KILL_PROCESS),
+        ]
+    }
+
+    /// Instala un filtro seccomp en el proceso actual.
+    fn install_filter(filter: &[libc::sock_filter]) -> io::Result<()> {
+        let program = libc::sock_fprog {
+            len: filter.len() as u16,
+            filter: filter.as_ptr() as *mut _,
+        };
+
+        unsafe {
+            // Requisito para instalar filtros sin CAP_SYS_ADMIN
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mode = libc::SECCOMP_MODE_FILTER as libc::c_ulong;
+            if libc::prctl(libc::PR_SET_SECCOMP, mode, &program as *const _) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+}
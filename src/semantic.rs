@@ -2,24 +2,39 @@ use thiserror::Error;
 
 use std::{
     borrow::Borrow,
-    collections::{HashMap, HashSet},
+    cell::RefCell,
+    collections::{hash_map::Entry, HashMap, HashSet},
     fmt::{self, Display},
     rc::Rc,
 };
 
 use crate::{
+    builtins,
+    error::Help,
     ir::{self, Function, Global, Instruction, Label, Local},
-    lex::{Identifier, NoCase},
+    lex::{self, Identifier, NoCase},
     parse,
     source::{Located, Location},
 };
 
+/// Tamaño en bytes de cada cuadro que recibe `builtin_play_frames`.
+const PLAY_FRAME_SIZE: usize = 10;
+
+/// Máximo de llamadas anidadas que se reanalizan con argumentos
+/// conocidos, ver `Context::check_specialized()`.
+const MAX_SPECIALIZATION_DEPTH: u32 = 4;
+
 #[derive(Default)]
 struct SymbolTable<'a> {
     outer: Option<&'a SymbolTable<'a>>,
     symbols: HashMap<Identifier, Named>,
     statics: HashMap<Identifier, Static>,
     lifted: HashSet<Identifier>,
+    temporaries: Vec<(Local, Type)>,
+
+    /// Locales definidas en este ámbito que aún no han sido leídas,
+    /// junto a la ubicación de su definición.
+    unread: RefCell<HashMap<Identifier, Location>>,
 }
 
 impl SymbolTable<'_> {
@@ -38,21 +53,31 @@ impl SymbolTable<'_> {
         }
     }
 
+    /// Busca un símbolo para leerlo. A diferencia de `try_lookup()`,
+    /// esto cuenta como un uso del símbolo.
     fn lookup(&self, id: &Located<Identifier>) -> Semantic<&Named> {
-        self.try_lookup(id).ok_or_else(|| {
+        let (table, named) = self.find(id).ok_or_else(|| {
             Located::at(
                 SemanticError::Undefined(id.as_ref().clone()),
                 id.location().clone(),
             )
-        })
+        })?;
+
+        table.unread.borrow_mut().remove(id.as_ref());
+        Ok(named)
     }
 
     fn try_lookup(&self, id: &Located<Identifier>) -> Option<&Named> {
+        self.find(id).map(|(_, named)| named)
+    }
+
+    /// Encuentra un símbolo junto a la tabla que lo define.
+    fn find(&self, id: &Located<Identifier>) -> Option<(&Self, &Named)> {
         let mut table = self;
 
         loop {
             match table.symbols.get(id) {
-                Some(id) => break Some(id),
+                Some(named) => break Some((table, named)),
 
                 None => match table.outer.as_ref() {
                     Some(outer) => table = outer,
@@ -62,18 +87,51 @@ impl SymbolTable<'_> {
         }
     }
 
+    /// Valor estático de una variable en este ámbito, o bien de una
+    /// constante visible desde él.
     fn lookup_static(&self, id: &Identifier) -> Option<Static> {
-        self.statics.get(id).copied()
+        if let Some(value) = self.statics.get(id) {
+            return Some(*value);
+        }
+
+        let mut table = self;
+        loop {
+            match table.symbols.get(id) {
+                Some(Named::Var(Variable {
+                    access: Access::Const(value),
+                    ..
+                })) => break Some(*value),
+
+                Some(_) => break None,
+                None => table = table.outer?,
+            }
+        }
     }
 }
 
 enum Named {
     Var(Variable),
+
+    /// Familia de procedimientos homónimos. Cada sobrecarga se indexa
+    /// por sus tipos de parámetros y de retorno, por lo cual dos de
+    /// ellas pueden diferir solo en lo que retornan.
     Procs {
-        variants: HashMap<Vec<Type>, Rc<String>>,
+        variants: HashMap<(Vec<Type>, Option<Type>), Overload>,
     },
 }
 
+#[derive(Clone)]
+struct Overload {
+    symbol: Rc<String>,
+    returns: Option<Type>,
+
+    /// Parámetros declarados con `ref`, en orden.
+    refs: Vec<bool>,
+
+    /// Nombre del procedimiento en su definición.
+    location: Location,
+}
+
 #[derive(Clone)]
 struct Variable {
     access: Access,
@@ -84,6 +142,13 @@ struct Variable {
 enum Access {
     Global(Global),
     Local(Local),
+
+    /// Parámetro `ref`. La local contiene un objeto prestado por quien
+    /// llama, el cual el procedimiento no libera ni puede reemplazar.
+    Ref(Local),
+
+    /// Constante declarada con `const`, no ocupa espacio en tiempo de ejecución.
+    Const(Static),
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -91,8 +156,13 @@ pub enum Type {
     Int,
     Bool,
     List,
+    IntList,
     Mat,
     Float,
+    Str,
+
+    /// Referencia a un procedimiento, obtenida con `@`.
+    Proc(&'static Signature),
 }
 
 impl Display for Type {
@@ -101,20 +171,91 @@ impl Display for Type {
             Type::Int => "int",
             Type::Bool => "bool",
             Type::List => "list",
+            Type::IntList => "ilist",
             Type::Mat => "mat",
             Type::Float => "float",
+            Type::Str => "string",
+            Type::Proc(signature) => return signature.fmt(fmt),
         };
 
         fmt.write_str(string)
     }
 }
 
+/// Tipos de parámetros y de retorno de un procedimiento referido.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Signature {
+    pub parameters: Vec<Type>,
+
+    /// Parámetros declarados con `ref`, en orden.
+    pub refs: Vec<bool>,
+
+    pub returns: Option<Type>,
+}
+
+impl Signature {
+    /// Obtiene la única instancia de una firma, lo cual permite que
+    /// [`Type`] sea `Copy`. Las instancias nunca se liberan, pero solo
+    /// existe una por cada firma distinta.
+    fn intern(self) -> &'static Signature {
+        thread_local! {
+            static INTERNED: RefCell<HashSet<&'static Signature>> = Default::default();
+        }
+
+        INTERNED.with(|interned| {
+            let mut interned = interned.borrow_mut();
+            match interned.get(&self) {
+                Some(signature) => *signature,
+                None => {
+                    let signature = &*Box::leak(Box::new(self));
+                    interned.insert(signature);
+
+                    signature
+                }
+            }
+        })
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str("procedure(")?;
+
+        for (i, (typ, by_ref)) in self.parameters.iter().zip(&self.refs).enumerate() {
+            let separator = if i > 0 { ", " } else { "" };
+            let by_ref = if *by_ref { "ref " } else { "" };
+
+            write!(fmt, "{}{}{}", separator, by_ref, typ)?;
+        }
+
+        fmt.write_str(")")?;
+        match self.returns {
+            Some(returns) => write!(fmt, ": {}", returns),
+            None => Ok(()),
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 enum Ownership {
     Owned,
     Borrowed,
 }
 
+/// Uso que se le da al resultado de una llamada, a partir del cual se
+/// elige entre sobrecargas que solo difieren en su tipo de retorno.
+#[derive(Copy, Clone)]
+enum Expect {
+    /// El valor de retorno se descarta, como en `call`.
+    Discard,
+
+    /// Se espera un valor de tipo aún desconocido.
+    Value,
+
+    /// Se espera un valor del tipo indicado.
+    Type(Type),
+}
+
 #[derive(Copy, Clone)]
 enum AssignmentMode {
     /// Asignación en main() que no inicializa una global
@@ -127,16 +268,35 @@ enum AssignmentMode {
     Normal,
 }
 
+/// Sentido en que avanza el iterador de un ciclo `for`.
+#[derive(Copy, Clone)]
+enum Direction {
+    /// Paso positivo u omitido
+    Up,
+
+    /// Paso negativo conocido en tiempo de compilación
+    Down,
+
+    /// Paso que siempre es cero
+    Stalled,
+
+    /// Paso desconocido hasta tiempo de ejecución
+    Dynamic,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum Addressed {
     List,
+    IntList,
     Mat,
     Pod(Type),
     ListEntry(Local),
+    IntListEntry(Local),
     MatEntry(Local, Local),
     MatRow(Local),
     MatColumn(Local),
     ListSlice(Local, Local),
+    IntListSlice(Local, Local),
     MatSlice(Local, Local),
 }
 
@@ -144,19 +304,31 @@ impl Display for Addressed {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Addressed::List => write!(fmt, "type `{}`", Type::List),
+            Addressed::IntList => write!(fmt, "type `{}`", Type::IntList),
             Addressed::Mat => write!(fmt, "type `{}`", Type::Mat),
             Addressed::Pod(typ) => write!(fmt, "type `{}`", typ),
             Addressed::ListEntry(_) => fmt.write_str("list entries"),
+            Addressed::IntListEntry(_) => fmt.write_str("ilist entries"),
             Addressed::MatEntry(_, _) => fmt.write_str("matrix entries"),
             Addressed::MatRow(_) => fmt.write_str("matrix rows"),
             Addressed::MatColumn(_) => fmt.write_str("matrix columns"),
             Addressed::ListSlice(_, _) => fmt.write_str("list slices"),
+            Addressed::IntListSlice(_, _) => fmt.write_str("ilist slices"),
             Addressed::MatSlice(_, _) => fmt.write_str("matrix slices"),
         }
     }
 }
 
-#[derive(Copy, Clone)]
+/// Valor conocido en tiempo de compilación. De listas y matrices solo
+/// se conoce la forma.
+///
+/// Los valores estáticos de las variables se propagan en el orden del
+/// código. Cada rama de un condicional parte de lo que se conoce antes
+/// de este, y al terminar el condicional se conserva solo lo que
+/// coincide en todos los caminos que no retornan. Los ciclos, las
+/// esperas y las llamadas a procedimientos descartan todo lo conocido,
+/// ya que pueden modificar cualquier variable.
+#[derive(Copy, Clone, PartialEq)]
 enum Static {
     Int(i32),
     Bool(bool),
@@ -165,9 +337,26 @@ enum Static {
     Mat { rows: i32, columns: i32 },
 }
 
+impl Static {
+    /// Tipo y representación en IR de un valor escalar. Las listas y
+    /// matrices no se pueden materializar, ya que solo se conoce su forma.
+    fn scalar(self) -> Option<(Type, i32)> {
+        match self {
+            Static::Int(integer) => Some((Type::Int, integer)),
+            Static::Bool(boolean) => Some((Type::Bool, boolean as i32)),
+            Static::Float(float) => Some((Type::Float, float.to_bits() as i32)),
+            Static::List { .. } | Static::Mat { .. } => None,
+        }
+    }
+}
+
 trait Sink: Default {
     fn push(&mut self, instruction: Instruction);
 
+    /// Asocia una ubicación a las instrucciones que siguen y retorna
+    /// la que se asociaba hasta el momento.
+    fn locate(&mut self, location: Location) -> Location;
+
     fn alloc_local(&mut self) -> Local;
 
     fn free_local(&mut self, local: Local);
@@ -181,6 +370,10 @@ struct TypeCheck;
 impl Sink for TypeCheck {
     fn push(&mut self, _instruction: Instruction) {}
 
+    fn locate(&mut self, location: Location) -> Location {
+        location
+    }
+
     fn alloc_local(&mut self) -> Local {
         Local::default()
     }
@@ -194,26 +387,86 @@ impl Sink for TypeCheck {
 
 #[derive(Default)]
 struct Listing {
-    body: Vec<Instruction>,
+    body: Vec<Located<Instruction>>,
+    location: Option<Location>,
     free_locals: Vec<Local>,
     next_local: Local,
     next_label: Label,
+
+    /// Línea que contiene `builtin_line` en este punto, si se conoce.
+    marked_line: Option<i32>,
 }
 
 impl Listing {
-    fn for_parameters(parameters: u32) -> Self {
+    fn for_parameters(parameters: u32, location: Location) -> Self {
         Listing {
             body: Vec::new(),
+            location: Some(location),
             free_locals: Vec::new(),
             next_local: Local(parameters),
             next_label: Label::default(),
+            marked_line: None,
+        }
+    }
+
+    /// Informa a `libruntime` sobre la línea en ejecución, con tal
+    /// de que los errores en tiempo de ejecución puedan reportarla.
+    /// Solo se actualiza `builtin_line` si su valor podría diferir.
+    fn mark_line(&mut self) {
+        let location = self.location.as_ref().expect("listing has no location");
+        let line_number = location.start().line() as i32;
+
+        if self.marked_line != Some(line_number) {
+            let line = self.alloc_local();
+
+            self.push(Instruction::LoadConst(line_number, line));
+            self.push(Instruction::StoreGlobal(line, Global::from("builtin_line")));
+
+            self.free_local(line);
+            self.marked_line = Some(line_number);
         }
     }
 }
 
 impl Sink for Listing {
     fn push(&mut self, instruction: Instruction) {
-        self.body.push(instruction);
+        use Function::*;
+
+        // Solo las rutinas que pueden fallar consultan builtin_line
+        if let Instruction::Call {
+            target: External(name),
+            ..
+        } = &instruction
+        {
+            if builtins::can_fail(name) {
+                self.mark_line();
+            }
+        }
+
+        // Tras una etiqueta no se sabe por dónde llegó la ejecución, y
+        // cualquier procedimiento invocado sobreescribe la línea actual
+        let overwrites = match &instruction {
+            Instruction::SetLabel(_) => true,
+            Instruction::Call { target, .. } => match target {
+                External(name) => builtins::runs_tasks(name),
+                Generated(_) | Indirect(_) => true,
+            },
+
+            _ => false,
+        };
+
+        if overwrites {
+            self.marked_line = None;
+        }
+
+        let location = self.location.clone().expect("listing has no location");
+        self.body.push(Located::at(instruction, location));
+    }
+
+    fn locate(&mut self, location: Location) -> Location {
+        self.location
+            .replace(location)
+            .expect("listing has no location")
     }
 
     fn alloc_local(&mut self) -> Local {
@@ -239,6 +492,7 @@ impl Sink for Listing {
             local
         );
 
+        self.push(Instruction::Kill(local));
         self.free_locals.push(local);
     }
 
@@ -282,14 +536,41 @@ pub enum SemanticError {
     #[error("Definition for `{0}` is in conflict with a global variable")]
     NameClash(Identifier),
 
-    #[error("Redefinition of procedure `{0}` with the same parameter types")]
-    SignatureClash(Identifier),
+    #[error("Value of constant `{0}` is not known at compile time")]
+    NotConstant(Identifier),
+
+    #[error("Cannot assign to constant `{0}`")]
+    AssignToConst(Identifier),
+
+    #[error("Cannot replace `ref` parameter `{0}`")]
+    AssignToRef(Identifier),
+
+    #[error("Only lists and matrices can be passed by `ref`, found `{0}`")]
+    BadRefType(Type),
+
+    #[error("Redefinition of procedure `{0}` with the same parameter and return types")]
+    SignatureClash(Identifier, Location),
+
+    #[error("Redefinition of constant `{0}`")]
+    ConstantClash(Identifier, Location),
 
     #[error("Parameter `{0}` is bound more than once")]
     RepeatedParameter(Identifier),
 
     #[error("Procedure family `{0}` exists, but the overload `{0}({1})` is undefined")]
-    NoSuchOverload(Identifier, String),
+    NoSuchOverload(Identifier, String, Vec<String>),
+
+    #[error("Reference to procedure family `{0}` is ambiguous")]
+    AmbiguousProcRef(Identifier, Vec<String>),
+
+    #[error("Call to `{0}({1})` is ambiguous")]
+    AmbiguousCall(Identifier, String, Vec<String>),
+
+    #[error("Type `{0}` has no default value")]
+    NoDefault(Type),
+
+    #[error("`{0}` is a `{1}`, which cannot be called with `({2})`")]
+    BadProcArgs(Identifier, Type, String),
 
     #[error("Invalid operands for `{0}`: `{1}` and `{2}`")]
     InvalidOperands(parse::BinOp, Type, Type),
@@ -324,52 +605,388 @@ pub enum SemanticError {
     #[error("Expected `{0}` columns, found `{1}`")]
     ExpectedColumns(usize, usize),
 
+    #[error("Expected a list of length `{0}`, found `{1}`")]
+    ExpectedLength(usize, usize),
+
+    #[error("Calling `{0}` with these arguments fails: {2}")]
+    FailsInCall(Identifier, u32, Box<SemanticError>),
+
     #[error("Index always evaluates to `{0}`, outside of bounds `[0, {1}{2}`")]
     OutOfBounds(i32, i32, char),
+
+    #[error("`{0}` outside of a loop")]
+    OutsideLoop(lex::Keyword),
+
+    #[error("Procedure `{0}` does not return a value")]
+    NoReturnValue(Identifier),
+
+    #[error("This procedure does not return a value")]
+    UnexpectedReturnValue,
+
+    #[error("Expected a return value of type `{0}`")]
+    ExpectedReturnValue(Type),
+
+    #[error("Procedure `{0}` may finish without returning a value")]
+    MissingReturn(Identifier),
+
+    #[error("Expected a frame literal of 8 rows and a duration")]
+    ExpectedFrame,
+
+    #[error("Frame contents must be known at compile time")]
+    NonConstantFrame,
+
+    #[error("Frame rate must be a positive constant")]
+    BadFrameRate,
+
+    #[error("Frame value `{0}` is outside of bounds `[0, {1}]`")]
+    FrameOutOfRange(i32, i32),
+
+    #[error("Expression exceeds the nesting limit of {0} levels")]
+    TooComplex(u32),
+}
+
+#[non_exhaustive]
+#[derive(Error, Debug, Clone)]
+pub enum SemanticWarning {
+    #[error("Variable `{0}` is assigned but never read")]
+    UnusedVariable(Identifier),
+
+    #[error("Procedure `{0}({1})` is never called")]
+    UnusedProcedure(Identifier, String),
+
+    #[error("This statement is unreachable")]
+    UnreachableCode,
+
+    #[error("Loop step is always zero")]
+    ZeroStep,
+}
+
+impl Help for SemanticError {
+    fn help(&self) -> Option<String> {
+        use SemanticError::*;
+
+        match self {
+            ExpectedVar(id) => Some(format!("procedures are invoked with `call {}(...)`", id)),
+
+            NoSuchOverload(id, _, candidates) => {
+                let candidates = candidates
+                    .iter()
+                    .map(|types| format!("`{}({})`", id, types))
+                    .collect::<Vec<_>>();
+
+                Some(format!("available overloads are {}", candidates.join(", ")))
+            }
+
+            AmbiguousProcRef(id, candidates) => {
+                let candidates = candidates
+                    .iter()
+                    .map(|types| format!("`{}({})`", id, types))
+                    .collect::<Vec<_>>();
+
+                Some(format!(
+                    "`@` requires a single overload, found {}",
+                    candidates.join(", ")
+                ))
+            }
+
+            MissingReturn(_) => Some(String::from("add a `return` at the end of the procedure")),
+
+            BadFrameRate => Some(String::from(
+                "the rate is given in frames per second, as in `loop at 30 { ... }`",
+            )),
+
+            OutsideLoop(_) => Some(String::from(
+                "`break` and `continue` only apply to an enclosing `for` loop",
+            )),
+
+            AssignToRef(_) => Some(String::from(
+                "modify its contents through indices or methods instead",
+            )),
+
+            FailsInCall(id, line, _) => Some(format!(
+                "the failing operation is at line {}, inside `{}`",
+                line, id
+            )),
+
+            ExpectedFrame => Some(String::from(
+                "frames are written as `[row0, ..., row7, milliseconds]`, with column 0 as the highest bit of each row",
+            )),
+
+            NotConstant(_) => Some(String::from(
+                "constants must be `int`, `bool` or `float` expressions of literals and other constants",
+            )),
+
+            TooComplex(_) => Some(String::from(
+                "split the expression using intermediate variables",
+            )),
+
+            AmbiguousCall(id, types, returns) => Some(format!(
+                "overloads of `{}({})` return {}; use the result where its type is known",
+                id,
+                types,
+                returns.join(", ")
+            )),
+
+            SignatureClash(_, previous) | ConstantClash(_, previous) => {
+                Some(format!("previous definition is at {}", previous))
+            }
+
+            _ => None,
+        }
+    }
+}
+
+impl SemanticError {
+    /// Determina si el error depende de valores conocidos en tiempo de
+    /// compilación, en vez de solo de tipos y nombres.
+    fn is_static(&self) -> bool {
+        use SemanticError::*;
+
+        matches!(
+            self,
+            DivisionByZero
+                | ExpectedMatMode(_)
+                | ExpectedRows(_, _)
+                | ExpectedColumns(_, _)
+                | ExpectedLength(_, _)
+                | OutOfBounds(_, _, _)
+                | FailsInCall(_, _, _)
+        )
+    }
+}
+
+impl Help for SemanticWarning {
+    fn help(&self) -> Option<String> {
+        match self {
+            SemanticWarning::UnreachableCode => Some(String::from(
+                "the preceding statement always returns or never finishes",
+            )),
+
+            SemanticWarning::ZeroStep => Some(String::from(
+                "the iterator never advances; use a negative step to iterate in reverse",
+            )),
+
+            _ => None,
+        }
+    }
+}
+
+/// Lo que denota un identificador en un sitio donde se nombra.
+#[derive(Clone, Debug)]
+pub enum Symbol {
+    Var(Type),
+    Proc {
+        parameters: Vec<Type>,
+        returns: Option<Type>,
+    },
+}
+
+/// Un identificador junto a lo que denota.
+#[derive(Clone, Debug)]
+pub struct Reference {
+    pub name: Identifier,
+    pub symbol: Symbol,
+}
+
+/// Resultado del análisis de un único procedimiento.
+///
+/// Esto contiene todo lo necesario para omitir el análisis del mismo
+/// procedimiento en una compilación posterior, ver [`crate::incremental`].
+#[derive(Clone)]
+pub struct Analyzed {
+    pub function: ir::GeneratedFunction,
+
+    /// Símbolos de los procedimientos que este invoca.
+    pub calls: HashSet<Rc<String>>,
+
+    /// Advertencias originadas en el cuerpo del procedimiento.
+    pub warnings: Vec<Located<SemanticWarning>>,
+}
+
+/// Resultado de [`parse::Ast::resolve_reusing()`].
+pub struct Resolution {
+    pub globals: Vec<Global>,
+    pub procedures: Vec<Analyzed>,
+    pub warnings: Vec<Located<SemanticWarning>>,
+}
+
+/// Advertencias y referencias acumuladas durante el análisis semántico.
+///
+/// Todos los contextos de un mismo programa comparten una instancia,
+/// incluyendo los que solo verifican tipos.
+#[derive(Default)]
+struct Lints {
+    warnings: RefCell<Vec<Located<SemanticWarning>>>,
+
+    /// Símbolos de los procedimientos con al menos una llamada.
+    called: RefCell<HashSet<Rc<String>>>,
+
+    /// Sitios donde se nombra a variables y procedimientos.
+    references: RefCell<Vec<Located<Reference>>>,
+}
+
+impl Lints {
+    fn warn(&self, warning: SemanticWarning, location: Location) {
+        self.warnings
+            .borrow_mut()
+            .push(Located::at(warning, location));
+    }
+
+    fn refer(&self, id: &Located<Identifier>, symbol: Symbol) {
+        let reference = Reference {
+            name: id.as_ref().clone(),
+            symbol,
+        };
+
+        self.references
+            .borrow_mut()
+            .push(Located::at(reference, id.location().clone()));
+    }
+
+    /// Extrae las advertencias en orden de aparición.
+    fn into_warnings(self) -> Vec<Located<SemanticWarning>> {
+        let mut warnings = self.warnings.into_inner();
+        warnings.sort_by_key(|warning| {
+            let start = warning.location().start();
+            (start.line(), start.column())
+        });
+
+        warnings
+    }
 }
 
 impl parse::Ast {
     pub fn resolve(self) -> Semantic<ir::Program> {
-        let mut global_scope = self.scan_global_scope()?;
+        self.resolve_with_warnings().map(|(program, _)| program)
+    }
+
+    /// Como `resolve()`, pero además retorna advertencias no fatales.
+    pub fn resolve_with_warnings(self) -> Semantic<(ir::Program, Vec<Located<SemanticWarning>>)> {
+        let lints = Lints::default();
+        let program = self.analyze(&lints)?;
+
+        Ok((program, lints.into_warnings()))
+    }
+
+    /// Sitios donde se nombra a variables y procedimientos, junto a sus
+    /// tipos. Esto existe para herramientas de edición. Si el programa
+    /// tiene errores semánticos, solo se incluyen las referencias
+    /// encontradas antes del primero.
+    pub fn references(&self) -> Vec<Located<Reference>> {
+        let lints = Lints::default();
+        let _ = self.analyze(&lints);
+
+        lints.references.into_inner()
+    }
+
+    /// Como `resolve_with_warnings()`, pero omite el análisis de los
+    /// procedimientos para los cuales `reuse` provea un resultado previo.
+    /// Las declaraciones de todos los procedimientos y las globales se
+    /// analizan siempre. Se retorna el análisis de cada procedimiento en
+    /// el mismo orden que el AST.
+    pub fn resolve_reusing<F>(&self, mut reuse: F) -> Semantic<Resolution>
+    where
+        F: FnMut(&parse::Procedure) -> Option<Analyzed>,
+    {
+        let lints = Lints::default();
+        let (globals, procedures) = self.analyze_reusing(&lints, &mut reuse)?;
+
+        Ok(Resolution {
+            globals,
+            procedures,
+            warnings: lints.into_warnings(),
+        })
+    }
+
+    fn analyze(&self, lints: &Lints) -> Semantic<ir::Program> {
+        let (globals, procedures) = self.analyze_reusing(lints, &mut |_| None)?;
+        let code = procedures
+            .into_iter()
+            .map(|analyzed| analyzed.function)
+            .collect();
+
+        Ok(ir::Program { code, globals })
+    }
+
+    fn analyze_reusing(
+        &self,
+        lints: &Lints,
+        reuse: &mut dyn FnMut(&parse::Procedure) -> Option<Analyzed>,
+    ) -> Semantic<(Vec<Global>, Vec<Analyzed>)> {
+        let mut global_scope = self.scan_global_scope(lints)?;
         let mut global_statics = Some(std::mem::take(&mut global_scope.statics));
 
-        let code = self
+        let mut symbols = Vec::new();
+        let procedures = self
             .iter()
             .map(|procedure| {
-                let parameters = procedure.parameters().len() as u32;
-                let mut context = Context {
-                    scope: SymbolTable {
-                        outer: Some(&global_scope),
-                        ..Default::default()
-                    },
-
-                    sink: Listing::for_parameters(parameters),
-                    procedure: Some(procedure),
-                    is_toplevel: Default::default(),
+                let is_main = procedure.is_entrypoint();
+                let analyzed = match reuse(procedure) {
+                    Some(analyzed) => analyzed,
+                    None => {
+                        let statics = if is_main { global_statics.take() } else { None };
+
+                        // Se aíslan las llamadas y advertencias de este procedimiento
+                        let called = lints.called.take();
+                        let warnings = lints.warnings.borrow().len();
+
+                        let function =
+                            self.analyze_procedure(procedure, &global_scope, lints, statics)?;
+
+                        let calls = lints.called.replace(called);
+                        let warnings = lints.warnings.borrow_mut().split_off(warnings);
+
+                        Analyzed {
+                            function,
+                            calls,
+                            warnings,
+                        }
+                    }
                 };
 
-                let is_main = procedure.is_entrypoint();
-                if is_main {
-                    context.scope.statics = global_statics.take().unwrap_or_default();
-                }
+                lints
+                    .called
+                    .borrow_mut()
+                    .extend(analyzed.calls.iter().cloned());
 
-                let (mut sink, symbol) = context.scan_procedure(procedure)?;
-                if is_main {
-                    drop_globals(&mut sink, &global_scope);
+                lints
+                    .warnings
+                    .borrow_mut()
+                    .extend(analyzed.warnings.iter().cloned());
+
+                if !is_main {
+                    symbols.push((procedure, analyzed.function.name.clone()));
                 }
 
-                Ok(ir::GeneratedFunction {
-                    name: symbol,
-                    body: sink.body,
-                    parameters,
-                })
+                Ok(analyzed)
             })
             .collect::<Result<Vec<_>, _>>()?;
 
-        let globals = global_scope
+        let called = lints.called.take();
+        for (procedure, symbol) in symbols.into_iter() {
+            if !called.contains(&symbol) {
+                let name = procedure.name();
+                let types = match global_scope.symbols.get(name.as_ref()) {
+                    Some(Named::Procs { variants }) => variants
+                        .iter()
+                        .find(|(_, overload)| overload.symbol == symbol)
+                        .map(|((types, _), _)| signature(types)),
+
+                    _ => None,
+                };
+
+                let warning = SemanticWarning::UnusedProcedure(
+                    name.as_ref().clone(),
+                    types.unwrap_or_default(),
+                );
+                lints.warn(warning, name.location().clone());
+            }
+        }
+
+        let mut globals: Vec<Global> = global_scope
             .symbols
-            .into_iter()
-            .filter_map(|(_, named)| match named {
+            .into_values()
+            .filter_map(|named| match named {
                 Named::Var(Variable {
                     access: Access::Global(global),
                     ..
@@ -379,10 +996,51 @@ impl parse::Ast {
             })
             .collect();
 
-        Ok(ir::Program { code, globals })
+        // El orden de la tabla de símbolos no es determinista
+        globals.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+        Ok((globals, procedures))
+    }
+
+    fn analyze_procedure(
+        &self,
+        procedure: &parse::Procedure,
+        global_scope: &SymbolTable<'_>,
+        lints: &Lints,
+        statics: Option<HashMap<Identifier, Static>>,
+    ) -> Semantic<ir::GeneratedFunction> {
+        let parameters = procedure.parameters().len() as u32;
+        let context = Context {
+            scope: SymbolTable {
+                outer: Some(global_scope),
+                statics: statics.unwrap_or_default(),
+                ..Default::default()
+            },
+
+            sink: Listing::for_parameters(parameters, procedure.name().location().clone()),
+            lints,
+            procedure: Some(procedure),
+            ast: self,
+            depth: 0,
+            nesting: 0,
+            loops: Vec::new(),
+            is_toplevel: Default::default(),
+            returned: false,
+        };
+
+        let (mut sink, symbol) = context.scan_procedure(procedure)?;
+        if procedure.is_entrypoint() {
+            // Ya no quedan otras locales
+            drop_globals(&mut sink, global_scope, Local::default());
+        }
+
+        Ok(ir::GeneratedFunction {
+            name: symbol,
+            body: sink.body,
+            parameters,
+        })
     }
 
-    fn scan_global_scope(&self) -> Semantic<SymbolTable<'_>> {
+    fn scan_global_scope<'a>(&'a self, lints: &'a Lints) -> Semantic<SymbolTable<'a>> {
         let main = self
             .iter()
             .find(|proc| proc.is_entrypoint())
@@ -395,21 +1053,53 @@ impl parse::Ast {
             },
 
             sink: TypeCheck,
+            lints,
             procedure: None,
+            ast: self,
+            depth: MAX_SPECIALIZATION_DEPTH,
+            nesting: 0,
+            loops: Vec::new(),
             is_toplevel: Default::default(),
+            returned: false,
         };
 
+        // Las enumeraciones y luego las constantes se definen antes que
+        // todo lo demás, cada una puede depender de las que la preceden
+        for enumeration in self.enums() {
+            context.define_enum(enumeration)?;
+        }
+
+        for constant in self.constants() {
+            context.define_constant(constant)?;
+        }
+
+        // Los procedimientos cuyas firmas no dependen de globales se registran
+        // primero, de forma que las inicializaciones de globales puedan llamarlos
+        let mut deferred = Vec::new();
+        for procedure in self.iter() {
+            if context.define_procedure(procedure).is_err() {
+                deferred.push(procedure);
+            }
+        }
+
         let mut statements = main.statements().iter();
         while let Some(parse::Statement::Assignment { targets, values }) = statements.next() {
             for (target, value) in break_assignment(targets, values)? {
                 // Inicialmente solo se consideran definiciones y no asignaciones
                 let id = target.var().as_ref();
+                if let Some(Named::Procs { .. }) = context.scope.symbols.get(id) {
+                    return Err(Located::at(
+                        SemanticError::NameClash(id.clone()),
+                        target.var().location().clone(),
+                    ));
+                }
+
                 if context.scope.symbols.get(id).is_none() && target.indices().is_empty() {
                     // Esto solo verifica e infiere tipos, todavía no se genera IR
                     let (typ, _) = context.eval(value, Local::default())?;
 
                     let var = Variable {
-                        access: Access::Global(Global::from(mangle(id, &[]))),
+                        access: Access::Global(Global::from(mangle(id, &[], &[], None))),
                         typ,
                     };
 
@@ -421,35 +1111,8 @@ impl parse::Ast {
             }
         }
 
-        for procedure in self.iter() {
-            let types = context.parameter_types(procedure)?;
-
-            let (location, name) = procedure.name().clone().split();
-            let named = context
-                .scope
-                .symbols
-                .entry(name)
-                .or_insert_with(|| Named::Procs {
-                    variants: HashMap::new(),
-                });
-
-            let id = procedure.name().as_ref();
-            let symbol = Rc::new(mangle(id, &types));
-
-            match named {
-                Named::Var(_) => {
-                    return Err(Located::at(SemanticError::NameClash(id.clone()), location))
-                }
-
-                Named::Procs { variants } => {
-                    if variants.insert(types, symbol).is_some() {
-                        return Err(Located::at(
-                            SemanticError::SignatureClash(id.clone()),
-                            location,
-                        ));
-                    }
-                }
-            }
+        for procedure in deferred.into_iter() {
+            context.define_procedure(procedure)?;
         }
 
         let globals = context.scope;
@@ -458,17 +1121,203 @@ impl parse::Ast {
 }
 
 impl parse::Procedure {
-    fn is_entrypoint(&self) -> bool {
+    pub(crate) fn is_entrypoint(&self) -> bool {
         let id = self.name().as_ref();
-        unicase::eq_ascii(id.as_ref(), "main") && self.parameters().is_empty()
+        let is_main = unicase::eq_ascii(id.as_ref(), "main");
+
+        // El símbolo `user_main` no codifica parámetros ni retorno
+        is_main && self.parameters().is_empty() && self.returns().is_none()
     }
 }
 
 struct Context<'a, S: Sink> {
     scope: SymbolTable<'a>,
     sink: S,
+    lints: &'a Lints,
     procedure: Option<&'a parse::Procedure>,
+    ast: &'a parse::Ast,
+
+    /// Llamadas anidadas bajo reanálisis, ver `check_specialized()`.
+    depth: u32,
+
+    /// Subexpresiones bajo evaluación, ver [`parse::MAX_DEPTH`].
+    nesting: u32,
+
+    /// Ciclos que encierran al enunciado bajo análisis, del más externo
+    /// al más interno.
+    loops: Vec<JumpTargets>,
+
     is_toplevel: bool,
+    returned: bool,
+}
+
+/// Destinos de `break` y `continue` en un ciclo.
+#[derive(Copy, Clone)]
+struct JumpTargets {
+    /// Salida del ciclo.
+    end: Label,
+
+    /// Incremento del iterador, solo si el cuerpo contiene `continue`.
+    next: Option<Label>,
+
+    /// Profundidad del ámbito que contiene al ciclo, ver `scope_depth()`.
+    depth: usize,
+}
+
+impl Context<'_, TypeCheck> {
+    fn define_constant(&mut self, constant: &parse::Constant) -> Semantic<()> {
+        let (name, value) = (constant.name(), constant.value());
+
+        // Se verifican tipos primero para reportar errores más precisos
+        self.type_check(value)?;
+
+        let static_value = self
+            .const_eval(value)
+            .filter(|static_value| static_value.scalar().is_some())
+            .ok_or_else(|| {
+                Located::at(
+                    SemanticError::NotConstant(name.as_ref().clone()),
+                    value.location().clone(),
+                )
+            })?;
+
+        self.define_static(name, static_value)
+    }
+
+    fn define_enum(&mut self, enumeration: &parse::Enum) -> Semantic<()> {
+        let mut next = 0;
+        for variant in enumeration.variants() {
+            let name = variant.name();
+            let value = match variant.value() {
+                None => next,
+
+                Some(value) => {
+                    let typ = self.type_check(value)?;
+                    if typ != Type::Int {
+                        let error = SemanticError::ExpectedType(Type::Int, typ);
+                        return Err(Located::at(error, value.location().clone()));
+                    }
+
+                    match self.const_eval(value) {
+                        Some(Static::Int(value)) => value,
+                        _ => {
+                            let error = SemanticError::NotConstant(name.as_ref().clone());
+                            return Err(Located::at(error, value.location().clone()));
+                        }
+                    }
+                }
+            };
+
+            self.define_static(name, Static::Int(value))?;
+            next = value.wrapping_add(1);
+        }
+
+        Ok(())
+    }
+
+    /// Define una constante o variante de enumeración con valor escalar.
+    fn define_static(&mut self, name: &Located<Identifier>, static_value: Static) -> Semantic<()> {
+        // Solo otra constante puede estar definida en este punto
+        if self.scope.symbols.contains_key(name.as_ref()) {
+            let variants = self
+                .ast
+                .enums()
+                .iter()
+                .flat_map(parse::Enum::variants)
+                .map(parse::Variant::name);
+
+            let previous = variants
+                .chain(self.ast.constants().iter().map(parse::Constant::name))
+                .find(|previous| previous.as_ref() == name.as_ref())
+                .map_or_else(|| name.location(), |previous| previous.location());
+
+            return Err(Located::at(
+                SemanticError::ConstantClash(name.as_ref().clone(), previous.clone()),
+                name.location().clone(),
+            ));
+        }
+
+        let (typ, _) = static_value.scalar().expect("constants are scalars");
+        let var = Variable {
+            access: Access::Const(static_value),
+            typ,
+        };
+
+        self.lints.refer(name, Symbol::Var(typ));
+        self.scope
+            .symbols
+            .insert(name.as_ref().clone(), Named::Var(var));
+
+        Ok(())
+    }
+
+    fn define_procedure(&mut self, procedure: &parse::Procedure) -> Semantic<()> {
+        let types = self.parameter_types(procedure)?;
+        let returns = procedure
+            .returns()
+            .map(|typ| self.scan_type(typ))
+            .transpose()?;
+
+        let refs: Vec<_> = procedure
+            .parameters()
+            .iter()
+            .map(parse::Parameter::is_ref)
+            .collect();
+        for (parameter, typ) in procedure.parameters().iter().zip(types.iter()) {
+            if parameter.is_ref() && !matches!(typ, Type::List | Type::IntList | Type::Mat) {
+                return Err(Located::at(
+                    SemanticError::BadRefType(*typ),
+                    parameter.name().location().clone(),
+                ));
+            }
+        }
+
+        let (location, name) = procedure.name().clone().split();
+        let named = self
+            .scope
+            .symbols
+            .entry(name)
+            .or_insert_with(|| Named::Procs {
+                variants: HashMap::new(),
+            });
+
+        let id = procedure.name().as_ref();
+        let symbol = Rc::new(mangle(id, &types, &refs, returns));
+
+        match named {
+            Named::Var(_) => Err(Located::at(SemanticError::NameClash(id.clone()), location)),
+
+            Named::Procs { variants } => {
+                let overload = Overload {
+                    symbol,
+                    returns,
+                    refs,
+                    location: location.clone(),
+                };
+                let signature = Symbol::Proc {
+                    parameters: types.clone(),
+                    returns,
+                };
+
+                // Se conserva la primera definición, aún si la segunda
+                // se reintenta luego de definir las globales
+                self.lints.refer(procedure.name(), signature);
+                match variants.entry((types, returns)) {
+                    Entry::Occupied(previous) => {
+                        let previous = previous.get().location.clone();
+                        let error = SemanticError::SignatureClash(id.clone(), previous);
+
+                        Err(Located::at(error, location))
+                    }
+
+                    Entry::Vacant(slot) => {
+                        slot.insert(overload);
+                        Ok(())
+                    }
+                }
+            }
+        }
+    }
 }
 
 impl<S: Sink> Context<'_, S> {
@@ -480,10 +1329,15 @@ impl<S: Sink> Context<'_, S> {
         let parameters = procedure.parameters().iter();
         for (i, (parameter, typ)) in parameters.zip(types.iter().copied()).enumerate() {
             let name = parameter.name();
-            let var = Named::Var(Variable {
-                access: Access::Local(Local(i as u32)),
-                typ,
-            });
+            let local = Local(i as u32);
+            let access = if parameter.is_ref() {
+                Access::Ref(local)
+            } else {
+                Access::Local(local)
+            };
+
+            let var = Named::Var(Variable { access, typ });
+            self.lints.refer(name, Symbol::Var(typ));
 
             let id = name.as_ref().clone();
             if self.scope.symbols.insert(id, var).is_some() {
@@ -494,12 +1348,25 @@ impl<S: Sink> Context<'_, S> {
             }
         }
 
+        let returns = procedure
+            .returns()
+            .map(|typ| self.scan_type(typ))
+            .transpose()?;
+
         let symbol = match self.scope.lookup(procedure.name()) {
-            Ok(Named::Procs { variants }) => variants.get(&types).unwrap().clone(),
+            Ok(Named::Procs { variants }) => variants[&(types, returns)].symbol.clone(),
             _ => unreachable!(),
         };
 
         self.scan_statements(procedure.statements())?;
+        if procedure.returns().is_some() && !self.returned {
+            let name = procedure.name();
+            return Err(Located::at(
+                SemanticError::MissingReturn(name.as_ref().clone()),
+                name.location().clone(),
+            ));
+        }
+
         Ok((self.expire(), symbol))
     }
 
@@ -516,10 +1383,24 @@ impl<S: Sink> Context<'_, S> {
             _ => AssignmentMode::Normal,
         };
 
+        // Solo se advierte sobre el primer enunciado inalcanzable
+        let mut diverged = false;
+        let mut unreachable = false;
+
         for statement in statements.iter() {
             use parse::{ObjectKind::*, Statement::*, TimeUnit::*};
             use AssignmentMode::*;
 
+            if (self.returned || diverged) && !unreachable {
+                let location = statement.location().clone();
+                self.lints.warn(SemanticWarning::UnreachableCode, location);
+                unreachable = true;
+            }
+
+            // Las instrucciones de un enunciado compuesto que siguen a su
+            // cuerpo, como el incremento de un ciclo, le pertenecen a este
+            let outer = self.sink.locate(statement.location().clone());
+
             assignment_mode = match (assignment_mode, statement) {
                 (GlobalInit, Assignment { .. }) => GlobalInit,
                 (GlobalInit, _) => Main,
@@ -528,7 +1409,11 @@ impl<S: Sink> Context<'_, S> {
             };
 
             match statement {
-                If { condition, body } => self.scan_conditional(condition, body)?,
+                If {
+                    condition,
+                    body,
+                    otherwise,
+                } => self.scan_conditional(condition, body, otherwise.as_deref())?,
 
                 For {
                     variable,
@@ -536,9 +1421,11 @@ impl<S: Sink> Context<'_, S> {
                     step,
                     body,
                 } => {
-                    self.scan_loop(variable, iterable, step.as_ref(), body)?;
+                    diverged |= self.scan_loop(variable, iterable, step.as_ref(), body)?;
                 }
 
+                Loop { rate, body } => diverged |= self.scan_frame_loop(rate, body)?,
+
                 UserCall { procedure, args } => self.scan_user_call(procedure, args)?,
                 Debug { location, hint } => self.scan_debug(location, hint.as_ref())?,
 
@@ -572,6 +1459,7 @@ impl<S: Sink> Context<'_, S> {
                     let types = [Type::Int];
                     let location = count.location();
                     self.eval_fixed_call(builtin, location, &[count], &types, None)?;
+                    self.scope.statics.clear();
                 }
 
                 PrintLed { column, row, value } => {
@@ -601,7 +1489,77 @@ impl<S: Sink> Context<'_, S> {
                     self.eval_fixed_call(builtin, location, &args, &types, None)?;
                 }
 
+                PrintLedB { column, row, level } => {
+                    let args = [column, row, level];
+                    let types = [Type::Int, Type::Int, Type::Int];
+                    let location = level.location();
+                    let builtin = "builtin_printled_intensity";
+
+                    self.eval_fixed_call(builtin, location, &args, &types, None)?;
+                }
+
+                PrintText { text, speed } => {
+                    let args = [text, speed];
+                    let types = [Type::Str, Type::Int];
+                    let location = speed.location();
+
+                    self.eval_fixed_call("builtin_scroll_text", location, &args, &types, None)?;
+                    self.scope.statics.clear();
+                }
+
+                Show(location) => {
+                    self.eval_fixed_call("builtin_present", location, &[], &[], None)?;
+                }
+
+                DisplaySize { width, height } => {
+                    let args = [width, height];
+                    let types = [Type::Int, Type::Int];
+                    let location = height.location();
+                    let builtin = "builtin_set_display_size";
+
+                    self.eval_fixed_call(builtin, location, &args, &types, None)?;
+                }
+
+                Play {
+                    frames, looping, ..
+                } => {
+                    diverged |= self.scan_play(frames, looping.as_ref())?;
+                    self.scope.statics.clear();
+                }
+
+                Seed(seed) => {
+                    let location = seed.location();
+                    let types = [Type::Int];
+
+                    self.eval_fixed_call("builtin_seed", location, &[seed], &types, None)?;
+                }
+
+                UartWrite(value) => {
+                    let location = value.location();
+                    let types = [Type::Int];
+                    let builtin = "builtin_uart_write_int";
+
+                    self.eval_fixed_call(builtin, location, &[value], &types, None)?;
+                }
+
+                Store { slot, value } => {
+                    let args = [slot, value];
+                    let types = [Type::Int, Type::Int];
+                    let location = value.location();
+
+                    self.eval_fixed_call("builtin_store", location, &args, &types, None)?;
+                }
+
+                Schedule {
+                    delay,
+                    procedure,
+                    repeat,
+                } => self.scan_schedule(delay, procedure, *repeat)?,
+
                 GlobalLift(id) => self.global_lift(id)?,
+                Return { location, value } => self.scan_return(location, value.as_ref())?,
+                Break(location) => self.scan_jump(location, lex::Keyword::Break)?,
+                Continue(location) => self.scan_jump(location, lex::Keyword::Continue)?,
 
                 Assignment { targets, values } => {
                     for (target, value) in break_assignment(targets, values)? {
@@ -631,6 +1589,8 @@ impl<S: Sink> Context<'_, S> {
                     self.scan_method_call(target, method, args)?;
                 }
             }
+
+            self.sink.locate(outer);
         }
 
         Ok(())
@@ -640,6 +1600,7 @@ impl<S: Sink> Context<'_, S> {
         &mut self,
         condition: &Located<parse::Expr>,
         body: &[parse::Statement],
+        otherwise: Option<&[parse::Statement]>,
     ) -> Semantic<()> {
         match condition.as_ref() {
             parse::Expr::Binary {
@@ -648,7 +1609,7 @@ impl<S: Sink> Context<'_, S> {
                 rhs,
                 ..
             } if (self.type_check(lhs)?, self.type_check(rhs)?) == (Type::List, Type::Bool) => {
-                return self.scan_iterated_conditional(lhs, *op, rhs, body);
+                self.scan_iterated_conditional(lhs, *op, rhs, body, otherwise)
             }
 
             _ => {
@@ -661,32 +1622,84 @@ impl<S: Sink> Context<'_, S> {
                     Ok((Type::Bool, Ownership::Owned, ()))
                 })?;
 
-                self.subscope(|this| this.scan_statements(body))?;
-                self.sink.push(Instruction::SetLabel(if_false));
+                let (returned, body_statics) = self.branch(|this| {
+                    this.scan_statements(body)?;
+                    Ok(this.returned)
+                });
+
+                // Solo se garantiza un retorno si ambas ramas retornan
+                let returned = returned?;
+                let (otherwise_returned, otherwise_statics) =
+                    self.scan_otherwise(if_false, otherwise)?;
+                self.returned |= returned && otherwise_returned;
+
+                self.scope.statics = match (returned, otherwise_returned) {
+                    (true, _) => otherwise_statics,
+                    (false, true) => body_statics,
+                    (false, false) => meet(body_statics, &otherwise_statics),
+                };
 
                 Ok(())
             }
         }
     }
 
+    /// Cierra un condicional en la etiqueta `if_false`, incluyendo la
+    /// rama `else` si existe. El cuerpo verdadero salta por encima de ella.
+    /// Retorna `true` si la rama `else` existe y siempre retorna, junto a
+    /// los valores estáticos conocidos al final de esta, ver `branch()`.
+    fn scan_otherwise(
+        &mut self,
+        if_false: Label,
+        otherwise: Option<&[parse::Statement]>,
+    ) -> Semantic<(bool, HashMap<Identifier, Static>)> {
+        match otherwise {
+            None => {
+                self.sink.push(Instruction::SetLabel(if_false));
+                Ok((false, self.scope.statics.clone()))
+            }
+
+            Some(otherwise) => {
+                let end_label = self.sink.next_label();
+
+                self.sink.push(Instruction::Jump(end_label));
+                self.sink.push(Instruction::SetLabel(if_false));
+
+                let (returned, statics) = self.branch(|this| {
+                    this.scan_statements(otherwise)?;
+                    Ok(this.returned)
+                });
+
+                self.sink.push(Instruction::SetLabel(end_label));
+                Ok((returned?, statics))
+            }
+        }
+    }
+
     fn scan_iterated_conditional(
         &mut self,
         lhs: &Located<parse::Expr>,
         op: parse::BinOp,
         rhs: &Located<parse::Expr>,
         body: &[parse::Statement],
+        otherwise: Option<&[parse::Statement]>,
     ) -> Semantic<()> {
         let limit = self.sink.alloc_local();
         let iterator = self.sink.alloc_local();
         let iterable = self.sink.alloc_local();
 
         let (_, ownership) = self.eval(lhs, iterable)?;
+        if destructor(Type::List, ownership).is_some() {
+            // Un return dentro del ciclo debe liberar la lista
+            self.scope.temporaries.push((iterable, Type::List));
+        }
+
         self.sink.push(Instruction::LoadConst(0, iterator));
-        self.sink.push(Instruction::Call {
-            target: Function::External("builtin_len_list"),
-            arguments: vec![iterable],
-            output: Some(limit),
-        });
+        self.sink.push(builtins::call(
+            "builtin_len_list",
+            vec![iterable],
+            Some(limit),
+        ));
 
         let condition_label = self.sink.next_label();
         let end_label = self.sink.next_label();
@@ -706,11 +1719,11 @@ impl<S: Sink> Context<'_, S> {
 
         self.ephemeral(|this, rhs_local| {
             this.ephemeral(|this, entry_local| {
-                this.sink.push(Instruction::Call {
-                    target: Function::External("builtin_index_list"),
-                    arguments: vec![iterable, iterator],
-                    output: Some(entry_local),
-                });
+                this.sink.push(builtins::call(
+                    "builtin_index_list",
+                    vec![iterable, iterator],
+                    Some(entry_local),
+                ));
 
                 let op = match op {
                     parse::BinOp::Equal => ir::LogicOp::Equal,
@@ -735,7 +1748,10 @@ impl<S: Sink> Context<'_, S> {
         })?;
 
         self.subscope(|this| this.scan_statements(body))?;
-        self.sink.push(Instruction::SetLabel(false_label));
+        self.scan_otherwise(false_label, otherwise)?;
+        self.scope
+            .temporaries
+            .retain(|&(local, _)| local != iterable);
 
         self.ephemeral(|this, one| {
             let add = ir::BinOp::Arithmetic(ir::ArithmeticOp::Add);
@@ -757,17 +1773,48 @@ impl<S: Sink> Context<'_, S> {
         Ok(())
     }
 
+    /// Retorna `true` si se demuestra que el ciclo nunca termina, lo cual
+    /// ocurre si itera al menos una vez, el paso es siempre cero y el
+    /// cuerpo no contiene `return`.
+    ///
+    /// Con un paso positivo el iterador recorre `0, step, ...` mientras
+    /// sea menor al límite. Con un paso negativo el recorrido es inverso:
+    /// inicia en `limit - 1` y continúa mientras no sea negativo. Si el
+    /// paso no se conoce en tiempo de compilación, la dirección se
+    /// escoge en tiempo de ejecución.
     fn scan_loop(
         &mut self,
         variable: &Located<Identifier>,
         iterable: &Located<parse::Expr>,
         step: Option<&Located<parse::Expr>>,
         body: &[parse::Statement],
-    ) -> Semantic<()> {
+    ) -> Semantic<bool> {
+        let iterates = match self.const_eval(iterable) {
+            Some(Static::Int(limit)) | Some(Static::List { length: limit }) => limit > 0,
+            Some(Static::Mat { rows, .. }) => rows > 0,
+            _ => false,
+        };
+
+        let direction = match step.map(|step| (step, self.const_eval(step))) {
+            None => Direction::Up,
+            Some((_, Some(Static::Int(step)))) if step < 0 => Direction::Down,
+            Some((step, Some(Static::Int(0)))) => {
+                let location = step.location().clone();
+                self.lints.warn(SemanticWarning::ZeroStep, location);
+                Direction::Stalled
+            }
+
+            Some((_, Some(_))) => Direction::Up,
+            Some((_, None)) => Direction::Dynamic,
+        };
+
+        let stalls = matches!(direction, Direction::Stalled);
+        let diverges = iterates && stalls && !contains_return(body) && !contains_break(body);
+
         let limit = self.sink.alloc_local();
         match self.type_check(iterable)? {
             Type::Int => drop(self.eval(iterable, limit)?),
-            Type::List | Type::Mat => drop(self.eval_len(iterable, limit)?),
+            Type::List | Type::IntList | Type::Mat => drop(self.eval_len(iterable, limit)?),
 
             bad => {
                 return Err(Located::at(
@@ -791,39 +1838,207 @@ impl<S: Sink> Context<'_, S> {
             local
         };
 
+        // Solo con paso dinámico: si el recorrido es inverso
+        let descending = match direction {
+            Direction::Dynamic => {
+                let descending = self.sink.alloc_local();
+                self.ephemeral(|this, zero| {
+                    let op = ir::BinOp::Logic(ir::LogicOp::Less);
+                    this.sink.push(Instruction::LoadConst(0, zero));
+                    this.sink.push(Instruction::Move(step, descending));
+                    this.sink.push(Instruction::Binary(descending, op, zero));
+
+                    Ok((Type::Bool, Ownership::Owned, ()))
+                })?;
+
+                Some(descending)
+            }
+
+            _ => None,
+        };
+
+        match (direction, descending) {
+            (Direction::Down, _) => self.start_from_last(iterator, limit)?,
+
+            (Direction::Dynamic, Some(descending)) => {
+                let ascending_label = self.sink.next_label();
+                self.sink
+                    .push(Instruction::JumpIfFalse(descending, ascending_label));
+                self.start_from_last(iterator, limit)?;
+                self.sink.push(Instruction::SetLabel(ascending_label));
+            }
+
+            _ => (),
+        }
+
         let condition_label = self.sink.next_label();
         let end_label = self.sink.next_label();
 
         self.sink.push(Instruction::SetLabel(condition_label));
-        self.ephemeral(|this, is_less| {
-            let op = ir::BinOp::Logic(ir::LogicOp::Less);
-            this.sink.push(Instruction::Move(iterator, is_less));
-            this.sink.push(Instruction::Binary(is_less, op, limit));
-            this.sink.push(Instruction::JumpIfFalse(is_less, end_label));
+        match (direction, descending) {
+            (Direction::Down, _) => self.check_lower_bound(iterator, end_label)?,
 
-            Ok((Type::Bool, Ownership::Owned, ()))
-        })?;
+            (Direction::Dynamic, Some(descending)) => {
+                let ascending_label = self.sink.next_label();
+                let body_label = self.sink.next_label();
+
+                self.sink
+                    .push(Instruction::JumpIfFalse(descending, ascending_label));
+                self.check_lower_bound(iterator, end_label)?;
+                self.sink.push(Instruction::Jump(body_label));
+
+                self.sink.push(Instruction::SetLabel(ascending_label));
+                self.check_upper_bound(iterator, limit, end_label)?;
+                self.sink.push(Instruction::SetLabel(body_label));
+            }
+
+            _ => self.check_upper_bound(iterator, limit, end_label)?,
+        }
+
+        let next_label = contains_continue(body).then(|| self.sink.next_label());
+        self.loops.push(JumpTargets {
+            end: end_label,
+            next: next_label,
+            depth: self.scope_depth(),
+        });
 
-        self.subscope(|this| {
+        let scanned = self.subscope(|this| {
             let named = Named::Var(Variable {
                 access: Access::Local(iterator),
                 typ: Type::Int,
             });
 
             this.scope.symbols.insert(variable.as_ref().clone(), named);
+            this.lints.refer(variable, Symbol::Var(Type::Int));
             this.scan_statements(body)
-        })?;
+        });
+
+        self.loops.pop();
+        scanned?;
+
+        if let Some(next_label) = next_label {
+            self.sink.push(Instruction::SetLabel(next_label));
+        }
 
         let op = ir::BinOp::Arithmetic(ir::ArithmeticOp::Add);
         self.sink.push(Instruction::Binary(iterator, op, step));
         self.sink.push(Instruction::Jump(condition_label));
         self.sink.push(Instruction::SetLabel(end_label));
 
+        if let Some(descending) = descending {
+            self.sink.free_local(descending);
+        }
+
         self.sink.free_local(step);
         self.sink.free_local(limit);
         // iterator es liberado por expire()
 
-        Ok(())
+        Ok(diverges)
+    }
+
+    /// Repite un cuerpo indefinidamente a razón de `rate` cuadros por
+    /// segundo. Al final de cada iteración `builtin_frame_sync` espera
+    /// lo que reste del cuadro, descontando el tiempo que tomó el cuerpo.
+    /// Retorna `true` si el ciclo nunca termina.
+    fn scan_frame_loop(
+        &mut self,
+        rate: &Located<parse::Expr>,
+        body: &[parse::Statement],
+    ) -> Semantic<bool> {
+        let typ = self.type_check(rate)?;
+        if typ != Type::Int {
+            let error = SemanticError::ExpectedType(Type::Int, typ);
+            return Err(Located::at(error, rate.location().clone()));
+        }
+
+        let millis = match self.const_eval(rate) {
+            Some(Static::Int(rate)) if rate > 0 => 1000 / rate,
+            _ => {
+                return Err(Located::at(
+                    SemanticError::BadFrameRate,
+                    rate.location().clone(),
+                ))
+            }
+        };
+
+        let start_label = self.sink.next_label();
+        let end_label = self.sink.next_label();
+        let next_label = contains_continue(body).then(|| self.sink.next_label());
+
+        self.sink.push(Instruction::SetLabel(start_label));
+        self.loops.push(JumpTargets {
+            end: end_label,
+            next: next_label,
+            depth: self.scope_depth(),
+        });
+
+        let scanned = self.subscope(|this| this.scan_statements(body));
+        self.loops.pop();
+        scanned?;
+
+        if let Some(next_label) = next_label {
+            self.sink.push(Instruction::SetLabel(next_label));
+        }
+
+        self.ephemeral(|this, local| {
+            this.sink.push(Instruction::LoadConst(millis, local));
+            this.sink
+                .push(builtins::call("builtin_frame_sync", vec![local], None));
+
+            Ok((Type::Int, Ownership::Owned, ()))
+        })?;
+
+        self.sink.push(Instruction::Jump(start_label));
+        self.sink.push(Instruction::SetLabel(end_label));
+
+        Ok(!contains_return(body) && !contains_break(body))
+    }
+
+    /// Inicia un recorrido inverso en `limit - 1`.
+    fn start_from_last(&mut self, iterator: Local, limit: Local) -> Semantic<()> {
+        self.ephemeral(|this, one| {
+            let op = ir::BinOp::Arithmetic(ir::ArithmeticOp::Sub);
+            this.sink.push(Instruction::LoadConst(1, one));
+            this.sink.push(Instruction::Move(limit, iterator));
+            this.sink.push(Instruction::Binary(iterator, op, one));
+
+            Ok((Type::Int, Ownership::Owned, ()))
+        })
+    }
+
+    /// Salta a `end_label` a menos que `iterator < limit`.
+    fn check_upper_bound(
+        &mut self,
+        iterator: Local,
+        limit: Local,
+        end_label: Label,
+    ) -> Semantic<()> {
+        self.ephemeral(|this, is_less| {
+            let op = ir::BinOp::Logic(ir::LogicOp::Less);
+            this.sink.push(Instruction::Move(iterator, is_less));
+            this.sink.push(Instruction::Binary(is_less, op, limit));
+            this.sink.push(Instruction::JumpIfFalse(is_less, end_label));
+
+            Ok((Type::Bool, Ownership::Owned, ()))
+        })
+    }
+
+    /// Salta a `end_label` a menos que `iterator >= 0`.
+    fn check_lower_bound(&mut self, iterator: Local, end_label: Label) -> Semantic<()> {
+        self.ephemeral(|this, is_positive| {
+            this.ephemeral(|this, zero| {
+                let op = ir::BinOp::Logic(ir::LogicOp::GreaterOrEqual);
+                this.sink.push(Instruction::LoadConst(0, zero));
+                this.sink.push(Instruction::Move(iterator, is_positive));
+                this.sink.push(Instruction::Binary(is_positive, op, zero));
+                this.sink
+                    .push(Instruction::JumpIfFalse(is_positive, end_label));
+
+                Ok((Type::Bool, Ownership::Owned, ()))
+            })?;
+
+            Ok((Type::Bool, Ownership::Owned, ()))
+        })
     }
 
     fn scan_debug(
@@ -836,27 +2051,24 @@ impl<S: Sink> Context<'_, S> {
             this.sink.push(Instruction::LoadConst(line, line_local));
 
             match hint {
-                None => this.sink.push(Instruction::Call {
-                    target: Function::External("builtin_debug"),
-                    arguments: vec![line_local],
-                    output: None,
-                }),
+                None => this
+                    .sink
+                    .push(builtins::call("builtin_debug", vec![line_local], None)),
 
                 Some(hint) => this.ephemeral(|this, hint_local| {
                     let (typ, ownership) = this.eval(hint, hint_local)?;
                     let builtin = match typ {
                         Type::Bool => "builtin_debug_bool",
-                        Type::Int => "builtin_debug_int",
+                        Type::Int | Type::Proc(_) => "builtin_debug_int",
                         Type::List => "builtin_debug_list",
+                        Type::IntList => "builtin_debug_ilist",
                         Type::Mat => "builtin_debug_mat",
                         Type::Float => "builtin_debug_float",
+                        Type::Str => "builtin_debug_str",
                     };
 
-                    this.sink.push(Instruction::Call {
-                        target: Function::External(builtin),
-                        arguments: vec![line_local, hint_local],
-                        output: None,
-                    });
+                    this.sink
+                        .push(builtins::call(builtin, vec![line_local, hint_local], None));
 
                     Ok((typ, ownership, ()))
                 })?,
@@ -880,6 +2092,10 @@ impl<S: Sink> Context<'_, S> {
                 Neg,
                 F,
                 T,
+                Transpose,
+                Rot,
+                Mirror,
+                Shift,
             }
 
             use Addressed::*;
@@ -892,13 +2108,23 @@ impl<S: Sink> Context<'_, S> {
                 (NoCase::new("neg"), Neg),
                 (NoCase::new("f"), F),
                 (NoCase::new("t"), T),
+                (NoCase::new("transpose"), Transpose),
+                (NoCase::new("rot"), Rot),
+                (NoCase::new("mirror"), Mirror),
+                (NoCase::new("shift"), Shift),
             ];
 
             let mut arg_locals = vec![base];
             match addressed {
-                List | Mat | Pod(_) => (),
-                ListEntry(local) | MatRow(local) | MatColumn(local) => arg_locals.push(local),
-                MatEntry(from, to) | ListSlice(from, to) | MatSlice(from, to) => {
+                List | IntList | Mat | Pod(_) => (),
+                ListEntry(local) | IntListEntry(local) | MatRow(local) | MatColumn(local) => {
+                    arg_locals.push(local)
+                }
+
+                MatEntry(from, to)
+                | ListSlice(from, to)
+                | IntListSlice(from, to)
+                | MatSlice(from, to) => {
                     arg_locals.push(from);
                     arg_locals.push(to);
                 }
@@ -916,7 +2142,7 @@ impl<S: Sink> Context<'_, S> {
                         ListSlice(_, _) => concat!("builtin_", $op, "_slice_list"),
                         MatSlice(_, _) => concat!("builtin_", $op, "_slice_mat"),
 
-                        Pod(_) => {
+                        Pod(_) | IntList | IntListEntry(_) | IntListSlice(_, _) => {
                             return Err(Located::at(
                                 SemanticError::NoSuchMethod(name.as_ref().clone(), addressed),
                                 name.location().clone(),
@@ -989,6 +2215,17 @@ impl<S: Sink> Context<'_, S> {
                 .find(|(key, _)| key == name.as_ref())
                 .map(|(_, method)| *method);
 
+            // `wrap` es opcional en `shift()`
+            let mut no_wrap = false;
+            let mut optional_wrap = |types: &'static [Type]| {
+                if args.len() < types.len() {
+                    no_wrap = true;
+                    &types[..types.len() - 1]
+                } else {
+                    types
+                }
+            };
+
             let (builtin, arg_types) = match (method, addressed) {
                 (Some(Insert), List) => {
                     check_index_arg(this, 0, false, true)?;
@@ -1065,128 +2302,608 @@ impl<S: Sink> Context<'_, S> {
                     (Some(builtin), types)
                 }
 
-                (Some(Delete), List) => {
+                (Some(Insert), IntList) => {
+                    check_index_arg(this, 0, false, true)?;
+
+                    this.update_static(target.var(), |_, old| match old {
+                        Static::List { length } => Some(Static::List { length: length + 1 }),
+                        _ => None,
+                    });
+
+                    (Some("builtin_insert_ilist"), &[Type::Int, Type::Int][..])
+                }
+
+                (Some(Delete), List | IntList) => {
                     check_index_arg(this, 0, false, false)?;
                     this.update_static(target.var(), |_, old| match old {
                         Static::List { length } => Some(Static::List { length: length - 1 }),
                         _ => None,
                     });
 
-                    (Some("builtin_delete_list"), &[Type::Int][..])
+                    let builtin = match addressed {
+                        List => "builtin_delete_list",
+                        _ => "builtin_delete_ilist",
+                    };
+
+                    (Some(builtin), &[Type::Int][..])
+                }
+
+                (Some(Delete), Mat) => {
+                    if let Some(is_column) = check_mat_mode(this, 1)? {
+                        check_index_arg(this, 0, is_column, false)?;
+                    }
+
+                    this.update_static(target.var(), |_, old| match old {
+                        Static::Mat { rows, columns } if rows > 0 => Some(Static::Mat {
+                            rows: rows - 1,
+                            columns,
+                        }),
+
+                        _ => None,
+                    });
+
+                    (Some("builtin_delete_mat"), &[Type::Int, Type::Int][..])
+                }
+
+                (Some(Transpose), Mat) => {
+                    this.update_static(target.var(), |_, old| match old {
+                        Static::Mat { rows, columns } if rows > 0 => Some(Static::Mat {
+                            rows: columns,
+                            columns: rows,
+                        }),
+
+                        _ => None,
+                    });
+
+                    (Some("builtin_transpose_mat"), &[][..])
+                }
+
+                (Some(Rot), Mat) => {
+                    // Un número impar de cuartos de vuelta intercambia las dimensiones
+                    let turns = args.first().and_then(|turns| this.const_eval(turns));
+                    this.update_static(target.var(), |_, old| match (old, turns) {
+                        (Static::Mat { rows, columns }, Some(Static::Int(turns)))
+                            if rows > 0 && turns % 2 != 0 =>
+                        {
+                            Some(Static::Mat {
+                                rows: columns,
+                                columns: rows,
+                            })
+                        }
+
+                        (Static::Mat { .. }, Some(Static::Int(_))) => Some(old),
+                        _ => None,
+                    });
+
+                    (Some("builtin_rotate_mat"), &[Type::Int][..])
+                }
+
+                (Some(Mirror), Mat) => {
+                    check_mat_mode(this, 0)?;
+                    (Some("builtin_mirror_mat"), &[Type::Int][..])
+                }
+
+                (Some(Shift), List) => (
+                    Some("builtin_shift_list"),
+                    optional_wrap(&[Type::Int, Type::Bool]),
+                ),
+
+                (Some(Shift), Mat) => (
+                    Some("builtin_shift_mat"),
+                    optional_wrap(&[Type::Int, Type::Int, Type::Bool]),
+                ),
+
+                (Some(Neg), Pod(Type::Bool)) => {
+                    this.sink.push(Instruction::Not(base));
+                    (None, &[][..])
+                }
+
+                (Some(F), Pod(Type::Bool)) => {
+                    this.sink.push(Instruction::LoadConst(0, base));
+                    (None, &[][..])
+                }
+
+                (Some(T), Pod(Type::Bool)) => {
+                    this.sink.push(Instruction::LoadConst(1, base));
+                    (None, &[][..])
+                }
+
+                (Some(Neg), _) => mutator!("neg"),
+                (Some(F), _) => mutator!("f"),
+                (Some(T), _) => mutator!("t"),
+
+                _ => {
+                    return Err(Located::at(
+                        SemanticError::NoSuchMethod(name.as_ref().clone(), addressed),
+                        name.location().clone(),
+                    ));
+                }
+            };
+
+            let mut args = this.alloc_expecting(name.location(), args, arg_types)?;
+            if no_wrap {
+                let wrap = this.sink.alloc_local();
+                this.sink.push(Instruction::LoadConst(0, wrap));
+                args.push((wrap, Type::Bool, Ownership::Owned));
+            }
+
+            if let Some(builtin) = builtin {
+                arg_locals.extend(args.iter().map(|(local, _, _)| *local));
+
+                this.sink.push(builtins::call(builtin, arg_locals, None));
+            }
+
+            for (local, typ, ownership) in args.into_iter() {
+                this.drop(local, typ, ownership);
+            }
+
+            Ok((builtin.is_none(), ()))
+        })
+    }
+
+    fn scan_user_call(
+        &mut self,
+        target: &Located<Identifier>,
+        args: &[Located<parse::Expr>],
+    ) -> Semantic<()> {
+        self.ephemeral(|this, output| {
+            // El valor de retorno, si lo hay, se descarta
+            let returns = this.call_user(target, args, Some(output), Expect::Discard)?;
+            Ok((returns.unwrap_or(Type::Int), Ownership::Owned, ()))
+        })
+    }
+
+    fn call_user(
+        &mut self,
+        target: &Located<Identifier>,
+        args: &[Located<parse::Expr>],
+        output: Option<Local>,
+        expect: Expect,
+    ) -> Semantic<Option<Type>> {
+        // La sobrecarga determina cuáles argumentos se pasan por `ref`,
+        // por lo cual se resuelve antes de evaluarlos
+        let hints = self.parameter_hints(target, args.len());
+        let types = args
+            .iter()
+            .zip(hints)
+            .map(|(arg, hint)| self.type_check_as(arg, hint))
+            .collect::<Semantic<Vec<_>>>()?;
+
+        // Sin símbolo, la llamada es indirecta a través de una variable
+        let (overload, returns, refs) = match self.scope.lookup(target)? {
+            Named::Procs { variants } => {
+                let overload = resolve(target, variants, &types, expect)?.clone();
+                let (returns, refs) = (overload.returns, overload.refs.clone());
+
+                (Some(overload), returns, refs)
+            }
+
+            Named::Var(Variable {
+                typ: Type::Proc(proc_type),
+                ..
+            }) if proc_type.parameters == types => {
+                (None, proc_type.returns, proc_type.refs.clone())
+            }
+
+            Named::Var(Variable {
+                typ: typ @ Type::Proc(_),
+                ..
+            }) => {
+                return Err(Located::at(
+                    SemanticError::BadProcArgs(target.as_ref().clone(), *typ, signature(&types)),
+                    target.location().clone(),
+                ))
+            }
+
+            Named::Var(_) => {
+                return Err(Located::at(
+                    SemanticError::ExpectedProc(target.as_ref().clone()),
+                    target.location().clone(),
+                ))
+            }
+        };
+
+        let mut arg_locals = Vec::new();
+        let mut lent = Vec::new();
+
+        let params = types.iter().copied().zip(refs);
+        for (arg, (typ, by_ref)) in args.iter().zip(params) {
+            let local = self.sink.alloc_local();
+
+            // Una local de quien llama sobrevive a la llamada, no así
+            // una global que el procedimiento podría reemplazar
+            if by_ref && self.reads_local(arg) {
+                self.eval_as(arg, local, Some(typ))?;
+            } else {
+                self.eval_owned(arg, local, Some(typ))?;
+                if by_ref {
+                    lent.push((local, typ));
+                }
+            }
+
+            arg_locals.push(local);
+        }
+
+        let (function, indirect) = match overload {
+            Some(Overload { symbol, .. }) => {
+                self.lints.called.borrow_mut().insert(symbol.clone());
+                self.check_specialized(target, &types, returns, args)?;
+
+                let signature = Symbol::Proc {
+                    parameters: types,
+                    returns,
+                };
+
+                self.lints.refer(target, signature);
+                (Function::Generated(symbol), None)
+            }
+
+            None => {
+                let callee = self.sink.alloc_local();
+                self.read(target, callee)?;
+
+                (Function::Indirect(callee), Some(callee))
+            }
+        };
+
+        let output = output.filter(|_| returns.is_some());
+        self.sink.push(Instruction::Call {
+            target: function,
+            arguments: arg_locals.clone(),
+            output,
+        });
+
+        // El procedimiento no libera sus parámetros `ref`
+        for (local, typ) in lent.into_iter() {
+            self.drop(local, typ, Ownership::Owned);
+        }
+
+        for local in arg_locals.into_iter().chain(indirect) {
+            self.sink.free_local(local);
+        }
+
+        self.scope.statics.clear();
+        Ok(returns)
+    }
+
+    /// Tipos de los parámetros en los que coinciden todas las sobrecargas
+    /// de `target` con `count` parámetros. Un argumento que a su vez es
+    /// una llamada elige así su sobrecarga antes de conocer la de `target`.
+    fn parameter_hints(&self, target: &Located<Identifier>, count: usize) -> Vec<Option<Type>> {
+        let mut hints = vec![None; count];
+        match self.scope.try_lookup(target) {
+            Some(Named::Procs { variants }) => {
+                let mut candidates = variants
+                    .keys()
+                    .map(|(types, _)| types)
+                    .filter(|types| types.len() == count);
+
+                if let Some(first) = candidates.next() {
+                    hints = first.iter().copied().map(Some).collect();
+                }
+
+                for types in candidates {
+                    for (hint, typ) in hints.iter_mut().zip(types) {
+                        if *hint != Some(*typ) {
+                            *hint = None;
+                        }
+                    }
                 }
+            }
+
+            Some(Named::Var(Variable {
+                typ: Type::Proc(signature),
+                ..
+            })) if signature.parameters.len() == count => {
+                hints = signature.parameters.iter().copied().map(Some).collect();
+            }
+
+            _ => (),
+        }
+
+        hints
+    }
+
+    /// Solo se admiten familias de un único procedimiento, ya que no hay
+    /// argumentos a partir de los cuales elegir una sobrecarga.
+    fn eval_proc_ref(&mut self, target: &Located<Identifier>, into: Local) -> Semantic<Type> {
+        let (parameters, overload) = match self.scope.lookup(target)? {
+            Named::Procs { variants } if variants.len() == 1 => {
+                let ((parameters, _), overload) = variants.iter().next().unwrap();
+                (parameters.clone(), overload.clone())
+            }
+
+            Named::Procs { variants } => {
+                let mut candidates: Vec<_> =
+                    variants.keys().map(|(types, _)| signature(types)).collect();
+
+                candidates.sort();
+                candidates.dedup();
+
+                return Err(Located::at(
+                    SemanticError::AmbiguousProcRef(target.as_ref().clone(), candidates),
+                    target.location().clone(),
+                ));
+            }
+
+            Named::Var(_) => {
+                return Err(Located::at(
+                    SemanticError::ExpectedProc(target.as_ref().clone()),
+                    target.location().clone(),
+                ))
+            }
+        };
+
+        // Cualquier llamada indirecta posterior podría invocarlo
+        self.lints
+            .called
+            .borrow_mut()
+            .insert(overload.symbol.clone());
+
+        let signature = Symbol::Proc {
+            parameters: parameters.clone(),
+            returns: overload.returns,
+        };
+
+        self.lints.refer(target, signature);
+        self.sink
+            .push(Instruction::LoadProcedure(overload.symbol, into));
+
+        let signature = Signature {
+            parameters,
+            refs: overload.refs,
+            returns: overload.returns,
+        };
+
+        Ok(Type::Proc(signature.intern()))
+    }
+
+    /// Solo se admiten procedimientos sin parámetros. Estos se ejecutan
+    /// durante las esperas del programa, ver `runtime::schedule`, por lo
+    /// cual toda espera descarta los valores estáticos conocidos.
+    fn scan_schedule(
+        &mut self,
+        delay: &Located<parse::Expr>,
+        target: &Located<Identifier>,
+        repeat: bool,
+    ) -> Semantic<()> {
+        let overload = match self.scope.lookup(target)? {
+            Named::Procs { variants } => resolve(target, variants, &[], Expect::Discard)?.clone(),
+
+            Named::Var(_) => {
+                return Err(Located::at(
+                    SemanticError::ExpectedProc(target.as_ref().clone()),
+                    target.location().clone(),
+                ))
+            }
+        };
 
-                (Some(Delete), Mat) => {
-                    if let Some(is_column) = check_mat_mode(this, 1)? {
-                        check_index_arg(this, 0, is_column, false)?;
-                    }
+        self.lints
+            .called
+            .borrow_mut()
+            .insert(overload.symbol.clone());
 
-                    this.update_static(target.var(), |_, old| match old {
-                        Static::Mat { rows, columns } if rows > 0 => Some(Static::Mat {
-                            rows: rows - 1,
-                            columns,
-                        }),
+        let signature = Symbol::Proc {
+            parameters: Vec::new(),
+            returns: overload.returns,
+        };
 
-                        _ => None,
-                    });
+        self.lints.refer(target, signature);
 
-                    (Some("builtin_delete_mat"), &[Type::Int, Type::Int][..])
-                }
+        let (delay_local, procedure) = (self.sink.alloc_local(), self.sink.alloc_local());
+        self.eval_expecting(delay, delay_local, Type::Int)?;
+        self.sink
+            .push(Instruction::LoadProcedure(overload.symbol, procedure));
 
-                (Some(Neg), Pod(Type::Bool)) => {
-                    this.sink.push(Instruction::Not(base));
-                    (None, &[][..])
-                }
+        let builtin = if repeat {
+            "builtin_every"
+        } else {
+            "builtin_after"
+        };
 
-                (Some(F), Pod(Type::Bool)) => {
-                    this.sink.push(Instruction::LoadConst(0, base));
-                    (None, &[][..])
-                }
+        self.sink
+            .push(builtins::call(builtin, vec![delay_local, procedure], None));
 
-                (Some(T), Pod(Type::Bool)) => {
-                    this.sink.push(Instruction::LoadConst(1, base));
-                    (None, &[][..])
-                }
+        for &local in &[delay_local, procedure] {
+            self.sink.free_local(local);
+        }
 
-                (Some(Neg), _) => mutator!("neg"),
-                (Some(F), _) => mutator!("f"),
-                (Some(T), _) => mutator!("t"),
+        Ok(())
+    }
 
+    /// Los cuadros se codifican en el formato de `builtin_play_frames`
+    /// y se emiten como datos constantes, de forma que el tamaño del
+    /// código no depende de la cantidad de cuadros. Retorna `true` si
+    /// la reproducción se repite siempre y, por tanto, nunca finaliza.
+    fn scan_play(
+        &mut self,
+        frames: &[Located<parse::Expr>],
+        looping: Option<&Located<parse::Expr>>,
+    ) -> Semantic<bool> {
+        let mut data = Vec::with_capacity(frames.len() * PLAY_FRAME_SIZE);
+        for frame in frames {
+            let items = match frame.as_ref() {
+                parse::Expr::List(items) if items.len() == 9 => items,
                 _ => {
                     return Err(Located::at(
-                        SemanticError::NoSuchMethod(name.as_ref().clone(), addressed),
-                        name.location().clone(),
-                    ));
+                        SemanticError::ExpectedFrame,
+                        frame.location().clone(),
+                    ))
                 }
             };
 
-            let args = this.alloc_expecting(name.location(), args, arg_types)?;
-            if let Some(builtin) = builtin {
-                arg_locals.extend(args.iter().map(|(local, _, _)| *local));
-
-                this.sink.push(Instruction::Call {
-                    target: Function::External(builtin),
-                    arguments: arg_locals,
-                    output: None,
-                });
+            let (rows, duration) = items.split_at(8);
+            for row in rows {
+                data.push(self.frame_value(row, u8::MAX.into())? as u8);
             }
 
-            for (local, typ, ownership) in args.into_iter() {
-                this.drop(local, typ, ownership);
+            let duration = self.frame_value(&duration[0], u16::MAX.into())? as u16;
+            data.extend_from_slice(&duration.to_le_bytes());
+        }
+
+        let (frames_local, len, looping_local) = (
+            self.sink.alloc_local(),
+            self.sink.alloc_local(),
+            self.sink.alloc_local(),
+        );
+
+        self.sink
+            .push(Instruction::LoadData(data.into(), frames_local));
+        self.sink
+            .push(Instruction::LoadConst(frames.len() as i32, len));
+
+        match looping {
+            Some(looping) => {
+                self.eval_expecting(looping, looping_local, Type::Bool)?;
             }
 
-            Ok((builtin.is_none(), ()))
-        })
+            None => self.sink.push(Instruction::LoadConst(0, looping_local)),
+        }
+
+        self.sink.push(builtins::call(
+            "builtin_play_frames",
+            vec![frames_local, len, looping_local],
+            None,
+        ));
+
+        for &local in &[frames_local, len, looping_local] {
+            self.sink.free_local(local);
+        }
+
+        let forever = looping.and_then(|looping| self.const_eval(looping));
+        Ok(matches!(forever, Some(Static::Bool(true))))
     }
 
-    fn scan_user_call(
-        &mut self,
-        target: &Located<Identifier>,
-        args: &[Located<parse::Expr>],
-    ) -> Semantic<()> {
-        let mut types = Vec::new();
-        let mut arg_locals = Vec::new();
+    /// Evalúa un valor constante de un cuadro de `Play`.
+    fn frame_value(&self, expr: &Located<parse::Expr>, max: i32) -> Semantic<i32> {
+        let error = match self.const_eval(expr).and_then(Static::scalar) {
+            Some((Type::Int, value)) if (0..=max).contains(&value) => return Ok(value),
+            Some((Type::Int, value)) => SemanticError::FrameOutOfRange(value, max),
+            Some((typ, _)) => SemanticError::ExpectedType(Type::Int, typ),
+            None => SemanticError::NonConstantFrame,
+        };
 
-        for arg in args.iter() {
-            let local = self.sink.alloc_local();
-            let typ = self.eval_owned(arg, local)?;
+        Err(Located::at(error, expr.location().clone()))
+    }
 
-            arg_locals.push(local);
-            types.push(typ);
+    fn scan_return(
+        &mut self,
+        location: &Location,
+        value: Option<&Located<parse::Expr>>,
+    ) -> Semantic<()> {
+        let returns = match self.procedure {
+            Some(procedure) => procedure.returns().map(|typ| self.scan_type(typ)),
+            None => None,
         }
+        .transpose()?;
 
-        let target = match self.scope.lookup(target)? {
-            Named::Procs { variants } => variants.get(&types).ok_or_else(|| {
-                let types = types.iter().map(ToString::to_string).collect::<Vec<_>>();
-                let types = types.join(", ");
+        let value = match (returns, value) {
+            (None, None) => None,
+            (Some(typ), Some(value)) => Some((typ, value)),
 
-                Located::at(
-                    SemanticError::NoSuchOverload(target.as_ref().clone(), types),
-                    target.location().clone(),
-                )
-            })?,
+            (None, Some(value)) => {
+                return Err(Located::at(
+                    SemanticError::UnexpectedReturnValue,
+                    value.location().clone(),
+                ))
+            }
 
-            Named::Var(_) => {
+            (Some(typ), None) => {
                 return Err(Located::at(
-                    SemanticError::ExpectedProc(target.as_ref().clone()),
-                    target.location().clone(),
+                    SemanticError::ExpectedReturnValue(typ),
+                    location.clone(),
                 ))
             }
         };
 
-        self.sink.push(Instruction::Call {
-            target: Function::Generated(target.clone()),
-            arguments: arg_locals.clone(),
-            output: None,
-        });
+        self.returned = true;
+        self.ephemeral(|this, local| {
+            if let Some((expected, value)) = value {
+                let found = this.eval_owned(value, local, Some(expected))?;
+                if found != expected {
+                    return Err(Located::at(
+                        SemanticError::ExpectedType(expected, found),
+                        value.location().clone(),
+                    ));
+                }
+            }
 
-        for local in arg_locals.into_iter() {
-            self.sink.free_local(local);
+            this.drop_live_locals();
+            this.sink.push(Instruction::Return(value.map(|_| local)));
+
+            // El valor ya le pertenece al llamador, no se debe destruir
+            Ok((Type::Int, Ownership::Owned, ()))
+        })
+    }
+
+    /// Sale del ciclo más interno con `break`, o bien salta a su
+    /// siguiente iteración con `continue`.
+    fn scan_jump(&mut self, location: &Location, keyword: lex::Keyword) -> Semantic<()> {
+        let target = match self.loops.last() {
+            Some(target) => *target,
+            None => {
+                let error = SemanticError::OutsideLoop(keyword);
+                return Err(Located::at(error, location.clone()));
+            }
+        };
+
+        // Las locales del cuerpo no alcanzan su destrucción al final de este
+        let mut table = &self.scope;
+        for _ in target.depth..self.scope_depth() {
+            drop_scope(&mut self.sink, table);
+            table = table.outer.expect("loop scope is missing");
         }
 
-        self.scope.statics.clear();
+        let label = match keyword {
+            lex::Keyword::Break => target.end,
+            _ => target.next.expect("loop with `continue` lacks a label"),
+        };
+
+        // Al igual que tras un retorno, lo que sigue es inalcanzable
+        self.sink.push(Instruction::Jump(label));
+        self.returned = true;
+
         Ok(())
     }
 
+    /// Cantidad de ámbitos desde el global hasta el actual.
+    fn scope_depth(&self) -> usize {
+        let mut depth = 0;
+        let mut table = Some(&self.scope);
+
+        while let Some(current) = table {
+            depth += 1;
+            table = current.outer;
+        }
+
+        depth
+    }
+
+    /// Destruye todas las locales vivas de la función en preparación
+    /// para un retorno anticipado, incluyendo globales si es `main()`.
+    fn drop_live_locals(&mut self) {
+        let mut table = Some(&self.scope);
+        let mut outermost = &self.scope;
+
+        while let Some(current) = table {
+            drop_scope(&mut self.sink, current);
+
+            outermost = current;
+            table = current.outer;
+        }
+
+        let is_entrypoint = self
+            .procedure
+            .map(|proc| proc.is_entrypoint())
+            .unwrap_or(false);
+
+        if is_entrypoint {
+            let scratch = self.sink.alloc_local();
+            drop_globals(&mut self.sink, outermost, scratch);
+            self.sink.free_local(scratch);
+        }
+    }
+
     fn global_lift(&mut self, id: &Located<Identifier>) -> Semantic<()> {
         match self.scope.lookup(id)? {
             Named::Var(Variable {
@@ -1216,9 +2933,39 @@ impl<S: Sink> Context<'_, S> {
             return self.assign_indexed(target, value);
         }
 
-        let value_type = self.type_check(value)?;
         let target = target.var();
 
+        // El tipo de una variable existente elige entre sobrecargas que
+        // solo difieren en su tipo de retorno
+        let expected = match self.scope.try_lookup(target) {
+            Some(Named::Var(var)) => Some(var.typ),
+            _ => None,
+        };
+
+        let value_type = self.type_check_as(value, expected)?;
+
+        if let Some(Named::Var(Variable {
+            access: Access::Const(_),
+            ..
+        })) = self.scope.try_lookup(target)
+        {
+            return Err(Located::at(
+                SemanticError::AssignToConst(target.as_ref().clone()),
+                target.location().clone(),
+            ));
+        }
+
+        if let Some(Named::Var(Variable {
+            access: Access::Ref(_),
+            ..
+        })) = self.scope.try_lookup(target)
+        {
+            return Err(Located::at(
+                SemanticError::AssignToRef(target.as_ref().clone()),
+                target.location().clone(),
+            ));
+        }
+
         let should_override = |var: &Variable, scope: &SymbolTable<'_>| {
             matches!(&var.access, Access::Global(_)) && !scope.is_lifted(target.as_ref())
         };
@@ -1234,7 +2981,7 @@ impl<S: Sink> Context<'_, S> {
                 let global = global.clone();
 
                 return self.ephemeral(|this, local| {
-                    this.eval_owned(value, local)?;
+                    this.eval_owned(value, local, Some(value_type))?;
                     this.sink.push(Instruction::StoreGlobal(local, global));
 
                     // Esto evita un drop de la local
@@ -1247,14 +2994,21 @@ impl<S: Sink> Context<'_, S> {
 
             _ => {
                 let local = self.sink.alloc_local();
-                self.eval_owned(value, local)?;
+                self.eval_owned(value, local, Some(value_type))?;
 
                 let named = Named::Var(Variable {
                     access: Access::Local(local),
                     typ: value_type,
                 });
 
-                self.scope.symbols.insert(target.as_ref().clone(), named);
+                let id = target.as_ref().clone();
+                self.scope.symbols.insert(id.clone(), named);
+                self.lints.refer(target, Symbol::Var(value_type));
+                self.scope
+                    .unread
+                    .borrow_mut()
+                    .insert(id, target.location().clone());
+
                 if let Some(value) = self.const_eval(value) {
                     self.scope.statics.insert(target.as_ref().clone(), value);
                 }
@@ -1270,18 +3024,19 @@ impl<S: Sink> Context<'_, S> {
             ));
         }
 
+        self.lints.refer(target, Symbol::Var(var.typ));
         let must_drop = destructor(var.typ, Ownership::Owned).is_some();
         match (&var.access, must_drop) {
             (Access::Local(local), false) => {
                 let local = *local;
-                self.eval(value, local)?;
+                self.eval_as(value, local, Some(value_type))?;
             }
 
             (Access::Local(local), true) => {
                 let local = *local;
 
                 self.ephemeral(|this, value_local| {
-                    this.eval(value, value_local)?;
+                    this.eval_as(value, value_local, Some(value_type))?;
                     this.drop(local, value_type, Ownership::Owned);
                     this.sink.push(Instruction::Move(value_local, local));
 
@@ -1293,7 +3048,7 @@ impl<S: Sink> Context<'_, S> {
             (Access::Global(global), drop) => {
                 let global = global.clone();
                 self.ephemeral(|this, value_local| {
-                    this.eval(value, value_local)?;
+                    this.eval_as(value, value_local, Some(value_type))?;
                     if drop {
                         let global = global.clone();
 
@@ -1309,6 +3064,9 @@ impl<S: Sink> Context<'_, S> {
                     Ok((Type::Int, Ownership::Owned, ()))
                 })?;
             }
+
+            // Se rechazaron al inicio de esta función
+            (Access::Const(_) | Access::Ref(_), _) => unreachable!(),
         }
 
         let id = target.as_ref();
@@ -1327,33 +3085,87 @@ impl<S: Sink> Context<'_, S> {
         target: &Located<parse::Target>,
         value: &Located<parse::Expr>,
     ) -> Semantic<()> {
+        self.check_assigned_shape(target, value)?;
+
         self.address(target, |this, base, addressed| {
             use Addressed::*;
             let (builtin, typ, mut args) = match addressed {
                 ListEntry(index) => ("builtin_set_entry_list", Type::Bool, vec![base, index]),
+                IntListEntry(index) => ("builtin_set_entry_ilist", Type::Int, vec![base, index]),
                 MatEntry(row, col) => ("builtin_set_entry_mat", Type::Bool, vec![base, row, col]),
                 MatRow(row) => ("builtin_set_row_mat", Type::List, vec![base, row]),
                 MatColumn(col) => ("builtin_set_column_mat", Type::List, vec![base, col]),
                 ListSlice(from, to) => ("builtin_set_slice_list", Type::List, vec![base, from, to]),
                 MatSlice(from, to) => ("builtin_set_slice_mat", Type::Mat, vec![base, from, to]),
-                List | Mat | Pod(_) => unreachable!(),
+
+                IntListSlice(from, to) => {
+                    let args = vec![base, from, to];
+                    ("builtin_set_slice_ilist", Type::IntList, args)
+                }
+
+                List | IntList | Mat | Pod(_) => unreachable!(),
             };
 
             this.ephemeral(move |this, value_local| {
                 let ownership = this.eval_expecting(value, value_local, typ)?;
                 args.push(value_local);
 
-                this.sink.push(Instruction::Call {
-                    target: Function::External(builtin),
-                    arguments: args,
-                    output: None,
-                });
+                this.sink.push(builtins::call(builtin, args, None));
 
                 Ok((typ, ownership, (false, ())))
             })
         })
     }
 
+    /// Verifica que la forma de un valor asignado a una fila, columna o
+    /// slice coincida con la del destino, si ambas se conocen.
+    fn check_assigned_shape(
+        &self,
+        target: &Located<parse::Target>,
+        value: &Located<parse::Expr>,
+    ) -> Semantic<()> {
+        let mut expected = self.scope.statics.get(target.var().as_ref()).copied();
+        for index in target.indices().iter() {
+            expected = match expected {
+                Some(base) => self.check_index(base, index)?,
+                None => return Ok(()),
+            };
+        }
+
+        let last_index = target.indices().last().map(|index| index.as_ref());
+        let error = match (expected, self.const_eval(value), last_index) {
+            (Some(Static::List { length }), Some(Static::List { length: found }), index)
+                if length != found =>
+            {
+                let (length, found) = (length as usize, found as usize);
+                match index {
+                    Some(parse::Index::Single(_)) => SemanticError::ExpectedColumns(length, found),
+                    Some(parse::Index::Transposed(_)) => SemanticError::ExpectedRows(length, found),
+                    _ => SemanticError::ExpectedLength(length, found),
+                }
+            }
+
+            (
+                Some(Static::Mat { rows, columns }),
+                Some(Static::Mat {
+                    rows: found_rows,
+                    columns: found_columns,
+                }),
+                _,
+            ) if rows != found_rows || (rows > 0 && columns != found_columns) => {
+                if rows != found_rows {
+                    SemanticError::ExpectedRows(rows as usize, found_rows as usize)
+                } else {
+                    SemanticError::ExpectedColumns(columns as usize, found_columns as usize)
+                }
+            }
+
+            _ => return Ok(()),
+        };
+
+        Err(Located::at(error, value.location().clone()))
+    }
+
     fn address<F, R>(&mut self, target: &Located<parse::Target>, callback: F) -> Semantic<R>
     where
         F: FnOnce(&mut Self, Local, Addressed) -> Semantic<(bool, R)>,
@@ -1364,8 +3176,9 @@ impl<S: Sink> Context<'_, S> {
         use Addressed::*;
 
         let mut addressed = match base_type {
-            Type::Bool | Type::Int | Type::Float => Pod(base_type),
+            Type::Bool | Type::Int | Type::Float | Type::Str | Type::Proc(_) => Pod(base_type),
             Type::List => List,
+            Type::IntList => IntList,
             Type::Mat => Mat,
         };
 
@@ -1405,6 +3218,12 @@ impl<S: Sink> Context<'_, S> {
                     double(self, first, second, from, to, &ListSlice)?
                 }
 
+                (IntList, Index::Single(expr)) => single(self, first, expr, &IntListEntry)?,
+
+                (IntList, Index::Range(from, to)) => {
+                    double(self, first, second, from, to, &IntListSlice)?
+                }
+
                 (Mat, Index::Single(expr)) => single(self, first, expr, &MatRow)?,
 
                 (Mat, Index::Range(from, to)) => double(self, first, second, from, to, &MatSlice)?,
@@ -1443,8 +3262,15 @@ impl<S: Sink> Context<'_, S> {
             };
 
             let instruction = match &var.access {
-                Access::Local(local) => Instruction::Move(base, *local),
+                Access::Local(local) | Access::Ref(local) => Instruction::Move(base, *local),
                 Access::Global(global) => Instruction::StoreGlobal(base, global.clone()),
+
+                Access::Const(_) => {
+                    return Err(Located::at(
+                        SemanticError::AssignToConst(target.var().as_ref().clone()),
+                        target.var().location().clone(),
+                    ))
+                }
             };
 
             self.sink.push(instruction);
@@ -1455,7 +3281,7 @@ impl<S: Sink> Context<'_, S> {
         Ok(result)
     }
 
-    fn parameter_types(&mut self, procedure: &parse::Procedure) -> Semantic<Vec<Type>> {
+    fn parameter_types(&self, procedure: &parse::Procedure) -> Semantic<Vec<Type>> {
         procedure
             .parameters()
             .iter()
@@ -1468,13 +3294,21 @@ impl<S: Sink> Context<'_, S> {
             parse::Type::Int => Ok(Type::Int),
             parse::Type::Bool => Ok(Type::Bool),
             parse::Type::List => Ok(Type::List),
+            parse::Type::IntList => Ok(Type::IntList),
             parse::Type::Mat => Ok(Type::Mat),
             parse::Type::Float => Ok(Type::Float),
+            parse::Type::Str => Ok(Type::Str),
             parse::Type::Of(expr) => self.type_check(expr),
         }
     }
 
     fn type_check(&self, expr: &Located<parse::Expr>) -> Semantic<Type> {
+        self.type_check_as(expr, None)
+    }
+
+    /// Como [`Context::type_check()`], pero eligiendo las sobrecargas que
+    /// retornen `expected` donde el tipo de retorno las distinga.
+    fn type_check_as(&self, expr: &Located<parse::Expr>, expected: Option<Type>) -> Semantic<Type> {
         let mut context = Context {
             scope: SymbolTable {
                 outer: Some(&self.scope),
@@ -1482,11 +3316,17 @@ impl<S: Sink> Context<'_, S> {
             },
 
             sink: TypeCheck,
+            lints: self.lints,
             procedure: None,
+            ast: self.ast,
+            depth: MAX_SPECIALIZATION_DEPTH,
+            nesting: self.nesting,
+            loops: Vec::new(),
             is_toplevel: Default::default(),
+            returned: false,
         };
 
-        let (typ, _) = context.eval(expr, Local::default())?;
+        let (typ, _) = context.eval_as(expr, Local::default(), expected)?;
         Ok(typ)
     }
 
@@ -1501,11 +3341,7 @@ impl<S: Sink> Context<'_, S> {
         let allocs = self.alloc_expecting(at, args, types)?;
         let arg_locals = allocs.iter().map(|(local, _, _)| *local).collect();
 
-        self.sink.push(Instruction::Call {
-            target: Function::External(builtin),
-            arguments: arg_locals,
-            output,
-        });
+        self.sink.push(builtins::call(builtin, arg_locals, output));
 
         for (local, typ, ownership) in allocs.into_iter() {
             self.drop(local, typ, ownership);
@@ -1542,23 +3378,27 @@ impl<S: Sink> Context<'_, S> {
         Ok(allocs)
     }
 
-    fn eval_owned(&mut self, expr: &Located<parse::Expr>, into: Local) -> Semantic<Type> {
+    fn eval_owned(
+        &mut self,
+        expr: &Located<parse::Expr>,
+        into: Local,
+        expected: Option<Type>,
+    ) -> Semantic<Type> {
         use Ownership::{Borrowed, Owned};
 
-        let (typ, ownership) = self.eval(expr, into)?;
+        let (typ, ownership) = self.eval_as(expr, into, expected)?;
         let cloner = match (typ, ownership) {
             (_, Owned) => None,
-            (Type::Int | Type::Bool | Type::Float, _) => None,
+            (Type::Int | Type::Bool | Type::Float | Type::Proc(_), _) => None,
             (Type::List, Borrowed) => Some("builtin_ref_list"),
+            (Type::IntList, Borrowed) => Some("builtin_ref_ilist"),
             (Type::Mat, Borrowed) => Some("builtin_ref_mat"),
+            (Type::Str, Borrowed) => Some("builtin_ref_str"),
         };
 
         if let Some(cloner) = cloner {
-            self.sink.push(Instruction::Call {
-                target: Function::External(cloner),
-                arguments: vec![into],
-                output: Some(into),
-            });
+            self.sink
+                .push(builtins::call(cloner, vec![into], Some(into)));
         }
 
         Ok(typ)
@@ -1570,7 +3410,7 @@ impl<S: Sink> Context<'_, S> {
         into: Local,
         typ: Type,
     ) -> Semantic<Ownership> {
-        let (actual, ownership) = self.eval(expr, into)?;
+        let (actual, ownership) = self.eval_as(expr, into, Some(typ))?;
         if actual == typ {
             Ok(ownership)
         } else {
@@ -1582,6 +3422,40 @@ impl<S: Sink> Context<'_, S> {
     }
 
     fn eval(&mut self, expr: &Located<parse::Expr>, into: Local) -> Semantic<(Type, Ownership)> {
+        self.eval_as(expr, into, None)
+    }
+
+    /// Evalúa una expresión de la cual se espera un valor de tipo
+    /// `expected`, si se conoce. Esto no verifica el tipo resultante, ver
+    /// [`Context::eval_expecting()`], sino que solo elige entre sobrecargas
+    /// que difieren en su tipo de retorno.
+    fn eval_as(
+        &mut self,
+        expr: &Located<parse::Expr>,
+        into: Local,
+        expected: Option<Type>,
+    ) -> Semantic<(Type, Ownership)> {
+        let max_depth = self.ast.max_depth();
+        if self.nesting >= max_depth {
+            return Err(Located::at(
+                SemanticError::TooComplex(max_depth),
+                expr.location().clone(),
+            ));
+        }
+
+        self.nesting += 1;
+        let result = self.eval_unbounded(expr, into, expected);
+        self.nesting -= 1;
+
+        result
+    }
+
+    fn eval_unbounded(
+        &mut self,
+        expr: &Located<parse::Expr>,
+        into: Local,
+        expected: Option<Type>,
+    ) -> Semantic<(Type, Ownership)> {
         use parse::Expr::*;
         use Ownership::{Borrowed, Owned};
 
@@ -1601,6 +3475,18 @@ impl<S: Sink> Context<'_, S> {
                 Ok((Type::Int, Owned))
             }
 
+            // Mismo formato que `libruntime` espera en sus builtins de float
+            Float(constant) => {
+                let bits = constant.to_bits() as i32;
+                self.sink.push(Instruction::LoadConst(bits, into));
+                Ok((Type::Float, Owned))
+            }
+
+            Str(literal) => {
+                self.eval_str(literal, into);
+                Ok((Type::Str, Owned))
+            }
+
             Read(id) => {
                 let typ = self.read(id, into)?;
                 Ok((typ, Borrowed))
@@ -1621,6 +3507,55 @@ impl<S: Sink> Context<'_, S> {
                 Ok((Type::Int, Owned))
             }
 
+            Call(procedure, args) => {
+                let expect = expected.map_or(Expect::Value, Expect::Type);
+                match self.call_user(procedure, args, Some(into), expect)? {
+                    Some(typ) => Ok((typ, Owned)),
+                    None => Err(Located::at(
+                        SemanticError::NoReturnValue(procedure.as_ref().clone()),
+                        procedure.location().clone(),
+                    )),
+                }
+            }
+
+            ProcRef(procedure) => {
+                let typ = self.eval_proc_ref(procedure, into)?;
+                Ok((typ, Owned))
+            }
+
+            UartRead => {
+                let location = expr.location();
+                self.eval_fixed_call("builtin_uart_read_int", location, &[], &[], Some(into))?;
+                Ok((Type::Int, Owned))
+            }
+
+            HeapUsed | HeapFree | Millis => {
+                let builtin = match expr.as_ref() {
+                    HeapUsed => "builtin_heap_used",
+                    HeapFree => "builtin_heap_free",
+                    _ => "builtin_millis",
+                };
+
+                self.eval_fixed_call(builtin, expr.location(), &[], &[], Some(into))?;
+                Ok((Type::Int, Owned))
+            }
+
+            Load(slot) => {
+                let location = slot.location();
+                let types = [Type::Int];
+
+                self.eval_fixed_call("builtin_load", location, &[&**slot], &types, Some(into))?;
+                Ok((Type::Int, Owned))
+            }
+
+            Random(max) => {
+                let location = max.location();
+                let types = [Type::Int];
+
+                self.eval_fixed_call("builtin_random", location, &[&**max], &types, Some(into))?;
+                Ok((Type::Int, Owned))
+            }
+
             Range(length, value) => {
                 let builtin = "builtin_range";
                 let args = [&**length, &**value];
@@ -1653,6 +3588,13 @@ impl<S: Sink> Context<'_, S> {
                 Ok((Type::Int, Owned))
             }
 
+            Not(expr) => {
+                self.eval_expecting(expr, into, Type::Bool)?;
+                self.sink.push(Instruction::Not(into));
+
+                Ok((Type::Bool, Owned))
+            }
+
             Binary { lhs, op, rhs, .. } => {
                 let typ = self.eval_binary(expr.location(), lhs, *op, rhs, into)?;
                 Ok((typ, Owned))
@@ -1660,6 +3602,21 @@ impl<S: Sink> Context<'_, S> {
         }
     }
 
+    fn eval_str(&mut self, literal: &lex::StrLiteral, into: Local) {
+        self.sink
+            .push(builtins::call("builtin_new_str", Vec::new(), Some(into)));
+
+        // Al igual que con listas, el literal se construye carácter por carácter
+        let item = self.sink.alloc_local();
+        for c in literal.as_ref().chars() {
+            self.sink.push(Instruction::LoadConst(c as i32, item));
+            self.sink
+                .push(builtins::call("builtin_push_str", vec![into, item], None));
+        }
+
+        self.sink.free_local(item);
+    }
+
     fn eval_new(&mut self, typ: &Located<parse::Type>, into: Local) -> Semantic<Type> {
         let at = typ.location();
         let typ = self.scan_type(typ)?;
@@ -1667,15 +3624,18 @@ impl<S: Sink> Context<'_, S> {
         match typ {
             Type::Int | Type::Bool => self.sink.push(Instruction::LoadConst(0, into)),
             Type::List => self.eval_fixed_call("builtin_new_list", at, &[], &[], Some(into))?,
+            Type::IntList => self.eval_fixed_call("builtin_new_ilist", at, &[], &[], Some(into))?,
             Type::Mat => self.eval_fixed_call("builtin_new_mat", at, &[], &[], Some(into))?,
+            Type::Str => self.eval_fixed_call("builtin_new_str", at, &[], &[], Some(into))?,
+            Type::Proc(_) => return Err(Located::at(SemanticError::NoDefault(typ), at.clone())),
 
             Type::Float => {
                 self.sink.push(Instruction::LoadConst(0, into));
-                self.sink.push(Instruction::Call {
-                    target: Function::External("builtin_cast_int_float"),
-                    arguments: vec![into],
-                    output: Some(into),
-                });
+                self.sink.push(builtins::call(
+                    "builtin_cast_int_float",
+                    vec![into],
+                    Some(into),
+                ));
             }
         }
 
@@ -1718,15 +3678,41 @@ impl<S: Sink> Context<'_, S> {
             }
         };
 
-        if let Some(caster) = caster {
-            self.sink.push(Instruction::Call {
-                target: Function::External(caster),
-                arguments: vec![into],
-                output: Some(into),
-            });
+        if let Some(caster) = caster {
+            self.sink
+                .push(builtins::call(caster, vec![into], Some(into)));
+        }
+
+        Ok((to_type, ownership))
+    }
+
+    /// Evalúa `&&` o `||` en cortocircuito.
+    ///
+    /// Si el operando izquierdo determina el resultado, este ya se
+    /// encuentra en `into` y el operando derecho no se evalúa.
+    fn eval_logical(
+        &mut self,
+        lhs: &Located<parse::Expr>,
+        op: parse::BinOp,
+        rhs: &Located<parse::Expr>,
+        into: Local,
+    ) -> Semantic<()> {
+        let end_label = self.sink.next_label();
+        self.eval_expecting(lhs, into, Type::Bool)?;
+
+        if let parse::BinOp::Or = op {
+            let rhs_label = self.sink.next_label();
+            self.sink.push(Instruction::JumpIfFalse(into, rhs_label));
+            self.sink.push(Instruction::Jump(end_label));
+            self.sink.push(Instruction::SetLabel(rhs_label));
+        } else {
+            self.sink.push(Instruction::JumpIfFalse(into, end_label));
         }
 
-        Ok((to_type, ownership))
+        self.eval_expecting(rhs, into, Type::Bool)?;
+        self.sink.push(Instruction::SetLabel(end_label));
+
+        Ok(())
     }
 
     fn eval_binary(
@@ -1737,6 +3723,11 @@ impl<S: Sink> Context<'_, S> {
         rhs: &Located<parse::Expr>,
         into: Local,
     ) -> Semantic<Type> {
+        if let parse::BinOp::And | parse::BinOp::Or = op {
+            self.eval_logical(lhs, op, rhs, into)?;
+            return Ok(Type::Bool);
+        }
+
         self.ephemeral(|this, rhs_local| {
             let (typ, lhs_ownership) = this.eval(lhs, into)?;
             let rhs_ownership = match this.eval(rhs, rhs_local)? {
@@ -1783,6 +3774,12 @@ impl<S: Sink> Context<'_, S> {
                     IrOp::Arithmetic(ArithmeticOp::Div)
                 }
 
+                (ParseOp::BitAnd, Int) => IrOp::Arithmetic(ArithmeticOp::BitAnd),
+                (ParseOp::BitOr, Int) => IrOp::Arithmetic(ArithmeticOp::BitOr),
+                (ParseOp::BitXor, Int) => IrOp::Arithmetic(ArithmeticOp::BitXor),
+                (ParseOp::ShiftLeft, Int) => IrOp::Arithmetic(ArithmeticOp::ShiftLeft),
+                (ParseOp::ShiftRight, Int) => IrOp::Arithmetic(ArithmeticOp::ShiftRight),
+
                 (ParseOp::Div, Int) => {
                     this.do_builtin_assign(into, "builtin_div_int", rhs_local);
                     return Ok((Type::Int, Ownership::Owned, Type::Float));
@@ -1800,20 +3797,84 @@ impl<S: Sink> Context<'_, S> {
                 (ParseOp::Less, Int | Bool) => IrOp::Logic(LogicOp::Less),
                 (ParseOp::LessOrEqual, Int | Bool) => IrOp::Logic(LogicOp::LessOrEqual),
 
-                (ParseOp::Equal | ParseOp::NotEqual, List | Mat) => {
-                    let comparator = if typ == List {
-                        "builtin_eq_list"
-                    } else {
-                        "builtin_eq_mat"
+                (ParseOp::Add, Str) => {
+                    this.ephemeral(|this, lhs_local| {
+                        this.sink.push(Instruction::Move(into, lhs_local));
+                        this.sink.push(builtins::call(
+                            "builtin_concat_str",
+                            vec![lhs_local, rhs_local],
+                            Some(into),
+                        ));
+
+                        Ok((typ, lhs_ownership, ()))
+                    })?;
+
+                    return Ok((typ, rhs_ownership, Str));
+                }
+
+                (ParseOp::BitAnd | ParseOp::BitOr | ParseOp::BitXor, Mat) => {
+                    if let (
+                        Some(Static::Mat { rows, columns }),
+                        Some(Static::Mat {
+                            rows: rhs_rows,
+                            columns: rhs_columns,
+                        }),
+                    ) = (this.const_eval(lhs), this.const_eval(rhs))
+                    {
+                        let error = if rows != rhs_rows {
+                            Some(SemanticError::ExpectedRows(
+                                rows as usize,
+                                rhs_rows as usize,
+                            ))
+                        } else if rows > 0 && columns != rhs_columns {
+                            Some(SemanticError::ExpectedColumns(
+                                columns as usize,
+                                rhs_columns as usize,
+                            ))
+                        } else {
+                            None
+                        };
+
+                        if let Some(error) = error {
+                            return Err(Located::at(error, rhs.location().clone()));
+                        }
+                    }
+
+                    let builtin = match op {
+                        ParseOp::BitAnd => "builtin_and_mat",
+                        ParseOp::BitOr => "builtin_or_mat",
+                        _ => "builtin_xor_mat",
                     };
 
                     this.ephemeral(|this, lhs_local| {
                         this.sink.push(Instruction::Move(into, lhs_local));
-                        this.sink.push(Instruction::Call {
-                            target: Function::External(comparator),
-                            arguments: vec![lhs_local, rhs_local],
-                            output: Some(into),
-                        });
+                        this.sink.push(builtins::call(
+                            builtin,
+                            vec![lhs_local, rhs_local],
+                            Some(into),
+                        ));
+
+                        Ok((typ, lhs_ownership, ()))
+                    })?;
+
+                    return Ok((typ, rhs_ownership, Mat));
+                }
+
+                (ParseOp::Equal | ParseOp::NotEqual, List | IntList | Mat | Str) => {
+                    let comparator = match typ {
+                        List => "builtin_eq_list",
+                        IntList => "builtin_eq_ilist",
+                        Mat => "builtin_eq_mat",
+                        _ => "builtin_eq_str",
+                    };
+
+                    this.ephemeral(|this, lhs_local| {
+                        this.sink.push(Instruction::Move(into, lhs_local));
+                        this.sink.push(builtins::call(
+                            comparator,
+                            vec![lhs_local, rhs_local],
+                            Some(into),
+                        ));
 
                         Ok((typ, lhs_ownership, ()))
                     })?;
@@ -1886,11 +3947,11 @@ impl<S: Sink> Context<'_, S> {
             }
 
             Logic(op) => self.ephemeral(|this, zero| {
-                this.sink.push(Instruction::Call {
-                    target: Function::External("builtin_cmp_float"),
-                    arguments: vec![lhs, rhs],
-                    output: Some(lhs),
-                });
+                this.sink.push(builtins::call(
+                    "builtin_cmp_float",
+                    vec![lhs, rhs],
+                    Some(lhs),
+                ));
 
                 this.sink.push(Instruction::LoadConst(0, zero));
                 this.sink
@@ -1902,19 +3963,17 @@ impl<S: Sink> Context<'_, S> {
     }
 
     fn do_builtin_assign(&mut self, lhs: Local, builtin: &'static str, rhs: Local) {
-        self.sink.push(Instruction::Call {
-            target: Function::External(builtin),
-            arguments: vec![lhs, rhs],
-            output: Some(lhs),
-        });
+        self.sink
+            .push(builtins::call(builtin, vec![lhs, rhs], Some(lhs)));
     }
 
     fn eval_len(&mut self, expr: &Located<parse::Expr>, into: Local) -> Semantic<()> {
         self.ephemeral(|this, arg| {
             let (arg_type, arg_ownership) = this.eval(expr, arg)?;
-            let target = match arg_type {
-                Type::List => Function::External("builtin_len_list"),
-                Type::Mat => Function::External("builtin_shapef"),
+            let builtin = match arg_type {
+                Type::List => "builtin_len_list",
+                Type::IntList => "builtin_len_ilist",
+                Type::Mat => "builtin_shapef",
 
                 _ => {
                     return Err(Located::at(
@@ -1924,11 +3983,8 @@ impl<S: Sink> Context<'_, S> {
                 }
             };
 
-            this.sink.push(Instruction::Call {
-                target,
-                arguments: vec![arg],
-                output: Some(into),
-            });
+            this.sink
+                .push(builtins::call(builtin, vec![arg], Some(into)));
 
             Ok((arg_type, arg_ownership, ()))
         })
@@ -1937,57 +3993,51 @@ impl<S: Sink> Context<'_, S> {
     fn eval_sequence(&mut self, items: &[Located<parse::Expr>], into: Local) -> Semantic<Type> {
         let item = self.sink.alloc_local();
 
-        let (is_mat, expected_columns) = match items.first() {
-            None => (false, None),
+        // El tipo del primer elemento determina el de la secuencia
+        let (typ, expected_columns) = match items.first() {
+            None => (Type::List, None),
 
             Some(first) => match self.type_check(first)? {
-                Type::Bool => (false, None),
+                Type::Bool => (Type::List, None),
+                Type::Int => (Type::IntList, None),
                 Type::List => {
                     let columns = match self.const_eval(first) {
                         Some(Static::List { length }) => Some(length),
                         _ => None,
                     };
 
-                    (true, columns)
+                    (Type::Mat, columns)
                 }
 
                 bad => {
                     return Err(Located::at(
-                        SemanticError::ExpectedTwo(Type::Bool, Type::List, bad),
+                        SemanticError::ExpectedThree(Type::Bool, Type::Int, Type::List, bad),
                         first.location().clone(),
                     ))
                 }
             },
         };
 
-        let constructor = if is_mat {
-            "builtin_new_mat"
-        } else {
-            "builtin_new_list"
+        let (constructor, insert, expected) = match typ {
+            Type::Mat => ("builtin_new_mat", "builtin_push_mat", Type::List),
+            Type::IntList => ("builtin_new_ilist", "builtin_insert_ilist", Type::Int),
+            _ => ("builtin_new_list", "builtin_insert_list", Type::Bool),
         };
 
-        self.sink.push(Instruction::Call {
-            target: Function::External(constructor),
-            arguments: Vec::new(),
-            output: Some(into),
-        });
+        self.sink
+            .push(builtins::call(constructor, Vec::new(), Some(into)));
 
         let index = self.sink.alloc_local();
         for (i, expr) in items.iter().enumerate() {
             self.sink.push(Instruction::LoadConst(i as i32, index));
 
-            let (insert, expected, arguments) = if is_mat {
-                ("builtin_push_mat", Type::List, vec![into, item])
-            } else {
-                ("builtin_insert_list", Type::Bool, vec![into, index, item])
+            let arguments = match typ {
+                Type::Mat => vec![into, item],
+                _ => vec![into, index, item],
             };
 
             let ownership = self.eval_expecting(expr, item, expected)?;
-            self.sink.push(Instruction::Call {
-                target: Function::External(insert),
-                arguments,
-                output: None,
-            });
+            self.sink.push(builtins::call(insert, arguments, None));
 
             self.drop(item, expected, ownership);
 
@@ -2011,11 +4061,7 @@ impl<S: Sink> Context<'_, S> {
         self.sink.free_local(index);
         self.sink.free_local(item);
 
-        if is_mat {
-            Ok(Type::Mat)
-        } else {
-            Ok(Type::List)
-        }
+        Ok(typ)
     }
 
     fn read_attr(
@@ -2066,17 +4112,42 @@ impl<S: Sink> Context<'_, S> {
         };
 
         let var = var.clone();
+        self.lints.refer(target, Symbol::Var(var.typ));
 
         match &var.access {
-            Access::Local(local) => self.sink.push(Instruction::Move(*local, into)),
+            Access::Local(local) | Access::Ref(local) => {
+                self.sink.push(Instruction::Move(*local, into))
+            }
+
             Access::Global(global) => self
                 .sink
                 .push(Instruction::LoadGlobal(global.clone(), into)),
+
+            // Las constantes se sustituyen por su valor en cada lectura
+            Access::Const(value) => {
+                let (_, constant) = value.scalar().unwrap();
+                self.sink.push(Instruction::LoadConst(constant, into));
+            }
         }
 
         Ok(var.typ)
     }
 
+    /// Determina si una expresión consiste en leer una variable local.
+    fn reads_local(&self, expr: &Located<parse::Expr>) -> bool {
+        match expr.as_ref() {
+            parse::Expr::Read(id) => matches!(
+                self.scope.try_lookup(id),
+                Some(Named::Var(Variable {
+                    access: Access::Local(_) | Access::Ref(_),
+                    ..
+                }))
+            ),
+
+            _ => false,
+        }
+    }
+
     fn read_index(
         &mut self,
         base: &Located<parse::Expr>,
@@ -2096,7 +4167,7 @@ impl<S: Sink> Context<'_, S> {
         };
 
         let expect_list_or_mat = || {
-            (base_type == Type::List || base_type == Type::Mat)
+            matches!(base_type, Type::List | Type::IntList | Type::Mat)
                 .then(|| ())
                 .ok_or_else(|| {
                     Located::at(
@@ -2109,10 +4180,10 @@ impl<S: Sink> Context<'_, S> {
         let (builtin, typ, args) = match index.as_ref() {
             Index::Single(expr) => {
                 expect_list_or_mat()?;
-                let (builtin, typ) = if base_type == Type::List {
-                    ("builtin_index_list", Type::Bool)
-                } else {
-                    ("builtin_index_row_mat", Type::List)
+                let (builtin, typ) = match base_type {
+                    Type::List => ("builtin_index_list", Type::Bool),
+                    Type::IntList => ("builtin_index_ilist", Type::Int),
+                    _ => ("builtin_index_row_mat", Type::List),
                 };
 
                 (builtin, typ, vec![base, expr])
@@ -2120,10 +4191,10 @@ impl<S: Sink> Context<'_, S> {
 
             Index::Range(from, to) => {
                 expect_list_or_mat()?;
-                let builtin = if base_type == Type::List {
-                    "builtin_slice_list"
-                } else {
-                    "builtin_slice_mat"
+                let builtin = match base_type {
+                    Type::List => "builtin_slice_list",
+                    Type::IntList => "builtin_slice_ilist",
+                    _ => "builtin_slice_mat",
                 };
 
                 (builtin, base_type, vec![base, from, to])
@@ -2159,13 +4230,17 @@ impl<S: Sink> Context<'_, S> {
 
     fn const_eval(&self, expr: &Located<parse::Expr>) -> Option<Static> {
         use parse::Expr::{self, *};
-        use Static::{List, *};
+        use Static::{Float, List, *};
 
         match expr.as_ref() {
             True => Some(Bool(true)),
             False => Some(Bool(false)),
             Integer(integer) => Some(Int(*integer)),
+            Expr::Float(float) => Some(Float(*float)),
+            Str(_) => None,
             Read(id) => self.scope.lookup_static(id),
+            Call(..) | Random(_) | Load(_) | UartRead | HeapUsed | HeapFree | Millis => None,
+            ProcRef(_) => None,
 
             Attr(base, attr) => {
                 let (base, attr) = (self.const_eval(base)?, attr.as_ref().as_ref());
@@ -2232,7 +4307,7 @@ impl<S: Sink> Context<'_, S> {
 
             Expr::List(items) => match items.first().map(|first| self.type_check(first)) {
                 None => Some(List { length: 0 }),
-                Some(Ok(Type::Bool)) => Some(List {
+                Some(Ok(Type::Bool | Type::Int)) => Some(List {
                     length: items.len() as i32,
                 }),
                 Some(Ok(Type::List)) => match self.const_eval(items.first().unwrap())? {
@@ -2250,12 +4325,12 @@ impl<S: Sink> Context<'_, S> {
                 Ok(Type::Bool) => Some(Bool(false)),
                 Ok(Type::Int) => Some(Int(0)),
                 Ok(Type::Float) => Some(Float(0.0)),
-                Ok(Type::List) => Some(List { length: 0 }),
+                Ok(Type::List | Type::IntList) => Some(List { length: 0 }),
                 Ok(Type::Mat) => Some(Mat {
                     rows: 0,
                     columns: 0,
                 }),
-                Err(_) => None,
+                Ok(Type::Str | Type::Proc(_)) | Err(_) => None,
             },
 
             Cast(typ, expr) => match (self.const_eval(expr)?, self.scan_type(typ)) {
@@ -2278,6 +4353,28 @@ impl<S: Sink> Context<'_, S> {
                 _ => None,
             },
 
+            Not(expr) => match self.const_eval(expr)? {
+                Bool(boolean) => Some(Bool(!boolean)),
+                _ => None,
+            },
+
+            // El operando izquierdo puede bastar, como en tiempo de ejecución
+            Binary {
+                lhs,
+                op: op @ (parse::BinOp::And | parse::BinOp::Or),
+                rhs,
+                ..
+            } => match (op, self.const_eval(lhs)?) {
+                (parse::BinOp::And, Bool(false)) => Some(Bool(false)),
+                (parse::BinOp::Or, Bool(true)) => Some(Bool(true)),
+                (_, Bool(_)) => match self.const_eval(rhs)? {
+                    Bool(rhs) => Some(Bool(rhs)),
+                    _ => None,
+                },
+
+                _ => None,
+            },
+
             Binary { lhs, op, rhs, .. } => {
                 use parse::BinOp::*;
 
@@ -2292,6 +4389,16 @@ impl<S: Sink> Context<'_, S> {
                     (Int(lhs), Div, Int(rhs)) => Some(Float(lhs as f32 / rhs as f32)),
                     (Int(lhs), Mod, Int(rhs)) if rhs != 0 => Some(Int(lhs % rhs)),
                     (Int(lhs), IntegerDiv, Int(rhs)) if rhs != 0 => Some(Int(lhs / rhs)),
+                    (Int(lhs), BitAnd, Int(rhs)) => Some(Int(lhs & rhs)),
+                    (Int(lhs), BitOr, Int(rhs)) => Some(Int(lhs | rhs)),
+                    (Int(lhs), BitXor, Int(rhs)) => Some(Int(lhs ^ rhs)),
+                    (Int(lhs), ShiftLeft, Int(rhs)) if (0..32).contains(&rhs) => {
+                        Some(Int(lhs << rhs))
+                    }
+
+                    (Int(lhs), ShiftRight, Int(rhs)) if (0..32).contains(&rhs) => {
+                        Some(Int(lhs >> rhs))
+                    }
                     (Int(lhs), Equal, Int(rhs)) if rhs != 0 => Some(Bool(lhs == rhs)),
                     (Int(lhs), NotEqual, Int(rhs)) if rhs != 0 => Some(Bool(lhs != rhs)),
                     (Int(lhs), Greater, Int(rhs)) if rhs != 0 => Some(Bool(lhs > rhs)),
@@ -2311,6 +4418,8 @@ impl<S: Sink> Context<'_, S> {
                     (Float(lhs), Less, Float(rhs)) => Some(Bool(lhs < rhs)),
                     (Float(lhs), LessOrEqual, Float(rhs)) => Some(Bool(lhs <= rhs)),
 
+                    (lhs @ Mat { .. }, BitAnd | BitOr | BitXor, rhs) if lhs == rhs => Some(lhs),
+
                     _ => None,
                 }
             }
@@ -2410,6 +4519,11 @@ impl<S: Sink> Context<'_, S> {
     }
 
     fn expire(mut self) -> S {
+        for (id, location) in self.scope.unread.take().into_iter() {
+            self.lints
+                .warn(SemanticWarning::UnusedVariable(id), location);
+        }
+
         std::mem::take(&mut self.scope.symbols)
             .into_iter()
             .for_each(|(_, named)| match named {
@@ -2432,23 +4546,152 @@ impl<S: Sink> Context<'_, S> {
         F: FnOnce(&mut Context<'_, S>) -> R,
     {
         self.scope.statics.clear();
+        self.nested(HashMap::new(), callback).0
+    }
+
+    /// Como `subscope()`, pero para una rama que se ejecuta a lo sumo
+    /// una vez. La rama parte de los valores estáticos conocidos hasta
+    /// ahora, y se retornan los que se conocen al final de ella para
+    /// variables externas a la rama.
+    fn branch<F, R>(&mut self, callback: F) -> (R, HashMap<Identifier, Static>)
+    where
+        F: FnOnce(&mut Context<'_, S>) -> R,
+    {
+        let statics = self.scope.statics.clone();
+        self.nested(statics, callback)
+    }
 
+    fn nested<F, R>(
+        &mut self,
+        statics: HashMap<Identifier, Static>,
+        callback: F,
+    ) -> (R, HashMap<Identifier, Static>)
+    where
+        F: FnOnce(&mut Context<'_, S>) -> R,
+    {
         let sink = std::mem::take(&mut self.sink);
         let mut subcontext = Context {
             scope: SymbolTable {
                 outer: Some(&self.scope),
+                statics,
                 ..Default::default()
             },
 
             sink,
+            lints: self.lints,
             procedure: self.procedure,
+            ast: self.ast,
+            depth: self.depth,
+            nesting: self.nesting,
+            loops: self.loops.clone(),
             is_toplevel: false,
+            returned: false,
         };
 
         let result = callback(&mut subcontext);
+
+        let mut statics = std::mem::take(&mut subcontext.scope.statics);
+        statics.retain(|id, _| !subcontext.scope.symbols.contains_key(id));
         self.sink = subcontext.expire();
 
-        result
+        (result, statics)
+    }
+
+    /// Repite el análisis de un procedimiento invocado conociendo los
+    /// valores estáticos de sus argumentos. Así, por ejemplo, una matriz
+    /// de forma conocida que se pasa a un procedimiento que la indexa
+    /// fuera de rango produce un error en el sitio de la llamada.
+    ///
+    /// Solo se reportan los errores que dependen de valores estáticos y
+    /// que el procedimiento no presenta por sí mismo, ya que de esos se
+    /// encarga el análisis ordinario del procedimiento.
+    fn check_specialized(
+        &self,
+        target: &Located<Identifier>,
+        types: &[Type],
+        returns: Option<Type>,
+        args: &[Located<parse::Expr>],
+    ) -> Semantic<()> {
+        let statics: Vec<_> = args.iter().map(|arg| self.const_eval(arg)).collect();
+        if self.depth >= MAX_SPECIALIZATION_DEPTH || statics.iter().all(Option::is_none) {
+            return Ok(());
+        }
+
+        let procedure = self.ast.iter().find(|procedure| {
+            let procedure_returns = procedure
+                .returns()
+                .map(|typ| self.scan_type(typ))
+                .transpose();
+
+            procedure.name().as_ref() == target.as_ref()
+                && procedure_returns.ok() == Some(returns)
+                && self
+                    .parameter_types(procedure)
+                    .map_or(false, |parameters| parameters == types)
+        });
+
+        let procedure = match procedure {
+            Some(procedure) => procedure,
+            None => return Ok(()),
+        };
+
+        let known = procedure
+            .parameters()
+            .iter()
+            .zip(statics)
+            .filter_map(|(parameter, value)| Some((parameter.name().as_ref().clone(), value?)))
+            .collect();
+
+        if self.specialize(procedure, HashMap::new()).is_err() {
+            return Ok(());
+        }
+
+        match self.specialize(procedure, known) {
+            Err(error) if error.as_ref().is_static() => {
+                let (location, error) = error.split();
+                let line = location.start().line();
+
+                Err(Located::at(
+                    SemanticError::FailsInCall(target.as_ref().clone(), line, Box::new(error)),
+                    target.location().clone(),
+                ))
+            }
+
+            _ => Ok(()),
+        }
+    }
+
+    /// Analiza un procedimiento sin generar código ni advertencias.
+    fn specialize(
+        &self,
+        procedure: &parse::Procedure,
+        statics: HashMap<Identifier, Static>,
+    ) -> Semantic<()> {
+        let mut global_scope = &self.scope;
+        while let Some(outer) = global_scope.outer {
+            global_scope = outer;
+        }
+
+        let lints = Lints::default();
+        let context = Context {
+            scope: SymbolTable {
+                outer: Some(global_scope),
+                statics,
+                ..Default::default()
+            },
+
+            sink: TypeCheck,
+            lints: &lints,
+            procedure: Some(procedure),
+            ast: self.ast,
+            depth: self.depth + 1,
+            nesting: self.nesting,
+            loops: Vec::new(),
+            is_toplevel: false,
+            returned: false,
+        };
+
+        context.scan_procedure(procedure).map(|_| ())
     }
 
     fn ephemeral<F, R>(&mut self, callback: F) -> Semantic<R>
@@ -2467,38 +4710,168 @@ impl<S: Sink> Context<'_, S> {
 
     fn drop(&mut self, local: Local, typ: Type, ownership: Ownership) {
         if let Some(destructor) = destructor(typ, ownership) {
-            self.sink.push(Instruction::Call {
-                target: Function::External(destructor),
-                arguments: vec![local],
-                output: None,
-            });
+            self.sink
+                .push(builtins::call(destructor, vec![local], None));
         }
     }
 }
 
-fn drop_globals<S: Sink>(sink: &mut S, globals: &SymbolTable<'_>) {
-    for named in globals.symbols.values() {
+fn drop_owned<S: Sink>(sink: &mut S, local: Local, typ: Type) {
+    if let Some(destructor) = destructor(typ, Ownership::Owned) {
+        sink.push(builtins::call(destructor, vec![local], None));
+    }
+}
+
+/// Destruye las locales y temporales de un único ámbito.
+fn drop_scope<S: Sink>(sink: &mut S, scope: &SymbolTable<'_>) {
+    for named in scope.symbols.values() {
+        if let Named::Var(Variable {
+            access: Access::Local(local),
+            typ,
+        }) = named
+        {
+            drop_owned(sink, *local, *typ);
+        }
+    }
+
+    for &(local, typ) in scope.temporaries.iter() {
+        drop_owned(sink, local, typ);
+    }
+}
+
+fn drop_globals<S: Sink>(sink: &mut S, globals: &SymbolTable<'_>, scratch: Local) {
+    let mut symbols: Vec<_> = globals.symbols.iter().collect();
+    symbols.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+
+    for (_, named) in symbols {
         if let Named::Var(Variable {
             access: Access::Global(global),
             typ,
         }) = named
         {
             if let Some(destructor) = destructor(*typ, Ownership::Owned) {
-                // Ya no quedan otras locales
-                let local = Local::default();
-                let load = Instruction::LoadGlobal(global.clone(), local);
+                let load = Instruction::LoadGlobal(global.clone(), scratch);
 
                 sink.push(load);
-                sink.push(Instruction::Call {
-                    target: Function::External(destructor),
-                    arguments: vec![local],
-                    output: None,
-                });
+                sink.push(builtins::call(destructor, vec![scratch], None));
             }
         }
     }
 }
 
+/// Elige la sobrecarga de `target` cuyos parámetros son `types`. Si
+/// varias difieren solo en su tipo de retorno, se elige la única que
+/// corresponde a `expect`.
+fn resolve<'a>(
+    target: &Located<Identifier>,
+    variants: &'a HashMap<(Vec<Type>, Option<Type>), Overload>,
+    types: &[Type],
+    expect: Expect,
+) -> Semantic<&'a Overload> {
+    let candidates: Vec<_> = variants
+        .iter()
+        .filter(|((parameters, _), _)| parameters == types)
+        .map(|(_, overload)| overload)
+        .collect();
+
+    if let [overload] = candidates[..] {
+        return Ok(overload);
+    } else if candidates.is_empty() {
+        let mut candidates: Vec<_> = variants.keys().map(|(types, _)| signature(types)).collect();
+
+        candidates.sort();
+        candidates.dedup();
+
+        return Err(Located::at(
+            SemanticError::NoSuchOverload(target.as_ref().clone(), signature(types), candidates),
+            target.location().clone(),
+        ));
+    }
+
+    let expected = |overload: &&Overload| match expect {
+        Expect::Discard => overload.returns.is_none(),
+        Expect::Value => overload.returns.is_some(),
+        Expect::Type(typ) => overload.returns == Some(typ),
+    };
+
+    let mut matching = candidates.iter().copied().filter(expected);
+    match (matching.next(), matching.next()) {
+        (Some(overload), None) => Ok(overload),
+        _ => {
+            let mut returns: Vec<_> = candidates
+                .iter()
+                .map(|overload| match overload.returns {
+                    Some(typ) => format!("`{}`", typ),
+                    None => String::from("no value"),
+                })
+                .collect();
+
+            returns.sort();
+
+            Err(Located::at(
+                SemanticError::AmbiguousCall(target.as_ref().clone(), signature(types), returns),
+                target.location().clone(),
+            ))
+        }
+    }
+}
+
+/// Lista de tipos de parámetros, tal como se escribe en una firma.
+fn signature(types: &[Type]) -> String {
+    let types = types.iter().map(ToString::to_string).collect::<Vec<_>>();
+    types.join(", ")
+}
+
+fn contains_return(statements: &[parse::Statement]) -> bool {
+    statements.iter().any(|statement| match statement {
+        parse::Statement::Return { .. } => true,
+        parse::Statement::For { body, .. } | parse::Statement::Loop { body, .. } => {
+            contains_return(body)
+        }
+
+        parse::Statement::If {
+            body, otherwise, ..
+        } => {
+            let otherwise = otherwise.as_deref().unwrap_or(&[]);
+            contains_return(body) || contains_return(otherwise)
+        }
+
+        _ => false,
+    })
+}
+
+/// Determina si algún `break` termina al ciclo cuyo cuerpo es `statements`.
+fn contains_break(statements: &[parse::Statement]) -> bool {
+    contains_jump(statements, &|statement| {
+        matches!(statement, parse::Statement::Break(_))
+    })
+}
+
+/// Como `contains_break()`, pero para `continue`.
+fn contains_continue(statements: &[parse::Statement]) -> bool {
+    contains_jump(statements, &|statement| {
+        matches!(statement, parse::Statement::Continue(_))
+    })
+}
+
+/// Los ciclos anidados no se recorren, ya que son el destino de sus
+/// propios `break` y `continue`.
+fn contains_jump(
+    statements: &[parse::Statement],
+    is_jump: &dyn Fn(&parse::Statement) -> bool,
+) -> bool {
+    statements.iter().any(|statement| match statement {
+        parse::Statement::If {
+            body, otherwise, ..
+        } => {
+            let otherwise = otherwise.as_deref().unwrap_or(&[]);
+            contains_jump(body, is_jump) || contains_jump(otherwise, is_jump)
+        }
+
+        statement => is_jump(statement),
+    })
+}
+
 fn break_assignment<'a>(
     targets: &'a [Located<parse::Target>],
     values: &'a [Located<parse::Expr>],
@@ -2520,13 +4893,18 @@ fn break_assignment<'a>(
 fn destructor(typ: Type, ownership: Ownership) -> Option<&'static str> {
     match (typ, ownership) {
         (_, Ownership::Borrowed) => None,
-        (Type::Int | Type::Bool | Type::Float, _) => None,
+        (Type::Int | Type::Bool | Type::Float | Type::Proc(_), _) => None,
         (Type::List, Ownership::Owned) => Some("builtin_drop_list"),
+        (Type::IntList, Ownership::Owned) => Some("builtin_drop_ilist"),
         (Type::Mat, Ownership::Owned) => Some("builtin_drop_mat"),
+        (Type::Str, Ownership::Owned) => Some("builtin_drop_str"),
     }
 }
 
-fn mangle(name: &Identifier, types: &[Type]) -> String {
+/// Los parámetros `ref` se distinguen con una `r` antes de su tipo. El
+/// tipo de retorno, si existe, sigue a `$t`, de forma que sobrecargas
+/// que solo difieren en lo que retornan obtienen símbolos distintos.
+fn mangle(name: &Identifier, types: &[Type], refs: &[bool], returns: Option<Type>) -> String {
     let name = name.as_ref();
 
     let mut mangled = String::from("user_");
@@ -2542,14 +4920,223 @@ fn mangle(name: &Identifier, types: &[Type]) -> String {
 
     if !types.is_empty() {
         mangled.push_str("$$");
-        mangled.extend(types.iter().map(|typ| match typ {
+        mangle_types(types, refs, &mut mangled);
+    }
+
+    if let Some(returns) = returns {
+        mangled.push_str("$t");
+        mangle_types(&[returns], &[], &mut mangled);
+    }
+
+    mangled
+}
+
+/// Una referencia a procedimiento se codifica como `p`, seguida de sus
+/// parámetros, `_` y su tipo de retorno, o bien `v` si no retorna.
+fn mangle_types(types: &[Type], refs: &[bool], mangled: &mut String) {
+    for (i, typ) in types.iter().enumerate() {
+        if refs.get(i).copied().unwrap_or(false) {
+            mangled.push('r');
+        }
+
+        let code = match typ {
             Type::Int => 'i',
             Type::Mat => 'm',
             Type::Bool => 'b',
             Type::List => 'l',
+            Type::IntList => 'j',
             Type::Float => 'f',
-        }));
+            Type::Str => 's',
+            Type::Proc(signature) => {
+                mangled.push('p');
+                mangle_types(&signature.parameters, &signature.refs, mangled);
+                mangled.push('_');
+
+                match signature.returns {
+                    Some(returns) => mangle_types(&[returns], &[], mangled),
+                    None => mangled.push('v'),
+                }
+
+                continue;
+            }
+        };
+
+        mangled.push(code);
     }
+}
 
-    mangled
+/// Inverso de [`mangle()`]. Un procedimiento se muestra junto a los
+/// tipos de sus parámetros y de retorno, como `foo(int, ref list): int`,
+/// mientras que un procedimiento sin parámetros ni retorno o una global
+/// se muestran solo por nombre. El nombre original se recupera en minúsculas. Se retorna
+/// `None` si `symbol` no es un nombre generado por el compilador.
+pub fn demangle(symbol: &str) -> Option<String> {
+    let mut rest = symbol.strip_prefix("user_")?;
+    let mut name = String::new();
+
+    let rest = loop {
+        let next = match rest {
+            "" => break rest,
+            _ if rest.starts_with("$$") || rest.starts_with("$t") => break rest,
+            _ if rest.starts_with("$a$") => ('@', 3),
+            _ if rest.starts_with("$q$") => ('?', 3),
+            _ => match rest.chars().next()? {
+                c @ ('a'..='z' | '0'..='9' | '_') => (c, 1),
+                _ => return None,
+            },
+        };
+
+        name.push(next.0);
+        rest = &rest[next.1..];
+    };
+
+    if name.is_empty() {
+        return None;
+    }
+
+    // Los códigos de tipo no incluyen `$`, a diferencia del nombre
+    let (types, returns) = match rest.split_once("$t") {
+        Some((types, mut returns)) => {
+            let returns = demangle_type(&mut returns).filter(|_| returns.is_empty())?;
+            (types, Some(returns))
+        }
+
+        None => (rest, None),
+    };
+
+    let parameters = match types.strip_prefix("$$") {
+        Some(mut types) => Some(demangle_parameters(&mut types, None)?),
+        None if types.is_empty() => None,
+        None => return None,
+    };
+
+    if parameters.is_some() || returns.is_some() {
+        let (parameters, refs) = parameters.unwrap_or_default();
+
+        let signature = Signature {
+            parameters,
+            refs,
+            returns,
+        };
+
+        // "procedure(...)" es el formato de las referencias a procedimientos
+        let signature = signature.to_string();
+        name.push_str(signature.strip_prefix("procedure")?);
+    }
+
+    Some(name)
+}
+
+/// Sustituye en un texto cada símbolo que reconozca [`demangle()`], por
+/// ejemplo en los mensajes de error de herramientas externas.
+pub fn demangle_text(text: &str) -> String {
+    let is_symbol_char = |c: char| matches!(c, 'a'..='z' | '0'..='9' | '_' | '$');
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("user_") {
+        // "user_" debe iniciar el símbolo, no ser parte de otro
+        let preceded = rest[..start]
+            .chars()
+            .next_back()
+            .filter(|&c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+
+        let length = rest[start..]
+            .find(|c| !is_symbol_char(c))
+            .unwrap_or(rest.len() - start);
+
+        let symbol = &rest[start..start + length];
+        output.push_str(&rest[..start]);
+
+        match demangle(symbol).filter(|_| preceded.is_none()) {
+            Some(demangled) => output.push_str(&demangled),
+            None => output.push_str(symbol),
+        }
+
+        rest = &rest[start + length..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Decodifica parámetros hasta agotar `mangled` o bien hasta encontrar
+/// `end`, el cual se consume.
+fn demangle_parameters(mangled: &mut &str, end: Option<char>) -> Option<(Vec<Type>, Vec<bool>)> {
+    let (mut parameters, mut refs) = (Vec::new(), Vec::new());
+
+    loop {
+        match (mangled.chars().next(), end) {
+            (None, None) => break,
+            (Some(c), Some(end)) if c == end => {
+                *mangled = &mangled[1..];
+                break;
+            }
+
+            _ => (),
+        }
+
+        let by_ref = match mangled.strip_prefix('r') {
+            Some(rest) => {
+                *mangled = rest;
+                true
+            }
+
+            None => false,
+        };
+
+        parameters.push(demangle_type(mangled)?);
+        refs.push(by_ref);
+    }
+
+    Some((parameters, refs))
+}
+
+/// Decodifica un único tipo, ver [`mangle_types()`].
+fn demangle_type(mangled: &mut &str) -> Option<Type> {
+    let code = mangled.chars().next()?;
+    *mangled = &mangled[code.len_utf8()..];
+
+    let typ = match code {
+        'i' => Type::Int,
+        'm' => Type::Mat,
+        'b' => Type::Bool,
+        'l' => Type::List,
+        'j' => Type::IntList,
+        'f' => Type::Float,
+        's' => Type::Str,
+
+        'p' => {
+            let (parameters, refs) = demangle_parameters(mangled, Some('_'))?;
+            let returns = match mangled.strip_prefix('v') {
+                Some(rest) => {
+                    *mangled = rest;
+                    None
+                }
+
+                None => Some(demangle_type(mangled)?),
+            };
+
+            let signature = Signature {
+                parameters,
+                refs,
+                returns,
+            };
+
+            Type::Proc(signature.intern())
+        }
+
+        _ => return None,
+    };
+
+    Some(typ)
+}
+
+/// Valores estáticos que coinciden en ambos caminos de una bifurcación.
+fn meet(
+    mut first: HashMap<Identifier, Static>,
+    second: &HashMap<Identifier, Static>,
+) -> HashMap<Identifier, Static> {
+    first.retain(|id, value| second.get(id) == Some(value));
+    first
 }
@@ -0,0 +1,212 @@
+//! Instantáneas de la matriz de LEDs.
+//!
+//! Una instantánea es la representación textual de una
+//! [`Recording`]: cada cambio de la matriz en orden, seguido de la
+//! forma en que terminó el programa. Se guardan en archivos
+//! `.expected` junto a cada programa, de modo que `compiler test`
+//! pueda detectar cualquier cambio en lo que un programa muestra.
+//!
+//! # Formato
+//! Cada cuadro inicia con el instante en que se observó, seguido de
+//! las ocho filas de la matriz. Cada LED es `.` si está apagado, `#`
+//! si está encendido con intensidad máxima o bien un dígito
+//! hexadecimal con su intensidad. Los bloques se separan por una línea
+//! vacía y el último describe cómo terminó el programa:
+//!
+//! ```text
+//! 0ms
+//! ........
+//! ........
+//! ........
+//! ........
+//! ........
+//! ........
+//! ........
+//! ........
+//!
+//! 1000ms
+//! #.......
+//! .7......
+//! ........
+//! ........
+//! ........
+//! ........
+//! ........
+//! ........
+//!
+//! finished
+//! ```
+
+use std::fmt::{self, Display, Write};
+
+use crate::{
+    grade::{Outcome, Recording, Sample},
+    interp::MAX_LEVEL,
+};
+
+/// Representación textual de una ejecución.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Snapshot(String);
+
+/// Primer bloque en el que difieren dos instantáneas.
+pub struct Mismatch {
+    /// Número del bloque, desde cero.
+    pub block: usize,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl Snapshot {
+    /// Construye la instantánea de una ejecución.
+    pub fn new(recording: &Recording) -> Self {
+        let mut text = String::new();
+        for sample in &recording.samples {
+            write_sample(&mut text, sample).unwrap();
+            text.push('\n');
+        }
+
+        match &recording.outcome {
+            Outcome::Finished => text.push_str("finished\n"),
+
+            Outcome::Crashed { exit_code, message } => {
+                writeln!(text, "exit code {}", exit_code).unwrap();
+                push_lines(&mut text, message);
+            }
+
+            Outcome::Rejected(diagnostics) => {
+                text.push_str("rejected\n");
+                push_lines(&mut text, diagnostics);
+            }
+        }
+
+        Snapshot(text)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Compara contra el contenido de un archivo `.expected`.
+    ///
+    /// Los finales de línea `\r\n` y los espacios al final de cada
+    /// línea no se consideran diferencias.
+    pub fn compare(&self, expected: &str) -> Option<Mismatch> {
+        let (expected, actual) = (blocks(expected), blocks(&self.0));
+
+        let count = expected.len().max(actual.len());
+        (0..count).find_map(|block| {
+            let expected = expected.get(block).cloned().unwrap_or_default();
+            let actual = actual.get(block).cloned().unwrap_or_default();
+
+            if expected != actual {
+                Some(Mismatch {
+                    block,
+                    expected,
+                    actual,
+                })
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl Display for Snapshot {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(&self.0)
+    }
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let missing = "(nothing)";
+        let expected: Vec<_> = self.expected.lines().collect();
+        let actual: Vec<_> = self.actual.lines().collect();
+
+        let width = expected
+            .iter()
+            .map(|line| line.chars().count())
+            .chain(Some(missing.len()))
+            .max()
+            .unwrap_or(0);
+
+        writeln!(fmt, "First difference in block {}", self.block)?;
+        writeln!(fmt, "  {:width$}  actual", "expected", width = width)?;
+
+        let count = expected.len().max(actual.len()).max(1);
+        for line in 0..count {
+            let expected = line_or(&expected, line, missing);
+            let actual = line_or(&actual, line, missing);
+            writeln!(fmt, "  {:width$}  {}", expected, actual, width = width)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Línea de un bloque, o un marcador si el bloque no existe.
+fn line_or<'a>(lines: &[&'a str], line: usize, missing: &'a str) -> &'a str {
+    match lines.get(line) {
+        Some(text) => text,
+        None if lines.is_empty() && line == 0 => missing,
+        None => "",
+    }
+}
+
+/// Escribe un cuadro de la instantánea.
+fn write_sample(text: &mut String, sample: &Sample) -> fmt::Result {
+    writeln!(text, "{}ms", sample.at)?;
+
+    for row in 0..8 {
+        for column in 0..8 {
+            let level = match &sample.levels {
+                Some(levels) => levels[row][column],
+                None if sample.frame.0[row] & (0x80 >> column) != 0 => MAX_LEVEL,
+                None => 0,
+            };
+
+            let symbol = match level {
+                0 => '.',
+                MAX_LEVEL => '#',
+                level => std::char::from_digit(level as u32, 16).unwrap_or('?'),
+            };
+
+            text.push(symbol);
+        }
+
+        text.push('\n');
+    }
+
+    Ok(())
+}
+
+/// Agrega un texto de varias líneas, omitiendo espacios al final.
+fn push_lines(text: &mut String, lines: &str) {
+    for line in lines.trim_end().lines() {
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+}
+
+/// Divide una instantánea en bloques normalizados.
+fn blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current = String::new();
+
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() {
+            if !current.is_empty() {
+                blocks.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+
+    blocks
+}
@@ -131,7 +131,9 @@ impl Debug for Location {
 }
 
 /// Una posición línea-columna en un archivo.
-#[derive(Copy, Clone, Eq, PartialEq)]
+///
+/// Las posiciones se ordenan según su aparición en el archivo.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct Position {
     line: u32,
     column: u32,
@@ -192,6 +194,11 @@ pub struct Source {
 }
 
 impl Source {
+    /// Nombre con el que se identifica al origen en diagnósticos.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     /// Realiza una operación con una línea fuente.
     pub fn with_line<R, F>(&self, number: u32, callback: F) -> R
     where
@@ -0,0 +1,131 @@
+//! Estadísticas de compilación.
+//!
+//! Con `--time-passes`, el driver registra el tiempo que toma cada
+//! fase del compilador (análisis léxico, sintáctico y semántico,
+//! generación de código y enlazado), así como algunas cantidades que
+//! permiten relacionar ese tiempo con el tamaño del programa: tokens,
+//! nodos del AST, instrucciones IR y líneas de ensamblador emitidas.
+//!
+//! La memoria que se reporta para cada fase es el máximo de memoria
+//! residente del proceso al concluir dicha fase, según `getrusage()`.
+//! Este valor nunca decrece, por lo que una fase solo es responsable
+//! del incremento respecto a la fase anterior. Fuera de Linux no se
+//! reporta memoria.
+
+use std::{
+    fmt::{self, Display},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+/// Mediciones acumuladas durante una compilación.
+#[derive(Default, Serialize)]
+pub struct Statistics {
+    phases: Vec<Phase>,
+    counts: Vec<Count>,
+}
+
+/// Medición de una fase.
+#[derive(Serialize)]
+struct Phase {
+    name: &'static str,
+    seconds: f64,
+    peak_memory_kib: Option<u64>,
+}
+
+/// Cantidad de elementos de algún tipo.
+#[derive(Serialize)]
+struct Count {
+    name: &'static str,
+    count: usize,
+}
+
+impl Statistics {
+    /// Ejecuta una fase, registrando su duración y la memoria al concluir.
+    pub fn measure<T, F: FnOnce() -> T>(&mut self, name: &'static str, phase: F) -> T {
+        let start = Instant::now();
+        let result = phase();
+        let duration = start.elapsed();
+
+        self.phases.push(Phase {
+            name,
+            seconds: duration.as_secs_f64(),
+            peak_memory_kib: peak_memory(),
+        });
+
+        result
+    }
+
+    /// Registra una cantidad.
+    pub fn count(&mut self, name: &'static str, count: usize) {
+        self.counts.push(Count { name, count });
+    }
+
+    /// Tiempo total de las fases medidas.
+    pub fn total_time(&self) -> Duration {
+        self.phases
+            .iter()
+            .map(|phase| Duration::from_secs_f64(phase.seconds))
+            .sum()
+    }
+
+    /// Representación JSON de las mediciones.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("statistics are always serializable")
+    }
+}
+
+impl Display for Statistics {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            fmt,
+            "{:<12} {:>10} {:>12}",
+            "Phase", "Time (ms)", "Peak (KiB)"
+        )?;
+        for phase in &self.phases {
+            let memory = match phase.peak_memory_kib {
+                Some(memory) => memory.to_string(),
+                None => String::from("-"),
+            };
+
+            writeln!(
+                fmt,
+                "{:<12} {:>10.3} {:>12}",
+                phase.name,
+                phase.seconds * 1000.0,
+                memory
+            )?;
+        }
+
+        let total = self.total_time().as_secs_f64() * 1000.0;
+        writeln!(fmt, "{:<12} {:>10.3}", "total", total)?;
+
+        if !self.counts.is_empty() {
+            writeln!(fmt)?;
+            for count in &self.counts {
+                writeln!(fmt, "{:<20} {:>10}", count.name, count.count)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Máximo de memoria residente del proceso hasta el momento, en KiB.
+#[cfg(target_os = "linux")]
+fn peak_memory() -> Option<u64> {
+    use std::mem::MaybeUninit;
+
+    let mut usage = MaybeUninit::<libc::rusage>::uninit();
+    match unsafe { libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) } {
+        // En Linux, ru_maxrss se expresa en KiB
+        0 => Some(unsafe { usage.assume_init() }.ru_maxrss as u64),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn peak_memory() -> Option<u64> {
+    None
+}
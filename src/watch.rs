@@ -0,0 +1,162 @@
+//! Recompilación continua.
+//!
+//! Con `--watch` el compilador no termina tras la primera compilación,
+//! sino que revisa periódicamente la fecha de modificación del archivo
+//! de entrada y recompila cada vez que esta cambia. Opcionalmente, cada
+//! compilación exitosa se flashea a la tarjeta.
+//!
+//! Muchos editores guardan en varias escrituras, o reemplazando el
+//! archivo. Por esto, un cambio se atiende solo una vez que la fecha de
+//! modificación deja de cambiar, y un archivo ausente se interpreta
+//! como un guardado en curso.
+//!
+//! Las recompilaciones comparten un mismo [`Cache`], por lo cual solo
+//! los procedimientos modificados repiten análisis semántico y
+//! generación de código. Al igual que con `--daemon`, las
+//! optimizaciones de `-O2` y `--checked-arithmetic` no se aplican.
+
+use anyhow::{self, bail, Context};
+
+use std::{
+    fs,
+    path::Path,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use compiler::{
+    incremental::Cache,
+    link::{LinkOptions, OutputFormat, Platform, ScriptOptions},
+};
+
+/// Intervalo entre revisiones del archivo.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Tiempo que debe pasar sin cambios antes de recompilar.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Velocidad para flashear tras cada compilación.
+const FLASH_BAUD: u32 = 115200;
+
+/// Parámetros de cada recompilación.
+pub struct Options<'a> {
+    pub platform: Platform,
+    pub output: &'a Path,
+    pub format: OutputFormat,
+    pub scripts: ScriptOptions<'a>,
+    pub link_options: LinkOptions,
+
+    /// Puerto al cual flashear, solo para ESP8266.
+    pub flash: Option<&'a Path>,
+
+    pub warnings: bool,
+    pub color: bool,
+    pub json: bool,
+}
+
+/// Recompila `input` cada vez que cambie. Nunca retorna con éxito.
+pub fn watch(input: &Path, options: &Options) -> anyhow::Result<()> {
+    let mut cache = Cache::default();
+    let mut last = None;
+
+    eprintln!("Watching {} for changes", input.display());
+
+    loop {
+        last = Some(wait_for_change(input, last));
+        rebuild(input, options, &mut cache);
+    }
+}
+
+/// Espera a que la fecha de modificación difiera de `last` y luego se
+/// mantenga estable durante [`DEBOUNCE`].
+fn wait_for_change(input: &Path, last: Option<SystemTime>) -> SystemTime {
+    let modified = || {
+        fs::metadata(input)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    };
+
+    let mut current = loop {
+        match modified() {
+            Some(modified) if Some(modified) != last => break modified,
+            _ => thread::sleep(POLL_INTERVAL),
+        }
+    };
+
+    let mut stable_since = Instant::now();
+    while stable_since.elapsed() < DEBOUNCE {
+        thread::sleep(POLL_INTERVAL);
+
+        match modified() {
+            Some(modified) if modified == current => (),
+            Some(modified) => {
+                current = modified;
+                stable_since = Instant::now();
+            }
+
+            None => stable_since = Instant::now(),
+        }
+    }
+
+    current
+}
+
+/// Recompila una vez y reporta un resumen de una línea.
+fn rebuild(input: &Path, options: &Options, cache: &mut Cache) {
+    let start_time = Instant::now();
+
+    match build(input, options, cache) {
+        Ok(summary) => {
+            let elapsed = start_time.elapsed().as_millis();
+            eprintln!("Finished build in {}ms ({})", elapsed, summary);
+        }
+
+        Err(error) => eprintln!("Build failed: {:#}", error),
+    }
+}
+
+/// Compila, enlaza y opcionalmente flashea.
+fn build(input: &Path, options: &Options, cache: &mut Cache) -> anyhow::Result<String> {
+    let source = fs::read_to_string(input)
+        .with_context(|| format!("Failed to open for reading: {}", input.display()))?;
+
+    let name = input.to_string_lossy();
+    let (build, warnings) = match cache.frontend(&mut source.as_bytes(), &name) {
+        Ok(result) => result,
+        Err(diagnostics) => {
+            let count = diagnostics.iter().count();
+            eprint!("{}", diagnostics.color(options.color).json(options.json));
+
+            bail!("{} error{}", count, if count == 1 { "" } else { "s" });
+        }
+    };
+
+    if options.warnings && !warnings.is_empty() {
+        eprint!("{}", warnings.color(options.color).json(options.json));
+    }
+
+    let mut assembly = Vec::new();
+    cache
+        .emit(&build, options.platform.arch(), &mut assembly)
+        .context("Failed to emit assembly")?;
+
+    crate::link_executable(
+        options.platform,
+        options.output,
+        options.format,
+        options.scripts,
+        (None, None),
+        options.link_options,
+        &assembly,
+    )?;
+
+    let procedures = build.program().code.len();
+    let mut summary = format!("{} of {} procedures reused", build.reused(), procedures);
+
+    if let Some(port) = options.flash {
+        crate::flash(options.output, port, FLASH_BAUD)?;
+        summary.push_str(", flashed");
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,213 @@
+//! Conformidad de la interfaz con `libruntime`.
+//!
+//! Cada rutina de [`compiler::builtins::BUILTINS`] se invoca desde código
+//! emitido para x86-64, que es la única arquitectura de 64 bits. Los
+//! argumentos enteros son negativos, por lo cual solo llegan intactos
+//! al runtime si el código emitido los extiende en signo. El programa
+//! puede terminar normalmente o con un error en tiempo de ejecución,
+//! pero no por una señal o un límite de tiempo, y ningún mensaje puede
+//! mostrar un argumento truncado a 32 bits sin signo.
+//!
+//! Las pruebas enlazan contra el `libruntime.a` que construye el
+//! workspace, o bien contra el de `ANIMLED_LIB_DIR/native`.
+
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Stdio},
+    rc::Rc,
+};
+
+use compiler::{
+    builtins::{self, Abi, Builtin, BUILTINS},
+    ir::{Instruction, Local, Program},
+    link::{self, Linker, LinkOptions, OutputFormat, Platform, ScriptOptions},
+    sandbox::{self, Limits},
+    source::Located,
+    target,
+};
+
+/// El cuerpo de `main` se reemplaza, ver [`program()`]. Un procedimiento
+/// vacío sirve de argumento a `after` y `every`.
+const SOURCE: &str = "
+procedure main() {
+    call tick();
+}
+
+procedure tick() {}
+";
+
+/// Valor de todo argumento entero.
+const NEGATIVE: i32 = -3;
+
+/// Rutinas que esperan entrada y no tienen argumentos que verificar.
+const SKIPPED: &[&str] = &["builtin_uart_read_int"];
+
+/// Construye un programa cuyo `main` invoca únicamente a `builtin`.
+///
+/// La mitad de los argumentos enteros se cargan como constantes y la
+/// otra mitad resultan de una negación, ya que ambos casos se emiten
+/// de forma distinta.
+fn program(builtin: &Builtin) -> Program {
+    let mut program = match compiler::frontend(&mut SOURCE.as_bytes(), "abi.led") {
+        Ok(program) => program,
+        Err(diagnostics) => panic!("{}", diagnostics),
+    };
+
+    let tick = program
+        .code
+        .iter()
+        .map(|function| Rc::clone(&function.name))
+        .find(|name| name.contains("tick"))
+        .expect("procedure was not emitted");
+
+    let main = program
+        .code
+        .iter_mut()
+        .find(|function| function.name.as_str() == "user_main")
+        .expect("missing main procedure");
+
+    let location = main.body[0].location().clone();
+
+    let mut body = Vec::new();
+    let mut arguments = Vec::new();
+
+    for (index, parameter) in builtin.parameters.iter().enumerate() {
+        let local = Local(index as u32);
+        arguments.push(local);
+
+        let object = |name| builtins::call(name, Vec::new(), Some(local));
+        match parameter {
+            Abi::Int if index % 2 == 0 => body.push(Instruction::LoadConst(NEGATIVE, local)),
+            Abi::Int => {
+                body.push(Instruction::LoadConst(-NEGATIVE, local));
+                body.push(Instruction::Negate(local));
+            }
+
+            Abi::Float => {
+                let bits = (NEGATIVE as f32).to_bits() as i32;
+                body.push(Instruction::LoadConst(bits, local));
+            }
+
+            Abi::Bool => body.push(Instruction::LoadConst(1, local)),
+            Abi::List => body.push(object("builtin_new_list")),
+            Abi::IntList => body.push(object("builtin_new_ilist")),
+            Abi::Mat => body.push(object("builtin_new_mat")),
+            Abi::Str => body.push(object("builtin_new_str")),
+            Abi::Procedure => body.push(Instruction::LoadProcedure(Rc::clone(&tick), local)),
+            Abi::Data => body.push(Instruction::LoadData(Rc::from(&[0xff; 64][..]), local)),
+        }
+    }
+
+    let output = builtin
+        .returns
+        .map(|_| Local(builtin.parameters.len() as u32));
+
+    body.push(builtins::call(builtin.name, arguments, output));
+    body.push(Instruction::Return(None));
+
+    main.body = body
+        .into_iter()
+        .map(|instruction| Located::at(instruction, location.clone()))
+        .collect();
+
+    program
+}
+
+/// Ubicación de `libruntime.a` para la plataforma nativa.
+fn runtime_library() -> PathBuf {
+    if let Some(dir) = env::var_os(link::LIB_DIR_VAR) {
+        return Path::new(&dir).join("native/libruntime.a");
+    }
+
+    // target/<perfil>/deps/abi-<hash>
+    let exe = env::current_exe().expect("failed to locate test executable");
+    let profile = exe.parent().and_then(|deps| deps.parent()).unwrap();
+    let library = profile.join("libruntime.a");
+
+    assert!(
+        library.is_file(),
+        "{} not found, build the runtime or set {}",
+        library.display(),
+        link::LIB_DIR_VAR
+    );
+
+    library
+}
+
+/// Enlaza y ejecuta una invocación, retornando una descripción del
+/// problema si lo hubo.
+fn check(builtin: &Builtin, workspace: &Path) -> Option<String> {
+    let executable = workspace.join(builtin.name);
+
+    let mut assembly = Vec::new();
+    target::emit(&program(builtin), Platform::Native.arch(), &mut assembly).unwrap();
+
+    let mut linker = Linker::spawn(
+        Platform::Native,
+        &executable,
+        OutputFormat::Elf,
+        ScriptOptions::default(),
+        None,
+        None,
+        LinkOptions::empty(),
+    )
+    .unwrap();
+
+    linker.stdin().write_all(&assembly).unwrap();
+    if let Err(error) = linker.finish() {
+        return Some(format!("failed to link: {}", error));
+    }
+
+    let errors = workspace.join(format!("{}.stderr", builtin.name));
+    let mut command = sandbox::command(&executable);
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(fs::File::create(&errors).unwrap());
+
+    let limits = Limits {
+        cpu: 5,
+        wall: 10,
+        memory: 256,
+    };
+
+    let termination = sandbox::run(command, &executable, Some(&limits)).unwrap();
+    let stderr = fs::read_to_string(&errors).unwrap();
+
+    let truncated = (NEGATIVE as u32).to_string();
+    let wrapped = (NEGATIVE as isize as usize).to_string();
+
+    if let Some(violation) = termination.violation {
+        Some(violation.to_string())
+    } else if stderr.contains(&truncated) || stderr.contains(&wrapped) {
+        Some(format!("received an unsigned argument: {}", stderr.trim()))
+    } else {
+        match termination.exit_code() {
+            0 => None,
+            1 if stderr.starts_with("Runtime error at line") => None,
+            code => Some(format!("exit code {}: {}", code, stderr.trim())),
+        }
+    }
+}
+
+#[test]
+fn builtins_receive_negative_integers() {
+    let workspace = env::temp_dir().join(format!("compiler-abi.{}", process::id()));
+    let native = workspace.join("lib/native");
+
+    fs::create_dir_all(&native).unwrap();
+    fs::copy(runtime_library(), native.join("libruntime.a")).unwrap();
+    env::set_var(link::LIB_DIR_VAR, workspace.join("lib"));
+
+    let failures: Vec<_> = BUILTINS
+        .iter()
+        .filter(|builtin| !SKIPPED.contains(&builtin.name))
+        .filter_map(|builtin| Some(format!("{}: {}", builtin.name, check(builtin, &workspace)?)))
+        .collect();
+
+    let _ = fs::remove_dir_all(&workspace);
+    assert!(failures.is_empty(), "ABI violations:\n{}", failures.join("\n"));
+}
@@ -0,0 +1,200 @@
+//! El registro de [`compiler::builtins`] debe declarar exactamente las
+//! rutinas que exporta `libruntime`, y con las mismas firmas.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+use compiler::builtins::{Abi, Builtin, BUILTINS};
+
+/// Firma de una función `builtin_*` tal como aparece en el código fuente.
+#[derive(Clone)]
+struct Signature {
+    /// Si la función utiliza la convención de llamada de C.
+    extern_c: bool,
+
+    /// Tipos de los parámetros.
+    parameters: Vec<String>,
+
+    /// Tipo de retorno, vacío si no existe.
+    returns: String,
+}
+
+/// Funciones `builtin_*` que define `runtime/src/builtin.rs`, incluyendo
+/// las que se generan con `mutator!`.
+fn exported() -> BTreeMap<String, Signature> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("runtime/src/builtin.rs");
+    let source = fs::read_to_string(path).expect("failed to read runtime source");
+
+    let mut functions = BTreeMap::new();
+    let mut templates = Vec::new();
+    let mut mutators = Vec::new();
+
+    for (start, _) in source.match_indices("fn builtin_") {
+        let (name, signature) = parse(&source, start + "fn ".len());
+        functions.insert(name, signature);
+    }
+
+    for (start, _) in source.match_indices("fn [<builtin_ $op ") {
+        let (suffix, signature) = parse(&source, start + "fn [<builtin_ $op ".len());
+        templates.push((suffix.trim_end_matches(">]").to_owned(), signature));
+    }
+
+    for line in source.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("mutator!(\"") {
+            mutators.push(rest.split('"').next().unwrap().to_owned());
+        }
+    }
+
+    for op in &mutators {
+        for (suffix, signature) in &templates {
+            functions.insert(format!("builtin_{}{}", op, suffix), signature.clone());
+        }
+    }
+
+    functions
+}
+
+/// Lee la firma de una función cuyo nombre inicia en `name`.
+fn parse(source: &str, name: usize) -> (String, Signature) {
+    let line_start = source[..name].rfind('\n').map_or(0, |newline| newline + 1);
+    let extern_c = source[line_start..name].contains("extern \"C\"");
+
+    let open = name + source[name..].find('(').expect("missing parameter list");
+    let mut depth = 0;
+    let close = open
+        + source[open..]
+            .find(|c| {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => (),
+                }
+
+                depth == 0
+            })
+            .unwrap();
+
+    let body = close + source[close..].find('{').expect("missing function body");
+    let returns = source[close + 1..body]
+        .trim()
+        .trim_start_matches("->")
+        .trim();
+
+    let parameters = source[open + 1..close]
+        .split(',')
+        .filter_map(|parameter| parameter.split_once(':'))
+        .map(|(_, typ)| typ.trim().to_owned())
+        .collect();
+
+    let signature = Signature {
+        extern_c,
+        parameters,
+        returns: returns.to_owned(),
+    };
+
+    (source[name..open].trim().to_owned(), signature)
+}
+
+/// Tipos de Rust que corresponden a cada [`Abi`] en `libruntime`.
+fn rust_types(abi: Abi) -> &'static [&'static str] {
+    match abi {
+        Abi::Int | Abi::Float => &["isize"],
+        Abi::Bool => &["bool", "isize"],
+        Abi::List => &["*mut List"],
+        Abi::IntList => &["*mut IntList"],
+        Abi::Mat => &["*mut Mat"],
+        Abi::Str => &["*mut Str"],
+        Abi::Procedure => &["schedule::Procedure", "extern \"C\" fn()"],
+        Abi::Data => &["*const u8"],
+    }
+}
+
+/// Diferencias entre la firma declarada en el registro y la exportada.
+fn mismatches(builtin: &Builtin, signature: &Signature) -> Vec<String> {
+    let mut mismatches = Vec::new();
+
+    if !signature.extern_c {
+        mismatches.push("not extern \"C\"".to_owned());
+    }
+
+    if builtin.parameters.len() != signature.parameters.len() {
+        mismatches.push(format!(
+            "{} parameters, registry declares {}",
+            signature.parameters.len(),
+            builtin.parameters.len()
+        ));
+    }
+
+    let parameters = builtin.parameters.iter().zip(&signature.parameters);
+    for (index, (&abi, typ)) in parameters.enumerate() {
+        if !rust_types(abi).contains(&typ.as_str()) {
+            mismatches.push(format!(
+                "parameter {} is `{}`, expected {:?}",
+                index, typ, abi
+            ));
+        }
+    }
+
+    let returns_ok = match builtin.returns {
+        Some(abi) => rust_types(abi).contains(&signature.returns.as_str()),
+        None => signature.returns.is_empty() || signature.returns == "!",
+    };
+
+    if !returns_ok {
+        mismatches.push(format!(
+            "returns `{}`, expected {:?}",
+            signature.returns, builtin.returns
+        ));
+    }
+
+    mismatches
+}
+
+#[test]
+fn registry_matches_runtime() {
+    let declared: BTreeSet<_> = BUILTINS
+        .iter()
+        .map(|builtin| builtin.name.to_owned())
+        .collect();
+
+    assert_eq!(declared.len(), BUILTINS.len(), "duplicate builtin");
+
+    let exported: BTreeSet<_> = exported().into_keys().collect();
+    let undeclared: Vec<_> = exported.difference(&declared).collect();
+    let missing: Vec<_> = declared.difference(&exported).collect();
+
+    assert!(
+        undeclared.is_empty(),
+        "undeclared builtins: {:?}",
+        undeclared
+    );
+    assert!(
+        missing.is_empty(),
+        "builtins missing from runtime: {:?}",
+        missing
+    );
+}
+
+#[test]
+fn signatures_match_runtime() {
+    let exported = exported();
+
+    let mismatches: Vec<_> = BUILTINS
+        .iter()
+        .filter_map(|builtin| Some((builtin, exported.get(builtin.name)?)))
+        .flat_map(|(builtin, signature)| {
+            mismatches(builtin, signature)
+                .into_iter()
+                .map(move |mismatch| format!("{}: {}", builtin.name, mismatch))
+        })
+        .collect();
+
+    assert!(
+        mismatches.is_empty(),
+        "signature mismatches:\n{}",
+        mismatches.join("\n")
+    );
+}
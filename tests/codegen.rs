@@ -0,0 +1,87 @@
+//! Instantáneas del código que emite el compilador.
+//!
+//! Cada programa `tests/codegen/<nombre>.led` se compila para x86-64 y
+//! Xtensa, y el ensamblador resultante se compara contra
+//! `tests/codegen/<nombre>.<arch>.s`. Con `UPDATE_SNAPSHOTS=1` las
+//! instantáneas se reescriben en vez de compararse. Una instantánea que
+//! aún no existe es un error, salvo con `UPDATE_SNAPSHOTS=1`.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use compiler::{compile_to_string, target::Arch};
+
+const ARCHES: &[(Arch, &str)] = &[(Arch::X86_64, "x86_64"), (Arch::Xtensa, "xtensa")];
+
+/// Descarta espacio en blanco al final de cada línea y líneas vacías,
+/// de forma que solo cambios en instrucciones o directivas afecten la
+/// comparación.
+fn normalize(assembly: &str) -> String {
+    assembly
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+fn corpus() -> Vec<PathBuf> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/codegen");
+
+    let mut programs: Vec<_> = fs::read_dir(directory)
+        .expect("failed to read corpus")
+        .map(|entry| entry.expect("failed to read corpus").path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "led"))
+        .collect();
+
+    programs.sort();
+    programs
+}
+
+#[test]
+fn codegen_snapshots() {
+    let update = env::var_os("UPDATE_SNAPSHOTS").map_or(false, |value| value == "1");
+    let mut mismatches = Vec::new();
+    let mut missing = Vec::new();
+
+    for program in corpus() {
+        let source = fs::read_to_string(&program).expect("failed to read program");
+
+        for (arch, name) in ARCHES {
+            let assembly = match compile_to_string(&source, *arch) {
+                Ok(assembly) => normalize(&assembly),
+                Err(diagnostics) => panic!("{}:\n{}", program.display(), diagnostics),
+            };
+
+            let snapshot = program.with_extension(format!("{}.s", name));
+            if update {
+                fs::write(&snapshot, assembly).expect("failed to write snapshot");
+                continue;
+            }
+
+            match fs::read_to_string(&snapshot) {
+                Ok(expected) if normalize(&expected) != assembly => {
+                    mismatches.push(snapshot.display().to_string());
+                }
+
+                Ok(_) => (),
+                Err(_) => missing.push(snapshot.display().to_string()),
+            }
+        }
+    }
+
+    assert!(
+        missing.is_empty(),
+        "snapshots do not exist, rerun with UPDATE_SNAPSHOTS=1 to record them: {:?}",
+        missing
+    );
+
+    assert!(
+        mismatches.is_empty(),
+        "emitted code differs from snapshots, rerun with UPDATE_SNAPSHOTS=1 \
+         if the change is intended: {:?}",
+        mismatches
+    );
+}
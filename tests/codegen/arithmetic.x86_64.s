@@ -0,0 +1,80 @@
+.lcomm user_x, 8
+.lcomm user_y, 8
+.text
+.section .text.user_main
+.balign 8
+.global user_main
+user_main:
+	push    %rbp
+	mov     %rsp, %rbp
+	sub     $0x20, %rsp
+	mov     %rbx, -0x8(%rbp)
+	.Luser_main.line.0:
+	mov     $7, %ebx
+	mov     %rbx, user_x(%rip)
+	.Luser_main.line.1:
+	mov     user_x(%rip), %rbx
+	mov     $3, %eax
+	imul    %eax, %ebx
+	movslq  %ebx, %rbx
+	mov     $2, %eax
+	add     %eax, %ebx
+	movslq  %ebx, %rbx
+	mov     %rbx, user_y(%rip)
+	.Luser_main.line.2:
+	mov     $6, %ebx
+	mov     user_y(%rip), %rax
+	mov     $4, %edi
+	push    %rax
+	push    %rdx
+	mov     8(%rsp), %rax
+	cqo
+	idivq   %rdi
+	mov     %rax, 8(%rsp)
+	pop     %rdx
+	pop     %rax
+	movslq  %eax, %rax
+	mov     $6, %edi
+	mov     %rdi, builtin_line(%rip)
+	mov     %rax, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     %rax, %rsi
+	call    builtin_debug_int@PLT
+	.Luser_main.line.3:
+	mov     $7, %ebx
+	mov     user_y(%rip), %rax
+	mov     $4, %edi
+	mov     %rax, -0x18(%rbp)
+	mov     %rdi, -0x20(%rbp)
+	mov     %rax, %rdi
+	mov     -0x20(%rbp), %rsi
+	call    builtin_div_int@PLT
+	mov     $7, %edi
+	mov     %rdi, builtin_line(%rip)
+	mov     %rax, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     %rax, %rsi
+	call    builtin_debug_float@PLT
+	.Luser_main.line.4:
+	mov     $8, %ebx
+	mov     user_x(%rip), %rax
+	neg     %eax
+	movslq  %eax, %rax
+	mov     $8, %edi
+	mov     %rdi, builtin_line(%rip)
+	mov     %rax, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     %rax, %rsi
+	call    builtin_debug_int@PLT
+	mov     -0x8(%rbp), %rbx
+	mov     %rbp, %rsp
+	pop     %rbp
+	ret
+.section .ledmap,""
+.asciz "user_main"
+.4byte .Luser_main.line.0 - user_main, 3
+.4byte .Luser_main.line.1 - user_main, 4
+.4byte .Luser_main.line.2 - user_main, 6
+.4byte .Luser_main.line.3 - user_main, 7
+.4byte .Luser_main.line.4 - user_main, 8
+.4byte 0xffffffff
@@ -0,0 +1,83 @@
+.lcomm user_x, 4
+.lcomm user_y, 4
+.text
+.section .text.user_main
+.balign 4
+.global user_main
+user_main:
+	addi    a1, a1, -32
+	s32i    a0, a1, 28
+	s32i    a12, a1, 24
+	.Luser_main.line.0:
+	movi    a12, 7
+	movi    a2, user_x
+	s32i    a12, a2, 0
+	.Luser_main.line.1:
+	movi    a12, user_x
+	l32i    a12, a12, 0
+	movi    a2, 3
+	mull    a12, a12, a2
+	movi    a2, 2
+	add     a12, a12, a2
+	movi    a2, user_y
+	s32i    a12, a2, 0
+	.Luser_main.line.2:
+	movi    a12, 6
+	movi    a2, user_y
+	l32i    a2, a2, 0
+	movi    a3, 4
+	s32i    a2, a1, 16
+	s32i    a3, a1, 12
+	addi    a1, a1, -8
+	s32i    a2, a1, 0
+	s32i    a3, a1, 4
+	l32i    a2, a1, 0
+	l32i    a3, a1, 4
+	addi    a1, a1, 8
+	call0   __divsi3
+	movi    a3, 6
+	movi    a4, builtin_line
+	s32i    a3, a4, 0
+	s32i    a2, a1, 16
+	mov.n   a2, a12
+	l32i    a3, a1, 16
+	call0   builtin_debug_int
+	.Luser_main.line.3:
+	movi    a12, 7
+	movi    a2, user_y
+	l32i    a2, a2, 0
+	movi    a3, 4
+	s32i    a2, a1, 16
+	s32i    a3, a1, 12
+	call0   builtin_div_int
+	movi    a3, 7
+	movi    a4, builtin_line
+	s32i    a3, a4, 0
+	s32i    a2, a1, 16
+	mov.n   a2, a12
+	l32i    a3, a1, 16
+	call0   builtin_debug_float
+	.Luser_main.line.4:
+	movi    a12, 8
+	movi    a2, user_x
+	l32i    a2, a2, 0
+	neg     a2, a2
+	movi    a3, 8
+	movi    a4, builtin_line
+	s32i    a3, a4, 0
+	s32i    a2, a1, 16
+	mov.n   a2, a12
+	l32i    a3, a1, 16
+	call0   builtin_debug_int
+	l32i    a12, a1, 24
+	l32i    a0, a1, 28
+	addi    a1, a1, 32
+	ret.n
+.section .ledmap,""
+.asciz "user_main"
+.4byte .Luser_main.line.0 - user_main, 3
+.4byte .Luser_main.line.1 - user_main, 4
+.4byte .Luser_main.line.2 - user_main, 6
+.4byte .Luser_main.line.3 - user_main, 7
+.4byte .Luser_main.line.4 - user_main, 8
+.4byte 0xffffffff
@@ -0,0 +1,115 @@
+.text
+.section .text.user_main
+.balign 8
+.global user_main
+user_main:
+	push    %rbp
+	mov     %rsp, %rbp
+	sub     $0x20, %rsp
+	.Luser_main.line.0:
+	mov     $8, %eax
+	mov     $100, %edi
+	mov     %rax, -0x10(%rbp)
+	mov     %rdi, -0x18(%rbp)
+	mov     %rax, %rdi
+	mov     -0x18(%rbp), %rsi
+	call    user_walk$$ii@PLT
+	mov     %rbp, %rsp
+	pop     %rbp
+	ret
+.section .ledmap,""
+.asciz "user_main"
+.4byte .Luser_main.line.0 - user_main, 3
+.4byte 0xffffffff
+.section .text.user_walk$$ii
+.balign 8
+.global user_walk$$ii
+user_walk$$ii:
+	push    %rbp
+	mov     %rsp, %rbp
+	sub     $0x70, %rsp
+	mov     %rbx, -0x8(%rbp)
+	mov     %r12, -0x10(%rbp)
+	mov     %r13, -0x18(%rbp)
+	mov     %r14, -0x20(%rbp)
+	mov     %rsi, %rbx
+	.Luser_walk$$ii.line.0:
+	mov     %rdi, %r12
+	xor     %r14d, %r14d
+	mov     $1, %r13d
+	.Luser_walk$$ii.0:
+	mov     %r14, %rax
+	cmp     %r12, %rax
+	setl    %al
+	movzx   %al, %rax
+	mov     %rax, -0x50(%rbp)
+	test    %eax, %eax
+	jz      .Luser_walk$$ii.1
+	.Luser_walk$$ii.line.1:
+	mov     %r14, %rax
+	mov     %r14, %rdi
+	mov     $1, %esi
+	mov     $8, %edx
+	mov     %rdx, builtin_line(%rip)
+	mov     %rax, -0x50(%rbp)
+	mov     %rdi, -0x58(%rbp)
+	mov     %rsi, -0x60(%rbp)
+	mov     %rax, %rdi
+	mov     -0x58(%rbp), %rsi
+	mov     -0x60(%rbp), %rdx
+	call    builtin_printled@PLT
+	.Luser_walk$$ii.line.2:
+	mov     %rbx, %rax
+	mov     $9, %edi
+	mov     %rdi, builtin_line(%rip)
+	mov     %rax, -0x60(%rbp)
+	mov     %rax, %rdi
+	call    builtin_delay_mil@PLT
+	.Luser_walk$$ii.line.3:
+	mov     %r14, %rax
+	mov     $4, %edi
+	cmp     %rdi, %rax
+	sete    %al
+	movzx   %al, %rax
+	mov     %rax, -0x60(%rbp)
+	test    %eax, %eax
+	jz      .Luser_walk$$ii.2
+	.Luser_walk$$ii.line.4:
+	mov     %r14, %rax
+	mov     %r14, %rdi
+	xor     %esi, %esi
+	mov     $12, %edx
+	mov     %rdx, builtin_line(%rip)
+	mov     %rax, -0x60(%rbp)
+	mov     %rdi, -0x58(%rbp)
+	mov     %rsi, -0x50(%rbp)
+	mov     %rax, %rdi
+	mov     -0x58(%rbp), %rsi
+	mov     -0x50(%rbp), %rdx
+	call    builtin_printled@PLT
+	.Luser_walk$$ii.line.5:
+	.Luser_walk$$ii.2:
+	.Luser_walk$$ii.line.6:
+	add     %r13d, %r14d
+	movslq  %r14d, %r14
+	jmp     .Luser_walk$$ii.0
+	.Luser_walk$$ii.1:
+	.Luser_walk$$ii.line.7:
+	mov     -0x8(%rbp), %rbx
+	mov     -0x10(%rbp), %r12
+	mov     -0x18(%rbp), %r13
+	mov     -0x20(%rbp), %r14
+	mov     %rbp, %rsp
+	pop     %rbp
+	ret
+.section .ledmap,""
+.asciz "user_walk$$ii"
+.4byte .Luser_walk$$ii.line.0 - user_walk$$ii, 7
+.4byte .Luser_walk$$ii.line.1 - user_walk$$ii, 8
+.4byte .Luser_walk$$ii.line.2 - user_walk$$ii, 9
+.4byte .Luser_walk$$ii.line.3 - user_walk$$ii, 11
+.4byte .Luser_walk$$ii.line.4 - user_walk$$ii, 12
+.4byte .Luser_walk$$ii.line.5 - user_walk$$ii, 11
+.4byte .Luser_walk$$ii.line.6 - user_walk$$ii, 7
+.4byte .Luser_walk$$ii.line.7 - user_walk$$ii, 6
+.4byte 0xffffffff
@@ -0,0 +1,108 @@
+.text
+.section .text.user_main
+.balign 4
+.global user_main
+user_main:
+	addi    a1, a1, -16
+	s32i    a0, a1, 12
+	.Luser_main.line.0:
+	movi    a2, 8
+	movi    a3, 100
+	s32i    a2, a1, 4
+	s32i    a3, a1, 0
+	call0   user_walk$$ii
+	l32i    a0, a1, 12
+	addi    a1, a1, 16
+	ret.n
+.section .ledmap,""
+.asciz "user_main"
+.4byte .Luser_main.line.0 - user_main, 3
+.4byte 0xffffffff
+.section .text.user_walk$$ii
+.balign 4
+.global user_walk$$ii
+user_walk$$ii:
+	addi    a1, a1, -64
+	s32i    a0, a1, 60
+	s32i    a12, a1, 56
+	s32i    a13, a1, 52
+	s32i    a14, a1, 48
+	s32i    a15, a1, 44
+	mov.n   a12, a3
+	.Luser_walk$$ii.line.0:
+	mov.n   a13, a2
+	movi    a15, 0
+	movi    a14, 1
+	.Luser_walk$$ii.0:
+	mov.n   a2, a15
+	mov.n   a3, a2
+	movi    a2, 0
+	bge     a3, a13, .Luser_walk$$ii.3
+	movi    a2, 1
+	.Luser_walk$$ii.3:
+	s32i    a2, a1, 20
+	beqz    a2, .Luser_walk$$ii.1
+	.Luser_walk$$ii.line.1:
+	mov.n   a2, a15
+	mov.n   a3, a15
+	movi    a4, 1
+	movi    a5, 8
+	movi    a6, builtin_line
+	s32i    a5, a6, 0
+	s32i    a2, a1, 20
+	s32i    a3, a1, 16
+	s32i    a4, a1, 12
+	call0   builtin_printled
+	.Luser_walk$$ii.line.2:
+	mov.n   a2, a12
+	movi    a3, 9
+	movi    a4, builtin_line
+	s32i    a3, a4, 0
+	s32i    a2, a1, 12
+	call0   builtin_delay_mil
+	.Luser_walk$$ii.line.3:
+	mov.n   a2, a15
+	movi    a3, 4
+	mov.n   a4, a2
+	movi    a2, 0
+	bne     a4, a3, .Luser_walk$$ii.4
+	movi    a2, 1
+	.Luser_walk$$ii.4:
+	s32i    a2, a1, 12
+	beqz    a2, .Luser_walk$$ii.2
+	.Luser_walk$$ii.line.4:
+	mov.n   a2, a15
+	mov.n   a3, a15
+	movi    a4, 0
+	movi    a5, 12
+	movi    a6, builtin_line
+	s32i    a5, a6, 0
+	s32i    a2, a1, 12
+	s32i    a3, a1, 16
+	s32i    a4, a1, 20
+	call0   builtin_printled
+	.Luser_walk$$ii.line.5:
+	.Luser_walk$$ii.2:
+	.Luser_walk$$ii.line.6:
+	add     a15, a15, a14
+	j.l     .Luser_walk$$ii.0, a2
+	.Luser_walk$$ii.1:
+	.Luser_walk$$ii.line.7:
+	l32i    a12, a1, 56
+	l32i    a13, a1, 52
+	l32i    a14, a1, 48
+	l32i    a15, a1, 44
+	l32i    a0, a1, 60
+	addi    a1, a1, 64
+	ret.n
+.section .ledmap,""
+.asciz "user_walk$$ii"
+.4byte .Luser_walk$$ii.line.0 - user_walk$$ii, 7
+.4byte .Luser_walk$$ii.line.1 - user_walk$$ii, 8
+.4byte .Luser_walk$$ii.line.2 - user_walk$$ii, 9
+.4byte .Luser_walk$$ii.line.3 - user_walk$$ii, 11
+.4byte .Luser_walk$$ii.line.4 - user_walk$$ii, 12
+.4byte .Luser_walk$$ii.line.5 - user_walk$$ii, 11
+.4byte .Luser_walk$$ii.line.6 - user_walk$$ii, 7
+.4byte .Luser_walk$$ii.line.7 - user_walk$$ii, 6
+.4byte 0xffffffff
@@ -0,0 +1,99 @@
+.text
+.section .text.user_main
+.balign 8
+.global user_main
+user_main:
+	push    %rbp
+	mov     %rsp, %rbp
+	sub     $0x10, %rsp
+	.Luser_main.line.0:
+	mov     $3, %eax
+	mov     %rax, -0x10(%rbp)
+	mov     %rax, %rdi
+	call    user_probe$$i@PLT
+	mov     %rbp, %rsp
+	pop     %rbp
+	ret
+.section .ledmap,""
+.asciz "user_main"
+.4byte .Luser_main.line.0 - user_main, 3
+.4byte 0xffffffff
+.section .text.user_probe$$i
+.balign 8
+.global user_probe$$i
+user_probe$$i:
+	push    %rbp
+	mov     %rsp, %rbp
+	sub     $0x30, %rsp
+	mov     %rbx, -0x8(%rbp)
+	.Luser_probe$$i.line.0:
+	xor     %ebx, %ebx
+	.Luser_probe$$i.line.1:
+	mov     %rdi, %rax
+	mov     %rbx, %rsi
+	cmp     %rsi, %rax
+	sete    %al
+	movzx   %al, %rax
+	mov     %rax, -0x20(%rbp)
+	mov     %rdi, -0x10(%rbp)
+	test    %eax, %eax
+	jz      .Luser_probe$$i.0
+	.Luser_probe$$i.line.2:
+	mov     $10, %eax
+	mov     %rdi, %rsi
+	mov     $10, %edx
+	mov     %rdx, builtin_line(%rip)
+	mov     %rax, -0x20(%rbp)
+	mov     %rsi, -0x28(%rbp)
+	mov     %rax, %rdi
+	call    builtin_debug_int@PLT
+	.Luser_probe$$i.line.3:
+	.Luser_probe$$i.0:
+	.Luser_probe$$i.line.4:
+	call    user_tick@PLT
+	.Luser_probe$$i.line.5:
+	mov     $14, %eax
+	mov     %rbx, %rdi
+	mov     $14, %esi
+	mov     %rsi, builtin_line(%rip)
+	mov     %rax, -0x20(%rbp)
+	mov     %rdi, -0x28(%rbp)
+	mov     %rax, %rdi
+	mov     -0x28(%rbp), %rsi
+	call    builtin_debug_int@PLT
+	.Luser_probe$$i.line.6:
+	mov     -0x8(%rbp), %rbx
+	mov     %rbp, %rsp
+	pop     %rbp
+	ret
+.section .ledmap,""
+.asciz "user_probe$$i"
+.4byte .Luser_probe$$i.line.0 - user_probe$$i, 7
+.4byte .Luser_probe$$i.line.1 - user_probe$$i, 9
+.4byte .Luser_probe$$i.line.2 - user_probe$$i, 10
+.4byte .Luser_probe$$i.line.3 - user_probe$$i, 9
+.4byte .Luser_probe$$i.line.4 - user_probe$$i, 13
+.4byte .Luser_probe$$i.line.5 - user_probe$$i, 14
+.4byte .Luser_probe$$i.line.6 - user_probe$$i, 6
+.4byte 0xffffffff
+.section .text.user_tick
+.balign 8
+.global user_tick
+user_tick:
+	push    %rbp
+	mov     %rsp, %rbp
+	sub     $0x10, %rsp
+	.Luser_tick.line.0:
+	mov     $1, %eax
+	mov     $18, %edi
+	mov     %rdi, builtin_line(%rip)
+	mov     %rax, -0x8(%rbp)
+	mov     %rax, %rdi
+	call    builtin_delay_mil@PLT
+	mov     %rbp, %rsp
+	pop     %rbp
+	ret
+.section .ledmap,""
+.asciz "user_tick"
+.4byte .Luser_tick.line.0 - user_tick, 18
+.4byte 0xffffffff
@@ -0,0 +1,97 @@
+.text
+.section .text.user_main
+.balign 4
+.global user_main
+user_main:
+	addi    a1, a1, -16
+	s32i    a0, a1, 12
+	.Luser_main.line.0:
+	movi    a2, 3
+	s32i    a2, a1, 4
+	call0   user_probe$$i
+	l32i    a0, a1, 12
+	addi    a1, a1, 16
+	ret.n
+.section .ledmap,""
+.asciz "user_main"
+.4byte .Luser_main.line.0 - user_main, 3
+.4byte 0xffffffff
+.section .text.user_probe$$i
+.balign 4
+.global user_probe$$i
+user_probe$$i:
+	addi    a1, a1, -32
+	s32i    a0, a1, 28
+	s32i    a12, a1, 24
+	.Luser_probe$$i.line.0:
+	movi    a12, 0
+	.Luser_probe$$i.line.1:
+	mov.n   a3, a2
+	mov.n   a4, a12
+	mov.n   a5, a3
+	movi    a3, 0
+	bne     a5, a4, .Luser_probe$$i.1
+	movi    a3, 1
+	.Luser_probe$$i.1:
+	s32i    a2, a1, 20
+	s32i    a3, a1, 12
+	beqz    a3, .Luser_probe$$i.0
+	.Luser_probe$$i.line.2:
+	movi    a3, 10
+	mov.n   a4, a2
+	movi    a5, 10
+	movi    a6, builtin_line
+	s32i    a5, a6, 0
+	s32i    a3, a1, 12
+	s32i    a4, a1, 8
+	mov.n   a2, a3
+	mov.n   a3, a4
+	call0   builtin_debug_int
+	.Luser_probe$$i.line.3:
+	.Luser_probe$$i.0:
+	.Luser_probe$$i.line.4:
+	call0   user_tick
+	.Luser_probe$$i.line.5:
+	movi    a2, 14
+	mov.n   a3, a12
+	movi    a4, 14
+	movi    a5, builtin_line
+	s32i    a4, a5, 0
+	s32i    a2, a1, 12
+	s32i    a3, a1, 8
+	call0   builtin_debug_int
+	.Luser_probe$$i.line.6:
+	l32i    a12, a1, 24
+	l32i    a0, a1, 28
+	addi    a1, a1, 32
+	ret.n
+.section .ledmap,""
+.asciz "user_probe$$i"
+.4byte .Luser_probe$$i.line.0 - user_probe$$i, 7
+.4byte .Luser_probe$$i.line.1 - user_probe$$i, 9
+.4byte .Luser_probe$$i.line.2 - user_probe$$i, 10
+.4byte .Luser_probe$$i.line.3 - user_probe$$i, 9
+.4byte .Luser_probe$$i.line.4 - user_probe$$i, 13
+.4byte .Luser_probe$$i.line.5 - user_probe$$i, 14
+.4byte .Luser_probe$$i.line.6 - user_probe$$i, 6
+.4byte 0xffffffff
+.section .text.user_tick
+.balign 4
+.global user_tick
+user_tick:
+	addi    a1, a1, -16
+	s32i    a0, a1, 12
+	.Luser_tick.line.0:
+	movi    a2, 1
+	movi    a3, 18
+	movi    a4, builtin_line
+	s32i    a3, a4, 0
+	s32i    a2, a1, 8
+	call0   builtin_delay_mil
+	l32i    a0, a1, 12
+	addi    a1, a1, 16
+	ret.n
+.section .ledmap,""
+.asciz "user_tick"
+.4byte .Luser_tick.line.0 - user_tick, 18
+.4byte 0xffffffff
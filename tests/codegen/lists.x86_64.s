@@ -0,0 +1,141 @@
+.lcomm user_grid, 8
+.lcomm user_row, 8
+.text
+.section .text.user_main
+.balign 8
+.global user_main
+user_main:
+	push    %rbp
+	mov     %rsp, %rbp
+	sub     $0x30, %rsp
+	mov     %rbx, -0x8(%rbp)
+	.Luser_main.line.0:
+	mov     $3, %eax
+	mov     %rax, builtin_line(%rip)
+	call    builtin_new_list@PLT
+	mov     %rax, %rbx
+	xor     %eax, %eax
+	mov     $1, %edi
+	mov     %rax, -0x20(%rbp)
+	mov     %rdi, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     %rax, %rsi
+	mov     -0x18(%rbp), %rdx
+	call    builtin_insert_list@PLT
+	mov     $1, %eax
+	xor     %edi, %edi
+	mov     %rax, -0x20(%rbp)
+	mov     %rdi, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     %rax, %rsi
+	mov     -0x18(%rbp), %rdx
+	call    builtin_insert_list@PLT
+	mov     $2, %eax
+	mov     $1, %edi
+	mov     %rax, -0x20(%rbp)
+	mov     %rdi, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     %rax, %rsi
+	mov     -0x18(%rbp), %rdx
+	call    builtin_insert_list@PLT
+	mov     $3, %eax
+	xor     %edi, %edi
+	mov     %rax, -0x20(%rbp)
+	mov     %rdi, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     %rax, %rsi
+	mov     -0x18(%rbp), %rdx
+	call    builtin_insert_list@PLT
+	mov     $4, %eax
+	mov     $1, %edi
+	mov     %rax, -0x20(%rbp)
+	mov     %rdi, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     %rax, %rsi
+	mov     -0x18(%rbp), %rdx
+	call    builtin_insert_list@PLT
+	mov     $5, %eax
+	xor     %edi, %edi
+	mov     %rax, -0x20(%rbp)
+	mov     %rdi, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     %rax, %rsi
+	mov     -0x18(%rbp), %rdx
+	call    builtin_insert_list@PLT
+	mov     $6, %eax
+	mov     $1, %edi
+	mov     %rax, -0x20(%rbp)
+	mov     %rdi, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     %rax, %rsi
+	mov     -0x18(%rbp), %rdx
+	call    builtin_insert_list@PLT
+	mov     $7, %eax
+	xor     %edi, %edi
+	mov     %rax, -0x20(%rbp)
+	mov     %rdi, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     %rax, %rsi
+	mov     -0x18(%rbp), %rdx
+	call    builtin_insert_list@PLT
+	mov     %rbx, user_row(%rip)
+	.Luser_main.line.1:
+	mov     $4, %eax
+	mov     %rax, builtin_line(%rip)
+	call    builtin_new_mat@PLT
+	mov     %rax, %rbx
+	xor     %eax, %eax
+	mov     user_row(%rip), %rdi
+	mov     %rdi, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     -0x18(%rbp), %rsi
+	call    builtin_push_mat@PLT
+	mov     $1, %eax
+	mov     user_row(%rip), %rdi
+	mov     %rdi, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     -0x18(%rbp), %rsi
+	call    builtin_push_mat@PLT
+	mov     %rbx, user_grid(%rip)
+	.Luser_main.line.2:
+	mov     $6, %ebx
+	mov     user_row(%rip), %rax
+	mov     $6, %edi
+	mov     %rdi, builtin_line(%rip)
+	mov     %rax, -0x20(%rbp)
+	mov     %rax, %rdi
+	call    builtin_len_list@PLT
+	mov     %rax, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     %rax, %rsi
+	call    builtin_debug_int@PLT
+	.Luser_main.line.3:
+	xor     %ebx, %ebx
+	mov     user_grid(%rip), %rax
+	mov     $7, %edi
+	mov     %rdi, builtin_line(%rip)
+	mov     %rax, -0x18(%rbp)
+	mov     %rbx, %rdi
+	mov     %rax, %rsi
+	call    builtin_printledx_m@PLT
+	.Luser_main.line.4:
+	mov     user_grid(%rip), %rbx
+	mov     $2, %eax
+	mov     %rax, builtin_line(%rip)
+	mov     %rbx, %rdi
+	call    builtin_drop_mat@PLT
+	mov     user_row(%rip), %rbx
+	mov     %rbx, %rdi
+	call    builtin_drop_list@PLT
+	mov     -0x8(%rbp), %rbx
+	mov     %rbp, %rsp
+	pop     %rbp
+	ret
+.section .ledmap,""
+.asciz "user_main"
+.4byte .Luser_main.line.0 - user_main, 3
+.4byte .Luser_main.line.1 - user_main, 4
+.4byte .Luser_main.line.2 - user_main, 6
+.4byte .Luser_main.line.3 - user_main, 7
+.4byte .Luser_main.line.4 - user_main, 2
+.4byte 0xffffffff
@@ -0,0 +1,150 @@
+.lcomm user_grid, 4
+.lcomm user_row, 4
+.text
+.section .text.user_main
+.balign 4
+.global user_main
+user_main:
+	addi    a1, a1, -32
+	s32i    a0, a1, 28
+	s32i    a12, a1, 24
+	.Luser_main.line.0:
+	movi    a2, 3
+	movi    a3, builtin_line
+	s32i    a2, a3, 0
+	call0   builtin_new_list
+	mov.n   a12, a2
+	movi    a2, 0
+	movi    a3, 1
+	s32i    a2, a1, 12
+	s32i    a3, a1, 16
+	mov.n   a2, a12
+	l32i    a3, a1, 12
+	l32i    a4, a1, 16
+	call0   builtin_insert_list
+	movi    a2, 1
+	movi    a3, 0
+	s32i    a2, a1, 12
+	s32i    a3, a1, 16
+	mov.n   a2, a12
+	l32i    a3, a1, 12
+	l32i    a4, a1, 16
+	call0   builtin_insert_list
+	movi    a2, 2
+	movi    a3, 1
+	s32i    a2, a1, 12
+	s32i    a3, a1, 16
+	mov.n   a2, a12
+	l32i    a3, a1, 12
+	l32i    a4, a1, 16
+	call0   builtin_insert_list
+	movi    a2, 3
+	movi    a3, 0
+	s32i    a2, a1, 12
+	s32i    a3, a1, 16
+	mov.n   a2, a12
+	l32i    a3, a1, 12
+	l32i    a4, a1, 16
+	call0   builtin_insert_list
+	movi    a2, 4
+	movi    a3, 1
+	s32i    a2, a1, 12
+	s32i    a3, a1, 16
+	mov.n   a2, a12
+	l32i    a3, a1, 12
+	l32i    a4, a1, 16
+	call0   builtin_insert_list
+	movi    a2, 5
+	movi    a3, 0
+	s32i    a2, a1, 12
+	s32i    a3, a1, 16
+	mov.n   a2, a12
+	l32i    a3, a1, 12
+	l32i    a4, a1, 16
+	call0   builtin_insert_list
+	movi    a2, 6
+	movi    a3, 1
+	s32i    a2, a1, 12
+	s32i    a3, a1, 16
+	mov.n   a2, a12
+	l32i    a3, a1, 12
+	l32i    a4, a1, 16
+	call0   builtin_insert_list
+	movi    a2, 7
+	movi    a3, 0
+	s32i    a2, a1, 12
+	s32i    a3, a1, 16
+	mov.n   a2, a12
+	l32i    a3, a1, 12
+	l32i    a4, a1, 16
+	call0   builtin_insert_list
+	movi    a2, user_row
+	s32i    a12, a2, 0
+	.Luser_main.line.1:
+	movi    a2, 4
+	movi    a3, builtin_line
+	s32i    a2, a3, 0
+	call0   builtin_new_mat
+	mov.n   a12, a2
+	movi    a2, 0
+	movi    a3, user_row
+	l32i    a3, a3, 0
+	s32i    a3, a1, 16
+	mov.n   a2, a12
+	call0   builtin_push_mat
+	movi    a2, 1
+	movi    a3, user_row
+	l32i    a3, a3, 0
+	s32i    a3, a1, 16
+	mov.n   a2, a12
+	call0   builtin_push_mat
+	movi    a2, user_grid
+	s32i    a12, a2, 0
+	.Luser_main.line.2:
+	movi    a12, 6
+	movi    a2, user_row
+	l32i    a2, a2, 0
+	movi    a3, 6
+	movi    a4, builtin_line
+	s32i    a3, a4, 0
+	s32i    a2, a1, 12
+	call0   builtin_len_list
+	s32i    a2, a1, 16
+	mov.n   a2, a12
+	l32i    a3, a1, 16
+	call0   builtin_debug_int
+	.Luser_main.line.3:
+	movi    a12, 0
+	movi    a2, user_grid
+	l32i    a2, a2, 0
+	movi    a3, 7
+	movi    a4, builtin_line
+	s32i    a3, a4, 0
+	s32i    a2, a1, 16
+	mov.n   a2, a12
+	l32i    a3, a1, 16
+	call0   builtin_printledx_m
+	.Luser_main.line.4:
+	movi    a12, user_grid
+	l32i    a12, a12, 0
+	movi    a2, 2
+	movi    a3, builtin_line
+	s32i    a2, a3, 0
+	mov.n   a2, a12
+	call0   builtin_drop_mat
+	movi    a12, user_row
+	l32i    a12, a12, 0
+	mov.n   a2, a12
+	call0   builtin_drop_list
+	l32i    a12, a1, 24
+	l32i    a0, a1, 28
+	addi    a1, a1, 32
+	ret.n
+.section .ledmap,""
+.asciz "user_main"
+.4byte .Luser_main.line.0 - user_main, 3
+.4byte .Luser_main.line.1 - user_main, 4
+.4byte .Luser_main.line.2 - user_main, 6
+.4byte .Luser_main.line.3 - user_main, 7
+.4byte .Luser_main.line.4 - user_main, 2
+.4byte 0xffffffff